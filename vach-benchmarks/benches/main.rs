@@ -2,6 +2,7 @@ use std::io;
 
 use criterion::{Criterion, black_box, criterion_group, criterion_main, Throughput};
 use vach::prelude::*;
+use vach::bench::{self, BenchConfig, Workload};
 use vach::crypto_utils::gen_keypair;
 
 // Remove io overhead by Sinking data into the void
@@ -126,6 +127,24 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 			black_box(Archive::with_config(&mut target, &a_config).unwrap());
 		})
 	});
+
+	/* WORKLOAD BENCHMARKS */
+	let mut workload_group = c.benchmark_group("Workloads");
+	workload_group.sample_size(10);
+
+	// Scaled down so a full run stays quick, each iteration packs and fetches the whole workload
+	let config = BenchConfig::default().scale(0.05).iterations(1);
+
+	for workload in Workload::ALL {
+		let bytes = bench::run(workload, &config).unwrap().bytes;
+		workload_group.throughput(Throughput::Bytes(bytes));
+
+		workload_group.bench_function(workload.name(), |b| {
+			b.iter(|| black_box(bench::run(workload, &config).unwrap()));
+		});
+	}
+
+	drop(workload_group);
 }
 
 criterion_group!(benches, criterion_benchmark);
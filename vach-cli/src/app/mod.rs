@@ -12,6 +12,9 @@ pub fn build_app<'a>(key_map: HashMap<&'static str, Arg<'a>>) -> Command<'a> {
 		.author(self::AUTHORS)
 		.about("A command-line interface for unpacking and packing files")
 		.version(self::VERSION)
+		.arg(key_map.get(key_names::ERROR_FORMAT).unwrap())
+		.arg(key_map.get(key_names::METRICS).unwrap())
+		.arg(key_map.get(key_names::LOCK).unwrap())
 		.subcommand(
 			Command::new("keypair")
 				.author(AUTHORS)
@@ -20,6 +23,34 @@ pub fn build_app<'a>(key_map: HashMap<&'static str, Arg<'a>>) -> Command<'a> {
 				.arg(key_map.get(key_names::OUTPUT).unwrap())
 				.arg(key_map.get(key_names::SPLIT_KEY).unwrap()),
 		)
+		.subcommand(
+			Command::new("key")
+				.author(AUTHORS)
+				.version(commands::key::VERSION)
+				.about("Inspect, fingerprint and rotate keys")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("inspect")
+						.about("Displays the kind, public key and fingerprint of a key file")
+						.arg(key_map.get(key_names::INPUT).unwrap())
+						.arg(key_map.get(key_names::FORMAT).unwrap()),
+				)
+				.subcommand(
+					Command::new("fingerprint")
+						.about("Prints the fingerprint of a key file, matching for all parts of a keypair")
+						.arg(key_map.get(key_names::INPUT).unwrap()),
+				)
+				.subcommand(
+					Command::new("rotate")
+						.about("Verifies an archive against its old key and re-signs it with a new key")
+						.arg(key_map.get(key_names::ARCHIVE).unwrap())
+						.arg(key_map.get(key_names::OUTPUT).unwrap())
+						.arg(key_map.get(key_names::MAGIC).unwrap())
+						.arg(key_map.get(key_names::OLD_KEY).unwrap())
+						.arg(key_map.get(key_names::KEYPAIR).unwrap())
+						.arg(key_map.get(key_names::SECRET_KEY).unwrap()),
+				),
+		)
 		.subcommand(
 			Command::new("split")
 				.author(AUTHORS)
@@ -31,24 +62,111 @@ pub fn build_app<'a>(key_map: HashMap<&'static str, Arg<'a>>) -> Command<'a> {
 			Command::new("verify")
 				.author(AUTHORS)
 				.version(commands::verify::VERSION)
-				.about("Verifies the validity of an archive")
+				.about("Verifies the validity of one or more archives, or directories of archives")
 				.arg(key_map.get(key_names::MAGIC).unwrap())
-				.arg(key_map.get(key_names::INPUT).unwrap()),
+				.arg(key_map.get(key_names::INPUT).unwrap())
+				.arg(key_map.get(key_names::FORMAT).unwrap())
+				.arg(key_map.get(key_names::DEEP).unwrap())
+				.arg(key_map.get(key_names::STRICT_SIGNATURES).unwrap())
+				.arg(key_map.get(key_names::JOBS).unwrap())
+				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
+				.arg(key_map.get(key_names::KEYPAIR).unwrap()),
+		)
+		.subcommand(
+			Command::new("spec")
+				.author(AUTHORS)
+				.version(commands::spec::VERSION)
+				.about("Describes the format: it's flags, header extensions and codecs")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("dump")
+						.about("Prints the format descriptor of an archive, or of this build without one")
+						.arg(key_map.get(key_names::INPUT).unwrap())
+						.arg(key_map.get(key_names::MAGIC).unwrap())
+						.arg(key_map.get(key_names::FORMAT).unwrap()),
+				),
+		)
+		.subcommand(
+			Command::new("release")
+				.author(AUTHORS)
+				.version(commands::release::VERSION)
+				.about("Creates and verifies signed manifests spanning every archive of a release")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("create")
+						.about("Hashes the given archives into a signed release manifest")
+						.arg(key_map.get(key_names::INPUT).unwrap())
+						.arg(key_map.get(key_names::OUTPUT).unwrap())
+						.arg(key_map.get(key_names::RELEASE).unwrap())
+						.arg(key_map.get(key_names::KEYPAIR).unwrap())
+						.arg(key_map.get(key_names::SECRET_KEY).unwrap()),
+				)
+				.subcommand(
+					Command::new("verify")
+						.about("Checks a release manifest's signature, then every archive it lists")
+						.arg(key_map.get(key_names::INPUT).unwrap())
+						.arg(key_map.get(key_names::KEYPAIR).unwrap())
+						.arg(key_map.get(key_names::PUBLIC_KEY).unwrap()),
+				),
+		)
+		.subcommand(
+			Command::new("selftest")
+				.author(AUTHORS)
+				.version(commands::selftest::VERSION)
+				.about("Checks the archive format's invariants and a full pack and unpack round trip on this platform"),
 		)
 		.subcommand(
 			Command::new("list")
 				.author(AUTHORS)
 				.version(commands::list::VERSION)
-				.about("Lists all the entries in a archive and their metadata")
+				.about("Lists all the entries in one or more archives and their metadata")
+				.arg(key_map.get(key_names::INPUT).unwrap())
+				.arg(key_map.get(key_names::MAGIC).unwrap())
+				.arg(key_map.get(key_names::SORT).unwrap())
+				.arg(key_map.get(key_names::WHERE).unwrap())
+				.arg(key_map.get(key_names::TAG).unwrap())
+				.arg(key_map.get(key_names::LAYOUT).unwrap())
+				.arg(key_map.get(key_names::FORMAT).unwrap()),
+		)
+		.subcommand(
+			Command::new("lint")
+				.author(AUTHORS)
+				.version(commands::lint::VERSION)
+				.about("Reports likely mistakes in one or more archives, like uncompressed text or duplicated content")
+				.arg(key_map.get(key_names::INPUT).unwrap())
+				.arg(key_map.get(key_names::MAGIC).unwrap())
+				.arg(key_map.get(key_names::KEYPAIR).unwrap())
+				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
+				.arg(key_map.get(key_names::FORMAT).unwrap())
+				.arg(key_map.get(key_names::STRICT).unwrap()),
+		)
+		.subcommand(
+			Command::new("diff")
+				.author(AUTHORS)
+				.version(commands::diff::VERSION)
+				.about("Lists the entries added, removed and changed between two archives")
 				.arg(key_map.get(key_names::INPUT).unwrap())
 				.arg(key_map.get(key_names::MAGIC).unwrap())
-				.arg(key_map.get(key_names::SORT).unwrap()),
+				.arg(key_map.get(key_names::KEYPAIR).unwrap())
+				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
+				.arg(key_map.get(key_names::FORMAT).unwrap()),
+		)
+		.subcommand(
+			Command::new("doctor")
+				.author(AUTHORS)
+				.version(commands::doctor::VERSION)
+				.about("Diagnoses why archives won't open, from keys and magic to spec versions and flags, suggesting fixes")
+				.arg(key_map.get(key_names::INPUT).unwrap())
+				.arg(key_map.get(key_names::MAGIC).unwrap())
+				.arg(key_map.get(key_names::KEYPAIR).unwrap())
+				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
+				.arg(key_map.get(key_names::FORMAT).unwrap()),
 		)
 		.subcommand(
 			Command::new("unpack")
 				.author(AUTHORS)
 				.version(commands::unpack::VERSION)
-				.about("Unpacks a archive")
+				.about("Unpacks one or more archives")
 				// Files
 				.arg(key_map.get(key_names::OUTPUT).unwrap())
 				.arg(key_map.get(key_names::INPUT).unwrap())
@@ -58,19 +176,79 @@ pub fn build_app<'a>(key_map: HashMap<&'static str, Arg<'a>>) -> Command<'a> {
 				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
 				// modifiers
 				.arg(key_map.get(key_names::JOBS).unwrap())
-				.arg(key_map.get(key_names::TRUNCATE).unwrap()),
+				.arg(key_map.get(key_names::TRUNCATE).unwrap())
+				.arg(key_map.get(key_names::STATS).unwrap())
+				.arg(key_map.get(key_names::VERIFY).unwrap())
+				.arg(key_map.get(key_names::WHERE).unwrap())
+				.arg(key_map.get(key_names::TAG).unwrap()),
 		)
 		.subcommand(
 			Command::new("pipe")
 				.author(AUTHORS)
 				.version(commands::pipe::VERSION)
-				.about("Pipes a Resource from an archive to stdout")
+				.about("Pipes a Resource, or several as a tar stream, from an archive to stdout")
 				.arg(key_map.get(key_names::INPUT).unwrap())
 				.arg(key_map.get(key_names::MAGIC).unwrap())
 				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
 				.arg(key_map.get(key_names::RESOURCE).unwrap())
+				.arg(key_map.get(key_names::TAR).unwrap())
+				.arg(key_map.get(key_names::INCLUDE).unwrap())
 				.arg(key_map.get(key_names::KEYPAIR).unwrap()),
 		)
+		.subcommand(
+			Command::new("recompress")
+				.author(AUTHORS)
+				.version(commands::recompress::VERSION)
+				.about("Compresses every entry of an archive anew, from stdin to stdout without temporary files")
+				.arg(key_map.get(key_names::INPUT).unwrap())
+				.arg(key_map.get(key_names::OUTPUT).unwrap())
+				.arg(key_map.get(key_names::MAGIC).unwrap())
+				.arg(key_map.get(key_names::KEYPAIR).unwrap())
+				.arg(key_map.get(key_names::SECRET_KEY).unwrap())
+				.arg(key_map.get(key_names::COMPRESS_MODE).unwrap())
+				.arg(key_map.get(key_names::COMPRESS_ALGO).unwrap())
+				.arg(key_map.get(key_names::JOBS).unwrap()),
+		)
+		.subcommand(
+			Command::new("mv")
+				.author(AUTHORS)
+				.version(commands::mv::VERSION)
+				.about("Renames entries within an archive, re-signing them if a key is provided")
+				.arg(key_map.get(key_names::INPUT).unwrap())
+				.arg(key_map.get(key_names::OUTPUT).unwrap())
+				.arg(key_map.get(key_names::MAGIC).unwrap())
+				.arg(key_map.get(key_names::FROM).unwrap())
+				.arg(key_map.get(key_names::TO).unwrap())
+				.arg(key_map.get(key_names::PREFIX_REWRITE).unwrap())
+				.arg(key_map.get(key_names::KEYPAIR).unwrap())
+				.arg(key_map.get(key_names::SECRET_KEY).unwrap()),
+		)
+		.subcommand(
+			Command::new("gen-manifest")
+				.author(AUTHORS)
+				.version(commands::gen_manifest::VERSION)
+				.about("Writes a manifest of the entries of archives or directories, for use with pack --from-manifest")
+				.arg(key_map.get(key_names::OUTPUT).unwrap())
+				// Data sources
+				.arg(key_map.get(key_names::INPUT).unwrap())
+				.arg(key_map.get(key_names::DIR_INPUT).unwrap())
+				.arg(key_map.get(key_names::DIR_INPUT_REC).unwrap())
+				.arg(key_map.get(key_names::FOLLOW_SYMLINKS).unwrap())
+				.arg(key_map.get(key_names::NO_FOLLOW).unwrap())
+				.arg(key_map.get(key_names::HIDDEN).unwrap())
+				.arg(key_map.get(key_names::FILE_TYPE).unwrap())
+				.arg(key_map.get(key_names::EXTENSIONS).unwrap())
+				// Keys, to read encrypted archives
+				.arg(key_map.get(key_names::KEYPAIR).unwrap())
+				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
+				// Entry settings
+				.arg(key_map.get(key_names::COMPRESS_MODE).unwrap())
+				.arg(key_map.get(key_names::COMPRESS_ALGO).unwrap())
+				.arg(key_map.get(key_names::MAGIC).unwrap())
+				.arg(key_map.get(key_names::ENCRYPT).unwrap())
+				.arg(key_map.get(key_names::HASH).unwrap())
+				.arg(key_map.get(key_names::VERSION).unwrap()),
+		)
 		.subcommand(
 			Command::new("pack")
 				.author(AUTHORS)
@@ -83,9 +261,17 @@ pub fn build_app<'a>(key_map: HashMap<&'static str, Arg<'a>>) -> Command<'a> {
 				.arg(key_map.get(key_names::DIR_INPUT).unwrap())
 				.arg(key_map.get(key_names::DIR_INPUT_REC).unwrap())
 				.arg(key_map.get(key_names::EXCLUDE).unwrap())
+				.arg(key_map.get(key_names::FOLLOW_SYMLINKS).unwrap())
+				.arg(key_map.get(key_names::NO_FOLLOW).unwrap())
+				.arg(key_map.get(key_names::HIDDEN).unwrap())
+				.arg(key_map.get(key_names::FILE_TYPE).unwrap())
+				.arg(key_map.get(key_names::EXTENSIONS).unwrap())
+				.arg(key_map.get(key_names::FROM_MANIFEST).unwrap())
 				// Crypto shit
 				.arg(key_map.get(key_names::KEYPAIR).unwrap())
 				.arg(key_map.get(key_names::SECRET_KEY).unwrap())
+				.arg(key_map.get(key_names::SIGN_WITH).unwrap())
+				.arg(key_map.get(key_names::PUBLIC_KEY).unwrap())
 				// Modifiers
 				.arg(key_map.get(key_names::JOBS).unwrap())
 				.arg(key_map.get(key_names::FLAGS).unwrap())
@@ -95,6 +281,18 @@ pub fn build_app<'a>(key_map: HashMap<&'static str, Arg<'a>>) -> Command<'a> {
 				.arg(key_map.get(key_names::ENCRYPT).unwrap())
 				.arg(key_map.get(key_names::HASH).unwrap())
 				.arg(key_map.get(key_names::VERSION).unwrap())
-				.arg(key_map.get(key_names::TRUNCATE).unwrap()),
+				.arg(key_map.get(key_names::TRUNCATE).unwrap())
+				.arg(key_map.get(key_names::STATS).unwrap())
+				.arg(key_map.get(key_names::PREFIX_IDS).unwrap())
+				.arg(key_map.get(key_names::ID_INDEX).unwrap())
+				.arg(key_map.get(key_names::FORMAT_DESCRIPTOR).unwrap())
+				.arg(key_map.get(key_names::FOOTER).unwrap())
+				.arg(key_map.get(key_names::COMPRESS_REGISTRY).unwrap())
+				.arg(key_map.get(key_names::NORMALIZE_NEWLINES).unwrap())
+				.arg(key_map.get(key_names::LAYOUT_PROFILE).unwrap())
+				.arg(key_map.get(key_names::DATA_OFFSET).unwrap())
+				// Budgets
+				.arg(key_map.get(key_names::MAX_SIZE).unwrap())
+				.arg(key_map.get(key_names::MAX_ENTRY_SIZE).unwrap()),
 		)
 }
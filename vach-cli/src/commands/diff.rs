@@ -0,0 +1,72 @@
+use std::fs::File;
+
+use vach::crypto_utils;
+use vach::diff::{self, ArchiveDelta};
+use vach::prelude::{Archive, ArchiveConfig};
+
+use super::CommandTrait;
+use crate::keys::key_names;
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command lists the entries added, removed and changed between two archives
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		let (old_path, new_path) = match args.values_of(key_names::INPUT).map(|v| v.collect::<Vec<_>>()).as_deref() {
+			Some([old, new]) => (old.to_string(), new.to_string()),
+			_ => fail!(Usage, "Please provide exactly two archives to compare: -i OLD NEW"),
+		};
+
+		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+			Some(magic) => magic.as_bytes().try_into()?,
+			None => *vach::DEFAULT_MAGIC,
+		};
+
+		// Only needed to compare encrypted entries whose stored bytes differ
+		let public_key = match args.value_of(key_names::KEYPAIR) {
+			Some(path) => Some(crypto_utils::read_keypair(File::open(path)?)?.verifying_key()),
+			None => match args.value_of(key_names::PUBLIC_KEY) {
+				Some(path) => Some(crypto_utils::read_public_key(File::open(path)?)?),
+				None => None,
+			},
+		};
+
+		let config = ArchiveConfig::new(magic, public_key);
+		let old = Archive::with_config(File::open(&old_path)?, &config)?;
+		let new = Archive::with_config(File::open(&new_path)?, &config)?;
+
+		let delta = diff::compare(&old, &new)?;
+
+		if args.value_of(key_names::FORMAT) == Some("json") {
+			let ids = |ids: &[vach::prelude::EntryId]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+			let report = serde_json::json!({
+				"added": ids(&delta.added),
+				"removed": ids(&delta.removed),
+				"changed": ids(&delta.changed),
+				"identical": delta.identical.len(),
+			});
+
+			println!("{}", serde_json::to_string_pretty(&report)?);
+		} else {
+			print_delta(&delta);
+		}
+
+		Ok(())
+	}
+}
+
+fn print_delta(delta: &ArchiveDelta) {
+	delta.added.iter().for_each(|id| println!("+ {}", id));
+	delta.removed.iter().for_each(|id| println!("- {}", id));
+	delta.changed.iter().for_each(|id| println!("~ {}", id));
+
+	println!(
+		"{} added, {} removed, {} changed, {} identical",
+		delta.added.len(),
+		delta.removed.len(),
+		delta.changed.len(),
+		delta.identical.len()
+	);
+}
@@ -0,0 +1,429 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use vach::{
+	crypto_utils,
+	format::header::{capabilities, RawHeader},
+	prelude::{Archive, ArchiveConfig, Flags, InternalError, VerifyingKey},
+};
+
+use super::CommandTrait;
+use crate::{keys::key_names, utils};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command diagnoses why archives fail to open, printing what to do about each problem found
+pub struct Evaluator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+	Ok,
+	Warn,
+	Fail,
+}
+
+impl Level {
+	fn name(self) -> &'static str {
+		match self {
+			Level::Ok => "ok",
+			Level::Warn => "warn",
+			Level::Fail => "fail",
+		}
+	}
+}
+
+/// A single observation, with a remediation step for anything that isn't fine
+struct Finding {
+	level: Level,
+	message: String,
+	fix: Option<String>,
+}
+
+#[derive(Default)]
+struct Findings(Vec<Finding>);
+
+impl Findings {
+	fn ok(&mut self, message: impl Into<String>) {
+		self.push(Level::Ok, message.into(), None)
+	}
+
+	fn warn(&mut self, message: impl Into<String>, fix: impl Into<String>) {
+		self.push(Level::Warn, message.into(), Some(fix.into()))
+	}
+
+	fn fail(&mut self, message: impl Into<String>, fix: impl Into<String>) {
+		self.push(Level::Fail, message.into(), Some(fix.into()))
+	}
+
+	fn push(&mut self, level: Level, message: String, fix: Option<String>) {
+		self.0.push(Finding { level, message, fix })
+	}
+
+	fn count(&self, level: Level) -> usize {
+		self.0.iter().filter(|f| f.level == level).count()
+	}
+}
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		let inputs = match args.values_of(key_names::INPUT) {
+			Some(inputs) => utils::collect_archives(inputs)?,
+			None => fail!(Usage, "Please provide an input path using the -i or --input key"),
+		};
+
+		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+			Some(magic) => magic.as_bytes().try_into()?,
+			None => *vach::DEFAULT_MAGIC,
+		};
+
+		let json = args.value_of(key_names::FORMAT) == Some("json");
+
+		// The environment is diagnosed once, a broken key is reported instead of aborting
+		let mut environment = Findings::default();
+		environment.ok(format!(
+			"vach-cli {}, reading spec version {} with compression, crypto and multithreading",
+			env!("CARGO_PKG_VERSION"),
+			vach::VERSION
+		));
+
+		let key = match load_key(args) {
+			Ok(Some(key)) => {
+				environment.ok(format!("Loaded public key with fingerprint {}", utils::fingerprint(&key)));
+				Some(key)
+			},
+			Ok(None) => None,
+			Err(err) => {
+				environment.fail(
+					format!("Unable to read the given key: {}", err),
+					"Pass a keypair with -k or a public key with -p, `vach key inspect -i <file>` shows what a key file holds",
+				);
+				None
+			},
+		};
+
+		let reports = inputs
+			.iter()
+			.map(|path| (path, diagnose(path, magic, key)))
+			.collect::<Vec<_>>();
+
+		let failures = environment.count(Level::Fail) + reports.iter().map(|(_, f)| f.count(Level::Fail)).sum::<usize>();
+		let warnings = environment.count(Level::Warn) + reports.iter().map(|(_, f)| f.count(Level::Warn)).sum::<usize>();
+
+		if json {
+			let findings = |findings: &Findings| {
+				findings
+					.0
+					.iter()
+					.map(|f| serde_json::json!({ "level": f.level.name(), "message": f.message, "fix": f.fix }))
+					.collect::<Vec<_>>()
+			};
+
+			let report = serde_json::json!({
+				"environment": findings(&environment),
+				"archives": reports
+					.iter()
+					.map(|(path, f)| serde_json::json!({ "path": path, "findings": findings(f) }))
+					.collect::<Vec<_>>(),
+			});
+
+			println!("{}", serde_json::to_string_pretty(&report)?);
+		} else {
+			print_findings("Environment", &environment);
+			for (path, findings) in &reports {
+				print_findings(&path.to_string_lossy(), findings);
+			}
+
+			println!("{} problem(s), {} warning(s)", failures, warnings);
+		}
+
+		if failures > 0 {
+			fail!(Verification, "Found {} problem(s) keeping archives from loading", failures);
+		}
+
+		Ok(())
+	}
+}
+
+fn print_findings(title: &str, findings: &Findings) {
+	println!("{}", title);
+
+	for finding in &findings.0 {
+		let status = match finding.level {
+			Level::Ok => "OK    ",
+			Level::Warn => "WARN  ",
+			Level::Fail => "FAILED",
+		};
+
+		println!("  {} {}", status, finding.message);
+		if let Some(fix) = &finding.fix {
+			println!("         -> {}", fix);
+		}
+	}
+
+	println!();
+}
+
+fn load_key(args: &clap::ArgMatches) -> anyhow::Result<Option<VerifyingKey>> {
+	Ok(match args.value_of(key_names::KEYPAIR) {
+		Some(path) => Some(crypto_utils::read_keypair(File::open(path)?)?.verifying_key()),
+		None => match args.value_of(key_names::PUBLIC_KEY) {
+			Some(path) => Some(crypto_utils::read_public_key(File::open(path)?)?),
+			None => None,
+		},
+	})
+}
+
+/// Walks from the raw header up to decoding entries, stopping at the first step that keeps the archive from loading
+fn diagnose(path: &Path, magic: [u8; vach::MAGIC_LENGTH], key: Option<VerifyingKey>) -> Findings {
+	let mut findings = Findings::default();
+
+	let (mut file, length) = match File::open(path).and_then(|f| f.metadata().map(|m| (f, m.len()))) {
+		Ok(file) => file,
+		Err(err) => {
+			findings.fail(
+				format!("Unable to open the file: {}", err),
+				"Check the path exists and that you are allowed to read it",
+			);
+			return findings;
+		},
+	};
+
+	// The raw header is read without validation, so every mismatch can be reported. Legacy headers end early
+	let legacy = |buffer: &[u8]| {
+		RawHeader::decode_prefix(buffer[..RawHeader::PREFIX_SIZE].try_into().unwrap()).2 == vach::LEGACY_VERSION
+	};
+
+	let mut buffer = [0u8; RawHeader::BASE_SIZE];
+	let read = file
+		.read_exact(&mut buffer[..RawHeader::LEGACY_SIZE])
+		.and_then(|_| match legacy(&buffer) {
+			true => Ok(()),
+			false => file.read_exact(&mut buffer[RawHeader::LEGACY_SIZE..]),
+		});
+
+	if let Err(err) = read {
+		findings.fail(
+			format!("The file holds {} bytes, too few for an archive header: {}", length, err),
+			"The file is truncated or not an archive, download or pack it again",
+		);
+		return findings;
+	}
+
+	let raw = match legacy(&buffer) {
+		true => RawHeader::decode_legacy(buffer[..RawHeader::LEGACY_SIZE].try_into().unwrap()),
+		false => RawHeader::decode(&buffer),
+	};
+	let found = String::from_utf8_lossy(&raw.magic);
+
+	if raw.magic == magic {
+		findings.ok(format!("Magic is {:?}", found));
+	} else if raw.magic == *vach::DEFAULT_MAGIC {
+		findings.fail(
+			format!("Magic is the default {:?}, not {:?}", found, String::from_utf8_lossy(&magic)),
+			"Drop the -m key, the archive was packed without a custom magic",
+		);
+	} else if raw.magic.iter().all(|b| b.is_ascii_graphic()) {
+		findings.fail(
+			format!("Magic is {:?}, not {:?}", found, String::from_utf8_lossy(&magic)),
+			format!("Pass `-m {}`, the archive was packed with a custom magic", found),
+		);
+	} else {
+		findings.fail(
+			format!("Magic is {:?}, this doesn't look like an archive", raw.magic),
+			"Check the path points at an archive, files downloaded as text may have been mangled",
+		);
+		return findings;
+	}
+
+	if raw.is_legacy() {
+		findings.ok(format!(
+			"Written with the legacy spec version {}, which remains readable by this loader",
+			raw.arch_version
+		));
+	} else if raw.arch_version < vach::VERSION {
+		findings.fail(
+			format!(
+				"Written with spec version {}, older than the {} this loader reads",
+				raw.arch_version,
+				vach::VERSION
+			),
+			"Pack the archive again with this vach-cli, or load it with the vach release it was written with",
+		);
+		return findings;
+	} else if raw.min_loader_version > vach::VERSION {
+		findings.fail(
+			format!(
+				"Needs a loader for spec version {}, this loader reads {}",
+				raw.min_loader_version,
+				vach::VERSION
+			),
+			"Upgrade vach-cli, and the vach dependency of your application, to a release reading the newer spec",
+		);
+		return findings;
+	} else if raw.arch_version > vach::VERSION {
+		findings.ok(format!(
+			"Written with the newer spec version {}, which remains readable by this loader",
+			raw.arch_version
+		));
+	} else {
+		findings.ok(format!("Spec version {}", raw.arch_version));
+	}
+
+	let unknown = raw.capabilities & !capabilities::KNOWN;
+	if unknown != 0 {
+		findings.fail(
+			format!("Relies on format capabilities unknown to this loader: {:#b}", unknown),
+			"Upgrade vach-cli, and the vach dependency of your application, to a release supporting them",
+		);
+		return findings;
+	}
+
+	// Applications built without some features open the archive, but can't fetch entries relying on them
+	let features = [
+		(capabilities::COMPRESSION, "`compression`"),
+		(capabilities::ENCRYPTION, "`crypto`"),
+	]
+	.iter()
+	.filter(|(bit, _)| raw.capabilities & bit != 0)
+	.map(|(_, feature)| *feature)
+	.collect::<Vec<_>>();
+
+	if !features.is_empty() {
+		findings.ok(format!("Applications fetching it's entries need the {} feature(s) of vach", features.join(" and ")));
+	}
+
+	if raw.capabilities & capabilities::PIPELINES != 0 {
+		findings.warn(
+			"Some entries went through custom pipeline stages, their data is left undecoded here",
+			"Applications must register the same stages with `ArchiveConfig::register_stage` to load them",
+		);
+	}
+
+	let file = match File::open(path) {
+		Ok(file) => file,
+		Err(err) => {
+			findings.fail(format!("Unable to reopen the file: {}", err), "Check the file wasn't moved while diagnosing");
+			return findings;
+		},
+	};
+
+	let archive = match Archive::with_config(file, &ArchiveConfig::new(raw.magic, key)) {
+		Ok(archive) => archive,
+		Err(err) => {
+			let fix = match err.root() {
+				InternalError::IOError(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+					"The registry is cut short, the file is truncated. Download or pack it again"
+				},
+				InternalError::ExpiredKeyError(_) => "Rotate the key with `vach key rotate`",
+				_ => "The header or registry is corrupt, pack the archive again",
+			};
+
+			findings.fail(format!("Unable to load the registry: {}", err), fix);
+			return findings;
+		},
+	};
+
+	let entries = archive.entries().values().filter(|e| !e.is_tombstone()).collect::<Vec<_>>();
+	findings.ok(format!("Registry lists {} entries", entries.len()));
+
+	if archive.key_expired() {
+		findings.warn(
+			"The key the archive was written with has expired",
+			"Rotate the key with `vach key rotate`, loaders enforcing key expiry refuse this archive",
+		);
+	}
+
+	let end = entries.iter().map(|e| e.location + e.offset).max().unwrap_or(0);
+	if end > length {
+		findings.fail(
+			format!("Entries reach {} bytes past the end of the file", end - length),
+			"The file is truncated, download or pack it again",
+		);
+	}
+
+	// Legacy headers declare no capabilities, any known one may be in use
+	let declared = match raw.is_legacy() {
+		true => capabilities::KNOWN,
+		false => raw.capabilities,
+	};
+
+	let suspicious = entries
+		.iter()
+		.filter(|e| suspicious(&e.flags, declared))
+		.collect::<Vec<_>>();
+
+	if let Some(first) = suspicious.first() {
+		findings.warn(
+			format!(
+				"{} entries carry contradicting flags, eg: {} with {}",
+				suspicious.len(),
+				first.id,
+				first.flags
+			),
+			"The archive was likely written by a broken or foreign tool, pack it again with vach-cli",
+		);
+	}
+
+	let encrypted = entries.iter().filter(|e| e.flags.contains(Flags::ENCRYPTED_FLAG)).collect::<Vec<_>>();
+	let signed = entries.iter().filter(|e| e.signature.is_some()).collect::<Vec<_>>();
+
+	match (encrypted.first(), key) {
+		(None, _) => (),
+		(Some(_), None) => findings.warn(
+			format!("{} entries are encrypted, but no key was given", encrypted.len()),
+			"Pass the keypair the archive was packed with using -k, or it's public key using -p",
+		),
+		(Some(entry), Some(_)) => match archive.fetch(&entry.id) {
+			Ok(_) => findings.ok(format!("Decrypted {}, one of {} encrypted entries", entry.id, encrypted.len())),
+			Err(err) => findings.fail(
+				format!("Unable to decrypt {}: {}", entry.id, err),
+				"The key doesn't match the archive, compare fingerprints with `vach key fingerprint -i <key>`",
+			),
+		},
+	}
+
+	match (signed.first(), key) {
+		(None, _) => (),
+		(Some(_), None) => findings.warn(
+			format!("{} entries are signed, but without a key their signatures aren't checked", signed.len()),
+			"Pass the archive's public key using -p to authenticate them",
+		),
+		(Some(entry), Some(_)) => match archive.fetch(&entry.id) {
+			Ok(resource) if resource.authenticated => findings.ok(format!("Authenticated signed entry {}", entry.id)),
+			Ok(_) => findings.fail(
+				format!("The signature of {} doesn't match the given key", entry.id),
+				"Use the key the archive was signed with, compare fingerprints with `vach key fingerprint -i <key>`",
+			),
+			Err(err) => findings.fail(
+				format!("Unable to load {}: {}", entry.id, err),
+				"Run `vach verify --deep` for a report on every entry",
+			),
+		},
+	}
+
+	findings
+}
+
+/// Flags that no writer sets together, or that need capabilities the header doesn't declare
+fn suspicious(flags: &Flags, declared: u32) -> bool {
+	let algorithms = [
+		Flags::LZ4_COMPRESSED,
+		Flags::SNAPPY_COMPRESSED,
+		Flags::BROTLI_COMPRESSED,
+		Flags::DEFLATE_COMPRESSED,
+	]
+	.iter()
+	.filter(|bit| flags.contains(**bit))
+	.count();
+
+	let compressed = flags.contains(Flags::COMPRESSED_FLAG);
+	let wrong_algorithm = if compressed { algorithms != 1 } else { algorithms != 0 };
+
+	let undeclared = (compressed && declared & capabilities::COMPRESSION == 0)
+		|| (flags.contains(Flags::ENCRYPTED_FLAG) && declared & capabilities::ENCRYPTION == 0)
+		|| (flags.contains(Flags::PIPELINE_FLAG) && declared & capabilities::PIPELINES == 0)
+		|| (flags.contains(Flags::KEY_EPOCH_FLAG) && declared & capabilities::KEY_EPOCHS == 0);
+
+	wrong_algorithm || undeclared
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use vach::crypto_utils;
+use vach::prelude::{Archive, ArchiveConfig, Flags};
+
+use super::{pack::WalkFilter, CommandTrait};
+use crate::keys::key_names;
+use crate::utils::manifest::{self, Manifest, ManifestEntry};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command writes a manifest describing directories or existing archives, for use with `pack --from-manifest`
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		let output_path = args.value_of(key_names::OUTPUT);
+
+		// Sources are written relative to the manifest's directory
+		let base = match output_path.map(Path::new).and_then(Path::parent) {
+			Some(dir) if !dir.as_os_str().is_empty() => dir.canonicalize()?,
+			_ => std::env::current_dir()?,
+		};
+
+		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+			Some(magic) => magic.as_bytes().try_into()?,
+			None => *vach::DEFAULT_MAGIC,
+		};
+
+		let public_key = match args.value_of(key_names::KEYPAIR) {
+			Some(path) => Some(crypto_utils::read_keypair(File::open(path)?)?.verifying_key()),
+			None => match args.value_of(key_names::PUBLIC_KEY) {
+				Some(path) => Some(crypto_utils::read_public_key(File::open(path)?)?),
+				None => None,
+			},
+		};
+
+		let mut entries = vec![];
+
+		// Entries of existing archives keep their settings, their sources are where `vach unpack` would write them
+		if let Some(inputs) = args.values_of(key_names::INPUT) {
+			let config = ArchiveConfig::new(magic, public_key);
+
+			for input in inputs {
+				let archive = Archive::with_config(File::open(input)?, &config)?;
+
+				for (_, entry) in archive.entries_vec_sorted() {
+					let resource = archive.fetch(&entry.id)?;
+					let flags = entry.flags;
+
+					entries.push(ManifestEntry {
+						id: entry.id.to_string(),
+						source: entry.id.to_string(),
+						compress: if flags.contains(Flags::COMPRESSED_FLAG) { "always" } else { "never" }.to_string(),
+						algorithm: manifest::algorithm_name(&flags).map(String::from),
+						encrypt: flags.contains(Flags::ENCRYPTED_FLAG),
+						sign: flags.contains(Flags::SIGNED_FLAG),
+						flags: Flags::from_bits(flags.bits() & !Flags::RESERVED_MASK),
+						version: entry.content_version,
+						sha256: Some(manifest::sha256(resource.data.as_ref())?),
+					});
+				}
+			}
+		}
+
+		// Files found in directories take their settings from the usual pack keys
+		let walk_filter = WalkFilter::new(args);
+
+		let files = args
+			.values_of(key_names::DIR_INPUT)
+			.into_iter()
+			.flatten()
+			.flat_map(|dir| walk_filter.walk(dir, Some(1)))
+			.chain(
+				args.values_of(key_names::DIR_INPUT_REC)
+					.into_iter()
+					.flatten()
+					.flat_map(|dir| walk_filter.walk(dir, None)),
+			)
+			.filter(|path| path.is_file())
+			.collect::<Vec<_>>();
+
+		let compress = args.value_of(key_names::COMPRESS_MODE).unwrap_or("never");
+		manifest::parse_compress_mode(compress)?;
+
+		let algorithm = args.value_of(key_names::COMPRESS_ALGO);
+		algorithm.map(manifest::parse_compression_algo).transpose()?;
+
+		let version = match args.value_of(key_names::VERSION) {
+			Some(version) => version.parse::<u8>()?,
+			None => 0,
+		};
+
+		for path in files {
+			let id = path.to_string_lossy().replace('\\', "/");
+
+			entries.push(ManifestEntry {
+				id,
+				source: relative_to(&base, &path)?,
+				compress: compress.to_lowercase(),
+				algorithm: algorithm.map(str::to_lowercase),
+				encrypt: args.is_present(key_names::ENCRYPT),
+				sign: args.is_present(key_names::HASH),
+				flags: Flags::empty(),
+				version,
+				sha256: Some(manifest::sha256(File::open(&path)?)?),
+			});
+		}
+
+		if entries.is_empty() {
+			fail!(Usage, "Please provide an archive using -i, or directories using -d or -r");
+		}
+
+		let mut ids = HashSet::new();
+		if let Some(entry) = entries.iter().find(|e| !ids.insert(e.id.as_str())) {
+			fail!(Usage, "The ID {} appears more than once in the given inputs", entry.id);
+		}
+
+		// Stable order, so regenerated manifests diff cleanly
+		entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+		let manifest = Manifest {
+			magic: args.value_of(key_names::MAGIC).map(String::from),
+			entries,
+		};
+
+		let json = serde_json::to_string_pretty(&manifest)?;
+		match output_path {
+			Some(path) => {
+				std::fs::write(path, json + "\n")?;
+				println!("Wrote a manifest of {} entries @ {}", manifest.entries.len(), path);
+			},
+			None => println!("{}", json),
+		};
+
+		Ok(())
+	}
+}
+
+/// The path of `file` relative to `base` with `/` separators, or its absolute path when it's outside of `base`
+fn relative_to(base: &Path, file: &Path) -> anyhow::Result<String> {
+	let file = file.canonicalize()?;
+	let path = file.strip_prefix(base).map(PathBuf::from).unwrap_or(file);
+
+	Ok(path.to_string_lossy().replace('\\', "/"))
+}
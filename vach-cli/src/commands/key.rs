@@ -0,0 +1,171 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use tempfile::NamedTempFile;
+use vach::crypto_utils;
+use vach::lock::LockMode;
+use vach::prelude::{Archive, ArchiveConfig, ArchiveEditor, Flags, SigningKey, VerifyingKey};
+
+use super::CommandTrait;
+use crate::{keys::key_names, utils};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command inspects, fingerprints and rotates keys
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		match args.subcommand() {
+			Some(("inspect", args)) => inspect(args),
+			Some(("fingerprint", args)) => fingerprint(args),
+			Some(("rotate", args)) => rotate(args),
+			_ => fail!(Usage, "Please provide a key subcommand: inspect, fingerprint or rotate"),
+		}
+	}
+}
+
+/// The kinds of key files written by `vach keypair`
+enum KeyFile {
+	Keypair(SigningKey),
+	Secret(SigningKey),
+	Public(VerifyingKey),
+}
+
+impl KeyFile {
+	/// Keypairs are told apart by their size, secret and public keys share a size so `.sk` files are read as secret keys
+	fn read(path: &str) -> anyhow::Result<KeyFile> {
+		let bytes = fs::read(path)?;
+		let is_secret = Path::new(path).extension().map(|ext| ext == "sk").unwrap_or(false);
+
+		Ok(match bytes.len() {
+			len if len == vach::SECRET_KEY_LENGTH + vach::PUBLIC_KEY_LENGTH => {
+				KeyFile::Keypair(crypto_utils::read_keypair(bytes.as_slice())?)
+			},
+			vach::SECRET_KEY_LENGTH if is_secret => KeyFile::Secret(crypto_utils::read_secret_key(bytes.as_slice())?),
+			vach::PUBLIC_KEY_LENGTH => KeyFile::Public(crypto_utils::read_public_key(bytes.as_slice())?),
+			len => fail!(BadKey, "{} is not a key file, found {} bytes", path, len),
+		})
+	}
+
+	fn kind(&self) -> &'static str {
+		match self {
+			KeyFile::Keypair(_) => "keypair",
+			KeyFile::Secret(_) => "secret key",
+			KeyFile::Public(_) => "public key",
+		}
+	}
+
+	fn verifying_key(&self) -> VerifyingKey {
+		match self {
+			KeyFile::Keypair(key) | KeyFile::Secret(key) => key.verifying_key(),
+			KeyFile::Public(key) => *key,
+		}
+	}
+}
+
+fn key_path(args: &clap::ArgMatches) -> anyhow::Result<&str> {
+	match args.value_of(key_names::INPUT) {
+		Some(path) => Ok(path),
+		None => fail!(Usage, "Please provide a key file using the -i or --input key"),
+	}
+}
+
+fn inspect(args: &clap::ArgMatches) -> anyhow::Result<()> {
+	let path = key_path(args)?;
+	let key = KeyFile::read(path)?;
+
+	let public_key = key.verifying_key();
+	let public_hex = public_key.as_bytes().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+	match args.value_of(key_names::FORMAT) {
+		Some("json") => {
+			let report = serde_json::json!({
+				"path": path,
+				"kind": key.kind(),
+				"public_key": public_hex,
+				"fingerprint": utils::fingerprint(&public_key),
+			});
+
+			println!("{}", serde_json::to_string_pretty(&report)?);
+		},
+		_ => {
+			println!("{}: {}", path, key.kind());
+			println!("Public key: {}", public_hex);
+			println!("Fingerprint: {}", utils::fingerprint(&public_key));
+		},
+	};
+
+	Ok(())
+}
+
+fn fingerprint(args: &clap::ArgMatches) -> anyhow::Result<()> {
+	let key = KeyFile::read(key_path(args)?)?;
+	println!("{}", utils::fingerprint(&key.verifying_key()));
+
+	Ok(())
+}
+
+fn rotate(args: &clap::ArgMatches) -> anyhow::Result<()> {
+	let archive_path = match args.value_of(key_names::ARCHIVE) {
+		Some(path) => path,
+		None => fail!(Usage, "Please provide the archive to re-sign using the --archive key"),
+	};
+
+	// Rewrites the archive in place, unless an output is provided
+	let output_path = args.value_of(key_names::OUTPUT).unwrap_or(archive_path);
+
+	let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+		Some(magic) => magic.as_bytes().try_into()?,
+		None => *vach::DEFAULT_MAGIC,
+	};
+
+	// Existing signatures are checked against the old key, so tampered entries are never re-signed
+	let old_key = match args.value_of(key_names::OLD_KEY) {
+		Some(path) => KeyFile::read(path)?.verifying_key(),
+		None => fail!(Usage, "Please provide the key the archive is currently signed with using --old-key"),
+	};
+
+	let new_key = match args.value_of(key_names::KEYPAIR).or(args.value_of(key_names::SECRET_KEY)) {
+		Some(path) => crypto_utils::read_secret_key(File::open(path)?)?,
+		None => fail!(Usage, "Please provide the new keypair or secret key using -k or -s"),
+	};
+
+	let archive = Archive::with_config(File::open(archive_path)?, &ArchiveConfig::new(magic, Some(old_key)))?;
+
+	// Reserved entries are re-signed too
+	for (id, entry) in archive.iter().chain(archive.reserved()) {
+		// The encryption key is derived from the public key, so encrypted entries can't be carried over
+		if entry.flags.contains(Flags::ENCRYPTED_FLAG) {
+			fail!(BadKey, "{} is encrypted with the old key, repack the archive to rotate its key", id);
+		}
+
+		if entry.flags.contains(Flags::SIGNED_FLAG) && !archive.fetch(id)?.authenticated {
+			fail!(Verification, "The signature of {} doesn't match the old key, refusing to re-sign it", id);
+		}
+	}
+
+	let mut editor = ArchiveEditor::new(archive);
+	editor.signing_key(new_key.clone());
+	let count = editor.resign();
+
+	// Write into a temporary file first, since the output usually overwrites the input
+	let parent = Path::new(output_path).parent().filter(|p| !p.as_os_str().is_empty());
+	let mut temporary_file = match parent {
+		Some(dir) => NamedTempFile::new_in(dir)?,
+		None => NamedTempFile::new_in(".")?,
+	};
+
+	editor.commit(&mut temporary_file)?;
+	let _lock = utils::lock_archive(args.is_present(key_names::LOCK), output_path, LockMode::Exclusive)?;
+	temporary_file.persist(output_path)?;
+
+	println!(
+		"Re-signed {} entries @ {} with {}",
+		count,
+		output_path,
+		utils::fingerprint(&new_key.verifying_key())
+	);
+
+	Ok(())
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::path::Path;
+
+use vach::crypto_utils;
+use vach::prelude::{Archive, ArchiveConfig, LintWarning};
+
+use super::CommandTrait;
+use crate::{keys::key_names, utils};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command reports likely mistakes in one or more archives, like uncompressed text or duplicated content
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		let inputs = match args.values_of(key_names::INPUT) {
+			Some(inputs) => utils::collect_archives(inputs)?,
+			None => fail!(Usage, "Please provide an input path using the -i or --input key"),
+		};
+
+		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+			Some(magic) => magic.as_bytes().try_into()?,
+			None => *vach::DEFAULT_MAGIC,
+		};
+
+		// Without a key, content checks are skipped for encrypted entries
+		let public_key = match args.value_of(key_names::KEYPAIR) {
+			Some(path) => Some(crypto_utils::read_keypair(File::open(path)?)?.verifying_key()),
+			None => match args.value_of(key_names::PUBLIC_KEY) {
+				Some(path) => Some(crypto_utils::read_public_key(File::open(path)?)?),
+				None => None,
+			},
+		};
+
+		let config = ArchiveConfig::new(magic, public_key);
+		let json = args.value_of(key_names::FORMAT) == Some("json");
+
+		let mut reports = Vec::with_capacity(inputs.len());
+		for path in &inputs {
+			reports.push((path, lint(path, &config)?));
+		}
+
+		let total = reports.iter().map(|(_, warnings)| warnings.len()).sum::<usize>();
+
+		if json {
+			let report = reports
+				.iter()
+				.map(|(path, warnings)| {
+					let warnings = warnings
+						.iter()
+						.map(|w| serde_json::json!({ "id": w.id().as_ref(), "kind": w.name(), "message": w.to_string() }))
+						.collect::<Vec<_>>();
+
+					serde_json::json!({ "path": path, "warnings": warnings })
+				})
+				.collect::<Vec<_>>();
+
+			println!("{}", serde_json::to_string_pretty(&report)?);
+		} else {
+			for (path, warnings) in &reports {
+				for warning in warnings {
+					println!("{}: [{}] {}", path.to_string_lossy(), warning.name(), warning);
+				}
+			}
+
+			println!("{} warning(s) in {} archive(s)", total, reports.len());
+		}
+
+		if total > 0 && args.is_present(key_names::STRICT) {
+			fail!(Lint, "Found {} lint warning(s)", total);
+		}
+
+		Ok(())
+	}
+}
+
+fn lint(path: &Path, config: &ArchiveConfig) -> anyhow::Result<Vec<LintWarning>> {
+	let archive = Archive::with_config(File::open(path)?, config)?;
+	Ok(archive.lint()?)
+}
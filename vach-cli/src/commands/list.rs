@@ -4,23 +4,24 @@ use tabled::{
 	Table, Tabled,
 	settings::{*, object::Columns},
 };
-use vach::prelude::{ArchiveConfig, Archive, Flags};
+use vach::prelude::{ArchiveConfig, Archive, Flags, LayoutReport, RegionKind};
+use vach::lock::LockMode;
 use indicatif::HumanBytes;
 
 use super::CommandTrait;
-use crate::keys::key_names;
+use crate::{keys::key_names, utils};
 
-pub const VERSION: &str = "0.2";
+pub const VERSION: &str = "0.4";
 
 /// This command lists the entries in an archive in tabulated form
 pub struct Evaluator;
 
 impl CommandTrait for Evaluator {
 	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
-		let archive_path = match args.value_of(key_names::INPUT) {
-			Some(path) => path,
+		let inputs = match args.values_of(key_names::INPUT) {
+			Some(inputs) => utils::collect_archives(inputs)?,
 			None => {
-				anyhow::bail!("Please provide an input archive file using the -i or --input keys!")
+				fail!(Usage, "Please provide an input archive file using the -i or --input keys!")
 			},
 		};
 
@@ -29,61 +30,185 @@ impl CommandTrait for Evaluator {
 			None => *vach::DEFAULT_MAGIC,
 		};
 
-		let file = File::open(archive_path)?;
-		let archive = Archive::with_config(file, &ArchiveConfig::new(magic, None))?;
-
-		// log basic metadata
-		println!("{}", archive);
-
-		let mut entries: Vec<_> = archive.entries().iter().map(|(_, entry)| entry).collect();
-
-		// Sort the entries accordingly
-		match args.value_of(key_names::SORT) {
-			Some("alphabetical") => entries.sort_by(|a, b| a.id.cmp(&b.id)),
-			Some("alphabetical-reversed") => entries.sort_by(|a, b| b.id.cmp(&a.id)),
-			Some("size-ascending") => entries.sort_by(|a, b| a.offset.cmp(&b.offset)),
-			Some("size-descending") => entries.sort_by(|a, b| b.offset.cmp(&a.offset)),
-			Some(sort) => anyhow::bail!("Unknown sort option provided: {}. Valid sort types are: 'alphabetical' 'alphabetical-descending' 'size-ascending' 'size-descending'", sort),
-			_ => (),
-		};
-
-		let table_entries: Vec<FileTableEntry> = entries
-			.into_iter()
-			.map(|entry| {
-				let c_algo = if entry.flags.contains(Flags::LZ4_COMPRESSED) {
-					"LZ4"
-				} else if entry.flags.contains(Flags::BROTLI_COMPRESSED) {
-					"Brotli"
-				} else if entry.flags.contains(Flags::SNAPPY_COMPRESSED) {
-					"Snappy"
+		let json = args.value_of(key_names::FORMAT) == Some("json");
+		let layout = args.is_present(key_names::LAYOUT);
+		let query = utils::parse_query(args)?;
+		let mut report = vec![];
+
+		for archive_path in &inputs {
+			let _lock = utils::lock_archive(args.is_present(key_names::LOCK), archive_path, LockMode::Shared)?;
+			let file = File::open(archive_path)?;
+			let archive = Archive::with_config(file, &ArchiveConfig::new(magic, None))?;
+
+			if layout {
+				let layout = archive.layout_report()?;
+
+				if json {
+					report.push(serde_json::json!({
+						"path": archive_path,
+						"size": layout.size,
+						"unused": layout.unused(),
+						"regions": layout.regions.iter().map(|r| serde_json::json!({
+							"start": r.start,
+							"end": r.end,
+							"kind": region_name(&r.kind),
+							"id": match &r.kind { RegionKind::Leaf(id) => Some(id.as_ref()), _ => None },
+						})).collect::<Vec<_>>(),
+					}));
 				} else {
-					"None"
-				};
+					if inputs.len() > 1 {
+						println!("{}", archive_path.to_string_lossy());
+					}
 
-				FileTableEntry {
+					print_layout(&layout);
+				}
+
+				continue;
+			}
+
+			let tagged = utils::tagged(args, &archive)?;
+			let mut entries: Vec<_> = archive
+				.entries()
+				.values()
+				.filter(|entry| query.as_ref().is_none_or(|q| q.matches(entry)))
+				.filter(|entry| tagged.as_ref().is_none_or(|t| t.contains(&entry.id)))
+				.collect();
+
+			// Sort the entries accordingly
+			match args.value_of(key_names::SORT) {
+				Some("alphabetical") => entries.sort_by(|a, b| a.id.cmp(&b.id)),
+				Some("alphabetical-reversed") => entries.sort_by(|a, b| b.id.cmp(&a.id)),
+				Some("size-ascending") => entries.sort_by_key(|a| a.offset),
+				Some("size-descending") => entries.sort_by_key(|a| std::cmp::Reverse(a.offset)),
+				Some(sort) => fail!(Usage, "Unknown sort option provided: {}. Valid sort types are: 'alphabetical' 'alphabetical-descending' 'size-ascending' 'size-descending'", sort),
+				_ => (),
+			};
+
+			if json {
+				let entries = entries
+					.into_iter()
+					.map(|entry| {
+						serde_json::json!({
+							"id": entry.id.as_ref(),
+							"size": entry.offset,
+							"decoded_size": entry.decoded_size,
+							"ratio": entry.ratio(),
+							"flags": entry.flags.bits(),
+							"compression": compression(&entry.flags),
+						})
+					})
+					.collect::<Vec<_>>();
+
+				report.push(serde_json::json!({
+					"path": archive_path,
+					"flags": archive.flags().bits(),
+					"entries": entries,
+				}));
+
+				continue;
+			}
+
+			// log basic metadata
+			if inputs.len() > 1 {
+				println!("{}", archive_path.to_string_lossy());
+			}
+
+			println!("{}", archive);
+
+			let table_entries: Vec<FileTableEntry> = entries
+				.into_iter()
+				.map(|entry| FileTableEntry {
 					id: &entry.id,
 					size: HumanBytes(entry.offset).to_string(),
+					decoded: HumanBytes(entry.decoded_size).to_string(),
+					ratio: format!("{:.2}", entry.ratio()),
 					flags: entry.flags,
-					compression: c_algo,
-				}
-			})
-			.collect();
+					compression: compression(&entry.flags),
+				})
+				.collect();
+
+			let mut table = Table::new(table_entries);
+			table
+				.with(Style::rounded())
+				.with(Modify::list(Columns::new(..1), Alignment::left()));
 
-		let mut table = Table::new(table_entries);
-		table
-			.with(Style::rounded())
-			.with(Modify::list(Columns::new(..1), Alignment::left()));
+			println!("{}", table);
+		}
 
-		println!("{}", table);
+		if json {
+			println!("{}", serde_json::to_string_pretty(&report)?);
+		}
 
 		Ok(())
 	}
 }
 
+fn region_name(kind: &RegionKind) -> &'static str {
+	match kind {
+		RegionKind::Header => "header",
+		RegionKind::Registry => "registry",
+		RegionKind::Leaf(_) => "leaf",
+		RegionKind::Unused => "unused",
+	}
+}
+
+fn print_layout(layout: &LayoutReport) {
+	let rows: Vec<LayoutTableEntry> = layout
+		.regions
+		.iter()
+		.map(|region| LayoutTableEntry {
+			start: region.start,
+			end: region.end,
+			size: HumanBytes(region.len()).to_string(),
+			region: match &region.kind {
+				RegionKind::Leaf(id) => id.to_string(),
+				kind => format!("<{}>", region_name(kind)),
+			},
+		})
+		.collect();
+
+	let mut table = Table::new(rows);
+	table
+		.with(Style::rounded())
+		.with(Modify::list(Columns::new(3..), Alignment::left()));
+
+	println!("{}", table);
+	println!(
+		"{} unused, {:.2}% of {}",
+		HumanBytes(layout.unused()),
+		layout.fragmentation() * 100.0,
+		HumanBytes(layout.size)
+	);
+}
+
+fn compression(flags: &Flags) -> &'static str {
+	if flags.contains(Flags::LZ4_COMPRESSED) {
+		"LZ4"
+	} else if flags.contains(Flags::BROTLI_COMPRESSED) {
+		"Brotli"
+	} else if flags.contains(Flags::DEFLATE_COMPRESSED) {
+		"Deflate"
+	} else if flags.contains(Flags::SNAPPY_COMPRESSED) {
+		"Snappy"
+	} else {
+		"None"
+	}
+}
+
+#[derive(Tabled)]
+struct LayoutTableEntry {
+	start: u64,
+	end: u64,
+	size: String,
+	region: String,
+}
+
 #[derive(Tabled)]
 struct FileTableEntry<'a> {
 	id: &'a str,
 	size: String,
+	decoded: String,
+	ratio: String,
 	flags: Flags,
 	compression: &'static str,
 }
@@ -12,10 +12,20 @@ pub trait CommandTrait: Sync {
 }
 
 // All sub-commands are defined in the below modules
+pub mod diff;
+pub mod doctor;
+pub mod gen_manifest;
+pub mod key;
 pub mod keypair;
+pub mod lint;
 pub mod list;
+pub mod mv;
 pub mod pack;
 pub mod pipe;
+pub mod recompress;
+pub mod release;
+pub mod selftest;
+pub mod spec;
 pub mod split;
 pub mod unpack;
 pub mod verify;
@@ -24,12 +34,22 @@ pub fn build_commands() -> HashMap<&'static str, Box<dyn CommandTrait>> {
 	let mut map: HashMap<&'static str, Box<dyn CommandTrait>> = HashMap::with_capacity(6);
 
 	map.insert("keypair", Box::new(keypair::Evaluator));
+	map.insert("key", Box::new(key::Evaluator));
 	map.insert("split", Box::new(split::Evaluator));
 	map.insert("verify", Box::new(verify::Evaluator));
 	map.insert("list", Box::new(list::Evaluator));
+	map.insert("lint", Box::new(lint::Evaluator));
+	map.insert("diff", Box::new(diff::Evaluator));
+	map.insert("doctor", Box::new(doctor::Evaluator));
 	map.insert("unpack", Box::new(unpack::Evaluator));
 	map.insert("pack", Box::new(pack::Evaluator));
+	map.insert("gen-manifest", Box::new(gen_manifest::Evaluator));
+	map.insert("mv", Box::new(mv::Evaluator));
 	map.insert("pipe", Box::new(pipe::Evaluator));
+	map.insert("recompress", Box::new(recompress::Evaluator));
+	map.insert("selftest", Box::new(selftest::Evaluator));
+	map.insert("release", Box::new(release::Evaluator));
+	map.insert("spec", Box::new(spec::Evaluator));
 
 	map
 }
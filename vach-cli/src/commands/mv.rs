@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::path::Path;
+
+use tempfile::NamedTempFile;
+use vach::prelude::{Archive, ArchiveConfig, ArchiveEditor};
+use vach::crypto_utils;
+use vach::lock::LockMode;
+
+use super::CommandTrait;
+use crate::{keys::key_names, utils};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command renames entries within an archive
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		// `-i` takes several values, so the IDs in `vach mv -i pack.vach old/id new/id` end up as extra inputs
+		let mut inputs = args.values_of(key_names::INPUT).into_iter().flatten();
+		let input_path = match inputs.next() {
+			Some(path) => path,
+			None => fail!(Usage, "Please provide an input path using the -i or --input key"),
+		};
+
+		let ids = inputs
+			.chain(args.value_of(key_names::FROM))
+			.chain(args.value_of(key_names::TO))
+			.collect::<Vec<_>>();
+
+		// Rewrites the archive in place, unless an output is provided
+		let output_path = args.value_of(key_names::OUTPUT).unwrap_or(input_path);
+
+		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+			Some(magic) => magic.as_bytes().try_into()?,
+			None => *vach::DEFAULT_MAGIC,
+		};
+
+		// Needed to re-sign renamed entries
+		let secret_key = match args.value_of(key_names::KEYPAIR).or(args.value_of(key_names::SECRET_KEY)) {
+			Some(path) => Some(crypto_utils::read_secret_key(File::open(path)?)?),
+			None => None,
+		};
+
+		let archive = Archive::with_config(File::open(input_path)?, &ArchiveConfig::new(magic, None))?;
+		let mut editor = ArchiveEditor::new(archive);
+
+		if let Some(key) = secret_key {
+			editor.signing_key(key);
+		}
+
+		let mut renamed = 0;
+
+		match ids.as_slice() {
+			[] => (),
+			[from, to] => {
+				editor.rename(from, to)?;
+				renamed += 1;
+			},
+			_ => fail!(Usage, "Please provide exactly one ID pair: vach mv -i <archive> <old-id> <new-id>"),
+		};
+
+		if let Some(rewrites) = args.values_of(key_names::PREFIX_REWRITE) {
+			for rewrite in rewrites {
+				let (from, to) = match rewrite.split_once('=') {
+					Some(pair) => pair,
+					None => fail!(Usage, "Invalid prefix rewrite: {}, expected the form: old/=new/", rewrite),
+				};
+
+				renamed += editor.rename_prefix(from, to)?;
+			}
+		}
+
+		if renamed == 0 {
+			fail!(Usage, "Nothing to rename, provide an <old-id> <new-id> pair or a --prefix-rewrite");
+		}
+
+		// Write into a temporary file first, in case the output overwrites the input
+		let parent = Path::new(output_path).parent().filter(|p| !p.as_os_str().is_empty());
+		let mut temporary_file = match parent {
+			Some(dir) => NamedTempFile::new_in(dir)?,
+			None => NamedTempFile::new_in(".")?,
+		};
+
+		if let Err(err) = editor.commit(&mut temporary_file) {
+			match err.root() {
+				vach::prelude::InternalError::NoKeypairError => fail!(
+					BadKey,
+					"Renamed entries are signed, provide a keypair or secret key using -k or -s to re-sign them"
+				),
+				_ => return Err(anyhow::Error::new(err).context("Unable to rewrite the archive")),
+			}
+		}
+
+		let _lock = utils::lock_archive(args.is_present(key_names::LOCK), output_path, LockMode::Exclusive)?;
+		temporary_file.persist(output_path)?;
+		println!("Renamed {} entries @ {}", renamed, output_path);
+
+		Ok(())
+	}
+}
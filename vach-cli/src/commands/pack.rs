@@ -1,20 +1,27 @@
 use std::{
 	fs::File,
-	io::{self, Read, Write},
+	io::{self, Read, Seek, Write},
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashSet;
+use std::sync::Arc;
 
 use tempfile::NamedTempFile;
 use vach::prelude::*;
 use vach::crypto_utils;
-use indicatif::{ProgressBar, ProgressStyle};
+use vach::lock::LockMode;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
 use walkdir;
 
 use super::CommandTrait;
-use crate::keys::key_names;
+use crate::{keys::key_names, utils};
+use crate::utils::{
+	manifest::{self, Manifest},
+	signer,
+	stats::{LiveStats, Timed},
+};
 
-pub const VERSION: &str = "0.0.5";
+pub const VERSION: &str = "0.0.7";
 
 struct FileWrapper(PathBuf, Option<File>);
 
@@ -40,49 +47,115 @@ impl Read for FileWrapper {
 	}
 }
 
+/// Filters applied to entries found when walking input directories
+pub(crate) struct WalkFilter {
+	follow_symlinks: bool,
+	hidden: bool,
+	files: bool,
+	symlinks: bool,
+	extensions: Option<HashSet<String>>,
+}
+
+impl WalkFilter {
+	pub(crate) fn new(args: &clap::ArgMatches) -> WalkFilter {
+		let types = args.values_of(key_names::FILE_TYPE).map(|v| v.collect::<Vec<_>>());
+		let has_type = |t: &str| types.as_ref().map(|types| types.contains(&t)).unwrap_or(true);
+
+		WalkFilter {
+			follow_symlinks: args.is_present(key_names::FOLLOW_SYMLINKS),
+			hidden: args.is_present(key_names::HIDDEN),
+			files: has_type("f"),
+			symlinks: has_type("l"),
+			extensions: args.values_of(key_names::EXTENSIONS).map(|v| {
+				v.map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+					.collect()
+			}),
+		}
+	}
+
+	pub(crate) fn walk<'a>(&'a self, dir: &'a str, max_depth: Option<usize>) -> impl Iterator<Item = PathBuf> + 'a {
+		let mut walker = walkdir::WalkDir::new(dir).follow_links(self.follow_symlinks);
+		if let Some(depth) = max_depth {
+			walker = walker.max_depth(depth);
+		};
+
+		walker
+			.into_iter()
+			// Skip hidden entries, the root is always walked
+			.filter_entry(move |e| self.hidden || e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+			.filter_map(move |entry| match entry {
+				Ok(entry) => Some(entry),
+				Err(err) => {
+					eprintln!("Failed to walk directory: {}. Skipping due to error: {}", dir, err);
+					None
+				},
+			})
+			.filter(move |entry| self.matches(entry))
+			.map(walkdir::DirEntry::into_path)
+	}
+
+	fn matches(&self, entry: &walkdir::DirEntry) -> bool {
+		// Without following, the entry's own type is a symlink
+		let is_symlink = entry.path_is_symlink();
+		if is_symlink && !(self.follow_symlinks && self.symlinks) {
+			return false;
+		};
+
+		if !is_symlink && !self.files {
+			return false;
+		};
+
+		match &self.extensions {
+			Some(extensions) => entry
+				.path()
+				.extension()
+				.map(|ext| extensions.contains(&ext.to_string_lossy().to_ascii_lowercase()))
+				.unwrap_or(false),
+			None => true,
+		}
+	}
+}
+
 /// This command verifies the validity and integrity of an archive
 pub struct Evaluator;
 
 impl CommandTrait for Evaluator {
 	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
-		// The archives magic
-		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+		// A manifest replaces all other inputs, and every entry must match its recorded digest
+		let manifest = match args.value_of(key_names::FROM_MANIFEST) {
+			Some(path) => Some((PathBuf::from(path), Manifest::read(Path::new(path))?)),
+			None => None,
+		};
+
+		// The archives magic, a manifest may carry its own
+		let magic = args
+			.value_of(key_names::MAGIC)
+			.or(manifest.as_ref().and_then(|(_, m)| m.magic.as_deref()));
+
+		let magic: [u8; vach::MAGIC_LENGTH] = match magic {
 			Some(magic) => magic.as_bytes().try_into()?,
 			None => *vach::DEFAULT_MAGIC,
 		};
 
 		// Flags that go into the header section of the archive
 		let flags = match args.value_of(key_names::FLAGS) {
-			Some(magic) => Flags::from_bits(magic.parse::<u32>()?),
+			Some(flags) => flags.parse::<Flags>()?,
 			None => Flags::default(),
 		};
 
 		// Extract the compress mode
-		let mut compress_mode = CompressMode::default();
-		if let Some(value) = args.value_of(key_names::COMPRESS_MODE) {
-			compress_mode = match value.to_lowercase().as_str() {
-				"always" => CompressMode::Always,
-				"detect" => CompressMode::Detect,
-				"never" => CompressMode::Never,
-				invalid_value => {
-					anyhow::bail!("{} is an invalid value for COMPRESS_MODE", invalid_value)
-				},
-			}
+		let compress_mode = match args.value_of(key_names::COMPRESS_MODE) {
+			Some(value) => manifest::parse_compress_mode(value)?,
+			None => CompressMode::default(),
 		};
 
-		// Extract the compress mode
-		let mut compression_algo = CompressionAlgorithm::default();
-		if let Some(value) = args.value_of(key_names::COMPRESS_ALGO) {
-			compression_algo = match value.to_lowercase().as_str() {
-				"lz4" => CompressionAlgorithm::LZ4,
-				"brotli" => CompressionAlgorithm::Brotli(8),
-				"snappy" => CompressionAlgorithm::Snappy,
-				invalid_value => {
-					anyhow::bail!("{} is an invalid value for COMPRESS_ALGO", invalid_value)
-				},
-			}
+		// Extract the compression algorithm
+		let compression_algo = match args.value_of(key_names::COMPRESS_ALGO) {
+			Some(value) => manifest::parse_compression_algo(value)?,
+			None => CompressionAlgorithm::default(),
 		};
 
+
 		// Extract entries to be excluded
 		let excludes = match args.values_of(key_names::EXCLUDE) {
 			Some(val) => val
@@ -129,28 +202,43 @@ impl CommandTrait for Evaluator {
 		};
 
 		// Extract directory inputs
+		let walk_filter = WalkFilter::new(args);
+
 		if let Some(val) = args.values_of(key_names::DIR_INPUT) {
-			val.for_each(|dir| {
-				walkdir::WalkDir::new(dir)
-					.max_depth(1)
-					.into_iter()
-					.map(|v| v.unwrap().into_path())
-					.filter(|f| path_filter(f))
-					.for_each(|p| inputs.push(FileWrapper(p, None)))
-			});
+			val.flat_map(|dir| walk_filter.walk(dir, Some(1)))
+				.filter(|f| path_filter(f))
+				.for_each(|p| inputs.push(FileWrapper(p, None)));
 		};
 
 		// Extract recursive directory inputs
 		if let Some(val) = args.values_of(key_names::DIR_INPUT_REC) {
-			val.flat_map(|dir| walkdir::WalkDir::new(dir).into_iter())
-				.map(|v| v.unwrap().into_path())
+			val.flat_map(|dir| walk_filter.walk(dir, None))
 				.filter(|f| path_filter(f))
 				.for_each(|p| inputs.push(FileWrapper(p, None)));
 		}
 
+		// Signing with an external signer implies signing
+		let signer = match args.value_of(key_names::SIGN_WITH) {
+			Some(spec) => {
+				let public_key = match args.value_of(key_names::PUBLIC_KEY) {
+					Some(path) => Some(crypto_utils::read_public_key(File::open(path)?)?),
+					None => None,
+				};
+
+				Some(signer::from_spec(spec, public_key)?)
+			},
+			None => None,
+		};
+
 		// Read valueless flags
 		let encrypt = args.is_present(key_names::ENCRYPT);
-		let hash = args.is_present(key_names::HASH);
+		let hash = args.is_present(key_names::HASH) || signer.is_some();
+
+		// Entries of a manifest can ask for keys too
+		let manifest_needs_key = manifest
+			.as_ref()
+			.map(|(_, m)| m.entries.iter().any(|e| e.encrypt || e.sign))
+			.unwrap_or(false);
 		let truncate = args.is_present(key_names::TRUNCATE);
 
 		// Extract the version information to be set
@@ -175,10 +263,10 @@ impl CommandTrait for Evaluator {
 		};
 
 		// Generate a keypair from the secret key
-		let mut kp = secret_key.map(|sk| SigningKey::from(sk));
+		let mut kp = secret_key;
 
 		// If encrypt is true, and no keypair was found: Generate and write a new keypair to a file
-		if (encrypt || hash) && kp.is_none() {
+		if (encrypt || hash || manifest_needs_key) && kp.is_none() && signer.is_none() {
 			let generated = crypto_utils::gen_keypair();
 
 			let mut file = File::create("keypair.kp")?;
@@ -188,7 +276,8 @@ impl CommandTrait for Evaluator {
 			kp = Some(generated);
 		}
 
-		let progress = ProgressBar::new(inputs.len() as u64 + 5 + if truncate { 3 } else { 0 });
+		let leaf_count = inputs.len() + manifest.as_ref().map(|(_, m)| m.entries.len()).unwrap_or(0);
+		let progress = ProgressBar::new(leaf_count as u64 + 5 + if truncate { 3 } else { 0 });
 		progress.set_style(
 			ProgressStyle::default_bar()
 				.template(super::PROGRESS_BAR_STYLE)?
@@ -196,26 +285,64 @@ impl CommandTrait for Evaluator {
 				.tick_chars("⢀ ⡀ ⠄ ⢂ ⡂ ⠅ ⢃ ⡃ ⠍ ⢋ ⡋ ⠍⠁⢋⠁⡋⠁⠍⠉⠋⠉⠋⠉⠉⠙⠉⠙⠉⠩⠈⢙⠈⡙⢈⠩⡀⢙⠄⡙⢂⠩⡂⢘⠅⡘⢃⠨⡃⢐⠍⡐⢋⠠⡋⢀⠍⡁⢋⠁⡋⠁⠍⠉⠋⠉⠋⠉⠉⠙⠉⠙⠉⠩⠈⢙⠈⡙⠈⠩ ⢙ ⡙ ⠩ ⢘ ⡘ ⠨ ⢐ ⡐ ⠠ ⢀ ⡀"),
 		);
 
+		// Build a builder-config using the above extracted data
+		let num_threads = args
+			.value_of(key_names::JOBS)
+			.and_then(|v| v.parse::<usize>().ok())
+			.unwrap_or(num_cpus::get());
+
+		// Live statistics are displayed in the progress area, under the progress bar
+		let multi = MultiProgress::new();
+		let stats = args.is_present(key_names::STATS).then(|| {
+			multi.add(progress.clone());
+			Arc::new(LiveStats::new(num_threads, multi.add(ProgressBar::new(0))))
+		});
+
 		// Since it wraps it's internal state in an arc, we can safely clone and send across threads
 		let callback = |entry: &RegistryEntry| {
 			progress.inc(1);
 			let message = entry.id.as_ref();
 			progress.set_message(message.to_string());
+
+			if let Some(stats) = &stats {
+				stats.record_pending(message, &entry.flags, entry.offset);
+			}
 		};
 
-		// Build a builder-config using the above extracted data
-		let num_threads = args
-			.value_of(key_names::JOBS)
-			.map(|v| v.parse::<usize>().ok())
-			.flatten()
-			.unwrap_or(num_cpus::get());
+		let layout_profile = match args.value_of(key_names::LAYOUT_PROFILE) {
+			Some(path) => Some(AccessProfile::from_bytes(&std::fs::read(path)?)?),
+			None => None,
+		};
 
 		let builder_config = BuilderConfig {
 			flags,
 			magic,
 			keypair: kp,
+			signer: signer.as_deref(),
+			encryption_key: None,
+			key_epoch: 0,
+			timestamp_authority: None,
 			progress_callback: Some(&callback),
-			num_threads,
+			detect_heuristics: DetectHeuristics::default(),
+			guardrail: Guardrail::Warn,
+			registry_compression: args.is_present(key_names::COMPRESS_REGISTRY).then_some(compression_algo),
+			registry_threshold: BuilderConfig::REGISTRY_THRESHOLD,
+			compute_threads: num_threads,
+			io_threads: (num_threads / 4).max(1),
+			prefix_ids: args.is_present(key_names::PREFIX_IDS),
+			id_index: args.is_present(key_names::ID_INDEX),
+			format_descriptor: args.is_present(key_names::FORMAT_DESCRIPTOR),
+			footer: args.is_present(key_names::FOOTER),
+			layout_profile: layout_profile.as_ref(),
+			data_offset: args.value_of(key_names::DATA_OFFSET).map(utils::parse_size).transpose()?,
+			key_policy: Default::default(),
+			build_id: None,
+		};
+
+		// Text files are told apart from binary ones by their contents
+		let newlines = match args.is_present(key_names::NORMALIZE_NEWLINES) {
+			true => NewlineMode::Detect,
+			false => NewlineMode::Keep,
 		};
 
 		// Construct the builder
@@ -225,13 +352,18 @@ impl CommandTrait for Evaluator {
 				.compression_algo(compression_algo)
 				.encrypt(encrypt)
 				.sign(hash)
-				.version(version),
+				.version(version)
+				.newlines(newlines),
 		);
 
+		// Parse budgets before any work is done
+		let max_size = args.value_of(key_names::MAX_SIZE).map(utils::parse_size).transpose()?;
+		let max_entry_size = args.value_of(key_names::MAX_ENTRY_SIZE).map(utils::parse_size).transpose()?;
+
 		// Prepare output file
 		let output_path = match args.value_of(key_names::OUTPUT) {
 			Some(path) => path,
-			None => anyhow::bail!("Please provide an output path using the -o or --output key"),
+			None => fail!(Usage, "Please provide an output path using the -o or --output key"),
 		};
 
 		let mut temporary_file = NamedTempFile::new().unwrap();
@@ -246,14 +378,77 @@ impl CommandTrait for Evaluator {
 			}
 
 			let id = wrapper.0.to_string_lossy().into_owned();
-			builder.add(wrapper, &id)?;
+			match &stats {
+				Some(stats) => builder.add(Timed::new(wrapper, id.clone(), stats.clone()), &id)?,
+				None => builder.add(wrapper, &id)?,
+			};
+		}
+
+		// Process manifest entries, each carries its own settings
+		if let Some((path, manifest)) = &manifest {
+			for entry in &manifest.entries {
+				let source = Manifest::source(path, entry);
+
+				if let Some(expected) = &entry.sha256 {
+					let found = manifest::sha256(File::open(&source)?)?;
+					if !found.eq_ignore_ascii_case(expected) {
+						fail!(
+							Verification,
+							"{} doesn't match the manifest, expected SHA-256: {} found: {}",
+							source.to_string_lossy(),
+							expected,
+							found
+						);
+					}
+				}
+
+				let file = FileWrapper(source, None);
+				let leaf = match &stats {
+					Some(stats) => Leaf::new(Timed::new(file, entry.id.clone(), stats.clone())),
+					None => Leaf::new(file),
+				};
+
+				let mut leaf = leaf
+					.id(&entry.id)
+					.compress(manifest::parse_compress_mode(&entry.compress)?)
+					.encrypt(entry.encrypt)
+					.sign(entry.sign)
+					.version(entry.version)
+					.flags(entry.custom_flags())
+					.newlines(newlines);
+
+				if let Some(algorithm) = &entry.algorithm {
+					leaf = leaf.compression_algo(manifest::parse_compression_algo(algorithm)?);
+				}
+
+				builder.add_leaf(leaf)?;
+			}
 		}
 
 		// Inform of success in input queue
 		progress.inc(2);
-		let bytes_written = builder.dump(&mut temporary_file, &builder_config)?;
+		let report = builder.dump_report(&mut temporary_file, &builder_config)?;
+		let bytes_written = report.leaf_bytes;
+		utils::metrics::record(report.metrics("pack"));
+
+		// Leaves that bloated the archive are written anyway, but worth knowing about
+		for warning in &report.warnings {
+			progress.println(format!("Warning: {}", warning));
+		}
+
+		// The archive is only written out if it fits its budgets
+		if max_size.is_some() || max_entry_size.is_some() {
+			let archive = Archive::with_config(temporary_file.reopen()?, &ArchiveConfig::new(magic, None))?;
+			check_budgets(&archive, temporary_file.as_file().metadata()?.len(), max_size, max_entry_size)?;
+		}
+
+		let _lock = utils::lock_archive(args.is_present(key_names::LOCK), output_path, LockMode::Exclusive)?;
 		temporary_file.persist(output_path)?;
 
+		if let Some(stats) = &stats {
+			stats.finish();
+		}
+
 		progress.println(format!(
 			"Generated a new archive @ {}; Bytes written: {}",
 			output_path, bytes_written
@@ -275,3 +470,56 @@ impl CommandTrait for Evaluator {
 		Ok(())
 	}
 }
+
+/// Fails with a report of the largest offenders if the archive, or any of it's entries, exceed their budgets
+fn check_budgets<T: Read + Seek>(
+	archive: &Archive<T>, archive_size: u64, max_size: Option<u64>, max_entry_size: Option<u64>,
+) -> anyhow::Result<()> {
+	const LARGEST: usize = 10;
+
+	let mut entries = archive.entries().values().collect::<Vec<_>>();
+	entries.sort_by_key(|entry| std::cmp::Reverse(entry.offset));
+
+	let mut problems = vec![];
+
+	if let Some(max) = max_size.filter(|max| archive_size > *max) {
+		problems.push(format!(
+			"The archive is {} ({} B), over the budget of {} ({} B). Largest entries:",
+			HumanBytes(archive_size),
+			archive_size,
+			HumanBytes(max),
+			max
+		));
+
+		for entry in entries.iter().take(LARGEST) {
+			problems.push(format!("  {:>12}  {}", HumanBytes(entry.offset).to_string(), entry.id));
+		}
+	}
+
+	if let Some(max) = max_entry_size {
+		let over = entries.iter().filter(|entry| entry.offset > max).collect::<Vec<_>>();
+
+		if !over.is_empty() {
+			problems.push(format!(
+				"{} entries are over the entry budget of {} ({} B):",
+				over.len(),
+				HumanBytes(max),
+				max
+			));
+
+			for entry in over.iter().take(LARGEST) {
+				problems.push(format!("  {:>12}  {}", HumanBytes(entry.offset).to_string(), entry.id));
+			}
+
+			if over.len() > LARGEST {
+				problems.push(format!("  ... and {} more", over.len() - LARGEST));
+			}
+		}
+	}
+
+	if !problems.is_empty() {
+		fail!(Budget, "Size budget exceeded\n{}", problems.join("\n"));
+	}
+
+	Ok(())
+}
@@ -1,13 +1,14 @@
 use std::{
 	fs::File,
-	io::{self, BufReader, Write},
+	io::{self, Read, Seek, Write},
 };
-use vach::{crypto_utils, prelude::*};
+use globset::{GlobBuilder, GlobSetBuilder};
+use vach::{crypto_utils, lock::LockMode, prelude::*};
 
 use super::CommandTrait;
-use crate::keys::key_names;
+use crate::{keys::key_names, utils};
 
-pub const VERSION: &str = "0.1.0";
+pub const VERSION: &str = "0.2.0";
 
 pub struct Evaluator;
 
@@ -15,12 +16,14 @@ impl CommandTrait for Evaluator {
 	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
 		let input_path = match args.value_of(key_names::INPUT) {
 			Some(path) => path,
-			None => anyhow::bail!("Please provide an input path using the -i or --input key"),
+			None => fail!(Usage, "Please provide an input path using the -i or --input key"),
 		};
 
+		let tar = args.is_present(key_names::TAR);
 		let resource = match args.value_of(key_names::RESOURCE) {
-			Some(resource) => resource,
-			None => anyhow::bail!("Please provide a resource to extract using the -r or --resource key"),
+			Some(resource) => Some(resource),
+			None if tar => None,
+			None => fail!(Usage, "Please provide a resource to extract using the -r or --resource key"),
 		};
 
 		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
@@ -33,7 +36,7 @@ impl CommandTrait for Evaluator {
 			Some(path) => {
 				let file = match File::open(path) {
 					Ok(it) => it,
-					Err(err) => anyhow::bail!("IOError: {} @ {}", err, path),
+					Err(err) => fail!(Io, "IOError: {} @ {}", err, path),
 				};
 
 				Some(crypto_utils::read_keypair(file)?.verifying_key())
@@ -47,9 +50,10 @@ impl CommandTrait for Evaluator {
 			},
 		};
 
+		let _lock = utils::lock_archive(args.is_present(key_names::LOCK), input_path, LockMode::Shared)?;
 		let input_file = match File::open(input_path) {
-			Ok(it) => BufReader::new(it),
-			Err(err) => anyhow::bail!("IOError: {} @ {}", err, input_path),
+			Ok(it) => it,
+			Err(err) => fail!(Io, "IOError: {} @ {}", err, input_path),
 		};
 
 		// Generate ArchiveConfig using given magic and public key
@@ -59,21 +63,69 @@ impl CommandTrait for Evaluator {
 		let mut archive = match Archive::with_config(input_file, &header_config) {
 			Ok(archive) => archive,
 			Err(err) => match err {
-				InternalError::NoKeypairError => anyhow::bail!(
+				InternalError::NoKeypairError => fail!(
+					BadKey,
 					"Please provide a public key or a keypair for use in decryption or signature verification"
 				),
-				InternalError::MalformedArchiveSource(_) => anyhow::bail!("Unable to validate the archive: {}", err),
-				err => anyhow::bail!("Encountered an error: {}", err.to_string()),
+				InternalError::MalformedArchiveSource(_) => fail!(Verification, "Unable to validate the archive: {}", err),
+				err => return Err(anyhow::Error::new(err).context("Unable to open the archive")),
 			},
 		};
 
 		let stdout = io::stdout();
-		{
-			let mut handle = stdout.lock();
-			let resource = archive.fetch_mut(resource)?;
-			handle.write_all(&resource.data)?;
-		}
+		let mut handle = stdout.lock();
+
+		match resource {
+			Some(resource) if !tar => {
+				let resource = archive.fetch_mut(resource)?;
+				handle.write_all(&resource.data)?;
+			},
+			_ => {
+				let patterns = args.values_of(key_names::INCLUDE).into_iter().flatten();
+				write_tar(&mut archive, resource, patterns, &mut handle)?;
+			},
+		};
 
 		Ok(())
 	}
 }
+
+/// Writes the selected resources into `target` as a tar stream, sorted by `ID`
+fn write_tar<'a, T: Read + Seek, W: Write>(
+	archive: &mut Archive<T>, resource: Option<&str>, patterns: impl Iterator<Item = &'a str>, target: W,
+) -> anyhow::Result<()> {
+	let mut globs = GlobSetBuilder::new();
+	let mut has_patterns = false;
+
+	for pattern in patterns {
+		globs.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+		has_patterns = true;
+	}
+
+	let globs = globs.build()?;
+
+	// Select resources matching any pattern, or all resources if neither patterns nor a resource are given
+	let mut ids = archive
+		.iter()
+		.map(|(id, _)| id.clone())
+		.filter(|id| globs.is_match(id.as_str()) || resource == Some(id.as_str()) || (!has_patterns && resource.is_none()))
+		.collect::<Vec<_>>();
+
+	ids.sort();
+
+	let mut tar = tar::Builder::new(target);
+	for id in ids {
+		let resource = archive.fetch_mut(&id)?;
+
+		let mut header = tar::Header::new_gnu();
+		header.set_size(resource.data.len() as u64);
+		header.set_mode(0o644);
+		header.set_mtime(0);
+		header.set_entry_type(tar::EntryType::Regular);
+
+		tar.append_data(&mut header, id.as_str(), resource.data.as_ref())?;
+	}
+
+	tar.finish()?;
+	Ok(())
+}
@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use vach::{crypto_utils, prelude::*};
+
+use super::CommandTrait;
+use crate::{keys::key_names, utils::manifest};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command compresses every leaf of an archive anew, reading from stdin and writing to stdout unless told otherwise
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+			Some(magic) => magic.as_bytes().try_into()?,
+			None => *vach::DEFAULT_MAGIC,
+		};
+
+		let compress_mode = match args.value_of(key_names::COMPRESS_MODE) {
+			Some(value) => manifest::parse_compress_mode(value)?,
+			None => CompressMode::default(),
+		};
+
+		let compression_algo = match args.value_of(key_names::COMPRESS_ALGO) {
+			Some(value) => manifest::parse_compression_algo(value)?,
+			None => CompressionAlgorithm::default(),
+		};
+
+		// Encrypted and signed leaves are decrypted, then encrypted and signed again with the same key
+		let secret_key = match args.value_of(key_names::KEYPAIR).or(args.value_of(key_names::SECRET_KEY)) {
+			Some(path) => Some(crypto_utils::read_secret_key(File::open(path)?)?),
+			None => None,
+		};
+
+		let num_threads = args
+			.value_of(key_names::JOBS)
+			.and_then(|v| v.parse::<usize>().ok())
+			.unwrap_or(num_cpus::get());
+
+		let config = ArchiveConfig::new(magic, secret_key.as_ref().map(|sk| sk.verifying_key()));
+		let mut builder_config = BuilderConfig::default()
+			.magic(magic)
+			.compute_threads(num_threads)
+			.io_threads((num_threads / 4).max(1));
+
+		builder_config.keypair = secret_key;
+
+		// `-` or no path at all stands for stdin and stdout, so no file is ever written in between
+		let source: Box<dyn Read> = match args.value_of(key_names::INPUT) {
+			None | Some("-") => Box::new(io::stdin().lock()),
+			Some(path) => Box::new(File::open(path)?),
+		};
+
+		let target: Box<dyn Write> = match args.value_of(key_names::OUTPUT) {
+			None | Some("-") => Box::new(io::stdout().lock()),
+			Some(path) => Box::new(File::create(path)?),
+		};
+
+		let report = match recompress(source, target, &config, compress_mode, compression_algo, &builder_config) {
+			Ok(report) => report,
+			Err(err) if matches!(err.root(), InternalError::NoKeypairError) => fail!(
+				BadKey,
+				"The archive holds encrypted or signed leaves, please provide it's keypair using the -k or --keypair key"
+			),
+			Err(err) => return Err(anyhow::Error::new(err).context("Unable to recompress the archive")),
+		};
+
+		// stdout carries the archive, so the summary goes to stderr
+		report.warnings.iter().for_each(|w| eprintln!("Warning: {}", w));
+		eprintln!(
+			"Recompressed {} leaves, {} bytes of leaf data written",
+			report.algorithms.values().map(|t| t.leaves).sum::<usize>(),
+			report.leaf_bytes
+		);
+
+		Ok(())
+	}
+}
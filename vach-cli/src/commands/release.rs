@@ -0,0 +1,97 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use vach::crypto_utils;
+use vach::release::ReleaseManifest;
+
+use super::CommandTrait;
+use crate::{keys::key_names, utils};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command creates and verifies release manifests, which authenticate every archive of a release at once
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		match args.subcommand() {
+			Some(("create", args)) => create(args),
+			Some(("verify", args)) => verify(args),
+			_ => fail!(Usage, "Please provide a release subcommand: create or verify"),
+		}
+	}
+}
+
+fn create(args: &clap::ArgMatches) -> anyhow::Result<()> {
+	let output_path = match args.value_of(key_names::OUTPUT) {
+		Some(path) => Path::new(path),
+		None => fail!(Usage, "Please provide where to write the manifest using the -o or --output key"),
+	};
+
+	let inputs = match args.values_of(key_names::INPUT) {
+		Some(inputs) => inputs.collect::<Vec<_>>(),
+		None => fail!(Usage, "Please provide the archives of the release using the -i or --input key"),
+	};
+
+	let signing_key = match args.value_of(key_names::KEYPAIR).or(args.value_of(key_names::SECRET_KEY)) {
+		Some(path) => crypto_utils::read_secret_key(File::open(path)?)?,
+		None => fail!(Usage, "Please provide a keypair or secret key to sign the manifest with, using -k or -s"),
+	};
+
+	// Archives are recorded relative to the manifest, so the release can be moved as a whole
+	let base = match output_path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir.canonicalize()?,
+		_ => std::env::current_dir()?,
+	};
+
+	let mut archives = Vec::with_capacity(inputs.len());
+	for input in inputs {
+		match Path::new(input).canonicalize()?.strip_prefix(&base) {
+			Ok(relative) => archives.push(relative.to_path_buf()),
+			Err(_) => fail!(Usage, "{} lies outside of the manifest's directory: {}", input, base.to_string_lossy()),
+		}
+	}
+
+	let name = args.value_of(key_names::RELEASE).unwrap_or_default();
+	let mut manifest = ReleaseManifest::create(name, &base, &archives)?;
+	manifest.sign(&signing_key);
+
+	fs::write(output_path, manifest.to_bytes())?;
+	println!(
+		"Signed release {} of {} archives @ {} with {}",
+		manifest.name,
+		manifest.archives.len(),
+		output_path.to_string_lossy(),
+		utils::fingerprint(&signing_key.verifying_key())
+	);
+
+	Ok(())
+}
+
+fn verify(args: &clap::ArgMatches) -> anyhow::Result<()> {
+	let manifest_path = match args.value_of(key_names::INPUT) {
+		Some(path) => Path::new(path),
+		None => fail!(Usage, "Please provide the release manifest using the -i or --input key"),
+	};
+
+	let public_key = match args.value_of(key_names::KEYPAIR) {
+		Some(path) => crypto_utils::read_keypair(File::open(path)?)?.verifying_key(),
+		None => match args.value_of(key_names::PUBLIC_KEY) {
+			Some(path) => crypto_utils::read_public_key(File::open(path)?)?,
+			None => fail!(Usage, "Please provide a public key or a keypair using -p or -k to verify the manifest with"),
+		},
+	};
+
+	let manifest = ReleaseManifest::from_bytes(&fs::read(manifest_path)?)?;
+	let base = manifest_path.parent().unwrap_or(Path::new(""));
+	manifest.verify(base, &public_key)?;
+
+	println!(
+		"Verified release {} @ {}: {} archives",
+		manifest.name,
+		manifest_path.to_string_lossy(),
+		manifest.archives.len()
+	);
+
+	Ok(())
+}
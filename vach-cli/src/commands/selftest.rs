@@ -0,0 +1,102 @@
+use std::io::Cursor;
+
+use vach::crypto_utils;
+use vach::prelude::{
+	Archive, ArchiveConfig, Builder, BuilderConfig, BrotliParams, CompressMode, CompressionAlgorithm, InternalError, Leaf,
+};
+
+use super::CommandTrait;
+
+pub const VERSION: &str = "0.1.0";
+
+type Check = fn() -> Result<(), InternalError>;
+
+/// This command checks the format's invariants and a full pack and unpack round trip on the current platform
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, _: &clap::ArgMatches) -> anyhow::Result<()> {
+		println!(
+			"Platform: {}-{}, {}-endian, {}-bit",
+			std::env::consts::ARCH,
+			std::env::consts::OS,
+			if cfg!(target_endian = "little") { "little" } else { "big" },
+			usize::BITS
+		);
+
+		let checks: [(&str, Check); 2] = [
+			("format invariants", || Ok(vach::format::roundtrip_check()?)),
+			("archive round trip", round_trip),
+		];
+
+		let mut failed = 0;
+		for (name, check) in checks {
+			match check() {
+				Ok(()) => println!("OK     {}", name),
+				Err(err) => {
+					println!("FAILED {}: {}", name, err);
+					failed += 1;
+				},
+			}
+		}
+
+		if failed > 0 {
+			fail!(Verification, "{} of {} self-tests failed on this platform", failed, checks.len());
+		}
+
+		Ok(())
+	}
+}
+
+/// Packs every kind of leaf into memory, then checks each decodes back to it's original data and authenticates
+fn round_trip() -> Result<(), InternalError> {
+	let keypair = crypto_utils::gen_keypair();
+	let data = (0..64 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+	let algorithms = [
+		CompressionAlgorithm::LZ4,
+		CompressionAlgorithm::Snappy,
+		CompressionAlgorithm::Brotli(BrotliParams::default()),
+		CompressionAlgorithm::Deflate(6),
+	];
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(data.as_slice()).id("plain"))?;
+	builder.add_leaf(Leaf::new(&[] as &[u8]).id("empty"))?;
+	builder.add_leaf(Leaf::new(data.as_slice()).id("sealed").encrypt(true).sign(true))?;
+
+	for (idx, algorithm) in algorithms.into_iter().enumerate() {
+		let leaf = Leaf::new(data.as_slice())
+			.id(format!("compressed/{}", idx))
+			.compress(CompressMode::Always)
+			.compression_algo(algorithm);
+
+		builder.add_leaf(leaf)?;
+	}
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default().keypair(keypair.clone()))?;
+
+	let config = ArchiveConfig::default().key(keypair.verifying_key());
+	let archive = Archive::with_config(target, &config)?;
+
+	for (id, _) in archive.entries_vec_sorted() {
+		let resource = archive.fetch(id)?;
+		let expected = if id.as_ref() == "empty" { &[] } else { data.as_slice() };
+
+		if resource.data.as_ref() != expected {
+			let message = format!("{} did not decode to it's original data", id);
+			return Err(InternalError::OtherError(message.into()));
+		}
+
+		if id.as_ref() == "sealed" && !resource.authenticated {
+			return Err(InternalError::UnauthenticatedError(id.clone()));
+		}
+	}
+
+	let report = archive.verify_stream(|_, _| {});
+	match report.failures.into_iter().next() {
+		Some(err) => Err(err),
+		None => Ok(()),
+	}
+}
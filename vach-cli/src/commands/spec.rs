@@ -0,0 +1,70 @@
+use std::fs::File;
+
+use vach::lock::LockMode;
+use vach::prelude::{Archive, ArchiveConfig, FormatDescriptor};
+
+use super::CommandTrait;
+use crate::{keys::key_names, utils};
+
+pub const VERSION: &str = "0.1.0";
+
+/// This command describes the structural parameters of the format, as written into an archive or as supported by this build
+pub struct Evaluator;
+
+impl CommandTrait for Evaluator {
+	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
+		match args.subcommand() {
+			Some(("dump", args)) => dump(args),
+			_ => fail!(Usage, "Please provide a spec subcommand: dump"),
+		}
+	}
+}
+
+fn dump(args: &clap::ArgMatches) -> anyhow::Result<()> {
+	let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
+		Some(magic) => magic.as_bytes().try_into()?,
+		None => *vach::DEFAULT_MAGIC,
+	};
+
+	// Without an archive, describe the format as written by this build
+	let descriptor = match args.value_of(key_names::INPUT) {
+		Some(path) => {
+			let _lock = utils::lock_archive(args.is_present(key_names::LOCK), path, LockMode::Shared)?;
+			Archive::with_config(File::open(path)?, &ArchiveConfig::new(magic, None))?.format_descriptor()?
+		},
+		None => FormatDescriptor::current(),
+	};
+
+	match args.value_of(key_names::FORMAT) {
+		Some("json") => {
+			let table = |table: &[(String, u32)]| {
+				table
+					.iter()
+					.map(|(name, bit)| serde_json::json!({ "name": name, "bit": bit }))
+					.collect::<Vec<_>>()
+			};
+
+			let extensions = descriptor
+				.extensions
+				.iter()
+				.map(|(name, tag)| serde_json::json!({ "name": name, "tag": tag }))
+				.collect::<Vec<_>>();
+
+			let json = serde_json::json!({
+				"spec_version": descriptor.spec_version,
+				"min_loader_version": descriptor.min_loader_version,
+				"writer": descriptor.writer,
+				"registry_entry_size": descriptor.registry_entry_size,
+				"flags": table(&descriptor.flags),
+				"capabilities": table(&descriptor.capabilities),
+				"extensions": extensions,
+				"codecs": table(&descriptor.codecs),
+			});
+
+			println!("{}", serde_json::to_string_pretty(&json)?);
+		},
+		_ => print!("{}", descriptor),
+	}
+
+	Ok(())
+}
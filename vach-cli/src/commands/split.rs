@@ -14,7 +14,7 @@ impl CommandTrait for Evaluator {
 		let mut input_path = match args.value_of(key_names::INPUT) {
 			Some(path) => path.to_string(),
 			None => {
-				anyhow::bail!("Please provide a some input to a keypair files using the -i or --input key!")
+				fail!(Usage, "Please provide a some input to a keypair files using the -i or --input key!")
 			},
 		};
 
@@ -1,27 +1,30 @@
 use std::fs::{self, File};
 use std::str::FromStr;
-use std::io::{BufReader, Read, Seek, Write};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::thread;
 use std::time::Instant;
 
-use vach::prelude::{ArchiveConfig, Archive, InternalError};
+use vach::prelude::{ArchiveConfig, Archive, Flags, InternalError, RegistryEntry, Resource};
 use vach::crypto_utils;
-use indicatif::{ProgressBar, ProgressStyle};
+use vach::lock::LockMode;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 
 use super::CommandTrait;
-use crate::keys::key_names;
+use crate::{keys::key_names, utils};
+use crate::utils::stats::LiveStats;
 
-pub const VERSION: &str = "0.1.1";
+pub const VERSION: &str = "0.2.0";
 
-/// This command extracts an archive into the specified output folder
+/// This command extracts one or more archives into the specified output folder
 pub struct Evaluator;
 
 impl CommandTrait for Evaluator {
 	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
-		let input_path = match args.value_of(key_names::INPUT) {
-			Some(path) => path,
-			None => anyhow::bail!("Please provide an input path using the -i or --input key"),
+		let inputs = match args.values_of(key_names::INPUT) {
+			Some(inputs) => utils::collect_archives(inputs)?,
+			None => fail!(Usage, "Please provide an input path using the -i or --input key"),
 		};
 
 		let output_path = match args.value_of(key_names::OUTPUT) {
@@ -30,7 +33,7 @@ impl CommandTrait for Evaluator {
 		};
 
 		if output_path.is_file() {
-			anyhow::bail!("Please provide a directory|folder path as the value of -o | --output")
+			fail!(Usage, "Please provide a directory|folder path as the value of -o | --output")
 		};
 
 		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
@@ -43,7 +46,7 @@ impl CommandTrait for Evaluator {
 			Some(path) => {
 				let file = match File::open(path) {
 					Ok(it) => it,
-					Err(err) => anyhow::bail!("IOError: {} @ {}", err, path),
+					Err(err) => fail!(Io, "IOError: {} @ {}", err, path),
 				};
 
 				Some(crypto_utils::read_keypair(file)?.verifying_key())
@@ -60,59 +63,137 @@ impl CommandTrait for Evaluator {
 		// Whether to truncate the original archive after extraction
 		let truncate = args.is_present(key_names::TRUNCATE);
 
-		let input_file = match File::open(input_path) {
-			Ok(it) => BufReader::new(it),
-			Err(err) => anyhow::bail!("IOError: {} @ {}", err, input_path),
-		};
-
 		// Generate ArchiveConfig using given magic and public key
 		let header_config = ArchiveConfig::new(magic, public_key);
 
-		// Parse then extract archive
-		let archive = match Archive::with_config(input_file, &header_config) {
-			Ok(archive) => archive,
-			Err(err) => match err {
-				InternalError::NoKeypairError => anyhow::bail!(
-					"Please provide a public key or a keypair for use in decryption or signature verification"
-				),
-				InternalError::MalformedArchiveSource(_) => anyhow::bail!("Unable to validate the archive: {}", err),
-				err => anyhow::bail!("Encountered an error: {}", err.to_string()),
-			},
-		};
-
 		let mut num_threads = args
 			.value_of(key_names::JOBS)
-			.map(|v| v.parse::<usize>().ok())
-			.flatten()
+			.and_then(|v| v.parse::<usize>().ok())
 			.unwrap_or(num_cpus::get());
 
 		if num_threads == 0 {
 			num_threads = num_cpus::get()
 		}
 
-		extract_archive(&archive, num_threads, output_path)?;
+		let stats = args.is_present(key_names::STATS);
+		let query = utils::parse_query(args)?;
 
-		// Delete original archive
-		if truncate {
-			println!("Truncating original archive @ {}", &input_path);
-			std::fs::remove_file(input_path)?;
-		};
+		let verify = args.is_present(key_names::VERIFY);
+		if verify && public_key.is_none() {
+			fail!(Usage, "Please provide a public key or a keypair using -p or -k to verify signatures with");
+		}
+
+		for input_path in &inputs {
+			let _lock = utils::lock_archive(args.is_present(key_names::LOCK), input_path, LockMode::Shared)?;
+			let input_file = match File::open(input_path) {
+				Ok(it) => it,
+				Err(err) => fail!(Io, "IOError: {} @ {}", err, input_path.to_string_lossy()),
+			};
+
+			// Parse then extract archive
+			let archive = match Archive::with_config(input_file, &header_config) {
+				Ok(archive) => archive,
+				Err(err) => match err {
+					InternalError::NoKeypairError => fail!(
+						BadKey,
+						"Please provide a public key or a keypair for use in decryption or signature verification"
+					),
+					InternalError::MalformedArchiveSource(_) => fail!(Verification, "Unable to validate the archive: {}", err),
+					err => return Err(anyhow::Error::new(err).context("Unable to open the archive")),
+				},
+			};
+
+			// Several archives are each extracted into their own folder
+			let target_folder = match (inputs.len(), input_path.file_stem()) {
+				(1, _) | (_, None) => output_path.clone(),
+				(_, Some(stem)) => output_path.join(stem),
+			};
+
+			let tagged = utils::tagged(args, &archive)?;
+			let filter = |entry: &RegistryEntry| {
+				query.as_ref().is_none_or(|q| q.matches(entry)) && tagged.as_ref().is_none_or(|t| t.contains(&entry.id))
+			};
+
+			let summary = extract_archive(&archive, num_threads, target_folder, stats, verify, &filter)?;
+
+			if verify {
+				summary.report(input_path)?;
+			}
+
+			// Delete original archive
+			if truncate {
+				println!("Truncating original archive @ {}", input_path.to_string_lossy());
+				std::fs::remove_file(input_path)?;
+			};
+		}
 
 		Ok(())
 	}
 }
 
-fn extract_archive<T: Read + Seek + Send + Sync>(
-	archive: &Archive<T>, jobs: usize, target_folder: PathBuf,
-) -> anyhow::Result<()> {
+/// The outcome of checking signatures during extraction
+#[derive(Default)]
+struct Summary {
+	authenticated: usize,
+	unsigned: usize,
+	unauthenticated: Vec<String>,
+}
+
+impl Summary {
+	fn report(mut self, input_path: &Path) -> anyhow::Result<()> {
+		println!(
+			"Verified {}: {} authenticated, {} unsigned, {} failed",
+			input_path.to_string_lossy(),
+			self.authenticated,
+			self.unsigned,
+			self.unauthenticated.len()
+		);
+
+		if self.unauthenticated.is_empty() {
+			return Ok(());
+		}
+
+		self.unauthenticated.sort();
+		for id in &self.unauthenticated {
+			println!("  UNAUTHENTICATED {}", id);
+		}
+
+		fail!(
+			Verification,
+			"{} entries of {} failed signature verification and were not extracted",
+			self.unauthenticated.len(),
+			input_path.to_string_lossy()
+		)
+	}
+}
+
+/// Entries carrying any of these flags must be decoded, the rest hold their data as is
+const DECODED_FLAGS: u32 = Flags::COMPRESSED_FLAG | Flags::ENCRYPTED_FLAG | Flags::PIPELINE_FLAG;
+
+/// Reads without locking the archive where supported, so extraction threads don't queue on the handle
+#[cfg(any(unix, windows))]
+fn fetch(archive: &Archive<File>, id: &str) -> Result<Resource, InternalError> {
+	archive.fetch_positional(id)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn fetch(archive: &Archive<File>, id: &str) -> Result<Resource, InternalError> {
+	archive.fetch(id)
+}
+
+fn extract_archive(
+	archive: &Archive<File>, jobs: usize, target_folder: PathBuf, stats: bool, verify: bool, filter: &dyn Fn(&RegistryEntry) -> bool,
+) -> anyhow::Result<Summary> {
 	// For measuring the time difference
 	let time = Instant::now();
 	fs::create_dir_all(&target_folder)?;
 
-	let total_size = archive
-		.entries()
+	// Only entries matching the `--where` and `--tag` filters are extracted
+	let entries = archive.entries().values().filter(|entry| filter(entry)).collect::<Vec<_>>();
+
+	let total_size = entries
 		.iter()
-		.map(|(_, entry)| entry.offset)
+		.map(|entry| entry.offset)
 		.reduce(|a, b| a + b)
 		.unwrap_or(0);
 
@@ -126,53 +207,106 @@ fn extract_archive<T: Read + Seek + Send + Sync>(
 			.tick_chars("⢀ ⡀ ⠄ ⢂ ⡂ ⠅ ⢃ ⡃ ⠍ ⢋ ⡋ ⠍⠁⢋⠁⡋⠁⠍⠉⠋⠉⠋⠉⠉⠙⠉⠙⠉⠩⠈⢙⠈⡙⢈⠩⡀⢙⠄⡙⢂⠩⡂⢘⠅⡘⢃⠨⡃⢐⠍⡐⢋⠠⡋⢀⠍⡁⢋⠁⡋⠁⠍⠉⠋⠉⠋⠉⠉⠙⠉⠙⠉⠩⠈⢙⠈⡙⠈⠩ ⢙ ⡙ ⠩ ⢘ ⡘ ⠨ ⢐ ⡐ ⠠ ⢀ ⡀"),
 	);
 
+	// Live statistics are displayed in the progress area, under the progress bar
+	let multi = MultiProgress::new();
+	let stats = stats.then(|| {
+		multi.add(pbar.clone());
+		LiveStats::new(jobs, multi.add(ProgressBar::new(0)))
+	});
+
 	// Extract all entries in parallel
-	let entries = archive.entries().iter().map(|(_, entry)| entry).collect::<Vec<_>>();
-	let chunk_size = (archive.entries().len() / jobs).max(archive.entries().len());
+	let chunk_size = entries.len().div_ceil(jobs).max(1);
+	let summary = Mutex::new(Summary::default());
 
 	thread::scope(|s| -> anyhow::Result<()> {
+		let mut handles = Vec::new();
+
 		for chunk in entries.chunks(chunk_size) {
 			let pbar = pbar.clone();
 			let target_folder = target_folder.clone();
+			let stats = stats.as_ref();
+			let summary = &summary;
 
-			s.spawn(move || -> anyhow::Result<()> {
+			handles.push(s.spawn(move || -> anyhow::Result<()> {
 				for entry in chunk {
 					let id = entry.id.as_ref();
 
 					// Set's the Progress Bar message
 					pbar.set_message(id.to_string());
 
-					// Process filesystem
+					let start = Instant::now();
+
 					let mut save_path = target_folder.clone();
 					save_path.push(id);
 
-					if let Some(parent_dir) = save_path.ancestors().nth(1) {
-						fs::create_dir_all(parent_dir)?;
-					};
+					let parent_dir = save_path.ancestors().nth(1).map(Path::to_path_buf);
+
+					// Leaves stored as is are copied straight from the archive, within the kernel where supported
+					if !verify && !entry.flags.contains(DECODED_FLAGS) {
+						parent_dir.map(fs::create_dir_all).transpose()?;
+						archive.extract_entry_to_file(id, &save_path)?;
+
+						if let Some(stats) = stats {
+							stats.record(id, &entry.flags, entry.offset, entry.offset, start.elapsed());
+						}
+
+						pbar.inc(entry.offset);
+						continue;
+					}
+
+					let resource = fetch(archive, id)?;
+
+					// Entries that fail verification are never written
+					if verify {
+						let mut summary = summary.lock().unwrap();
+
+						if !entry.flags.contains(Flags::SIGNED_FLAG) {
+							summary.unsigned += 1;
+						} else if resource.authenticated {
+							summary.authenticated += 1;
+						} else {
+							summary.unauthenticated.push(id.to_string());
+							pbar.inc(entry.offset);
+							continue;
+						}
+					}
+
+					// Process filesystem
+					parent_dir.map(fs::create_dir_all).transpose()?;
 
 					// Write to file and update process queue
 					let mut file = File::create(save_path)?;
-					let resource = archive.fetch(id)?;
 					file.write_all(&resource.data)?;
 
+					if let Some(stats) = stats {
+						let raw = resource.data.len() as u64;
+						stats.record(id, &entry.flags, raw, entry.offset, start.elapsed());
+					}
+
 					// Increment Progress Bar
 					pbar.inc(entry.offset);
 				}
 
 				Ok(())
-			});
+			}));
 		}
 
-		Ok(())
+		// Surface the first failure, so a partial extraction doesn't exit successfully
+		handles.into_iter().try_for_each(|handle| handle.join().unwrap())
 	})?;
 
 	// Finished extracting
 	pbar.finish();
+	if let Some(stats) = &stats {
+		stats.finish();
+	}
+
+	let summary = summary.into_inner().unwrap();
 	println!(
 		"Extracted {} files in {}s",
-		archive.entries().len(),
+		entries.len() - summary.unauthenticated.len(),
 		time.elapsed().as_secs_f64()
 	);
 
-	Ok(())
+	Ok(summary)
 }
@@ -1,20 +1,66 @@
 use std::fs::File;
+use std::path::Path;
 
-use vach::archive::{Archive, ArchiveConfig};
+use vach::{
+	archive::{Archive, ArchiveConfig},
+	crypto_utils,
+	lock::LockMode,
+};
 
 use super::CommandTrait;
-use crate::keys::key_names;
+use crate::{
+	error::{ErrorClass, Failure},
+	keys::key_names,
+	utils,
+};
 
-pub const VERSION: &str = "0.0.1";
+pub const VERSION: &str = "0.2.0";
 
-/// This command verifies the validity and integrity of an archive
+/// This command verifies the validity and integrity of one or more archives
 pub struct Evaluator;
 
+/// What verifying a single archive found
+struct Verified {
+	entries: usize,
+	// Only set with `--deep`
+	deep: Option<Deep>,
+}
+
+/// The outcome of decoding every entry of an archive
+#[derive(Default)]
+struct Deep {
+	ok: usize,
+	authenticated: usize,
+	unauthenticated: usize,
+	undecodable: usize,
+	failures: Vec<String>,
+}
+
+impl Deep {
+	fn add(&mut self, other: &Deep) {
+		self.ok += other.ok;
+		self.authenticated += other.authenticated;
+		self.unauthenticated += other.unauthenticated;
+		self.undecodable += other.undecodable;
+	}
+
+	fn summary(&self) -> String {
+		format!(
+			"{} entries OK ({} authenticated, {} unauthenticated), {} failed ({} undecodable)",
+			self.ok,
+			self.authenticated,
+			self.unauthenticated,
+			self.failures.len(),
+			self.undecodable
+		)
+	}
+}
+
 impl CommandTrait for Evaluator {
 	fn evaluate(&self, args: &clap::ArgMatches) -> anyhow::Result<()> {
-		let input_path = match args.value_of(key_names::INPUT) {
-			Some(path) => path,
-			None => anyhow::bail!("Please provide an input path using the -i or --input key"),
+		let inputs = match args.values_of(key_names::INPUT) {
+			Some(inputs) => utils::collect_archives(inputs)?,
+			None => fail!(Usage, "Please provide an input path using the -i or --input key"),
 		};
 
 		let magic: [u8; vach::MAGIC_LENGTH] = match args.value_of(key_names::MAGIC) {
@@ -22,12 +68,124 @@ impl CommandTrait for Evaluator {
 			None => *vach::DEFAULT_MAGIC,
 		};
 
-		let input_file = File::open(input_path)?;
+		// Signatures are only checked with a public key, from a -p or -k input
+		let public_key = match args.value_of(key_names::KEYPAIR) {
+			Some(path) => Some(crypto_utils::read_keypair(File::open(path)?)?.verifying_key()),
+			None => match args.value_of(key_names::PUBLIC_KEY) {
+				Some(path) => Some(crypto_utils::read_public_key(File::open(path)?)?),
+				None => None,
+			},
+		};
+
+		let jobs = match args.value_of(key_names::JOBS) {
+			Some(jobs) => match jobs.parse::<usize>() {
+				Ok(0) | Err(_) => fail!(Usage, "{} is not a valid number of jobs", jobs),
+				Ok(jobs) => jobs,
+			},
+			None => num_cpus::get(),
+		};
+
+		let deep = args.is_present(key_names::DEEP).then_some(jobs);
+		let json = args.value_of(key_names::FORMAT) == Some("json");
+		let strict = args.is_present(key_names::STRICT_SIGNATURES);
+		let lock = args.is_present(key_names::LOCK);
+		let config = ArchiveConfig::new(magic, public_key).legacy_signatures(!strict);
 
-		if let Err(err) = Archive::with_config(input_file, &ArchiveConfig::new(magic, None)) {
-			anyhow::bail!("Unable to verify the archive source, error: {}", err.to_string())
+		let results = inputs
+			.iter()
+			.map(|path| (path, verify(path, &config, deep, lock)))
+			.collect::<Vec<_>>();
+
+		let failed = results
+			.iter()
+			.filter(|(_, res)| match res {
+				Ok(verified) => verified.deep.as_ref().is_some_and(|deep| !deep.failures.is_empty()),
+				Err(_) => true,
+			})
+			.count();
+
+		if json {
+			let report = results
+				.iter()
+				.map(|(path, res)| match res {
+					Ok(Verified { entries, deep }) => serde_json::json!({
+						"path": path,
+						"valid": deep.as_ref().is_none_or(|deep| deep.failures.is_empty()),
+						"entries": entries,
+						"deep": deep.as_ref().map(|deep| serde_json::json!({
+							"ok": deep.ok,
+							"failed": deep.failures.len(),
+							"authenticated": deep.authenticated,
+							"unauthenticated": deep.unauthenticated,
+							"undecodable": deep.undecodable,
+							"failures": deep.failures,
+						})),
+						"error": null
+					}),
+					Err(err) => serde_json::json!({ "path": path, "valid": false, "entries": null, "deep": null, "error": err.to_string() }),
+				})
+				.collect::<Vec<_>>();
+
+			println!("{}", serde_json::to_string_pretty(&report)?);
+		} else if results.len() > 1 || deep.is_some() {
+			let mut total = Deep::default();
+
+			for (path, res) in &results {
+				match res {
+					Ok(Verified { deep: Some(deep), .. }) => {
+						let status = if deep.failures.is_empty() { "OK    " } else { "FAILED" };
+						println!("{} {}: {}", status, path.to_string_lossy(), deep.summary());
+
+						deep.failures.iter().for_each(|failure| println!("    {}", failure));
+						total.add(deep);
+						total.failures.extend(deep.failures.iter().cloned());
+					},
+					Ok(Verified { entries, deep: None }) => println!("OK     {} ({} entries)", path.to_string_lossy(), entries),
+					Err(err) => println!("FAILED {}: {}", path.to_string_lossy(), err),
+				}
+			}
+
+			if deep.is_some() && results.len() > 1 {
+				println!("Total: {}", total.summary());
+			}
 		};
 
-		Ok(())
+		match results.as_slice() {
+			[(_, Err(err))] => Err(Failure {
+				class: ErrorClass::of(err),
+				message: format!("Unable to verify the archive source, error: {}", err),
+			}
+			.into()),
+			_ if failed > 0 => fail!(Verification, "{} of {} archives failed verification", failed, results.len()),
+			_ => Ok(()),
+		}
 	}
 }
+
+fn verify(path: &Path, config: &ArchiveConfig, deep: Option<usize>, lock: bool) -> anyhow::Result<Verified> {
+	let _lock = utils::lock_archive(lock, path, LockMode::Shared)?;
+	let input_file = File::open(path)?;
+	let archive = Archive::with_config(input_file, config)?;
+
+	// Expired keys don't make an archive invalid, but compliance policies usually want to know
+	if archive.key_expired() {
+		eprintln!("Warning: the key of {} has expired", path.to_string_lossy());
+	}
+
+	let deep = deep.map(|jobs| {
+		let report = archive.verify_parallel(jobs, |_, _| ());
+
+		Deep {
+			ok: report.authenticated + report.unauthenticated,
+			authenticated: report.authenticated,
+			unauthenticated: report.unauthenticated,
+			undecodable: report.undecodable(),
+			failures: report.failures.iter().map(|err| err.to_string()).collect(),
+		}
+	});
+
+	Ok(Verified {
+		entries: archive.entries().len(),
+		deep,
+	})
+}
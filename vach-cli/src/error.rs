@@ -0,0 +1,128 @@
+use std::{fmt, io};
+
+use vach::prelude::InternalError;
+
+/// Classes of failure, each exits with a stable code so scripts can branch on them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+	/// Anything not covered below
+	Other = 1,
+	/// Missing or invalid arguments, same as `clap`'s own usage errors
+	Usage = 2,
+	/// Reading or writing a file failed
+	Io = 3,
+	/// A key is missing, malformed or doesn't match the archive
+	BadKey = 4,
+	/// An archive is malformed, incompatible or failed signature checks
+	Verification = 5,
+	/// A requested resource isn't in the archive
+	MissingResource = 6,
+	/// An archive or entry exceeds its size budget
+	Budget = 7,
+	/// `lint --strict` found warnings
+	Lint = 8,
+}
+
+impl ErrorClass {
+	pub fn code(self) -> i32 {
+		self as i32
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			ErrorClass::Other => "other",
+			ErrorClass::Usage => "usage",
+			ErrorClass::Io => "io",
+			ErrorClass::BadKey => "bad_key",
+			ErrorClass::Verification => "verification",
+			ErrorClass::MissingResource => "missing_resource",
+			ErrorClass::Budget => "budget",
+			ErrorClass::Lint => "lint",
+		}
+	}
+
+	/// Walks the error's causes, the first one with a known class decides
+	pub fn of(err: &anyhow::Error) -> ErrorClass {
+		for cause in err.chain() {
+			if let Some(failure) = cause.downcast_ref::<Failure>() {
+				return failure.class;
+			}
+
+			if let Some(err) = cause.downcast_ref::<InternalError>() {
+				return match err.root() {
+					// Sources that end early are truncated or not archives at all
+					InternalError::IOError(err) if err.kind() == io::ErrorKind::UnexpectedEof => ErrorClass::Verification,
+					InternalError::IOError(_) | InternalError::LockedError(_) => ErrorClass::Io,
+					InternalError::NoKeypairError
+					| InternalError::ParseError(_)
+					| InternalError::CryptoError(_)
+					| InternalError::ExpiredKeyError(_) => ErrorClass::BadKey,
+					InternalError::MalformedArchiveSource(_)
+					| InternalError::IncompatibleArchiveVersionError(_)
+					| InternalError::MissingCapabilityError(_)
+					| InternalError::UnauthenticatedError(_)
+					| InternalError::ReleaseMismatchError(_)
+					| InternalError::TimestampError(_)
+					| InternalError::FooterMismatchError(_)
+					| InternalError::DeCompressionError(_) => ErrorClass::Verification,
+					InternalError::InvalidConfigError(_) => ErrorClass::Usage,
+					InternalError::MissingResourceError(_) | InternalError::OutsideValidityError(_) => {
+						ErrorClass::MissingResource
+					},
+					InternalError::MemoryBudgetError(..) => ErrorClass::Budget,
+					_ => ErrorClass::Other,
+				};
+			}
+
+			if cause.is::<io::Error>() {
+				return ErrorClass::Io;
+			}
+		}
+
+		ErrorClass::Other
+	}
+}
+
+/// An error raised by the CLI itself, tagged with its class
+#[derive(Debug)]
+pub struct Failure {
+	pub class: ErrorClass,
+	pub message: String,
+}
+
+impl fmt::Display for Failure {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+impl std::error::Error for Failure {}
+
+/// Like `anyhow::bail!`, but tags the error with an [`ErrorClass`]: `fail!(Usage, "Please provide ...")`
+macro_rules! fail {
+	($class:ident, $($arg:tt)*) => {
+		return Err(anyhow::Error::new($crate::error::Failure {
+			class: $crate::error::ErrorClass::$class,
+			message: format!($($arg)*),
+		}))
+	};
+}
+
+/// Prints an error to stderr, either as text or as a JSON object
+pub fn report(class: ErrorClass, message: &str, causes: &[String], json: bool) {
+	if json {
+		let report = serde_json::json!({
+			"error": {
+				"class": class.name(),
+				"code": class.code(),
+				"message": message,
+				"causes": causes,
+			}
+		});
+
+		eprintln!("{}", report);
+	} else {
+		eprintln!("Error: {}", message);
+		causes.iter().for_each(|cause| eprintln!("  Caused by: {}", cause));
+	}
+}
@@ -12,6 +12,11 @@ pub mod key_names {
 	pub(crate) const DIR_INPUT_REC: &str = "DIR_INPUT_REC";
 
 	pub(crate) const EXCLUDE: &str = "EXCLUDE";
+	pub(crate) const FOLLOW_SYMLINKS: &str = "FOLLOW_SYMLINKS";
+	pub(crate) const NO_FOLLOW: &str = "NO_FOLLOW";
+	pub(crate) const HIDDEN: &str = "HIDDEN";
+	pub(crate) const FILE_TYPE: &str = "FILE_TYPE";
+	pub(crate) const EXTENSIONS: &str = "EXTENSIONS";
 	pub(crate) const TRUNCATE: &str = "TRUNCATE";
 
 	pub(crate) const FLAGS: &str = "FLAGS";
@@ -28,6 +33,41 @@ pub mod key_names {
 	pub(crate) const KEYPAIR: &str = "KEYPAIR";
 
 	pub(crate) const SORT: &str = "SORT";
+	pub(crate) const STATS: &str = "STATS";
+	pub(crate) const FORMAT: &str = "FORMAT";
+
+	pub(crate) const FROM: &str = "FROM";
+	pub(crate) const TO: &str = "TO";
+	pub(crate) const PREFIX_REWRITE: &str = "PREFIX_REWRITE";
+
+	pub(crate) const TAR: &str = "TAR";
+	pub(crate) const INCLUDE: &str = "INCLUDE";
+
+	pub(crate) const ARCHIVE: &str = "ARCHIVE";
+	pub(crate) const OLD_KEY: &str = "OLD_KEY";
+	pub(crate) const SIGN_WITH: &str = "SIGN_WITH";
+	pub(crate) const ERROR_FORMAT: &str = "ERROR_FORMAT";
+	pub(crate) const METRICS: &str = "METRICS";
+	pub(crate) const LOCK: &str = "LOCK";
+	pub(crate) const FROM_MANIFEST: &str = "FROM_MANIFEST";
+	pub(crate) const MAX_SIZE: &str = "MAX_SIZE";
+	pub(crate) const MAX_ENTRY_SIZE: &str = "MAX_ENTRY_SIZE";
+	pub(crate) const VERIFY: &str = "VERIFY";
+	pub(crate) const STRICT: &str = "STRICT";
+	pub(crate) const LAYOUT: &str = "LAYOUT";
+	pub(crate) const PREFIX_IDS: &str = "PREFIX_IDS";
+	pub(crate) const ID_INDEX: &str = "ID_INDEX";
+	pub(crate) const FORMAT_DESCRIPTOR: &str = "FORMAT_DESCRIPTOR";
+	pub(crate) const FOOTER: &str = "FOOTER";
+	pub(crate) const COMPRESS_REGISTRY: &str = "COMPRESS_REGISTRY";
+	pub(crate) const NORMALIZE_NEWLINES: &str = "NORMALIZE_NEWLINES";
+	pub(crate) const WHERE: &str = "WHERE";
+	pub(crate) const LAYOUT_PROFILE: &str = "LAYOUT_PROFILE";
+	pub(crate) const RELEASE: &str = "RELEASE";
+	pub(crate) const TAG: &str = "TAG";
+	pub(crate) const DATA_OFFSET: &str = "DATA_OFFSET";
+	pub(crate) const DEEP: &str = "DEEP";
+	pub(crate) const STRICT_SIGNATURES: &str = "STRICT_SIGNATURES";
 }
 
 pub fn build_keys<'a>() -> HashMap<&'static str, Arg<'a>> {
@@ -126,6 +166,67 @@ pub fn build_keys<'a>() -> HashMap<&'static str, Arg<'a>> {
 			.multiple_values(true),
 	);
 
+	// Follow symbolic links when walking directories
+	map.insert(
+		key_names::FOLLOW_SYMLINKS,
+		Arg::new(key_names::FOLLOW_SYMLINKS)
+			.long("follow-symlinks")
+			.value_name(key_names::FOLLOW_SYMLINKS)
+			.help("Follow symbolic links when walking directories")
+			.required(false)
+			.takes_value(false)
+			.conflicts_with(key_names::NO_FOLLOW),
+	);
+
+	// Don't follow symbolic links when walking directories, the default
+	map.insert(
+		key_names::NO_FOLLOW,
+		Arg::new(key_names::NO_FOLLOW)
+			.long("no-follow")
+			.value_name(key_names::NO_FOLLOW)
+			.help("Do not follow symbolic links when walking directories, this is the default")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Include hidden files and directories when walking directories
+	map.insert(
+		key_names::HIDDEN,
+		Arg::new(key_names::HIDDEN)
+			.long("hidden")
+			.value_name(key_names::HIDDEN)
+			.help("Include hidden files and directories (starting with a '.') when walking directories")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Only include entries of the given type when walking directories
+	map.insert(
+		key_names::FILE_TYPE,
+		Arg::new(key_names::FILE_TYPE)
+			.long("type")
+			.value_name(key_names::FILE_TYPE)
+			.help("Only include entries of the given types when walking directories: 'f' (regular files) or 'l' (symbolic links to files, requires --follow-symlinks)")
+			.required(false)
+			.takes_value(true)
+			.multiple_values(true)
+			.use_value_delimiter(true)
+			.possible_values(["f", "l"]),
+	);
+
+	// Only include files with the given extensions when walking directories
+	map.insert(
+		key_names::EXTENSIONS,
+		Arg::new(key_names::EXTENSIONS)
+			.long("ext")
+			.value_name(key_names::EXTENSIONS)
+			.help("Only include files with the given extensions when walking directories, eg: --ext png,ogg")
+			.required(false)
+			.takes_value(true)
+			.multiple_values(true)
+			.use_value_delimiter(true),
+	);
+
 	// Deletes the original files after they have been processed successfully
 	map.insert(
 		key_names::TRUNCATE,
@@ -170,14 +271,14 @@ pub fn build_keys<'a>() -> HashMap<&'static str, Arg<'a>> {
 			.long("compress-mode")
 			.short('c')
 			.value_name(key_names::COMPRESS_MODE)
-			.help("The compress mode of the adjacent leafs, Can be 'Always', 'Detect' or 'Never' (case insensitive). Defaults to 'Detect'")
+			.help("The compress mode of the adjacent leafs, Can be 'Always', 'Detect', 'Never' or 'Store' (case insensitive). 'Store' also skips encryption and signing. Defaults to 'Detect'")
 			.required(false)
 			.takes_value(true)
 			.number_of_values(1)
 			.validator(|c_mode| {
 				let c_mode = c_mode.to_ascii_lowercase();
-				if c_mode != "always" && c_mode != "never" && c_mode != "detect" {
-					return Err(format!("Please provide a valid Compress Mode, either 'Always', 'Detect' or 'Never' (case insensitive). Not: {}", c_mode));
+				if !["always", "never", "detect", "store"].contains(&c_mode.as_str()) {
+					return Err(format!("Please provide a valid Compress Mode, either 'Always', 'Detect', 'Never' or 'Store' (case insensitive). Not: {}", c_mode));
 				};
 
 				Ok(())
@@ -191,14 +292,14 @@ pub fn build_keys<'a>() -> HashMap<&'static str, Arg<'a>> {
 			.long("compress-algo")
 			.short('g')
 			.value_name(key_names::COMPRESS_ALGO)
-			.help("The compression algorithm to use in compression, can be; 'lz4', 'brotli' or 'snappy'")
+			.help("The compression algorithm to use in compression, can be; 'lz4', 'brotli', 'snappy' or 'deflate'")
 			.required(false)
 			.takes_value(true)
 			.number_of_values(1)
 			.validator(|c_mode| {
 				let c_mode = c_mode.to_ascii_lowercase();
-				if c_mode != "lz4" && c_mode != "brotli" && c_mode != "snappy" {
-					return Err(format!("Please provide a valid Compression Algorithm to use, either 'lz4', 'brotli' or 'snappy' (case insensitive). Not: {}", c_mode));
+				if !matches!(c_mode.as_str(), "lz4" | "brotli" | "snappy" | "deflate") {
+					return Err(format!("Please provide a valid Compression Algorithm to use, either 'lz4', 'brotli', 'snappy' or 'deflate' (case insensitive). Not: {}", c_mode));
 				};
 
 				Ok(())
@@ -286,7 +387,7 @@ pub fn build_keys<'a>() -> HashMap<&'static str, Arg<'a>> {
 			.long("flags")
 			.short('f')
 			.value_name(key_names::FLAGS)
-			.help("The flags that go into the .vach file header section")
+			.help("The flags that go into the .vach file header section, as a number or names joined by '|', eg: 0x8000|0b1")
 			.required(false)
 			.takes_value(true)
 			.number_of_values(1),
@@ -317,5 +418,378 @@ pub fn build_keys<'a>() -> HashMap<&'static str, Arg<'a>> {
 			.number_of_values(1),
 	);
 
+	// The format of the output
+	map.insert(
+		key_names::FORMAT,
+		Arg::new(key_names::FORMAT)
+			.long("format")
+			.value_name(key_names::FORMAT)
+			.help("The format of the output, either 'text' or 'json'. Defaults to 'text'")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1)
+			.possible_values(["text", "json"]),
+	);
+
+	// The ID of the entry to be moved
+	map.insert(
+		key_names::FROM,
+		Arg::new(key_names::FROM)
+			.value_name(key_names::FROM)
+			.help("The ID of the entry to move")
+			.required(false)
+			.index(1),
+	);
+
+	// The new ID of the entry being moved
+	map.insert(
+		key_names::TO,
+		Arg::new(key_names::TO)
+			.value_name(key_names::TO)
+			.help("The new ID of the entry")
+			.required(false)
+			.index(2)
+			.requires(key_names::FROM),
+	);
+
+	// Rewrite the prefix of all matching IDs
+	map.insert(
+		key_names::PREFIX_REWRITE,
+		Arg::new(key_names::PREFIX_REWRITE)
+			.long("prefix-rewrite")
+			.value_name(key_names::PREFIX_REWRITE)
+			.help("Replace the prefix of all matching IDs, in the form: old/=new/")
+			.required(false)
+			.takes_value(true)
+			.multiple_occurrences(true)
+			.number_of_values(1),
+	);
+
+	// The archive a key is rotated on
+	map.insert(
+		key_names::ARCHIVE,
+		Arg::new(key_names::ARCHIVE)
+			.long("archive")
+			.value_name(key_names::ARCHIVE)
+			.help("The archive to operate on")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	// The key an archive is currently signed with
+	map.insert(
+		key_names::OLD_KEY,
+		Arg::new(key_names::OLD_KEY)
+			.long("old-key")
+			.value_name(key_names::OLD_KEY)
+			.help("The public key or keypair an archive is currently signed with")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	// Build an archive strictly from a manifest
+	map.insert(
+		key_names::FROM_MANIFEST,
+		Arg::new(key_names::FROM_MANIFEST)
+			.long("from-manifest")
+			.value_name(key_names::FROM_MANIFEST)
+			.help("Pack exactly the entries of a manifest written by gen-manifest, checking each source against its recorded SHA-256")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1)
+			.conflicts_with_all(&[key_names::INPUT, key_names::DIR_INPUT, key_names::DIR_INPUT_REC, key_names::TRUNCATE]),
+	);
+
+	// Size budgets, checked after packing
+	map.insert(
+		key_names::MAX_SIZE,
+		Arg::new(key_names::MAX_SIZE)
+			.long("max-size")
+			.value_name(key_names::MAX_SIZE)
+			.help("Fail if the archive is larger than this size, eg: 1.8GiB")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	map.insert(
+		key_names::MAX_ENTRY_SIZE,
+		Arg::new(key_names::MAX_ENTRY_SIZE)
+			.long("max-entry-size")
+			.value_name(key_names::MAX_ENTRY_SIZE)
+			.help("Fail if any entry is larger than this size once processed, eg: 256MiB")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	// Check signatures while extracting
+	map.insert(
+		key_names::VERIFY,
+		Arg::new(key_names::VERIFY)
+			.long("verify")
+			.value_name(key_names::VERIFY)
+			.help("Check the signature of every entry while extracting, entries that fail are not written. Requires -p or -k")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Decode every entry while verifying
+	map.insert(
+		key_names::DEEP,
+		Arg::new(key_names::DEEP)
+			.long("deep")
+			.value_name(key_names::DEEP)
+			.help("Read and decode every entry, checking signatures when -p or -k is given. Otherwise only the header and registry are checked")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Refuse signatures that aren't bound to their archive
+	map.insert(
+		key_names::STRICT_SIGNATURES,
+		Arg::new(key_names::STRICT_SIGNATURES)
+			.long("strict-signatures")
+			.value_name(key_names::STRICT_SIGNATURES)
+			.help("Don't authenticate entries of archives signed without a build ID, whose signatures could be replayed from another archive")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Treat lint warnings as failures
+	map.insert(
+		key_names::STRICT,
+		Arg::new(key_names::STRICT)
+			.long("strict")
+			.value_name(key_names::STRICT)
+			.help("Exit with a non-zero code if any warnings are found")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Store IDs relative to each other, for deeply nested projects
+	map.insert(
+		key_names::PREFIX_IDS,
+		Arg::new(key_names::PREFIX_IDS)
+			.long("prefix-ids")
+			.value_name(key_names::PREFIX_IDS)
+			.help("Sort the registry and store each ID relative to the previous one, shrinking it when IDs share long prefixes")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// A perfect hash over all IDs, for loaders outside of Rust
+	map.insert(
+		key_names::ID_INDEX,
+		Arg::new(key_names::ID_INDEX)
+			.long("id-index")
+			.value_name(key_names::ID_INDEX)
+			.help("Store a perfect hash mapping every ID to it's registry position, so loaders in other languages can look entries up in constant time")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// A description of the format, for third-party readers
+	map.insert(
+		key_names::FORMAT_DESCRIPTOR,
+		Arg::new(key_names::FORMAT_DESCRIPTOR)
+			.long("format-descriptor")
+			.value_name(key_names::FORMAT_DESCRIPTOR)
+			.help("Store a description of the format's flags, header extensions and codecs, see `vach spec dump`")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Store CRLF line endings of text files as LF
+	map.insert(
+		key_names::NORMALIZE_NEWLINES,
+		Arg::new(key_names::NORMALIZE_NEWLINES)
+			.long("normalize-newlines")
+			.value_name(key_names::NORMALIZE_NEWLINES)
+			.help("Store the CRLF line endings of text files as LF, so packs built on Windows and Unix from the same sources are identical. Binary files are left as is")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// A CRC checked copy of the header at the end of the archive
+	map.insert(
+		key_names::FOOTER,
+		Arg::new(key_names::FOOTER)
+			.long("footer")
+			.value_name(key_names::FOOTER)
+			.help("End the archive with a CRC checked copy of the header, so truncation is caught on open and the archive can be appended to other files")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Compress large registries, stored after the leaves
+	map.insert(
+		key_names::COMPRESS_REGISTRY,
+		Arg::new(key_names::COMPRESS_REGISTRY)
+			.long("compress-registry")
+			.value_name(key_names::COMPRESS_REGISTRY)
+			.help("Compress the registry with the chosen compression algorithm once it takes 64 KiB or more, shrinking archives of very many small files")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Reserve room between the registry and the first leaf
+	map.insert(
+		key_names::DATA_OFFSET,
+		Arg::new(key_names::DATA_OFFSET)
+			.long("data-offset")
+			.value_name(key_names::DATA_OFFSET)
+			.help("Start leaf data at this absolute offset, eg: 64KiB, leaving a zeroed gap after the registry for platform headers or registry growth")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	// Order leaves by a recorded access profile
+	map.insert(
+		key_names::LAYOUT_PROFILE,
+		Arg::new(key_names::LAYOUT_PROFILE)
+			.long("layout-profile")
+			.value_name(key_names::LAYOUT_PROFILE)
+			.help("Lay leaves out in the order they were fetched, as recorded in an access profile written by AccessProfile::to_bytes")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	// The name of a release, recorded in it's manifest
+	map.insert(
+		key_names::RELEASE,
+		Arg::new(key_names::RELEASE)
+			.long("release")
+			.value_name(key_names::RELEASE)
+			.help("The name of the release, eg: its version")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	// Map the archive's bytes instead of listing its entries
+	map.insert(
+		key_names::LAYOUT,
+		Arg::new(key_names::LAYOUT)
+			.long("layout")
+			.value_name(key_names::LAYOUT)
+			.help("Show which byte ranges hold the header, the registry, each entry and unused space")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Only consider entries matching a filter expression
+	map.insert(
+		key_names::WHERE,
+		Arg::new(key_names::WHERE)
+			.long("where")
+			.value_name(key_names::WHERE)
+			.help("Only consider entries matching a filter, eg: 'size > 1MB && flag(ENCRYPTED) && id ~ \"textures/*\"'")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1),
+	);
+
+	// Only consider entries carrying one of the given tags
+	map.insert(
+		key_names::TAG,
+		Arg::new(key_names::TAG)
+			.long("tag")
+			.value_name(key_names::TAG)
+			.help("Only consider entries carrying any of the given tags, as set using Leaf::tag")
+			.required(false)
+			.takes_value(true)
+			.multiple_values(true),
+	);
+
+	// How errors are reported, applies to all subcommands
+	map.insert(
+		key_names::ERROR_FORMAT,
+		Arg::new(key_names::ERROR_FORMAT)
+			.long("error-format")
+			.value_name(key_names::ERROR_FORMAT)
+			.help("How errors are printed to stderr: text or json. Exit codes are stable either way")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1)
+			.possible_values(["text", "json"])
+			.global(true),
+	);
+
+	map.insert(
+		key_names::LOCK,
+		Arg::new(key_names::LOCK)
+			.long("lock")
+			.help("Takes advisory locks on archives, shared while reading and exclusive while writing, waiting on other processes holding them")
+			.required(false)
+			.takes_value(false)
+			.global(true),
+	);
+
+	map.insert(
+		key_names::METRICS,
+		Arg::new(key_names::METRICS)
+			.long("metrics")
+			.value_name(key_names::METRICS)
+			.help("Appends the duration, sizes and ratio of this run to a JSON state file, eg: state.json")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1)
+			.global(true),
+	);
+
+	// Sign with a key held outside the process
+	map.insert(
+		key_names::SIGN_WITH,
+		Arg::new(key_names::SIGN_WITH)
+			.long("sign-with")
+			.value_name(key_names::SIGN_WITH)
+			.help("Sign using ssh-agent:<fingerprint>, or cmd:<command> which reads data on stdin and writes a raw signature to stdout")
+			.required(false)
+			.takes_value(true)
+			.number_of_values(1)
+			.conflicts_with_all(&[key_names::KEYPAIR, key_names::SECRET_KEY]),
+	);
+
+	// Emit resources as a tar stream
+	map.insert(
+		key_names::TAR,
+		Arg::new(key_names::TAR)
+			.long("tar")
+			.value_name(key_names::TAR)
+			.help("Emit the selected resources as a tar stream, select resources using --include")
+			.required(false)
+			.takes_value(false),
+	);
+
+	// Glob patterns selecting resources by ID
+	map.insert(
+		key_names::INCLUDE,
+		Arg::new(key_names::INCLUDE)
+			.long("include")
+			.value_name(key_names::INCLUDE)
+			.help("Glob patterns selecting resources by ID, eg: 'configs/**'. Selects all resources if omitted")
+			.required(false)
+			.takes_value(true)
+			.multiple_occurrences(true)
+			.number_of_values(1)
+			.requires(key_names::TAR),
+	);
+
+	// Display live statistics while processing
+	map.insert(
+		key_names::STATS,
+		Arg::new(key_names::STATS)
+			.long("stats")
+			.value_name(key_names::STATS)
+			.help("Display live statistics while processing: per-algorithm throughput and compression ratios, thread utilization and the slowest files")
+			.required(false)
+			.takes_value(false),
+	);
+
 	map
 }
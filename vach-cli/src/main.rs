@@ -1,9 +1,19 @@
 // Fundamental modules
+#[macro_use]
+mod error;
 mod app;
 mod commands;
 mod keys;
 mod utils;
 
+use std::time::Instant;
+
+use clap::ErrorKind;
+use error::ErrorClass;
+use vach::metrics::Reporter;
+
+use utils::metrics::{self, StateFile};
+
 // NOTE: Unwrapping in a CLI is a no-no. Since throwing Rust developer errors at average users is mental overload
 fn main() {
 	// Build CLI
@@ -11,15 +21,62 @@ fn main() {
 	let app = app::build_app(keys);
 	let commands = commands::build_commands();
 
+	// Usage errors happen before arguments are parsed, so look for the error format directly
+	let args = std::env::args().collect::<Vec<_>>();
+	let json_errors = args
+		.windows(2)
+		.any(|w| w[0] == "--error-format" && w[1] == "json")
+		|| args.iter().any(|a| a == "--error-format=json");
+
 	// Start CLI
-	let matches = app.get_matches();
+	let matches = match app.try_get_matches_from(&args) {
+		Ok(matches) => matches,
+		Err(err) => match err.kind() {
+			ErrorKind::DisplayHelp | ErrorKind::DisplayVersion | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
+				err.exit()
+			},
+			_ if json_errors => {
+				let message = err.to_string();
+				let message = message.lines().next().unwrap_or_default().trim_start_matches("error: ");
+
+				error::report(ErrorClass::Usage, message, &[], true);
+				std::process::exit(ErrorClass::Usage.code());
+			},
+			_ => err.exit(),
+		},
+	};
 
-	match matches.subcommand() {
-		Some((key, mtx)) => commands.get(key).unwrap().evaluate(mtx),
+	let started = Instant::now();
+	let result = match matches.subcommand() {
+		Some((key, mtx)) => {
+			let metrics_path = mtx.value_of(keys::key_names::METRICS);
+			if metrics_path.is_some() {
+				metrics::start(key);
+			}
+
+			let result = commands.get(key).unwrap().evaluate(mtx);
+			let code = result.as_ref().map_or_else(|err| ErrorClass::of(err).code(), |_| 0);
+
+			// Failing to record metrics is reported, but doesn't fail an otherwise successful run
+			if let (Some(path), Some(run)) = (metrics_path, metrics::finish(started.elapsed(), code)) {
+				if let Err(err) = StateFile::new(path).report(&run) {
+					eprintln!("vach-cli: Unable to record metrics @ {}: {}", path, err);
+				}
+			}
+
+			result
+		},
 		None => {
 			println!("vach-cli: Run `vach --help` and refer to crates.io/vach-cli for the manual");
 			Ok(())
 		},
+	};
+
+	if let Err(err) = result {
+		let class = ErrorClass::of(&err);
+		let causes = err.chain().skip(1).map(|c| c.to_string()).collect::<Vec<_>>();
+
+		error::report(class, &err.to_string(), &causes, json_errors);
+		std::process::exit(class.code());
 	}
-	.unwrap();
 }
@@ -0,0 +1,112 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use vach::prelude::{BrotliParams, CompressMode, CompressionAlgorithm, Flags};
+
+/// A reviewable pack definition: every entry of an archive, where its data comes from and how it's processed.
+/// Sources are relative to the manifest's directory
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub magic: Option<String>,
+	pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+	pub id: String,
+	pub source: String,
+	/// One of `never`, `always` or `detect`
+	#[serde(default = "default_compress")]
+	pub compress: String,
+	/// One of `lz4`, `snappy`, `brotli` or `deflate`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub algorithm: Option<String>,
+	#[serde(default)]
+	pub encrypt: bool,
+	#[serde(default)]
+	pub sign: bool,
+	/// Custom flag bits, reserved bits are ignored. A number or names joined by `|`
+	#[serde(default)]
+	pub flags: Flags,
+	#[serde(default)]
+	pub version: u8,
+	/// Hex encoded SHA-256 digest of the uncompressed data, checked when packing
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sha256: Option<String>,
+}
+
+fn default_compress() -> String {
+	"never".to_string()
+}
+
+impl Manifest {
+	pub fn read(path: &Path) -> Result<Manifest> {
+		let file = File::open(path)?;
+		Ok(serde_json::from_reader(io::BufReader::new(file))?)
+	}
+
+	/// Resolves an entry's source against the directory of the manifest at `path`
+	pub fn source(path: &Path, entry: &ManifestEntry) -> PathBuf {
+		match path.parent() {
+			Some(dir) => dir.join(&entry.source),
+			None => PathBuf::from(&entry.source),
+		}
+	}
+}
+
+impl ManifestEntry {
+	/// Only custom bits are kept, the rest are derived from the other fields when packing
+	pub fn custom_flags(&self) -> Flags {
+		Flags::from_bits(self.flags.bits() & !Flags::RESERVED_MASK)
+	}
+}
+
+/// Parses a `-c` or manifest compress mode
+pub fn parse_compress_mode(value: &str) -> Result<CompressMode> {
+	Ok(match value.to_lowercase().as_str() {
+		"always" => CompressMode::Always,
+		"detect" => CompressMode::Detect,
+		"never" => CompressMode::Never,
+		"store" => CompressMode::Store,
+		invalid_value => fail!(Usage, "{} is an invalid value for COMPRESS_MODE", invalid_value),
+	})
+}
+
+/// Parses a `-g` or manifest compression algorithm
+pub fn parse_compression_algo(value: &str) -> Result<CompressionAlgorithm> {
+	Ok(match value.to_lowercase().as_str() {
+		"lz4" => CompressionAlgorithm::LZ4,
+		"brotli" => CompressionAlgorithm::Brotli(BrotliParams::default()),
+		"deflate" => CompressionAlgorithm::Deflate(6),
+		"snappy" => CompressionAlgorithm::Snappy,
+		invalid_value => fail!(Usage, "{} is an invalid value for COMPRESS_ALGO", invalid_value),
+	})
+}
+
+/// The name of the compression algorithm set in `flags`, if any
+pub fn algorithm_name(flags: &Flags) -> Option<&'static str> {
+	if flags.contains(Flags::LZ4_COMPRESSED) {
+		Some("lz4")
+	} else if flags.contains(Flags::SNAPPY_COMPRESSED) {
+		Some("snappy")
+	} else if flags.contains(Flags::BROTLI_COMPRESSED) {
+		Some("brotli")
+	} else if flags.contains(Flags::DEFLATE_COMPRESSED) {
+		Some("deflate")
+	} else {
+		None
+	}
+}
+
+/// Hex encoded SHA-256 digest of everything read from `reader`
+pub fn sha256<R: Read>(mut reader: R) -> io::Result<String> {
+	let mut hasher = Sha256::new();
+	io::copy(&mut reader, &mut hasher)?;
+
+	Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
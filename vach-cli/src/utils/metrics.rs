@@ -0,0 +1,66 @@
+use std::{
+	fs, io,
+	path::PathBuf,
+	sync::Mutex,
+	time::{Duration, SystemTime},
+};
+
+use vach::prelude::{InternalError, InternalResult};
+use vach::metrics::{Metrics, Reporter};
+
+// The run being measured, commands merge what they measured into it using `record`
+static RUN: Mutex<Option<Metrics>> = Mutex::new(None);
+
+/// Starts measuring a run of `operation`, enabled using `--metrics`
+pub fn start(operation: &str) {
+	*RUN.lock().unwrap() = Some(Metrics::new(operation));
+}
+
+/// Merges sizes and measurements taken by a command into the current run, does nothing without `--metrics`
+pub fn record(measured: Metrics) {
+	if let Some(run) = RUN.lock().unwrap().as_mut() {
+		run.bytes_in += measured.bytes_in;
+		run.bytes_out += measured.bytes_out;
+		run.entries += measured.entries;
+		run.extra.extend(measured.extra);
+	}
+}
+
+/// Ends the current run, returning it's metrics with the wall time and exit code filled in
+pub fn finish(elapsed: Duration, code: i32) -> Option<Metrics> {
+	let mut run = RUN.lock().unwrap().take()?;
+	run.elapsed = elapsed;
+	run.started = SystemTime::now() - elapsed;
+	run.extra.insert("exit_code".to_string(), code as f64);
+
+	Some(run)
+}
+
+/// Keeps every run in a JSON array within a state file, eg: `state.json`, for build farms to trend over time
+pub struct StateFile {
+	path: PathBuf,
+}
+
+impl StateFile {
+	pub fn new(path: impl Into<PathBuf>) -> StateFile {
+		StateFile { path: path.into() }
+	}
+}
+
+impl Reporter for StateFile {
+	fn report(&mut self, metrics: &Metrics) -> InternalResult {
+		let invalid = |err: serde_json::Error| InternalError::IOError(io::Error::new(io::ErrorKind::InvalidData, err));
+
+		let mut runs = match fs::read(&self.path) {
+			Ok(bytes) if !bytes.is_empty() => serde_json::from_slice::<Vec<serde_json::Value>>(&bytes).map_err(invalid)?,
+			Ok(_) => vec![],
+			Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+			Err(err) => return Err(err.into()),
+		};
+
+		runs.push(serde_json::from_str(&metrics.to_json()).map_err(invalid)?);
+		fs::write(&self.path, serde_json::to_vec_pretty(&runs).map_err(invalid)?)?;
+
+		Ok(())
+	}
+}
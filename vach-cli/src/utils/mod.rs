@@ -1,8 +1,20 @@
-use std::path::PathBuf;
-use std::fs::File;
-use std::io::Write;
+pub mod manifest;
+pub mod metrics;
+pub mod signer;
+pub mod stats;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use std::io::{Read, Seek, Write};
 use std::str::FromStr;
+use std::time::Duration;
 use anyhow::{Result, bail};
+use sha2::{Digest, Sha256};
+use vach::prelude::{Archive, EntryId, InternalError, Query, VerifyingKey};
+use vach::lock::{ArchiveLock, LockMode};
+
+use crate::keys::key_names;
 
 pub fn create_and_write_to_file(path: &str, data: &[u8]) -> Result<()> {
 	let path = PathBuf::from_str(path)?;
@@ -17,3 +29,117 @@ pub fn create_and_write_to_file(path: &str, data: &[u8]) -> Result<()> {
 
 	Ok(())
 }
+
+/// Collects the archives given with `-i`. Directories are expanded into the `.vach` files they contain
+pub fn collect_archives<'a>(inputs: impl Iterator<Item = &'a str>) -> Result<Vec<PathBuf>> {
+	let mut archives = vec![];
+
+	for input in inputs {
+		let path = Path::new(input);
+
+		if path.is_dir() {
+			let mut found = fs::read_dir(path)?
+				.filter_map(|entry| entry.ok().map(|e| e.path()))
+				.filter(|p| p.is_file() && p.extension().map(|ext| ext == "vach").unwrap_or(false))
+				.collect::<Vec<_>>();
+
+			found.sort();
+			archives.extend(found);
+		} else {
+			archives.push(path.to_path_buf());
+		}
+	}
+
+	if archives.is_empty() {
+		fail!(Usage, "No archives found in the given inputs");
+	}
+
+	Ok(archives)
+}
+
+/// The SHA-256 digest of a public key, in hex. Secret keys and their keypairs share the fingerprint of their public key
+pub fn fingerprint(key: &VerifyingKey) -> String {
+	let digest = Sha256::digest(key.as_bytes());
+	let hex = digest.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+	format!("SHA256:{}", hex)
+}
+
+/// Takes an advisory lock over the archive at `path` if `enabled`, using `--lock`. Held until the returned lock is dropped
+pub fn lock_archive(enabled: bool, path: impl AsRef<Path>, mode: LockMode) -> Result<Option<ArchiveLock>> {
+	if !enabled {
+		return Ok(None);
+	}
+
+	let path = path.as_ref();
+	let lock = match ArchiveLock::acquire(path, mode, Some(Duration::ZERO)) {
+		Err(InternalError::LockedError(_)) => {
+			eprintln!("Waiting for another process to release the lock on {}", path.to_string_lossy());
+			ArchiveLock::acquire(path, mode, None)?
+		},
+		lock => lock?,
+	};
+
+	if let Some(owner) = lock.recovered() {
+		eprintln!(
+			"Warning: process {} exited while writing {}, it may be incomplete",
+			owner.pid,
+			path.to_string_lossy()
+		);
+	}
+
+	Ok(Some(lock))
+}
+
+/// Parses the filter passed using `--where`, if any
+pub fn parse_query(args: &clap::ArgMatches) -> Result<Option<Query>> {
+	match args.value_of(key_names::WHERE).map(Query::parse) {
+		Some(Ok(query)) => Ok(Some(query)),
+		Some(Err(err)) => fail!(Usage, "{}", err),
+		None => Ok(None),
+	}
+}
+
+/// The `ID`s of the entries carrying any of the tags passed using `--tag`, `None` if no tag was passed
+pub fn tagged<T: Read + Seek>(args: &clap::ArgMatches, archive: &Archive<T>) -> Result<Option<HashSet<EntryId>>> {
+	let tags = match args.values_of(key_names::TAG) {
+		Some(tags) => tags,
+		None => return Ok(None),
+	};
+
+	let mut ids = HashSet::new();
+	for tag in tags {
+		ids.extend(archive.group(tag)?);
+	}
+
+	Ok(Some(ids))
+}
+
+/// Parses a size such as `512`, `1.8GiB` or `256 MB`. Binary and decimal units are both accepted, units are case insensitive
+pub fn parse_size(value: &str) -> Result<u64> {
+	let value = value.trim();
+	let split = value
+		.find(|c: char| !(c.is_ascii_digit() || c == '.'))
+		.unwrap_or(value.len());
+
+	let (number, unit) = value.split_at(split);
+	let number = match number.parse::<f64>() {
+		Ok(number) => number,
+		Err(_) => fail!(Usage, "Invalid size: {}, expected a number followed by a unit, eg: 1.8GiB", value),
+	};
+
+	let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+		"" | "b" => 1,
+		"k" | "kb" => 1000,
+		"kib" => 1 << 10,
+		"m" | "mb" => 1000 * 1000,
+		"mib" => 1 << 20,
+		"g" | "gb" => 1000 * 1000 * 1000,
+		"gib" => 1 << 30,
+		"t" | "tb" => 1000 * 1000 * 1000 * 1000,
+		"tib" => 1 << 40,
+		unit => fail!(Usage, "Unknown size unit: {}, expected one of B, KB, KiB, MB, MiB, GB, GiB, TB or TiB", unit),
+	};
+
+	Ok((number * multiplier as f64) as u64)
+}
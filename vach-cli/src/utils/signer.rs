@@ -0,0 +1,240 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+use vach::prelude::{InternalError, InternalResult, LeafSigner, Signature, VerifyingKey};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// Parses a `--sign-with` value: `ssh-agent:<fingerprint>` or `cmd:<command>`.
+/// External commands can't report their public key, so it must be provided
+pub fn from_spec(spec: &str, public_key: Option<VerifyingKey>) -> Result<Box<dyn LeafSigner>> {
+	match spec.split_once(':') {
+		#[cfg(unix)]
+		Some(("ssh-agent", fingerprint)) => Ok(Box::new(SshAgentSigner::connect(fingerprint)?)),
+		#[cfg(not(unix))]
+		Some(("ssh-agent", _)) => fail!(BadKey, "ssh-agent signing is only supported on unix"),
+		Some(("cmd", command)) => match public_key {
+			Some(public_key) => Ok(Box::new(CommandSigner::new(command, public_key)?)),
+			None => fail!(Usage, "Signing with an external command requires its public key, provide it using -p or --public-key"),
+		},
+		_ => fail!(
+			Usage,
+			"Invalid signer: {}, expected ssh-agent:<fingerprint> or cmd:<command>",
+			spec
+		),
+	}
+}
+
+/// Runs an external program for every signature. The message is written to its stdin, and the raw 64 byte signature read from its stdout
+pub struct CommandSigner {
+	program: String,
+	args: Vec<String>,
+	public_key: VerifyingKey,
+}
+
+impl CommandSigner {
+	pub fn new(command: &str, public_key: VerifyingKey) -> Result<CommandSigner> {
+		let mut parts = command.split_whitespace().map(String::from);
+		let program = match parts.next() {
+			Some(program) => program,
+			None => fail!(Usage, "Please provide a command to sign with, eg: cmd:my-signer --key build"),
+		};
+
+		Ok(CommandSigner {
+			program,
+			args: parts.collect(),
+			public_key,
+		})
+	}
+}
+
+impl LeafSigner for CommandSigner {
+	fn verifying_key(&self) -> VerifyingKey {
+		self.public_key
+	}
+
+	fn sign_leaf(&self, message: &[u8]) -> InternalResult<Signature> {
+		let mut child = Command::new(&self.program)
+			.args(&self.args)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.spawn()?;
+
+		child.stdin.take().unwrap().write_all(message)?;
+		let output = child.wait_with_output()?;
+
+		if !output.status.success() {
+			return Err(signer_error(format!("{} exited with {}", self.program, output.status)));
+		}
+
+		let signature = Signature::from_slice(&output.stdout).map_err(|err| InternalError::ParseError(err.to_string()))?;
+		verified(&self.public_key, message, signature)
+	}
+}
+
+// SSH agent protocol message numbers, see https://datatracker.ietf.org/doc/html/draft-miller-ssh-agent
+#[cfg(unix)]
+const SSH_AGENT_FAILURE: u8 = 5;
+#[cfg(unix)]
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+#[cfg(unix)]
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+#[cfg(unix)]
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+#[cfg(unix)]
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+#[cfg(unix)]
+const ED25519_KEY_TYPE: &[u8] = b"ssh-ed25519";
+
+/// Signs through the agent listening on `SSH_AUTH_SOCK`. Works with any agent speaking the SSH agent protocol, including `gpg-agent` with ssh support enabled.
+/// The agent's `ed25519` signatures are plain `ed25519` signatures, so archives signed this way verify with the key's public half
+#[cfg(unix)]
+pub struct SshAgentSigner {
+	stream: Mutex<UnixStream>,
+	key_blob: Vec<u8>,
+	public_key: VerifyingKey,
+}
+
+#[cfg(unix)]
+impl SshAgentSigner {
+	/// Connects to the agent and looks up the `ed25519` identity with the given fingerprint.
+	/// Both OpenSSH fingerprints, as printed by `ssh-add -l`, and `vach key fingerprint` fingerprints are accepted
+	pub fn connect(fingerprint: &str) -> Result<SshAgentSigner> {
+		let socket = match std::env::var_os("SSH_AUTH_SOCK") {
+			Some(socket) => socket,
+			None => fail!(BadKey, "SSH_AUTH_SOCK is not set, is an ssh-agent running?"),
+		};
+
+		let mut stream = UnixStream::connect(socket)?;
+		let response = request(&mut stream, SSH_AGENTC_REQUEST_IDENTITIES, &[])?;
+
+		let mut reader = response.as_slice();
+		if read_u8(&mut reader)? != SSH_AGENT_IDENTITIES_ANSWER {
+			fail!(BadKey, "The ssh-agent refused to list its identities");
+		}
+
+		for _ in 0..read_u32(&mut reader)? {
+			let key_blob = read_string(&mut reader)?.to_vec();
+			let _comment = read_string(&mut reader)?;
+
+			let mut blob = key_blob.as_slice();
+			if read_string(&mut blob)? != ED25519_KEY_TYPE {
+				continue;
+			}
+
+			let public_key = VerifyingKey::try_from(read_string(&mut blob)?)?;
+			let openssh = format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(&key_blob)));
+
+			if fingerprint == openssh || fingerprint == super::fingerprint(&public_key) {
+				return Ok(SshAgentSigner {
+					stream: Mutex::new(stream),
+					key_blob,
+					public_key,
+				});
+			}
+		}
+
+		fail!(BadKey, "No ed25519 key with the fingerprint {} was found in the ssh-agent", fingerprint)
+	}
+}
+
+#[cfg(unix)]
+impl LeafSigner for SshAgentSigner {
+	fn verifying_key(&self) -> VerifyingKey {
+		self.public_key
+	}
+
+	fn sign_leaf(&self, message: &[u8]) -> InternalResult<Signature> {
+		let mut payload = Vec::with_capacity(self.key_blob.len() + message.len() + 12);
+		write_string(&mut payload, &self.key_blob);
+		write_string(&mut payload, message);
+		payload.extend_from_slice(&0u32.to_be_bytes());
+
+		let response = {
+			let mut stream = self.stream.lock().unwrap();
+			request(&mut stream, SSH_AGENTC_SIGN_REQUEST, &payload)?
+		};
+
+		let mut reader = response.as_slice();
+		match read_u8(&mut reader)? {
+			SSH_AGENT_SIGN_RESPONSE => (),
+			SSH_AGENT_FAILURE => return Err(signer_error("The ssh-agent refused to sign, is the key locked?")),
+			other => return Err(signer_error(format!("Unexpected ssh-agent response: {}", other))),
+		};
+
+		let mut blob = read_string(&mut reader)?;
+		if read_string(&mut blob)? != ED25519_KEY_TYPE {
+			return Err(signer_error("The ssh-agent returned a signature that isn't ed25519"));
+		}
+
+		let signature =
+			Signature::from_slice(read_string(&mut blob)?).map_err(|err| InternalError::ParseError(err.to_string()))?;
+		verified(&self.public_key, message, signature)
+	}
+}
+
+/// Sends a single message to the agent and reads back its response
+#[cfg(unix)]
+fn request(stream: &mut UnixStream, kind: u8, payload: &[u8]) -> std::io::Result<Vec<u8>> {
+	stream.write_all(&(payload.len() as u32 + 1).to_be_bytes())?;
+	stream.write_all(&[kind])?;
+	stream.write_all(payload)?;
+
+	let mut length = [0u8; 4];
+	stream.read_exact(&mut length)?;
+
+	let mut response = vec![0u8; u32::from_be_bytes(length) as usize];
+	stream.read_exact(&mut response)?;
+
+	Ok(response)
+}
+
+#[cfg(unix)]
+fn read_u8(reader: &mut &[u8]) -> std::io::Result<u8> {
+	let mut byte = [0u8; 1];
+	reader.read_exact(&mut byte)?;
+	Ok(byte[0])
+}
+
+#[cfg(unix)]
+fn read_u32(reader: &mut &[u8]) -> std::io::Result<u32> {
+	let mut bytes = [0u8; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(u32::from_be_bytes(bytes))
+}
+
+#[cfg(unix)]
+fn read_string<'a>(reader: &mut &'a [u8]) -> std::io::Result<&'a [u8]> {
+	let length = read_u32(reader)? as usize;
+	if length > reader.len() {
+		return Err(std::io::ErrorKind::UnexpectedEof.into());
+	}
+
+	let (string, rest) = reader.split_at(length);
+	*reader = rest;
+	Ok(string)
+}
+
+#[cfg(unix)]
+fn write_string(buffer: &mut Vec<u8>, string: &[u8]) {
+	buffer.extend_from_slice(&(string.len() as u32).to_be_bytes());
+	buffer.extend_from_slice(string);
+}
+
+/// Catches signers using the wrong key before a broken archive is written
+fn verified(public_key: &VerifyingKey, message: &[u8], signature: Signature) -> InternalResult<Signature> {
+	match public_key.verify_strict(message, &signature) {
+		Ok(()) => Ok(signature),
+		Err(_) => Err(signer_error("The signer produced a signature that doesn't match its public key")),
+	}
+}
+
+fn signer_error(message: impl Into<String>) -> InternalError {
+	InternalError::OtherError(message.into().into())
+}
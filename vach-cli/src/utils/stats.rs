@@ -0,0 +1,178 @@
+use std::{
+	collections::BTreeMap,
+	io::{self, Read},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use vach::prelude::Flags;
+
+// How many of the slowest files to display
+const SLOWEST: usize = 3;
+
+#[derive(Default)]
+struct AlgoStats {
+	files: u64,
+	raw_bytes: u64,
+	stored_bytes: u64,
+	time: Duration,
+}
+
+#[derive(Default)]
+struct Inner {
+	algos: BTreeMap<&'static str, AlgoStats>,
+	busy: Duration,
+	slowest: Vec<(String, Duration)>,
+	// Sizes and start times of files that are yet to be processed by the builder
+	pending: BTreeMap<String, (u64, Instant)>,
+}
+
+/// Live statistics, displayed in a line under the progress bar, enabled using `--stats`
+pub struct LiveStats {
+	inner: Mutex<Inner>,
+	start: Instant,
+	threads: usize,
+	bar: ProgressBar,
+}
+
+impl LiveStats {
+	pub fn new(threads: usize, bar: ProgressBar) -> LiveStats {
+		bar.set_style(ProgressStyle::default_bar().template("{msg}").unwrap());
+
+		LiveStats {
+			inner: Mutex::new(Inner::default()),
+			start: Instant::now(),
+			threads: threads.max(1),
+			bar,
+		}
+	}
+
+	/// The name of the compression algorithm used by an entry
+	pub fn algo_name(flags: &Flags) -> &'static str {
+		if flags.contains(Flags::LZ4_COMPRESSED) {
+			"lz4"
+		} else if flags.contains(Flags::BROTLI_COMPRESSED) {
+			"brotli"
+		} else if flags.contains(Flags::DEFLATE_COMPRESSED) {
+			"deflate"
+		} else if flags.contains(Flags::SNAPPY_COMPRESSED) {
+			"snappy"
+		} else {
+			"none"
+		}
+	}
+
+	/// Stash the size of a file and when it started being read, to be matched with it's registry entry once it's processed
+	pub fn read_done(&self, id: &str, bytes: u64, started: Instant) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.pending.insert(id.to_string(), (bytes, started));
+	}
+
+	/// Record a processed entry whose raw size and start time were stashed with `LiveStats::read_done`
+	pub fn record_pending(&self, id: &str, flags: &Flags, stored: u64) {
+		let pending = self.inner.lock().unwrap().pending.remove(id);
+		let (raw, time) = match pending {
+			Some((raw, started)) => (raw, started.elapsed()),
+			None => (stored, Duration::ZERO),
+		};
+
+		self.record(id, flags, raw, stored, time);
+	}
+
+	/// Record a processed entry, updating the live display
+	pub fn record(&self, id: &str, flags: &Flags, raw: u64, stored: u64, time: Duration) {
+		let mut inner = self.inner.lock().unwrap();
+
+		let algo = inner.algos.entry(LiveStats::algo_name(flags)).or_default();
+		algo.files += 1;
+		algo.raw_bytes += raw;
+		algo.stored_bytes += stored;
+		algo.time += time;
+
+		inner.busy += time;
+
+		// Keep the slowest files, slowest first
+		let idx = inner.slowest.partition_point(|(_, t)| *t >= time);
+		if idx < SLOWEST {
+			inner.slowest.insert(idx, (id.to_string(), time));
+			inner.slowest.truncate(SLOWEST);
+		}
+
+		self.bar.set_message(self.render(&inner));
+	}
+
+	fn render(&self, inner: &Inner) -> String {
+		let algos = inner
+			.algos
+			.iter()
+			.map(|(name, s)| {
+				let throughput = s.raw_bytes as f64 / s.time.as_secs_f64().max(f64::EPSILON) / (1024.0 * 1024.0);
+				let ratio = s.raw_bytes as f64 / s.stored_bytes.max(1) as f64;
+
+				format!("{name}: {} files, {throughput:.1} MiB/s, {ratio:.2}x", s.files)
+			})
+			.collect::<Vec<_>>()
+			.join(" | ");
+
+		let capacity = self.start.elapsed().as_secs_f64() * self.threads as f64;
+		let utilization = (inner.busy.as_secs_f64() / capacity.max(f64::EPSILON) * 100.0).min(100.0);
+
+		let slowest = inner
+			.slowest
+			.iter()
+			.map(|(id, t)| format!("{id} ({}ms)", t.as_millis()))
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		format!("{algos}\nthreads: {utilization:.0}% busy of {} | slowest: {slowest}", self.threads)
+	}
+
+	/// Stop updating the live display, leaving the final statistics on screen
+	pub fn finish(&self) {
+		let inner = self.inner.lock().unwrap();
+		let summary = self.render(&inner);
+
+		// Nothing is drawn when not attached to a terminal, so print the statistics instead
+		if self.bar.is_hidden() {
+			println!("{summary}");
+		}
+
+		self.bar.finish_with_message(summary);
+	}
+}
+
+/// Wraps a file being packed, measuring it's size and when the builder started processing it
+pub struct Timed<R> {
+	inner: R,
+	id: String,
+	stats: Arc<LiveStats>,
+	started: Option<Instant>,
+	bytes: u64,
+}
+
+impl<R> Timed<R> {
+	pub fn new(inner: R, id: String, stats: Arc<LiveStats>) -> Timed<R> {
+		Timed {
+			inner,
+			id,
+			stats,
+			started: None,
+			bytes: 0,
+		}
+	}
+}
+
+impl<R: Read> Read for Timed<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let started = *self.started.get_or_insert_with(Instant::now);
+		let read = self.inner.read(buf)?;
+		self.bytes += read as u64;
+
+		if read == 0 && !buf.is_empty() {
+			self.stats.read_done(&self.id, self.bytes, started);
+		}
+
+		Ok(read)
+	}
+}
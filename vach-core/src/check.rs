@@ -0,0 +1,221 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+	flags,
+	header::{capabilities, RawHeader},
+	le,
+	registry::{RawEntry, RegistryBlock},
+	Error, SIGNATURE_LENGTH,
+};
+
+// Values at the edges of each integer type, plus patterns whose bytes all differ so swapped bytes can't go unnoticed
+const U16S: [u16; 4] = [0, 1, 0x0102, u16::MAX];
+const U32S: [u32; 4] = [0, 1, 0x0102_0304, u32::MAX];
+const U64S: [u64; 4] = [0, 1, 0x0102_0304_0506_0708, u64::MAX];
+const IDS: [&str; 5] = ["", "a", "textures/ui/button.png", "textures/ui/buttón.png", "音楽/テーマ"];
+
+/// Exhaustively checks the format's invariants on the current platform: integers are little-endian, sizes match the spec,
+/// and headers and registry entries decode to exactly what was encoded, over every combination of edge values.
+/// Returns the first violated invariant. Cheap enough to run in CI on every target, or at startup on exotic hardware
+/// ```
+/// assert_eq!(vach_core::roundtrip_check(), Ok(()));
+/// ```
+pub fn roundtrip_check() -> Result<(), Error> {
+	check_byte_order()?;
+	check_sizes()?;
+	check_headers()?;
+	check_entries()?;
+	check_registry()
+}
+
+fn ensure(holds: bool, invariant: &'static str) -> Result<(), Error> {
+	match holds {
+		true => Ok(()),
+		false => Err(Error::Invariant(invariant)),
+	}
+}
+
+fn check_byte_order() -> Result<(), Error> {
+	let mut buffer = Vec::new();
+	le::write_u16(&mut buffer, 0x0102);
+	le::write_u32(&mut buffer, 0x0304_0506);
+	le::write_u64(&mut buffer, 0x0708_090A_0B0C_0D0E);
+
+	let expected = [2, 1, 6, 5, 4, 3, 14, 13, 12, 11, 10, 9, 8, 7];
+	ensure(buffer == expected, "integers are written little-endian")?;
+
+	ensure(le::read_u16(&expected, 0) == 0x0102, "u16 are read little-endian")?;
+	ensure(le::read_u32(&expected, 2) == 0x0304_0506, "u32 are read little-endian")?;
+	ensure(le::read_u64(&expected, 6) == 0x0708_090A_0B0C_0D0E, "u64 are read little-endian")
+}
+
+fn check_sizes() -> Result<(), Error> {
+	ensure(flags::SIZE == 4, "flags take up 4 bytes")?;
+	ensure(RawHeader::PREFIX_SIZE == 11, "the header prefix takes up 11 bytes")?;
+	ensure(RawHeader::BASE_SIZE == 19, "the header takes up 19 bytes")?;
+	ensure(RawHeader::CAPABILITIES_OFFSET == 13, "capabilities start at byte 13 of the header")?;
+	ensure(RawHeader::LEGACY_SIZE == 13, "legacy headers take up 13 bytes")?;
+	ensure(RawEntry::MIN_SIZE == 31, "the fixed part of a registry entry takes up 31 bytes")?;
+	ensure(RawEntry::LEGACY_MIN_SIZE == 23, "the fixed part of a legacy registry entry takes up 23 bytes")?;
+	ensure(RegistryBlock::SIZE == 28, "a compressed registry's description takes up 28 bytes")?;
+	ensure(SIGNATURE_LENGTH == 64, "signatures take up 64 bytes")
+}
+
+fn check_headers() -> Result<(), Error> {
+	// A known header, byte for byte
+	let header = RawHeader {
+		magic: *b"VfACH",
+		flags: 0x0102_0304,
+		arch_version: 0x0506,
+		min_loader_version: 0x0708,
+		capabilities: capabilities::EXTENDED_HEADER,
+		capacity: 0x090A,
+		extensions: alloc::vec![(7, alloc::vec![0xAB, 0xCD])],
+	};
+
+	let golden = [
+		b'V', b'f', b'A', b'C', b'H', 4, 3, 2, 1, 6, 5, 8, 7, 0x10, 0, 0, 0, 10, 9, 5, 0, 0, 0, 7, 2, 0, 0xAB, 0xCD,
+	];
+	ensure(header.encode() == golden, "headers encode to the bytes the spec describes")?;
+
+	// Legacy headers end with the capacity
+	let legacy = RawHeader {
+		arch_version: crate::LEGACY_VERSION,
+		min_loader_version: crate::LEGACY_VERSION,
+		capabilities: 0,
+		extensions: Vec::new(),
+		..header
+	};
+
+	let golden = [b'V', b'f', b'A', b'C', b'H', 4, 3, 2, 1, 30, 0, 10, 9];
+	ensure(legacy.encode() == golden, "legacy headers encode to the bytes the spec describes")?;
+	ensure(crate::parse_header(&golden)? == (legacy, golden.len()), "legacy headers decode to what was encoded")?;
+
+	for &flags in &U32S {
+		for &version in &U16S {
+			for &capacity in &U16S {
+				for extended in [false, true] {
+					let header = RawHeader {
+						flags,
+						arch_version: version,
+						min_loader_version: !version,
+						capabilities: if extended { capabilities::EXTENDED_HEADER } else { flags & !capabilities::EXTENDED_HEADER },
+						capacity,
+						extensions: match extended {
+							true => alloc::vec![(0, Vec::new()), (u8::MAX, alloc::vec![0x5A; version as usize % 512])],
+							false => Vec::new(),
+						},
+						..RawHeader::default()
+					};
+
+					let bytes = header.encode();
+					ensure(bytes.len() == header.size(), "headers encode to their reported size")?;
+					ensure(crate::parse_header(&bytes)? == (header, bytes.len()), "headers decode to what was encoded")?;
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn entries() -> impl Iterator<Item = RawEntry> {
+	IDS.iter().enumerate().flat_map(|(idx, id)| {
+		U64S.iter().flat_map(move |&value| {
+			[0, flags::SIGNED_FLAG, U32S[idx % U32S.len()] | flags::SIGNED_FLAG].map(|flags| RawEntry {
+				id: String::from(*id),
+				flags,
+				content_version: value as u8 ^ idx as u8,
+				location: value,
+				offset: value.rotate_left(8),
+				decoded_size: !value,
+				signature: (flags & flags::SIGNED_FLAG != 0).then(|| core::array::from_fn(|i| i as u8 ^ value as u8)),
+			})
+		})
+	})
+}
+
+fn check_entries() -> Result<(), Error> {
+	// A known entry, byte for byte
+	let entry = RawEntry {
+		id: String::from("id"),
+		flags: 0x0102_0304,
+		content_version: 5,
+		location: 0x0607_0809_0A0B_0C0D,
+		offset: 0x0E0F_1011_1213_1415,
+		decoded_size: 0x1617_1819_1A1B_1C1D,
+		signature: None,
+	};
+
+	let golden = [
+		4, 3, 2, 1, 5, 0x0D, 0x0C, 0x0B, 0x0A, 9, 8, 7, 6, 0x15, 0x14, 0x13, 0x12, 0x11, 0x10, 0x0F, 0x0E, 0x1D, 0x1C, 0x1B, 0x1A,
+		0x19, 0x18, 0x17, 0x16, 2, 0, b'i', b'd',
+	];
+	ensure(entry.encode(None, false)? == golden, "registry entries encode to the bytes the spec describes")?;
+
+	// Legacy entries lack the decoded size
+	let golden = [
+		4, 3, 2, 1, 5, 0x0D, 0x0C, 0x0B, 0x0A, 9, 8, 7, 6, 0x15, 0x14, 0x13, 0x12, 0x11, 0x10, 0x0F, 0x0E, 2, 0, b'i', b'd',
+	];
+	ensure(entry.encode_legacy(false)? == golden, "legacy registry entries encode to the bytes the spec describes")?;
+
+	for entry in entries() {
+		for previous in [None, Some(""), Some("textures/ui/"), Some("音楽/")] {
+			let signed = entry.signature.is_some();
+			let bytes = entry.encode(previous, false)?;
+
+			ensure(
+				bytes.len() == RawEntry::encoded_size(&entry.id, previous, signed),
+				"registry entries encode to their reported size",
+			)?;
+			ensure(
+				RawEntry::MIN_SIZE + RawEntry::tail_size(bytes[..RawEntry::MIN_SIZE].try_into().unwrap(), previous.is_some())
+					== bytes.len(),
+				"the fixed part of a registry entry tells its full size",
+			)?;
+			ensure(RawEntry::decode(&bytes, previous)? == (entry.clone(), bytes.len()), "registry entries decode to what was encoded")?;
+
+			// Signatures cover the entry without the signature itself
+			if signed {
+				let unsigned = entry.encode(previous, true)?;
+				ensure(unsigned.len() + SIGNATURE_LENGTH == bytes.len(), "signed messages leave out the signature")?;
+			}
+		}
+
+		// Legacy entries only know their decoded size when stored as is
+		let legacy = RawEntry {
+			decoded_size: match entry.flags & (flags::COMPRESSED_FLAG | flags::ENCRYPTED_FLAG) {
+				0 => entry.offset,
+				_ => 0,
+			},
+			..entry
+		};
+
+		let bytes = legacy.encode_legacy(false)?;
+		ensure(RawEntry::decode_legacy(&bytes)? == (legacy, bytes.len()), "legacy registry entries decode to what was encoded")?;
+	}
+
+	Ok(())
+}
+
+fn check_registry() -> Result<(), Error> {
+	for prefixed in [false, true] {
+		let mut entries = entries().collect::<Vec<_>>();
+		if prefixed {
+			entries.sort_by(|a, b| a.id.cmp(&b.id));
+		}
+
+		let mut bytes = Vec::new();
+		let mut previous = prefixed.then_some("");
+
+		for entry in &entries {
+			bytes.extend_from_slice(&entry.encode(previous, false)?);
+			previous = previous.map(|_| entry.id.as_str());
+		}
+
+		let parsed = crate::parse_registry(&bytes, entries.len() as u16, prefixed)?;
+		ensure(parsed == entries, "registries decode to what was encoded")?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,35 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Errors raised while encoding or decoding the format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// The data ended before the structure being decoded did
+	Truncated,
+	/// An `ID` longer than `MAX_ID_LENGTH`, contains the overflowing `ID`
+	IdTooLong(String),
+	/// An `ID` that isn't valid UTF-8
+	InvalidId,
+	/// A prefixed `ID` claims to share more bytes with the previous `ID` than it holds, contains the claimed length
+	InvalidPrefix(usize),
+	/// A checksum over the data doesn't match the data
+	Checksum,
+	/// An invariant of the format doesn't hold on this platform, found by [`roundtrip_check`](crate::roundtrip_check). Names the invariant
+	Invariant(&'static str),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Truncated => f.write_str("Truncated data"),
+			Error::IdTooLong(id) => write!(f, "The ID: {} is longer than the maximum of {} bytes", id, crate::MAX_ID_LENGTH),
+			Error::InvalidId => f.write_str("ID is not valid UTF-8"),
+			Error::InvalidPrefix(shared) => write!(f, "Prefixed ID shares {} bytes with a shorter ID", shared),
+			Error::Checksum => f.write_str("Checksum mismatch"),
+			Error::Invariant(invariant) => write!(f, "Format invariant violated: {}", invariant),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
@@ -0,0 +1,37 @@
+//! The bits of the `u32` flags stored in the header and in every registry entry
+
+/// The bits used by the format itself, the rest are free for custom use
+pub const RESERVED_MASK: u32 = 0b1111_1111_1111_1111_0000_0000_0000_0000;
+/// The size in bytes of any flags entry
+pub const SIZE: usize = 32 / 8;
+
+/// The entry is compressed
+pub const COMPRESSED_FLAG: u32 = 0b_1000_0000_0000_0000_0000_0000_0000_0000;
+/// The entry was compressed using LZ4
+pub const LZ4_COMPRESSED: u32 = 0b_0100_0000_0000_0000_0000_0000_0000_0000;
+/// The entry was compressed using snappy
+pub const SNAPPY_COMPRESSED: u32 = 0b_0010_0000_0000_0000_0000_0000_0000_0000;
+/// The entry was compressed using brotli
+pub const BROTLI_COMPRESSED: u32 = 0b_0001_0000_0000_0000_0000_0000_0000_0000;
+/// The brotli window size used, stored as `window - 9` in these four bits. Zero if unknown
+pub const BROTLI_WINDOW_MASK: u32 = 0b_0000_0000_0000_1111_0000_0000_0000_0000;
+/// The entry was compressed into a gzip stream using deflate
+pub const DEFLATE_COMPRESSED: u32 = 0b_0000_0000_1000_0000_0000_0000_0000_0000;
+
+/// The entry is a tombstone, it holds no data and hides any entry with the same `ID` in the layers below it
+pub const TOMBSTONE_FLAG: u32 = 0b_0000_0000_0100_0000_0000_0000_0000_0000;
+/// When the entry is meant to be loaded, `0` for on demand, `1` for preload and `2` for streaming
+pub const RESIDENCY_HINT_MASK: u32 = 0b_0000_0000_0011_0000_0000_0000_0000_0000;
+
+/// The entry is followed by a signature in the registry, in the header it shows that the archive has signatures
+pub const SIGNED_FLAG: u32 = 0b_0000_1000_0000_0000_0000_0000_0000_0000;
+/// The entry went through a custom pipeline, its data is prefixed with the codes of the stages that ran
+pub const PIPELINE_FLAG: u32 = 0b_0000_0100_0000_0000_0000_0000_0000_0000;
+/// The entry is encrypted
+pub const ENCRYPTED_FLAG: u32 = 0b_0000_0010_0000_0000_0000_0000_0000_0000;
+/// The entry was encrypted under a rotated key, its data is prefixed with the `u32` epoch of that key
+pub const KEY_EPOCH_FLAG: u32 = 0b_0000_0001_0000_0000_0000_0000_0000_0000;
+
+/// The bits with a meaning in [`LEGACY_VERSION`](crate::LEGACY_VERSION) archives, the other reserved bits were unused then
+pub const LEGACY_MASK: u32 =
+	COMPRESSED_FLAG | LZ4_COMPRESSED | SNAPPY_COMPRESSED | BROTLI_COMPRESSED | SIGNED_FLAG | ENCRYPTED_FLAG | !RESERVED_MASK;
@@ -0,0 +1,69 @@
+//! The optional footer at the very end of an archive, mirroring the fixed part of the header under a CRC.
+//! Truncation shows as a missing or mismatched footer without reading anything else, and archives appended to other files,
+//! eg: a self-extracting executable, are found from the end of the file without scanning
+
+use crate::{header::RawHeader, le, Error};
+
+/// The footer, as stored. The mirrored header is kept as bytes, decode it using [`RawHeader::decode`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFooter {
+	/// A copy of the fixed part of the header, see [`RawHeader::BASE_SIZE`]
+	pub header: [u8; RawHeader::BASE_SIZE],
+	/// The size of the whole archive, from the first byte of the header to the last byte of the footer
+	pub archive_size: u64,
+}
+
+impl RawFooter {
+	/// The size of the footer: the mirrored header, the archive's size and a CRC-32 over both
+	pub const SIZE: usize = RawHeader::BASE_SIZE + 8 + 4;
+
+	/// Encodes the footer, ending with a CRC-32 (IEEE) over everything before it
+	pub fn encode(&self) -> [u8; Self::SIZE] {
+		let mut bytes = [0; Self::SIZE];
+		bytes[..RawHeader::BASE_SIZE].copy_from_slice(&self.header);
+		bytes[RawHeader::BASE_SIZE..Self::SIZE - 4].copy_from_slice(&self.archive_size.to_le_bytes());
+
+		let crc = crc32(&bytes[..Self::SIZE - 4]);
+		bytes[Self::SIZE - 4..].copy_from_slice(&crc.to_le_bytes());
+		bytes
+	}
+
+	/// Decodes a footer, as found in the last [`RawFooter::SIZE`] bytes of an archive. Fails with [`Error::Checksum`] if the CRC doesn't match
+	/// ```
+	/// use vach_core::{footer::RawFooter, header::RawHeader};
+	///
+	/// let header = RawHeader::default().encode()[..RawHeader::BASE_SIZE].try_into().unwrap();
+	/// let mut bytes = RawFooter { header, archive_size: 1024 }.encode();
+	/// assert_eq!(RawFooter::decode(&bytes).unwrap().archive_size, 1024);
+	///
+	/// bytes[0] ^= 1;
+	/// assert!(RawFooter::decode(&bytes).is_err());
+	/// ```
+	pub fn decode(bytes: &[u8; Self::SIZE]) -> Result<RawFooter, Error> {
+		if crc32(&bytes[..Self::SIZE - 4]) != le::read_u32(bytes, Self::SIZE - 4) {
+			return Err(Error::Checksum);
+		}
+
+		let mut header = [0; RawHeader::BASE_SIZE];
+		header.copy_from_slice(&bytes[..RawHeader::BASE_SIZE]);
+
+		Ok(RawFooter {
+			header,
+			archive_size: le::read_u64(bytes, RawHeader::BASE_SIZE),
+		})
+	}
+}
+
+/// CRC-32 with the IEEE polynomial, as used by zip and PNG
+pub fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+
+	for byte in bytes {
+		crc ^= *byte as u32;
+		for _ in 0..8 {
+			crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+		}
+	}
+
+	!crc
+}
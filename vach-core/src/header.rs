@@ -0,0 +1,215 @@
+//! The fixed size header at the start of every archive, optionally followed by tagged extension records
+
+use alloc::vec::Vec;
+use crate::{flags, le, Error, MAGIC_LENGTH};
+
+/// Format features an archive relies on, loaders lacking one must refuse the archive
+pub mod capabilities {
+	/// Some entries are compressed
+	pub const COMPRESSION: u32 = 0b0001;
+	/// Some entries are encrypted
+	pub const ENCRYPTION: u32 = 0b0010;
+	/// Some entries went through a custom pipeline
+	pub const PIPELINES: u32 = 0b0100;
+	/// The registry stores `ID`s relative to the previous entry's
+	pub const PREFIXED_IDS: u32 = 0b1000;
+	/// The header is followed by tagged extension records
+	pub const EXTENDED_HEADER: u32 = 0b1_0000;
+	/// The registry is compressed and stored elsewhere, as recorded in the `COMPRESSED_REGISTRY` extension
+	pub const COMPRESSED_REGISTRY: u32 = 0b10_0000;
+	/// Some entries were encrypted under rotated keys, their data is prefixed with the key's epoch
+	pub const KEY_EPOCHS: u32 = 0b100_0000;
+
+	/// Every capability known to this version of the format
+	pub const KNOWN: u32 =
+		COMPRESSION | ENCRYPTION | PIPELINES | PREFIXED_IDS | EXTENDED_HEADER | COMPRESSED_REGISTRY | KEY_EPOCHS;
+}
+
+/// Extension record tags
+pub mod extensions {
+	/// When the key used to encrypt and|or sign the archive expires, `u64` seconds since the UNIX epoch
+	pub const KEY_EXPIRY: u8 = 0;
+	/// The name of the archive holding leaves shared with other archives written in the same pass, UTF-8
+	pub const SHARED_ARCHIVE: u8 = 1;
+	/// A timestamp token over the archive's content, a `u16` length, the token, then zero padding up to the reserved size
+	pub const TIMESTAMP: u8 = 2;
+	/// The archive ends with a [`RawFooter`](crate::footer::RawFooter), empty
+	pub const FOOTER: u8 = 3;
+	/// 16 random bytes identifying the archive. Signed messages start with a domain built from it, the magic and the spec version
+	pub const BUILD_ID: u8 = 4;
+	/// Where the compressed registry lies and how to decompress it, see [`RegistryBlock`](crate::registry::RegistryBlock)
+	pub const COMPRESSED_REGISTRY: u8 = 5;
+}
+
+/// The header, as stored. Nothing is validated, loaders check the magic, versions and capabilities themselves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawHeader {
+	/// Identifies the source as an archive
+	pub magic: [u8; MAGIC_LENGTH],
+	/// Archive wide flags, see [`flags`]
+	pub flags: u32,
+	/// The spec version the archive was written with
+	pub arch_version: u16,
+	/// The oldest spec version able to load the archive
+	pub min_loader_version: u16,
+	/// See [`capabilities`]
+	pub capabilities: u32,
+	/// The number of entries in the registry
+	pub capacity: u16,
+	/// Tagged records following the fixed part of the header, only written with [`capabilities::EXTENDED_HEADER`]
+	pub extensions: Vec<(u8, Vec<u8>)>,
+}
+
+impl Default for RawHeader {
+	fn default() -> RawHeader {
+		RawHeader {
+			magic: *crate::DEFAULT_MAGIC,
+			flags: 0,
+			arch_version: crate::VERSION,
+			min_loader_version: crate::VERSION,
+			capabilities: 0,
+			capacity: 0,
+			extensions: Vec::new(),
+		}
+	}
+}
+
+impl RawHeader {
+	/// The size of the header without extensions
+	pub const BASE_SIZE: usize = Self::PREFIX_SIZE + Self::VERSION_SIZE + Self::CAPABILITIES_SIZE + Self::CAPACITY_SIZE;
+
+	// Data appears in this order
+	/// The size of a spec version
+	pub const VERSION_SIZE: usize = 2;
+	/// The size of the capabilities
+	pub const CAPABILITIES_SIZE: usize = 4;
+	/// The size of the capacity
+	pub const CAPACITY_SIZE: usize = 2;
+
+	/// The magic, flags and spec version. Their layout never changes, so any loader can tell whether it can read the rest
+	pub const PREFIX_SIZE: usize = MAGIC_LENGTH + flags::SIZE + Self::VERSION_SIZE;
+	/// The size of a header written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), the prefix followed by the capacity
+	pub const LEGACY_SIZE: usize = Self::PREFIX_SIZE + Self::CAPACITY_SIZE;
+	/// Where the capabilities lie, writers only know them once all leaves are processed
+	pub const CAPABILITIES_OFFSET: usize = Self::PREFIX_SIZE + Self::VERSION_SIZE;
+
+	/// Reads the magic, flags and spec version from the start of a header, see [`RawHeader::PREFIX_SIZE`]
+	pub fn decode_prefix(bytes: &[u8; Self::PREFIX_SIZE]) -> ([u8; MAGIC_LENGTH], u32, u16) {
+		let magic = bytes[0..MAGIC_LENGTH].try_into().unwrap();
+		let flags = le::read_u32(bytes, MAGIC_LENGTH);
+		let arch_version = le::read_u16(bytes, 9);
+
+		(magic, flags, arch_version)
+	}
+
+	/// Decodes the fixed part of a header, extensions are read separately using [`RawHeader::decode_extensions`]
+	pub fn decode(bytes: &[u8; Self::BASE_SIZE]) -> RawHeader {
+		let (magic, flags, arch_version) = RawHeader::decode_prefix(bytes[..Self::PREFIX_SIZE].try_into().unwrap());
+
+		RawHeader {
+			magic,
+			flags,
+			arch_version,
+			min_loader_version: le::read_u16(bytes, 11),
+			capabilities: le::read_u32(bytes, 13),
+			capacity: le::read_u16(bytes, 17),
+			extensions: Vec::new(),
+		}
+	}
+
+	/// Decodes a header written in [`LEGACY_VERSION`](crate::LEGACY_VERSION). It lacks the minimum loader version, taken to be it's spec version, and capabilities
+	pub fn decode_legacy(bytes: &[u8; Self::LEGACY_SIZE]) -> RawHeader {
+		let (magic, flags, arch_version) = RawHeader::decode_prefix(bytes[..Self::PREFIX_SIZE].try_into().unwrap());
+
+		RawHeader {
+			magic,
+			flags,
+			arch_version,
+			min_loader_version: arch_version,
+			capabilities: 0,
+			capacity: le::read_u16(bytes, Self::PREFIX_SIZE),
+			extensions: Vec::new(),
+		}
+	}
+
+	/// Whether the header was written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), whose registry entries are read using [`RawEntry::decode_legacy`](crate::registry::RawEntry::decode_legacy)
+	pub fn is_legacy(&self) -> bool {
+		self.arch_version == crate::LEGACY_VERSION
+	}
+
+	/// Whether the header is followed by extension records: a `u32` length, then that many bytes of records
+	pub fn is_extended(&self) -> bool {
+		self.capabilities & capabilities::EXTENDED_HEADER != 0
+	}
+
+	/// Decodes the extension records, each a tag, a `u16` length and the record's data. `block` excludes the leading `u32` length
+	pub fn decode_extensions(block: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+		let mut extensions = Vec::new();
+		let mut rest = block;
+
+		while rest.len() >= 3 {
+			let (tag, length, data) = (rest[0], le::read_u16(rest, 1) as usize, &rest[3..]);
+			if data.len() < length {
+				break;
+			}
+
+			extensions.push((tag, data[..length].to_vec()));
+			rest = &data[length..];
+		}
+
+		match rest.is_empty() {
+			true => Ok(extensions),
+			false => Err(Error::Truncated),
+		}
+	}
+
+	/// The data of the extension record with the given tag, unknown tags are ignored by loaders
+	pub fn extension(&self, tag: u8) -> Option<&[u8]> {
+		self.extensions.iter().find(|(t, _)| *t == tag).map(|(_, data)| data.as_slice())
+	}
+
+	/// The size of the header including extensions, the registry starts right after
+	pub fn size(&self) -> usize {
+		match (self.is_legacy(), self.is_extended()) {
+			(true, _) => Self::LEGACY_SIZE,
+			(false, true) => Self::BASE_SIZE + RawHeader::extensions_size(&self.extensions),
+			(false, false) => Self::BASE_SIZE,
+		}
+	}
+
+	/// The size of the given extension records, including the leading `u32` length
+	pub fn extensions_size(extensions: &[(u8, Vec<u8>)]) -> usize {
+		4 + extensions.iter().map(|(_, data)| 3 + data.len()).sum::<usize>()
+	}
+
+	/// Serializes the header, including extensions if [`capabilities::EXTENDED_HEADER`] is set. Legacy headers keep their layout
+	pub fn encode(&self) -> Vec<u8> {
+		let mut buffer = Vec::with_capacity(self.size());
+
+		buffer.extend_from_slice(&self.magic);
+		le::write_u32(&mut buffer, self.flags);
+		le::write_u16(&mut buffer, self.arch_version);
+
+		if self.is_legacy() {
+			le::write_u16(&mut buffer, self.capacity);
+			return buffer;
+		}
+
+		le::write_u16(&mut buffer, self.min_loader_version);
+		le::write_u32(&mut buffer, self.capabilities);
+		le::write_u16(&mut buffer, self.capacity);
+
+		if self.is_extended() {
+			let length = self.size() - Self::BASE_SIZE - 4;
+			le::write_u32(&mut buffer, length as u32);
+
+			for (tag, data) in &self.extensions {
+				buffer.push(*tag);
+				le::write_u16(&mut buffer, data.len() as u16);
+				buffer.extend_from_slice(data);
+			}
+		}
+
+		buffer
+	}
+}
@@ -0,0 +1,24 @@
+//! Every integer of the format is stored little-endian, whatever the platform. All reading and writing of integers goes through here,
+//! so a single place decides the byte order; see [`roundtrip_check`](crate::roundtrip_check) for the invariants checked on the current platform
+
+use alloc::vec::Vec;
+
+macro_rules! integer {
+	($read:ident, $write:ident, $int:ty) => {
+		#[doc = concat!("Reads a little-endian `", stringify!($int), "` starting at `at`. Panics if `bytes` is too short, callers index fixed size data")]
+		#[inline(always)]
+		pub fn $read(bytes: &[u8], at: usize) -> $int {
+			<$int>::from_le_bytes(bytes[at..at + core::mem::size_of::<$int>()].try_into().unwrap())
+		}
+
+		#[doc = concat!("Appends `value` to `buffer` as a little-endian `", stringify!($int), "`")]
+		#[inline(always)]
+		pub fn $write(buffer: &mut Vec<u8>, value: $int) {
+			buffer.extend_from_slice(&value.to_le_bytes());
+		}
+	};
+}
+
+integer!(read_u16, write_u16, u16);
+integer!(read_u32, write_u32, u32);
+integer!(read_u64, write_u64, u64);
@@ -0,0 +1,150 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![deny(missing_docs)]
+
+/*!
+#### The `vach` archive format, without any IO
+
+Encodes and decodes the header and registry of `vach` archives from plain byte slices, with no dependencies and no `std`.
+Meant for loaders that bring their own IO: WASM, embedded targets or bindings for other languages.
+Reading, decompressing, decrypting and verifying leaves is left to [`vach`](https://docs.rs/vach), which re-exports this crate as `vach::format`.
+
+> Check out the `vach` spec at **[spec.txt](https://github.com/zeskeertwee/vach/blob/main/spec/main.txt)**.
+
+```
+use vach_core::{header::RawHeader, registry::RawEntry};
+
+let header = RawHeader { capacity: 1, ..RawHeader::default() };
+let entry = RawEntry { id: "hello".into(), location: 64, offset: 5, decoded_size: 5, ..RawEntry::default() };
+
+let mut bytes = header.encode();
+bytes.extend_from_slice(&entry.encode(None, false).unwrap());
+
+let parsed = RawHeader::decode(&bytes[..RawHeader::BASE_SIZE].try_into().unwrap());
+assert_eq!(parsed.capacity, 1);
+
+let (parsed, used) = RawEntry::decode(&bytes[RawHeader::BASE_SIZE..], None).unwrap();
+assert_eq!((parsed.id.as_str(), used), ("hello", RawEntry::MIN_SIZE + 5));
+```
+*/
+
+extern crate alloc;
+
+mod check;
+mod error;
+pub mod flags;
+pub mod footer;
+pub mod header;
+pub mod le;
+pub mod registry;
+
+pub use check::roundtrip_check;
+pub use error::Error;
+
+use alloc::vec::Vec;
+use header::RawHeader;
+use registry::RawEntry;
+
+/// Current [`vach`](crate) spec version. increments by ten with every spec change
+pub const VERSION: u16 = 40;
+
+/// The spec version before [`VERSION`], archives written in it remain readable. Their header ends with the capacity,
+/// without a minimum loader version, capabilities or extensions, and their entries lack the decoded size. See [`RawHeader::decode_legacy`]
+pub const LEGACY_VERSION: u16 = 30;
+
+/// Size of a signature
+pub const SIGNATURE_LENGTH: usize = 64;
+
+/// Maximum size for any ID
+pub const MAX_ID_LENGTH: usize = 65535; // u16::MAX
+
+/// The standard size of any MAGIC entry in bytes
+pub const MAGIC_LENGTH: usize = 5;
+
+/// The default MAGIC used by `vach`
+pub const DEFAULT_MAGIC: &[u8; crate::MAGIC_LENGTH] = b"VfACH";
+
+/// Parses the header at the start of `bytes`, including extension records. Returns the header and it's size, the registry starts right after.
+/// Never panics, malformed input fails with an [`Error`], so it's safe to fuzz and to run over untrusted uploads.
+/// The magic, versions and capabilities are returned as is, for the caller to check
+/// ```
+/// use vach_core::{parse_header, header::RawHeader};
+///
+/// let bytes = RawHeader::default().encode();
+/// let (header, size) = parse_header(&bytes).unwrap();
+///
+/// assert_eq!((header, size), (RawHeader::default(), RawHeader::BASE_SIZE));
+/// assert!(parse_header(&bytes[..4]).is_err());
+/// ```
+pub fn parse_header(bytes: &[u8]) -> Result<(RawHeader, usize), Error> {
+	let prefix = bytes.get(..RawHeader::PREFIX_SIZE).ok_or(Error::Truncated)?;
+	let (_, _, arch_version) = RawHeader::decode_prefix(prefix.try_into().unwrap());
+
+	if arch_version == LEGACY_VERSION {
+		let base = bytes.get(..RawHeader::LEGACY_SIZE).ok_or(Error::Truncated)?;
+		return Ok((RawHeader::decode_legacy(base.try_into().unwrap()), RawHeader::LEGACY_SIZE));
+	}
+
+	let base = bytes.get(..RawHeader::BASE_SIZE).ok_or(Error::Truncated)?;
+	let mut header = RawHeader::decode(base.try_into().unwrap());
+
+	if header.is_extended() {
+		let rest = &bytes[RawHeader::BASE_SIZE..];
+		let (length, rest) = rest.split_first_chunk::<4>().ok_or(Error::Truncated)?;
+
+		let block = rest.get(..le::read_u32(length, 0) as usize).ok_or(Error::Truncated)?;
+		header.extensions = RawHeader::decode_extensions(block)?;
+	}
+
+	let size = header.size();
+	Ok((header, size))
+}
+
+/// Parses `capacity` registry entries from the start of `bytes`, as found right after the header, or decompressed from the block described by
+/// a [`RegistryBlock`](registry::RegistryBlock) in archives with a compressed registry. `prefixed` is whether the
+/// registry stores prefixed `ID`s, as recorded in the header's capabilities. Never panics, see [`parse_header`]
+/// ```
+/// use vach_core::{parse_registry, registry::RawEntry};
+///
+/// let entry = RawEntry { id: "hello".into(), ..RawEntry::default() };
+/// let bytes = entry.encode(None, false).unwrap();
+///
+/// assert_eq!(parse_registry(&bytes, 1, false).unwrap(), vec![entry]);
+/// assert!(parse_registry(&bytes, 2, false).is_err());
+/// ```
+pub fn parse_registry(mut bytes: &[u8], capacity: u16, prefixed: bool) -> Result<Vec<RawEntry>, Error> {
+	// A hostile capacity can't force allocations larger than the input
+	let mut entries: Vec<RawEntry> = Vec::with_capacity((capacity as usize).min(bytes.len() / RawEntry::MIN_SIZE));
+
+	for _ in 0..capacity {
+		let previous = prefixed.then(|| entries.last().map_or("", |e| e.id.as_str()));
+		let (entry, used) = RawEntry::decode(bytes, previous)?;
+
+		entries.push(entry);
+		bytes = &bytes[used..];
+	}
+
+	Ok(entries)
+}
+
+/// Parses `capacity` registry entries from the start of `bytes`, as written in [`LEGACY_VERSION`], see [`RawEntry::decode_legacy`]
+/// ```
+/// use vach_core::{parse_legacy_registry, registry::RawEntry};
+///
+/// let entry = RawEntry { id: "hello".into(), offset: 5, decoded_size: 5, ..RawEntry::default() };
+/// let bytes = entry.encode_legacy(false).unwrap();
+///
+/// assert_eq!(parse_legacy_registry(&bytes, 1).unwrap(), vec![entry]);
+/// assert!(parse_legacy_registry(&bytes, 2).is_err());
+/// ```
+pub fn parse_legacy_registry(mut bytes: &[u8], capacity: u16) -> Result<Vec<RawEntry>, Error> {
+	let mut entries: Vec<RawEntry> = Vec::with_capacity((capacity as usize).min(bytes.len() / RawEntry::LEGACY_MIN_SIZE));
+
+	for _ in 0..capacity {
+		let (entry, used) = RawEntry::decode_legacy(bytes)?;
+
+		entries.push(entry);
+		bytes = &bytes[used..];
+	}
+
+	Ok(entries)
+}
@@ -0,0 +1,254 @@
+//! The registry, one entry per leaf describing where its data lies and how it was processed
+
+use alloc::{string::String, vec::Vec};
+use crate::{flags, le, Error, MAX_ID_LENGTH, SIGNATURE_LENGTH};
+
+/// A registry entry, as stored. Flags are kept as raw bits, see [`flags`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawEntry {
+	/// The `ID` the entry is looked up by
+	pub id: String,
+	/// The entry's flags, see [`flags`]
+	pub flags: u32,
+	/// The content version of the entry
+	pub content_version: u8,
+	/// The location of the entry's data, as an offset of bytes from the beginning of the archive
+	pub location: u64,
+	/// The size of the entry's data in bytes, as stored
+	pub offset: u64,
+	/// The size of the entry's data in bytes once decrypted and decompressed
+	pub decoded_size: u64,
+	/// The signature over the entry's data and registry entry, only stored when [`flags::SIGNED_FLAG`] is set
+	pub signature: Option<[u8; SIGNATURE_LENGTH]>,
+}
+
+impl RawEntry {
+	/// The size of the fixed part of an entry: flags, content version, location, offset, decoded size and `ID` length
+	pub const MIN_SIZE: usize = flags::SIZE + 27;
+	/// The size of the fixed part of an entry written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), which lacks the decoded size
+	pub const LEGACY_MIN_SIZE: usize = flags::SIZE + 19;
+
+	/// The number of bytes following the fixed part of an entry: the signature, the shared prefix length and the `ID`.
+	/// Lets loaders read an entry from a stream in two reads, `prefixed` is whether the registry has prefixed `ID`s
+	pub fn tail_size(fixed: &[u8; Self::MIN_SIZE], prefixed: bool) -> usize {
+		let flags = le::read_u32(fixed, 0);
+		let id_length = le::read_u16(fixed, 29) as usize;
+
+		let signature = if flags & flags::SIGNED_FLAG != 0 { SIGNATURE_LENGTH } else { 0 };
+		signature + if prefixed { 2 } else { 0 } + id_length
+	}
+
+	/// The number of bytes following the fixed part of an entry written in [`LEGACY_VERSION`](crate::LEGACY_VERSION): the signature and the `ID`
+	pub fn legacy_tail_size(fixed: &[u8; Self::LEGACY_MIN_SIZE]) -> usize {
+		let flags = le::read_u32(fixed, 0);
+		let id_length = le::read_u16(fixed, 21) as usize;
+
+		let signature = if flags & flags::SIGNED_FLAG != 0 { SIGNATURE_LENGTH } else { 0 };
+		signature + id_length
+	}
+
+	/// Decodes an entry from the start of `bytes`, returning the entry and the number of bytes it took up.
+	/// In registries with prefixed `ID`s, `previous` is the `ID` of the entry before this one, see [`RawEntry::encode`]
+	pub fn decode(bytes: &[u8], previous: Option<&str>) -> Result<(RawEntry, usize), Error> {
+		let fixed: &[u8; Self::MIN_SIZE] = bytes.get(..Self::MIN_SIZE).ok_or(Error::Truncated)?.try_into().unwrap();
+		let size = Self::MIN_SIZE + RawEntry::tail_size(fixed, previous.is_some());
+		let mut tail = bytes.get(Self::MIN_SIZE..size).ok_or(Error::Truncated)?;
+
+		let flags = le::read_u32(fixed, 0);
+
+		let signature = match flags & flags::SIGNED_FLAG != 0 {
+			true => {
+				let (signature, rest) = tail.split_at(SIGNATURE_LENGTH);
+				tail = rest;
+				Some(signature.try_into().unwrap())
+			},
+			false => None,
+		};
+
+		// Prefixed IDs start with part of the previous ID
+		let mut id = match previous {
+			Some(previous) => {
+				let shared = le::read_u16(tail, 0) as usize;
+				tail = &tail[2..];
+
+				previous.as_bytes().get(..shared).ok_or(Error::InvalidPrefix(shared))?.to_vec()
+			},
+			None => Vec::new(),
+		};
+
+		id.extend_from_slice(tail);
+
+		let entry = RawEntry {
+			id: String::from_utf8(id).map_err(|_| Error::InvalidId)?,
+			flags,
+			content_version: fixed[4],
+			location: le::read_u64(fixed, 5),
+			offset: le::read_u64(fixed, 13),
+			decoded_size: le::read_u64(fixed, 21),
+			signature,
+		};
+
+		Ok((entry, size))
+	}
+
+	/// Serializes the entry. With `previous`, only the part of the `ID` that differs from it is stored, entries sorted by `ID` share the longest prefixes.
+	/// Signatures are computed over the entry with `skip_signature` set
+	pub fn encode(&self, previous: Option<&str>, skip_signature: bool) -> Result<Vec<u8>, Error> {
+		// Make sure the ID is not too big or else it will break the archive
+		if self.id.len() >= MAX_ID_LENGTH {
+			return Err(Error::IdTooLong(self.id.clone()));
+		};
+
+		let shared = previous.map(|previous| shared_prefix(previous, &self.id));
+		let suffix = &self.id[shared.unwrap_or(0)..];
+
+		let mut buffer = Vec::with_capacity(Self::MIN_SIZE + SIGNATURE_LENGTH + 2 + suffix.len());
+
+		le::write_u32(&mut buffer, self.flags);
+		buffer.push(self.content_version);
+		le::write_u64(&mut buffer, self.location);
+		le::write_u64(&mut buffer, self.offset);
+		le::write_u64(&mut buffer, self.decoded_size);
+		le::write_u16(&mut buffer, suffix.len() as u16);
+
+		// Only write signature if one exists
+		if let (Some(signature), false) = (&self.signature, skip_signature) {
+			buffer.extend_from_slice(signature);
+		};
+
+		if let Some(shared) = shared {
+			le::write_u16(&mut buffer, shared as u16);
+		}
+
+		buffer.extend_from_slice(suffix.as_bytes());
+
+		Ok(buffer)
+	}
+
+	/// Decodes an entry written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), from the start of `bytes`. Such entries are never prefixed, and lack the decoded size.
+	/// It's taken to be the stored size for entries neither compressed nor encrypted, and zero, for unknown, otherwise
+	pub fn decode_legacy(bytes: &[u8]) -> Result<(RawEntry, usize), Error> {
+		let fixed: &[u8; Self::LEGACY_MIN_SIZE] =
+			bytes.get(..Self::LEGACY_MIN_SIZE).ok_or(Error::Truncated)?.try_into().unwrap();
+		let size = Self::LEGACY_MIN_SIZE + RawEntry::legacy_tail_size(fixed);
+		let mut tail = bytes.get(Self::LEGACY_MIN_SIZE..size).ok_or(Error::Truncated)?;
+
+		let flags = le::read_u32(fixed, 0);
+		let offset = le::read_u64(fixed, 13);
+
+		let signature = match flags & flags::SIGNED_FLAG != 0 {
+			true => {
+				let (signature, rest) = tail.split_at(SIGNATURE_LENGTH);
+				tail = rest;
+				Some(signature.try_into().unwrap())
+			},
+			false => None,
+		};
+
+		let entry = RawEntry {
+			id: String::from_utf8(tail.to_vec()).map_err(|_| Error::InvalidId)?,
+			flags,
+			content_version: fixed[4],
+			location: le::read_u64(fixed, 5),
+			offset,
+			decoded_size: match flags & (flags::COMPRESSED_FLAG | flags::ENCRYPTED_FLAG) {
+				0 => offset,
+				_ => 0,
+			},
+			signature,
+		};
+
+		Ok((entry, size))
+	}
+
+	/// Serializes the entry as written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), leaving out the decoded size.
+	/// Signatures in archives of that version are computed over the entry with `skip_signature` set
+	pub fn encode_legacy(&self, skip_signature: bool) -> Result<Vec<u8>, Error> {
+		if self.id.len() >= MAX_ID_LENGTH {
+			return Err(Error::IdTooLong(self.id.clone()));
+		};
+
+		let mut buffer = Vec::with_capacity(Self::LEGACY_MIN_SIZE + SIGNATURE_LENGTH + self.id.len());
+
+		le::write_u32(&mut buffer, self.flags);
+		buffer.push(self.content_version);
+		le::write_u64(&mut buffer, self.location);
+		le::write_u64(&mut buffer, self.offset);
+		le::write_u16(&mut buffer, self.id.len() as u16);
+
+		if let (Some(signature), false) = (&self.signature, skip_signature) {
+			buffer.extend_from_slice(signature);
+		};
+
+		buffer.extend_from_slice(self.id.as_bytes());
+		Ok(buffer)
+	}
+
+	/// The number of bytes an entry takes up in the registry, see [`RawEntry::encode`]
+	pub fn encoded_size(id: &str, previous: Option<&str>, signed: bool) -> usize {
+		let signature = if signed { SIGNATURE_LENGTH } else { 0 };
+
+		match previous {
+			Some(previous) => Self::MIN_SIZE + 2 + id.len() - shared_prefix(previous, id) + signature,
+			None => Self::MIN_SIZE + id.len() + signature,
+		}
+	}
+}
+
+/// Where a compressed registry lies and how to decompress it, the data of the `COMPRESSED_REGISTRY` header extension.
+/// The decompressed block holds the entries exactly as an uncompressed registry would, with leaf data starting right after the header
+/// ```
+/// use vach_core::registry::RegistryBlock;
+///
+/// let block = RegistryBlock { codec: 1 << 30, location: 19, size: 100, decoded_size: 400 };
+/// assert_eq!(RegistryBlock::decode(&block.encode()), Ok(block));
+/// assert!(RegistryBlock::decode(&[0; 4]).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegistryBlock {
+	/// The flag marking entries compressed with the codec the registry was compressed with, see [`flags`]
+	pub codec: u32,
+	/// Where the compressed registry starts
+	pub location: u64,
+	/// The size of the compressed registry
+	pub size: u64,
+	/// The size of the registry once decompressed
+	pub decoded_size: u64,
+}
+
+impl RegistryBlock {
+	/// The size of the extension's data
+	pub const SIZE: usize = 4 + 8 + 8 + 8;
+
+	/// Encodes the block's description, as stored in the header extension
+	pub fn encode(&self) -> Vec<u8> {
+		let mut buffer = Vec::with_capacity(Self::SIZE);
+		le::write_u32(&mut buffer, self.codec);
+		le::write_u64(&mut buffer, self.location);
+		le::write_u64(&mut buffer, self.size);
+		le::write_u64(&mut buffer, self.decoded_size);
+		buffer
+	}
+
+	/// Decodes the block's description from the header extension's data. Fails with [`Error::Truncated`] if it's the wrong size
+	pub fn decode(bytes: &[u8]) -> Result<RegistryBlock, Error> {
+		if bytes.len() != Self::SIZE {
+			return Err(Error::Truncated);
+		}
+
+		Ok(RegistryBlock {
+			codec: le::read_u32(bytes, 0),
+			location: le::read_u64(bytes, 4),
+			size: le::read_u64(bytes, 12),
+			decoded_size: le::read_u64(bytes, 20),
+		})
+	}
+}
+
+/// The length in bytes of the common prefix of two `ID`s, ending on a character boundary
+fn shared_prefix(a: &str, b: &str) -> usize {
+	a.char_indices()
+		.zip(b.chars())
+		.find(|((_, a), b)| a != b)
+		.map_or(a.len().min(b.len()), |((idx, _), _)| idx)
+}
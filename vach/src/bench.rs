@@ -0,0 +1,312 @@
+use std::{
+	fmt,
+	io::Cursor,
+	time::{Duration, Instant},
+};
+
+use crate::{
+	archive::{Archive, ArchiveConfig},
+	builder::{Builder, BuilderConfig, Leaf},
+	global::{error::*, metrics::Metrics},
+};
+
+#[cfg(feature = "compression")]
+use crate::builder::CompressMode;
+
+/// A standardized shape of archive, filled with synthetic data generated the same way on every run and every machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Workload {
+	/// `16384` leaves of `1 KiB` each, mostly text. Stresses per leaf overhead: the registry, lookups and scheduling
+	ManySmallLeaves,
+	/// `4` leaves of `16 MiB` each, half text and half noise. Stresses raw throughput of compression and IO
+	FewHugeLeaves,
+	/// `256` leaves of `64 KiB` each, encrypted and signed. Stresses the `crypto` feature, which it needs
+	Encrypted,
+	/// `1024` leaves from `256 B` to `256 KiB`, mixing text and noise, a quarter of them encrypted and signed with the `crypto` feature.
+	/// Closest to a real asset archive
+	Mixed,
+}
+
+impl Workload {
+	/// Every workload, in the order [`run_all`] runs them
+	pub const ALL: [Workload; 4] = [
+		Workload::ManySmallLeaves,
+		Workload::FewHugeLeaves,
+		Workload::Encrypted,
+		Workload::Mixed,
+	];
+
+	/// A stable name, eg: to key results by
+	pub fn name(&self) -> &'static str {
+		match self {
+			Workload::ManySmallLeaves => "many-small-leaves",
+			Workload::FewHugeLeaves => "few-huge-leaves",
+			Workload::Encrypted => "encrypted",
+			Workload::Mixed => "mixed",
+		}
+	}
+
+	/// The leaves of the workload at the given scale, as `(id, data, secure)`. Secure leaves are encrypted and signed
+	fn generate(&self, scale: f64) -> Vec<(String, Vec<u8>, bool)> {
+		let count = |count: usize| ((count as f64 * scale).round() as usize).max(1);
+		let mut rng = XorShift(0x9E37_79B9_7F4A_7C15 ^ *self as u64);
+
+		match self {
+			Workload::ManySmallLeaves => (0..count(16384))
+				.map(|idx| (format!("small/{}", idx), rng.text(1024), false))
+				.collect(),
+			Workload::FewHugeLeaves => {
+				let size = ((16 << 20) as f64 * scale).max(1.0) as usize;
+
+				(0..4)
+					.map(|idx| {
+						let mut data = rng.text(size / 2);
+						data.extend(rng.noise(size - size / 2));
+						(format!("huge/{}", idx), data, false)
+					})
+					.collect()
+			},
+			Workload::Encrypted => (0..count(256))
+				.map(|idx| (format!("secure/{}", idx), rng.text(64 * 1024), true))
+				.collect(),
+			Workload::Mixed => (0..count(1024))
+				.map(|idx| {
+					// Sizes spread evenly between 2^8 and 2^18 bytes on a log scale
+					let size = 1usize << (8 + rng.next() % 11);
+					let data = match idx % 3 {
+						0 => rng.noise(size),
+						_ => rng.text(size),
+					};
+
+					(format!("mixed/{}", idx), data, idx % 4 == 0)
+				})
+				.collect(),
+		}
+	}
+}
+
+impl fmt::Display for Workload {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// How workloads are run, see [`run`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchConfig {
+	/// Scales the amount of data of each workload, `1.0` generates the sizes documented on [`Workload`], eg: use `0.01` in debug builds or tests
+	pub scale: f64,
+	/// How many times each workload is packed and fetched, the fastest run is kept to filter out noise. At least `1`
+	pub iterations: usize,
+}
+
+impl Default for BenchConfig {
+	fn default() -> BenchConfig {
+		BenchConfig {
+			scale: 1.0,
+			iterations: 3,
+		}
+	}
+}
+
+impl BenchConfig {
+	/// Setter for the `scale` field
+	pub fn scale(mut self, scale: f64) -> BenchConfig {
+		self.scale = scale;
+		self
+	}
+
+	/// Setter for the `iterations` field
+	pub fn iterations(mut self, iterations: usize) -> BenchConfig {
+		self.iterations = iterations;
+		self
+	}
+}
+
+/// The measurements of one workload, see [`run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+	/// The workload measured
+	pub workload: Workload,
+	/// The number of leaves packed
+	pub leaves: usize,
+	/// Bytes of leaf data, as generated
+	pub bytes: u64,
+	/// The size of the packed archive
+	pub archive_size: u64,
+	/// The fastest time taken to pack every leaf into memory
+	pub pack: Duration,
+	/// The fastest time taken to open the archive from memory and fetch every leaf
+	pub fetch: Duration,
+}
+
+impl BenchResult {
+	/// Bytes of leaf data packed per second
+	pub fn pack_throughput(&self) -> f64 {
+		self.bytes as f64 / self.pack.as_secs_f64().max(f64::EPSILON)
+	}
+
+	/// Bytes of leaf data fetched per second
+	pub fn fetch_throughput(&self) -> f64 {
+		self.bytes as f64 / self.fetch.as_secs_f64().max(f64::EPSILON)
+	}
+
+	/// Whether packing or fetching is more than `tolerance` slower than in `baseline`, eg: `0.1` allows up to 10%.
+	/// Compare results of the same workload, at the same scale, from the same machine
+	pub fn regressed(&self, baseline: &BenchResult, tolerance: f64) -> bool {
+		let slower = |now: f64, before: f64| now < before * (1.0 - tolerance);
+		slower(self.pack_throughput(), baseline.pack_throughput()) || slower(self.fetch_throughput(), baseline.fetch_throughput())
+	}
+
+	/// The result as [`Metrics`] of a `bench:<workload>` run, for a [`Reporter`](crate::metrics::Reporter) to trend over time.
+	/// The pack and fetch times and throughputs are kept in `Metrics::extra`
+	pub fn metrics(&self) -> Metrics {
+		let mut metrics = Metrics::new(format!("bench:{}", self.workload));
+		metrics.elapsed = self.pack + self.fetch;
+		metrics.bytes_in = self.bytes;
+		metrics.bytes_out = self.archive_size;
+		metrics.entries = self.leaves;
+
+		metrics.extra.insert("pack".to_string(), self.pack.as_secs_f64());
+		metrics.extra.insert("fetch".to_string(), self.fetch.as_secs_f64());
+		metrics.extra.insert("pack_throughput".to_string(), self.pack_throughput());
+		metrics.extra.insert("fetch_throughput".to_string(), self.fetch_throughput());
+		metrics
+	}
+}
+
+impl fmt::Display for BenchResult {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mib = |throughput: f64| throughput / (1024.0 * 1024.0);
+
+		write!(
+			f,
+			"[{}] leaves: {}, data: {}B, archive: {}B, pack: {:.1} MiB/s, fetch: {:.1} MiB/s",
+			self.workload,
+			self.leaves,
+			self.bytes,
+			self.archive_size,
+			mib(self.pack_throughput()),
+			mib(self.fetch_throughput())
+		)
+	}
+}
+
+/// Packs a workload into memory then fetches every leaf, `BenchConfig::iterations` times, keeping the fastest times. Data is generated before timing starts.
+/// Leaves use `CompressMode::Detect` with the `compression` feature. [`Workload::Encrypted`] fails with [`InternalError::MissingFeatureError`] without the `crypto` feature
+/// ```
+/// use vach::bench::{run, BenchConfig, Workload};
+///
+/// let result = run(Workload::ManySmallLeaves, &BenchConfig::default().scale(0.01).iterations(1)).unwrap();
+/// assert_eq!(result.leaves, 164);
+/// assert!(result.pack_throughput() > 0.0);
+/// ```
+pub fn run(workload: Workload, config: &BenchConfig) -> InternalResult<BenchResult> {
+	#[cfg(not(feature = "crypto"))]
+	if workload == Workload::Encrypted {
+		return Err(InternalError::MissingFeatureError("crypto"));
+	}
+
+	let leaves = workload.generate(config.scale);
+	let bytes = leaves.iter().map(|(_, data, _)| data.len() as u64).sum();
+
+	#[cfg(feature = "crypto")]
+	let keypair = crate::crypto_utils::gen_keypair();
+
+	let mut result = BenchResult {
+		workload,
+		leaves: leaves.len(),
+		bytes,
+		archive_size: 0,
+		pack: Duration::MAX,
+		fetch: Duration::MAX,
+	};
+
+	for _ in 0..config.iterations.max(1) {
+		let mut builder = Builder::new();
+		for (id, data, _secure) in &leaves {
+			let leaf = Leaf::new(data.as_slice()).id(id);
+
+			#[cfg(feature = "compression")]
+			let leaf = leaf.compress(CompressMode::Detect);
+			#[cfg(feature = "crypto")]
+			let leaf = leaf.encrypt(*_secure).sign(*_secure);
+
+			builder.add_leaf(leaf)?;
+		}
+
+		let builder_config = BuilderConfig::default();
+		#[cfg(feature = "crypto")]
+		let builder_config = builder_config.keypair(keypair.clone());
+
+		let mut target = Cursor::new(Vec::with_capacity(bytes as usize));
+		let started = Instant::now();
+		builder.dump(&mut target, &builder_config)?;
+		result.pack = result.pack.min(started.elapsed());
+
+		let archive_config = ArchiveConfig::default();
+		#[cfg(feature = "crypto")]
+		let archive_config = archive_config.key(keypair.verifying_key());
+
+		let source = target.into_inner();
+		result.archive_size = source.len() as u64;
+
+		let started = Instant::now();
+		let archive = Archive::with_config(Cursor::new(source), &archive_config)?;
+		for (id, _, _) in &leaves {
+			archive.fetch(id)?;
+		}
+		result.fetch = result.fetch.min(started.elapsed());
+	}
+
+	Ok(result)
+}
+
+/// Runs every workload the enabled features support, in the order of [`Workload::ALL`]. [`Workload::Encrypted`] is skipped without the `crypto` feature
+pub fn run_all(config: &BenchConfig) -> InternalResult<Vec<BenchResult>> {
+	Workload::ALL
+		.iter()
+		.filter(|workload| cfg!(feature = "crypto") || **workload != Workload::Encrypted)
+		.map(|workload| run(*workload, config))
+		.collect()
+}
+
+/// A xorshift64 generator, so synthetic data is the same on every machine without pulling `rand`
+struct XorShift(u64);
+
+impl XorShift {
+	const WORDS: [&'static str; 16] = [
+		"texture", "mesh", "sound", "level", "shader", "player", "enemy", "vertex", "normal", "the", "of", "and", "dialogue",
+		"quest", "frame", "bone",
+	];
+
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	/// Compressible data, words drawn from a small vocabulary
+	fn text(&mut self, size: usize) -> Vec<u8> {
+		let mut data = Vec::with_capacity(size + 16);
+		while data.len() < size {
+			data.extend_from_slice(Self::WORDS[(self.next() % 16) as usize].as_bytes());
+			data.push(b' ');
+		}
+
+		data.truncate(size);
+		data
+	}
+
+	/// Incompressible data
+	fn noise(&mut self, size: usize) -> Vec<u8> {
+		let mut data = Vec::with_capacity(size + 8);
+		while data.len() < size {
+			data.extend_from_slice(&self.next().to_le_bytes());
+		}
+
+		data.truncate(size);
+		data
+	}
+}
@@ -9,6 +9,65 @@ use aes_gcm::{Aes256Gcm, Nonce, KeyInit};
 pub use ed25519_dalek::{SigningKey, VerifyingKey, Signature};
 
 use crate::prelude::{InternalResult, InternalError};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+
+/// Produces the signatures written into signed registry entries, implement this to keep secret keys out of process, eg in an `ssh-agent` or a hardware token.
+/// Signatures must be plain `ed25519` signatures over the given message, verifiable by [`LeafSigner::verifying_key`].
+pub trait LeafSigner: Send + Sync {
+	/// The public half of the signing key, used to verify signatures and derive the encryption key
+	fn verifying_key(&self) -> VerifyingKey;
+	/// Signs a leaf's data followed by its registry entry
+	fn sign_leaf(&self, message: &[u8]) -> InternalResult<Signature>;
+}
+
+impl LeafSigner for SigningKey {
+	fn verifying_key(&self) -> VerifyingKey {
+		SigningKey::verifying_key(self)
+	}
+
+	fn sign_leaf(&self, message: &[u8]) -> InternalResult<Signature> {
+		Ok(ed25519_dalek::Signer::sign(self, message))
+	}
+}
+
+/// A 256 bit key used only to encrypt leaves, so reading encrypted leaves doesn't require the key that verifies them and vice versa.
+/// Without one, the encryption key is derived from the public key of the signing keypair. See `BuilderConfig::encryption_key` and `ArchiveConfig::decryption_key`
+/// ```
+/// use vach::crypto::EncryptionKey;
+///
+/// let key = EncryptionKey::generate();
+/// assert_eq!(EncryptionKey::from_bytes(key.to_bytes()), key);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+	/// Wraps raw key bytes, eg: loaded from a secret store
+	pub fn from_bytes(bytes: [u8; 32]) -> EncryptionKey {
+		EncryptionKey(bytes)
+	}
+
+	/// Generates a new random key using `OsRng`
+	pub fn generate() -> EncryptionKey {
+		use rand::RngCore;
+
+		let mut bytes = [0; 32];
+		rand::rngs::OsRng.fill_bytes(&mut bytes);
+		EncryptionKey(bytes)
+	}
+
+	/// The raw key bytes, keep these secret
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+}
+
+// The key itself never shows up in logs
+impl fmt::Debug for EncryptionKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("EncryptionKey(<redacted>)")
+	}
+}
 
 /// Encryption - Decryption, A convenient wrapper around aes encryption and decryption
 pub(crate) struct Encryptor {
@@ -24,15 +83,16 @@ impl fmt::Debug for Encryptor {
 
 impl Encryptor {
 	pub(crate) fn new(vk: &VerifyingKey, magic: [u8; crate::MAGIC_LENGTH]) -> Encryptor {
-		// Build encryption key
-		let bytes = &vk.to_bytes();
+		Encryptor::from_key(&vk.to_bytes(), magic)
+	}
 
+	pub(crate) fn from_key(bytes: &[u8; 32], magic: [u8; crate::MAGIC_LENGTH]) -> Encryptor {
 		// Build Nonce
 		let mut v = [178, 5, 239, 228, 165, 44, 169, 0, 0, 0, 0, 0];
-		(&mut v[7..12]).copy_from_slice(&magic);
+		v[7..12].copy_from_slice(&magic);
 
 		Encryptor {
-			cipher: Aes256Gcm::new_from_slice(bytes).unwrap(),
+			cipher: Aes256Gcm::new(&(*bytes).into()),
 			nonce: *Nonce::from_slice(v.as_slice()),
 		}
 	}
@@ -50,3 +110,25 @@ impl Encryptor {
 			.map_err(InternalError::CryptoError)
 	}
 }
+
+/// Strictly verifies a signature over a message made up of several contiguous parts, without concatenating them.
+/// Equivalent to `VerifyingKey::verify_strict` over the concatenated parts.
+pub(crate) fn verify_parts(vk: &VerifyingKey, signature: &Signature, parts: &[&[u8]]) -> bool {
+	// Mirror the malleability checks done by `verify_strict`, which the stream verifier skips
+	let weak_r = match CompressedEdwardsY(*signature.r_bytes()).decompress() {
+		Some(r) => r.is_small_order(),
+		None => true,
+	};
+
+	if weak_r || vk.is_weak() {
+		return false;
+	}
+
+	let mut verifier = match vk.verify_stream(signature) {
+		Ok(verifier) => verifier,
+		Err(_) => return false,
+	};
+
+	parts.iter().for_each(|part| verifier.update(part));
+	verifier.finalize_and_verify().is_ok()
+}
@@ -1,14 +1,18 @@
 #![cfg(feature = "compression")]
 #![cfg_attr(docsrs, doc(cfg(feature = "compression")))]
 
-use std::io::{self, Read, Write};
+use std::{
+	collections::HashSet,
+	io::{self, Read, Write},
+};
 
 use crate::prelude::Flags;
-use super::error::*;
+use super::{error::*, lint::entropy};
 
 use lz4_flex as lz4;
 use snap;
 use brotli;
+use flate2;
 
 #[derive(Debug)]
 /// Exported utility compressor used by `vach`
@@ -38,15 +42,22 @@ impl<T: Read> Compressor<T> {
 
 				Ok(())
 			},
-			CompressionAlgorithm::Brotli(quality) if quality < 12 && quality > 0 => {
-				let mut compressor = brotli::CompressorReader::new(&mut self.data, 4096, quality, 21u32);
+			CompressionAlgorithm::Brotli(params) => {
+				params.validate()?;
+
+				let mut compressor = brotli::CompressorReader::new(&mut self.data, 4096, params.quality, params.window);
+				io::copy(&mut compressor, output)?;
+
+				Ok(())
+			},
+			CompressionAlgorithm::Deflate(level) => {
+				algo.validate()?;
+
+				let mut compressor = flate2::read::GzEncoder::new(&mut self.data, flate2::Compression::new(level));
 				io::copy(&mut compressor, output)?;
 
 				Ok(())
 			},
-			CompressionAlgorithm::Brotli(_) => Err(InternalError::OtherError(
-				"Maximum Brotli compression level is 11 and minimum is 1".into(),
-			)),
 		}
 	}
 
@@ -62,25 +73,152 @@ impl<T: Read> Compressor<T> {
 				let mut rdr = snap::read::FrameDecoder::new(&mut self.data);
 				rdr.read_to_end(output).map_err(InternalError::IOError)
 			},
-			CompressionAlgorithm::Brotli(_) => {
-				let mut rdr = brotli::Decompressor::new(&mut self.data, 4096);
+			CompressionAlgorithm::Brotli(params) => {
+				// Larger windows reference data further back, read in larger chunks to match
+				let buffer_size = 1 << params.window.clamp(12, 16);
+				let mut rdr = brotli::Decompressor::new(&mut self.data, buffer_size);
+				rdr.read_to_end(output).map_err(InternalError::IOError)
+			},
+			CompressionAlgorithm::Deflate(_) => {
+				let mut rdr = flate2::read::GzDecoder::new(&mut self.data);
 				rdr.read_to_end(output).map_err(InternalError::IOError)
 			},
 		}
 	}
+
+	/// Like `Compressor::decompress`, but streams the decompressed data into any writer instead of collecting it.
+	/// Returns the number of bytes written
+	pub fn decompress_into(&mut self, algo: CompressionAlgorithm, output: &mut dyn Write) -> InternalResult<u64> {
+		let copied = match algo {
+			CompressionAlgorithm::LZ4 => io::copy(&mut lz4::frame::FrameDecoder::new(&mut self.data), output),
+			CompressionAlgorithm::Snappy => io::copy(&mut snap::read::FrameDecoder::new(&mut self.data), output),
+			CompressionAlgorithm::Brotli(params) => {
+				let buffer_size = 1 << params.window.clamp(12, 16);
+				io::copy(&mut brotli::Decompressor::new(&mut self.data, buffer_size), output)
+			},
+			CompressionAlgorithm::Deflate(_) => io::copy(&mut flate2::read::GzDecoder::new(&mut self.data), output),
+		};
+
+		copied.map_err(InternalError::IOError)
+	}
+
+	/// Wraps the compressed data in a reader decompressing it as it's read, for `Archive::fetch_reader`
+	pub(crate) fn into_decoder<'a>(self, algo: CompressionAlgorithm) -> Box<dyn Read + 'a>
+	where
+		T: 'a,
+	{
+		match algo {
+			CompressionAlgorithm::LZ4 => Box::new(lz4::frame::FrameDecoder::new(self.data)),
+			CompressionAlgorithm::Snappy => Box::new(snap::read::FrameDecoder::new(self.data)),
+			CompressionAlgorithm::Brotli(params) => {
+				let buffer_size = 1 << params.window.clamp(12, 16);
+				Box::new(brotli::Decompressor::new(self.data, buffer_size))
+			},
+			CompressionAlgorithm::Deflate(_) => Box::new(flate2::read::GzDecoder::new(self.data)),
+		}
+	}
 }
 
-/// Allows the user to specify which of three `Compression Algorithms` to use.
+/// Allows the user to specify which of four `Compression Algorithms` to use.
 #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum CompressionAlgorithm {
 	/// Uses [snappy](https://crates.io/crates/snap) for a well balanced compression experienced
 	Snappy,
 	/// Uses [LZ4](https://crates.io/crates/lz4_flex) for very fast decompression with average compression ratios
+	#[default]
 	LZ4,
 	/// Uses [brotli](https://crates.io/crates/brotli) for higher compression ratios but *much* slower compression speed
-	/// Allows one to specify the quality of the compression, from 1-11. (9 Recommended, 11 for extra compression)
-	Brotli(u32),
+	/// Allows one to specify the quality and window size of the compression, see [`BrotliParams`]
+	Brotli(BrotliParams),
+	/// Uses [deflate](https://crates.io/crates/flate2) wrapped in a gzip stream, so leaves can be passed through as is to HTTP clients accepting `Content-Encoding: gzip`.
+	/// Allows one to specify the level of compression, from 0-9
+	Deflate(u32),
+}
+
+impl CompressionAlgorithm {
+	/// Checks the algorithm's parameters are within range
+	pub(crate) fn validate(&self) -> InternalResult {
+		match self {
+			CompressionAlgorithm::Brotli(params) => params.validate(),
+			CompressionAlgorithm::Deflate(level) if *level > 9 => Err(InternalError::OtherError(
+				"Maximum Deflate compression level is 9 and minimum is 0".into(),
+			)),
+			_ => Ok(()),
+		}
+	}
+
+	/// Recovers the algorithm, and it's parameters where recorded, from an entry's flags. `None` if the entry isn't compressed
+	pub fn from_flags(flags: &Flags) -> Option<CompressionAlgorithm> {
+		if flags.contains(Flags::LZ4_COMPRESSED) {
+			Some(CompressionAlgorithm::LZ4)
+		} else if flags.contains(Flags::BROTLI_COMPRESSED) {
+			let mut params = BrotliParams::default();
+
+			// Entries written before the window was recorded used the default window
+			let window = (flags.bits() & Flags::BROTLI_WINDOW_MASK) >> 16;
+			if window != 0 {
+				params.window = window + 9;
+			}
+
+			Some(CompressionAlgorithm::Brotli(params))
+		} else if flags.contains(Flags::SNAPPY_COMPRESSED) {
+			Some(CompressionAlgorithm::Snappy)
+		} else if flags.contains(Flags::DEFLATE_COMPRESSED) {
+			Some(CompressionAlgorithm::Deflate(0))
+		} else {
+			None
+		}
+	}
+}
+
+/// Parameters for [brotli](https://crates.io/crates/brotli) compression.
+/// The quality only affects compression, the window is recorded in the entry's flags so decompression always matches the encoder.
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BrotliParams {
+	/// The quality of the compression, from 1-11. (9 Recommended, 11 for extra compression)
+	pub quality: u32,
+	/// The base 2 logarithm of the sliding window size, from 10-24. Larger windows find repetitions further apart but use more memory
+	pub window: u32,
+}
+
+impl Default for BrotliParams {
+	fn default() -> Self {
+		BrotliParams { quality: 8, window: 21 }
+	}
+}
+
+impl BrotliParams {
+	/// Brotli parameters with the given quality and the default window of `21`
+	pub fn new(quality: u32) -> BrotliParams {
+		BrotliParams {
+			quality,
+			..Default::default()
+		}
+	}
+
+	/// Setter for the `window` field
+	pub fn window(mut self, window: u32) -> Self {
+		self.window = window;
+		self
+	}
+
+	pub(crate) fn validate(&self) -> InternalResult {
+		if !(1..=11).contains(&self.quality) {
+			return Err(InternalError::OtherError(
+				"Maximum Brotli compression level is 11 and minimum is 1".into(),
+			));
+		}
+
+		if !(10..=24).contains(&self.window) {
+			return Err(InternalError::OtherError(
+				"Maximum Brotli window size is 24 and minimum is 10".into(),
+			));
+		}
+
+		Ok(())
+	}
 }
 
 impl std::fmt::Display for CompressionAlgorithm {
@@ -89,22 +227,103 @@ impl std::fmt::Display for CompressionAlgorithm {
 			CompressionAlgorithm::Snappy => write!(f, "Snappy"),
 			CompressionAlgorithm::LZ4 => write!(f, "LZ4"),
 			CompressionAlgorithm::Brotli(_) => write!(f, "Brotli"),
+			CompressionAlgorithm::Deflate(_) => write!(f, "Deflate"),
 		}
 	}
 }
 
-impl Default for CompressionAlgorithm {
-	fn default() -> CompressionAlgorithm {
-		CompressionAlgorithm::LZ4
-	}
-}
-
 impl From<CompressionAlgorithm> for u32 {
 	fn from(algo: CompressionAlgorithm) -> Self {
 		match algo {
 			CompressionAlgorithm::Snappy => Flags::SNAPPY_COMPRESSED,
 			CompressionAlgorithm::LZ4 => Flags::LZ4_COMPRESSED,
-			CompressionAlgorithm::Brotli(_) => Flags::BROTLI_COMPRESSED,
+			CompressionAlgorithm::Brotli(params) => {
+				Flags::BROTLI_COMPRESSED | ((params.window.saturating_sub(9) << 16) & Flags::BROTLI_WINDOW_MASK)
+			},
+			CompressionAlgorithm::Deflate(_) => Flags::DEFLATE_COMPRESSED,
 		}
 	}
 }
+
+/// Media and archive formats that are already compressed, trial compression is skipped for these by default
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+	"png", "jpg", "jpeg", "gif", "webp", "avif", "heic", "ktx2", "basis", "ogg", "opus", "mp3", "m4a", "aac", "flac", "mp4",
+	"m4v", "webm", "mkv", "mov", "avi", "zip", "gz", "xz", "bz2", "7z", "zst", "rar", "br", "lz4", "woff2",
+];
+
+/// Decides which leaves `CompressMode::Detect` stores as is without a trial compression.
+/// A leaf is skipped if its `ID` has a known compressed extension, or if a sample of its data looks like noise
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub struct DetectHeuristics {
+	/// Lowercase extensions, without the leading `.`, of formats that are already compressed
+	pub extensions: HashSet<String>,
+	/// How many bytes of a leaf are sampled for entropy, spread over its start, middle and end. `0` disables sampling
+	pub sample_size: usize,
+	/// Samples with more entropy than this, in bits per byte, are considered incompressible. Noise comes close to `8.0`
+	pub max_entropy: f64,
+}
+
+impl Default for DetectHeuristics {
+	fn default() -> Self {
+		DetectHeuristics {
+			extensions: COMPRESSED_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+			sample_size: 16 * 1024,
+			max_entropy: 7.8,
+		}
+	}
+}
+
+impl DetectHeuristics {
+	/// Heuristics that never skip, every leaf gets a trial compression
+	pub fn none() -> DetectHeuristics {
+		DetectHeuristics {
+			extensions: HashSet::new(),
+			sample_size: 0,
+			max_entropy: 8.0,
+		}
+	}
+
+	/// Adds an extension to skip, eg: `"dds"`
+	pub fn extension(mut self, extension: &str) -> Self {
+		self.extensions.insert(extension.trim_start_matches('.').to_lowercase());
+		self
+	}
+
+	/// Setter for the `sample_size` field
+	pub fn sample_size(mut self, sample_size: usize) -> Self {
+		self.sample_size = sample_size;
+		self
+	}
+
+	/// Setter for the `max_entropy` field
+	pub fn max_entropy(mut self, max_entropy: f64) -> Self {
+		self.max_entropy = max_entropy;
+		self
+	}
+
+	/// Whether the leaf with the given `ID` and data is likely incompressible, and should be stored without a trial compression
+	pub fn skip(&self, id: &str, data: &[u8]) -> bool {
+		let extension = id.rsplit_once('.').map(|(_, ext)| ext).filter(|ext| !ext.contains('/'));
+		if let Some(ext) = extension {
+			if self.extensions.contains(ext) || self.extensions.contains(&ext.to_lowercase()) {
+				return true;
+			}
+		}
+
+		// Small leaves are cheap to trial compress, only sample larger ones
+		if self.sample_size == 0 || data.len() < self.sample_size * 2 {
+			return false;
+		}
+
+		let window = self.sample_size / 3;
+		let middle = data.len() / 2 - window / 2;
+
+		let mut sample = Vec::with_capacity(window * 3);
+		sample.extend_from_slice(&data[..window]);
+		sample.extend_from_slice(&data[middle..middle + window]);
+		sample.extend_from_slice(&data[data.len() - window..]);
+
+		entropy(&sample) > self.max_entropy
+	}
+}
@@ -0,0 +1,84 @@
+#[cfg(feature = "archive")]
+use std::collections::HashMap;
+
+use super::{entry_id::EntryId, error::*};
+
+/// The `ID` of the reserved leaf holding an archive's dependency graph, written whenever a leaf uses `Leaf::depends_on`
+pub const DEPENDENCIES_ID: &str = "vach::dependencies";
+
+/// Serializes the adjacency of each leaf with dependencies: `[id_len: u16][id][count: u16]` followed by `count` times `[dep_len: u16][dep]`
+#[cfg(feature = "builder")]
+pub(crate) fn encode<'a>(graph: impl Iterator<Item = (&'a EntryId, &'a [EntryId])>) -> InternalResult<Vec<u8>> {
+	let mut buffer = Vec::new();
+
+	for (id, dependencies) in graph {
+		if dependencies.len() > u16::MAX as usize {
+			return Err(InternalError::InvalidConfigError(format!(
+				"{} has {} dependencies, at most {} are supported",
+				id,
+				dependencies.len(),
+				u16::MAX
+			)));
+		}
+
+		write_id(&mut buffer, id);
+		buffer.extend_from_slice(&(dependencies.len() as u16).to_le_bytes());
+		dependencies.iter().for_each(|dependency| write_id(&mut buffer, dependency));
+	}
+
+	Ok(buffer)
+}
+
+#[cfg(feature = "builder")]
+fn write_id(buffer: &mut Vec<u8>, id: &EntryId) {
+	buffer.extend_from_slice(&(id.len() as u16).to_le_bytes());
+	buffer.extend_from_slice(id.as_bytes());
+}
+
+/// Parses data written by [`encode`]
+#[cfg(feature = "archive")]
+pub(crate) fn decode(mut data: &[u8]) -> InternalResult<HashMap<EntryId, Vec<EntryId>>> {
+	let mut graph = HashMap::new();
+
+	while !data.is_empty() {
+		let id = read_id(&mut data)?;
+		let count = read_u16(&mut data)?;
+
+		let dependencies = (0..count).map(|_| read_id(&mut data)).collect::<InternalResult<Vec<_>>>()?;
+		graph.insert(id, dependencies);
+	}
+
+	Ok(graph)
+}
+
+#[cfg(feature = "archive")]
+fn read_u16(data: &mut &[u8]) -> InternalResult<u16> {
+	match data.split_first_chunk::<2>() {
+		Some((bytes, rest)) => {
+			*data = rest;
+			Ok(u16::from_le_bytes(*bytes))
+		},
+		None => Err(truncated()),
+	}
+}
+
+#[cfg(feature = "archive")]
+fn read_id(data: &mut &[u8]) -> InternalResult<EntryId> {
+	let len = read_u16(data)? as usize;
+	if data.len() < len {
+		return Err(truncated());
+	}
+
+	let (id, rest) = data.split_at(len);
+	*data = rest;
+
+	match std::str::from_utf8(id) {
+		Ok(id) => Ok(EntryId::from_raw(id)),
+		Err(err) => Err(InternalError::OtherError(Box::new(err))),
+	}
+}
+
+#[cfg(feature = "archive")]
+fn truncated() -> InternalError {
+	InternalError::OtherError("Truncated dependency graph".into())
+}
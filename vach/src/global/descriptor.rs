@@ -0,0 +1,217 @@
+use std::{fmt, str::FromStr};
+
+use super::{error::*, flags::Flags, header::Capabilities};
+use crate::format::{header::extensions, registry::RawEntry};
+
+/// The `ID` of the reserved leaf holding the [`FormatDescriptor`] of the writer, written when `BuilderConfig::format_descriptor` is set
+pub const DESCRIPTOR_ID: &str = "vach::format";
+
+/// The version of the descriptor's own encoding, bumped if lines change meaning. Unknown lines are skipped, so new lines don't bump it
+const DESCRIPTOR_VERSION: u16 = 1;
+
+const CAPABILITIES: [(&str, u32); 7] = [
+	("COMPRESSION", Capabilities::COMPRESSION),
+	("ENCRYPTION", Capabilities::ENCRYPTION),
+	("PIPELINES", Capabilities::PIPELINES),
+	("PREFIXED_IDS", Capabilities::PREFIXED_IDS),
+	("EXTENDED_HEADER", Capabilities::EXTENDED_HEADER),
+	("COMPRESSED_REGISTRY", Capabilities::COMPRESSED_REGISTRY),
+	("KEY_EPOCHS", Capabilities::KEY_EPOCHS),
+];
+
+const EXTENSIONS: [(&str, u8); 6] = [
+	("KEY_EXPIRY", extensions::KEY_EXPIRY),
+	("SHARED_ARCHIVE", extensions::SHARED_ARCHIVE),
+	("TIMESTAMP", extensions::TIMESTAMP),
+	("FOOTER", extensions::FOOTER),
+	("BUILD_ID", extensions::BUILD_ID),
+	("COMPRESSED_REGISTRY", extensions::COMPRESSED_REGISTRY),
+];
+
+const CODECS: [(&str, u32); 4] = [
+	("lz4", Flags::LZ4_COMPRESSED),
+	("snappy", Flags::SNAPPY_COMPRESSED),
+	("brotli", Flags::BROTLI_COMPRESSED),
+	("deflate", Flags::DEFLATE_COMPRESSED),
+];
+
+/// A machine readable description of the structural parameters of the format: the spec version, the size of registry entries,
+/// what each flag, capability and header extension means and which codecs exist. Tools and third-party readers use it to adapt to format changes
+/// without hardcoding a table per version, see `Archive::format_descriptor`.
+///
+/// Encoded as UTF-8 text, one `key value...` line per parameter, numbers in decimal or hex. Readers skip lines they don't know
+/// ```
+/// use vach::archive::FormatDescriptor;
+///
+/// let descriptor = FormatDescriptor::current();
+/// assert_eq!(descriptor.spec_version, vach::VERSION);
+/// assert_eq!(descriptor.flag("signed"), Some(vach::archive::Flags::SIGNED_FLAG));
+///
+/// let text = descriptor.to_string();
+/// assert!(text.contains("\nflag SIGNED 0x8000000\n"));
+/// assert_eq!(text.parse::<FormatDescriptor>().unwrap(), descriptor);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatDescriptor {
+	/// The spec version archives are written in, see [`VERSION`](crate::VERSION)
+	pub spec_version: u16,
+	/// The oldest spec version loaders must support
+	pub min_loader_version: u16,
+	/// The library and version that wrote the descriptor, eg: `vach 0.6.2`. `None` for descriptors rebuilt from an archive's header
+	pub writer: Option<String>,
+	/// The size of a registry entry before it's `ID` and signature
+	pub registry_entry_size: usize,
+	/// Each flag's name and bit
+	pub flags: Vec<(String, u32)>,
+	/// Each header capability's name and bit
+	pub capabilities: Vec<(String, u32)>,
+	/// Each header extension's name and tag
+	pub extensions: Vec<(String, u8)>,
+	/// Each compression codec's name and the flag marking entries it compressed
+	pub codecs: Vec<(String, u32)>,
+}
+
+impl FormatDescriptor {
+	/// The descriptor of this build of `vach`. Codecs are listed whether or not the `compression` feature is enabled, they describe the format
+	pub fn current() -> FormatDescriptor {
+		FormatDescriptor {
+			spec_version: crate::VERSION,
+			min_loader_version: crate::VERSION,
+			writer: Some(concat!("vach ", env!("CARGO_PKG_VERSION")).to_string()),
+			registry_entry_size: RawEntry::MIN_SIZE,
+			flags: owned(&Flags::NAMES),
+			capabilities: owned(&CAPABILITIES),
+			extensions: owned(&EXTENSIONS),
+			codecs: owned(&CODECS),
+		}
+	}
+
+	/// The bit of a flag by name, ignoring case
+	pub fn flag(&self, name: &str) -> Option<u32> {
+		find(&self.flags, name)
+	}
+
+	/// The tag of a header extension by name, ignoring case
+	pub fn extension(&self, name: &str) -> Option<u8> {
+		find(&self.extensions, name)
+	}
+
+	/// The flag marking entries compressed with a codec, by name, ignoring case
+	pub fn codec(&self, name: &str) -> Option<u32> {
+		find(&self.codecs, name)
+	}
+}
+
+impl fmt::Display for FormatDescriptor {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "vach-format {}", DESCRIPTOR_VERSION)?;
+		writeln!(f, "spec-version {}", self.spec_version)?;
+		writeln!(f, "min-loader-version {}", self.min_loader_version)?;
+
+		if let Some(writer) = &self.writer {
+			writeln!(f, "writer {}", writer)?;
+		}
+
+		writeln!(f, "registry-entry-size {}", self.registry_entry_size)?;
+
+		for (name, bit) in &self.flags {
+			writeln!(f, "flag {} {:#x}", name, bit)?;
+		}
+
+		for (name, bit) in &self.capabilities {
+			writeln!(f, "capability {} {:#x}", name, bit)?;
+		}
+
+		for (name, tag) in &self.extensions {
+			writeln!(f, "extension {} {}", name, tag)?;
+		}
+
+		for (name, bit) in &self.codecs {
+			writeln!(f, "codec {} {:#x}", name, bit)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl FromStr for FormatDescriptor {
+	type Err = InternalError;
+
+	/// Parses a descriptor written by it's `Display` implementation. Fails with [`InternalError::ParseError`] on malformed lines or a newer encoding
+	fn from_str(text: &str) -> InternalResult<FormatDescriptor> {
+		let mut lines = text.lines().map(|line| line.split_once(' ').unwrap_or((line, "")));
+
+		match lines.next() {
+			Some(("vach-format", version)) if number::<u16>(version)? <= DESCRIPTOR_VERSION => (),
+			Some(("vach-format", version)) => {
+				return Err(InternalError::ParseError(format!(
+					"The format descriptor is encoded in version {}, newer than the supported {}",
+					version, DESCRIPTOR_VERSION
+				)))
+			},
+			_ => return Err(InternalError::ParseError("Not a format descriptor".to_string())),
+		};
+
+		let mut descriptor = FormatDescriptor {
+			spec_version: 0,
+			min_loader_version: 0,
+			writer: None,
+			registry_entry_size: 0,
+			flags: vec![],
+			capabilities: vec![],
+			extensions: vec![],
+			codecs: vec![],
+		};
+
+		for (key, value) in lines {
+			match key {
+				"spec-version" => descriptor.spec_version = number(value)?,
+				"min-loader-version" => descriptor.min_loader_version = number(value)?,
+				"writer" => descriptor.writer = Some(value.to_string()),
+				"registry-entry-size" => descriptor.registry_entry_size = number(value)?,
+				"flag" => descriptor.flags.push(named(value)?),
+				"capability" => descriptor.capabilities.push(named(value)?),
+				"extension" => descriptor.extensions.push(named(value)?),
+				"codec" => descriptor.codecs.push(named(value)?),
+				// Written by newer versions
+				_ => (),
+			}
+		}
+
+		Ok(descriptor)
+	}
+}
+
+fn owned<T: Copy>(table: &[(&str, T)]) -> Vec<(String, T)> {
+	table.iter().map(|(name, value)| (name.to_string(), *value)).collect()
+}
+
+fn find<T: Copy>(table: &[(String, T)], name: &str) -> Option<T> {
+	table
+		.iter()
+		.find(|(n, _)| n.eq_ignore_ascii_case(name))
+		.map(|(_, value)| *value)
+}
+
+fn number<T: TryFrom<u64>>(value: &str) -> InternalResult<T> {
+	let value = value.trim();
+	let parsed = match value.strip_prefix("0x") {
+		Some(hex) => u64::from_str_radix(hex, 16),
+		None => value.parse::<u64>(),
+	};
+
+	parsed
+		.ok()
+		.and_then(|n| T::try_from(n).ok())
+		.ok_or_else(|| InternalError::ParseError(format!("Invalid number in format descriptor: {}", value)))
+}
+
+fn named<T: TryFrom<u64>>(value: &str) -> InternalResult<(String, T)> {
+	match value.split_once(' ') {
+		Some((name, value)) => Ok((name.to_string(), number(value)?)),
+		None => Err(InternalError::ParseError(format!(
+			"Expected a name and a value in format descriptor, found: {}",
+			value
+		))),
+	}
+}
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, iter::FusedIterator};
+
+use super::{entry_id::EntryId, query, reg_entry::RegistryEntry};
+
+/// A file or directory directly within a directory, see `Archive::children`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirEntry<'a> {
+	/// An entry, by it's full `ID`
+	File(&'a str),
+	/// A directory holding entries further down, by it's full path without a trailing `/`
+	Dir(&'a str),
+}
+
+impl<'a> DirEntry<'a> {
+	/// The full path of the file or directory
+	pub fn path(&self) -> &'a str {
+		match self {
+			DirEntry::File(path) | DirEntry::Dir(path) => path,
+		}
+	}
+
+	/// The last segment of the path, eg: `rock.png` for `textures/rock.png`
+	pub fn name(&self) -> &'a str {
+		let path = self.path();
+		path.rsplit_once('/').map_or(path, |(_, name)| name)
+	}
+
+	/// Whether this is a directory
+	pub fn is_dir(&self) -> bool {
+		matches!(self, DirEntry::Dir(_))
+	}
+}
+
+/// An iterator over the files and directories directly within a directory, in `ID` order. Returned by `Archive::children`
+#[derive(Debug, Clone)]
+pub struct Children<'a> {
+	// Every `ID` within the directory, sorted
+	ids: &'a [EntryId],
+	prefix: usize,
+}
+
+impl<'a> Iterator for Children<'a> {
+	type Item = DirEntry<'a>;
+
+	fn next(&mut self) -> Option<DirEntry<'a>> {
+		let id = self.ids.first()?;
+
+		match id[self.prefix..].find('/') {
+			None => {
+				self.ids = &self.ids[1..];
+				Some(DirEntry::File(id))
+			},
+			// Entries within a subdirectory are adjacent, the whole run is skipped at once
+			Some(idx) => {
+				let dir = &id[..self.prefix + idx + 1];
+				let nested = self.ids.partition_point(|id| id.starts_with(dir));
+
+				self.ids = &self.ids[nested..];
+				Some(DirEntry::Dir(&dir[..dir.len() - 1]))
+			},
+		}
+	}
+}
+
+impl FusedIterator for Children<'_> {}
+
+/// The `ID`s of live entries sorted, so those within a directory, or sharing any prefix, form a contiguous run found by binary search
+#[derive(Debug, Default)]
+pub(crate) struct DirIndex {
+	ids: Vec<EntryId>,
+}
+
+impl DirIndex {
+	pub(crate) fn new(entries: &HashMap<EntryId, RegistryEntry>) -> DirIndex {
+		let mut ids = entries
+			.iter()
+			.filter(|(_, entry)| !entry.is_tombstone())
+			.map(|(id, _)| id.clone())
+			.collect::<Vec<_>>();
+
+		ids.sort_unstable();
+		DirIndex { ids }
+	}
+
+	/// Every `ID` starting with `prefix`, sorted
+	pub(crate) fn prefixed(&self, prefix: &str) -> &[EntryId] {
+		let start = self.ids.partition_point(|id| id.as_ref() < prefix);
+		let len = self.ids[start..].partition_point(|id| id.starts_with(prefix));
+
+		&self.ids[start..start + len]
+	}
+
+	/// The files and directories directly within `dir`, with or without a trailing `/`. `""` is the root
+	pub(crate) fn children(&self, dir: &str) -> Children<'_> {
+		let prefix = dir_prefix(dir);
+
+		Children {
+			ids: self.prefixed(&prefix),
+			prefix: prefix.len(),
+		}
+	}
+
+	/// Every `ID` matching the glob, sorted. Only those sharing the pattern's literal prefix are matched
+	pub(crate) fn glob<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = &'a EntryId> + 'a {
+		let literal = pattern.find(['*', '?']).unwrap_or(pattern.len());
+
+		self.prefixed(&pattern[..literal])
+			.iter()
+			.filter(move |id| query::glob(pattern, id))
+	}
+}
+
+/// `path` with a trailing separator, the root stays empty
+pub(crate) fn dir_prefix(path: &str) -> String {
+	match path.trim_end_matches('/') {
+		"" => String::new(),
+		path => format!("{}/", path),
+	}
+}
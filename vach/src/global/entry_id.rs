@@ -0,0 +1,203 @@
+use std::{
+	borrow::{Borrow, Cow},
+	fmt,
+	ops::Deref,
+	sync::Arc,
+};
+use super::error::*;
+
+/// The `ID` under which a [`Leaf`](crate::builder::Leaf) is stored and a [`RegistryEntry`](crate::archive::RegistryEntry) is looked up.
+/// Cheap to clone, since the underlying string is reference counted.
+/// ```
+/// use vach::prelude::EntryId;
+///
+/// let id = EntryId::new("sounds/footstep.wav").unwrap();
+/// assert_eq!(id.as_str(), "sounds/footstep.wav");
+///
+/// // Separators are normalized
+/// assert_eq!(EntryId::new(r".\sounds\\footstep.wav").unwrap(), "sounds/footstep.wav");
+///
+/// // IDs must fit into the registry, and can't be empty or hold NUL bytes
+/// assert!(EntryId::new("x".repeat(vach::MAX_ID_LENGTH)).is_err());
+/// assert!(EntryId::new("").is_err());
+/// assert!(EntryId::new("sounds\0").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntryId(Arc<str>);
+
+/// Separates an `ID`'s namespace from the rest of it, eg: `dlc1::maps/harbor.map`. See `Leaf::namespace`
+pub const NAMESPACE_SEPARATOR: &str = "::";
+
+/// The namespace `vach` keeps for it's own leaves, eg: `vach::tags`. Leaves can't be added under it, and it's entries are left out of listings
+pub const RESERVED_NAMESPACE: &str = "vach";
+
+impl EntryId {
+	/// Normalizes, validates and wraps the given `ID`. `\` separators become `/`, a leading `./` is stripped and repeated `/` are collapsed.
+	/// Fails with [`InternalError::IDSizeOverflowError`] if the `ID` can't fit in the registry, or [`InternalError::InvalidIdError`] if it's empty or holds a NUL byte.
+	///
+	/// IDs passed to `Leaf::id` are normalized alike, and validated once the leaf is added. Lookups normalize the given `ID` too, so `./a\b` finds `a/b`
+	pub fn new(id: impl AsRef<str>) -> InternalResult<EntryId> {
+		let id = EntryId::from_raw(normalize(id.as_ref()));
+		id.validate()?;
+
+		Ok(id)
+	}
+
+	/// Checks that the `ID` can be written into a registry
+	pub(crate) fn validate(&self) -> InternalResult {
+		if self.0.len() >= crate::MAX_ID_LENGTH {
+			return Err(InternalError::IDSizeOverflowError(self.0.to_string()));
+		};
+
+		if self.0.is_empty() {
+			return Err(InternalError::InvalidIdError(self.0.to_string(), "IDs can't be empty"));
+		}
+
+		if self.0.contains('\0') {
+			return Err(InternalError::InvalidIdError(self.0.to_string(), "IDs can't contain NUL bytes"));
+		}
+
+		Ok(())
+	}
+
+	/// Checks that the `ID` isn't in the [`RESERVED_NAMESPACE`], for `ID`s given by users
+	#[cfg(feature = "builder")]
+	pub(crate) fn unreserved(&self) -> InternalResult {
+		match self.is_reserved() {
+			true => Err(InternalError::InvalidIdError(
+				self.0.to_string(),
+				"the `vach` namespace is reserved",
+			)),
+			false => Ok(()),
+		}
+	}
+
+	/// Normalizes an `ID` without validating it, validation is deferred to `Builder::add_leaf`
+	#[inline(always)]
+	#[cfg(feature = "builder")]
+	pub(crate) fn normalized(id: impl AsRef<str>) -> EntryId {
+		EntryId::from_raw(normalize(id.as_ref()))
+	}
+
+	/// Wraps an `ID` without validating it, used for `ID`s read from a source
+	#[inline(always)]
+	pub(crate) fn from_raw(id: impl AsRef<str>) -> EntryId {
+		EntryId(Arc::from(id.as_ref()))
+	}
+
+	/// The namespace of this `ID`, the part before the first `::`, if any
+	/// ```
+	/// use vach::prelude::EntryId;
+	///
+	/// let id = EntryId::new("dlc1::maps/harbor.map").unwrap();
+	/// assert_eq!(id.namespace(), Some("dlc1"));
+	/// assert_eq!(id.local(), "maps/harbor.map");
+	/// ```
+	pub fn namespace(&self) -> Option<&str> {
+		self.0.split_once(NAMESPACE_SEPARATOR).map(|(namespace, _)| namespace)
+	}
+
+	/// Whether this `ID` is in the [`RESERVED_NAMESPACE`], like the leaves `vach` writes for tags and dependencies
+	#[inline(always)]
+	pub fn is_reserved(&self) -> bool {
+		self.namespace() == Some(RESERVED_NAMESPACE)
+	}
+
+	/// The `ID` within it's namespace, the whole `ID` if it has none
+	pub fn local(&self) -> &str {
+		self.0.split_once(NAMESPACE_SEPARATOR).map_or(&self.0, |(_, local)| local)
+	}
+
+	/// The `ID` as a string slice
+	#[inline(always)]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+/// Rewrites `\` as `/`, strips leading `./` and collapses repeated `/`. Borrows when the `ID` is already normalized
+pub(crate) fn normalize(id: &str) -> Cow<'_, str> {
+	if !id.contains('\\') && !id.contains("//") && !id.starts_with("./") {
+		return Cow::Borrowed(id);
+	}
+
+	let mut normalized = String::with_capacity(id.len());
+	for c in id.chars().map(|c| if c == '\\' { '/' } else { c }) {
+		if !(c == '/' && normalized.ends_with('/')) {
+			normalized.push(c);
+		}
+	}
+
+	let mut trimmed = normalized.as_str();
+	while let Some(rest) = trimmed.strip_prefix("./") {
+		trimmed = rest;
+	}
+
+	Cow::Owned(trimmed.to_string())
+}
+
+impl Deref for EntryId {
+	type Target = str;
+
+	#[inline(always)]
+	fn deref(&self) -> &str {
+		&self.0
+	}
+}
+
+impl AsRef<str> for EntryId {
+	#[inline(always)]
+	fn as_ref(&self) -> &str {
+		&self.0
+	}
+}
+
+impl Borrow<str> for EntryId {
+	#[inline(always)]
+	fn borrow(&self) -> &str {
+		&self.0
+	}
+}
+
+impl PartialEq<str> for EntryId {
+	fn eq(&self, other: &str) -> bool {
+		self.as_str() == other
+	}
+}
+
+impl PartialEq<&str> for EntryId {
+	fn eq(&self, other: &&str) -> bool {
+		self.as_str() == *other
+	}
+}
+
+impl TryFrom<&str> for EntryId {
+	type Error = InternalError;
+
+	#[inline(always)]
+	fn try_from(id: &str) -> InternalResult<EntryId> {
+		EntryId::new(id)
+	}
+}
+
+impl TryFrom<String> for EntryId {
+	type Error = InternalError;
+
+	#[inline(always)]
+	fn try_from(id: String) -> InternalResult<EntryId> {
+		EntryId::new(id)
+	}
+}
+
+impl From<EntryId> for Arc<str> {
+	#[inline(always)]
+	fn from(id: EntryId) -> Arc<str> {
+		id.0
+	}
+}
+
+impl fmt::Display for EntryId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
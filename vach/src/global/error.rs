@@ -1,4 +1,5 @@
-use std::{error, io, sync::Arc};
+use std::{error, fmt, io, path::PathBuf};
+use super::entry_id::EntryId;
 use thiserror::Error;
 
 /// Internal `Result` type alias used by `vach`. Basically equal to: `Result<T, InternalError>`
@@ -25,13 +26,43 @@ pub enum InternalError {
 	MalformedArchiveSource([u8; crate::MAGIC_LENGTH]),
 	/// Thrown by `Archive::fetch(---)` when a given resource is not found
 	#[error("[VachError::MissingResourceError] Resource not found: {0}")]
-	MissingResourceError(String),
+	MissingResourceError(EntryId),
 	/// Thrown when a leaf with an identical ID to a queued leaf is add with the `Builder::add(---)` functions
 	#[error("[VachError::LeafAppendError] A leaf with the ID: {0} already exists. Consider changing the ID to prevent collisions")]
-	LeafAppendError(Arc<str>),
+	LeafAppendError(EntryId),
+	/// Thrown by `BuilderConfig::validate` when the configuration is contradictory or unusable, before any data is written
+	#[error("[VachError::InvalidConfigError] {0}")]
+	InvalidConfigError(String),
+	/// Thrown when an entry went through a custom pipeline stage, marked by the contained flag, with no decoder registered using `ArchiveConfig::register_stage`
+	#[error("[VachError::MissingStageError] The entry was written with a custom pipeline stage (flag: {0:#x}), but no decoder is registered for it. See ArchiveConfig::register_stage")]
+	MissingStageError(u32),
 	/// Thrown when no `Keypair` is provided and an encrypted [Leaf](crate::builder::Leaf) is encountered
 	#[error("[VachError::NoKeypairError] Unable to continue with cryptographic operation, as no keypair was supplied")]
 	NoKeypairError,
+	/// Thrown by [`Sealed`](crate::seal::Sealed) archives when an entry is unsigned, unencrypted or fails signature checks
+	#[error("[VachError::UnauthenticatedError] The entry: {0} is not sealed, or it's signature doesn't match it's data")]
+	UnauthenticatedError(EntryId),
+	/// Thrown when an archive's key expired and `ArchiveConfig::enforce_key_expiry` is set, or when writing with a key past its `KeyPolicy` expiry
+	#[error("[VachError::ExpiredKeyError] The archive's key expired {} seconds after the UNIX epoch", .0.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs()))]
+	ExpiredKeyError(std::time::SystemTime),
+	/// Thrown when fetching an entry outside it's validity window while `ArchiveConfig::enforce_validity` is set, see `Leaf::valid_from`
+	#[error("[VachError::OutsideValidityError] The entry: {0} is not valid at this time")]
+	OutsideValidityError(EntryId),
+	/// Thrown when the [`MemoryGate`](crate::prelude::MemoryGate) set by `ArchiveConfig::memory_gate` refuses an entry, contains the entry's decoded size
+	#[error("[VachError::MemoryBudgetError] The entry: {0} decodes to {1} bytes, which the memory gate refused")]
+	MemoryBudgetError(EntryId, u64),
+	/// Thrown when verifying a [`ReleaseManifest`](crate::release::ReleaseManifest): it's signature doesn't hold, or an archive is missing or doesn't match it
+	#[error("[VachError::ReleaseMismatchError] {0}")]
+	ReleaseMismatchError(String),
+	/// Thrown when an archive has no timestamp token, or it's token doesn't hold, see `Archive::verify_timestamp`
+	#[error("[VachError::TimestampError] {0}")]
+	TimestampError(String),
+	/// Thrown when an archive written with `BuilderConfig::footer` doesn't end with a matching footer, usually because it was truncated
+	#[error("[VachError::FooterMismatchError] The archive's footer doesn't match it's header, it may be truncated: {0}")]
+	FooterMismatchError(String),
+	/// Thrown when a lock over an archive, see [`ArchiveLock`](crate::lock::ArchiveLock), can't be taken in time. Contains the path of the lock file
+	#[error("[VachError::LockedError] The archive is locked by another process, lock file: {}", .0.display())]
+	LockedError(PathBuf),
 	/// Thrown when decryption or encryption fails
 	#[cfg(feature = "crypto")]
 	#[error("[VachError::CryptoError] {0}")]
@@ -42,11 +73,113 @@ pub enum InternalError {
 	/// When a [`Leaf`](crate::builder::Leaf) has an ID that is longer than `crate::MAX_ID_LENGTH`, contains the overflowing `ID`
 	#[error("[VachError::IDSizeOverflowError] The maximum size of any ID is: {}. The leaf with ID: {0} has an overflowing ID of length: {}", crate::MAX_ID_LENGTH, .0.len())]
 	IDSizeOverflowError(String),
+	/// Thrown when an `ID` is empty or contains a NUL byte, or uses a namespace reserved by `vach`. Contains the `ID` and what's wrong with it
+	#[error("[VachError::InvalidIdError] The ID: {0:?} is invalid, {1}")]
+	InvalidIdError(String, &'static str),
 	/// An error that is thrown when the current archive attempts to load an incompatible version, contains the incompatible version
 	#[error("The provided archive source has version: {}. While the current implementation has a spec-version: {}. The provided source is incompatible!", .0, crate::VERSION)]
 	IncompatibleArchiveVersionError(u16),
+	/// Thrown when an archive relies on a capability this loader lacks, describes the capability and how to get it where possible
+	#[error("[VachError::MissingCapabilityError] The archive relies on a capability this loader lacks: {0}")]
+	MissingCapabilityError(String),
 	/// Errors thrown  during compression or decompression
 	#[error("[VachError::CompressorDecompressorError]: {0}")]
 	#[cfg(feature = "compression")]
 	DeCompressionError(#[from] lz4_flex::frame::Error),
+	/// Wraps an error raised by `Archive::open`, with the path of the archive that failed to open
+	#[error("[VachError::OpenError] Unable to open archive at: {}. {source}", .path.display())]
+	OpenError {
+		/// The path passed to `Archive::open`
+		path: PathBuf,
+		/// The underlying error
+		#[source]
+		source: Box<InternalError>,
+	},
+	/// Wraps an error raised while processing a specific entry, with the entry's `ID`, the [`Stage`] that failed and where in the source|target the entry lies
+	#[error("[VachError::EntryError] {stage} failed for entry: {id}{}. {source}", .offset.map(|o| format!(", at offset: {o}")).unwrap_or_default())]
+	EntryError {
+		/// The `ID` of the offending entry
+		id: EntryId,
+		/// The byte offset of the entry's data in the source|target, if it was known when the error occurred
+		offset: Option<u64>,
+		/// The step of processing that failed
+		stage: Stage,
+		/// The underlying error
+		#[source]
+		source: Box<InternalError>,
+	},
+}
+
+impl InternalError {
+	/// Attaches entry context to an error, errors that already carry context are returned as is
+	pub(crate) fn context(self, id: &EntryId, offset: Option<u64>, stage: Stage) -> InternalError {
+		match self {
+			InternalError::EntryError { .. } => self,
+			err => InternalError::EntryError {
+				id: id.clone(),
+				offset,
+				stage,
+				source: Box::new(err),
+			},
+		}
+	}
+
+	/// Strips any entry context, returning the underlying error
+	pub fn root(&self) -> &InternalError {
+		match self {
+			InternalError::EntryError { source, .. } | InternalError::OpenError { source, .. } => source.root(),
+			err => err,
+		}
+	}
+}
+
+impl From<vach_core::Error> for InternalError {
+	fn from(err: vach_core::Error) -> InternalError {
+		match err {
+			vach_core::Error::IdTooLong(id) => InternalError::IDSizeOverflowError(id),
+			vach_core::Error::Truncated => io::Error::from(io::ErrorKind::UnexpectedEof).into(),
+			err => InternalError::OtherError(Box::new(err)),
+		}
+	}
+}
+
+/// The step of processing an entry during which an [`InternalError::EntryError`] occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+	/// Reading the leaf's data from the source
+	Read,
+	/// Validating the leaf's signature
+	Verify,
+	/// Decrypting the leaf's data
+	Decrypt,
+	/// Decompressing the leaf's data
+	Decompress,
+	/// Compressing the leaf's data
+	Compress,
+	/// Encrypting the leaf's data
+	Encrypt,
+	/// Signing the leaf's data and registry entry
+	Sign,
+	/// Writing the leaf's data or registry entry into the target
+	Write,
+	/// Running a custom pipeline stage over the leaf's data, or undoing it
+	Custom,
+}
+
+impl fmt::Display for Stage {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let stage = match self {
+			Stage::Read => "Read",
+			Stage::Verify => "Verification",
+			Stage::Decrypt => "Decryption",
+			Stage::Decompress => "Decompression",
+			Stage::Compress => "Compression",
+			Stage::Encrypt => "Encryption",
+			Stage::Sign => "Signing",
+			Stage::Write => "Write",
+			Stage::Custom => "Custom stage",
+		};
+
+		f.write_str(stage)
+	}
 }
@@ -1,5 +1,6 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 use super::error::*;
+use vach_core::flags;
 
 /// Abstracted flag access and manipulation `struct`.
 /// A knock-off minimal [bitflags](https://crates.io/crates/bitflags) of sorts.
@@ -14,23 +15,58 @@ pub struct Flags {
 impl Flags {
 	/// The flags used within the crate, to whom all access is denied.
 	/// Any interaction with `Flags::set()` will yield an error.
-	pub const RESERVED_MASK: u32 = 0b1111_1111_1111_1111_0000_0000_0000_0000;
+	pub const RESERVED_MASK: u32 = flags::RESERVED_MASK;
 	/// The size in bytes of any flags entry
-	pub const SIZE: usize = 32 / 8;
+	pub const SIZE: usize = flags::SIZE;
 
 	/// This flag shows that the adjacent entry is compressed
-	pub const COMPRESSED_FLAG: u32 = 0b_1000_0000_0000_0000_0000_0000_0000_0000;
+	pub const COMPRESSED_FLAG: u32 = flags::COMPRESSED_FLAG;
 	/// This entry was compressed using the [LZ4](https://crates.io/crates/lz4_flex) scheme for very fast decompression with average compression ratios
-	pub const LZ4_COMPRESSED: u32 = 0b_0100_0000_0000_0000_0000_0000_0000_0000;
+	pub const LZ4_COMPRESSED: u32 = flags::LZ4_COMPRESSED;
 	/// This entry was compressed using the [snappy](https://crates.io/crates/snap) scheme for balanced compression properties
-	pub const SNAPPY_COMPRESSED: u32 = 0b_0010_0000_0000_0000_0000_0000_0000_0000;
+	pub const SNAPPY_COMPRESSED: u32 = flags::SNAPPY_COMPRESSED;
 	/// This entry was compressed using the [brotli](https://crates.io/crates/brotli) scheme for higher compression ratios but slower compression speed
-	pub const BROTLI_COMPRESSED: u32 = 0b_0001_0000_0000_0000_0000_0000_0000_0000;
+	pub const BROTLI_COMPRESSED: u32 = flags::BROTLI_COMPRESSED;
+	/// The window size used to [brotli](https://crates.io/crates/brotli) compress this entry, stored as `window - 9` in these four bits. Zero if unknown
+	pub const BROTLI_WINDOW_MASK: u32 = flags::BROTLI_WINDOW_MASK;
+	/// This entry was compressed into a gzip stream using [deflate](https://crates.io/crates/flate2), which can be served as is with `Content-Encoding: gzip`
+	pub const DEFLATE_COMPRESSED: u32 = flags::DEFLATE_COMPRESSED;
+
+	/// The entry is a tombstone, it holds no data and hides any entry with the same `ID` in the layers below it, see `Leaf::tombstone`
+	pub const TOMBSTONE_FLAG: u32 = flags::TOMBSTONE_FLAG;
+	/// The [`ResidencyHint`](crate::archive::ResidencyHint) of this entry, `0` for `OnDemand`, `1` for `Preload` and `2` for `Stream`
+	pub const RESIDENCY_HINT_MASK: u32 = flags::RESIDENCY_HINT_MASK;
 
 	/// The flag that denotes that the archive source has signatures
-	pub const SIGNED_FLAG: u32 = 0b_0000_1000_0000_0000_0000_0000_0000_0000;
+	pub const SIGNED_FLAG: u32 = flags::SIGNED_FLAG;
+	/// The leaf went through a custom pipeline, its data is prefixed with the codes of the stages that ran, see `Leaf::pipeline`
+	pub const PIPELINE_FLAG: u32 = flags::PIPELINE_FLAG;
 	/// The flag that shows data in the leaf in encrypted
-	pub const ENCRYPTED_FLAG: u32 = 0b_0000_0010_0000_0000_0000_0000_0000_0000;
+	pub const ENCRYPTED_FLAG: u32 = flags::ENCRYPTED_FLAG;
+	/// The leaf was encrypted under a rotated key, its data is prefixed with the key's epoch, see `BuilderConfig::key_epoch`
+	pub const KEY_EPOCH_FLAG: u32 = flags::KEY_EPOCH_FLAG;
+
+	/// Symbolic names of single bit flags, as parsed by `Flags::from_str` and written by `{:#}`
+	pub(crate) const NAMES: [(&'static str, u32); 10] = [
+		("COMPRESSED", Flags::COMPRESSED_FLAG),
+		("ENCRYPTED", Flags::ENCRYPTED_FLAG),
+		("SIGNED", Flags::SIGNED_FLAG),
+		("PIPELINE", Flags::PIPELINE_FLAG),
+		("KEY_EPOCH", Flags::KEY_EPOCH_FLAG),
+		("TOMBSTONE", Flags::TOMBSTONE_FLAG),
+		("LZ4", Flags::LZ4_COMPRESSED),
+		("SNAPPY", Flags::SNAPPY_COMPRESSED),
+		("BROTLI", Flags::BROTLI_COMPRESSED),
+		("DEFLATE", Flags::DEFLATE_COMPRESSED),
+	];
+
+	/// The bit of a flag by it's symbolic name, eg: `SIGNED` or `lz4`, ignoring case
+	pub fn bit_by_name(name: &str) -> Option<u32> {
+		Flags::NAMES
+			.iter()
+			.find(|(n, _)| n.eq_ignore_ascii_case(name))
+			.map(|(_, bit)| *bit)
+	}
 
 	#[inline(always)]
 	/// Construct a `Flags` struct from a `u32` number
@@ -107,11 +143,69 @@ impl Flags {
 	pub fn contains(&self, bit: u32) -> bool {
 		(self.bits & bit) != 0
 	}
+
+	fn fmt_symbolic(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut rest = self.bits;
+		let mut parts = Vec::new();
+
+		for (name, bit) in Flags::NAMES {
+			if rest & bit != 0 {
+				parts.push(name.to_string());
+				rest &= !bit;
+			}
+		}
+
+		if rest != 0 || parts.is_empty() {
+			parts.push(format!("{:#x}", rest));
+		}
+
+		f.write_str(&parts.join("|"))
+	}
 }
 
+/// Parses flags written as symbolic names and numbers joined by `|`, eg: `COMPRESSED|SIGNED|0x40`.
+/// Names ignore case, see `Flags::bit_by_name`. Numbers are decimal, or hexadecimal and binary with a `0x` or `0b` prefix, and may contain `_`.
+/// An empty string parses as no flags
+/// ```
+/// use vach::prelude::Flags;
+///
+/// let flags: Flags = "compressed | SIGNED | 0x40".parse().unwrap();
+/// assert_eq!(flags.bits(), Flags::COMPRESSED_FLAG | Flags::SIGNED_FLAG | 0x40);
+/// assert_eq!(format!("{:#}", flags), "COMPRESSED|SIGNED|0x40");
+/// assert_eq!(format!("{:#}", flags).parse::<Flags>().unwrap(), flags);
+/// ```
+impl FromStr for Flags {
+	type Err = InternalError;
+
+	fn from_str(s: &str) -> InternalResult<Flags> {
+		let mut bits = 0;
+
+		for part in s.split('|').map(str::trim).filter(|p| !p.is_empty()) {
+			let digits = part.replace('_', "");
+			let number = match digits.get(..2).map(|p| p.to_ascii_lowercase()).as_deref() {
+				Some("0x") => u32::from_str_radix(&digits[2..], 16).ok(),
+				Some("0b") => u32::from_str_radix(&digits[2..], 2).ok(),
+				_ => digits.parse::<u32>().ok(),
+			};
+
+			bits |= number
+				.or_else(|| Flags::bit_by_name(part))
+				.ok_or_else(|| InternalError::ParseError(format!("Unknown flag: {}", part)))?;
+		}
+
+		Ok(Flags { bits })
+	}
+}
+
+/// `{}` gives a compact summary, eg: `Flags[C-S]`. `{:#}` gives the symbolic form parsed by `Flags::from_str`:
+/// the names of set flags, then any other bits in hexadecimal, joined by `|`. No flags at all are written as `0`
 #[rustfmt::skip]
 impl fmt::Display for Flags {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if f.alternate() {
+			return self.fmt_symbolic(f);
+		}
+
 		let compressed = if self.contains(Flags::COMPRESSED_FLAG) { 'C' } else { '-' };
 		let signed = if self.contains(Flags::SIGNED_FLAG) { 'S' } else { '-' };
 		let encrypted = if self.contains(Flags::ENCRYPTED_FLAG) { 'E' } else { '-' };
@@ -134,3 +228,47 @@ impl fmt::Debug for Flags {
 		)
 	}
 }
+
+/// Flags are written in their symbolic form to human readable formats, eg: `"COMPRESSED|SIGNED|0x40"`, and as a `u32` otherwise.
+/// Human readable formats accept either
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for Flags {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match serializer.is_human_readable() {
+			true => serializer.collect_str(&format_args!("{:#}", self)),
+			false => serializer.serialize_u32(self.bits),
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de> serde::Deserialize<'de> for Flags {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Flags, D::Error> {
+		struct Visitor;
+
+		impl serde::de::Visitor<'_> for Visitor {
+			type Value = Flags;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("flags as a u32 or symbolic names joined by '|'")
+			}
+
+			fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Flags, E> {
+				u32::try_from(value)
+					.map(Flags::from_bits)
+					.map_err(|_| E::custom(format!("{} doesn't fit in 32 bits", value)))
+			}
+
+			fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Flags, E> {
+				value.parse().map_err(E::custom)
+			}
+		}
+
+		match deserializer.is_human_readable() {
+			true => deserializer.deserialize_any(Visitor),
+			false => deserializer.deserialize_u32(Visitor),
+		}
+	}
+}
@@ -1,24 +1,76 @@
-use std::{fmt, io::Read, str};
+use std::{
+	fmt,
+	io::Read,
+	str,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "crypto")]
+use std::collections::BTreeMap;
 
 #[cfg(feature = "crypto")]
 use crate::crypto;
-use super::{error::*, flags::Flags};
+use super::{error::*, flags::Flags, pipeline::StageFn, stats::SlowFetchCallback, validity::Clock};
+use vach_core::{
+	header::{capabilities, extensions, RawHeader},
+	registry::RegistryBlock,
+};
+
+/// Decides whether an entry of the given decoded size may be loaded, see [`ArchiveConfig::memory_gate`]
+pub type MemoryGate = fn(usize) -> bool;
 
 /// Used to configure and give extra information to the [`Archive`](crate::archive::Archive) loader.
 /// Used exclusively in archive source and integrity validation.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct ArchiveConfig {
 	/// If the archive has a custom magic sequence, pass the custom _MAGIC_ sequence here.
 	/// The custom _MAGIC_ sequence can then be used to validate archive sources.
 	pub magic: [u8; crate::MAGIC_LENGTH],
+	/// Extra _MAGIC_ sequences accepted alongside `magic`, so archives packed under an older or different brand still open.
+	/// Encrypted leaves are decrypted with whichever sequence the archive was actually written with.
+	pub legacy_magic: &'static [[u8; crate::MAGIC_LENGTH]],
+	/// Capacity in bytes of the internal read buffer wrapped around the archive source.
+	/// Small sequential reads, like parsing the registry, are served from this buffer instead of hitting the source every time.
+	pub read_buffer: usize,
+	/// How many [`FetchStats`](crate::prelude::FetchStats) records the [`Archive`](crate::archive::Archive) keeps, oldest records are dropped first. `0` disables recording.
+	pub fetch_stats: usize,
+	/// An optional threshold and callback, called with the [`FetchStats`](crate::prelude::FetchStats) of every fetch whose decoding took longer than the threshold
+	pub slow_fetch: Option<(Duration, SlowFetchCallback)>,
+	/// Decoders for custom pipeline stages, indexed by the bit of the stage's flag. See [`ArchiveConfig::register_stage`]
+	pub stages: [Option<StageFn>; 16],
+	/// Refuse archives whose key expired, see `BuilderConfig::key_policy`. Otherwise check `Archive::key_expired` to warn about them
+	pub enforce_key_expiry: bool,
+	/// Record the order in which entries are first fetched, see `Archive::access_profile`. Defaults to `false`
+	pub record_access: bool,
+	/// Refuse to serve entries outside their validity window, as judged by this clock. See `Leaf::valid_from` and [`ArchiveConfig::enforce_validity`]
+	pub validity_clock: Option<Clock>,
+	/// Consulted with an entry's decoded size before it's decoded, see [`ArchiveConfig::memory_gate`]
+	pub memory_gate: Option<MemoryGate>,
 	/// An ed25519 public key. **If no key is provided, (is `None`), then signature validation is ignored**. Even if the
 	/// archive source has signatures.
 	#[cfg(feature = "crypto")]
 	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
 	pub public_key: Option<crypto::VerifyingKey>,
+	/// The key encrypted leaves were written with, see `BuilderConfig::encryption_key`. When `None`, the key is derived from `public_key`.
+	/// Holding it only allows decrypting leaves, signatures are still only checked against `public_key`
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub decryption_key: Option<crypto::EncryptionKey>,
+	/// Keys of later epochs, for leaves encrypted after the key was rotated. See [`ArchiveConfig::add_decryption_key`]
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub epoch_keys: BTreeMap<u32, crypto::EncryptionKey>,
+	/// Accept signatures from archives without a build ID, which only cover an entry and it's data, so can be replayed in another archive.
+	/// Defaults to `true`, set to `false` once every archive has been re-signed, see `ArchiveEditor::resign`
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub legacy_signatures: bool,
 }
 
 impl ArchiveConfig {
+	/// The default capacity of the internal read buffer, 8KiB
+	pub const DEFAULT_READ_BUFFER: usize = 8 * 1024;
+
 	/// Construct a new [`ArchiveConfig`] struct.
 	/// ```
 	/// use vach::prelude::ArchiveConfig;
@@ -28,7 +80,22 @@ impl ArchiveConfig {
 	#[cfg(feature = "crypto")]
 	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
 	pub const fn new(magic: [u8; crate::MAGIC_LENGTH], key: Option<crypto::VerifyingKey>) -> ArchiveConfig {
-		ArchiveConfig { magic, public_key: key }
+		ArchiveConfig {
+			magic,
+			legacy_magic: &[],
+			read_buffer: Self::DEFAULT_READ_BUFFER,
+			fetch_stats: 0,
+			slow_fetch: None,
+			stages: [None; 16],
+			enforce_key_expiry: false,
+			record_access: false,
+			validity_clock: None,
+			memory_gate: None,
+			public_key: key,
+			decryption_key: None,
+			epoch_keys: BTreeMap::new(),
+			legacy_signatures: true,
+		}
 	}
 
 	/// Construct a new [`ArchiveConfig`] struct.
@@ -38,7 +105,18 @@ impl ArchiveConfig {
 	/// ```
 	#[cfg(not(feature = "crypto"))]
 	pub const fn new(magic: [u8; crate::MAGIC_LENGTH]) -> ArchiveConfig {
-		ArchiveConfig { magic }
+		ArchiveConfig {
+			magic,
+			legacy_magic: &[],
+			read_buffer: Self::DEFAULT_READ_BUFFER,
+			fetch_stats: 0,
+			slow_fetch: None,
+			stages: [None; 16],
+			enforce_key_expiry: false,
+			record_access: false,
+			validity_clock: None,
+			memory_gate: None,
+		}
 	}
 
 	/// Shorthand to load and parse an ed25519 public key from a [`Read`] handle, into this [`ArchiveConfig`],
@@ -68,11 +146,184 @@ impl ArchiveConfig {
 		self
 	}
 
+	/// Setter for the `decryption_key` field
+	/// ```
+	/// use vach::{crypto::EncryptionKey, prelude::ArchiveConfig};
+	/// let config = ArchiveConfig::default().decryption_key(EncryptionKey::generate());
+	/// ```
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn decryption_key(mut self, key: crypto::EncryptionKey) -> ArchiveConfig {
+		self.decryption_key = Some(key);
+		self
+	}
+
+	/// Adds the key leaves of the given epoch were encrypted with, see `BuilderConfig::key_epoch`. Epoch `0` is the `decryption_key` itself,
+	/// adding a key for an epoch twice replaces it. Decryption contexts are only built for epochs an entry is actually fetched from
+	/// ```
+	/// use vach::{crypto::EncryptionKey, prelude::ArchiveConfig};
+	///
+	/// let config = ArchiveConfig::default()
+	///   .decryption_key(EncryptionKey::generate())
+	///   .add_decryption_key(1, EncryptionKey::generate());
+	/// ```
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn add_decryption_key(mut self, epoch: u32, key: crypto::EncryptionKey) -> ArchiveConfig {
+		match epoch {
+			0 => self.decryption_key = Some(key),
+			_ => {
+				self.epoch_keys.insert(epoch, key);
+			},
+		}
+
+		self
+	}
+
+	/// Setter for the `legacy_signatures` field. Without them, signed entries of archives lacking a build ID are never reported as verified
+	/// ```
+	/// use vach::prelude::ArchiveConfig;
+	/// let config = ArchiveConfig::default().legacy_signatures(false);
+	/// ```
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn legacy_signatures(mut self, accept: bool) -> ArchiveConfig {
+		self.legacy_signatures = accept;
+		self
+	}
+
 	/// Setter for the magic into a [ArchiveConfig]
 	pub fn magic(mut self, magic: [u8; crate::MAGIC_LENGTH]) -> ArchiveConfig {
 		self.magic = magic;
 		self
 	}
+
+	/// Setter for the `legacy_magic` field, extra _MAGIC_ sequences to accept when opening an archive
+	/// ```
+	/// use vach::prelude::ArchiveConfig;
+	///
+	/// const LEGACY: &[[u8; vach::MAGIC_LENGTH]] = &[*b"GAME1", *vach::DEFAULT_MAGIC];
+	/// let config = ArchiveConfig::default().magic(*b"GAME2").legacy_magic(LEGACY);
+	/// ```
+	pub fn legacy_magic(mut self, magic: &'static [[u8; crate::MAGIC_LENGTH]]) -> ArchiveConfig {
+		self.legacy_magic = magic;
+		self
+	}
+
+	/// Whether an archive with the given _MAGIC_ sequence can be opened with this config
+	pub fn accepts_magic(&self, magic: &[u8; crate::MAGIC_LENGTH]) -> bool {
+		self.magic == *magic || self.legacy_magic.contains(magic)
+	}
+
+	/// Registers the decoder undoing a custom pipeline stage, identified by the stage's flag. Entries that went through an unregistered stage fail to decode.
+	/// Errors if `flag` isn't a single bit from the lower 16 bits of [`Flags`]
+	/// ```
+	/// use vach::prelude::{ArchiveConfig, InternalResult};
+	///
+	/// // Undoes a delta encoding stage
+	/// fn undelta(data: &[u8]) -> InternalResult<Vec<u8>> {
+	///   Ok(data.iter().scan(0u8, |prev, d| { *prev = prev.wrapping_add(*d); Some(*prev) }).collect())
+	/// }
+	///
+	/// let mut config = ArchiveConfig::default();
+	/// config.register_stage(0b1, undelta).unwrap();
+	/// ```
+	pub fn register_stage(&mut self, flag: u32, decode: StageFn) -> InternalResult {
+		if flag.count_ones() != 1 || flag & Flags::RESERVED_MASK != 0 {
+			return Err(InternalError::InvalidConfigError(format!(
+				"Custom stages need a single bit from the lower 16 bits of Flags, found {:#034b}",
+				flag
+			)));
+		}
+
+		self.stages[flag.trailing_zeros() as usize] = Some(decode);
+		Ok(())
+	}
+
+	/// Setter for the `enforce_key_expiry` field, when set archives whose key expired fail to open
+	/// ```
+	/// use vach::prelude::ArchiveConfig;
+	/// let config = ArchiveConfig::default().enforce_key_expiry(true);
+	/// ```
+	pub fn enforce_key_expiry(mut self, enforce: bool) -> ArchiveConfig {
+		self.enforce_key_expiry = enforce;
+		self
+	}
+
+	/// Refuse to serve entries outside their validity window, fetching them fails with `InternalError::OutsideValidityError`.
+	/// The clock is consulted on every fetch of an entry with a window, pass `SystemTime::now` or a trusted time source
+	/// ```
+	/// use std::time::SystemTime;
+	/// use vach::prelude::ArchiveConfig;
+	///
+	/// let config = ArchiveConfig::default().enforce_validity(SystemTime::now);
+	/// ```
+	pub fn enforce_validity(mut self, clock: Clock) -> ArchiveConfig {
+		self.validity_clock = Some(clock);
+		self
+	}
+
+	/// Setter for the `record_access` field, see `Archive::access_profile`
+	/// ```
+	/// use vach::prelude::ArchiveConfig;
+	/// let config = ArchiveConfig::default().record_access(true);
+	/// ```
+	pub fn record_access(mut self, record: bool) -> ArchiveConfig {
+		self.record_access = record;
+		self
+	}
+
+	/// Setter for the capacity of the internal read buffer, in bytes
+	/// ```
+	/// use vach::prelude::ArchiveConfig;
+	/// let config = ArchiveConfig::default().read_buffer(64 * 1024);
+	/// ```
+	pub fn read_buffer(mut self, bytes: usize) -> ArchiveConfig {
+		self.read_buffer = bytes;
+		self
+	}
+
+	/// Setter for the number of [`FetchStats`](crate::prelude::FetchStats) records to keep, `0` disables recording
+	/// ```
+	/// use vach::prelude::ArchiveConfig;
+	/// let config = ArchiveConfig::default().fetch_stats(256);
+	/// ```
+	pub fn fetch_stats(mut self, capacity: usize) -> ArchiveConfig {
+		self.fetch_stats = capacity;
+		self
+	}
+
+	/// Setter for the slow fetch callback, called whenever decoding a fetched [`Resource`](crate::archive::Resource) takes longer than `threshold`
+	/// ```
+	/// use std::time::Duration;
+	/// use vach::prelude::{ArchiveConfig, FetchStats};
+	///
+	/// fn log(stats: &FetchStats) {
+	///   eprintln!("Slow asset: {}", stats)
+	/// }
+	///
+	/// let config = ArchiveConfig::default().slow_fetch(Duration::from_millis(50), log);
+	/// ```
+	pub fn slow_fetch(mut self, threshold: Duration, callback: SlowFetchCallback) -> ArchiveConfig {
+		self.slow_fetch = Some((threshold, callback));
+		self
+	}
+
+	/// Setter for the memory gate, called with an entry's decoded size before decoding it. Returning `false` refuses the fetch with an [`InternalError::MemoryBudgetError`],
+	/// so memory constrained platforms can defer or skip oversized loads instead of running out of memory. Entries without a recorded decoded size are not gated
+	/// ```
+	/// use vach::prelude::ArchiveConfig;
+	///
+	/// fn gate(size: usize) -> bool {
+	///   size <= 64 * 1024 * 1024
+	/// }
+	///
+	/// let config = ArchiveConfig::default().memory_gate(gate);
+	/// ```
+	pub fn memory_gate(mut self, gate: MemoryGate) -> ArchiveConfig {
+		self.memory_gate = Some(gate);
+		self
+	}
 }
 
 impl fmt::Display for ArchiveConfig {
@@ -85,7 +336,7 @@ impl fmt::Display for ArchiveConfig {
 
 		write!(
 			f,
-			"[ArchiveConfig] magic: {}, has_public_key: {}",
+			"[ArchiveConfig] magic: {}, read_buffer: {}B, fetch_stats: {}, has_public_key: {}",
 			match str::from_utf8(&self.magic) {
 				Ok(magic) => {
 					magic.to_string()
@@ -94,6 +345,8 @@ impl fmt::Display for ArchiveConfig {
 					format!("{:?}", &self.magic)
 				},
 			},
+			self.read_buffer,
+			self.fetch_stats,
 			has_pk
 		)
 	}
@@ -115,12 +368,107 @@ impl Default for ArchiveConfig {
 	}
 }
 
+/// Format features an archive relies on, recorded in its `Header` so loaders lacking one fail upfront with a precise error.
+/// See [`Archive::capabilities`](crate::archive::Archive::capabilities)
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+	bits: u32,
+}
+
+impl Capabilities {
+	/// Some entries are compressed, loading them requires the `compression` feature
+	pub const COMPRESSION: u32 = capabilities::COMPRESSION;
+	/// Some entries are encrypted, loading them requires the `crypto` feature
+	pub const ENCRYPTION: u32 = capabilities::ENCRYPTION;
+	/// Some entries went through a custom pipeline, see `Leaf::pipeline`
+	pub const PIPELINES: u32 = capabilities::PIPELINES;
+	/// The registry stores `ID`s relative to the previous entry's, see `BuilderConfig::prefix_ids`
+	pub const PREFIXED_IDS: u32 = capabilities::PREFIXED_IDS;
+	/// The header is followed by tagged extension records, eg: a key expiry date
+	pub const EXTENDED_HEADER: u32 = capabilities::EXTENDED_HEADER;
+	/// The registry is compressed and stored after the leaves, loading it requires the `compression` feature. See `BuilderConfig::compress_registry`
+	pub const COMPRESSED_REGISTRY: u32 = capabilities::COMPRESSED_REGISTRY;
+	/// Some entries were encrypted under rotated keys, see `ArchiveConfig::add_decryption_key`
+	pub const KEY_EPOCHS: u32 = capabilities::KEY_EPOCHS;
+
+	/// Every capability known to this version of `vach`
+	pub const KNOWN: u32 = capabilities::KNOWN;
+
+	/// Construct [`Capabilities`] from raw bits
+	#[inline(always)]
+	pub fn from_bits(bits: u32) -> Capabilities {
+		Capabilities { bits }
+	}
+
+	/// The raw bits
+	#[inline(always)]
+	pub fn bits(&self) -> u32 {
+		self.bits
+	}
+
+	/// Whether all the given capabilities are set
+	#[inline(always)]
+	pub fn contains(&self, bits: u32) -> bool {
+		(self.bits & bits) == bits
+	}
+
+	/// The capabilities needed to load an entry with the given flags
+	pub(crate) fn of(flags: &Flags) -> Capabilities {
+		let mut bits = 0;
+
+		if flags.contains(Flags::COMPRESSED_FLAG) {
+			bits |= Self::COMPRESSION;
+		}
+
+		if flags.contains(Flags::ENCRYPTED_FLAG) {
+			bits |= Self::ENCRYPTION;
+		}
+
+		if flags.contains(Flags::PIPELINE_FLAG) {
+			bits |= Self::PIPELINES;
+		}
+
+		if flags.contains(Flags::KEY_EPOCH_FLAG) {
+			bits |= Self::KEY_EPOCHS;
+		}
+
+		Capabilities { bits }
+	}
+
+	pub(crate) fn insert(&mut self, other: Capabilities) {
+		self.bits |= other.bits;
+	}
+
+	pub(crate) fn remove(&mut self, other: Capabilities) {
+		self.bits &= !other.bits;
+	}
+
+	/// Describes the first capability this build can't open the archive without, if any.
+	/// Compressed and encrypted entries only fail once fetched, with [`InternalError::MissingFeatureError`], so the rest stays readable
+	fn missing(&self) -> Option<String> {
+		if self.bits & !Self::KNOWN != 0 {
+			return Some(format!("unknown capabilities {:#x}, from a newer writer", self.bits & !Self::KNOWN));
+		}
+
+		if cfg!(not(feature = "compression")) && self.contains(Self::COMPRESSED_REGISTRY) {
+			return Some("a compressed registry, enable the `compression` feature".to_string());
+		}
+
+		None
+	}
+}
+
 #[derive(Debug)]
 pub(crate) struct Header {
 	pub magic: [u8; crate::MAGIC_LENGTH], // VfACH
 	pub flags: Flags,
 	pub arch_version: u16,
+	/// The oldest spec version able to load the archive
+	pub min_loader_version: u16,
+	pub capabilities: Capabilities,
 	pub capacity: u16,
+	/// Tagged records following the fixed part of the header, only written with [`Capabilities::EXTENDED_HEADER`]
+	pub extensions: Vec<(u8, Vec<u8>)>,
 }
 
 impl Default for Header {
@@ -130,47 +478,196 @@ impl Default for Header {
 			magic: *crate::DEFAULT_MAGIC,
 			flags: Flags::default(),
 			arch_version: crate::VERSION,
+			min_loader_version: crate::VERSION,
+			capabilities: Capabilities::default(),
 			capacity: 0,
+			extensions: Vec::new(),
 		}
 	}
 }
 
 impl Header {
-	pub const BASE_SIZE: usize = crate::MAGIC_LENGTH + Flags::SIZE + Self::VERSION_SIZE + Self::CAPACITY_SIZE;
+	pub const BASE_SIZE: usize = RawHeader::BASE_SIZE;
+
+	/// Where the capabilities lie, they are only known once all leaves are processed
+	pub const CAPABILITIES_OFFSET: usize = RawHeader::CAPABILITIES_OFFSET;
+
+	// Extension tags
+	/// When the key used to encrypt and|or sign the archive expires, `u64` seconds since the UNIX epoch
+	pub const KEY_EXPIRY: u8 = extensions::KEY_EXPIRY;
+	/// The name of the archive holding leaves shared with this one, see `PackSet::common`
+	pub const SHARED_ARCHIVE: u8 = extensions::SHARED_ARCHIVE;
+	/// A timestamp token over the archive's content, see `BuilderConfig::timestamp_authority`
+	#[cfg(feature = "crypto")]
+	pub const TIMESTAMP: u8 = extensions::TIMESTAMP;
+	/// Marks archives ending with a footer, see `BuilderConfig::footer`
+	pub const FOOTER: u8 = extensions::FOOTER;
+	/// Identifies the archive within signed messages, see `BuilderConfig::build_id`
+	#[cfg(feature = "crypto")]
+	pub const BUILD_ID: u8 = extensions::BUILD_ID;
+	/// Where the compressed registry lies, see `BuilderConfig::compress_registry`
+	pub const COMPRESSED_REGISTRY: u8 = extensions::COMPRESSED_REGISTRY;
+
+	/// Starts the signature domain, followed by the magic, spec version and build ID
+	#[cfg(feature = "crypto")]
+	const SIGNATURE_DOMAIN: &'static [u8; 16] = b"vach::signature\0";
 
-	// Data appears in this order
-	pub const VERSION_SIZE: usize = 2;
-	pub const CAPACITY_SIZE: usize = 2;
+	/// Reads and validates a `Header` with a template [ArchiveConfig]
+	pub(crate) fn from_handle<T: Read>(mut handle: T, config: &ArchiveConfig) -> InternalResult<Header> {
+		let mut prefix = [0u8; RawHeader::PREFIX_SIZE];
+		handle.read_exact(&mut prefix)?;
 
-	/// Validates a `Header` with a template [ArchiveConfig]
-	pub(crate) fn validate(config: &ArchiveConfig, header: &Header) -> InternalResult {
-		// Validate magic
-		if header.magic != config.magic {
-			return Err(InternalError::MalformedArchiveSource(header.magic));
+		let (magic, _, arch_version) = RawHeader::decode_prefix(&prefix);
+		if !config.accepts_magic(&magic) {
+			return Err(InternalError::MalformedArchiveSource(magic));
 		};
 
-		// Validate version
-		if crate::VERSION != header.arch_version {
+		// Older layouts can't be parsed, save for the legacy one. Newer ones say whether they remain readable
+		let raw = if arch_version == crate::LEGACY_VERSION {
+			let mut buffer = [0u8; RawHeader::LEGACY_SIZE];
+			buffer[..RawHeader::PREFIX_SIZE].copy_from_slice(&prefix);
+			handle.read_exact(&mut buffer[RawHeader::PREFIX_SIZE..])?;
+			RawHeader::decode_legacy(&buffer)
+		} else if arch_version < crate::VERSION {
+			return Err(InternalError::IncompatibleArchiveVersionError(arch_version));
+		} else {
+			let mut buffer: [u8; Header::BASE_SIZE] = [0u8; Header::BASE_SIZE];
+			buffer[..RawHeader::PREFIX_SIZE].copy_from_slice(&prefix);
+			handle.read_exact(&mut buffer[RawHeader::PREFIX_SIZE..])?;
+			RawHeader::decode(&buffer)
+		};
+
+		let mut header = Header {
+			magic: raw.magic,
+			flags: Flags::from_bits(raw.flags),
+			arch_version: raw.arch_version,
+			min_loader_version: raw.min_loader_version,
+			capabilities: Capabilities::from_bits(raw.capabilities),
+			capacity: raw.capacity,
+			extensions: Vec::new(),
+		};
+
+		if header.min_loader_version > crate::VERSION {
 			return Err(InternalError::IncompatibleArchiveVersionError(header.arch_version));
 		};
 
-		Ok(())
+		if let Some(missing) = header.capabilities.missing() {
+			return Err(InternalError::MissingCapabilityError(missing));
+		};
+
+		if header.capabilities.contains(Capabilities::EXTENDED_HEADER) {
+			header.extensions = Header::read_extensions(&mut handle)?;
+		}
+
+		if let Some(expiry) = header.key_expiry().filter(|_| config.enforce_key_expiry && header.key_expired()) {
+			return Err(InternalError::ExpiredKeyError(expiry));
+		}
+
+		Ok(header)
 	}
 
-	pub(crate) fn from_handle<T: Read>(mut handle: T) -> InternalResult<Header> {
-		let mut buffer: [u8; Header::BASE_SIZE] = [0u8; Header::BASE_SIZE];
-		handle.read_exact(&mut buffer)?;
-
-		// Construct header
-		Ok(Header {
-			// Read magic, [u8;5]
-			magic: buffer[0..crate::MAGIC_LENGTH].try_into().unwrap(),
-			// Read flags, u32 from [u8;4]
-			flags: Flags::from_bits(u32::from_le_bytes(buffer[crate::MAGIC_LENGTH..9].try_into().unwrap())),
-			// Read version, u16 from [u8;2]
-			arch_version: u16::from_le_bytes(buffer[9..11].try_into().unwrap()),
-			// Read the capacity of the archive, u16 from [u8;2]
-			capacity: u16::from_le_bytes(buffer[11..13].try_into().unwrap()),
-		})
+	/// Reads the length of the extension records, then the records themselves
+	fn read_extensions<T: Read>(mut handle: T) -> InternalResult<Vec<(u8, Vec<u8>)>> {
+		let mut length = [0u8; 4];
+		handle.read_exact(&mut length)?;
+
+		let mut block = Vec::new();
+		handle.take(u32::from_le_bytes(length) as u64).read_to_end(&mut block)?;
+
+		RawHeader::decode_extensions(&block)
+			.map_err(|_| InternalError::OtherError("Truncated header extension".into()))
+	}
+
+	/// The data of the extension record with the given tag, unknown tags are ignored by loaders
+	pub(crate) fn extension(&self, tag: u8) -> Option<&[u8]> {
+		self.extensions.iter().find(|(t, _)| *t == tag).map(|(_, data)| data.as_slice())
+	}
+
+	/// Adds or replaces an extension record, marking the header as extended
+	pub(crate) fn set_extension(&mut self, tag: u8, data: Vec<u8>) {
+		self.extensions.retain(|(t, _)| *t != tag);
+		self.extensions.push((tag, data));
+		self.capabilities.insert(Capabilities::from_bits(Capabilities::EXTENDED_HEADER));
+	}
+
+	/// Removes an extension record, the header stays extended
+	pub(crate) fn remove_extension(&mut self, tag: u8) {
+		self.extensions.retain(|(t, _)| *t != tag);
+	}
+
+	/// Where the compressed registry lies, `None` if the registry follows the header as is
+	pub(crate) fn registry_block(&self) -> InternalResult<Option<RegistryBlock>> {
+		if !self.capabilities.contains(Capabilities::COMPRESSED_REGISTRY) {
+			return Ok(None);
+		}
+
+		self.extension(Header::COMPRESSED_REGISTRY)
+			.and_then(|data| RegistryBlock::decode(data).ok())
+			.map(Some)
+			.ok_or_else(|| InternalError::OtherError("Missing or malformed compressed registry extension".into()))
+	}
+
+	/// When the archive's key expires, see `BuilderConfig::key_policy`
+	pub(crate) fn key_expiry(&self) -> Option<SystemTime> {
+		let seconds = self.extension(Header::KEY_EXPIRY)?.try_into().ok().map(u64::from_le_bytes)?;
+		UNIX_EPOCH.checked_add(Duration::from_secs(seconds))
+	}
+
+	/// The archive's build ID, `None` for archives signed before signatures were bound to the archive
+	#[cfg(feature = "crypto")]
+	pub(crate) fn build_id(&self) -> Option<[u8; 16]> {
+		self.extension(Header::BUILD_ID)?.try_into().ok()
+	}
+
+	/// Prefixed to every signed message, so a signature made for an entry can't be replayed for an entry of the same `ID` in another archive.
+	/// `None` for archives without a build ID, whose signatures only cover the data and entry
+	#[cfg(feature = "crypto")]
+	pub(crate) fn signature_domain(&self) -> Option<Vec<u8>> {
+		let build_id = self.build_id()?;
+
+		let mut domain = Vec::with_capacity(Header::SIGNATURE_DOMAIN.len() + crate::MAGIC_LENGTH + 2 + build_id.len());
+		domain.extend_from_slice(Header::SIGNATURE_DOMAIN);
+		domain.extend_from_slice(&self.magic);
+		domain.extend_from_slice(&self.arch_version.to_le_bytes());
+		domain.extend_from_slice(&build_id);
+
+		Some(domain)
+	}
+
+	/// The name of the shared archive this one was written against, see `PackSet::common`
+	pub(crate) fn shared_archive(&self) -> Option<&str> {
+		std::str::from_utf8(self.extension(Header::SHARED_ARCHIVE)?).ok()
+	}
+
+	pub(crate) fn key_expired(&self) -> bool {
+		self.key_expiry().is_some_and(|expiry| expiry <= SystemTime::now())
+	}
+
+	/// Whether the archive was written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), it's registry entries lack the decoded size
+	pub(crate) fn is_legacy(&self) -> bool {
+		self.arch_version == crate::LEGACY_VERSION
+	}
+
+	/// The size of the header including extensions, the registry starts right after
+	pub(crate) fn size(&self) -> usize {
+		match (self.is_legacy(), self.capabilities.contains(Capabilities::EXTENDED_HEADER)) {
+			(true, _) => RawHeader::LEGACY_SIZE,
+			(false, true) => Header::BASE_SIZE + RawHeader::extensions_size(&self.extensions),
+			(false, false) => Header::BASE_SIZE,
+		}
+	}
+
+	/// Serializes the `Header`, see `Header::from_handle`
+	pub(crate) fn to_bytes(&self) -> Vec<u8> {
+		RawHeader {
+			magic: self.magic,
+			flags: self.flags.bits(),
+			arch_version: self.arch_version,
+			min_loader_version: self.min_loader_version,
+			capabilities: self.capabilities.bits(),
+			capacity: self.capacity,
+			extensions: self.extensions.clone(),
+		}
+		.encode()
 	}
 }
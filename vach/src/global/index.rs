@@ -0,0 +1,171 @@
+use super::error::*;
+
+#[cfg(feature = "builder")]
+use super::entry_id::EntryId;
+
+/// The `ID` of the reserved leaf holding a perfect hash over an archive's `ID`s, written when `BuilderConfig::id_index` is set
+pub const INDEX_ID: &str = "vach::index";
+
+/// FNV-1a over the seed's little endian bytes followed by the `ID`, finished with MurmurHash3's `fmix64`. See [`IdIndex::hash`]
+const FNV_FMIX: u8 = 0;
+
+/// Marks a slot no `ID` hashes to
+const EMPTY: u16 = u16::MAX;
+
+/// `ID`s per bucket on average, bigger buckets shrink the index but take longer to place
+#[cfg(feature = "builder")]
+const BUCKET_SIZE: usize = 4;
+
+/// Seeds tried per bucket before giving up, far more than the free slots left by the load factor need
+#[cfg(feature = "builder")]
+const MAX_SEED: u32 = 1 << 16;
+
+/// A perfect hash over every `ID` in an archive, mapping each to the position of it's entry in the registry.
+/// Loaders in other languages can look entries up in constant time without building a hash map at load, see the spec for the layout.
+/// Any string hashes to some position, compare the `ID` of the entry found there to confirm a hit
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// let mut builder = Builder::new();
+/// builder.add(b"Hello, Cassandra!" as &[u8], "hello").unwrap();
+///
+/// let mut target = Cursor::new(Vec::new());
+/// builder.dump(&mut target, &BuilderConfig::default().id_index(true)).unwrap();
+///
+/// let archive = Archive::new(target).unwrap();
+/// let index = archive.id_index().unwrap().unwrap();
+/// assert_eq!(index.position("hello"), Some(0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdIndex {
+	seeds: Vec<u32>,
+	slots: Vec<u16>,
+}
+
+impl IdIndex {
+	/// Hashes an `ID` with the given seed. `IdIndex::position` picks a bucket with seed `0`, then a slot with the bucket's seed
+	pub fn hash(id: &[u8], seed: u32) -> u64 {
+		let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+		for byte in seed.to_le_bytes().iter().chain(id) {
+			hash ^= *byte as u64;
+			hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+		}
+
+		hash ^= hash >> 33;
+		hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+		hash ^= hash >> 33;
+		hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+		hash ^ (hash >> 33)
+	}
+
+	/// The registry position `id` hashes to, `None` if no `ID` does
+	pub fn position(&self, id: impl AsRef<str>) -> Option<usize> {
+		let id = id.as_ref().as_bytes();
+
+		let bucket = IdIndex::hash(id, 0) % self.seeds.len() as u64;
+		let slot = IdIndex::hash(id, self.seeds[bucket as usize]) % self.slots.len() as u64;
+
+		match self.slots[slot as usize] {
+			EMPTY => None,
+			position => Some(position as usize),
+		}
+	}
+
+	/// Builds a perfect hash over `ids`, each mapping to it's index in the slice. Uses hash and displace: `ID`s are grouped into buckets,
+	/// then starting with the largest bucket each is given the first seed that sends all of it's `ID`s to free slots
+	#[cfg(feature = "builder")]
+	pub(crate) fn build(ids: &[&str]) -> InternalResult<IdIndex> {
+		let bucket_count = ids.len().div_ceil(BUCKET_SIZE).max(1);
+		let slot_count = (ids.len() + ids.len() / 4).max(1);
+
+		let mut buckets = vec![Vec::new(); bucket_count];
+		for (position, id) in ids.iter().enumerate() {
+			let bucket = IdIndex::hash(id.as_bytes(), 0) % bucket_count as u64;
+			buckets[bucket as usize].push(position);
+		}
+
+		// Place the largest buckets first, while most slots are still free
+		let mut order = (0..bucket_count).filter(|b| !buckets[*b].is_empty()).collect::<Vec<_>>();
+		order.sort_by_key(|b| std::cmp::Reverse(buckets[*b].len()));
+
+		let mut seeds = vec![0; bucket_count];
+		let mut slots = vec![EMPTY; slot_count];
+		let mut taken = Vec::with_capacity(BUCKET_SIZE);
+
+		for bucket in order {
+			let positions = &buckets[bucket];
+
+			// Equal `ID`s collide under every seed
+			for (idx, position) in positions.iter().enumerate() {
+				if positions[..idx].iter().any(|other| ids[*other] == ids[*position]) {
+					return Err(InternalError::LeafAppendError(EntryId::from_raw(ids[*position])));
+				}
+			}
+
+			let seed = (1..MAX_SEED).find(|seed| {
+				taken.clear();
+				positions.iter().all(|position| {
+					let slot = (IdIndex::hash(ids[*position].as_bytes(), *seed) % slot_count as u64) as usize;
+					let free = slots[slot] == EMPTY && !taken.contains(&slot);
+
+					taken.push(slot);
+					free
+				})
+			});
+
+			seeds[bucket] = seed.ok_or_else(|| {
+				InternalError::InvalidConfigError("Unable to build a perfect hash over the archive's IDs".to_string())
+			})?;
+
+			for (position, slot) in positions.iter().zip(&taken) {
+				slots[*slot] = *position as u16;
+			}
+		}
+
+		Ok(IdIndex { seeds, slots })
+	}
+
+	/// Serializes the index: `[hash: u8][bucket_count: u32][slot_count: u32]` followed by `bucket_count` times `[seed: u32]`,
+	/// then `slot_count` times `[position: u16]`, `u16::MAX` marking empty slots
+	#[cfg(feature = "builder")]
+	pub(crate) fn encode(&self) -> Vec<u8> {
+		let mut buffer = Vec::with_capacity(9 + self.seeds.len() * 4 + self.slots.len() * 2);
+		buffer.push(FNV_FMIX);
+		buffer.extend_from_slice(&(self.seeds.len() as u32).to_le_bytes());
+		buffer.extend_from_slice(&(self.slots.len() as u32).to_le_bytes());
+
+		self.seeds.iter().for_each(|seed| buffer.extend_from_slice(&seed.to_le_bytes()));
+		self.slots.iter().for_each(|slot| buffer.extend_from_slice(&slot.to_le_bytes()));
+
+		buffer
+	}
+
+	/// Parses data written by [`IdIndex::encode`]
+	#[cfg(feature = "archive")]
+	pub(crate) fn decode(data: &[u8]) -> InternalResult<IdIndex> {
+		let invalid = || InternalError::ParseError("Invalid ID index".to_string());
+
+		let (hash, rest) = data.split_first().ok_or_else(invalid)?;
+		if *hash != FNV_FMIX {
+			return Err(InternalError::ParseError(format!("Unknown ID index hash: {}", hash)));
+		}
+
+		let (bucket_count, rest) = rest.split_first_chunk::<4>().ok_or_else(invalid)?;
+		let (slot_count, rest) = rest.split_first_chunk::<4>().ok_or_else(invalid)?;
+		let (bucket_count, slot_count) = (u32::from_le_bytes(*bucket_count) as usize, u32::from_le_bytes(*slot_count) as usize);
+
+		if bucket_count == 0 || slot_count == 0 || rest.len() != bucket_count * 4 + slot_count * 2 {
+			return Err(invalid());
+		}
+
+		let (seeds, slots) = rest.split_at(bucket_count * 4);
+		Ok(IdIndex {
+			seeds: seeds
+				.chunks_exact(4)
+				.map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+				.collect(),
+			slots: slots.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect(),
+		})
+	}
+}
@@ -0,0 +1,112 @@
+use super::entry_id::EntryId;
+
+/// What occupies a byte range of an archive, see [`LayoutReport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionKind {
+	/// The archive's header
+	Header,
+	/// The registry, holding an entry for every leaf. Compressed registries follow the leaves, see `BuilderConfig::compress_registry`
+	Registry,
+	/// The stored data of a leaf
+	Leaf(EntryId),
+	/// Bytes no entry points to, padding or data left behind by removed and replaced leaves
+	Unused,
+}
+
+/// A byte range of an archive, `start..end`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+	/// The first byte of the region
+	pub start: u64,
+	/// One past the last byte of the region
+	pub end: u64,
+	/// What occupies the region
+	pub kind: RegionKind,
+}
+
+impl Region {
+	/// The size of the region in bytes
+	pub fn len(&self) -> u64 {
+		self.end - self.start
+	}
+
+	/// Whether the region holds no bytes, only possible for empty leaves
+	pub fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+}
+
+/// A map of an archive's bytes, as returned by `Archive::layout_report`. Use it to visualize fragmentation and decide when an archive is worth compacting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutReport {
+	/// Every region of the archive ordered by `start`, together they cover the whole file
+	pub regions: Vec<Region>,
+	/// The size of the archive in bytes
+	pub size: u64,
+}
+
+impl LayoutReport {
+	/// Builds the report from where the header and registry end, the size of the archive and each leaf's range, along with a compressed registry's.
+	/// Leaves sharing bytes are reported as they are, only bytes no region covers are [`RegionKind::Unused`]
+	pub(crate) fn new(header_size: u64, registry_end: u64, size: u64, mut leaves: Vec<Region>) -> LayoutReport {
+		// A compressed registry leaves nothing between the header and the leaves
+		let compressed = leaves.iter().any(|r| r.kind == RegionKind::Registry);
+		leaves.sort_by_key(|r| (r.start, r.end));
+
+		let mut regions = Vec::with_capacity(leaves.len() * 2 + 2);
+		regions.push(Region {
+			start: 0,
+			end: header_size,
+			kind: RegionKind::Header,
+		});
+
+		if !compressed {
+			regions.push(Region {
+				start: header_size,
+				end: registry_end,
+				kind: RegionKind::Registry,
+			});
+		}
+
+		let mut covered = registry_end;
+		for leaf in leaves {
+			if leaf.start > covered {
+				regions.push(Region {
+					start: covered,
+					end: leaf.start,
+					kind: RegionKind::Unused,
+				});
+			}
+
+			covered = covered.max(leaf.end);
+			regions.push(leaf);
+		}
+
+		if size > covered {
+			regions.push(Region {
+				start: covered,
+				end: size,
+				kind: RegionKind::Unused,
+			});
+		}
+
+		LayoutReport { regions, size }
+	}
+
+	/// The total size of [`RegionKind::Unused`] regions, what compacting the archive would save
+	pub fn unused(&self) -> u64 {
+		self.regions
+			.iter()
+			.filter(|r| r.kind == RegionKind::Unused)
+			.map(Region::len)
+			.sum()
+	}
+
+	/// The fraction of the archive that is unused, from `0.0` to `1.0`
+	pub fn fragmentation(&self) -> f64 {
+		match self.size {
+			0 => 0.0,
+			size => self.unused() as f64 / size as f64,
+		}
+	}
+}
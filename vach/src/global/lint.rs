@@ -0,0 +1,229 @@
+use std::{
+	collections::{
+		hash_map::{DefaultHasher, Entry},
+		HashMap,
+	},
+	fmt,
+	hash::Hasher,
+};
+
+use super::entry_id::EntryId;
+
+/// Text leaves at least this large are worth compressing
+const TEXT_THRESHOLD: usize = 4 * 1024;
+/// Leaves smaller than this carry too little data for a meaningful entropy estimate
+const ENTROPY_SAMPLE: usize = 1024;
+/// Encrypted data should be indistinguishable from noise, `8.0` bits per byte
+const ENCRYPTED_MIN_ENTROPY: f64 = 7.5;
+/// Data this close to noise doesn't compress, it's likely already compressed or encrypted
+const INCOMPRESSIBLE_ENTROPY: f64 = 7.9;
+
+/// A potential problem found by `Archive::lint` or `Builder::lint`, or while writing, see `DumpReport::warnings`. None of these prevent an archive from working
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum LintWarning {
+	/// A large text leaf stored without compression
+	UncompressedText {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+		/// The size of the text in bytes
+		size: u64,
+	},
+	/// A leaf with the same content as another leaf
+	DuplicateContent {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+		/// The `ID` of the first leaf seen with this content
+		duplicate_of: EntryId,
+	},
+	/// An encrypted leaf whose stored bytes don't look encrypted
+	CompressibleEncrypted {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+		/// Bits of entropy per byte of the stored data, encrypted data comes close to `8.0`
+		entropy: f64,
+	},
+	/// A compressed leaf whose content looks incompressible, for example data encrypted before being added
+	IncompressibleCompressed {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+		/// Bits of entropy per byte of the content
+		entropy: f64,
+	},
+	/// A leaf whose custom pipeline compresses it's data after encrypting it, encrypted data doesn't shrink
+	CompressedAfterEncryption {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+	},
+	/// A leaf that grew when compressed with `CompressMode::Always`
+	CompressionGrew {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+		/// The size of the data before compressing it
+		before: u64,
+		/// The size of the compressed data
+		after: u64,
+	},
+	/// An `ID` using both `/` and `\` as separators
+	MixedSeparators {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+	},
+	/// An `ID` with a leading `/`, empty segments or `.` and `..` segments, which won't map cleanly onto paths
+	NonCanonicalId {
+		/// The `ID` of the offending leaf
+		id: EntryId,
+	},
+}
+
+impl LintWarning {
+	/// The `ID` of the offending leaf
+	pub fn id(&self) -> &EntryId {
+		match self {
+			LintWarning::UncompressedText { id, .. }
+			| LintWarning::DuplicateContent { id, .. }
+			| LintWarning::CompressibleEncrypted { id, .. }
+			| LintWarning::IncompressibleCompressed { id, .. }
+			| LintWarning::CompressedAfterEncryption { id }
+			| LintWarning::CompressionGrew { id, .. }
+			| LintWarning::MixedSeparators { id }
+			| LintWarning::NonCanonicalId { id } => id,
+		}
+	}
+
+	/// A short stable name for the kind of warning, eg: `uncompressed-text`
+	pub fn name(&self) -> &'static str {
+		match self {
+			LintWarning::UncompressedText { .. } => "uncompressed-text",
+			LintWarning::DuplicateContent { .. } => "duplicate-content",
+			LintWarning::CompressibleEncrypted { .. } => "compressible-encrypted",
+			LintWarning::IncompressibleCompressed { .. } => "incompressible-compressed",
+			LintWarning::CompressedAfterEncryption { .. } => "compressed-after-encryption",
+			LintWarning::CompressionGrew { .. } => "compression-grew",
+			LintWarning::MixedSeparators { .. } => "mixed-separators",
+			LintWarning::NonCanonicalId { .. } => "non-canonical-id",
+		}
+	}
+}
+
+impl fmt::Display for LintWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LintWarning::UncompressedText { id, size } => {
+				write!(f, "{}: {} bytes of text stored without compression", id, size)
+			},
+			LintWarning::DuplicateContent { id, duplicate_of } => write!(f, "{}: same content as {}", id, duplicate_of),
+			LintWarning::CompressibleEncrypted { id, entropy } => write!(
+				f,
+				"{}: encrypted, but the stored data only has {:.2} bits of entropy per byte",
+				id, entropy
+			),
+			LintWarning::IncompressibleCompressed { id, entropy } => write!(
+				f,
+				"{}: compressed, but the content has {:.2} bits of entropy per byte and is unlikely to shrink",
+				id, entropy
+			),
+			LintWarning::CompressedAfterEncryption { id } => {
+				write!(f, "{}: the pipeline compresses after encrypting, encrypted data doesn't shrink", id)
+			},
+			LintWarning::CompressionGrew { id, before, after } => {
+				write!(f, "{}: compressing grew the data from {} to {} bytes", id, before, after)
+			},
+			LintWarning::MixedSeparators { id } => write!(f, "{}: mixes `/` and `\\` as separators", id),
+			LintWarning::NonCanonicalId { id } => {
+				write!(f, "{}: has a leading `/`, empty segments or `.`|`..` segments", id)
+			},
+		}
+	}
+}
+
+/// Collects [`LintWarning`]s, shared by `Archive::lint` and `Builder::lint`
+#[derive(Default)]
+pub(crate) struct Linter {
+	seen: HashMap<(u64, usize), EntryId>,
+	warnings: Vec<LintWarning>,
+}
+
+impl Linter {
+	pub(crate) fn id(&mut self, id: &EntryId) {
+		if id.contains('/') && id.contains('\\') {
+			self.warnings.push(LintWarning::MixedSeparators { id: id.clone() });
+		}
+
+		let non_canonical = id.starts_with(['/', '\\']) || id.split(['/', '\\']).any(|s| matches!(s, "" | "." | ".."));
+		if non_canonical {
+			self.warnings.push(LintWarning::NonCanonicalId { id: id.clone() });
+		}
+	}
+
+	/// Checks the decoded content of a leaf
+	pub(crate) fn content(&mut self, id: &EntryId, data: &[u8], compressed: bool) {
+		if !compressed && data.len() >= TEXT_THRESHOLD && is_text(data) {
+			self.warnings.push(LintWarning::UncompressedText {
+				id: id.clone(),
+				size: data.len() as u64,
+			});
+		}
+
+		if compressed && data.len() >= ENTROPY_SAMPLE {
+			let entropy = entropy(data);
+			if entropy > INCOMPRESSIBLE_ENTROPY {
+				self.warnings.push(LintWarning::IncompressibleCompressed { id: id.clone(), entropy });
+			}
+		}
+
+		// Empty leaves are cheap, and usually placeholders
+		if !data.is_empty() {
+			let mut hasher = DefaultHasher::new();
+			hasher.write(data);
+
+			match self.seen.entry((hasher.finish(), data.len())) {
+				Entry::Occupied(original) => self.warnings.push(LintWarning::DuplicateContent {
+					id: id.clone(),
+					duplicate_of: original.get().clone(),
+				}),
+				Entry::Vacant(slot) => {
+					slot.insert(id.clone());
+				},
+			}
+		}
+	}
+
+	/// Checks the stored bytes of an encrypted leaf
+	pub(crate) fn encrypted(&mut self, id: &EntryId, raw: &[u8]) {
+		if raw.len() >= ENTROPY_SAMPLE {
+			let entropy = entropy(raw);
+			if entropy < ENCRYPTED_MIN_ENTROPY {
+				self.warnings.push(LintWarning::CompressibleEncrypted { id: id.clone(), entropy });
+			}
+		}
+	}
+
+	pub(crate) fn finish(self) -> Vec<LintWarning> {
+		self.warnings
+	}
+}
+
+/// Shannon entropy in bits per byte
+pub(crate) fn entropy(data: &[u8]) -> f64 {
+	let mut counts = [0usize; 256];
+	data.iter().for_each(|b| counts[*b as usize] += 1);
+
+	let len = data.len() as f64;
+	counts
+		.iter()
+		.filter(|c| **c > 0)
+		.map(|c| {
+			let p = *c as f64 / len;
+			-p * p.log2()
+		})
+		.sum()
+}
+
+/// Valid UTF-8 without control characters other than whitespace
+fn is_text(data: &[u8]) -> bool {
+	match std::str::from_utf8(data) {
+		Ok(text) => !text.chars().any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t')),
+		Err(_) => false,
+	}
+}
@@ -0,0 +1,258 @@
+use std::{
+	fs::{File, OpenOptions, TryLockError},
+	io::{self, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+	thread,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::error::*;
+
+// How often a lock with a timeout is retried
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How an [`ArchiveLock`] is held
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockMode {
+	/// Held by any number of readers at once, while no writer holds the lock
+	Shared,
+	/// Held by a single writer, while no one else holds the lock
+	Exclusive,
+}
+
+/// The writer recorded in a lock file while it holds an exclusive lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockOwner {
+	/// The writer's process ID
+	pub pid: u32,
+	/// When the writer took the lock, in whole seconds
+	pub acquired: SystemTime,
+}
+
+/// An advisory lock over an archive, so processes reading and writing the same archive, eg: an asset server hot-swapping archives
+/// and a packer writing them, take turns instead of reading half written files. Readers take shared locks, writers exclusive ones.
+///
+/// The lock is held on a sidecar file: the archive's path with `.lock` appended, so it outlives archives being replaced by a rename.
+/// Locks are advisory, only processes taking them are coordinated, and are released when dropped.
+///
+/// Locks are held by the OS, so a writer that crashes releases it's lock and no lock is ever left stale.
+/// What a crashed writer does leave behind is it's [`LockOwner`] record, which the next exclusive lock reclaims and returns from
+/// [`ArchiveLock::recovered`], hinting that the archive may be half written
+/// ```
+/// use vach::lock::{ArchiveLock, LockMode};
+///
+/// let path = std::env::temp_dir().join(format!("vach-lock-doc-{}.vach", std::process::id()));
+///
+/// let writer = ArchiveLock::exclusive(&path).unwrap();
+/// assert!(ArchiveLock::try_shared(&path).is_err());
+/// drop(writer);
+///
+/// let reader = ArchiveLock::shared(&path).unwrap();
+/// assert_eq!(reader.mode(), LockMode::Shared);
+/// # drop(reader);
+/// # std::fs::remove_file(ArchiveLock::lock_path(&path)).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ArchiveLock {
+	file: File,
+	path: PathBuf,
+	mode: LockMode,
+	recovered: Option<LockOwner>,
+}
+
+impl ArchiveLock {
+	/// The sidecar file locks over `archive` are held on: it's path with `.lock` appended
+	pub fn lock_path(archive: impl AsRef<Path>) -> PathBuf {
+		let mut path = archive.as_ref().as_os_str().to_owned();
+		path.push(".lock");
+		PathBuf::from(path)
+	}
+
+	/// Takes a shared lock over `archive`, blocking while a writer holds it
+	pub fn shared(archive: impl AsRef<Path>) -> InternalResult<ArchiveLock> {
+		ArchiveLock::acquire(archive, LockMode::Shared, None)
+	}
+
+	/// Takes an exclusive lock over `archive`, blocking while anyone else holds it
+	pub fn exclusive(archive: impl AsRef<Path>) -> InternalResult<ArchiveLock> {
+		ArchiveLock::acquire(archive, LockMode::Exclusive, None)
+	}
+
+	/// Takes a shared lock over `archive`, failing with [`InternalError::LockedError`] if a writer holds it
+	pub fn try_shared(archive: impl AsRef<Path>) -> InternalResult<ArchiveLock> {
+		ArchiveLock::acquire(archive, LockMode::Shared, Some(Duration::ZERO))
+	}
+
+	/// Takes an exclusive lock over `archive`, failing with [`InternalError::LockedError`] if anyone else holds it
+	pub fn try_exclusive(archive: impl AsRef<Path>) -> InternalResult<ArchiveLock> {
+		ArchiveLock::acquire(archive, LockMode::Exclusive, Some(Duration::ZERO))
+	}
+
+	/// Takes a lock over `archive`, waiting at most `timeout` for it before failing with [`InternalError::LockedError`]. Waits indefinitely without a timeout
+	pub fn acquire(archive: impl AsRef<Path>, mode: LockMode, timeout: Option<Duration>) -> InternalResult<ArchiveLock> {
+		let path = ArchiveLock::lock_path(archive);
+		let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+
+		let lock = |file: &File| match mode {
+			LockMode::Shared => file.try_lock_shared(),
+			LockMode::Exclusive => file.try_lock(),
+		};
+
+		match timeout {
+			None => match mode {
+				LockMode::Shared => file.lock_shared()?,
+				LockMode::Exclusive => file.lock()?,
+			},
+			Some(timeout) => {
+				let started = Instant::now();
+
+				loop {
+					match lock(&file) {
+						Ok(()) => break,
+						Err(TryLockError::Error(err)) => return Err(err.into()),
+						Err(TryLockError::WouldBlock) if started.elapsed() >= timeout => {
+							return Err(InternalError::LockedError(path))
+						},
+						Err(TryLockError::WouldBlock) => thread::sleep(RETRY_INTERVAL.min(timeout)),
+					}
+				}
+			},
+		}
+
+		// A record left in the file belongs to a writer that never released it's lock
+		let recovered = read_owner(&mut file)?;
+		let recovered = match mode {
+			LockMode::Exclusive => {
+				let acquired = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+
+				file.set_len(0)?;
+				file.seek(SeekFrom::Start(0))?;
+				write!(file, "{} {}", std::process::id(), acquired)?;
+				file.sync_data()?;
+
+				recovered
+			},
+			LockMode::Shared => None,
+		};
+
+		Ok(ArchiveLock {
+			file,
+			path,
+			mode,
+			recovered,
+		})
+	}
+
+	/// The writer holding an exclusive lock over `archive`, or that held one and crashed, if any. Doesn't take a lock
+	pub fn owner(archive: impl AsRef<Path>) -> InternalResult<Option<LockOwner>> {
+		match File::open(ArchiveLock::lock_path(archive)) {
+			Ok(mut file) => read_owner(&mut file),
+			Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+			Err(err) => Err(err.into()),
+		}
+	}
+
+	/// How the lock is held
+	pub fn mode(&self) -> LockMode {
+		self.mode
+	}
+
+	/// The path of the lock file, see [`ArchiveLock::lock_path`]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// The writer whose record this exclusive lock reclaimed: it held the lock and exited without releasing it, eg: by crashing.
+	/// The archive may be half written, so check it, eg: using it's footer, before relying on it
+	pub fn recovered(&self) -> Option<LockOwner> {
+		self.recovered
+	}
+}
+
+impl Drop for ArchiveLock {
+	fn drop(&mut self) {
+		// Clear the record before the lock is released, so only crashed writers leave one
+		if self.mode == LockMode::Exclusive {
+			let _ = self.file.set_len(0);
+		}
+
+		let _ = self.file.unlock();
+	}
+}
+
+fn read_owner(file: &mut File) -> InternalResult<Option<LockOwner>> {
+	let mut record = String::new();
+	file.seek(SeekFrom::Start(0))?;
+	file.read_to_string(&mut record)?;
+
+	// Records that don't parse are ignored, the lock file carries no other data
+	let mut fields = record.split_whitespace().map(str::parse::<u64>);
+	match (fields.next(), fields.next()) {
+		(Some(Ok(pid)), Some(Ok(acquired))) => Ok(Some(LockOwner {
+			pid: pid as u32,
+			acquired: UNIX_EPOCH + Duration::from_secs(acquired),
+		})),
+		_ => Ok(None),
+	}
+}
+
+/// A file opened together with an [`ArchiveLock`] over it, held until the [`LockedFile`] is dropped.
+/// Shared locks open the file for reading, exclusive ones for reading and writing, eg: for `ArchiveEditor::commit_in_place`
+/// ```skip
+/// let archive = Archive::new(LockedFile::open("assets.vach", LockMode::Shared)?)?;
+/// ```
+#[derive(Debug)]
+pub struct LockedFile {
+	file: File,
+	lock: ArchiveLock,
+}
+
+impl LockedFile {
+	/// Locks `path` and opens it, blocking until the lock is taken
+	pub fn open(path: impl AsRef<Path>, mode: LockMode) -> InternalResult<LockedFile> {
+		let lock = ArchiveLock::acquire(&path, mode, None)?;
+		LockedFile::with_lock(path, lock)
+	}
+
+	/// Opens `path` under a lock taken beforehand, eg: using [`ArchiveLock::try_exclusive`]
+	pub fn with_lock(path: impl AsRef<Path>, lock: ArchiveLock) -> InternalResult<LockedFile> {
+		let file = OpenOptions::new()
+			.read(true)
+			.write(lock.mode == LockMode::Exclusive)
+			.open(path)?;
+
+		Ok(LockedFile { file, lock })
+	}
+
+	/// The lock held over the file
+	pub fn lock(&self) -> &ArchiveLock {
+		&self.lock
+	}
+
+	/// Consume the [`LockedFile`], returning the file and it's lock. The file stays locked until the lock is dropped
+	pub fn into_parts(self) -> (File, ArchiveLock) {
+		(self.file, self.lock)
+	}
+}
+
+impl Read for LockedFile {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.file.read(buf)
+	}
+}
+
+impl Write for LockedFile {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.file.write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.file.flush()
+	}
+}
+
+impl Seek for LockedFile {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		self.file.seek(pos)
+	}
+}
@@ -0,0 +1,146 @@
+use std::{
+	collections::BTreeMap,
+	fmt::Write as _,
+	io::Write,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::error::*;
+
+/// Measurements of a single run of an operation, eg: packing an archive, handed to a [`Reporter`] so build farms can trend performance over time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics {
+	/// What ran, eg: `pack`
+	pub operation: String,
+	/// When the run started
+	pub started: SystemTime,
+	/// Wall time of the run
+	pub elapsed: Duration,
+	/// Bytes read from sources
+	pub bytes_in: u64,
+	/// Bytes written into targets
+	pub bytes_out: u64,
+	/// Entries processed
+	pub entries: usize,
+	/// Further measurements by name, eg: the time spent in each stage in seconds
+	pub extra: BTreeMap<String, f64>,
+}
+
+impl Metrics {
+	/// Empty metrics for a run of `operation` starting now
+	pub fn new(operation: impl Into<String>) -> Metrics {
+		Metrics {
+			operation: operation.into(),
+			started: SystemTime::now(),
+			elapsed: Duration::ZERO,
+			bytes_in: 0,
+			bytes_out: 0,
+			entries: 0,
+			extra: BTreeMap::new(),
+		}
+	}
+
+	/// Bytes written per byte read, eg: the compression ratio of a pack. `0.0` if nothing was read
+	pub fn ratio(&self) -> f64 {
+		match self.bytes_in {
+			0 => 0.0,
+			read => self.bytes_out as f64 / read as f64,
+		}
+	}
+
+	/// Encodes the metrics as a single line JSON object, `ratio` included. Non finite measurements are written as `null`
+	pub fn to_json(&self) -> String {
+		let number = |value: f64| match value.is_finite() {
+			true => value.to_string(),
+			false => "null".to_string(),
+		};
+
+		let started = self.started.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+		let mut json = format!(
+			"{{\"operation\":{},\"started\":{},\"elapsed\":{},\"bytes_in\":{},\"bytes_out\":{},\"ratio\":{},\"entries\":{},\"extra\":{{",
+			quote(&self.operation),
+			started,
+			number(self.elapsed.as_secs_f64()),
+			self.bytes_in,
+			self.bytes_out,
+			number(self.ratio()),
+			self.entries
+		);
+
+		for (idx, (name, value)) in self.extra.iter().enumerate() {
+			let separator = if idx == 0 { "" } else { "," };
+			let _ = write!(json, "{}{}:{}", separator, quote(name), number(*value));
+		}
+
+		json.push_str("}}");
+		json
+	}
+}
+
+/// A sink for [`Metrics`], eg: a file, a time series database or a build farm's telemetry endpoint
+pub trait Reporter {
+	/// Records the metrics of one run
+	fn report(&mut self, metrics: &Metrics) -> InternalResult;
+}
+
+/// Writes each run's [`Metrics`] as a line of JSON, see `Metrics::to_json`. Open files in append mode to keep a history of runs
+/// ```
+/// use vach::metrics::{JsonLines, Metrics, Reporter};
+///
+/// let mut metrics = Metrics::new("pack");
+/// (metrics.bytes_in, metrics.bytes_out) = (4096, 1024);
+///
+/// let mut reporter = JsonLines::new(Vec::new());
+/// reporter.report(&metrics).unwrap();
+///
+/// let line = String::from_utf8(reporter.into_inner()).unwrap();
+/// assert!(line.starts_with("{\"operation\":\"pack\""));
+/// assert!(line.contains("\"ratio\":0.25"));
+/// ```
+#[derive(Debug)]
+pub struct JsonLines<W> {
+	writer: W,
+}
+
+impl<W: Write> JsonLines<W> {
+	/// Reports into `writer`
+	pub fn new(writer: W) -> JsonLines<W> {
+		JsonLines { writer }
+	}
+
+	/// Consume the [`JsonLines`], returning the wrapped writer
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+impl<W: Write> Reporter for JsonLines<W> {
+	fn report(&mut self, metrics: &Metrics) -> InternalResult {
+		writeln!(self.writer, "{}", metrics.to_json())?;
+		self.writer.flush()?;
+		Ok(())
+	}
+}
+
+/// Quotes and escapes a JSON string
+fn quote(value: &str) -> String {
+	let mut quoted = String::with_capacity(value.len() + 2);
+	quoted.push('"');
+
+	for c in value.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			c if (c as u32) < 0x20 => {
+				let _ = write!(quoted, "\\u{:04x}", c as u32);
+			},
+			c => quoted.push(c),
+		}
+	}
+
+	quoted.push('"');
+	quoted
+}
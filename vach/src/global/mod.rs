@@ -1,8 +1,32 @@
 // Globally available exports
 pub mod error;
 
+pub mod entry_id;
+
+pub mod dependencies;
+pub mod descriptor;
+#[cfg(feature = "archive")]
+pub mod directory;
+
 pub mod flags;
 pub mod header;
+pub mod index;
+pub mod layout;
+pub mod lint;
+pub mod lock;
+pub mod metrics;
+pub mod path_mapper;
+pub mod pipeline;
+pub mod profile;
+#[cfg(feature = "archive")]
+pub mod query;
 pub mod reg_entry;
+pub mod stats;
+pub(crate) mod sync;
+pub mod tags;
+pub mod validity;
+#[cfg(feature = "archive")]
+pub mod verify;
+pub mod vfs;
 
 pub mod compressor;
@@ -0,0 +1,55 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::error::*;
+
+/// Maps file paths to `ID`s when packing with `Builder::add_dir_recursive`, and `ID`s back to file paths when extracting with `Archive::unpack_to`.
+/// Implement both directions together, so archives round trip through a directory. The default is [`SeparatorMapper`]
+pub trait PathMapper {
+	/// The `ID` of the file at `path`, relative to the directory being packed
+	fn to_id(&self, path: &Path) -> InternalResult<String>;
+
+	/// Where to write the entry `id`, relative to the directory being extracted into.
+	/// Must not produce absolute paths or `..` components, these are rejected by `Archive::unpack_to` regardless
+	fn to_path(&self, id: &str) -> InternalResult<PathBuf>;
+}
+
+/// Every `/` in an `ID` is a directory: `textures/ui/button.png` is extracted to `<target>/textures/ui/button.png` and vice versa
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SeparatorMapper;
+
+impl PathMapper for SeparatorMapper {
+	fn to_id(&self, path: &Path) -> InternalResult<String> {
+		let components = path
+			.components()
+			.map(|component| match component {
+				Component::Normal(name) => name.to_str(),
+				_ => None,
+			})
+			.collect::<Option<Vec<_>>>();
+
+		match components {
+			Some(components) => Ok(components.join("/")),
+			None => Err(InternalError::ParseError(format!("{} can't be mapped to an ID", path.display()))),
+		}
+	}
+
+	fn to_path(&self, id: &str) -> InternalResult<PathBuf> {
+		Ok(id.split('/').collect())
+	}
+}
+
+/// Resolves `id` within `root` using `mapper`, rejecting paths that would escape `root`
+#[cfg(feature = "archive")]
+pub(crate) fn resolve(mapper: &dyn PathMapper, root: &Path, id: &str) -> InternalResult<PathBuf> {
+	let relative = mapper.to_path(id)?;
+
+	let escapes = relative.as_os_str().is_empty()
+		|| relative
+			.components()
+			.any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+
+	match escapes {
+		true => Err(InternalError::ParseError(format!("{} maps to an unsafe path: {}", id, relative.display()))),
+		false => Ok(root.join(relative)),
+	}
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use super::{error::*, flags::Flags};
+
+/// A custom transform, given a leaf's data as it stands at that point of the pipeline and returning the transformed data
+pub type StageFn = fn(&[u8]) -> InternalResult<Vec<u8>>;
+
+/// One step of a [`Leaf`](crate::builder::Leaf)'s processing pipeline, see `Leaf::pipeline`
+#[derive(Clone, Copy)]
+pub enum PipelineStage {
+	/// Compresses the data, only if compression is enabled using `Leaf::compress`
+	Compress,
+	/// Encrypts the data, only if encryption is enabled using `Leaf::encrypt`
+	Encrypt,
+	/// A user defined transform, eg: texture swizzling or delta encoding
+	Custom {
+		/// A single bit from the lower 16 bits of [`Flags`], set on every entry that went through this stage so loaders know to undo it
+		flag: u32,
+		/// Transforms the data
+		encode: StageFn,
+	},
+}
+
+impl fmt::Debug for PipelineStage {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PipelineStage::Compress => f.write_str("Compress"),
+			PipelineStage::Encrypt => f.write_str("Encrypt"),
+			PipelineStage::Custom { flag, .. } => write!(f, "Custom({:#x})", flag),
+		}
+	}
+}
+
+/// The pipeline of leaves without a custom one, no descriptor is written for these
+pub(crate) const DEFAULT_PIPELINE: &[PipelineStage] = &[PipelineStage::Compress, PipelineStage::Encrypt];
+
+// Codes recorded in the pipeline descriptor, custom stages are recorded as `CUSTOM` plus the index of their flag bit
+pub(crate) const COMPRESS: u8 = 0;
+pub(crate) const ENCRYPT: u8 = 1;
+const CUSTOM: u8 = 2;
+
+/// The descriptor code of a custom stage's flag
+pub(crate) fn custom_code(flag: u32) -> u8 {
+	CUSTOM + flag.trailing_zeros() as u8
+}
+
+/// The flag of a custom stage's descriptor code
+pub(crate) fn custom_flag(code: u8) -> Option<u32> {
+	(CUSTOM..CUSTOM + 16).contains(&code).then(|| 1 << (code - CUSTOM))
+}
+
+/// Checks a custom pipeline can be recorded and mirrored by loaders
+pub(crate) fn validate(stages: &[PipelineStage]) -> InternalResult {
+	let invalid = |msg: String| Err(InternalError::InvalidConfigError(msg));
+
+	if stages.len() > u8::MAX as usize {
+		return invalid(format!("A pipeline holds at most {} stages, found {}", u8::MAX, stages.len()));
+	}
+
+	let mut seen = 0u32;
+	let (mut compress, mut encrypt) = (false, false);
+
+	for stage in stages {
+		match stage {
+			PipelineStage::Compress if compress => return invalid("Compress appears more than once in the pipeline".into()),
+			PipelineStage::Encrypt if encrypt => return invalid("Encrypt appears more than once in the pipeline".into()),
+			PipelineStage::Compress => compress = true,
+			PipelineStage::Encrypt => encrypt = true,
+			PipelineStage::Custom { flag, .. } => {
+				if flag.count_ones() != 1 || flag & Flags::RESERVED_MASK != 0 {
+					return invalid(format!(
+						"Custom stages need a single bit from the lower 16 bits of Flags, found {:#034b}",
+						flag
+					));
+				}
+
+				if seen & flag != 0 {
+					return invalid(format!("The custom stage flag {:#x} is used more than once", flag));
+				}
+
+				seen |= flag;
+			},
+		}
+	}
+
+	Ok(())
+}
+
+/// Prefixes the processed data with the codes of the stages that ran, in order
+pub(crate) fn with_descriptor(codes: &[u8], data: Vec<u8>) -> Vec<u8> {
+	let mut buffer = Vec::with_capacity(1 + codes.len() + data.len());
+	buffer.push(codes.len() as u8);
+	buffer.extend_from_slice(codes);
+	buffer.extend_from_slice(&data);
+	buffer
+}
+
+/// Splits stored data into the codes of the stages that ran, in order, and the processed data
+pub(crate) fn split_descriptor(raw: &[u8]) -> InternalResult<(&[u8], &[u8])> {
+	match raw.split_first() {
+		Some((count, rest)) if rest.len() >= *count as usize => Ok(rest.split_at(*count as usize)),
+		_ => Err(InternalError::OtherError("Truncated pipeline descriptor".into())),
+	}
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+#[cfg(feature = "archive")]
+use std::{collections::HashSet, time::Instant};
+
+#[cfg(feature = "builder")]
+use std::collections::HashMap;
+
+use super::{entry_id::EntryId, error::*};
+
+/// The order in which entries were first fetched, recorded by an [`Archive`](crate::archive::Archive) opened with `ArchiveConfig::record_access`.
+/// Feed it back into `BuilderConfig::layout_from_profile` so leaves are laid out in the order they're actually loaded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessProfile {
+	/// Each entry's first fetch, in order
+	pub accesses: Vec<Access>,
+}
+
+/// A single entry of an [`AccessProfile`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+	/// The `ID` of the fetched entry
+	pub id: EntryId,
+	/// When the entry was first fetched, since recording started
+	pub at: Duration,
+}
+
+impl AccessProfile {
+	const MAGIC: &'static [u8; 4] = b"VPRF";
+
+	/// Serializes the profile, to store it between a profiling run and the next build
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buffer = Vec::with_capacity(8 + self.accesses.iter().map(|a| 10 + a.id.len()).sum::<usize>());
+		buffer.extend_from_slice(Self::MAGIC);
+		buffer.extend_from_slice(&(self.accesses.len() as u32).to_le_bytes());
+
+		for access in &self.accesses {
+			buffer.extend_from_slice(&(access.at.as_micros() as u64).to_le_bytes());
+			buffer.extend_from_slice(&(access.id.len() as u16).to_le_bytes());
+			buffer.extend_from_slice(access.id.as_bytes());
+		}
+
+		buffer
+	}
+
+	/// Parses a profile written by [`AccessProfile::to_bytes`]
+	pub fn from_bytes(bytes: &[u8]) -> InternalResult<AccessProfile> {
+		let invalid = || InternalError::ParseError("Invalid access profile".to_string());
+
+		let rest = bytes.strip_prefix(Self::MAGIC).ok_or_else(invalid)?;
+		let (count, mut rest) = rest.split_first_chunk::<4>().ok_or_else(invalid)?;
+
+		let mut accesses = Vec::with_capacity((u32::from_le_bytes(*count) as usize).min(rest.len() / 10));
+		for _ in 0..u32::from_le_bytes(*count) {
+			let (at, tail) = rest.split_first_chunk::<8>().ok_or_else(invalid)?;
+			let (len, tail) = tail.split_first_chunk::<2>().ok_or_else(invalid)?;
+
+			let len = u16::from_le_bytes(*len) as usize;
+			if tail.len() < len {
+				return Err(invalid());
+			}
+
+			let (id, tail) = tail.split_at(len);
+			let id = std::str::from_utf8(id).map_err(|_| invalid())?;
+
+			accesses.push(Access {
+				id: EntryId::from_raw(id),
+				at: Duration::from_micros(u64::from_le_bytes(*at)),
+			});
+
+			rest = tail;
+		}
+
+		Ok(AccessProfile { accesses })
+	}
+
+	/// The position of each `ID` within the profile
+	#[cfg(feature = "builder")]
+	pub(crate) fn ranks(&self) -> HashMap<&str, usize> {
+		self.accesses
+			.iter()
+			.enumerate()
+			.map(|(rank, access)| (access.id.as_ref(), rank))
+			.collect()
+	}
+}
+
+/// Records the first fetch of every entry, see [`AccessProfile`]
+#[derive(Debug)]
+#[cfg(feature = "archive")]
+pub(crate) struct AccessRecorder {
+	started: Instant,
+	seen: HashSet<EntryId>,
+	profile: AccessProfile,
+}
+
+#[cfg(feature = "archive")]
+impl AccessRecorder {
+	pub(crate) fn new() -> AccessRecorder {
+		AccessRecorder {
+			started: Instant::now(),
+			seen: HashSet::new(),
+			profile: AccessProfile::default(),
+		}
+	}
+
+	pub(crate) fn record(&mut self, id: &EntryId) {
+		if self.seen.insert(id.clone()) {
+			self.profile.accesses.push(Access {
+				id: id.clone(),
+				at: self.started.elapsed(),
+			});
+		}
+	}
+
+	pub(crate) fn profile(&self) -> &AccessProfile {
+		&self.profile
+	}
+
+	pub(crate) fn into_profile(self) -> AccessProfile {
+		self.profile
+	}
+}
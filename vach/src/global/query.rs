@@ -0,0 +1,361 @@
+use std::{fmt, str::FromStr};
+
+use super::{error::*, flags::Flags, reg_entry::RegistryEntry};
+
+/// A filter over [`RegistryEntry`]s, parsed from expressions like `size > 1MB && flag(ENCRYPTED) && id ~ "textures/*"`.
+///
+/// - `id == "a"`, `id != "a"` compare `ID`s, `id ~ "glob"` matches them against a glob: `*` matches within a path segment, `**` across segments and `?` a single character
+/// - `size`, `decoded_size` and `version` compare against numbers with `==`, `!=`, `<`, `<=`, `>` and `>=`. Sizes take decimal (`KB`, `MB`, `GB`) and binary (`KiB`, `MiB`, `GiB`) units
+/// - `flag(NAME)` checks for a flag: `COMPRESSED`, `ENCRYPTED`, `SIGNED`, `PIPELINE`, `KEY_EPOCH`, `TOMBSTONE`, `LZ4`, `SNAPPY`, `BROTLI` or `DEFLATE`, or a custom bit as a number
+/// - Conditions combine with `&&`, `||`, `!` and parentheses, `&&` binds tighter than `||`
+/// ```
+/// use vach::archive::{Query, RegistryEntry, Flags};
+///
+/// let query: Query = "size > 1KB && !flag(COMPRESSED)".parse().unwrap();
+///
+/// let mut entry = RegistryEntry::default();
+/// entry.offset = 4096;
+/// assert!(query.matches(&entry));
+///
+/// entry.flags = Flags::from_bits(Flags::COMPRESSED_FLAG);
+/// assert!(!query.matches(&entry));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Query {
+	expr: String,
+	root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+	And(Box<Node>, Box<Node>),
+	Or(Box<Node>, Box<Node>),
+	Not(Box<Node>),
+	Compare(Field, Op, u64),
+	Id(Op, String),
+	Glob(String),
+	Flag(u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+	Size,
+	DecodedSize,
+	Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	Match,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Ident(String),
+	Str(String),
+	Num(u64),
+	Op(Op),
+	And,
+	Or,
+	Not,
+	Open,
+	Close,
+}
+
+impl Query {
+	/// Parses a filter expression, see [`Query`] for the syntax
+	pub fn parse(expr: &str) -> InternalResult<Query> {
+		let tokens = tokenize(expr)?;
+
+		let mut parser = Parser { tokens: &tokens, pos: 0 };
+		let root = parser.or()?;
+
+		if let Some(token) = parser.tokens.get(parser.pos) {
+			return Err(invalid(format!("unexpected {:?}", token)));
+		}
+
+		Ok(Query {
+			expr: expr.to_string(),
+			root,
+		})
+	}
+
+	/// Whether the entry satisfies this [`Query`]
+	pub fn matches(&self, entry: &RegistryEntry) -> bool {
+		self.root.eval(entry)
+	}
+}
+
+impl FromStr for Query {
+	type Err = InternalError;
+
+	fn from_str(expr: &str) -> InternalResult<Query> {
+		Query::parse(expr)
+	}
+}
+
+impl fmt::Display for Query {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.expr)
+	}
+}
+
+impl Node {
+	fn eval(&self, entry: &RegistryEntry) -> bool {
+		match self {
+			Node::And(a, b) => a.eval(entry) && b.eval(entry),
+			Node::Or(a, b) => a.eval(entry) || b.eval(entry),
+			Node::Not(node) => !node.eval(entry),
+			Node::Compare(field, op, value) => {
+				let actual = match field {
+					Field::Size => entry.offset,
+					Field::DecodedSize => entry.decoded_size,
+					Field::Version => entry.content_version as u64,
+				};
+
+				match op {
+					Op::Eq => actual == *value,
+					Op::Ne => actual != *value,
+					Op::Lt => actual < *value,
+					Op::Le => actual <= *value,
+					Op::Gt => actual > *value,
+					Op::Ge => actual >= *value,
+					Op::Match => false,
+				}
+			},
+			Node::Id(op, id) => (entry.id.as_ref() == id) == (*op == Op::Eq),
+			Node::Glob(pattern) => glob(pattern, &entry.id),
+			Node::Flag(bit) => entry.flags.contains(*bit),
+		}
+	}
+}
+
+fn invalid(msg: String) -> InternalError {
+	InternalError::ParseError(format!("Invalid query, {}", msg))
+}
+
+fn tokenize(expr: &str) -> InternalResult<Vec<Token>> {
+	let mut tokens = Vec::new();
+	let mut chars = expr.char_indices().peekable();
+
+	while let Some((start, c)) = chars.next() {
+		let next = chars.peek().map(|(_, c)| *c);
+
+		let token = match (c, next) {
+			(c, _) if c.is_whitespace() => continue,
+			('&', Some('&')) | ('|', Some('|')) | ('=', Some('=')) | ('!', Some('=')) | ('<', Some('=')) | ('>', Some('=')) => {
+				chars.next();
+
+				match c {
+					'&' => Token::And,
+					'|' => Token::Or,
+					'=' => Token::Op(Op::Eq),
+					'!' => Token::Op(Op::Ne),
+					'<' => Token::Op(Op::Le),
+					_ => Token::Op(Op::Ge),
+				}
+			},
+			('<', _) => Token::Op(Op::Lt),
+			('>', _) => Token::Op(Op::Gt),
+			('~', _) => Token::Op(Op::Match),
+			('!', _) => Token::Not,
+			('(', _) => Token::Open,
+			(')', _) => Token::Close,
+			('"', _) => {
+				let mut string = String::new();
+
+				loop {
+					match chars.next() {
+						Some((_, '"')) => break,
+						Some((_, '\\')) => match chars.next() {
+							Some((_, c)) => string.push(c),
+							None => return Err(invalid("unterminated string".into())),
+						},
+						Some((_, c)) => string.push(c),
+						None => return Err(invalid("unterminated string".into())),
+					}
+				}
+
+				Token::Str(string)
+			},
+			(c, _) if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+				let mut end = start + c.len_utf8();
+				while let Some((idx, c)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_' || *c == '.') {
+					end = idx + c.len_utf8();
+				}
+
+				let word = &expr[start..end];
+				match c.is_ascii_digit() {
+					true => Token::Num(number(word)?),
+					false => Token::Ident(word.to_string()),
+				}
+			},
+			(c, _) => return Err(invalid(format!("unexpected character '{}' at {}", c, start))),
+		};
+
+		tokens.push(token);
+	}
+
+	Ok(tokens)
+}
+
+/// Parses numbers with an optional size unit, eg: `512`, `0x10` or `1.5MiB`. Units are case insensitive
+fn number(word: &str) -> InternalResult<u64> {
+	if let Some(hex) = word.strip_prefix("0x") {
+		return u64::from_str_radix(hex, 16).map_err(|_| invalid(format!("invalid number {}", word)));
+	}
+
+	let split = word.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(word.len());
+	let (value, unit) = word.split_at(split);
+
+	let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+		"" | "b" => 1,
+		"kb" => 1000,
+		"kib" => 1 << 10,
+		"mb" => 1000 * 1000,
+		"mib" => 1 << 20,
+		"gb" => 1000 * 1000 * 1000,
+		"gib" => 1 << 30,
+		_ => return Err(invalid(format!("unknown unit {} in {}", unit, word))),
+	};
+
+	match value.parse::<f64>() {
+		Ok(value) if value >= 0.0 => Ok((value * multiplier as f64) as u64),
+		_ => Err(invalid(format!("invalid number {}", word))),
+	}
+}
+
+struct Parser<'a> {
+	tokens: &'a [Token],
+	pos: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn next(&mut self) -> Option<&'a Token> {
+		let token = self.tokens.get(self.pos);
+		self.pos += 1;
+		token
+	}
+
+	fn eat(&mut self, token: &Token) -> bool {
+		let found = self.tokens.get(self.pos) == Some(token);
+		self.pos += found as usize;
+		found
+	}
+
+	fn or(&mut self) -> InternalResult<Node> {
+		let mut node = self.and()?;
+		while self.eat(&Token::Or) {
+			node = Node::Or(Box::new(node), Box::new(self.and()?));
+		}
+
+		Ok(node)
+	}
+
+	fn and(&mut self) -> InternalResult<Node> {
+		let mut node = self.unary()?;
+		while self.eat(&Token::And) {
+			node = Node::And(Box::new(node), Box::new(self.unary()?));
+		}
+
+		Ok(node)
+	}
+
+	fn unary(&mut self) -> InternalResult<Node> {
+		match self.next() {
+			Some(Token::Not) => Ok(Node::Not(Box::new(self.unary()?))),
+			Some(Token::Open) => {
+				let node = self.or()?;
+				match self.eat(&Token::Close) {
+					true => Ok(node),
+					false => Err(invalid("missing ')'".into())),
+				}
+			},
+			Some(Token::Ident(name)) if name == "flag" => self.flag(),
+			Some(Token::Ident(name)) => self.condition(name),
+			Some(token) => Err(invalid(format!("unexpected {:?}", token))),
+			None => Err(invalid("unexpected end of the expression".into())),
+		}
+	}
+
+	fn flag(&mut self) -> InternalResult<Node> {
+		if !self.eat(&Token::Open) {
+			return Err(invalid("expected '(' after flag".into()));
+		}
+
+		let bit = match self.next() {
+			Some(Token::Num(bit)) if *bit <= u32::MAX as u64 => *bit as u32,
+			Some(Token::Ident(name)) => Flags::bit_by_name(name).ok_or_else(|| invalid(format!("unknown flag {}", name)))?,
+			token => return Err(invalid(format!("expected a flag name, found {:?}", token))),
+		};
+
+		match self.eat(&Token::Close) {
+			true => Ok(Node::Flag(bit)),
+			false => Err(invalid("missing ')'".into())),
+		}
+	}
+
+	fn condition(&mut self, name: &str) -> InternalResult<Node> {
+		let op = match self.next() {
+			Some(Token::Op(op)) => *op,
+			token => return Err(invalid(format!("expected an operator after {}, found {:?}", name, token))),
+		};
+
+		let field = match name {
+			"size" => Field::Size,
+			"decoded_size" => Field::DecodedSize,
+			"version" => Field::Version,
+			"id" => {
+				return match (op, self.next()) {
+					(Op::Match, Some(Token::Str(pattern))) => Ok(Node::Glob(pattern.clone())),
+					(Op::Eq | Op::Ne, Some(Token::Str(id))) => Ok(Node::Id(op, id.clone())),
+					(Op::Match | Op::Eq | Op::Ne, token) => Err(invalid(format!("expected a string, found {:?}", token))),
+					_ => Err(invalid("id only supports ==, != and ~".into())),
+				}
+			},
+			_ => return Err(invalid(format!("unknown field {}", name))),
+		};
+
+		match (op, self.next()) {
+			(Op::Match, _) => Err(invalid(format!("{} can't be matched against a glob", name))),
+			(op, Some(Token::Num(value))) => Ok(Node::Compare(field, op, *value)),
+			(_, token) => Err(invalid(format!("expected a number, found {:?}", token))),
+		}
+	}
+}
+
+/// Matches `text` against a glob, `*` doesn't cross `/` while `**` does
+pub(crate) fn glob(pattern: &str, text: &str) -> bool {
+	if let Some(rest) = pattern.strip_prefix("**") {
+		return text
+			.char_indices()
+			.map(|(idx, _)| idx)
+			.chain([text.len()])
+			.any(|idx| glob(rest, &text[idx..]));
+	}
+
+	if let Some(rest) = pattern.strip_prefix('*') {
+		let segment = text.find('/').unwrap_or(text.len());
+		return text[..segment]
+			.char_indices()
+			.map(|(idx, _)| idx)
+			.chain([segment])
+			.any(|idx| glob(rest, &text[idx..]));
+	}
+
+	let mut pattern_chars = pattern.chars();
+	let mut text_chars = text.chars();
+
+	match (pattern_chars.next(), text_chars.next()) {
+		(None, None) => true,
+		(Some('?'), Some(c)) if c != '/' => glob(pattern_chars.as_str(), text_chars.as_str()),
+		(Some(p), Some(c)) if p == c && p != '?' => glob(pattern_chars.as_str(), text_chars.as_str()),
+		_ => false,
+	}
+}
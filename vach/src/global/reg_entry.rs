@@ -1,5 +1,6 @@
-use std::{fmt, io::Read, sync::Arc};
-use super::{error::*, flags::Flags};
+use std::{fmt, io::Read};
+use super::{entry_id::EntryId, error::*, flags::Flags};
+use vach_core::registry::RawEntry;
 
 #[cfg(feature = "crypto")]
 use crate::crypto;
@@ -8,7 +9,7 @@ use crate::crypto;
 #[derive(Debug, Clone)]
 pub struct RegistryEntry {
 	/// Self explanatory?
-	pub id: Arc<str>,
+	pub id: EntryId,
 	/// The flags extracted from the archive entry and parsed into a accessible struct
 	pub flags: Flags,
 	/// The content version of the extracted archive entry
@@ -17,6 +18,8 @@ pub struct RegistryEntry {
 	pub location: u64,
 	/// The offset|size of the [`Leaf`](crate::builder::Leaf), in bytes. This is the actual number of bytes in the leaf endpoint. But the size of the data may vary once processed, ie when decompressed
 	pub offset: u64,
+	/// The size of the data in bytes once decrypted and decompressed, ie the size of [`Resource::data`](crate::archive::Resource)
+	pub decoded_size: u64,
 	/// The signature of the data in the archive, used when verifying data authenticity
 	#[cfg(feature = "crypto")]
 	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
@@ -24,17 +27,18 @@ pub struct RegistryEntry {
 }
 
 impl RegistryEntry {
-	// (flags) + 1(content version) + 8(location) + 8(offset) + 2(path length) + ..Dynamic
-	pub(crate) const MIN_SIZE: usize = Flags::SIZE + 19;
+	// (flags) + 1(content version) + 8(location) + 8(offset) + 8(decoded size) + 2(path length) + ..Dynamic
+	pub(crate) const MIN_SIZE: usize = RawEntry::MIN_SIZE;
 
 	#[inline(always)]
 	pub(crate) fn empty() -> RegistryEntry {
 		RegistryEntry {
-			id: Arc::from("<NULL ID>"),
+			id: EntryId::from_raw("<NULL ID>"),
 			flags: Flags::empty(),
 			content_version: 0,
 			location: 0,
 			offset: 0,
+			decoded_size: 0,
 
 			#[cfg(feature = "crypto")]
 			signature: None,
@@ -42,90 +46,156 @@ impl RegistryEntry {
 	}
 
 	/// Given a read handle, will proceed to read and parse bytes into a [`RegistryEntry`] struct. (de-serialization)
-	pub(crate) fn from_handle<T: Read>(mut handle: T) -> InternalResult<RegistryEntry> {
-		let mut buffer: [u8; RegistryEntry::MIN_SIZE] = [0u8; RegistryEntry::MIN_SIZE];
-		handle.read_exact(&mut buffer)?;
+	/// In registries with prefixed `ID`s, `previous` is the `ID` of the entry before this one, see `RegistryEntry::to_bytes_prefixed`
+	pub(crate) fn from_handle<T: Read>(mut handle: T, previous: Option<&str>) -> InternalResult<RegistryEntry> {
+		let mut fixed = [0u8; RegistryEntry::MIN_SIZE];
+		handle.read_exact(&mut fixed)?;
 
-		// Construct entry
-		let flags = Flags::from_bits(u32::from_le_bytes(buffer[0..4].try_into().unwrap()));
-		let content_version = buffer[4];
+		/* The data after this is dynamically sized, therefore *MUST* be read conditionally */
+		let tail = RawEntry::tail_size(&fixed, previous.is_some());
+		let mut buffer = fixed.to_vec();
+		buffer.resize(RegistryEntry::MIN_SIZE + tail, 0);
+		handle.read_exact(&mut buffer[RegistryEntry::MIN_SIZE..])?;
 
-		let location = u64::from_le_bytes(buffer[5..13].try_into().unwrap());
-		let offset = u64::from_le_bytes(buffer[13..21].try_into().unwrap());
+		let (raw, _) = RawEntry::decode(&buffer, previous)?;
+		Ok(RegistryEntry::from_raw(raw))
+	}
 
-		let id_length = u16::from_le_bytes([buffer[21], buffer[22]]);
+	/// Reads a [`RegistryEntry`] as written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), whose entries lack the decoded size
+	pub(crate) fn from_legacy_handle<T: Read>(mut handle: T) -> InternalResult<RegistryEntry> {
+		let mut fixed = [0u8; RawEntry::LEGACY_MIN_SIZE];
+		handle.read_exact(&mut fixed)?;
 
-		#[cfg(feature = "crypto")]
-		let mut signature = None;
+		let tail = RawEntry::legacy_tail_size(&fixed);
+		let mut buffer = fixed.to_vec();
+		buffer.resize(RawEntry::LEGACY_MIN_SIZE + tail, 0);
+		handle.read_exact(&mut buffer[RawEntry::LEGACY_MIN_SIZE..])?;
 
-		/* The data after this is dynamically sized, therefore *MUST* be read conditionally */
-		// Only produce a flag from data that is signed
-		if flags.contains(Flags::SIGNED_FLAG) {
-			let mut sig_bytes: [u8; crate::SIGNATURE_LENGTH] = [0u8; crate::SIGNATURE_LENGTH];
-			handle.read_exact(&mut sig_bytes)?;
+		// Reserved bits without a meaning back then can't be allowed to mark pipelines, key epochs or tombstones now
+		let (mut raw, _) = RawEntry::decode_legacy(&buffer)?;
+		raw.flags &= vach_core::flags::LEGACY_MASK;
 
-			// If the `crypto` feature is turned off then the bytes are just read then discarded
+		Ok(RegistryEntry::from_raw(raw))
+	}
+
+	fn from_raw(raw: RawEntry) -> RegistryEntry {
+		// Build entry step manually, to prevent unnecessary `Default::default()` call, then changing fields individually
+		RegistryEntry {
+			id: EntryId::from_raw(raw.id),
+			flags: Flags::from_bits(raw.flags),
+			content_version: raw.content_version,
+			location: raw.location,
+			offset: raw.offset,
+			decoded_size: raw.decoded_size,
+
+			// If the `crypto` feature is turned off then the signature is just read then discarded
 			#[cfg(feature = "crypto")]
-			{
-				let sig = match crypto::Signature::try_from(sig_bytes) {
-					Ok(sig) => sig,
-					Err(err) => return Err(InternalError::ParseError(err.to_string())),
-				};
-
-				signature = Some(sig);
-			}
-		};
+			signature: raw.signature.map(|bytes| crypto::Signature::from_bytes(&bytes)),
+		}
+	}
 
-		// Construct ID
-		let mut id = String::with_capacity(id_length as usize);
-		handle.take(id_length as u64).read_to_string(&mut id)?;
+	/// Serializes a [`RegistryEntry`] struct into an array of bytes
+	pub(crate) fn to_bytes(&self, skip_signature: bool) -> InternalResult<Vec<u8>> {
+		self.encode(skip_signature, None)
+	}
 
-		// Build entry step manually, to prevent unnecessary `Default::default()` call, then changing fields individually
-		let entry = RegistryEntry {
-			id: id.into(),
-			flags,
-			content_version,
-			location,
-			offset,
+	/// Serializes a [`RegistryEntry`] struct into an array of bytes, storing only the part of it's `ID` that differs from `previous`.
+	/// Entries sorted by `ID` share the longest prefixes
+	pub(crate) fn to_bytes_prefixed(&self, previous: &str) -> InternalResult<Vec<u8>> {
+		self.encode(false, Some(previous))
+	}
+
+	/// The number of bytes an entry takes up in the registry, see `RegistryEntry::to_bytes_prefixed`
+	pub(crate) fn encoded_size(id: &str, previous: Option<&str>, signed: bool) -> usize {
+		RawEntry::encoded_size(id, previous, signed)
+	}
+
+	/// Serializes a [`RegistryEntry`] as written in [`LEGACY_VERSION`](crate::LEGACY_VERSION), legacy signatures cover these bytes
+	#[cfg(feature = "crypto")]
+	pub(crate) fn to_legacy_bytes(&self, skip_signature: bool) -> InternalResult<Vec<u8>> {
+		Ok(self.raw().encode_legacy(skip_signature)?)
+	}
+
+	fn encode(&self, skip_signature: bool, previous: Option<&str>) -> InternalResult<Vec<u8>> {
+		Ok(self.raw().encode(previous, skip_signature)?)
+	}
+
+	fn raw(&self) -> RawEntry {
+		RawEntry {
+			id: self.id.to_string(),
+			flags: self.flags.bits(),
+			content_version: self.content_version,
+			location: self.location,
+			offset: self.offset,
+			decoded_size: self.decoded_size,
 
 			#[cfg(feature = "crypto")]
-			signature,
-		};
+			signature: self.signature.map(|signature| signature.to_bytes()),
+			#[cfg(not(feature = "crypto"))]
+			signature: None,
+		}
+	}
 
-		Ok(entry)
+	/// When the entry's data is meant to be loaded, as set using `Leaf::hint`
+	pub fn hint(&self) -> ResidencyHint {
+		ResidencyHint::from_flags(self.flags)
 	}
 
-	/// Serializes a [`RegistryEntry`] struct into an array of bytes
-	pub(crate) fn to_bytes(&self, skip_signature: bool) -> InternalResult<Vec<u8>> {
-		// Make sure the ID is not too big or else it will break the archive
-		let id = self.id.as_ref();
+	/// Whether this entry is a tombstone, hiding the entry with the same `ID` in lower layers of an [`Overlay`](crate::archive::Overlay)
+	pub fn is_tombstone(&self) -> bool {
+		self.flags.contains(Flags::TOMBSTONE_FLAG)
+	}
 
-		if id.len() >= crate::MAX_ID_LENGTH {
-			let copy = id.to_string();
-			return Err(InternalError::IDSizeOverflowError(copy));
-		};
+	/// Whether the entry holds no data and went through no processing, as written by `Leaf::placeholder` or for any unprocessed empty leaf
+	pub fn is_placeholder(&self) -> bool {
+		let processed = Flags::COMPRESSED_FLAG | Flags::ENCRYPTED_FLAG | Flags::PIPELINE_FLAG | Flags::TOMBSTONE_FLAG;
+		self.offset == 0 && self.flags.bits() & processed == 0
+	}
+
+	/// The stored size over the decoded size, eg `0.25` for data compressed to a quarter of it's size.
+	/// Encryption adds a few bytes, so uncompressed encrypted leaves have a ratio slightly above `1.0`. Empty leaves have a ratio of `1.0`
+	pub fn ratio(&self) -> f64 {
+		if self.decoded_size == 0 {
+			1.0
+		} else {
+			self.offset as f64 / self.decoded_size as f64
+		}
+	}
+}
 
-		let mut buffer = Vec::with_capacity(RegistryEntry::MIN_SIZE + id.len());
-		let len = id.len() as u16;
-
-		buffer.extend_from_slice(&self.flags.bits().to_le_bytes());
-		buffer.extend_from_slice(&self.content_version.to_le_bytes());
-		buffer.extend_from_slice(&self.location.to_le_bytes());
-		buffer.extend_from_slice(&self.offset.to_le_bytes());
-		buffer.extend_from_slice(&len.to_le_bytes());
-
-		// Only write signature if one exists
-		#[cfg(feature = "crypto")]
-		if let Some(signature) = self.signature {
-			if !skip_signature {
-				buffer.extend_from_slice(&signature.to_bytes())
-			}
+/// A hint stored with each entry, so engines can decide what to load at boot and what to stream in later straight from the registry.
+/// Loaders don't act on it, [`Archive`](crate::archive::Archive) fetches every entry the same way
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ResidencyHint {
+	/// Loaded when first requested
+	#[default]
+	OnDemand,
+	/// Loaded up front, eg: during boot or a loading screen
+	Preload,
+	/// Large data read incrementally, eg: music or video
+	Stream,
+}
+
+impl ResidencyHint {
+	const SHIFT: u32 = Flags::RESIDENCY_HINT_MASK.trailing_zeros();
+
+	pub(crate) fn bits(self) -> u32 {
+		let value = match self {
+			ResidencyHint::OnDemand => 0,
+			ResidencyHint::Preload => 1,
+			ResidencyHint::Stream => 2,
 		};
 
-		// Append id
-		buffer.extend_from_slice(id.as_bytes());
+		value << Self::SHIFT
+	}
 
-		Ok(buffer)
+	/// Unknown values, from newer writers, fall back to `OnDemand`
+	pub(crate) fn from_flags(flags: Flags) -> ResidencyHint {
+		match (flags.bits() & Flags::RESIDENCY_HINT_MASK) >> Self::SHIFT {
+			1 => ResidencyHint::Preload,
+			2 => ResidencyHint::Stream,
+			_ => ResidencyHint::OnDemand,
+		}
 	}
 }
 
@@ -140,9 +210,10 @@ impl fmt::Display for RegistryEntry {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(
 			f,
-			"[RegistryEntry] location: {}, length: {}, content_version: {}, flags: {}",
+			"[RegistryEntry] location: {}, length: {}, decoded_size: {}, content_version: {}, flags: {}",
 			self.location,
 			self.offset,
+			self.decoded_size,
 			self.content_version,
 			self.flags.bits()
 		)
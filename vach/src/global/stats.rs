@@ -0,0 +1,29 @@
+use std::{fmt, time::Duration};
+use super::entry_id::EntryId;
+
+/// A callback receiving the [`FetchStats`] of a slow fetch, see [`ArchiveConfig::slow_fetch`](crate::archive::ArchiveConfig)
+pub type SlowFetchCallback = fn(&FetchStats);
+
+/// Timing and size information recorded for a single decoded [`Resource`](crate::archive::Resource).
+/// Enable recording using [`ArchiveConfig::fetch_stats`](crate::archive::ArchiveConfig) and read them using `Archive::recent_fetch_stats`
+#[derive(Debug, Clone)]
+pub struct FetchStats {
+	/// The `ID` of the fetched entry
+	pub id: EntryId,
+	/// Size of the leaf as stored in the source, in bytes
+	pub raw_size: u64,
+	/// Size of the data after decryption and decompression, in bytes
+	pub decoded_size: u64,
+	/// Time spent validating, decrypting and decompressing the leaf
+	pub decode_time: Duration,
+}
+
+impl fmt::Display for FetchStats {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"[FetchStats] id: {}, raw_size: {}B, decoded_size: {}B, decode_time: {:?}",
+			self.id, self.raw_size, self.decoded_size, self.decode_time
+		)
+	}
+}
@@ -0,0 +1,19 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Locks `mutex`, recovering the guard if another thread panicked while holding it. Everything `vach` guards is left
+/// consistent between operations, eg: reads always seek before reading, so a panic elsewhere never has to spread
+pub(crate) fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Like [`lock`], for exclusive access through `&mut`
+#[cfg(feature = "archive")]
+pub(crate) fn get_mut<T: ?Sized>(mutex: &mut Mutex<T>) -> &mut T {
+	mutex.get_mut().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Like [`lock`], consuming the [`Mutex`]
+#[cfg(all(feature = "archive", feature = "multithreaded"))]
+pub(crate) fn into_inner<T>(mutex: Mutex<T>) -> T {
+	mutex.into_inner().unwrap_or_else(PoisonError::into_inner)
+}
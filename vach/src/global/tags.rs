@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use super::{entry_id::EntryId, error::*};
+
+/// The `ID` of the reserved leaf holding an archive's tags, written whenever a leaf uses `Leaf::tag`
+pub const TAGS_ID: &str = "vach::tags";
+
+/// Serializes the tags of each tagged leaf. Each distinct tag is stored once, as `[tag_count: u16]` followed by `tag_count` times `[tag_len: u16][tag]`,
+/// then each leaf refers to it's tags by index: `[id_len: u16][id][count: u16]` followed by `count` times `[tag_index: u16]`
+#[cfg(feature = "builder")]
+pub(crate) fn encode(tagged: &[(&EntryId, &[String])]) -> InternalResult<Vec<u8>> {
+	let mut names = Vec::new();
+	let mut indices = HashMap::new();
+
+	for (id, tags) in tagged {
+		for tag in tags.iter() {
+			if tag.is_empty() || tag.len() > u16::MAX as usize {
+				return Err(InternalError::InvalidConfigError(format!("{} has an empty or overlong tag", id)));
+			}
+
+			indices.entry(tag.as_str()).or_insert_with(|| {
+				names.push(tag.as_str());
+				names.len() - 1
+			});
+		}
+	}
+
+	if names.len() > u16::MAX as usize {
+		return Err(InternalError::InvalidConfigError(format!(
+			"An archive holds at most {} distinct tags, found {}",
+			u16::MAX,
+			names.len()
+		)));
+	}
+
+	let mut buffer = Vec::new();
+	buffer.extend_from_slice(&(names.len() as u16).to_le_bytes());
+
+	for name in &names {
+		write_str(&mut buffer, name);
+	}
+
+	for (id, tags) in tagged {
+		write_str(&mut buffer, id);
+		buffer.extend_from_slice(&(tags.len() as u16).to_le_bytes());
+
+		for tag in tags.iter() {
+			buffer.extend_from_slice(&(indices[tag.as_str()] as u16).to_le_bytes());
+		}
+	}
+
+	Ok(buffer)
+}
+
+#[cfg(feature = "builder")]
+fn write_str(buffer: &mut Vec<u8>, string: &str) {
+	buffer.extend_from_slice(&(string.len() as u16).to_le_bytes());
+	buffer.extend_from_slice(string.as_bytes());
+}
+
+/// Parses data written by [`encode`] into the `ID`s carrying each tag, in the order they were written
+#[cfg(feature = "archive")]
+pub(crate) fn decode(mut data: &[u8]) -> InternalResult<HashMap<String, Vec<EntryId>>> {
+	let count = read_u16(&mut data)?;
+	let names = (0..count).map(|_| read_str(&mut data)).collect::<InternalResult<Vec<_>>>()?;
+
+	let mut groups = names.iter().map(|name| (name.to_string(), Vec::new())).collect::<HashMap<_, _>>();
+
+	while !data.is_empty() {
+		let id = EntryId::from_raw(read_str(&mut data)?);
+
+		for _ in 0..read_u16(&mut data)? {
+			let name = names.get(read_u16(&mut data)? as usize).ok_or_else(truncated)?;
+			groups.entry(name.to_string()).or_default().push(id.clone());
+		}
+	}
+
+	Ok(groups)
+}
+
+#[cfg(feature = "archive")]
+fn read_u16(data: &mut &[u8]) -> InternalResult<u16> {
+	match data.split_first_chunk::<2>() {
+		Some((bytes, rest)) => {
+			*data = rest;
+			Ok(u16::from_le_bytes(*bytes))
+		},
+		None => Err(truncated()),
+	}
+}
+
+#[cfg(feature = "archive")]
+fn read_str<'a>(data: &mut &'a [u8]) -> InternalResult<&'a str> {
+	let len = read_u16(data)? as usize;
+	if data.len() < len {
+		return Err(truncated());
+	}
+
+	let (string, rest) = data.split_at(len);
+	*data = rest;
+
+	std::str::from_utf8(string).map_err(|err| InternalError::OtherError(Box::new(err)))
+}
+
+#[cfg(feature = "archive")]
+fn truncated() -> InternalError {
+	InternalError::OtherError("Truncated or corrupt tag table".into())
+}
@@ -0,0 +1,92 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "archive")]
+use {super::error::*, std::collections::HashMap};
+
+use super::entry_id::EntryId;
+
+/// The `ID` of the reserved leaf holding the validity windows of an archive's entries, written whenever a leaf uses `Leaf::valid_from` or `Leaf::valid_until`
+pub const VALIDITY_ID: &str = "vach::validity";
+
+/// The current time as seen by a loader, see `ArchiveConfig::enforce_validity`
+pub type Clock = fn() -> SystemTime;
+
+/// When an entry may be served, eg: content for a timed event shipped ahead of it's unlock date.
+/// Timestamps are stored with a precision of one second, an unbounded side is `None`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Validity {
+	/// The entry is not served before this point in time
+	pub valid_from: Option<SystemTime>,
+	/// The entry is not served from this point in time onwards
+	pub valid_until: Option<SystemTime>,
+}
+
+impl Validity {
+	/// Whether the window is unbounded on both sides
+	pub fn is_unbounded(&self) -> bool {
+		self.valid_from.is_none() && self.valid_until.is_none()
+	}
+
+	/// Whether the window contains the given point in time
+	pub fn contains(&self, now: SystemTime) -> bool {
+		self.valid_from.is_none_or(|from| now >= from) && self.valid_until.is_none_or(|until| now < until)
+	}
+}
+
+// Unbounded sides are stored as `0` and `u64::MAX` respectively
+#[cfg(feature = "builder")]
+fn to_seconds(time: Option<SystemTime>, unbounded: u64) -> u64 {
+	time.map_or(unbounded, |t| t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+}
+
+#[cfg(feature = "archive")]
+fn from_seconds(seconds: u64, unbounded: u64) -> Option<SystemTime> {
+	(seconds != unbounded).then(|| UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+}
+
+/// Serializes the windows of each leaf with one: `[id_len: u16][id][valid_from: u64][valid_until: u64]`, in seconds since the UNIX epoch
+#[cfg(feature = "builder")]
+pub(crate) fn encode<'a>(windows: impl Iterator<Item = (&'a EntryId, Validity)>) -> Vec<u8> {
+	let mut buffer = Vec::new();
+
+	for (id, validity) in windows {
+		buffer.extend_from_slice(&(id.len() as u16).to_le_bytes());
+		buffer.extend_from_slice(id.as_bytes());
+		buffer.extend_from_slice(&to_seconds(validity.valid_from, 0).to_le_bytes());
+		buffer.extend_from_slice(&to_seconds(validity.valid_until, u64::MAX).to_le_bytes());
+	}
+
+	buffer
+}
+
+/// Parses data written by [`encode`]
+#[cfg(feature = "archive")]
+pub(crate) fn decode(mut data: &[u8]) -> InternalResult<HashMap<EntryId, Validity>> {
+	let truncated = || InternalError::OtherError("Truncated validity windows".into());
+	let mut windows = HashMap::new();
+
+	while !data.is_empty() {
+		let (len, rest) = data.split_first_chunk::<2>().ok_or_else(truncated)?;
+		let len = u16::from_le_bytes(*len) as usize;
+
+		if rest.len() < len + 16 {
+			return Err(truncated());
+		}
+
+		let (id, rest) = rest.split_at(len);
+		let id = std::str::from_utf8(id).map_err(|err| InternalError::OtherError(Box::new(err)))?;
+
+		let (from, rest) = rest.split_first_chunk::<8>().ok_or_else(truncated)?;
+		let (until, rest) = rest.split_first_chunk::<8>().ok_or_else(truncated)?;
+
+		let validity = Validity {
+			valid_from: from_seconds(u64::from_le_bytes(*from), 0),
+			valid_until: from_seconds(u64::from_le_bytes(*until), u64::MAX),
+		};
+
+		windows.insert(EntryId::from_raw(id), validity);
+		data = rest;
+	}
+
+	Ok(windows)
+}
@@ -0,0 +1,50 @@
+use super::error::{InternalError, InternalResult};
+
+/// How far `Archive::verify_stream` has come, passed to it's progress callback after each entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyProgress {
+	/// Entries verified so far, including failures
+	pub entries_done: usize,
+	/// Entries to verify in total, tombstones are skipped
+	pub entries_total: usize,
+	/// Leaf bytes read so far
+	pub bytes_done: u64,
+	/// Leaf bytes to read in total
+	pub bytes_total: u64,
+}
+
+/// The result of walking an archive with `Archive::verify_stream`
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+	/// Entries whose signature was checked against the archive's public key
+	pub authenticated: usize,
+	/// Entries that decoded fine but carry no signature, or no public key was provided to check it against
+	pub unauthenticated: usize,
+	/// Leaf bytes read from the source
+	pub bytes: u64,
+	/// Entries that failed to verify, each error carries the offending entry's `ID` and the stage it failed at
+	pub failures: Vec<InternalError>,
+}
+
+impl VerifyReport {
+	/// Whether every entry verified, signed or not
+	pub fn is_ok(&self) -> bool {
+		self.failures.is_empty()
+	}
+
+	/// Failed entries that couldn't be read or decoded, as opposed to signed entries that failed to authenticate
+	pub fn undecodable(&self) -> usize {
+		self.failures
+			.iter()
+			.filter(|err| !matches!(err.root(), InternalError::UnauthenticatedError(_)))
+			.count()
+	}
+
+	pub(crate) fn record(&mut self, outcome: InternalResult<bool>) {
+		match outcome {
+			Ok(true) => self.authenticated += 1,
+			Ok(false) => self.unauthenticated += 1,
+			Err(err) => self.failures.push(err),
+		}
+	}
+}
@@ -0,0 +1,282 @@
+use std::{
+	fs,
+	future::Future,
+	io::{self, Read},
+	path::{Component, Path, PathBuf},
+	pin::Pin,
+	sync::{Arc, Mutex},
+	task::{Context, Poll, Waker},
+	thread,
+};
+
+use super::{entry_id::EntryId, error::*, sync};
+
+/// A minimal read-only filesystem, so loose files during development and archives in release can sit behind one interface.
+/// Paths use `/` as a separator and are relative to the root of the source, the root itself is the empty path `""`.
+pub trait Vfs {
+	/// Opens the file at `path` for reading
+	fn open(&self, path: &str) -> InternalResult<Box<dyn Read + '_>>;
+
+	/// Reads the whole file at `path` into memory
+	fn read(&self, path: &str) -> InternalResult<Vec<u8>> {
+		let mut buffer = Vec::new();
+		self.open(path)?.read_to_end(&mut buffer)?;
+		Ok(buffer)
+	}
+
+	/// Queries whether `path` is a file or a directory, and the size of files
+	fn stat(&self, path: &str) -> InternalResult<VfsMetadata>;
+
+	/// Lists the paths of the files and directories directly within the directory `path`, sorted
+	fn list(&self, path: &str) -> InternalResult<Vec<String>>;
+
+	/// Whether a file or directory exists at `path`
+	fn exists(&self, path: &str) -> bool {
+		self.stat(path).is_ok()
+	}
+}
+
+/// Metadata returned by [`Vfs::stat`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VfsMetadata {
+	/// Whether the path is a directory, archives have implicit directories formed by the `/` separated prefixes of their `ID`s
+	pub is_dir: bool,
+	/// The size of a file as stored. For archives this is the size after compression and|or encryption, `0` for directories
+	pub len: u64,
+}
+
+/// A [`Vfs`] over a directory on disk. Paths can't escape the root directory
+#[derive(Debug, Clone)]
+pub struct DirectoryVfs {
+	root: PathBuf,
+}
+
+impl DirectoryVfs {
+	/// Serve files from within `root`
+	pub fn new(root: impl Into<PathBuf>) -> DirectoryVfs {
+		DirectoryVfs { root: root.into() }
+	}
+
+	/// The directory files are served from
+	pub fn root(&self) -> &Path {
+		&self.root
+	}
+
+	fn resolve(&self, path: &str) -> InternalResult<PathBuf> {
+		let relative = Path::new(path);
+
+		// Only plain components, so `..` and absolute paths can't reach outside of the root
+		if relative.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+			return Err(InternalError::MissingResourceError(EntryId::from_raw(path)));
+		};
+
+		Ok(self.root.join(relative))
+	}
+}
+
+/// Reports missing files the same way archives report missing entries
+fn not_found(path: &str) -> impl FnOnce(io::Error) -> InternalError + '_ {
+	move |err| match err.kind() {
+		io::ErrorKind::NotFound => InternalError::MissingResourceError(EntryId::from_raw(path)),
+		_ => InternalError::IOError(err),
+	}
+}
+
+impl Vfs for DirectoryVfs {
+	fn open(&self, path: &str) -> InternalResult<Box<dyn Read + '_>> {
+		let file = fs::File::open(self.resolve(path)?).map_err(not_found(path))?;
+		Ok(Box::new(file))
+	}
+
+	fn stat(&self, path: &str) -> InternalResult<VfsMetadata> {
+		let metadata = fs::metadata(self.resolve(path)?).map_err(not_found(path))?;
+
+		Ok(VfsMetadata {
+			is_dir: metadata.is_dir(),
+			len: if metadata.is_dir() { 0 } else { metadata.len() },
+		})
+	}
+
+	fn list(&self, path: &str) -> InternalResult<Vec<String>> {
+		let prefix = path.trim_end_matches('/');
+
+		let mut paths = fs::read_dir(self.resolve(path)?)
+			.map_err(not_found(path))?
+			.map(|entry| {
+				let name = entry?.file_name().to_string_lossy().into_owned();
+
+				Ok(match prefix {
+					"" => name,
+					prefix => format!("{}/{}", prefix, name),
+				})
+			})
+			.collect::<io::Result<Vec<_>>>()?;
+
+		paths.sort();
+		Ok(paths)
+	}
+}
+
+/// Loose files on disk layered over an archive (or any other [`Vfs`]). By default loose files win, so assets can be iterated on
+/// without repacking, and anything missing on disk falls through to the archive. Release builds can flip the priority with [`HybridSource::prefer_archive`]
+#[derive(Debug)]
+pub struct HybridSource<A> {
+	loose: DirectoryVfs,
+	archive: A,
+	prefer_archive: bool,
+}
+
+impl<A: Vfs> HybridSource<A> {
+	/// Serve files from `loose_dir` first, then from `archive`
+	pub fn new(loose_dir: impl Into<PathBuf>, archive: A) -> HybridSource<A> {
+		HybridSource {
+			loose: DirectoryVfs::new(loose_dir),
+			archive,
+			prefer_archive: false,
+		}
+	}
+
+	/// Setter for the `prefer_archive` field, when set the archive is searched before the loose directory
+	pub fn prefer_archive(mut self, prefer_archive: bool) -> Self {
+		self.prefer_archive = prefer_archive;
+		self
+	}
+
+	/// The underlying loose directory
+	pub fn loose(&self) -> &DirectoryVfs {
+		&self.loose
+	}
+
+	/// The underlying archive
+	pub fn archive(&self) -> &A {
+		&self.archive
+	}
+
+	/// Consume the [`HybridSource`], returning the underlying archive
+	pub fn into_archive(self) -> A {
+		self.archive
+	}
+
+	fn layers(&self) -> [&dyn Vfs; 2] {
+		if self.prefer_archive {
+			[&self.archive, &self.loose]
+		} else {
+			[&self.loose, &self.archive]
+		}
+	}
+
+	/// Tries each layer in order, only missing paths fall through to the next layer
+	fn first<'a, R>(&'a self, path: &str, op: impl Fn(&'a dyn Vfs) -> InternalResult<R>) -> InternalResult<R> {
+		for layer in self.layers() {
+			match op(layer) {
+				Err(InternalError::MissingResourceError(_)) => continue,
+				result => return result,
+			}
+		}
+
+		Err(InternalError::MissingResourceError(EntryId::from_raw(path)))
+	}
+}
+
+impl<A: Vfs> Vfs for HybridSource<A> {
+	fn open(&self, path: &str) -> InternalResult<Box<dyn Read + '_>> {
+		self.first(path, |layer| layer.open(path))
+	}
+
+	fn read(&self, path: &str) -> InternalResult<Vec<u8>> {
+		self.first(path, |layer| layer.read(path))
+	}
+
+	fn stat(&self, path: &str) -> InternalResult<VfsMetadata> {
+		self.first(path, |layer| layer.stat(path))
+	}
+
+	fn list(&self, path: &str) -> InternalResult<Vec<String>> {
+		let mut paths = Vec::new();
+		let mut found = false;
+
+		for layer in self.layers() {
+			match layer.list(path) {
+				Ok(list) => {
+					paths.extend(list);
+					found = true;
+				},
+				Err(InternalError::MissingResourceError(_)) => continue,
+				Err(err) => return Err(err),
+			}
+		}
+
+		if !found {
+			return Err(InternalError::MissingResourceError(EntryId::from_raw(path)));
+		}
+
+		paths.sort();
+		paths.dedup();
+		Ok(paths)
+	}
+}
+
+/// Reads the whole file at `path` on a separate thread, so async executors aren't blocked by disk IO or decompression.
+/// The returned [`BackgroundRead`] resolves once the read is done, it works with any executor as it only relies on it's [`Waker`]
+/// ```
+/// use std::sync::Arc;
+/// use vach::vfs::{read_in_background, DirectoryVfs};
+///
+/// # async fn load() -> vach::prelude::InternalResult {
+/// let source = Arc::new(DirectoryVfs::new("assets"));
+/// let data = read_in_background(source, "textures/grass.png").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_in_background<V: Vfs + Send + Sync + 'static>(source: Arc<V>, path: impl Into<String>) -> BackgroundRead {
+	let path = path.into();
+	let state = Arc::new(Mutex::new(ReadState::default()));
+	let shared = state.clone();
+
+	thread::spawn(move || {
+		let result = source.read(&path);
+
+		let mut state = sync::lock(&shared);
+		state.result = Some(result);
+
+		if let Some(waker) = state.waker.take() {
+			waker.wake();
+		}
+	});
+
+	BackgroundRead { state }
+}
+
+#[derive(Default)]
+struct ReadState {
+	result: Option<InternalResult<Vec<u8>>>,
+	waker: Option<Waker>,
+}
+
+/// A read running on a separate thread, see [`read_in_background`]
+pub struct BackgroundRead {
+	state: Arc<Mutex<ReadState>>,
+}
+
+impl Future for BackgroundRead {
+	type Output = InternalResult<Vec<u8>>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let mut state = sync::lock(&self.state);
+
+		match state.result.take() {
+			Some(result) => Poll::Ready(result),
+			None => {
+				state.waker = Some(cx.waker().clone());
+				Poll::Pending
+			},
+		}
+	}
+}
+
+impl std::fmt::Debug for BackgroundRead {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let done = sync::lock(&self.state).result.is_some();
+		f.debug_struct("BackgroundRead").field("done", &done).finish()
+	}
+}
@@ -21,12 +21,19 @@ It was built to be secure, contained and protected. A big benefit of `vach` is t
 
 ### 🔫 Cargo Features
 - `archive` and `builder` (default): Turning them off turns off their respective modules. For example a game only needs the `archive` feature but a tool for packing assets would only need the `builder` feature.
-- `multithreaded`: Runs `Builder::dump(---)` on multiple threads. Number of threads can be set manually using `BuilderConfig::num_threads`
+- `multithreaded`: Runs `Builder::dump(---)` on multiple threads. Threads are split between reading (`BuilderConfig::io_threads`) and compressing (`BuilderConfig::compute_threads`)
 - `compression`: Pulls `snap`, `lz4_flex` and `brotli` as dependencies and allows for compression in `vach` archives.
+- `uring`: Linux only, adds `Archive::fetch_batch_uring` which submits batched reads via `io_uring`.
+- `async`: Adds [`AsyncArchive`](crate::archive::AsyncArchive), which loads archives from `tokio`'s `AsyncRead + AsyncSeek` sources, eg: over the network.
+- `mmap`: Unix only, adds `Archive::from_mmap` which opens archives through a memory map, and `Archive::fetch_slice` which borrows leaves stored as is without copying them.
 - `crypto`: Enables encryption and authentication functionality by pulling the `ed25519_dalek` and `aes_gcm` crates
+- `bench`: Adds the [`bench`](crate::bench) module, standardized workloads measuring pack and fetch throughput on the current machine.
+- `serde`: Implements `Serialize` and `Deserialize` for [`Flags`](crate::prelude::Flags), as symbolic names in human readable formats.
 - `default`: Enables the `archive` and `builder` features.
 - `all`: Enables all the features listed above
 
+`vach` never installs a global allocator, decode buffers and the registry use whatever `#[global_allocator]` the host application sets.
+
 ### 🀄 Show me some code _dang it!_
 
 ##### > Building a basic unsigned `.vach` file
@@ -118,14 +125,17 @@ As `SigningKey`, `SecretKey` and `VerifyingKey` are reflected from [ed25519_dale
 /// All tests are included in this module.
 mod tests;
 
+#[forbid(clippy::unwrap_used)]
 pub(crate) mod global;
 
 #[cfg(feature = "archive")]
 #[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
+#[forbid(clippy::unwrap_used)]
 pub(crate) mod loader;
 
 #[cfg(feature = "builder")]
 #[cfg_attr(docsrs, doc(cfg(feature = "builder")))]
+#[forbid(clippy::unwrap_used)]
 pub(crate) mod writer;
 
 // Re-export
@@ -133,8 +143,10 @@ pub(crate) mod writer;
 #[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
 pub use rand;
 
-/// Current [`vach`](crate) spec version. increments by ten with every spec change
-pub const VERSION: u16 = 30;
+/// The dependency free encoding and decoding of headers and registries, see [`vach_core`]
+pub use vach_core as format;
+
+pub use vach_core::{DEFAULT_MAGIC, LEGACY_VERSION, MAGIC_LENGTH, MAX_ID_LENGTH, SIGNATURE_LENGTH, VERSION};
 
 /// Size of a secret key
 pub const SECRET_KEY_LENGTH: usize = 32;
@@ -142,21 +154,12 @@ pub const SECRET_KEY_LENGTH: usize = 32;
 /// Size of a public key
 pub const PUBLIC_KEY_LENGTH: usize = 32;
 
-/// Size of a signature
-pub const SIGNATURE_LENGTH: usize = 64;
-
-/// Maximum size for any ID
-pub const MAX_ID_LENGTH: usize = 65535; // u16::MAX
-
-/// The standard size of any MAGIC entry in bytes
-pub const MAGIC_LENGTH: usize = 5;
-
-/// The default MAGIC used by `vach`
-pub const DEFAULT_MAGIC: &[u8; crate::MAGIC_LENGTH] = b"VfACH";
 
 /// Consolidated import for crate logic; This module stores all `structs` associated with this crate. Constants can be accesses [directly](#constants) with `crate::<CONSTANT>`
 pub mod prelude {
-	pub use crate::global::{error::*, flags::Flags, header::ArchiveConfig, reg_entry::RegistryEntry};
+	pub use crate::global::{
+		entry_id::EntryId, error::*, flags::Flags, header::{ArchiveConfig, MemoryGate}, reg_entry::RegistryEntry, stats::{FetchStats, SlowFetchCallback},
+	};
 
 	#[cfg(feature = "crypto")]
 	pub use crate::crypto::*;
@@ -166,6 +169,8 @@ pub mod prelude {
 
 	#[cfg(feature = "builder")]
 	pub use crate::builder::*;
+
+	pub use crate::vfs::*;
 }
 
 /// Import keypairs and signatures from here, mirrors from `ed25519_dalek`
@@ -176,21 +181,104 @@ pub mod crypto;
 #[cfg_attr(docsrs, doc(cfg(feature = "builder")))]
 pub mod builder {
 	pub use crate::writer::*;
-	pub use crate::global::{error::*, flags::Flags};
+	pub use crate::global::{
+		entry_id::EntryId,
+		error::*,
+		flags::Flags,
+		lint::LintWarning,
+		path_mapper::{PathMapper, SeparatorMapper},
+		pipeline::{PipelineStage, StageFn},
+		reg_entry::ResidencyHint,
+		dependencies::DEPENDENCIES_ID,
+		descriptor::{FormatDescriptor, DESCRIPTOR_ID},
+		entry_id::{NAMESPACE_SEPARATOR, RESERVED_NAMESPACE},
+		index::{IdIndex, INDEX_ID},
+		profile::{Access, AccessProfile},
+		tags::TAGS_ID,
+		validity::{Validity, VALIDITY_ID},
+	};
 
 	#[cfg(feature = "compression")]
-	pub use crate::global::compressor::CompressionAlgorithm;
+	pub use crate::global::compressor::{BrotliParams, CompressionAlgorithm, DetectHeuristics};
 }
 
 /// Loader-based logic and data-structures
 #[cfg(feature = "archive")]
 #[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
 pub mod archive {
-	pub use crate::loader::{archive::Archive, resource::Resource};
-	pub use crate::global::{reg_entry::RegistryEntry, header::ArchiveConfig, error::*, flags::Flags};
+	pub use crate::loader::{
+		archive::{Archive, Entries, IntoEntries},
+		footer::Embedded,
+		namespace::Namespace,
+		overlay::Overlay,
+		reader::LeafReader,
+		resource::{KeyId, Resource},
+		throttle::{Priority, RateLimit, ThrottledArchive},
+	};
+	pub use crate::global::{
+		reg_entry::{RegistryEntry, ResidencyHint}, header::{ArchiveConfig, Capabilities, MemoryGate}, dependencies::DEPENDENCIES_ID,
+		entry_id::{EntryId, NAMESPACE_SEPARATOR, RESERVED_NAMESPACE}, error::*, flags::Flags, lint::LintWarning,
+		descriptor::{FormatDescriptor, DESCRIPTOR_ID},
+		directory::{Children, DirEntry},
+		layout::{LayoutReport, Region, RegionKind},
+		index::{IdIndex, INDEX_ID},
+		path_mapper::{PathMapper, SeparatorMapper},
+		query::Query,
+		profile::{Access, AccessProfile},
+		stats::{FetchStats, SlowFetchCallback},
+		tags::TAGS_ID,
+		validity::{Clock, Validity, VALIDITY_ID},
+		verify::{VerifyProgress, VerifyReport},
+		pipeline::StageFn,
+	};
 	#[cfg(feature = "compression")]
-	pub use crate::global::compressor::CompressionAlgorithm;
+	pub use crate::global::compressor::{BrotliParams, CompressionAlgorithm};
+	#[cfg(feature = "crypto")]
+	pub use crate::loader::verifier::BackgroundVerifier;
+	#[cfg(all(feature = "mmap", unix))]
+	pub use crate::loader::mmap::{MappedResource, Mmap};
+	#[cfg(feature = "async")]
+	pub use crate::loader::asynchronous::AsyncArchive;
+}
+
+/// Compare archives entry by entry, eg: to find what changed between two releases
+#[cfg(feature = "archive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
+pub mod diff {
+	pub use crate::loader::diff::{compare, ArchiveDelta};
+}
+
+/// A minimal virtual filesystem, so loose files and archives can be used interchangeably. [`Archive`](crate::archive::Archive) implements [`Vfs`](crate::vfs::Vfs)
+pub mod vfs {
+	pub use crate::global::vfs::{read_in_background, BackgroundRead, DirectoryVfs, HybridSource, Vfs, VfsMetadata};
+}
+
+/// Advisory locks over archives, so processes reading and writing them take turns, see [`ArchiveLock`](crate::lock::ArchiveLock)
+pub mod lock {
+	pub use crate::global::lock::{ArchiveLock, LockMode, LockOwner, LockedFile};
+}
+
+/// Per run measurements of operations, eg: packing, handed to a [`Reporter`](crate::metrics::Reporter) to trend performance over time
+pub mod metrics {
+	pub use crate::global::metrics::{JsonLines, Metrics, Reporter};
 }
 
 /// Some utility functions to keep you happy
 pub mod crypto_utils;
+
+/// Single call helpers for encrypted and signed archives, with secure defaults that can't be misconfigured
+pub mod seal;
+
+/// Readers and writers that inject faults, latency and count calls, for testing code built around archives
+pub mod testing;
+
+/// Standardized workloads of synthetic archives measuring pack and fetch throughput, to validate performance on target hardware and catch regressions in CI
+#[cfg(feature = "bench")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bench")))]
+pub mod bench;
+
+/// Signed manifests spanning every archive of a content release, see [`ReleaseManifest`](crate::release::ReleaseManifest)
+pub mod release;
+
+/// Hooks for trusted timestamps over an archive's content, eg: RFC 3161 tokens, see [`TimestampAuthority`](crate::timestamp::TimestampAuthority)
+pub mod timestamp;
@@ -1,21 +1,49 @@
 use std::{
-	collections::HashMap,
-	io::{Read, Seek, SeekFrom},
+	collections::{hash_map, HashMap, HashSet, VecDeque},
+	io::{BufReader, Read, Seek, SeekFrom, Write},
+	iter::FusedIterator,
 	ops::DerefMut,
+	path::{Path, PathBuf},
 	str,
-	sync::{Arc, Mutex},
+	sync::Mutex,
+	time::{Duration, Instant, SystemTime},
 };
 
-use super::resource::Resource;
+use super::{
+	footer,
+	namespace::{self, Namespace},
+	resource::{KeyId, Resource},
+};
+use crate::format::registry::RegistryBlock;
 use crate::global::{
+	dependencies::{self, DEPENDENCIES_ID},
+	descriptor::{FormatDescriptor, DESCRIPTOR_ID},
+	directory::{Children, DirEntry, DirIndex},
 	error::*,
 	flags::Flags,
-	header::{Header, ArchiveConfig},
+	header::{ArchiveConfig, Capabilities, Header, MemoryGate},
+	index::{IdIndex, INDEX_ID},
+	layout::{LayoutReport, Region, RegionKind},
+	lint::{Linter, LintWarning},
+	path_mapper::{self, PathMapper},
+	pipeline::{self, StageFn},
+	profile::{AccessProfile, AccessRecorder},
+	query::Query,
 	reg_entry::RegistryEntry,
+	entry_id::{normalize, EntryId},
+	stats::{FetchStats, SlowFetchCallback},
+	sync,
+	tags::{self, TAGS_ID},
+	validity::{self, Clock, Validity, VALIDITY_ID},
+	verify::{VerifyProgress, VerifyReport},
 };
 
 #[cfg(feature = "crypto")]
-use crate::crypto;
+use {
+	super::verifier::Verified,
+	crate::crypto,
+	std::sync::{Arc, OnceLock},
+};
 
 #[cfg(feature = "compression")]
 use crate::global::compressor::*;
@@ -29,26 +57,53 @@ use crate::global::compressor::*;
 #[derive(Debug)]
 pub struct Archive<T> {
 	/// Wrapping `handle` in a Mutex means that we only ever lock when reading from the underlying buffer, thus ensuring maximum performance across threads
-	/// Since all other work is done per thread
-	handle: Mutex<T>,
+	/// Since all other work is done per thread. The handle is buffered internally, see [`ArchiveConfig::read_buffer`]
+	pub(crate) handle: Mutex<BufReader<T>>,
+
+	/// Where the archive was opened from, set by `Archive::open`
+	pub(crate) path: Option<PathBuf>,
 
 	// Registry Data
-	header: Header,
-	entries: HashMap<Arc<str>, RegistryEntry>,
+	pub(crate) header: Header,
+	entries: HashMap<EntryId, RegistryEntry>,
+	// Entries in the reserved `vach` namespace, kept out of listings
+	reserved: HashMap<EntryId, RegistryEntry>,
+	pub(crate) registry_end: u64,
+	// Live `ID`s sorted, for directory listings and globs
+	pub(crate) directory: DirIndex,
+
+	// Fetch statistics
+	stats: Option<Mutex<VecDeque<FetchStats>>>,
+	stats_capacity: usize,
+	slow_fetch: Option<(Duration, SlowFetchCallback)>,
+	stages: [Option<StageFn>; 16],
+	accesses: Option<Mutex<AccessRecorder>>,
+	// The clock and windows used to refuse entries outside their validity window, see `ArchiveConfig::enforce_validity`
+	pub(crate) validity: Option<(Clock, HashMap<EntryId, Validity>)>,
+	memory_gate: Option<MemoryGate>,
 
 	// Optional parts
 	#[cfg(feature = "crypto")]
 	decryptor: Option<crypto::Encryptor>,
+	// Keys of later epochs, their decryptors are only built once needed
+	#[cfg(feature = "crypto")]
+	epochs: Vec<EpochKey>,
 	#[cfg(feature = "crypto")]
 	key: Option<crypto::VerifyingKey>,
+	#[cfg(feature = "crypto")]
+	legacy_signatures: bool,
+	// Signatures already checked, see `Archive::verify_in_background`
+	#[cfg(feature = "crypto")]
+	verified: OnceLock<Arc<Verified>>,
+	// Fingerprints of the verifying and decrypting keys, reported with each `Resource`
+	key_ids: (Option<KeyId>, Option<KeyId>),
 }
 
 impl<T> std::fmt::Display for Archive<T> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		let bytes = self
-			.entries
-			.values()
-			.map(|re| re.offset)
+			.registry()
+			.map(|(_, re)| re.offset)
 			.reduce(|a, b| a + b)
 			.unwrap_or(0);
 
@@ -67,95 +122,411 @@ impl<T> std::fmt::Display for Archive<T> {
 impl<T> Archive<T> {
 	/// Consume the [Archive] and return the underlying handle
 	/// `None` if underlying
-	pub fn into_inner(self) -> Result<T, std::sync::PoisonError<T>> {
-		self.handle.into_inner()
+	pub fn into_inner(self) -> Result<T, std::sync::PoisonError<BufReader<T>>> {
+		self.handle.into_inner().map(BufReader::into_inner)
+	}
+
+	/// Every entry, reserved ones included, for work over the whole archive like verification
+	pub(crate) fn registry(&self) -> impl Iterator<Item = (&EntryId, &RegistryEntry)> {
+		self.entries.iter().chain(self.reserved.iter())
+	}
+
+	/// Puts together an [`Archive`] from it's parsed header and registry, configured but without validity windows
+	pub(crate) fn assemble(
+		handle: BufReader<T>, header: Header, entries: HashMap<EntryId, RegistryEntry>, registry_end: u64, config: &ArchiveConfig,
+	) -> Archive<T> {
+		#[cfg(feature = "crypto")]
+		let header_magic = header.magic;
+
+		let (reserved, entries) = entries.into_iter().partition(|(id, _)| id.is_reserved());
+
+		Archive {
+			path: None,
+			header,
+			handle: Mutex::new(handle),
+			directory: DirIndex::new(&entries),
+			entries,
+			reserved,
+			registry_end,
+
+			stats: (config.fetch_stats > 0).then(|| Mutex::new(VecDeque::with_capacity(config.fetch_stats))),
+			stats_capacity: config.fetch_stats,
+			slow_fetch: config.slow_fetch,
+			stages: config.stages,
+			accesses: config.record_access.then(|| Mutex::new(AccessRecorder::new())),
+			validity: None,
+			memory_gate: config.memory_gate,
+
+			#[cfg(feature = "crypto")]
+			key: config.public_key,
+			#[cfg(feature = "crypto")]
+			legacy_signatures: config.legacy_signatures,
+			#[cfg(feature = "crypto")]
+			verified: OnceLock::new(),
+			#[cfg(feature = "crypto")]
+			decryptor: match (config.decryption_key, config.public_key) {
+				(Some(key), _) => Some(crypto::Encryptor::from_key(&key.to_bytes(), header_magic)),
+				(None, pk) => pk.as_ref().map(|pk| crypto::Encryptor::new(pk, header_magic)),
+			},
+			#[cfg(feature = "crypto")]
+			epochs: config
+				.epoch_keys
+				.iter()
+				.map(|(epoch, key)| EpochKey {
+					epoch: *epoch,
+					key: *key,
+					decryptor: OnceLock::new(),
+				})
+				.collect(),
+			key_ids: key_ids(config),
+		}
+	}
+
+	/// Turns raw data read from the source into a [`Resource`]
+	pub(crate) fn decode(&self, entry: &RegistryEntry, raw: Vec<u8>) -> InternalResult<Resource> {
+		self.admit(entry)?;
+
+		// A decoded size of zero is unknown for any non-empty leaf, such entries pass
+		if let Some(gate) = self.memory_gate {
+			if entry.decoded_size > 0 && !gate(usize::try_from(entry.decoded_size).unwrap_or(usize::MAX)) {
+				return Err(InternalError::MemoryBudgetError(entry.id.clone(), entry.decoded_size));
+			}
+		}
+
+		// Only time decoding when someone is listening
+		let start = (self.stats.is_some() || self.slow_fetch.is_some()).then(Instant::now);
+
+		// Prepare contextual variables
+		#[cfg(feature = "crypto")]
+		let epoch = key_epoch(entry, &raw)?;
+		#[cfg(feature = "crypto")]
+		let decrypted_with = self.decrypted_with(epoch);
+		#[cfg(not(feature = "crypto"))]
+		let decrypted_with = self.key_ids.1;
+
+		// Decompress and|or decrypt the data
+		let (buffer, is_secure) = self.process(
+			entry,
+			raw,
+			#[cfg(feature = "crypto")]
+			epoch,
+		)?;
+
+		if let Some(start) = start {
+			self.record(FetchStats {
+				id: entry.id.clone(),
+				raw_size: entry.offset,
+				decoded_size: buffer.len() as u64,
+				decode_time: start.elapsed(),
+			});
+		}
+
+		Ok(Resource {
+			content_version: entry.content_version,
+			flags: entry.flags,
+			data: buffer.into_boxed_slice(),
+			authenticated: is_secure,
+			verified_with: self.key_ids.0.filter(|_| is_secure),
+			decrypted_with: decrypted_with.filter(|_| entry.flags.contains(Flags::ENCRYPTED_FLAG)),
+		})
+	}
+
+	/// Enforces the entry's validity window and records the access, ahead of decoding it
+	pub(crate) fn admit(&self, entry: &RegistryEntry) -> InternalResult {
+		if let Some((clock, windows)) = &self.validity {
+			if windows.get(&entry.id).is_some_and(|validity| !validity.contains(clock())) {
+				return Err(InternalError::OutsideValidityError(entry.id.clone()));
+			}
+		}
+
+		if let Some(recorder) = &self.accesses {
+			sync::lock(recorder).record(&entry.id);
+		}
+
+		Ok(())
+	}
+
+	/// Whether decoding the entry needs all of it's data at once: to verify it's signature, decrypt it or undo a custom pipeline
+	pub(crate) fn buffered(&self, entry: &RegistryEntry) -> bool {
+		#[cfg(feature = "crypto")]
+		let verify = self.key.is_some() && entry.signature.is_some();
+		#[cfg(not(feature = "crypto"))]
+		let verify = false;
+
+		verify || entry.flags.contains(Flags::ENCRYPTED_FLAG | Flags::PIPELINE_FLAG)
+	}
+
+	fn record(&self, stats: FetchStats) {
+		if let Some((threshold, callback)) = self.slow_fetch {
+			if stats.decode_time > threshold {
+				callback(&stats);
+			}
+		}
+
+		if let Some(log) = &self.stats {
+			let mut log = sync::lock(log);
+			if log.len() >= self.stats_capacity {
+				log.pop_front();
+			}
+
+			log.push_back(stats);
+		}
+	}
+
+	/// Returns the most recently recorded [`FetchStats`], oldest first.
+	/// Always empty unless recording is enabled using [`ArchiveConfig::fetch_stats`]
+	pub fn recent_fetch_stats(&self) -> Vec<FetchStats> {
+		match &self.stats {
+			Some(log) => sync::lock(log).iter().cloned().collect(),
+			None => Vec::new(),
+		}
+	}
+
+	/// The order in which entries were first fetched since the archive was opened, or since the last `Archive::take_access_profile`.
+	/// Always empty unless recording is enabled using [`ArchiveConfig::record_access`]
+	pub fn access_profile(&self) -> AccessProfile {
+		match &self.accesses {
+			Some(recorder) => sync::lock(recorder).profile().clone(),
+			None => AccessProfile::default(),
+		}
+	}
+
+	/// Returns the recorded [`AccessProfile`] and starts a new one, eg: to profile each level load on it's own
+	pub fn take_access_profile(&self) -> AccessProfile {
+		match &self.accesses {
+			Some(recorder) => std::mem::replace(&mut *sync::lock(recorder), AccessRecorder::new()).into_profile(),
+			None => AccessProfile::default(),
+		}
+	}
+
+	/// Checks an entry's signature over it's raw data, `false` if there is no public key to check against, see `ArchiveConfig::key`
+	#[cfg(feature = "crypto")]
+	pub(crate) fn check_signature(&self, entry: &RegistryEntry, raw: &[u8]) -> InternalResult<bool> {
+		let (Some(pk), Some(signature)) = (self.key, entry.signature) else {
+			return Ok(false);
+		};
+
+		// The signed message is the signature domain, the raw data then the entry bytes, verify over all without copying the data
+		let entry_bytes = match self.header.is_legacy() {
+			true => entry.to_legacy_bytes(true),
+			false => entry.to_bytes(true),
+		}
+		.map_err(|err| err.context(&entry.id, Some(entry.location), Stage::Verify))?;
+
+		let authentic = match self.header.signature_domain() {
+			Some(domain) => crypto::verify_parts(&pk, &signature, &[&domain, raw, &entry_bytes]),
+			None => self.legacy_signatures && crypto::verify_parts(&pk, &signature, &[raw, &entry_bytes]),
+		};
+
+		Ok(authentic)
+	}
+
+	/// Whether an entry's signature over it's raw data holds, reusing the result of an earlier check if there was one
+	#[cfg(feature = "crypto")]
+	pub(crate) fn authenticate(&self, entry: &RegistryEntry, raw: &[u8]) -> InternalResult<bool> {
+		if entry.signature.is_none() {
+			return Ok(false);
+		}
+
+		let verified = self.verified.get();
+		match verified.and_then(|v| v.get(entry)) {
+			Some(authentic) => Ok(authentic),
+			None => {
+				let authentic = self.check_signature(entry, raw)?;
+				verified.inspect(|v| v.set(entry, authentic));
+				Ok(authentic)
+			},
+		}
+	}
+
+	/// The shared record of checked signatures, created on first use. `None` without a public key
+	#[cfg(feature = "crypto")]
+	pub(crate) fn verified(&self) -> Option<Arc<Verified>> {
+		self.key?;
+		Some(self.verified.get_or_init(|| Arc::new(Verified::new(self.registry().map(|(_, entry)| entry)))).clone())
+	}
+
+	/// The decryptor of leaves encrypted under the given key epoch, built on first use. `None` if the key wasn't provided
+	#[cfg(feature = "crypto")]
+	fn decryptor(&self, epoch: u32) -> Option<&crypto::Encryptor> {
+		if epoch == 0 {
+			return self.decryptor.as_ref();
+		}
+
+		let key = self.epochs.iter().find(|key| key.epoch == epoch)?;
+		Some(key.decryptor.get_or_init(|| crypto::Encryptor::from_key(&key.key.to_bytes(), self.header.magic)))
+	}
+
+	/// The fingerprint of the key leaves of the given epoch are decrypted with
+	#[cfg(feature = "crypto")]
+	fn decrypted_with(&self, epoch: u32) -> Option<KeyId> {
+		match epoch {
+			0 => self.key_ids.1,
+			epoch => self
+				.epochs
+				.iter()
+				.find(|key| key.epoch == epoch)
+				.map(|key| KeyId::of_encryption_key(&key.key)),
+		}
 	}
 
 	// Decompress and|or decrypt the data
 	#[inline(never)]
-	fn process(&self, entry: &RegistryEntry, mut raw: Vec<u8>) -> InternalResult<(Vec<u8>, bool)> {
+	fn process(
+		&self, entry: &RegistryEntry, raw: Vec<u8>, #[cfg(feature = "crypto")] epoch: u32,
+	) -> InternalResult<(Vec<u8>, bool)> {
 		/* Literally the hottest function in the block (🕶) */
+		let context = |stage| move |err: InternalError| err.context(&entry.id, Some(entry.location), stage);
 
 		// buffer_a originally contains the raw data
+		#[cfg(feature = "crypto")]
 		let mut decrypted = None;
-		let mut is_secure = false;
+		#[cfg(not(feature = "crypto"))]
+		let decrypted: Option<Vec<u8>> = None;
 
 		// Signature validation
-		// Validate signature only if a public key is passed with Some(PUBLIC_KEY)
 		#[cfg(feature = "crypto")]
-		if let Some(pk) = self.key {
-			// If there is an error the data is flagged as invalid
-			if let Some(signature) = entry.signature {
-				let raw_size = raw.len();
+		let is_secure = self.authenticate(entry, &raw)?;
+		#[cfg(not(feature = "crypto"))]
+		let is_secure = false;
 
-				let entry_bytes = entry.to_bytes(true)?;
-				raw.extend_from_slice(&entry_bytes);
+		// Leaves encrypted under a rotated key lead with the key's epoch, read by the caller, see `key_epoch`
+		let payload = match entry.flags.contains(Flags::KEY_EPOCH_FLAG) {
+			true if raw.len() < EPOCH_SIZE => return Err(short_epoch(entry, raw.len())),
+			true => EPOCH_SIZE,
+			false => 0,
+		};
 
-				is_secure = pk.verify_strict(&raw, &signature).is_ok();
-				raw.truncate(raw_size);
-			}
+		// Custom pipelines are undone stage by stage, in reverse
+		if entry.flags.contains(Flags::PIPELINE_FLAG) {
+			return self
+				.unwind(
+					entry,
+					&raw[payload..],
+					#[cfg(feature = "crypto")]
+					epoch,
+				)
+				.map(|data| (data, is_secure));
 		}
 
 		// Add read layers
 		// 1: Decryption layer
 		if entry.flags.contains(Flags::ENCRYPTED_FLAG) {
 			#[cfg(feature = "crypto")]
-			match self.decryptor.as_ref() {
+			match self.decryptor(epoch) {
 				Some(dc) => {
-					decrypted = Some(dc.decrypt(&raw)?);
+					decrypted = Some(dc.decrypt(&raw[payload..]).map_err(context(Stage::Decrypt))?);
 				},
-				None => return Err(InternalError::NoKeypairError),
+				None => return Err(context(Stage::Decrypt)(InternalError::NoKeypairError)),
 			}
 
 			#[cfg(not(feature = "crypto"))]
-			return Err(InternalError::MissingFeatureError("crypto"));
+			return Err(context(Stage::Decrypt)(InternalError::MissingFeatureError("crypto")));
 		}
 
 		// 2: Decompression layer
 		if entry.flags.contains(Flags::COMPRESSED_FLAG) {
 			#[cfg(feature = "compression")]
 			{
-				let (source, mut target) = match decrypted {
+				let (source, start, mut target) = match decrypted {
 					// data was decrypted and stored.
 					Some(vec) => {
-						raw.clear();
-						(vec, raw)
+						let mut buffer = raw;
+						buffer.clear();
+						buffer.reserve_exact(capacity_hint(entry));
+						(vec, 0, buffer)
 					},
 					// data was not decrypted nor stored.
+					None => (raw, payload, Vec::with_capacity(capacity_hint(entry))),
+				};
+
+				let algo = match CompressionAlgorithm::from_flags(&entry.flags) {
+					Some(algo) => algo,
 					None => {
-						let capacity = raw.capacity();
-						(raw, Vec::with_capacity(capacity))
+						return InternalResult::Err(context(Stage::Decompress)(InternalError::OtherError(
+							format!(
+								"Unable to determine the compression algorithm used for entry: {}",
+								entry
+							)
+							.into(),
+						)))
 					},
 				};
 
-				if entry.flags.contains(Flags::LZ4_COMPRESSED) {
-					Compressor::new(source.as_slice()).decompress(CompressionAlgorithm::LZ4, &mut target)?
-				} else if entry.flags.contains(Flags::BROTLI_COMPRESSED) {
-					Compressor::new(source.as_slice()).decompress(CompressionAlgorithm::Brotli(0), &mut target)?
-				} else if entry.flags.contains(Flags::SNAPPY_COMPRESSED) {
-					Compressor::new(source.as_slice()).decompress(CompressionAlgorithm::Snappy, &mut target)?
-				} else {
-					return InternalResult::Err(InternalError::OtherError(
-						format!(
-							"Unable to determine the compression algorithm used for entry: {}",
-							entry
-						)
-						.into(),
-					));
-				};
+				Compressor::new(&source[start..])
+					.decompress(algo, &mut target)
+					.map_err(context(Stage::Decompress))?;
 
 				Ok((target, is_secure))
 			}
 
 			#[cfg(not(feature = "compression"))]
-			Err(InternalError::MissingFeatureError("compression"))
+			Err(context(Stage::Decompress)(InternalError::MissingFeatureError("compression")))
 		} else {
 			match decrypted {
 				Some(decrypted) => Ok((decrypted, is_secure)),
-				None => Ok((raw, is_secure)),
+				None if payload == 0 => Ok((raw, is_secure)),
+				None => Ok((raw[payload..].to_vec(), is_secure)),
 			}
 		}
 	}
+
+	/// Undoes the stages recorded in the descriptor of an entry with a custom pipeline, in reverse
+	#[cfg_attr(not(any(feature = "crypto", feature = "compression")), allow(unreachable_code))]
+	fn unwind(&self, entry: &RegistryEntry, raw: &[u8], #[cfg(feature = "crypto")] epoch: u32) -> InternalResult<Vec<u8>> {
+		let context = |stage| move |err: InternalError| err.context(&entry.id, Some(entry.location), stage);
+
+		let (codes, payload) = pipeline::split_descriptor(raw).map_err(context(Stage::Read))?;
+		let mut data = payload.to_vec();
+
+		for code in codes.iter().rev() {
+			data = match *code {
+				pipeline::ENCRYPT => {
+					#[cfg(feature = "crypto")]
+					match self.decryptor(epoch) {
+						Some(dc) => dc.decrypt(&data).map_err(context(Stage::Decrypt))?,
+						None => return Err(context(Stage::Decrypt)(InternalError::NoKeypairError)),
+					}
+
+					#[cfg(not(feature = "crypto"))]
+					return Err(context(Stage::Decrypt)(InternalError::MissingFeatureError("crypto")));
+				},
+				pipeline::COMPRESS => {
+					#[cfg(feature = "compression")]
+					{
+						let algo = CompressionAlgorithm::from_flags(&entry.flags).ok_or_else(|| {
+							context(Stage::Decompress)(InternalError::OtherError(
+								format!("Unable to determine the compression algorithm used for entry: {}", entry).into(),
+							))
+						})?;
+
+						let mut target = Vec::with_capacity(capacity_hint(entry));
+						Compressor::new(data.as_slice())
+							.decompress(algo, &mut target)
+							.map_err(context(Stage::Decompress))?;
+
+						target
+					}
+
+					#[cfg(not(feature = "compression"))]
+					return Err(context(Stage::Decompress)(InternalError::MissingFeatureError("compression")));
+				},
+				code => match pipeline::custom_flag(code) {
+					Some(flag) => match self.stages[flag.trailing_zeros() as usize] {
+						Some(decode) => decode(&data).map_err(context(Stage::Custom))?,
+						None => return Err(context(Stage::Custom)(InternalError::MissingStageError(flag))),
+					},
+					None => {
+						return Err(context(Stage::Custom)(InternalError::OtherError(
+							format!("Unknown pipeline stage code: {}", code).into(),
+						)))
+					},
+				},
+			};
+		}
+
+		Ok(data)
+	}
 }
 
 // INFO: Record Based FileSystem: https://en.wikipedia.org/wiki/Record-oriented_filesystem
@@ -175,55 +546,249 @@ where
 
 	/// Given a read handle, this will read and parse the data into an [`Archive`] struct.
 	/// Pass a reference to [ArchiveConfig] and it will be used to validate the source and for further configuration.
-	pub fn with_config(mut handle: T, config: &ArchiveConfig) -> InternalResult<Archive<T>> {
-		// Start reading from the start of the input
+	pub fn with_config(handle: T, config: &ArchiveConfig) -> InternalResult<Archive<T>> {
+		// Buffer the source, then start reading from the start of the input
+		let mut handle = BufReader::with_capacity(config.read_buffer, handle);
 		handle.seek(SeekFrom::Start(0))?;
 
-		let header = Header::from_handle(&mut handle)?;
-		Header::validate(config, &header)?;
+		let header = Header::from_handle(&mut handle, config)?;
 
-		// Generate and store Registry Entries
-		let mut entries = HashMap::new();
-
-		// Construct entries map
-		for _ in 0..header.capacity {
-			let entry = RegistryEntry::from_handle(&mut handle)?;
-			entries.insert(entry.id.clone(), entry);
+		// Truncation shows in the footer, before anything else is parsed
+		if header.extension(Header::FOOTER).is_some() {
+			footer::check(&mut handle, &header)?;
+			handle.seek(SeekFrom::Start(header.size() as u64))?;
 		}
 
-		let archive = Archive {
-			header,
-			handle: Mutex::new(handle),
-			entries,
+		// Compressed registries follow the leaves, which then start right after the header
+		let (entries, registry_end) = match header.registry_block()? {
+			Some(block) => {
+				let mut compressed = Vec::new();
+				handle.seek(SeekFrom::Start(block.location))?;
+				(&mut handle).take(block.size).read_to_end(&mut compressed)?;
 
-			#[cfg(feature = "crypto")]
-			key: config.public_key,
-			#[cfg(feature = "crypto")]
-			decryptor: config
-				.public_key
-				.as_ref()
-				.map(|pk| crypto::Encryptor::new(pk, config.magic)),
+				let registry = inflate_registry(&block, &compressed)?;
+				(read_registry(registry.as_slice(), &header)?, header.size() as u64)
+			},
+			None => {
+				let entries = read_registry(&mut handle, &header)?;
+				(entries, handle.stream_position()?)
+			},
 		};
+
+		let mut archive = Archive::assemble(handle, header, entries, registry_end, config);
+
+		// Windows are loaded up front, so fetches only need to consult the clock
+		if let Some(clock) = config.validity_clock {
+			archive.validity = Some((clock, archive.validity_windows()?));
+		}
+
 		Ok(archive)
 	}
 
 	/// Fetch a [`RegistryEntry`] from this [`Archive`].
 	/// This can be used for debugging, as the [`RegistryEntry`] holds information on data with the adjacent ID.
 	pub fn fetch_entry(&self, id: impl AsRef<str>) -> Option<RegistryEntry> {
-		self.entries.get(id.as_ref()).cloned()
+		self.fetch_entry_ref(id).cloned()
+	}
+
+	/// Borrow a [`RegistryEntry`] from this [`Archive`], without cloning it.
+	/// Cheaper than `Archive::fetch_entry` as the `ID` and signature are not copied.
+	#[inline(always)]
+	pub fn fetch_entry_ref(&self, id: impl AsRef<str>) -> Option<&RegistryEntry> {
+		lookup(&self.entries, &self.reserved, id.as_ref())
 	}
 
 	/// Returns an immutable reference to the underlying [`HashMap`]. This hashmap stores [`RegistryEntry`] values and uses `String` keys.
+	/// Entries in the [`RESERVED_NAMESPACE`](crate::archive::RESERVED_NAMESPACE) are left out, like they are by the other listings, see `Archive::reserved`
 	#[inline(always)]
-	pub fn entries(&self) -> &HashMap<Arc<str>, RegistryEntry> {
+	pub fn entries(&self) -> &HashMap<EntryId, RegistryEntry> {
 		&self.entries
 	}
 
+	/// An iterator over the entries `vach` keeps in the [`RESERVED_NAMESPACE`](crate::archive::RESERVED_NAMESPACE), eg: `vach::tags`. They can still be fetched by `ID`
+	#[inline(always)]
+	pub fn reserved(&self) -> Entries<'_> {
+		Entries(self.reserved.iter())
+	}
+
+	/// Looks up a live entry by `ID`, reserved ones included. Tombstones are reported as missing
+	pub(crate) fn live(&self, id: &str) -> InternalResult<&RegistryEntry> {
+		live(lookup(&self.entries, &self.reserved, id), id)
+	}
+
+	/// An iterator over the `ID`s and [`RegistryEntry`]s in this [`Archive`], in arbitrary order. Includes tombstones, see `RegistryEntry::is_tombstone`, but not reserved entries
+	#[inline(always)]
+	pub fn iter(&self) -> Entries<'_> {
+		Entries(self.entries.iter())
+	}
+
+	/// Consume the [`Archive`], returning an iterator over owned `ID`s and [`RegistryEntry`]s, in arbitrary order.
+	#[inline(always)]
+	pub fn into_entries(self) -> IntoEntries {
+		IntoEntries(self.entries.into_iter())
+	}
+
+	/// Collects the `ID`s and [`RegistryEntry`]s in this [`Archive`] into a [`Vec`], sorted by `ID`.
+	pub fn entries_vec_sorted(&self) -> Vec<(&EntryId, &RegistryEntry)> {
+		let mut entries = self.entries.iter().collect::<Vec<_>>();
+		entries.sort_unstable_by_key(|(id, _)| *id);
+		entries
+	}
+
+	/// A view of the entries stored in the given namespace, looked up by their `ID`s within it. See `Leaf::namespace`
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add_leaf(Leaf::new(b"Base harbor" as &[u8]).id("maps/harbor.map").namespace("core")).unwrap();
+	/// builder.add_leaf(Leaf::new(b"Stormy harbor" as &[u8]).id("maps/harbor.map").namespace("dlc1")).unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let dlc = archive.namespace("dlc1");
+	/// assert_eq!(dlc.fetch("maps/harbor.map").unwrap().data.as_ref(), b"Stormy harbor");
+	/// assert_eq!(archive.namespaces(), vec!["core", "dlc1"]);
+	/// ```
+	pub fn namespace(&self, namespace: &str) -> Namespace<'_, T> {
+		Namespace::new(self, namespace)
+	}
+
+	/// The namespaces of the entries in this archive, sorted. Entries without a namespace aren't counted
+	pub fn namespaces(&self) -> Vec<&str> {
+		namespace::namespaces(self.entries.keys())
+	}
+
+	/// The entries matching a filter expression, sorted by `ID`. See [`Query`] for the syntax
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(Cursor::new(vec![0u8; 2048]), "textures/rock.png").unwrap();
+	/// builder.add(b"fn main() {}" as &[u8], "scripts/main.rs").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let large = archive.select(r#"size >= 1KB && id ~ "textures/*""#).unwrap();
+	/// assert_eq!(large[0].id.as_ref(), "textures/rock.png");
+	/// ```
+	pub fn select(&self, expr: &str) -> InternalResult<Vec<&RegistryEntry>> {
+		let query = Query::parse(expr)?;
+
+		let mut entries = self.entries.values().filter(|entry| query.matches(entry)).collect::<Vec<_>>();
+		entries.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+
+		Ok(entries)
+	}
+
+	/// The files and directories directly within the directory `dir`, sorted by path. `ID`s nest within directories by their `/` separated segments,
+	/// `dir` may end with a `/` or not and `""` is the root. Empty if there's no such directory, tombstones are left out.
+	/// Looked up in an index of `ID`s built at load, so listings don't visit every entry
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(b"Rock" as &[u8], "textures/rock.png").unwrap();
+	/// builder.add(b"Grass" as &[u8], "textures/terrain/grass.png").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let listing = archive.list_dir("textures/");
+	/// assert_eq!(listing, vec![DirEntry::File("textures/rock.png"), DirEntry::Dir("textures/terrain")]);
+	/// ```
+	pub fn list_dir(&self, dir: &str) -> Vec<DirEntry<'_>> {
+		let mut children = self.children(dir).collect::<Vec<_>>();
+		children.sort_unstable_by_key(DirEntry::path);
+		children
+	}
+
+	/// An iterator over the files and directories directly within the directory `dir`, like `Archive::list_dir` without collecting them.
+	/// Children come in `ID` order, each directory where the first `ID` within it would be, eg: `maps/a.map` comes before `maps/a`
+	pub fn children(&self, dir: &str) -> Children<'_> {
+		self.directory.children(dir)
+	}
+
+	/// The entries whose `ID`s match a glob, sorted by `ID`. `*` matches within a path segment, `**` across segments and `?` a single character, like `id ~ "glob"` in a [`Query`].
+	/// Only entries sharing the pattern's literal prefix are matched against it, eg: those within `sounds/` for `sounds/**/*.ogg`. Tombstones are left out
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(b"Boom" as &[u8], "sounds/sfx/boom.ogg").unwrap();
+	/// builder.add(b"Theme" as &[u8], "sounds/music/theme.mp3").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let ogg = archive.glob("sounds/**/*.ogg");
+	/// assert_eq!(ogg.len(), 1);
+	/// assert_eq!(ogg[0].id.as_ref(), "sounds/sfx/boom.ogg");
+	/// ```
+	pub fn glob(&self, pattern: &str) -> Vec<&RegistryEntry> {
+		self.directory.glob(pattern).filter_map(|id| self.entries.get(id)).collect()
+	}
+
+	/// The _MAGIC_ sequence this archive was written with, which may be one of [`ArchiveConfig::legacy_magic`]
+	#[inline(always)]
+	pub fn magic(&self) -> &[u8; crate::MAGIC_LENGTH] {
+		&self.header.magic
+	}
+
+	/// The path this archive was opened from, `None` unless it was opened with `Archive::open`
+	#[inline(always)]
+	pub fn path(&self) -> Option<&Path> {
+		self.path.as_deref()
+	}
+
+	/// The format features this archive relies on, see [`Capabilities`]
+	#[inline(always)]
+	pub fn capabilities(&self) -> Capabilities {
+		self.header.capabilities
+	}
+
 	/// Global flags extracted from the `Header` section of the source
 	#[inline(always)]
 	pub fn flags(&self) -> &Flags {
 		&self.header.flags
 	}
+
+	/// When the key this archive was encrypted and|or signed with expires, if it was written with a key policy. See `BuilderConfig::key_policy`
+	pub fn key_expiry(&self) -> Option<SystemTime> {
+		self.header.key_expiry()
+	}
+
+	/// Whether the archive's key has expired, use this to warn about expired archives or set `ArchiveConfig::enforce_key_expiry` to refuse them
+	pub fn key_expired(&self) -> bool {
+		self.header.key_expired()
+	}
+
+	/// The name of the archive holding the leaves this one shares with others written alongside it, see `PackSet::common`.
+	/// Layer this archive over the shared one to resolve them, eg: using `Overlay::with_shared`
+	pub fn shared_archive(&self) -> Option<&str> {
+		self.header.shared_archive()
+	}
+
+	/// Whether the signature of the entry with the given `ID` was already checked: `Some(true)` if authentic, `Some(false)` if not.
+	/// `None` for unsigned entries and those not checked yet, see `Archive::verify_in_background`
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn is_verified(&self, id: impl AsRef<str>) -> Option<bool> {
+		let entry = self.fetch_entry_ref(id.as_ref())?;
+		self.verified.get()?.get(entry)
+	}
+
+	/// The random bytes binding the archive's signatures to it, `None` for archives signed without one, see `ArchiveConfig::legacy_signatures`
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn build_id(&self) -> Option<[u8; 16]> {
+		self.header.build_id()
+	}
 }
 
 impl<T> Archive<T>
@@ -231,61 +796,829 @@ where
 	T: Read + Seek,
 {
 	/// Given a data source and a [`RegistryEntry`], gets the adjacent raw data
-	pub(crate) fn read_raw(handle: &mut T, entry: &RegistryEntry) -> InternalResult<Vec<u8>> {
-		let mut buffer = Vec::with_capacity(entry.offset as usize + 64);
-		handle.seek(SeekFrom::Start(entry.location))?;
+	pub(crate) fn read_raw(handle: &mut BufReader<T>, entry: &RegistryEntry) -> InternalResult<Vec<u8>> {
+		// Empty leaves, like placeholders, need no IO at all
+		if entry.offset == 0 {
+			return Ok(Vec::new());
+		}
+
+		let read = |handle: &mut BufReader<T>| -> std::io::Result<Vec<u8>> {
+			handle.seek(SeekFrom::Start(entry.location))?;
 
-		let mut take = handle.take(entry.offset);
-		take.read_to_end(&mut buffer)?;
+			// Reads larger than the internal buffer bypass it entirely. The size comes from the registry, so the buffer only grows as data arrives
+			let mut buffer = Vec::with_capacity(entry.offset.min(READ_RESERVE) as usize);
+			handle.by_ref().take(entry.offset).read_to_end(&mut buffer)?;
 
-		Ok(buffer)
+			match buffer.len() as u64 == entry.offset {
+				true => Ok(buffer),
+				false => Err(std::io::ErrorKind::UnexpectedEof.into()),
+			}
+		};
+
+		read(handle).map_err(|err| InternalError::from(err).context(&entry.id, Some(entry.location), Stage::Read))
 	}
 
 	/// Cheaper alternative to `fetch` that works best for single threaded applications.
 	/// It does not lock the underlying [Mutex], since it requires a mutable reference.
 	/// Therefore the borrow checker statically guarantees the operation is safe. Refer to [`Mutex::get_mut`](Mutex).
 	pub fn fetch_mut(&mut self, id: impl AsRef<str>) -> InternalResult<Resource> {
-		// The reason for this function's unnecessary complexity is it uses the provided functions independently, thus preventing an unnecessary allocation [MAYBE TOO MUCH?]
-		if let Some(entry) = self.fetch_entry(&id) {
-			let raw = Archive::read_raw(self.handle.get_mut().unwrap(), &entry)?;
-
-			// Prepare contextual variables
-			// Decompress and|or decrypt the data
-			let (buffer, is_secure) = self.process(&entry, raw)?;
-
-			Ok(Resource {
-				content_version: entry.content_version,
-				flags: entry.flags,
-				data: buffer.into_boxed_slice(),
-				authenticated: is_secure,
+		// Borrow the entry and the handle independently, thus preventing an unnecessary clone of the entry
+		let entry = live(lookup(&self.entries, &self.reserved, id.as_ref()), id.as_ref())?;
+
+		let raw = Archive::read_raw(sync::get_mut(&mut self.handle), entry)?;
+
+		self.decode(entry, raw)
+	}
+
+	/// Fetch a [`Resource`] with the given `ID`. Tombstones are reported as missing, see `Leaf::tombstone`
+	/// > Locks the underlying [`Mutex`], for a cheaper non-locking operation refer to `Archive::fetch_mut`
+	pub fn fetch(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
+		let entry = self.live(id.as_ref())?;
+
+		let raw = {
+			let mut guard = sync::lock(&self.handle);
+			Archive::read_raw(guard.deref_mut(), entry)?
+		};
+
+		self.decode(entry, raw)
+	}
+
+	/// Fetch several [`Resource`]s at once, results are returned in the same order as the given `ID`s.
+	/// The underlying [`Mutex`] is locked only once, and leaves are read in the order they appear in the source, to reduce seeking.
+	/// Decompression, decryption and validation only happen once the lock is released.
+	pub fn fetch_batch<I, S>(&self, ids: I) -> Vec<InternalResult<Resource>>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		let entries = ids
+			.into_iter()
+			.map(|id| self.live(id.as_ref()))
+			.collect::<Vec<_>>();
+
+		// Read in order of location within the source
+		let mut order = (0..entries.len()).collect::<Vec<_>>();
+		order.sort_by_key(|&idx| entries[idx].as_ref().map(|e| e.location).unwrap_or(0));
+
+		let mut raw = (0..entries.len()).map(|_| None).collect::<Vec<Option<InternalResult<Vec<u8>>>>>();
+
+		{
+			let mut guard = sync::lock(&self.handle);
+			for idx in order {
+				if let Ok(entry) = &entries[idx] {
+					raw[idx] = Some(Archive::read_raw(guard.deref_mut(), entry));
+				}
+			}
+		}
+
+		entries
+			.into_iter()
+			.zip(raw)
+			.map(|(entry, raw)| match (entry, raw) {
+				(Ok(entry), Some(raw)) => self.decode(entry, raw?),
+				(Err(err), _) => Err(err),
+				(Ok(entry), None) => Err(InternalError::MissingResourceError(entry.id.clone())),
 			})
+			.collect()
+	}
+
+	/// Fetches several [`Resource`]s, writing each into it's paired writer. Results are returned in the same order as the requests, each holding the number of bytes written.
+	/// Requests are served in a single pass over the source in order of location, holding the lock throughout, which suits sources where seeking is expensive: hard drives, network shares.
+	/// Unlike `Archive::fetch_batch`, each leaf is decoded and written out before the next is read, so only one leaf is held in memory at a time.
+	/// ```
+	/// use std::io::{Cursor, Write};
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(Cursor::new(b"Hello"), "hello").unwrap();
+	/// builder.add(Cursor::new(b"World"), "world").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let (mut hello, mut world) = (Vec::new(), Vec::new());
+	///
+	/// let results = archive.fetch_many_write(&mut [("world", &mut world as &mut dyn Write), ("hello", &mut hello)]);
+	/// assert!(results.iter().all(|r| r.is_ok()));
+	/// assert_eq!((hello.as_slice(), world.as_slice()), (b"Hello".as_slice(), b"World".as_slice()));
+	/// ```
+	pub fn fetch_many_write<S: AsRef<str>>(&self, requests: &mut [(S, &mut dyn Write)]) -> Vec<InternalResult<u64>> {
+		let mut results = requests
+			.iter()
+			.map(|(id, _)| self.live(id.as_ref()).map(|_| 0))
+			.collect::<Vec<_>>();
+
+		// Serve requests in order of location within the source
+		let mut order = (0..requests.len())
+			.filter_map(|idx| self.live(requests[idx].0.as_ref()).ok().map(|entry| (idx, entry)))
+			.collect::<Vec<_>>();
+
+		order.sort_by_key(|(_, entry)| entry.location);
+
+		let mut guard = sync::lock(&self.handle);
+		for (idx, entry) in order {
+			let writer = &mut requests[idx].1;
+
+			results[idx] = Archive::read_raw(guard.deref_mut(), entry)
+				.and_then(|raw| self.decode(entry, raw))
+				.and_then(|resource| {
+					writer
+						.write_all(&resource.data)
+						.map(|_| resource.data.len() as u64)
+						.map_err(|err| InternalError::from(err).context(&entry.id, Some(entry.location), Stage::Write))
+				});
+		}
+
+		results
+	}
+
+	/// Fetch the entry with the given `ID`, writing it's decoded data into `target` instead of returning a [`Resource`].
+	/// Returns the entry's flags, content version and whether it was authenticated.
+	/// Plain and compressed leaves are streamed straight from the source into `target`, holding the lock throughout; leaves that are encrypted,
+	/// signed while a public key is set, or run through a custom pipeline are decoded in memory first, like `Archive::fetch`.
+	/// Streamed leaves are never held in memory whole, so they bypass `ArchiveConfig::memory_gate`
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add_leaf(Leaf::new(b"Hello".as_slice()).id("hello").version(3)).unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let mut output = Vec::new();
+	///
+	/// let (_, content_version, verified) = archive.fetch_write("hello", &mut output).unwrap();
+	/// assert_eq!((output.as_slice(), content_version, verified), (b"Hello".as_slice(), 3, false));
+	/// ```
+	pub fn fetch_write(&self, id: impl AsRef<str>, mut target: impl Write) -> InternalResult<(Flags, u8, bool)> {
+		let entry = self.live(id.as_ref())?;
+		let context = |stage| move |err: InternalError| err.context(&entry.id, Some(entry.location), stage);
+
+		if self.buffered(entry) {
+			let resource = self.fetch(id)?;
+			target.write_all(&resource.data).map_err(|err| context(Stage::Write)(err.into()))?;
+
+			return Ok((resource.flags, resource.content_version, resource.authenticated));
+		}
+
+		self.admit(entry)?;
+		let start = (self.stats.is_some() || self.slow_fetch.is_some()).then(Instant::now);
+
+		let mut guard = sync::lock(&self.handle);
+		let handle = guard.deref_mut();
+		handle
+			.seek(SeekFrom::Start(entry.location))
+			.map_err(|err| context(Stage::Read)(err.into()))?;
+
+		let mut source = handle.take(entry.offset);
+
+		let written = if entry.flags.contains(Flags::COMPRESSED_FLAG) {
+			#[cfg(feature = "compression")]
+			{
+				let algo = CompressionAlgorithm::from_flags(&entry.flags).ok_or_else(|| {
+					context(Stage::Decompress)(InternalError::OtherError(
+						format!("Unable to determine the compression algorithm used for entry: {}", entry).into(),
+					))
+				})?;
+
+				Compressor::new(&mut source)
+					.decompress_into(algo, &mut target)
+					.map_err(context(Stage::Decompress))?
+			}
+
+			#[cfg(not(feature = "compression"))]
+			return Err(context(Stage::Decompress)(InternalError::MissingFeatureError("compression")));
 		} else {
-			return Err(InternalError::MissingResourceError(id.as_ref().to_string()));
+			// Copied by hand, so read and write errors can be told apart
+			let mut buffer = vec![0; (entry.offset as usize).min(64 * 1024)];
+			let mut written = 0;
+
+			while written < entry.offset {
+				let read = match source.read(&mut buffer) {
+					Ok(0) => return Err(context(Stage::Read)(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())),
+					Ok(read) => read,
+					Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+					Err(err) => return Err(context(Stage::Read)(err.into())),
+				};
+
+				target.write_all(&buffer[..read]).map_err(|err| context(Stage::Write)(err.into()))?;
+				written += read as u64;
+			}
+
+			written
+		};
+
+		drop(guard);
+
+		if let Some(start) = start {
+			self.record(FetchStats {
+				id: entry.id.clone(),
+				raw_size: entry.offset,
+				decoded_size: written,
+				decode_time: start.elapsed(),
+			});
 		}
+
+		Ok((entry.flags, entry.content_version, false))
 	}
 
-	/// Fetch a [`Resource`] with the given `ID`.
-	/// > Locks the underlying [`Mutex`], for a cheaper non-locking operation refer to `Archive::fetch_mut`
-	pub fn fetch(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
-		// The reason for this function's unnecessary complexity is it uses the provided functions independently, thus preventing an unnecessary allocation [MAYBE TOO MUCH?]
-		if let Some(entry) = self.fetch_entry(&id) {
+	/// Walks every leaf once in order of location, validating signatures and decoding each leaf without keeping the decoded data.
+	/// Use it to check an archive's integrity after a download, `progress` is called after each entry. Validity windows are not enforced and nothing is recorded.
+	/// Signatures are only checked when a public key was provided, see `ArchiveConfig::key`. Signed entries that fail to authenticate are reported as [`InternalError::UnauthenticatedError`]
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(Cursor::new(b"Hello"), "hello").unwrap();
+	/// builder.add(Cursor::new(b"World"), "world").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let report = archive.verify_stream(|entry, progress| println!("{}: {}/{}", entry.id, progress.entries_done, progress.entries_total));
+	///
+	/// assert!(report.is_ok());
+	/// assert_eq!((report.unauthenticated, report.bytes), (2, 10));
+	/// ```
+	pub fn verify_stream(&self, mut progress: impl FnMut(&RegistryEntry, &VerifyProgress)) -> VerifyReport {
+		let mut entries = self.registry().map(|(_, e)| e).filter(|e| !e.is_tombstone()).collect::<Vec<_>>();
+		entries.sort_by_key(|e| e.location);
+
+		let mut state = VerifyProgress {
+			entries_done: 0,
+			entries_total: entries.len(),
+			bytes_done: 0,
+			bytes_total: entries.iter().map(|e| e.offset).sum(),
+		};
+
+		let mut report = VerifyReport::default();
+
+		for entry in entries {
+			report.record(self.verify_entry(entry));
+
+			state.entries_done += 1;
+			state.bytes_done += entry.offset;
+			progress(entry, &state);
+		}
+
+		report.bytes = state.bytes_done;
+		report
+	}
+
+	/// Same as `Archive::verify_stream`, decoding entries on `threads` threads at once. Reads still take turns on the source,
+	/// so this pays off when decoding, not reading, is the bottleneck. Entries complete out of order, as do calls to `progress`
+	#[cfg(feature = "multithreaded")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "multithreaded")))]
+	pub fn verify_parallel(&self, threads: usize, progress: impl Fn(&RegistryEntry, &VerifyProgress) + Sync) -> VerifyReport
+	where
+		T: Send,
+	{
+		use std::sync::atomic::{AtomicUsize, Ordering};
+
+		let mut entries = self.registry().map(|(_, e)| e).filter(|e| !e.is_tombstone()).collect::<Vec<_>>();
+		entries.sort_by_key(|e| e.location);
+
+		let state = Mutex::new((
+			VerifyReport::default(),
+			VerifyProgress {
+				entries_done: 0,
+				entries_total: entries.len(),
+				bytes_done: 0,
+				bytes_total: entries.iter().map(|e| e.offset).sum(),
+			},
+		));
+
+		let next = AtomicUsize::new(0);
+		std::thread::scope(|s| {
+			for _ in 0..threads.max(1) {
+				s.spawn(|| {
+					while let Some(entry) = entries.get(next.fetch_add(1, Ordering::Relaxed)) {
+						let outcome = self.verify_entry(entry);
+
+						let mut state = sync::lock(&state);
+						let (report, progress_state) = &mut *state;
+
+						report.record(outcome);
+						progress_state.entries_done += 1;
+						progress_state.bytes_done += entry.offset;
+						progress(entry, progress_state);
+					}
+				});
+			}
+		});
+
+		let (mut report, state) = sync::into_inner(state);
+		report.bytes = state.bytes_done;
+		report
+	}
+
+	/// Reads and decodes an entry, returning whether it was authenticated. Signed entries failing to authenticate are errors
+	fn verify_entry(&self, entry: &RegistryEntry) -> InternalResult<bool> {
+		// Only hold the lock while reading, so other threads may fetch in between
+		let raw = {
+			let mut guard = sync::lock(&self.handle);
+			Archive::read_raw(guard.deref_mut(), entry)
+		}?;
+
+		#[cfg(feature = "crypto")]
+		let epoch = key_epoch(entry, &raw)?;
+
+		let (data, is_secure) = self.process(
+			entry,
+			raw,
+			#[cfg(feature = "crypto")]
+			epoch,
+		)?;
+
+		// Zero is unknown, legacy entries don't record the decoded size of compressed or encrypted data
+		if entry.decoded_size != 0 && data.len() as u64 != entry.decoded_size {
+			let err = InternalError::OtherError(format!("Decoded to {} bytes, expected {}", data.len(), entry.decoded_size).into());
+			return Err(err.context(&entry.id, Some(entry.location), Stage::Verify));
+		}
+
+		#[cfg(feature = "crypto")]
+		if !is_secure && self.key.is_some() && entry.signature.is_some() {
+			let err = InternalError::UnauthenticatedError(entry.id.clone());
+			return Err(err.context(&entry.id, Some(entry.location), Stage::Verify));
+		}
+
+		Ok(is_secure)
+	}
+
+	/// Extracts every entry into the directory `target`, creating it and any subdirectories as needed. Paths are assigned by `mapper` from each entry's `ID`.
+	/// Entries are extracted one at a time in `ID` order, tombstones are skipped. Returns the number of files written
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(Cursor::new(b"Hello"), "greetings/hello.txt").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let directory = std::env::temp_dir().join("vach-unpack-doctest");
+	/// let archive = Archive::new(target).unwrap();
+	///
+	/// assert_eq!(archive.unpack_to(&directory, &SeparatorMapper).unwrap(), 1);
+	/// assert_eq!(std::fs::read(directory.join("greetings/hello.txt")).unwrap(), b"Hello");
+	/// # std::fs::remove_dir_all(directory).unwrap();
+	/// ```
+	pub fn unpack_to(&self, target: impl AsRef<Path>, mapper: &dyn PathMapper) -> InternalResult<usize> {
+		self.unpack(self.entries_vec_sorted(), target.as_ref(), mapper)
+	}
+
+	fn unpack(&self, entries: Vec<(&EntryId, &RegistryEntry)>, target: &Path, mapper: &dyn PathMapper) -> InternalResult<usize> {
+		use std::fs;
+
+		let mut written = 0;
+
+		for (id, entry) in entries {
+			if entry.is_tombstone() {
+				continue;
+			}
+
+			// Resolve before decoding, so unsafe paths are refused without any work
+			let path = path_mapper::resolve(mapper, target, id)?;
+			let resource = self.fetch(id)?;
+
+			if let Some(parent) = path.parent() {
+				fs::create_dir_all(parent)?;
+			}
+
+			fs::write(&path, &resource.data)
+				.map_err(|err| InternalError::from(err).context(id, Some(entry.location), Stage::Write))?;
+			written += 1;
+		}
+
+		Ok(written)
+	}
+
+	/// The validity windows set using `Leaf::valid_from` and `Leaf::valid_until`, empty if the archive has none
+	pub(crate) fn validity_windows(&self) -> InternalResult<HashMap<EntryId, Validity>> {
+		match self.reserved.contains_key(VALIDITY_ID) {
+			true => validity::decode(&self.fetch(VALIDITY_ID)?.data),
+			false => Ok(HashMap::new()),
+		}
+	}
+
+	/// When the entry may be served, unbounded if it has no window. See `Leaf::valid_from` and `ArchiveConfig::enforce_validity`
+	pub fn validity(&self, id: impl AsRef<str>) -> InternalResult<Validity> {
+		let id = self.live(id.as_ref())?.id.as_ref();
+
+		let validity = match &self.validity {
+			Some((_, windows)) => windows.get(id).copied(),
+			None => self.validity_windows()?.get(id).copied(),
+		};
+
+		Ok(validity.unwrap_or_default())
+	}
+
+	/// The dependency graph recorded using `Leaf::depends_on`, empty if the archive has none
+	pub(crate) fn dependency_graph(&self) -> InternalResult<HashMap<EntryId, Vec<EntryId>>> {
+		match self.reserved.contains_key(DEPENDENCIES_ID) {
+			true => dependencies::decode(&self.fetch(DEPENDENCIES_ID)?.data),
+			false => Ok(HashMap::new()),
+		}
+	}
+
+	/// The `ID`s of the leaves the given entry directly depends on, as recorded using `Leaf::depends_on`
+	pub fn dependencies(&self, id: impl AsRef<str>) -> InternalResult<Vec<EntryId>> {
+		let id = self.live(id.as_ref())?.id.clone();
+		Ok(self.dependency_graph()?.remove(&id).unwrap_or_default())
+	}
+
+	/// The given `ID` followed by all of it's dependencies, direct or not, each listed once and nearest first.
+	/// Load an asset with everything it needs in one go by passing the result to [`Archive::fetch_batch`]
+	pub fn closure(&self, id: impl AsRef<str>) -> InternalResult<Vec<EntryId>> {
+		let graph = self.dependency_graph()?;
+
+		let mut closure = vec![self.live(id.as_ref())?.id.clone()];
+		let mut seen = closure.iter().cloned().collect::<HashSet<_>>();
+		let mut idx = 0;
+
+		while idx < closure.len() {
+			for dependency in graph.get(&closure[idx]).into_iter().flatten() {
+				if seen.insert(dependency.clone()) {
+					closure.push(dependency.clone());
+				}
+			}
+
+			idx += 1;
+		}
+
+		Ok(closure)
+	}
+
+	/// The perfect hash over the archive's `ID`s written when `BuilderConfig::id_index` is set, `None` if the archive has none
+	pub fn id_index(&self) -> InternalResult<Option<IdIndex>> {
+		match self.reserved.contains_key(INDEX_ID) {
+			true => IdIndex::decode(&self.fetch(INDEX_ID)?.data).map(Some),
+			false => Ok(None),
+		}
+	}
+
+	/// The [`FormatDescriptor`] of the writer, stored when `BuilderConfig::format_descriptor` is set. Archives without one get a descriptor
+	/// rebuilt from their header: it's spec versions, with the flags, capabilities, extensions and codecs as this loader knows them, and no writer
+	pub fn format_descriptor(&self) -> InternalResult<FormatDescriptor> {
+		if self.reserved.contains_key(DESCRIPTOR_ID) {
+			let data = self.fetch(DESCRIPTOR_ID)?.data;
+			let text = std::str::from_utf8(&data)
+				.map_err(|_| InternalError::ParseError("The format descriptor isn't valid UTF-8".to_string()))?;
+
+			return text.parse();
+		}
+
+		Ok(FormatDescriptor {
+			spec_version: self.header.arch_version,
+			min_loader_version: self.header.min_loader_version,
+			writer: None,
+			..FormatDescriptor::current()
+		})
+	}
+
+	/// The tags set using `Leaf::tag`, each with the `ID`s carrying it. Empty if the archive has none
+	pub(crate) fn tag_table(&self) -> InternalResult<HashMap<String, Vec<EntryId>>> {
+		match self.reserved.contains_key(TAGS_ID) {
+			true => tags::decode(&self.fetch(TAGS_ID)?.data),
+			false => Ok(HashMap::new()),
+		}
+	}
+
+	/// Every tag used within the archive, sorted
+	pub fn groups(&self) -> InternalResult<Vec<String>> {
+		let mut groups = self.tag_table()?.into_keys().collect::<Vec<_>>();
+		groups.sort_unstable();
+
+		Ok(groups)
+	}
+
+	/// The tags of the given entry, in the order they were added using `Leaf::tag`
+	pub fn tags(&self, id: impl AsRef<str>) -> InternalResult<Vec<String>> {
+		let id = self.live(id.as_ref())?.id.as_ref();
+
+		let mut tags = self
+			.tag_table()?
+			.into_iter()
+			.filter_map(|(tag, ids)| ids.iter().any(|i| i.as_ref() == id).then_some(tag))
+			.collect::<Vec<_>>();
+
+		tags.sort_unstable();
+		Ok(tags)
+	}
+
+	/// The `ID`s of every live entry carrying the given tag, sorted. Empty for unknown tags
+	pub fn group(&self, tag: &str) -> InternalResult<Vec<EntryId>> {
+		let mut ids = self.tag_table()?.remove(tag).unwrap_or_default();
+		ids.retain(|id| self.live(id).is_ok());
+		ids.sort_unstable();
+
+		Ok(ids)
+	}
+
+	/// Fetches every entry carrying the given tag, in one pass over the source using `Archive::fetch_batch`. Fails on the first entry that can't be fetched
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add_leaf(Leaf::new(b"grass".as_slice()).id("grass.png").tag("level1").tag("level2")).unwrap();
+	/// builder.add_leaf(Leaf::new(b"lava".as_slice()).id("lava.png").tag("level2")).unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let level = archive.fetch_group("level1").unwrap();
+	///
+	/// assert_eq!(level.len(), 1);
+	/// assert_eq!(level[0].1.data.as_ref(), b"grass");
+	/// ```
+	pub fn fetch_group(&self, tag: &str) -> InternalResult<Vec<(EntryId, Resource)>> {
+		let ids = self.group(tag)?;
+		let resources = self.fetch_batch(&ids);
+
+		ids.into_iter().zip(resources).map(|(id, resource)| Ok((id, resource?))).collect()
+	}
+
+	/// Like `Archive::unpack_to`, but only writes out the entries carrying the given tag. Returns the number of files written
+	pub fn unpack_group(&self, tag: &str, target: impl AsRef<Path>, mapper: &dyn PathMapper) -> InternalResult<usize> {
+		let ids = self.group(tag)?;
+		let entries = ids.iter().filter_map(|id| self.entries.get_key_value(id)).collect();
+
+		self.unpack(entries, target.as_ref(), mapper)
+	}
+
+	/// Checks every leaf for likely mistakes, see [`LintWarning`]. Warnings are grouped by leaf, in `ID` order.
+	/// Content checks are skipped for leaves that can't be decoded, for example encrypted leaves when no key was provided
+	pub fn lint(&self) -> InternalResult<Vec<LintWarning>> {
+		let mut linter = Linter::default();
+
+		for (id, entry) in self.entries_vec_sorted() {
+			linter.id(id);
+
 			let raw = {
-				let mut guard = self.handle.lock().unwrap();
-				Archive::read_raw(guard.deref_mut(), &entry)?
+				let mut guard = sync::lock(&self.handle);
+				Archive::read_raw(guard.deref_mut(), entry)?
 			};
 
-			// Prepare contextual variables
-			// Decompress and|or decrypt the data
-			let (buffer, is_secure) = self.process(&entry, raw)?;
+			if entry.flags.contains(Flags::ENCRYPTED_FLAG) {
+				linter.encrypted(id, &raw);
+			}
+
+			match self.decode(entry, raw) {
+				Ok(resource) => linter.content(id, &resource.data, entry.flags.contains(Flags::COMPRESSED_FLAG)),
+				Err(err) if matches!(err.root(), InternalError::NoKeypairError | InternalError::MissingFeatureError(_)) => {
+					continue
+				},
+				Err(err) => return Err(err),
+			}
+		}
+
+		Ok(linter.finish())
+	}
 
-			Ok(Resource {
-				content_version: entry.content_version,
-				flags: entry.flags,
-				data: buffer.into_boxed_slice(),
-				authenticated: is_secure,
+	/// Maps the archive's bytes into the header, the registry, each leaf and any unused gaps between them, see [`LayoutReport`]
+	pub fn layout_report(&self) -> InternalResult<LayoutReport> {
+		let size = sync::lock(&self.handle).seek(SeekFrom::End(0))?;
+
+		let mut regions = self
+			.registry()
+			.map(|(_, entry)| Region {
+				start: entry.location,
+				end: entry.location + entry.offset,
+				kind: RegionKind::Leaf(entry.id.clone()),
 			})
-		} else {
-			return Err(InternalError::MissingResourceError(id.as_ref().to_string()));
+			.collect::<Vec<_>>();
+
+		// Compressed registries follow the leaves
+		if let Some(block) = self.header.registry_block()? {
+			regions.push(Region {
+				start: block.location,
+				end: block.location + block.size,
+				kind: RegionKind::Registry,
+			});
+		}
+
+		Ok(LayoutReport::new(self.header.size() as u64, self.registry_end, size, regions))
+	}
+}
+
+/// A key of a later epoch, see `ArchiveConfig::add_decryption_key`
+#[cfg(feature = "crypto")]
+#[derive(Debug)]
+struct EpochKey {
+	epoch: u32,
+	key: crypto::EncryptionKey,
+	decryptor: OnceLock<crypto::Encryptor>,
+}
+
+/// The size of the key epoch leading the data of leaves encrypted under a rotated key
+const EPOCH_SIZE: usize = 4;
+
+/// The epoch of the key an entry was encrypted with, read from the start of it's raw data. Entries without one are of epoch `0`
+#[cfg(feature = "crypto")]
+fn key_epoch(entry: &RegistryEntry, raw: &[u8]) -> InternalResult<u32> {
+	if !entry.flags.contains(Flags::KEY_EPOCH_FLAG) {
+		return Ok(0);
+	}
+
+	match raw.first_chunk::<EPOCH_SIZE>() {
+		Some(epoch) => Ok(u32::from_le_bytes(*epoch)),
+		None => Err(short_epoch(entry, raw.len())),
+	}
+}
+
+fn short_epoch(entry: &RegistryEntry, len: usize) -> InternalError {
+	InternalError::OtherError(format!("The data is {} bytes long, too short to hold a key epoch", len).into()).context(
+		&entry.id,
+		Some(entry.location),
+		Stage::Read,
+	)
+}
+
+/// Fingerprints of the keys an [`Archive`] verifies and decrypts with
+#[cfg(feature = "crypto")]
+fn key_ids(config: &ArchiveConfig) -> (Option<KeyId>, Option<KeyId>) {
+	let verify = config.public_key.as_ref().map(KeyId::of_public_key);
+
+	match config.decryption_key.as_ref() {
+		Some(key) => (verify, Some(KeyId::of_encryption_key(key))),
+		None => (verify, verify),
+	}
+}
+
+#[cfg(not(feature = "crypto"))]
+fn key_ids(_: &ArchiveConfig) -> (Option<KeyId>, Option<KeyId>) {
+	(None, None)
+}
+
+/// Reads the registry following the header, prefixed IDs are read relative to the previous entry's
+pub(crate) fn read_registry<R: Read>(mut handle: R, header: &Header) -> InternalResult<HashMap<EntryId, RegistryEntry>> {
+	let mut entries = HashMap::new();
+
+	let prefixed = header.capabilities.contains(Capabilities::PREFIXED_IDS);
+	let mut previous = String::new();
+
+	for _ in 0..header.capacity {
+		let entry = match header.is_legacy() {
+			true => RegistryEntry::from_legacy_handle(&mut handle)?,
+			false => RegistryEntry::from_handle(&mut handle, prefixed.then_some(previous.as_str()))?,
+		};
+
+		if prefixed {
+			previous.clear();
+			previous.push_str(&entry.id);
 		}
+
+		entries.insert(entry.id.clone(), entry);
+	}
+
+	Ok(entries)
+}
+
+/// Decompresses a registry stored after the leaves, see `BuilderConfig::compress_registry`
+#[cfg(feature = "compression")]
+pub(crate) fn inflate_registry(block: &RegistryBlock, compressed: &[u8]) -> InternalResult<Vec<u8>> {
+	if compressed.len() as u64 != block.size {
+		return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+	}
+
+	let algo = CompressionAlgorithm::from_flags(&Flags::from_bits(block.codec))
+		.ok_or_else(|| InternalError::OtherError("Unknown compression algorithm for the registry".into()))?;
+
+	// Stop decompressing a byte past the recorded size, so a forged block can't inflate without bound
+	let mut registry = Vec::with_capacity(block.decoded_size.min(READ_RESERVE) as usize);
+	Compressor::new(compressed)
+		.into_decoder(algo)
+		.take(block.decoded_size.saturating_add(1))
+		.read_to_end(&mut registry)?;
+
+	if registry.len() as u64 > block.decoded_size {
+		return Err(InternalError::OtherError(
+			format!(
+				"The registry decompressed into more than the {} bytes recorded",
+				block.decoded_size
+			)
+			.into(),
+		));
+	}
+
+	if registry.len() as u64 != block.decoded_size {
+		return Err(InternalError::OtherError(
+			format!(
+				"The registry decompressed into {} bytes, {} were recorded",
+				registry.len(),
+				block.decoded_size
+			)
+			.into(),
+		));
+	}
+
+	Ok(registry)
+}
+
+/// Refused by `Header::from_handle` beforehand, archives with a compressed registry need the `compression` feature
+#[cfg(not(feature = "compression"))]
+pub(crate) fn inflate_registry(_: &RegistryBlock, _: &[u8]) -> InternalResult<Vec<u8>> {
+	Err(InternalError::MissingFeatureError("compression"))
+}
+
+/// Looks an entry up among the listed entries, then the reserved ones. The `ID` is normalized first, like `EntryId::new` does
+#[inline(always)]
+fn lookup<'a>(
+	entries: &'a HashMap<EntryId, RegistryEntry>, reserved: &'a HashMap<EntryId, RegistryEntry>, id: &str,
+) -> Option<&'a RegistryEntry> {
+	let id = normalize(id);
+	entries.get(id.as_ref()).or_else(|| reserved.get(id.as_ref()))
+}
+
+/// The entry with the given `ID`, tombstones are treated as missing
+fn live<'a>(entry: Option<&'a RegistryEntry>, id: &str) -> InternalResult<&'a RegistryEntry> {
+	match entry {
+		Some(entry) if !entry.is_tombstone() => Ok(entry),
+		_ => Err(InternalError::MissingResourceError(EntryId::from_raw(id))),
+	}
+}
+
+/// Capacity to reserve for a leaf's decompressed data. The recorded decoded size is bounded, so a corrupt or hostile registry can't force huge allocations
+#[cfg(feature = "compression")]
+#[inline(always)]
+fn capacity_hint(entry: &RegistryEntry) -> usize {
+	entry.decoded_size.min(entry.offset.saturating_mul(64)) as usize
+}
+
+/// A borrowing iterator over the entries of an [`Archive`], see `Archive::iter`
+#[derive(Debug, Clone)]
+pub struct Entries<'a>(hash_map::Iter<'a, EntryId, RegistryEntry>);
+
+impl<'a> Iterator for Entries<'a> {
+	type Item = (&'a EntryId, &'a RegistryEntry);
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next()
+	}
+
+	#[inline(always)]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+
+impl<'a> ExactSizeIterator for Entries<'a> {}
+impl<'a> FusedIterator for Entries<'a> {}
+
+/// A consuming iterator over the entries of an [`Archive`], see `Archive::into_entries`
+#[derive(Debug)]
+pub struct IntoEntries(hash_map::IntoIter<EntryId, RegistryEntry>);
+
+impl Iterator for IntoEntries {
+	type Item = (EntryId, RegistryEntry);
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next()
+	}
+
+	#[inline(always)]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+
+impl ExactSizeIterator for IntoEntries {}
+impl FusedIterator for IntoEntries {}
+
+impl<'a, T> IntoIterator for &'a Archive<T> {
+	type Item = (&'a EntryId, &'a RegistryEntry);
+	type IntoIter = Entries<'a>;
+
+	#[inline(always)]
+	fn into_iter(self) -> Self::IntoIter {
+		Entries(self.entries.iter())
+	}
+}
+
+impl<T> IntoIterator for Archive<T> {
+	type Item = (EntryId, RegistryEntry);
+	type IntoIter = IntoEntries;
+
+	#[inline(always)]
+	fn into_iter(self) -> Self::IntoIter {
+		IntoEntries(self.entries.into_iter())
+	}
+}
+
+/// The most reserved up front for a leaf's data, larger leaves grow their buffer as they're read.
+/// Sizes come from the registry, so a forged one fails on a short read instead of allocating all it claims
+pub(crate) const READ_RESERVE: u64 = 8 * 1024 * 1024;
+
+/// Refuses an entry whose data runs past the end of a source of `size` bytes, before any buffer is allocated for it
+pub(crate) fn within(entry: &RegistryEntry, size: u64) -> std::io::Result<()> {
+	match entry.location.checked_add(entry.offset) {
+		Some(end) if end <= size => Ok(()),
+		_ => Err(std::io::ErrorKind::UnexpectedEof.into()),
 	}
 }
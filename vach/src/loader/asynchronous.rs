@@ -0,0 +1,273 @@
+#![cfg(feature = "async")]
+#![cfg_attr(docsrs, doc(cfg(feature = "async")))]
+
+use std::{
+	collections::HashMap,
+	io::{self, BufReader, SeekFrom},
+};
+
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt},
+	sync::Mutex,
+};
+
+use super::{
+	archive::{inflate_registry, read_registry, Archive, READ_RESERVE},
+	footer,
+	resource::Resource,
+};
+use crate::{
+	format::{footer::RawFooter, header::RawHeader, registry::RawEntry},
+	global::{
+		entry_id::EntryId,
+		error::*,
+		flags::Flags,
+		header::{ArchiveConfig, Capabilities, Header},
+		reg_entry::RegistryEntry,
+		validity::{self, VALIDITY_ID},
+	},
+};
+
+/// Like [`Archive`], but over an asynchronous source, eg: a TCP stream or an HTTP body with range requests behind [`AsyncRead`] and [`AsyncSeek`].
+/// The header and registry are parsed as they arrive, without blocking the runtime.
+///
+/// Only reads are asynchronous, decompression, decryption and signature checks run inline on the calling task,
+/// so move large leaves to a blocking pool if they hold up the runtime
+#[derive(Debug)]
+pub struct AsyncArchive<T> {
+	handle: Mutex<T>,
+	// Decodes raw data, it's own source is never read
+	archive: Archive<io::Empty>,
+}
+
+impl<T> AsyncArchive<T>
+where
+	T: AsyncRead + AsyncSeek + Unpin,
+{
+	/// Load an [`AsyncArchive`] with the default settings from an asynchronous source, see `AsyncArchive::with_config`
+	#[inline(always)]
+	pub async fn new(handle: T) -> InternalResult<AsyncArchive<T>> {
+		AsyncArchive::with_config(handle, &ArchiveConfig::default()).await
+	}
+
+	/// Reads and parses the header and registry of an archive from an asynchronous source, validating and configuring it with [`ArchiveConfig`].
+	/// The header is read in one go, then each registry entry in two reads, so nothing past the registry is read unless the archive has a footer.
+	/// `ArchiveConfig::read_buffer` is ignored, wrap the source in a [`BufReader`](tokio::io::BufReader) to buffer it
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::{prelude::*, archive::AsyncArchive};
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(b"Hello, Cassandra!" as &[u8], "hello").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+	/// let archive = AsyncArchive::new(Cursor::new(target.into_inner())).await.unwrap();
+	/// let resource = archive.fetch("hello").await.unwrap();
+	/// assert_eq!(resource.data.as_ref(), b"Hello, Cassandra!");
+	/// # });
+	/// ```
+	pub async fn with_config(mut handle: T, config: &ArchiveConfig) -> InternalResult<AsyncArchive<T>> {
+		handle.seek(SeekFrom::Start(0)).await?;
+
+		let bytes = read_header(&mut handle, config).await?;
+		let header = Header::from_handle(bytes.as_slice(), config)?;
+
+		// Truncation shows in the footer, before anything else is parsed
+		if header.extension(Header::FOOTER).is_some() {
+			let size = handle.seek(SeekFrom::End(0)).await?;
+			if size < RawFooter::SIZE as u64 {
+				return Err(InternalError::FooterMismatchError(format!(
+					"The source takes {} bytes, too few to hold a footer",
+					size
+				)));
+			}
+
+			let mut bytes = [0; RawFooter::SIZE];
+			handle.seek(SeekFrom::End(-(RawFooter::SIZE as i64))).await?;
+			handle.read_exact(&mut bytes).await?;
+
+			footer::compare(&footer::decode(&bytes)?, size, &header)?;
+			handle.seek(SeekFrom::Start(header.size() as u64)).await?;
+		}
+
+		// Compressed registries follow the leaves, which then start right after the header
+		let (entries, registry_end) = match header.registry_block()? {
+			Some(block) => {
+				let mut compressed = Vec::new();
+				handle.seek(SeekFrom::Start(block.location)).await?;
+				(&mut handle).take(block.size).read_to_end(&mut compressed).await?;
+
+				let registry = inflate_registry(&block, &compressed)?;
+				(read_registry(registry.as_slice(), &header)?, header.size() as u64)
+			},
+			None => {
+				let registry = read_registry_bytes(&mut handle, &header).await?;
+				let entries = read_registry(registry.as_slice(), &header)?;
+				(entries, (header.size() + registry.len()) as u64)
+			},
+		};
+
+		let archive = Archive::assemble(
+			BufReader::with_capacity(0, io::empty()),
+			header,
+			entries,
+			registry_end,
+			config,
+		);
+
+		let mut archive = AsyncArchive {
+			handle: Mutex::new(handle),
+			archive,
+		};
+
+		// Windows are loaded up front, so fetches only need to consult the clock
+		if let Some(clock) = config.validity_clock {
+			let windows = match archive.archive.fetch_entry_ref(VALIDITY_ID).is_some() {
+				true => validity::decode(&archive.fetch(VALIDITY_ID).await?.data)?,
+				false => HashMap::new(),
+			};
+
+			archive.archive.validity = Some((clock, windows));
+		}
+
+		Ok(archive)
+	}
+
+	/// Fetch a [`Resource`] with the given `ID`, reading it's data asynchronously. Tombstones are reported as missing, see `Leaf::tombstone`.
+	/// > Locks the source for the duration of the read, concurrent fetches are read one after another then decoded independently
+	pub async fn fetch(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
+		let entry = self.archive.live(id.as_ref())?;
+
+		let raw = {
+			let mut handle = self.handle.lock().await;
+			read_raw(&mut *handle, entry)
+				.await
+				.map_err(|err| InternalError::from(err).context(&entry.id, Some(entry.location), Stage::Read))?
+		};
+
+		self.archive.decode(entry, raw)
+	}
+}
+
+impl<T> AsyncArchive<T> {
+	/// Fetch a [`RegistryEntry`] from this [`AsyncArchive`], see `Archive::fetch_entry`
+	pub fn fetch_entry(&self, id: impl AsRef<str>) -> Option<RegistryEntry> {
+		self.archive.fetch_entry(id)
+	}
+
+	/// Returns an immutable reference to the underlying [`HashMap`]. This hashmap stores [`RegistryEntry`] values and uses `String` keys.
+	pub fn entries(&self) -> &HashMap<EntryId, RegistryEntry> {
+		self.archive.entries()
+	}
+
+	/// Global flags extracted from the `Header` section of the source
+	pub fn flags(&self) -> &Flags {
+		self.archive.flags()
+	}
+
+	/// The magic the archive was written with
+	pub fn magic(&self) -> &[u8; crate::MAGIC_LENGTH] {
+		self.archive.magic()
+	}
+
+	/// The optional parts of the format the archive was written with
+	pub fn capabilities(&self) -> Capabilities {
+		self.archive.capabilities()
+	}
+
+	/// Consume the [`AsyncArchive`] and return the underlying source
+	pub fn into_inner(self) -> T {
+		self.handle.into_inner()
+	}
+}
+
+/// Reads the header's fixed part, then it's extensions if it has any
+async fn read_header<T: AsyncRead + Unpin>(handle: &mut T, config: &ArchiveConfig) -> io::Result<Vec<u8>> {
+	let mut prefix = [0u8; RawHeader::PREFIX_SIZE];
+	handle.read_exact(&mut prefix).await?;
+
+	// Foreign sources are refused by `Header::from_handle`, without reading anything more
+	let (magic, _, arch_version) = RawHeader::decode_prefix(&prefix);
+	if !config.accepts_magic(&magic) {
+		return Ok(prefix.to_vec());
+	}
+
+	// Legacy headers end with the capacity
+	if arch_version == crate::LEGACY_VERSION {
+		let mut base = [0u8; RawHeader::LEGACY_SIZE];
+		base[..RawHeader::PREFIX_SIZE].copy_from_slice(&prefix);
+		handle.read_exact(&mut base[RawHeader::PREFIX_SIZE..]).await?;
+		return Ok(base.to_vec());
+	}
+
+	let mut base = [0u8; Header::BASE_SIZE];
+	base[..RawHeader::PREFIX_SIZE].copy_from_slice(&prefix);
+	handle.read_exact(&mut base[RawHeader::PREFIX_SIZE..]).await?;
+
+	let raw = RawHeader::decode(&base);
+	let mut bytes = base.to_vec();
+
+	if raw.capabilities & Capabilities::EXTENDED_HEADER == 0 {
+		return Ok(bytes);
+	}
+
+	let mut length = [0u8; 4];
+	handle.read_exact(&mut length).await?;
+	bytes.extend_from_slice(&length);
+
+	handle.take(u32::from_le_bytes(length) as u64).read_to_end(&mut bytes).await?;
+	Ok(bytes)
+}
+
+/// Reads every registry entry in two reads, the fixed part then the tail it announces
+async fn read_registry_bytes<T: AsyncRead + Unpin>(handle: &mut T, header: &Header) -> io::Result<Vec<u8>> {
+	let prefixed = header.capabilities.contains(Capabilities::PREFIXED_IDS);
+	let mut bytes = Vec::new();
+
+	// Legacy entries lack the decoded size, and are never prefixed
+	if header.is_legacy() {
+		for _ in 0..header.capacity {
+			let mut fixed = [0u8; RawEntry::LEGACY_MIN_SIZE];
+			handle.read_exact(&mut fixed).await?;
+			bytes.extend_from_slice(&fixed);
+
+			let start = bytes.len();
+			bytes.resize(start + RawEntry::legacy_tail_size(&fixed), 0);
+			handle.read_exact(&mut bytes[start..]).await?;
+		}
+
+		return Ok(bytes);
+	}
+
+	for _ in 0..header.capacity {
+		let mut fixed = [0u8; RawEntry::MIN_SIZE];
+		handle.read_exact(&mut fixed).await?;
+		bytes.extend_from_slice(&fixed);
+
+		let start = bytes.len();
+		bytes.resize(start + RawEntry::tail_size(&fixed, prefixed), 0);
+		handle.read_exact(&mut bytes[start..]).await?;
+	}
+
+	Ok(bytes)
+}
+
+async fn read_raw<T: AsyncRead + AsyncSeek + Unpin>(handle: &mut T, entry: &RegistryEntry) -> io::Result<Vec<u8>> {
+	// Empty leaves, like placeholders, need no IO at all
+	if entry.offset == 0 {
+		return Ok(Vec::new());
+	}
+
+	handle.seek(SeekFrom::Start(entry.location)).await?;
+
+	// The size comes from the registry, so the buffer only grows as data arrives
+	let mut buffer = Vec::with_capacity(entry.offset.min(READ_RESERVE) as usize);
+	(&mut *handle).take(entry.offset).read_to_end(&mut buffer).await?;
+
+	match buffer.len() as u64 == entry.offset {
+		true => Ok(buffer),
+		false => Err(io::ErrorKind::UnexpectedEof.into()),
+	}
+}
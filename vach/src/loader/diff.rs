@@ -0,0 +1,98 @@
+use std::{
+	io::{Read, Seek},
+	ops::DerefMut,
+};
+
+use super::archive::Archive;
+use crate::global::{entry_id::EntryId, error::*, reg_entry::RegistryEntry, sync};
+
+/// The differences between two archives, as returned by [`compare`]. Every list is sorted by `ID`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveDelta {
+	/// Entries only found in the new archive
+	pub added: Vec<EntryId>,
+	/// Entries only found in the old archive
+	pub removed: Vec<EntryId>,
+	/// Entries found in both archives, whose content or content version differ
+	pub changed: Vec<EntryId>,
+	/// Entries found in both archives, with the same content and content version
+	pub identical: Vec<EntryId>,
+}
+
+impl ArchiveDelta {
+	/// Whether any entry was added, removed or changed, eg: whether a launcher has an update to download
+	pub fn has_changes(&self) -> bool {
+		!(self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty())
+	}
+}
+
+/// Compares two archives entry by entry. Entries are the same when their decoded content and content version match,
+/// regardless of how each archive compressed or encrypted them. Stored bytes are compared first, so entries are only decoded
+/// when their stored bytes differ, which needs the archives' keys for encrypted entries
+/// ```
+/// use std::io::Cursor;
+/// use vach::{prelude::*, diff};
+///
+/// let mut builder = Builder::new();
+/// builder.add(b"Version 1" as &[u8], "notes.txt").unwrap();
+///
+/// let mut old = Cursor::new(Vec::new());
+/// builder.dump(&mut old, &BuilderConfig::default()).unwrap();
+///
+/// let mut builder = Builder::new();
+/// builder.add(b"Version 2" as &[u8], "notes.txt").unwrap();
+///
+/// let mut new = Cursor::new(Vec::new());
+/// builder.dump(&mut new, &BuilderConfig::default()).unwrap();
+///
+/// let delta = diff::compare(&Archive::new(old).unwrap(), &Archive::new(new).unwrap()).unwrap();
+/// assert_eq!(delta.changed, ["notes.txt"]);
+/// ```
+pub fn compare<A, B>(old: &Archive<A>, new: &Archive<B>) -> InternalResult<ArchiveDelta>
+where
+	A: Read + Seek,
+	B: Read + Seek,
+{
+	let mut delta = ArchiveDelta::default();
+
+	for (id, entry) in old.iter() {
+		match new.fetch_entry_ref(id) {
+			Some(other) if same(old, entry, new, other)? => delta.identical.push(id.clone()),
+			Some(_) => delta.changed.push(id.clone()),
+			None => delta.removed.push(id.clone()),
+		}
+	}
+
+	delta.added = new
+		.iter()
+		.filter(|(id, _)| old.fetch_entry_ref(id).is_none())
+		.map(|(id, _)| id.clone())
+		.collect();
+
+	delta.added.sort_unstable();
+	delta.removed.sort_unstable();
+	delta.changed.sort_unstable();
+	delta.identical.sort_unstable();
+
+	Ok(delta)
+}
+
+fn same<A, B>(old: &Archive<A>, a: &RegistryEntry, new: &Archive<B>, b: &RegistryEntry) -> InternalResult<bool>
+where
+	A: Read + Seek,
+	B: Read + Seek,
+{
+	if a.content_version != b.content_version || a.decoded_size != b.decoded_size {
+		return Ok(false);
+	}
+
+	let raw_a = Archive::read_raw(sync::lock(&old.handle).deref_mut(), a)?;
+	let raw_b = Archive::read_raw(sync::lock(&new.handle).deref_mut(), b)?;
+
+	// Identically processed bytes decode identically
+	if a.flags == b.flags && raw_a == raw_b {
+		return Ok(true);
+	}
+
+	Ok(old.decode(a, raw_a)?.data == new.decode(b, raw_b)?.data)
+}
@@ -0,0 +1,102 @@
+use std::{
+	fs::File,
+	io::{self, Read, Seek, SeekFrom},
+	ops::DerefMut,
+	path::Path,
+};
+
+use super::archive::Archive;
+use crate::{
+	global::{error::*, flags::Flags, sync},
+	testing::CountingWriter,
+};
+
+impl Archive<File> {
+	/// Write the decoded data of the entry with the given `ID` into a new file at `path`, replacing any existing file. Returns the number of bytes written.
+	/// Leaves stored as is, neither compressed, encrypted, run through a custom pipeline nor signed while a public key is set, are copied from file to file
+	/// within the kernel where the platform allows it: `copy_file_range` on Linux, which also shares extents on filesystems supporting reflinks.
+	/// Elsewhere, or when the kernel refuses, they are copied through a buffer. All other leaves are decoded as by `Archive::fetch_write`
+	/// ```skip
+	/// let archive = Archive::open("videos.vach")?;
+	/// archive.extract_entry_to_file("intro.webm", "cache/intro.webm")?;
+	/// ```
+	pub fn extract_entry_to_file(&self, id: impl AsRef<str>, path: impl AsRef<Path>) -> InternalResult<u64> {
+		let entry = self.live(id.as_ref())?;
+		let context = |stage| move |err: io::Error| InternalError::from(err).context(&entry.id, Some(entry.location), stage);
+
+		let mut target = File::create(path).map_err(context(Stage::Write))?;
+
+		if self.buffered(entry) || entry.flags.contains(Flags::COMPRESSED_FLAG) {
+			let mut target = CountingWriter::new(io::BufWriter::new(target));
+
+			self.fetch_write(id, &mut target)?;
+			io::Write::flush(&mut target).map_err(context(Stage::Write))?;
+
+			return Ok(target.bytes_written());
+		}
+
+		self.admit(entry)?;
+
+		let mut guard = sync::lock(&self.handle);
+		let handle = guard.deref_mut();
+
+		// Copy within the kernel first, finishing any remainder through a buffer
+		let copied = kernel_copy(handle.get_ref(), entry.location, &target, entry.offset).map_err(context(Stage::Read))?;
+
+		if copied < entry.offset {
+			let remaining = entry.offset - copied;
+
+			handle
+				.seek(SeekFrom::Start(entry.location + copied))
+				.map_err(context(Stage::Read))?;
+
+			let written = io::copy(&mut handle.take(remaining), &mut target).map_err(context(Stage::Write))?;
+			if written != remaining {
+				return Err(context(Stage::Read)(io::ErrorKind::UnexpectedEof.into()));
+			}
+		}
+
+		Ok(entry.offset)
+	}
+}
+
+/// Copies up to `len` bytes from `source` at `offset` to the current position of `target`, without passing them through userspace.
+/// Returns how many bytes were copied, stopping early where the kernel doesn't support the copy so the caller can finish it
+#[cfg(target_os = "linux")]
+fn kernel_copy(source: &File, offset: u64, target: &File, len: u64) -> io::Result<u64> {
+	use std::os::fd::AsRawFd;
+
+	let mut offset = offset as libc::loff_t;
+	let mut copied = 0;
+
+	while copied < len {
+		let chunk = (len - copied).min(1 << 30) as usize;
+
+		// SAFETY: Both descriptors are valid for the duration of the call. The source offset is passed explicitly, so the source's cursor is left untouched
+		let result = unsafe {
+			libc::copy_file_range(source.as_raw_fd(), &mut offset, target.as_raw_fd(), std::ptr::null_mut(), chunk, 0)
+		};
+
+		match result {
+			0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+			n if n > 0 => copied += n as u64,
+			_ => {
+				let err = io::Error::last_os_error();
+
+				// Old kernels, cross filesystem copies and special files fall back to a buffered copy
+				return match err.raw_os_error() {
+					Some(libc::ENOSYS | libc::EXDEV | libc::EINVAL | libc::EOPNOTSUPP | libc::EPERM) => Ok(copied),
+					Some(libc::EINTR) => continue,
+					_ => Err(err),
+				};
+			},
+		}
+	}
+
+	Ok(copied)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn kernel_copy(_: &File, _: u64, _: &File, _: u64) -> io::Result<u64> {
+	Ok(0)
+}
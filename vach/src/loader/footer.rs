@@ -0,0 +1,121 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::archive::Archive;
+use crate::{
+	format::footer::RawFooter,
+	global::{
+		error::*,
+		header::{ArchiveConfig, Header},
+	},
+};
+
+/// A source holding an archive after other data, eg: a self-extracting executable with an archive appended to it.
+/// Positions are relative to the first byte of the archive, see [`Archive::from_trailing`]
+#[derive(Debug)]
+pub struct Embedded<T> {
+	inner: T,
+	base: u64,
+}
+
+impl<T> Embedded<T> {
+	/// Wraps `inner`, with the archive starting `base` bytes in
+	pub fn new(inner: T, base: u64) -> Embedded<T> {
+		Embedded { inner, base }
+	}
+
+	/// Where the archive starts within the wrapped source
+	pub fn base(&self) -> u64 {
+		self.base
+	}
+
+	/// Consume the [`Embedded`], returning the wrapped source
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+
+impl<T: Read> Read for Embedded<T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.inner.read(buf)
+	}
+}
+
+impl<T: Seek> Seek for Embedded<T> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let position = match pos {
+			SeekFrom::Start(offset) => self.inner.seek(SeekFrom::Start(self.base + offset))?,
+			pos => self.inner.seek(pos)?,
+		};
+
+		position.checked_sub(self.base).ok_or_else(|| {
+			io::Error::new(io::ErrorKind::InvalidInput, "Seeked before the start of the embedded archive")
+		})
+	}
+}
+
+impl<T: Read + Seek> Archive<Embedded<T>> {
+	/// Opens an archive appended to other data, eg: the payload of a self-extracting executable. The archive is located using it's footer,
+	/// so it must have been written with `BuilderConfig::footer` and end where `handle` ends
+	/// ```skip
+	/// let archive = Archive::from_trailing(File::open(std::env::current_exe()?)?, &ArchiveConfig::default())?;
+	/// ```
+	pub fn from_trailing(mut handle: T, config: &ArchiveConfig) -> InternalResult<Archive<Embedded<T>>> {
+		let footer = read_footer(&mut handle)?;
+		let end = handle.seek(SeekFrom::End(0))?;
+
+		let base = end.checked_sub(footer.archive_size).ok_or_else(|| {
+			InternalError::FooterMismatchError(format!(
+				"The footer records an archive of {} bytes, the source only holds {}",
+				footer.archive_size, end
+			))
+		})?;
+
+		Archive::with_config(Embedded::new(handle, base), config)
+	}
+}
+
+/// Checks the footer of an archive whose header marks one: it must be intact, mirror the header and record the archive's size
+pub(crate) fn check<R: Read + Seek>(handle: &mut R, header: &Header) -> InternalResult {
+	let footer = read_footer(handle)?;
+	let size = handle.seek(SeekFrom::End(0))?;
+
+	compare(&footer, size, header)
+}
+
+/// Checks a footer against the header and the size of the source it was read from
+pub(crate) fn compare(footer: &RawFooter, size: u64, header: &Header) -> InternalResult {
+	if footer.header[..] != header.to_bytes()[..Header::BASE_SIZE] {
+		return Err(InternalError::FooterMismatchError(
+			"The mirrored header differs from the header".to_string(),
+		));
+	}
+
+	if footer.archive_size != size {
+		return Err(InternalError::FooterMismatchError(format!(
+			"The archive takes {} bytes, the footer records {}",
+			size, footer.archive_size
+		)));
+	}
+
+	Ok(())
+}
+
+fn read_footer<R: Read + Seek>(handle: &mut R) -> InternalResult<RawFooter> {
+	let size = handle.seek(SeekFrom::End(0))?;
+	if size < RawFooter::SIZE as u64 {
+		return Err(InternalError::FooterMismatchError(format!(
+			"The source takes {} bytes, too few to hold a footer",
+			size
+		)));
+	}
+
+	let mut bytes = [0; RawFooter::SIZE];
+	handle.seek(SeekFrom::End(-(RawFooter::SIZE as i64)))?;
+	handle.read_exact(&mut bytes)?;
+
+	decode(&bytes)
+}
+
+pub(crate) fn decode(bytes: &[u8; RawFooter::SIZE]) -> InternalResult<RawFooter> {
+	RawFooter::decode(bytes).map_err(|_| InternalError::FooterMismatchError("The footer's CRC doesn't match".to_string()))
+}
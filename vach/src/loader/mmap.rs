@@ -0,0 +1,231 @@
+#![cfg(all(feature = "mmap", unix))]
+#![cfg_attr(docsrs, doc(cfg(all(feature = "mmap", unix))))]
+
+use std::{
+	borrow::Cow,
+	fmt,
+	fs::File,
+	io::{self, Read, Seek, SeekFrom},
+	os::fd::AsRawFd,
+	path::Path,
+	ptr::NonNull,
+};
+
+use super::archive::Archive;
+use crate::global::{error::*, flags::Flags, header::ArchiveConfig, sync};
+
+/// A read-only memory map of a whole file, read and seeked through like a [`Cursor`](std::io::Cursor). See `Archive::from_mmap`
+pub struct Mmap {
+	ptr: NonNull<u8>,
+	len: usize,
+	position: u64,
+}
+
+// SAFETY: The mapping is read-only and owned by `Mmap` alone, so it's as safe to share and send as a `Box<[u8]>`
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+	/// Maps the whole of `file` into memory, read-only. The mapping outlives `file`
+	///
+	/// # Safety
+	/// The file must not be truncated or written to while mapped, by this process or any other
+	pub unsafe fn map(file: &File) -> io::Result<Mmap> {
+		let len = usize::try_from(file.metadata()?.len()).map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+
+		// Empty mappings are refused by the kernel, and there's nothing to map anyway
+		if len == 0 {
+			return Ok(Mmap {
+				ptr: NonNull::dangling(),
+				len,
+				position: 0,
+			});
+		}
+
+		let ptr = libc::mmap(
+			std::ptr::null_mut(),
+			len,
+			libc::PROT_READ,
+			libc::MAP_PRIVATE,
+			file.as_raw_fd(),
+			0,
+		);
+
+		match NonNull::new(ptr as *mut u8) {
+			Some(ptr) if ptr.as_ptr() as *mut libc::c_void != libc::MAP_FAILED => Ok(Mmap { ptr, len, position: 0 }),
+			_ => Err(io::Error::last_os_error()),
+		}
+	}
+
+	/// The mapped contents of the file
+	pub fn as_slice(&self) -> &[u8] {
+		// SAFETY: `ptr` points to `len` readable bytes until `self` is dropped, or is dangling with a `len` of zero
+		unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+	}
+}
+
+impl Drop for Mmap {
+	fn drop(&mut self) {
+		if self.len > 0 {
+			// SAFETY: The mapping was created by `Mmap::map` with this exact address and length, and nothing borrows it past `self`
+			unsafe {
+				libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len);
+			}
+		}
+	}
+}
+
+impl Read for Mmap {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let start = self.position.min(self.len as u64) as usize;
+		let read = (&self.as_slice()[start..]).read(buf)?;
+
+		self.position += read as u64;
+		Ok(read)
+	}
+}
+
+impl Seek for Mmap {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let position = match pos {
+			SeekFrom::Start(offset) => Some(offset),
+			SeekFrom::End(offset) => (self.len as u64).checked_add_signed(offset),
+			SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+		};
+
+		match position {
+			Some(position) => {
+				self.position = position;
+				Ok(position)
+			},
+			None => Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"invalid seek to a negative or overflowing position",
+			)),
+		}
+	}
+}
+
+impl fmt::Debug for Mmap {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Mmap")
+			.field("len", &self.len)
+			.field("position", &self.position)
+			.finish()
+	}
+}
+
+/// A leaf's data, borrowed straight from the memory map when it's stored as is. Returned by `Archive::fetch_slice`
+#[non_exhaustive]
+#[derive(Debug)]
+pub struct MappedResource<'a> {
+	/// The leaf's data, borrowed for leaves that are neither compressed, encrypted nor run through a custom pipeline, and owned otherwise
+	pub data: Cow<'a, [u8]>,
+	/// The flags extracted from the archive's registry entry
+	pub flags: Flags,
+	/// The content version of the extracted archive entry
+	pub content_version: u8,
+	/// Whether the data checked out against it's signature, same as `Resource::authenticated`
+	pub authenticated: bool,
+}
+
+impl Archive<Mmap> {
+	/// Open the archive file at `path` through a memory map, with the default settings. See `Archive::from_mmap_with_config`
+	///
+	/// # Safety
+	/// The file must not be truncated or written to while the archive is open, by this process or any other
+	#[inline(always)]
+	pub unsafe fn from_mmap(path: impl AsRef<Path>) -> InternalResult<Archive<Mmap>> {
+		Archive::from_mmap_with_config(path, &ArchiveConfig::default())
+	}
+
+	/// Open the archive file at `path` through a memory map, so `Archive::fetch_slice` hands out leaves without copying them and
+	/// every other fetch reads from memory, without a syscall per seek and read. Nothing is buffered, `ArchiveConfig::read_buffer` is ignored
+	///
+	/// # Safety
+	/// The file must not be truncated or written to while the archive is open, by this process or any other.
+	/// Truncating it is undefined behaviour, and usually kills the process with a `SIGBUS`
+	/// ```
+	/// use vach::prelude::*;
+	///
+	/// let path = std::env::temp_dir().join("vach-mmap-doc.vach");
+	/// let mut builder = Builder::new();
+	/// builder.add(b"Hello, Cassandra!" as &[u8], "hello").unwrap();
+	/// builder.dump(std::fs::File::create(&path).unwrap(), &BuilderConfig::default()).unwrap();
+	///
+	/// // SAFETY: Nothing else writes to the file while it's open
+	/// let archive = unsafe { Archive::from_mmap(&path).unwrap() };
+	/// let resource = archive.fetch_slice("hello").unwrap();
+	/// assert_eq!(resource.data.as_ref(), b"Hello, Cassandra!");
+	/// ```
+	pub unsafe fn from_mmap_with_config(path: impl AsRef<Path>, config: &ArchiveConfig) -> InternalResult<Archive<Mmap>> {
+		let path = path.as_ref();
+		let with_path = |err: InternalError| InternalError::OpenError {
+			path: path.to_path_buf(),
+			source: Box::new(err),
+		};
+
+		let file = File::open(path).map_err(|err| with_path(err.into()))?;
+		let map = Mmap::map(&file).map_err(|err| with_path(err.into()))?;
+
+		// Every read copies out of memory already, a buffer in between would only copy twice
+		let mut archive = Archive::with_config(map, &config.clone().read_buffer(0)).map_err(with_path)?;
+		archive.path = Some(path.to_path_buf());
+
+		Ok(archive)
+	}
+
+	/// The whole mapped archive
+	fn mapped(&self) -> &[u8] {
+		let slice = sync::lock(&self.handle).get_ref().as_slice() as *const [u8];
+
+		// SAFETY: The mapping is owned by the handle and only unmapped once it's dropped, which takes the archive by value.
+		// It's never written to, so it's sound to borrow it for as long as `self` past the lock
+		unsafe { &*slice }
+	}
+
+	/// Fetch the leaf with the given `ID` without copying it's data out of the memory map. Leaves that are compressed, encrypted or run through
+	/// a custom pipeline are decoded into owned data like `Archive::fetch`. Signatures are checked as usual when a public key was provided.
+	///
+	/// Borrowed leaves bypass `ArchiveConfig::memory_gate` since they allocate nothing, and aren't recorded in fetch stats
+	pub fn fetch_slice(&self, id: impl AsRef<str>) -> InternalResult<MappedResource<'_>> {
+		let entry = self.live(id.as_ref())?;
+
+		if entry.flags.contains(Flags::COMPRESSED_FLAG | Flags::ENCRYPTED_FLAG | Flags::PIPELINE_FLAG) {
+			let resource = self.fetch(id)?;
+
+			return Ok(MappedResource {
+				data: Cow::Owned(resource.data.into_vec()),
+				flags: resource.flags,
+				content_version: resource.content_version,
+				authenticated: resource.authenticated,
+			});
+		}
+
+		self.admit(entry)?;
+
+		let data = usize::try_from(entry.location)
+			.ok()
+			.zip(usize::try_from(entry.offset).ok())
+			.and_then(|(start, len)| self.mapped().get(start..start.checked_add(len)?))
+			.ok_or_else(|| {
+				InternalError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).context(
+					&entry.id,
+					Some(entry.location),
+					Stage::Read,
+				)
+			})?;
+
+		#[cfg(feature = "crypto")]
+		let authenticated = self.authenticate(entry, data)?;
+		#[cfg(not(feature = "crypto"))]
+		let authenticated = false;
+
+		Ok(MappedResource {
+			data: Cow::Borrowed(data),
+			flags: entry.flags,
+			content_version: entry.content_version,
+			authenticated,
+		})
+	}
+}
@@ -1,2 +1,18 @@
 pub mod archive;
+pub mod asynchronous;
+pub mod diff;
+pub mod namespace;
+pub mod overlay;
+pub mod reader;
 pub mod resource;
+pub mod throttle;
+mod extract;
+pub mod footer;
+pub mod mmap;
+mod open;
+mod positional;
+mod uring;
+
+#[cfg(feature = "crypto")]
+pub mod verifier;
+mod vfs;
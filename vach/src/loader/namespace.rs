@@ -0,0 +1,79 @@
+use std::io::{Read, Seek};
+
+use super::{archive::Archive, resource::Resource};
+use crate::global::{
+	entry_id::{EntryId, NAMESPACE_SEPARATOR},
+	error::*,
+	reg_entry::RegistryEntry,
+};
+
+/// A view of the entries within one namespace of an [`Archive`], looked up by their `ID`s within the namespace. See `Archive::namespace`
+#[derive(Debug)]
+pub struct Namespace<'a, T> {
+	archive: &'a Archive<T>,
+	prefix: String,
+}
+
+impl<'a, T: Read + Seek> Namespace<'a, T> {
+	pub(crate) fn new(archive: &'a Archive<T>, name: &str) -> Namespace<'a, T> {
+		Namespace {
+			archive,
+			prefix: format!("{}{}", name, NAMESPACE_SEPARATOR),
+		}
+	}
+
+	/// The name of this namespace
+	pub fn name(&self) -> &str {
+		&self.prefix[..self.prefix.len() - NAMESPACE_SEPARATOR.len()]
+	}
+
+	/// The underlying [`Archive`]
+	pub fn archive(&self) -> &'a Archive<T> {
+		self.archive
+	}
+
+	fn qualify(&self, id: &str) -> String {
+		format!("{}{}", self.prefix, id)
+	}
+
+	/// Fetch the [`RegistryEntry`] with the given `ID` within this namespace
+	pub fn fetch_entry_ref(&self, id: impl AsRef<str>) -> Option<&'a RegistryEntry> {
+		self.archive.fetch_entry_ref(self.qualify(id.as_ref()))
+	}
+
+	/// Fetch the [`Resource`] with the given `ID` within this namespace
+	pub fn fetch(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
+		self.archive.fetch(self.qualify(id.as_ref()))
+	}
+
+	/// Fetch several [`Resource`]s from this namespace at once, see `Archive::fetch_batch`
+	pub fn fetch_batch<I, S>(&self, ids: I) -> Vec<InternalResult<Resource>>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		self.archive.fetch_batch(ids.into_iter().map(|id| self.qualify(id.as_ref())))
+	}
+
+	/// An iterator over the `ID`s within this namespace and their [`RegistryEntry`]s, in arbitrary order
+	pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a RegistryEntry)> + '_ {
+		self.archive
+			.iter()
+			.filter_map(|(id, entry)| id.strip_prefix(self.prefix.as_str()).map(|local| (local, entry)))
+	}
+
+	/// The `ID`s within this namespace, sorted
+	pub fn ids(&self) -> Vec<&'a str> {
+		let mut ids = self.iter().map(|(id, _)| id).collect::<Vec<_>>();
+		ids.sort_unstable();
+		ids
+	}
+}
+
+/// The distinct namespaces of the given `ID`s, sorted
+pub(crate) fn namespaces<'a>(ids: impl Iterator<Item = &'a EntryId>) -> Vec<&'a str> {
+	let mut namespaces = ids.filter_map(|id| id.namespace()).collect::<Vec<_>>();
+	namespaces.sort_unstable();
+	namespaces.dedup();
+	namespaces
+}
@@ -0,0 +1,61 @@
+use std::{fs::File, path::Path};
+
+use super::archive::Archive;
+use crate::global::{error::*, header::ArchiveConfig};
+
+impl Archive<File> {
+	/// Open the archive file at `path` with the default settings. The path is kept for error messages and reloading, see `Archive::path`
+	/// ```skip
+	/// let archive = Archive::open("assets.vach")?;
+	/// ```
+	#[inline(always)]
+	pub fn open(path: impl AsRef<Path>) -> InternalResult<Archive<File>> {
+		Archive::open_with_config(path, &ArchiveConfig::default())
+	}
+
+	/// Open the archive file at `path`, validating and configuring it with the given [`ArchiveConfig`].
+	/// On Linux the kernel is hinted to read ahead while the registry is parsed, then to expect random access for fetches
+	pub fn open_with_config(path: impl AsRef<Path>, config: &ArchiveConfig) -> InternalResult<Archive<File>> {
+		let path = path.as_ref();
+		let with_path = |err: InternalError| InternalError::OpenError {
+			path: path.to_path_buf(),
+			source: Box::new(err),
+		};
+
+		let file = File::open(path).map_err(|err| with_path(err.into()))?;
+		advise(&file, Advice::Sequential);
+
+		let mut archive = Archive::with_config(file, config).map_err(with_path)?;
+		archive.path = Some(path.to_path_buf());
+
+		if let Ok(handle) = archive.handle.get_mut() {
+			advise(handle.get_ref(), Advice::Random);
+		}
+
+		Ok(archive)
+	}
+}
+
+enum Advice {
+	Sequential,
+	Random,
+}
+
+/// Read-ahead hints are only an optimization, failures are ignored
+#[cfg(target_os = "linux")]
+fn advise(file: &File, advice: Advice) {
+	use std::os::fd::AsRawFd;
+
+	let advice = match advice {
+		Advice::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+		Advice::Random => libc::POSIX_FADV_RANDOM,
+	};
+
+	// SAFETY: The descriptor is valid for the lifetime of `file`, and a length of `0` covers the whole file
+	unsafe {
+		libc::posix_fadvise(file.as_raw_fd(), 0, 0, advice);
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+fn advise(_: &File, _: Advice) {}
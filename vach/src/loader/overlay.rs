@@ -0,0 +1,101 @@
+use std::io::{Read, Seek};
+
+use super::{archive::Archive, resource::Resource};
+use crate::global::{entry_id::EntryId, error::*, reg_entry::RegistryEntry};
+
+/// Archives layered over each other, eg: a base archive followed by incremental patch archives.
+/// Entries in later layers take precedence, and tombstones hide the entries with the same `ID` in earlier layers, see `Leaf::tombstone`
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// let mut base = Cursor::new(Vec::new());
+/// let mut builder = Builder::new();
+/// builder.add(b"Old intro" as &[u8], "intro").unwrap();
+/// builder.add(b"Cut level" as &[u8], "levels/cut").unwrap();
+/// builder.dump(&mut base, &BuilderConfig::default()).unwrap();
+///
+/// let mut patch = Cursor::new(Vec::new());
+/// let mut builder = Builder::new();
+/// builder.add(b"New intro" as &[u8], "intro").unwrap();
+/// builder.add_leaf(Leaf::tombstone("levels/cut")).unwrap();
+/// builder.dump(&mut patch, &BuilderConfig::default()).unwrap();
+///
+/// let overlay = Overlay::new(Archive::new(base).unwrap()).layer(Archive::new(patch).unwrap());
+/// assert_eq!(overlay.fetch("intro").unwrap().data.as_ref(), b"New intro");
+/// assert!(overlay.fetch("levels/cut").is_err());
+/// ```
+#[derive(Debug)]
+pub struct Overlay<T> {
+	layers: Vec<Archive<T>>,
+}
+
+impl<T> Overlay<T> {
+	/// Start an [`Overlay`] with a single base layer
+	pub fn new(base: Archive<T>) -> Overlay<T> {
+		Overlay { layers: vec![base] }
+	}
+
+	/// Adds a layer on top of the existing ones
+	pub fn layer(mut self, archive: Archive<T>) -> Overlay<T> {
+		self.layers.push(archive);
+		self
+	}
+
+	/// The layers, the base layer first
+	pub fn layers(&self) -> &[Archive<T>] {
+		&self.layers
+	}
+
+	/// Consume the [`Overlay`], returning it's layers
+	pub fn into_layers(self) -> Vec<Archive<T>> {
+		self.layers
+	}
+}
+
+impl<T: Read + Seek> Overlay<T> {
+	/// Layers `archive` over the shared archive it was written against, opened by name using `open`. Archives written without one
+	/// are returned as the only layer, see `PackSet::common`
+	/// ```skip
+	/// let archive = Archive::open("assets-windows.vach")?;
+	/// let overlay = Overlay::with_shared(archive, |name| Archive::open(name))?;
+	/// ```
+	pub fn with_shared(archive: Archive<T>, open: impl FnOnce(&str) -> InternalResult<Archive<T>>) -> InternalResult<Overlay<T>> {
+		match archive.shared_archive() {
+			Some(name) => Ok(Overlay::new(open(name)?).layer(archive)),
+			None => Ok(Overlay::new(archive)),
+		}
+	}
+
+	/// The visible entry with the given `ID` and the layer holding it, `None` if it is missing or hidden by a tombstone
+	pub fn fetch_entry_ref(&self, id: impl AsRef<str>) -> Option<(&Archive<T>, &RegistryEntry)> {
+		self.layers
+			.iter()
+			.rev()
+			.find_map(|layer| layer.fetch_entry_ref(id.as_ref()).map(|entry| (layer, entry)))
+			.filter(|(_, entry)| !entry.is_tombstone())
+	}
+
+	/// The `ID`s of all visible entries, sorted
+	pub fn ids(&self) -> Vec<&EntryId> {
+		let mut ids = self
+			.layers
+			.iter()
+			.flat_map(|layer| layer.iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+
+		ids.sort_unstable();
+		ids.dedup();
+		ids.retain(|id| self.fetch_entry_ref(id).is_some());
+
+		ids
+	}
+
+	/// Fetch a [`Resource`] from the topmost layer holding it, tombstones are reported as missing
+	pub fn fetch(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
+		match self.fetch_entry_ref(id.as_ref()) {
+			Some((layer, _)) => layer.fetch(id),
+			None => Err(InternalError::MissingResourceError(EntryId::from_raw(id))),
+		}
+	}
+}
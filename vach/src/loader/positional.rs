@@ -0,0 +1,109 @@
+#![cfg(any(unix, windows))]
+
+use std::{
+	fs::File,
+	io::{self, Read, Seek},
+	mem::ManuallyDrop,
+};
+
+#[cfg(unix)]
+use std::os::{
+	fd::{AsRawFd, FromRawFd},
+	unix::fs::FileExt,
+};
+
+#[cfg(windows)]
+use std::os::windows::{
+	fs::FileExt,
+	io::{AsRawHandle, FromRawHandle},
+};
+
+use super::{
+	archive::{within, Archive},
+	resource::Resource,
+};
+use crate::global::{entry_id::EntryId, error::*, reg_entry::RegistryEntry, sync};
+
+#[cfg(unix)]
+impl<T> Archive<T>
+where
+	T: Read + Seek + AsRawFd,
+{
+	/// Fetch a [`Resource`] using a positioned read (`pread`), without locking the underlying [`Mutex`](std::sync::Mutex) for the read.
+	/// This lets `fetch`es from many threads read from the source concurrently, instead of queueing on the handle lock.
+	pub fn fetch_positional(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
+		let entry = match self.fetch_entry_ref(&id) {
+			Some(entry) => entry,
+			None => return Err(InternalError::MissingResourceError(EntryId::from_raw(id))),
+		};
+
+		// SAFETY: The descriptor is owned by the handle, which outlives this borrow of `self`. `ManuallyDrop` ensures it's never closed here
+		let fd = sync::lock(&self.handle).get_ref().as_raw_fd();
+		let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+
+		let raw = read_leaf(&file, entry)
+			.map_err(|err| InternalError::from(err).context(&entry.id, Some(entry.location), Stage::Read))?;
+
+		self.decode(entry, raw)
+	}
+}
+
+#[cfg(windows)]
+impl<T> Archive<T>
+where
+	T: Read + Seek + AsRawHandle,
+{
+	/// Fetch a [`Resource`] using a positioned read (`seek_read`). Unlike `pread`, `seek_read` moves the file cursor that `Archive::fetch` seeks and reads through,
+	/// so the underlying [`Mutex`](std::sync::Mutex) is held for the read and reads don't overlap. Decoding still runs outside the lock
+	pub fn fetch_positional(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
+		let entry = match self.fetch_entry_ref(&id) {
+			Some(entry) => entry,
+			None => return Err(InternalError::MissingResourceError(EntryId::from_raw(id))),
+		};
+
+		// The cursor is only moved while the lock is held, every other read through the handle seeks first
+		let raw = {
+			let guard = sync::lock(&self.handle);
+
+			// SAFETY: The handle is owned by the source, which outlives this borrow of `self`. `ManuallyDrop` ensures it's never closed here
+			let file = ManuallyDrop::new(unsafe { File::from_raw_handle(guard.get_ref().as_raw_handle()) });
+			read_leaf(&file, entry)
+		}
+		.map_err(|err| InternalError::from(err).context(&entry.id, Some(entry.location), Stage::Read))?;
+
+		self.decode(entry, raw)
+	}
+}
+
+/// Reads the data of a leaf, refusing leaves running past the end of the file before allocating their buffer
+fn read_leaf(file: &File, entry: &RegistryEntry) -> io::Result<Vec<u8>> {
+	within(entry, file.metadata()?.len())?;
+
+	let mut raw = vec![0; entry.offset as usize];
+	read_exact_at(file, &mut raw, entry.location)?;
+
+	Ok(raw)
+}
+
+#[cfg(unix)]
+#[inline(always)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+	file.read_exact_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+	while !buf.is_empty() {
+		match file.seek_read(buf, offset) {
+			Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+			Ok(read) => {
+				buf = &mut buf[read..];
+				offset += read as u64;
+			},
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+			Err(err) => return Err(err),
+		}
+	}
+
+	Ok(())
+}
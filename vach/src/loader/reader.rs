@@ -0,0 +1,171 @@
+use std::{
+	fmt,
+	io::{self, Cursor, Read, Seek, SeekFrom},
+};
+
+use super::archive::Archive;
+use crate::global::{error::*, flags::Flags, sync};
+
+#[cfg(feature = "compression")]
+use crate::global::compressor::{CompressionAlgorithm, Compressor};
+
+/// A leaf's data, decoded as it's read, see `Archive::fetch_reader`
+pub struct LeafReader<'a> {
+	inner: Box<dyn Read + 'a>,
+	flags: Flags,
+	content_version: u8,
+	authenticated: bool,
+	decoded_size: u64,
+}
+
+impl LeafReader<'_> {
+	/// The flags of the leaf's registry entry
+	pub fn flags(&self) -> Flags {
+		self.flags
+	}
+
+	/// The content version of the leaf
+	pub fn content_version(&self) -> u8 {
+		self.content_version
+	}
+
+	/// Whether the leaf's signature was checked and holds, only ever `true` for leaves decoded in memory
+	pub fn authenticated(&self) -> bool {
+		self.authenticated
+	}
+
+	/// The size of the leaf's data once decoded, as recorded in the registry. `0` if unknown, for any non-empty leaf
+	pub fn decoded_size(&self) -> u64 {
+		self.decoded_size
+	}
+}
+
+impl Read for LeafReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.inner.read(buf)
+	}
+}
+
+impl fmt::Debug for LeafReader<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("LeafReader")
+			.field("flags", &self.flags)
+			.field("content_version", &self.content_version)
+			.field("authenticated", &self.authenticated)
+			.field("decoded_size", &self.decoded_size)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Reads a range of the archive's source, locking the handle only for the duration of each read, so other fetches go on in between
+struct RangeReader<'a, T> {
+	archive: &'a Archive<T>,
+	position: u64,
+	end: u64,
+}
+
+impl<T: Read + Seek> Read for RangeReader<'_, T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let length = buf.len().min((self.end - self.position) as usize);
+		if length == 0 {
+			return Ok(0);
+		}
+
+		let read = {
+			let mut handle = sync::lock(&self.archive.handle);
+			handle.seek(SeekFrom::Start(self.position))?;
+			handle.read(&mut buf[..length])?
+		};
+
+		// Sources ending within a leaf are truncated, don't let it pass for the end of the leaf
+		if read == 0 {
+			return Err(io::ErrorKind::UnexpectedEof.into());
+		}
+
+		self.position += read as u64;
+		Ok(read)
+	}
+}
+
+impl<T: Read + Seek> Archive<T> {
+	/// Fetch the entry with the given `ID` as a reader, decompressing it's data as it's read, so large leaves, eg: video or audio banks,
+	/// can be streamed into a decoder without holding them in memory whole. The source is only locked during each read, so other fetches carry on in between.
+	///
+	/// Leaves that are encrypted, signed while a public key is set, or run through a custom pipeline need all of their data at once,
+	/// so they are decoded in memory first, like `Archive::fetch`. Streamed leaves bypass `ArchiveConfig::memory_gate` and aren't recorded in fetch stats.
+	/// Errors past this call, including truncated sources, surface as [`io::Error`]s from reads
+	/// ```
+	/// use std::io::{Cursor, Read};
+	/// use vach::prelude::*;
+	///
+	/// let mut target = Cursor::new(Vec::new());
+	/// let mut builder = Builder::new();
+	/// builder.add(b"Hello, Cassandra!" as &[u8], "hello").unwrap();
+	/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+	///
+	/// let archive = Archive::new(target).unwrap();
+	/// let mut reader = archive.fetch_reader("hello").unwrap();
+	///
+	/// let mut greeting = [0; 5];
+	/// reader.read_exact(&mut greeting).unwrap();
+	/// assert_eq!(&greeting, b"Hello");
+	/// ```
+	pub fn fetch_reader(&self, id: impl AsRef<str>) -> InternalResult<LeafReader<'_>> {
+		let entry = self.live(id.as_ref())?;
+
+		if self.buffered(entry) {
+			let resource = self.fetch(id)?;
+
+			return Ok(LeafReader {
+				decoded_size: resource.data.len() as u64,
+				inner: Box::new(Cursor::new(resource.data)),
+				flags: resource.flags,
+				content_version: resource.content_version,
+				authenticated: resource.authenticated,
+			});
+		}
+
+		self.admit(entry)?;
+
+		let range = RangeReader {
+			archive: self,
+			position: entry.location,
+			end: entry.location + entry.offset,
+		};
+
+		let inner: Box<dyn Read + '_> = if entry.flags.contains(Flags::COMPRESSED_FLAG) {
+			#[cfg(feature = "compression")]
+			{
+				let algo = CompressionAlgorithm::from_flags(&entry.flags).ok_or_else(|| {
+					InternalError::OtherError(
+						format!(
+							"Unable to determine the compression algorithm used for entry: {}",
+							entry
+						)
+						.into(),
+					)
+					.context(&entry.id, Some(entry.location), Stage::Decompress)
+				})?;
+
+				Compressor::new(range).into_decoder(algo)
+			}
+
+			#[cfg(not(feature = "compression"))]
+			return Err(InternalError::MissingFeatureError("compression").context(
+				&entry.id,
+				Some(entry.location),
+				Stage::Decompress,
+			));
+		} else {
+			Box::new(range)
+		};
+
+		Ok(LeafReader {
+			inner,
+			flags: entry.flags,
+			content_version: entry.content_version,
+			authenticated: false,
+			decoded_size: entry.decoded_size,
+		})
+	}
+}
@@ -16,6 +16,43 @@ pub struct Resource {
 	/// A [`Resource`] is checked for authenticity, corruption or obsolescence against it's signature.
 	/// If the checks pass, then this becomes true, this is always false if the `crypto` feature is off or if the data had no signature
 	pub authenticated: bool,
+	/// The key whose signature over the data checked out, `Some` exactly when `authenticated` is true
+	pub verified_with: Option<KeyId>,
+	/// The key the data was decrypted with, `None` if it wasn't encrypted. Decrypting says nothing about authenticity, see `verified_with`
+	pub decrypted_with: Option<KeyId>,
+}
+
+/// A short fingerprint identifying a key without revealing it, see `Resource::verified_with` and `Resource::decrypted_with`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyId(pub [u8; 8]);
+
+#[cfg(feature = "crypto")]
+impl KeyId {
+	/// The fingerprint of a public key, used to verify signatures or, without a decryption key, to decrypt leaves
+	pub fn of_public_key(key: &crate::crypto::VerifyingKey) -> KeyId {
+		KeyId::fingerprint(b"public", key.as_bytes())
+	}
+
+	/// The fingerprint of a dedicated encryption key, see `ArchiveConfig::decryption_key`
+	pub fn of_encryption_key(key: &crate::crypto::EncryptionKey) -> KeyId {
+		KeyId::fingerprint(b"encryption", &key.to_bytes())
+	}
+
+	fn fingerprint(role: &[u8], key: &[u8]) -> KeyId {
+		use ed25519_dalek::{Digest, Sha512};
+
+		let hash = Sha512::new().chain_update(b"vach::key-id:").chain_update(role).chain_update(key).finalize();
+
+		let mut id = [0; 8];
+		id.copy_from_slice(&hash[..8]);
+		KeyId(id)
+	}
+}
+
+impl fmt::Display for KeyId {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+	}
 }
 
 impl fmt::Display for Resource {
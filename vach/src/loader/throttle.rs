@@ -0,0 +1,133 @@
+use std::{
+	io::{Read, Seek},
+	sync::Mutex,
+	thread,
+	time::{Duration, Instant},
+};
+
+use super::{archive::Archive, resource::Resource};
+use crate::global::{entry_id::EntryId, error::*, sync};
+
+/// Who a fetch through a [`ThrottledArchive`] is for, each priority draws from it's own bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+	/// Reads the player is waiting on, usually left unlimited
+	Foreground,
+	/// Streaming and prefetching that may wait
+	Background,
+}
+
+/// A token bucket limit: reads of up to `burst` bytes go through at once, then the bucket refills at `bytes_per_second`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+	/// How fast the bucket refills
+	pub bytes_per_second: u64,
+	/// How many bytes the bucket holds when full
+	pub burst: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+	limit: RateLimit,
+	// Negative while reads larger than what was available are being paid off
+	tokens: f64,
+	refilled: Instant,
+}
+
+impl Bucket {
+	fn new(limit: RateLimit) -> Bucket {
+		Bucket {
+			limit,
+			tokens: limit.burst as f64,
+			refilled: Instant::now(),
+		}
+	}
+
+	/// Takes `bytes` from the bucket, returning how long the caller must wait for them
+	fn take(&mut self, bytes: u64) -> Duration {
+		let now = Instant::now();
+		let refill = now.duration_since(self.refilled).as_secs_f64() * self.limit.bytes_per_second as f64;
+
+		self.tokens = (self.tokens + refill).min(self.limit.burst as f64) - bytes as f64;
+		self.refilled = now;
+
+		match self.tokens < 0.0 {
+			true => Duration::from_secs_f64(-self.tokens / self.limit.bytes_per_second.max(1) as f64),
+			false => Duration::ZERO,
+		}
+	}
+}
+
+/// Applies per [`Priority`] bandwidth limits to the reads of an [`Archive`], so background streaming can't starve foreground IO.
+/// Fetches wait until their bucket holds enough tokens for the leaf's stored size, then read as usual. Limits can be changed at any time
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// let mut target = Cursor::new(Vec::new());
+/// let mut builder = Builder::new();
+/// builder.add(Cursor::new(b"Hello"), "hello").unwrap();
+/// builder.dump(&mut target, &BuilderConfig::default()).unwrap();
+///
+/// let archive = ThrottledArchive::new(Archive::new(target).unwrap());
+/// archive.set_limit(Priority::Background, Some(RateLimit { bytes_per_second: 4 * 1024 * 1024, burst: 256 * 1024 }));
+///
+/// assert_eq!(archive.fetch("hello", Priority::Background).unwrap().data.as_ref(), b"Hello");
+/// ```
+#[derive(Debug)]
+pub struct ThrottledArchive<T> {
+	archive: Archive<T>,
+	buckets: [Mutex<Option<Bucket>>; 2],
+}
+
+impl<T> ThrottledArchive<T> {
+	/// Wraps an [`Archive`], without any limits
+	pub fn new(archive: Archive<T>) -> ThrottledArchive<T> {
+		ThrottledArchive {
+			archive,
+			buckets: [Mutex::new(None), Mutex::new(None)],
+		}
+	}
+
+	/// Sets or lifts the limit of a priority, the bucket starts out full
+	pub fn set_limit(&self, priority: Priority, limit: Option<RateLimit>) {
+		*sync::lock(&self.buckets[priority as usize]) = limit.map(Bucket::new);
+	}
+
+	/// The current limit of a priority, `None` if it's unlimited
+	pub fn limit(&self, priority: Priority) -> Option<RateLimit> {
+		sync::lock(&self.buckets[priority as usize]).as_ref().map(|b| b.limit)
+	}
+
+	/// The underlying [`Archive`], reads through it directly aren't limited
+	pub fn archive(&self) -> &Archive<T> {
+		&self.archive
+	}
+
+	/// Consume the [`ThrottledArchive`], returning the underlying [`Archive`]
+	pub fn into_inner(self) -> Archive<T> {
+		self.archive
+	}
+}
+
+impl<T: Read + Seek> ThrottledArchive<T> {
+	/// Fetch a [`Resource`], first waiting for the priority's bucket to allow reading the leaf
+	pub fn fetch(&self, id: impl AsRef<str>, priority: Priority) -> InternalResult<Resource> {
+		let entry = match self.archive.fetch_entry_ref(id.as_ref()) {
+			Some(entry) => entry,
+			None => return Err(InternalError::MissingResourceError(EntryId::from_raw(id))),
+		};
+
+		// The bucket is only locked to take tokens, waiting happens outside of it
+		let wait = match sync::lock(&self.buckets[priority as usize]).as_mut() {
+			Some(bucket) => bucket.take(entry.offset),
+			None => Duration::ZERO,
+		};
+
+		if !wait.is_zero() {
+			thread::sleep(wait);
+		}
+
+		self.archive.fetch(id)
+	}
+}
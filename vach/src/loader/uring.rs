@@ -0,0 +1,257 @@
+#![cfg(all(feature = "uring", target_os = "linux"))]
+#![cfg_attr(docsrs, doc(cfg(all(feature = "uring", target_os = "linux"))))]
+
+use std::{
+	collections::VecDeque,
+	fs::File,
+	io::{self, Read, Seek},
+	mem::ManuallyDrop,
+	os::fd::{AsRawFd, FromRawFd, RawFd},
+};
+
+use io_uring::{opcode, types, IoUring};
+
+use super::{
+	archive::{within, Archive},
+	resource::Resource,
+};
+use crate::global::{entry_id::EntryId, error::*, reg_entry::RegistryEntry, sync};
+
+/// Maximum number of reads in flight at any one time
+const QUEUE_DEPTH: u32 = 64;
+
+impl<T> Archive<T>
+where
+	T: Read + Seek + AsRawFd,
+{
+	/// Same as `Archive::fetch_batch`, but all reads are submitted in bulk through [io_uring](https://man7.org/linux/man-pages/man7/io_uring.7.html).
+	/// Reads are positional, so the underlying [`Mutex`](std::sync::Mutex) is only locked to get the file descriptor.
+	/// Falls back to `Archive::fetch_batch` if an io_uring instance can't be created, eg on older kernels.
+	pub fn fetch_batch_uring<I, S>(&self, ids: I) -> Vec<InternalResult<Resource>>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		let ids = ids.into_iter().collect::<Vec<S>>();
+
+		let mut ring = match IoUring::new(QUEUE_DEPTH) {
+			Ok(ring) => ring,
+			Err(_) => return self.fetch_batch(ids),
+		};
+
+		let entries = ids
+			.iter()
+			.map(|id| {
+				self.fetch_entry_ref(id)
+					.ok_or_else(|| InternalError::MissingResourceError(EntryId::from_raw(id)))
+			})
+			.collect::<Vec<_>>();
+
+		let fd = sync::lock(&self.handle).get_ref().as_raw_fd();
+
+		// SAFETY: The descriptor stays owned by `handle`, `ManuallyDrop` keeps it from being closed here
+		let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+		let size = match file.metadata() {
+			Ok(metadata) => metadata.len(),
+			Err(_) => return self.fetch_batch(ids),
+		};
+
+		let found = entries.iter().filter_map(|e| e.as_ref().ok().copied()).collect::<Vec<_>>();
+		let mut raw = read_batch(&mut ring, fd, size, &found).into_iter();
+
+		entries
+			.into_iter()
+			.map(|entry| {
+				let entry = entry?;
+				match raw.next() {
+					Some(Ok(data)) => self.decode(entry, data),
+					Some(Err(err)) => Err(InternalError::from(err).context(&entry.id, Some(entry.location), Stage::Read)),
+					None => Err(InternalError::MissingResourceError(entry.id.clone())),
+				}
+			})
+			.collect()
+	}
+
+	/// Hints the kernel to start reading the leaves of the given entries into the page cache, without waiting for the data.
+	/// The advice is submitted in bulk through io_uring, falling back to plain `posix_fadvise` calls if no ring can be created.
+	/// Useful ahead of a later `fetch_batch_uring` or `fetch_batch`, eg while a loading screen is being drawn
+	pub fn prefetch_uring<I, S>(&self, ids: I) -> InternalResult
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		let entries = ids
+			.into_iter()
+			.map(|id| {
+				self.fetch_entry_ref(id.as_ref())
+					.ok_or_else(|| InternalError::MissingResourceError(EntryId::from_raw(id.as_ref())))
+			})
+			.collect::<InternalResult<Vec<_>>>()?;
+
+		let fd = sync::lock(&self.handle).get_ref().as_raw_fd();
+		let ranges = entries
+			.iter()
+			.map(|entry| {
+				let location = entry.location.min(i64::MAX as u64) as libc::off_t;
+				let offset = entry.offset.min(i64::MAX as u64) as libc::off_t;
+				(location, offset)
+			})
+			.collect::<Vec<_>>();
+
+		match IoUring::new(QUEUE_DEPTH) {
+			Ok(mut ring) => advise_batch(&mut ring, fd, &ranges)?,
+			Err(_) => {
+				for &(location, offset) in &ranges {
+					// SAFETY: The descriptor stays valid for as long as `handle` lives
+					let res = unsafe { libc::posix_fadvise(fd, location, offset, libc::POSIX_FADV_WILLNEED) };
+					if res != 0 {
+						return Err(io::Error::from_raw_os_error(res).into());
+					}
+				}
+			},
+		};
+
+		Ok(())
+	}
+}
+
+/// Submits `POSIX_FADV_WILLNEED` for every `(location, offset)` range, returning the first error reported
+fn advise_batch(ring: &mut IoUring, fd: RawFd, ranges: &[(libc::off_t, libc::off_t)]) -> io::Result<()> {
+	let mut pending = ranges.iter().copied().collect::<VecDeque<_>>();
+	let mut in_flight = 0usize;
+	let mut error = None;
+
+	while !pending.is_empty() || in_flight > 0 {
+		{
+			let mut queue = ring.submission();
+			while !queue.is_full() {
+				let Some((location, offset)) = pending.pop_front() else { break };
+
+				let advise = opcode::Fadvise::new(types::Fd(fd), offset, libc::POSIX_FADV_WILLNEED)
+					.offset(location as u64)
+					.build();
+
+				// SAFETY: The advice references no user memory
+				unsafe {
+					if queue.push(&advise).is_err() {
+						pending.push_front((location, offset));
+						break;
+					}
+				};
+
+				in_flight += 1;
+			}
+		}
+
+		match ring.submit_and_wait(1) {
+			Ok(_) => (),
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err),
+		}
+
+		for completion in ring.completion() {
+			in_flight -= 1;
+			if completion.result() < 0 && error.is_none() {
+				error = Some(io::Error::from_raw_os_error(-completion.result()));
+			}
+		}
+	}
+
+	match error {
+		Some(err) => Err(err),
+		None => Ok(()),
+	}
+}
+
+/// Reads the leaves of the given entries, resubmitting short reads until each buffer is full.
+/// Leaves reaching past `size` fail without a buffer being allocated for them
+fn read_batch(ring: &mut IoUring, fd: RawFd, size: u64, entries: &[&RegistryEntry]) -> Vec<io::Result<Vec<u8>>> {
+	let mut errors = entries
+		.iter()
+		.map(|entry| within(entry, size).err())
+		.collect::<Vec<Option<io::Error>>>();
+
+	let mut buffers = entries
+		.iter()
+		.zip(&errors)
+		.map(|(entry, error)| match error {
+			None => vec![0u8; entry.offset as usize],
+			Some(_) => Vec::new(),
+		})
+		.collect::<Vec<_>>();
+
+	let mut filled = vec![0usize; entries.len()];
+
+	let mut pending = (0..entries.len())
+		.filter(|&idx| !buffers[idx].is_empty())
+		.collect::<VecDeque<_>>();
+	let mut in_flight = 0usize;
+
+	while !pending.is_empty() || in_flight > 0 {
+		{
+			let mut queue = ring.submission();
+			while !queue.is_full() {
+				let Some(idx) = pending.pop_front() else { break };
+
+				let remaining = &mut buffers[idx][filled[idx]..];
+				let len = remaining.len().min(u32::MAX as usize) as u32;
+
+				let read = opcode::Read::new(types::Fd(fd), remaining.as_mut_ptr(), len)
+					.offset(entries[idx].location + filled[idx] as u64)
+					.build()
+					.user_data(idx as u64);
+
+				// SAFETY: `buffers` is neither dropped nor resized until every in-flight read has completed
+				unsafe {
+					if queue.push(&read).is_err() {
+						pending.push_front(idx);
+						break;
+					}
+				};
+
+				in_flight += 1;
+			}
+		}
+
+		match ring.submit_and_wait(1) {
+			Ok(_) => (),
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(err) => {
+				// The ring is unusable, so in-flight reads may still land in the buffers. Leak them instead of freeing them
+				if in_flight > 0 {
+					std::mem::forget(buffers);
+				}
+
+				return entries
+					.iter()
+					.map(|_| Err(io::Error::new(err.kind(), err.to_string())))
+					.collect();
+			},
+		}
+
+		for completion in ring.completion() {
+			in_flight -= 1;
+
+			let idx = completion.user_data() as usize;
+			match completion.result() {
+				res if res < 0 => errors[idx] = Some(io::Error::from_raw_os_error(-res)),
+				0 => errors[idx] = Some(io::ErrorKind::UnexpectedEof.into()),
+				res => {
+					filled[idx] += res as usize;
+					if filled[idx] < buffers[idx].len() {
+						pending.push_back(idx);
+					}
+				},
+			}
+		}
+	}
+
+	buffers
+		.into_iter()
+		.zip(errors)
+		.map(|(buffer, error)| match error {
+			Some(err) => Err(err),
+			None => Ok(buffer),
+		})
+		.collect()
+}
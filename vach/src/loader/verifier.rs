@@ -0,0 +1,183 @@
+use std::{
+	collections::HashMap,
+	io::{Read, Seek},
+	ops::DerefMut,
+	sync::{
+		atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+		Arc,
+	},
+	thread::{self, JoinHandle},
+	time::{Duration, Instant},
+};
+
+use super::archive::Archive;
+use crate::global::{entry_id::EntryId, reg_entry::RegistryEntry, sync};
+
+const PENDING: u8 = 0;
+const VERIFIED: u8 = 1;
+const FAILED: u8 = 2;
+
+/// Whether each signed entry of an archive was found authentic, shared between a [`BackgroundVerifier`] and fetches.
+/// Results are recorded against the entry's location, offset and signature, so an entry that differs from the one checked is checked again
+#[derive(Debug)]
+pub(crate) struct Verified {
+	states: HashMap<EntryId, (Stamp, AtomicU8)>,
+}
+
+/// What a result was recorded against: (location, offset, signature)
+type Stamp = (u64, u64, [u8; 64]);
+
+fn stamp(entry: &RegistryEntry) -> Option<Stamp> {
+	let signature = entry.signature?;
+	Some((entry.location, entry.offset, signature.to_bytes()))
+}
+
+impl Verified {
+	pub(crate) fn new<'a>(entries: impl Iterator<Item = &'a RegistryEntry>) -> Verified {
+		let states = entries
+			.filter_map(|entry| Some((entry.id.clone(), (stamp(entry)?, AtomicU8::new(PENDING)))))
+			.collect();
+
+		Verified { states }
+	}
+
+	/// `Some(true)` if the entry's signature was checked and holds, `Some(false)` if it doesn't, `None` if it wasn't checked yet or the entry changed since
+	pub(crate) fn get(&self, entry: &RegistryEntry) -> Option<bool> {
+		let (recorded, state) = self.states.get(&entry.id)?;
+		if stamp(entry)? != *recorded {
+			return None;
+		}
+
+		match state.load(Ordering::Acquire) {
+			VERIFIED => Some(true),
+			FAILED => Some(false),
+			_ => None,
+		}
+	}
+
+	pub(crate) fn set(&self, entry: &RegistryEntry, authentic: bool) {
+		if let Some((recorded, state)) = self.states.get(&entry.id) {
+			if stamp(entry).as_ref() == Some(recorded) {
+				state.store(if authentic { VERIFIED } else { FAILED }, Ordering::Release);
+			}
+		}
+	}
+}
+
+/// Checks the signatures of an archive's entries on a separate thread, see `Archive::verify_in_background`.
+/// Dropping the handle stops the thread after the entry it's checking, without waiting for it
+#[derive(Debug)]
+pub struct BackgroundVerifier {
+	stop: Arc<AtomicBool>,
+	checked: Arc<AtomicUsize>,
+	total: usize,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl BackgroundVerifier {
+	/// The number of signed entries checked so far, by this thread or by fetches
+	pub fn checked(&self) -> usize {
+		self.checked.load(Ordering::Relaxed)
+	}
+
+	/// The number of signed entries in the archive
+	pub fn total(&self) -> usize {
+		self.total
+	}
+
+	/// Whether the thread is done, having checked every signed entry or been stopped
+	pub fn is_finished(&self) -> bool {
+		self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+	}
+
+	/// Blocks until every signed entry has been checked
+	pub fn wait(mut self) {
+		self.join();
+	}
+
+	/// Stops the thread after the entry it's checking and waits for it, entries left unchecked are verified when fetched
+	pub fn stop(mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		self.join();
+	}
+
+	fn join(&mut self) {
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for BackgroundVerifier {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+	}
+}
+
+impl<T: Read + Seek + Send + 'static> Archive<T> {
+	/// Checks the signatures of all signed entries on a separate thread, in the order they are stored, recording which are authentic.
+	/// Fetches of checked entries then reuse the result instead of hashing the leaf again, so the first fetch of a huge leaf doesn't pay for it's signature.
+	/// The thread works in slices of `slice`, then sleeps for `pause`, taking turns on the source with fetches in between entries.
+	///
+	/// Does nothing without a public key, see `ArchiveConfig::key`. Results are only sound while the source is unchanged, so use it on read-only sources
+	/// ```skip
+	/// let archive = Arc::new(Archive::with_config(File::open("assets.vach")?, &ArchiveConfig::default().key(key))?);
+	/// let verifier = archive.clone().verify_in_background(Duration::from_millis(4), Duration::from_millis(12));
+	/// ```
+	pub fn verify_in_background(self: Arc<Self>, slice: Duration, pause: Duration) -> BackgroundVerifier {
+		let verified = self.verified();
+
+		let mut entries = self
+			.registry()
+			.map(|(_, entry)| entry)
+			.filter(|entry| entry.signature.is_some())
+			.cloned()
+			.collect::<Vec<_>>();
+		entries.sort_by_key(|entry| entry.location);
+
+		let stop = Arc::new(AtomicBool::new(false));
+		let checked = Arc::new(AtomicUsize::new(0));
+		let total = entries.len();
+
+		let (flag, counter) = (stop.clone(), checked.clone());
+		let thread = verified.map(|verified| {
+			thread::spawn(move || {
+				let mut started = Instant::now();
+
+				for entry in entries {
+					if flag.load(Ordering::Relaxed) {
+						break;
+					}
+
+					// Fetches may have checked it already
+					if verified.get(&entry).is_none() {
+						let raw = {
+							let mut guard = sync::lock(&self.handle);
+							Archive::read_raw(guard.deref_mut(), &entry)
+						};
+
+						// Unreadable entries are left to fetches, which report the error
+						let Ok(raw) = raw else { continue };
+						if let Ok(authentic) = self.check_signature(&entry, &raw) {
+							verified.set(&entry, authentic);
+						}
+					}
+
+					counter.fetch_add(1, Ordering::Relaxed);
+
+					if started.elapsed() >= slice {
+						thread::sleep(pause);
+						started = Instant::now();
+					}
+				}
+			})
+		});
+
+		BackgroundVerifier {
+			stop,
+			checked,
+			total,
+			thread,
+		}
+	}
+}
@@ -0,0 +1,102 @@
+use std::io::{Cursor, Read, Seek};
+
+use super::{archive::Archive, overlay::Overlay};
+use crate::global::{
+	directory::dir_prefix,
+	entry_id::EntryId,
+	error::*,
+	reg_entry::RegistryEntry,
+	vfs::{Vfs, VfsMetadata},
+};
+
+impl<T: Read + Seek> Vfs for Archive<T> {
+	fn open(&self, path: &str) -> InternalResult<Box<dyn Read + '_>> {
+		let resource = self.fetch(path)?;
+		Ok(Box::new(Cursor::new(resource.data)))
+	}
+
+	fn read(&self, path: &str) -> InternalResult<Vec<u8>> {
+		self.fetch(path).map(|resource| resource.data.into_vec())
+	}
+
+	fn stat(&self, path: &str) -> InternalResult<VfsMetadata> {
+		let entry = self.fetch_entry_ref(path).filter(|entry| !entry.is_tombstone());
+		stat(entry, self.directory.prefixed(&dir_prefix(path)).iter(), path)
+	}
+
+	fn list(&self, path: &str) -> InternalResult<Vec<String>> {
+		let paths = self.list_dir(path).into_iter().map(|child| child.path().to_string()).collect::<Vec<_>>();
+
+		if paths.is_empty() && !dir_prefix(path).is_empty() {
+			return Err(InternalError::MissingResourceError(EntryId::from_raw(path)));
+		}
+
+		Ok(paths)
+	}
+}
+
+impl<T: Read + Seek> Vfs for Overlay<T> {
+	fn open(&self, path: &str) -> InternalResult<Box<dyn Read + '_>> {
+		let resource = self.fetch(path)?;
+		Ok(Box::new(Cursor::new(resource.data)))
+	}
+
+	fn read(&self, path: &str) -> InternalResult<Vec<u8>> {
+		self.fetch(path).map(|resource| resource.data.into_vec())
+	}
+
+	fn stat(&self, path: &str) -> InternalResult<VfsMetadata> {
+		stat(self.fetch_entry_ref(path).map(|(_, entry)| entry), self.ids().into_iter(), path)
+	}
+
+	fn list(&self, path: &str) -> InternalResult<Vec<String>> {
+		list(self.ids(), path)
+	}
+}
+
+/// Stats `path`, given the entry stored at it and the `ID`s of all visible entries
+fn stat<'a>(
+	entry: Option<&RegistryEntry>, mut ids: impl Iterator<Item = &'a EntryId>, path: &str,
+) -> InternalResult<VfsMetadata> {
+	if let Some(entry) = entry {
+		return Ok(VfsMetadata {
+			is_dir: false,
+			len: entry.offset,
+		});
+	};
+
+	// Any prefix of an `ID`, up to a separator, is a directory
+	let prefix = dir_prefix(path);
+	if ids.any(|id| id.starts_with(&prefix)) {
+		Ok(VfsMetadata { is_dir: true, len: 0 })
+	} else {
+		Err(InternalError::MissingResourceError(EntryId::from_raw(path)))
+	}
+}
+
+/// Lists the directory `path`, given the `ID`s of all visible entries
+fn list<'a>(ids: impl IntoIterator<Item = &'a EntryId>, path: &str) -> InternalResult<Vec<String>> {
+	let prefix = dir_prefix(path);
+
+	let mut paths = ids
+		.into_iter()
+		.filter_map(|id| {
+			let rest = id.strip_prefix(&prefix)?;
+
+			// Nested entries show up as their directory
+			Some(match rest.find('/') {
+				Some(idx) => &id[..prefix.len() + idx],
+				None => id.as_ref(),
+			})
+		})
+		.collect::<Vec<_>>();
+
+	if paths.is_empty() && !prefix.is_empty() {
+		return Err(InternalError::MissingResourceError(EntryId::from_raw(path)));
+	}
+
+	paths.sort_unstable();
+	paths.dedup();
+
+	Ok(paths.into_iter().map(String::from).collect())
+}
@@ -0,0 +1,256 @@
+#![cfg(feature = "crypto")]
+#![cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+
+use std::{
+	fs::File,
+	io::Read,
+	path::{Component, Path},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ed25519_dalek::{Digest, Sha512, Signer, Verifier};
+
+use crate::{
+	crypto::{Signature, SigningKey, VerifyingKey},
+	global::error::*,
+};
+
+/// The size of the hash recorded for each archive, `SHA-512`
+pub const RELEASE_HASH_LENGTH: usize = 64;
+
+/// A single archive of a [`ReleaseManifest`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseArchive {
+	/// Where the archive lies relative to the manifest, `/` separated
+	pub path: String,
+	/// The size of the archive in bytes
+	pub size: u64,
+	/// The `SHA-512` hash of the whole archive file
+	pub hash: [u8; RELEASE_HASH_LENGTH],
+}
+
+/// Describes a whole content release: every archive file, it's size and hash, when the release was made, and a signature over all of it.
+/// Launchers verify the manifest once, and with it every archive of the release, instead of trusting each `.vach` in isolation.
+/// Catches archives that were swapped for ones from another release, rolled back, truncated or left out
+/// ```skip
+/// let key = vach::crypto_utils::gen_keypair();
+///
+/// let mut manifest = ReleaseManifest::create("1.2.0", "release", &["base.vach", "dlc/winter.vach"])?;
+/// manifest.sign(&key);
+/// std::fs::write("release/release.vrel", manifest.to_bytes())?;
+///
+/// let manifest = ReleaseManifest::from_bytes(&std::fs::read("release/release.vrel")?)?;
+/// manifest.verify("release", &key.verifying_key())?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseManifest {
+	/// The name of the release, eg: it's version
+	pub name: String,
+	/// When the manifest was created, with a precision of one second
+	pub created: SystemTime,
+	/// Every archive of the release
+	pub archives: Vec<ReleaseArchive>,
+	/// The signature over the rest of the manifest, set by [`ReleaseManifest::sign`]
+	pub signature: Option<Signature>,
+}
+
+impl ReleaseManifest {
+	const MAGIC: &'static [u8; 4] = b"VREL";
+
+	/// Hashes each archive at it's path relative to `base`, stamping the manifest with the current time. The manifest is unsigned, see [`ReleaseManifest::sign`]
+	pub fn create(name: impl Into<String>, base: impl AsRef<Path>, archives: &[impl AsRef<Path>]) -> InternalResult<ReleaseManifest> {
+		let base = base.as_ref();
+
+		let archives = archives
+			.iter()
+			.map(|path| {
+				let path = release_path(path.as_ref())?;
+				let (size, hash) = hash_file(&base.join(&path))?;
+
+				Ok(ReleaseArchive { path, size, hash })
+			})
+			.collect::<InternalResult<Vec<_>>>()?;
+
+		// Truncated to whole seconds, as stored
+		let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+
+		Ok(ReleaseManifest {
+			name: name.into(),
+			created: UNIX_EPOCH + Duration::from_secs(seconds),
+			archives,
+			signature: None,
+		})
+	}
+
+	/// Signs the manifest, replacing any previous signature
+	pub fn sign(&mut self, key: &SigningKey) {
+		self.signature = Some(key.sign(&self.message()));
+	}
+
+	/// Checks the manifest's signature, failing if it's unsigned or was signed by another key
+	pub fn verify_signature(&self, key: &VerifyingKey) -> InternalResult {
+		let signature = self.signature.as_ref().ok_or_else(|| mismatch("The release manifest is not signed"))?;
+
+		key.verify(&self.message(), signature)
+			.map_err(|_| mismatch("The signature of the release manifest doesn't match the key"))
+	}
+
+	/// Checks the manifest's signature, then the size and hash of every archive at it's path relative to `base`.
+	/// Fails on the first archive that is missing or doesn't match, with an [`InternalError::ReleaseMismatchError`]
+	pub fn verify(&self, base: impl AsRef<Path>, key: &VerifyingKey) -> InternalResult {
+		self.verify_signature(key)?;
+
+		for archive in &self.archives {
+			let path = base.as_ref().join(release_path(Path::new(&archive.path))?);
+
+			let size = path
+				.metadata()
+				.map_err(|err| mismatch(format!("{} is unreadable: {}", archive.path, err)))?
+				.len();
+
+			// Cheap size check first, so truncated downloads aren't hashed
+			if size != archive.size {
+				return Err(mismatch(format!("{} is {} bytes, expected {}", archive.path, size, archive.size)));
+			}
+
+			if hash_file(&path)?.1 != archive.hash {
+				return Err(mismatch(format!("The hash of {} doesn't match the release", archive.path)));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The signed part of the manifest: everything but the signature
+	fn message(&self) -> Vec<u8> {
+		let mut buffer = Vec::with_capacity(64 + self.archives.iter().map(|a| 74 + a.path.len()).sum::<usize>());
+		buffer.extend_from_slice(Self::MAGIC);
+
+		buffer.extend_from_slice(&(self.name.len() as u16).to_le_bytes());
+		buffer.extend_from_slice(self.name.as_bytes());
+
+		let created = self.created.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+		buffer.extend_from_slice(&created.to_le_bytes());
+		buffer.extend_from_slice(&(self.archives.len() as u32).to_le_bytes());
+
+		for archive in &self.archives {
+			buffer.extend_from_slice(&(archive.path.len() as u16).to_le_bytes());
+			buffer.extend_from_slice(archive.path.as_bytes());
+			buffer.extend_from_slice(&archive.size.to_le_bytes());
+			buffer.extend_from_slice(&archive.hash);
+		}
+
+		buffer
+	}
+
+	/// Serializes the manifest, to ship it alongside the release
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buffer = self.message();
+
+		match &self.signature {
+			Some(signature) => {
+				buffer.push(1);
+				buffer.extend_from_slice(&signature.to_bytes());
+			},
+			None => buffer.push(0),
+		}
+
+		buffer
+	}
+
+	/// Parses a manifest written by [`ReleaseManifest::to_bytes`]. Nothing is verified, see [`ReleaseManifest::verify`]
+	pub fn from_bytes(bytes: &[u8]) -> InternalResult<ReleaseManifest> {
+		let mut rest = bytes.strip_prefix(Self::MAGIC).ok_or_else(invalid)?;
+
+		let name_length = u16::from_le_bytes(take_array(&mut rest)?) as usize;
+		let name = String::from_utf8(take(&mut rest, name_length)?.to_vec()).map_err(|_| invalid())?;
+		let created = UNIX_EPOCH + Duration::from_secs(u64::from_le_bytes(take_array(&mut rest)?));
+
+		let count = u32::from_le_bytes(take_array(&mut rest)?);
+		let mut archives = Vec::new();
+
+		for _ in 0..count {
+			let path_length = u16::from_le_bytes(take_array(&mut rest)?) as usize;
+			let path = String::from_utf8(take(&mut rest, path_length)?.to_vec()).map_err(|_| invalid())?;
+
+			archives.push(ReleaseArchive {
+				path,
+				size: u64::from_le_bytes(take_array(&mut rest)?),
+				hash: take_array(&mut rest)?,
+			});
+		}
+
+		let signature = match take_array(&mut rest)? {
+			[0] => None,
+			[1] => Some(Signature::from_bytes(&take_array(&mut rest)?)),
+			_ => return Err(invalid()),
+		};
+
+		match rest.is_empty() {
+			true => Ok(ReleaseManifest {
+				name,
+				created,
+				archives,
+				signature,
+			}),
+			false => Err(invalid()),
+		}
+	}
+}
+
+fn invalid() -> InternalError {
+	InternalError::ParseError("Invalid release manifest".to_string())
+}
+
+/// Splits `len` bytes off the front of a manifest being parsed
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> InternalResult<&'a [u8]> {
+	let (taken, tail) = rest.split_at_checked(len).ok_or_else(invalid)?;
+	*rest = tail;
+	Ok(taken)
+}
+
+/// Splits `N` bytes off the front of a manifest being parsed, as an array
+fn take_array<const N: usize>(rest: &mut &[u8]) -> InternalResult<[u8; N]> {
+	let (taken, tail) = rest.split_first_chunk::<N>().ok_or_else(invalid)?;
+	*rest = tail;
+	Ok(*taken)
+}
+
+fn mismatch(reason: impl Into<String>) -> InternalError {
+	InternalError::ReleaseMismatchError(reason.into())
+}
+
+/// Paths are stored `/` separated and can't point outside of the release's directory
+fn release_path(path: &Path) -> InternalResult<String> {
+	let components = path
+		.components()
+		.map(|component| match component {
+			Component::Normal(name) => name.to_str(),
+			_ => None,
+		})
+		.collect::<Option<Vec<_>>>();
+
+	match components {
+		Some(components) if !components.is_empty() => Ok(components.join("/")),
+		_ => Err(InternalError::ParseError(format!("{} is not a relative path within the release", path.display()))),
+	}
+}
+
+fn hash_file(path: &Path) -> InternalResult<(u64, [u8; RELEASE_HASH_LENGTH])> {
+	let mut file = File::open(path)?;
+	let mut hasher = Sha512::new();
+	let mut buffer = vec![0; 64 * 1024];
+	let mut size = 0;
+
+	loop {
+		match file.read(&mut buffer)? {
+			0 => break,
+			read => {
+				hasher.update(&buffer[..read]);
+				size += read as u64;
+			},
+		}
+	}
+
+	Ok((size, hasher.finalize().into()))
+}
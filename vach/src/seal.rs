@@ -0,0 +1,109 @@
+#![cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+#![cfg_attr(docsrs, doc(cfg(all(feature = "builder", feature = "archive", feature = "crypto"))))]
+
+use std::{
+	fs::{self, File},
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	crypto::{SigningKey, VerifyingKey},
+	global::{entry_id::EntryId, error::*, flags::Flags, header::ArchiveConfig},
+	loader::{archive::Archive, resource::Resource},
+	writer::{Builder, BuilderConfig, Leaf},
+};
+
+#[cfg(feature = "compression")]
+use crate::writer::CompressMode;
+
+/// Packs every file within `dir`, recursively, into a sealed archive at `out`: compressed where it helps, then encrypted, with every entry
+/// and it's registry data signed by `signing_key`. `ID`s are paths relative to `dir`, separated by `/`. Returns the number of bytes of leaf data written.
+/// Anyone holding the verifying key can decrypt the archive, so distribute it only to those meant to read the contents. Open it with [`open_sealed`]
+/// ```skip
+/// let key = vach::crypto_utils::gen_keypair();
+/// vach::seal::seal_directory("assets", "assets.vach", &key)?;
+///
+/// let sealed = vach::seal::open_sealed("assets.vach", key.verifying_key())?;
+/// let script = sealed.fetch("scripts/intro.lua")?;
+/// ```
+pub fn seal_directory(dir: impl AsRef<Path>, out: impl AsRef<Path>, signing_key: &SigningKey) -> InternalResult<u64> {
+	let dir = dir.as_ref();
+
+	let mut files = Vec::new();
+	collect_files(dir, &mut files)?;
+	files.sort();
+
+	let template = Leaf::default().encrypt(true).sign(true);
+	#[cfg(feature = "compression")]
+	let template = template.compress(CompressMode::Detect);
+
+	let mut builder = Builder::new().template(template);
+	for path in files {
+		let id = path
+			.strip_prefix(dir)
+			.map_err(|_| InternalError::ParseError(format!("{} can't be mapped to an ID", path.display())))?
+			.iter()
+			.map(|s| s.to_string_lossy())
+			.collect::<Vec<_>>()
+			.join("/");
+
+		builder.add(File::open(&path)?, id)?;
+	}
+
+	let config = BuilderConfig::default().keypair(signing_key.clone());
+	builder.dump(File::create(out)?, &config)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> InternalResult {
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+
+		if path.is_dir() {
+			collect_files(&path, files)?;
+		} else {
+			files.push(path);
+		}
+	}
+
+	Ok(())
+}
+
+/// Opens an archive written by [`seal_directory`], refusing archives with entries that aren't both encrypted and signed
+pub fn open_sealed(path: impl AsRef<Path>, key: VerifyingKey) -> InternalResult<Sealed> {
+	let archive = Archive::open_with_config(path, &ArchiveConfig::default().key(key))?;
+
+	let sealed = Flags::ENCRYPTED_FLAG | Flags::SIGNED_FLAG;
+	if let Some((id, _)) = archive.iter().find(|(_, entry)| !entry.flags.contains(sealed)) {
+		return Err(InternalError::UnauthenticatedError(id.clone()));
+	}
+
+	Ok(Sealed { archive })
+}
+
+/// A sealed archive, as returned by [`open_sealed`]. Unlike a plain [`Archive`], fetches fail outright when an entry's signature doesn't match
+#[derive(Debug)]
+pub struct Sealed {
+	archive: Archive<File>,
+}
+
+impl Sealed {
+	/// Fetch and authenticate a [`Resource`], failing with [`InternalError::UnauthenticatedError`] if it was tampered with
+	pub fn fetch(&self, id: impl AsRef<str>) -> InternalResult<Resource> {
+		let resource = self.archive.fetch(id.as_ref())?;
+
+		match resource.authenticated {
+			true => Ok(resource),
+			false => Err(InternalError::UnauthenticatedError(EntryId::from_raw(id))),
+		}
+	}
+
+	/// The underlying [`Archive`], for listing entries. Resources fetched from it directly aren't checked, see `Resource::authenticated`
+	pub fn archive(&self) -> &Archive<File> {
+		&self.archive
+	}
+
+	/// Consume the [`Sealed`] archive, returning the underlying [`Archive`]
+	pub fn into_inner(self) -> Archive<File> {
+		self.archive
+	}
+}
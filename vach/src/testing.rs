@@ -0,0 +1,227 @@
+use std::{
+	io::{self, Read, Seek, SeekFrom, Write},
+	thread,
+	time::Duration,
+};
+
+/// Wraps a source, failing reads that reach any of the configured offsets. Reads stop short right before a faulty offset,
+/// the next read starting at it then fails. Faults fire every time, so retries past one must seek over it
+/// ```
+/// use std::io::{Cursor, ErrorKind, Read};
+/// use vach::testing::FaultyReader;
+///
+/// let mut reader = FaultyReader::new(Cursor::new(vec![0u8; 16])).fail_at(10, ErrorKind::TimedOut);
+/// let mut buffer = [0u8; 16];
+///
+/// assert_eq!(reader.read(&mut buffer).unwrap(), 10);
+/// assert_eq!(reader.read(&mut buffer).unwrap_err().kind(), ErrorKind::TimedOut);
+/// assert_eq!(reader.faults_triggered(), 1);
+/// ```
+#[derive(Debug)]
+pub struct FaultyReader<R> {
+	inner: R,
+	position: u64,
+	faults: Vec<(u64, io::ErrorKind)>,
+	triggered: usize,
+}
+
+impl<R> FaultyReader<R> {
+	/// Wraps `inner`, without any faults. The position is assumed to start at `0`
+	pub fn new(inner: R) -> FaultyReader<R> {
+		FaultyReader {
+			inner,
+			position: 0,
+			faults: Vec::new(),
+			triggered: 0,
+		}
+	}
+
+	/// Fails reads reaching `offset` with an error of the given kind
+	pub fn fail_at(mut self, offset: u64, kind: io::ErrorKind) -> Self {
+		self.faults.push((offset, kind));
+		self
+	}
+
+	/// How many reads failed so far
+	pub fn faults_triggered(&self) -> usize {
+		self.triggered
+	}
+
+	/// The wrapped source
+	pub fn get_ref(&self) -> &R {
+		&self.inner
+	}
+
+	/// Consume the [`FaultyReader`], returning the wrapped source
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+impl<R: Read> Read for FaultyReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let end = self.position + buf.len() as u64;
+		let fault = self
+			.faults
+			.iter()
+			.filter(|(offset, _)| (self.position..end).contains(offset))
+			.min_by_key(|(offset, _)| *offset);
+
+		let len = match fault {
+			Some((offset, kind)) if *offset == self.position => {
+				self.triggered += 1;
+				return Err(io::Error::new(*kind, format!("Injected fault at offset {}", offset)));
+			},
+			Some((offset, _)) => (offset - self.position) as usize,
+			None => buf.len(),
+		};
+
+		let read = self.inner.read(&mut buf[..len])?;
+		self.position += read as u64;
+		Ok(read)
+	}
+}
+
+impl<R: Seek> Seek for FaultyReader<R> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		self.position = self.inner.seek(pos)?;
+		Ok(self.position)
+	}
+}
+
+/// Wraps a source, sleeping before every read and seek to stand in for slow disks or network shares.
+/// Optionally caps how much each read returns, to exercise handling of short reads
+#[derive(Debug)]
+pub struct SlowReader<R> {
+	inner: R,
+	latency: Duration,
+	max_read: Option<usize>,
+}
+
+impl<R> SlowReader<R> {
+	/// Wraps `inner`, delaying every read and seek by `latency`
+	pub fn new(inner: R, latency: Duration) -> SlowReader<R> {
+		SlowReader {
+			inner,
+			latency,
+			max_read: None,
+		}
+	}
+
+	/// Returns at most `max_read` bytes per read
+	pub fn max_read(mut self, max_read: usize) -> Self {
+		self.max_read = Some(max_read.max(1));
+		self
+	}
+
+	/// The wrapped source
+	pub fn get_ref(&self) -> &R {
+		&self.inner
+	}
+
+	/// Consume the [`SlowReader`], returning the wrapped source
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+impl<R: Read> Read for SlowReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		thread::sleep(self.latency);
+
+		let len = self.max_read.map_or(buf.len(), |max| buf.len().min(max));
+		self.inner.read(&mut buf[..len])
+	}
+}
+
+impl<R: Seek> Seek for SlowReader<R> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		thread::sleep(self.latency);
+		self.inner.seek(pos)
+	}
+}
+
+/// Wraps a target, counting the bytes written and the calls made to it, eg: to assert how many bytes a `Builder::dump` wrote.
+/// `Builder::dump_report` counts it's IO the same way
+/// ```
+/// use std::io::{Cursor, Write};
+/// use vach::testing::CountingWriter;
+///
+/// let mut writer = CountingWriter::new(Cursor::new(Vec::new()));
+/// writer.write_all(b"Hello").unwrap();
+/// writer.flush().unwrap();
+///
+/// assert_eq!((writer.bytes_written(), writer.writes(), writer.flushes()), (5, 1, 1));
+/// ```
+#[derive(Debug)]
+pub struct CountingWriter<W> {
+	inner: W,
+	bytes_written: u64,
+	writes: usize,
+	flushes: usize,
+	seeks: usize,
+}
+
+impl<W> CountingWriter<W> {
+	/// Wraps `inner`, with all counters at `0`
+	pub fn new(inner: W) -> CountingWriter<W> {
+		CountingWriter {
+			inner,
+			bytes_written: 0,
+			writes: 0,
+			flushes: 0,
+			seeks: 0,
+		}
+	}
+
+	/// Bytes written so far, including bytes overwritten after seeking back
+	pub fn bytes_written(&self) -> u64 {
+		self.bytes_written
+	}
+
+	/// Calls to `write` so far
+	pub fn writes(&self) -> usize {
+		self.writes
+	}
+
+	/// Calls to `flush` so far
+	pub fn flushes(&self) -> usize {
+		self.flushes
+	}
+
+	/// Calls to `seek` so far
+	pub fn seeks(&self) -> usize {
+		self.seeks
+	}
+
+	/// The wrapped target
+	pub fn get_ref(&self) -> &W {
+		&self.inner
+	}
+
+	/// Consume the [`CountingWriter`], returning the wrapped target
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = self.inner.write(buf)?;
+		self.bytes_written += written as u64;
+		self.writes += 1;
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.flushes += 1;
+		self.inner.flush()
+	}
+}
+
+impl<W: Seek> Seek for CountingWriter<W> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		self.seeks += 1;
+		self.inner.seek(pos)
+	}
+}
@@ -7,11 +7,14 @@ use crate::prelude::*;
 
 // Contains both the public key and secret key in the same file:
 // secret -> [u8; crate::SECRET_KEY_LENGTH], public -> [u8; crate::PUBLIC_KEY_LENGTH]
+#[cfg(feature = "crypto")]
 const KEYPAIR: &[u8; crate::SECRET_KEY_LENGTH + crate::PUBLIC_KEY_LENGTH] = include_bytes!("../test_data/pair.pub");
 
 // The paths to the Archives, to be written|loaded
+#[cfg(feature = "crypto")]
 const SIGNED_TARGET: &str = "test_data/signed/target.vach";
 const SIMPLE_TARGET: &str = "test_data/simple/target.vach";
+#[cfg(all(feature = "crypto", feature = "compression"))]
 const ENCRYPTED_TARGET: &str = "test_data/encrypted/target.vach";
 
 // Custom bitflag tests
@@ -35,6 +38,20 @@ fn custom_bitflags() -> InternalResult {
 	Ok(())
 }
 
+#[test]
+#[cfg(all(feature = "archive", not(feature = "compression")))]
+fn fetch_missing_feature() -> InternalResult {
+	let target = File::open(SIMPLE_TARGET)?;
+	let archive = Archive::new(target)?;
+
+	// Only the compressed entry is refused, the rest of the archive stays readable
+	assert!(archive.fetch("greeting").is_ok());
+	let err = archive.fetch("poem").unwrap_err();
+	assert!(matches!(err.root(), InternalError::MissingFeatureError("compression")));
+
+	Ok(())
+}
+
 #[test]
 fn flag_restricted_access() {
 	let mut flag = Flags::from_bits(0b1111_1000_0000_0000);
@@ -70,6 +87,51 @@ fn flags_set_intersects() {
 	assert_eq!(flag.bits(), Flags::COMPRESSED_FLAG | Flags::SIGNED_FLAG);
 }
 
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn forged_leaf_size() -> InternalResult {
+	use std::io::{Cursor, ErrorKind};
+	use crate::global::header::Header;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "hello")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	// The registry claims far more data than the archive holds, past flags, content version and location
+	let start = Header::BASE_SIZE + 4 + 1 + 8;
+	target.get_mut()[start..start + 8].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+
+	let eof = |err: InternalError| matches!(err.root(), InternalError::IOError(err) if err.kind() == ErrorKind::UnexpectedEof);
+
+	let archive = Archive::new(target.clone())?;
+	assert_eq!(archive.fetch_entry("hello").unwrap().offset, u64::MAX / 2);
+	assert!(eof(archive.fetch("hello").unwrap_err()));
+
+	#[cfg(any(unix, windows))]
+	{
+		let path = std::env::temp_dir().join("vach-forged-leaf-size.vach");
+		std::fs::write(&path, target.get_ref())?;
+
+		let archive = Archive::new(File::open(&path)?)?;
+		assert!(eof(archive.fetch_positional("hello").unwrap_err()));
+
+		#[cfg(all(feature = "uring", target_os = "linux"))]
+		assert!(eof(archive.fetch_batch_uring(["hello"]).remove(0).unwrap_err()));
+
+		std::fs::remove_file(path)?;
+	}
+
+	#[cfg(feature = "async")]
+	{
+		let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+		let archive = runtime.block_on(crate::archive::AsyncArchive::new(target.clone()))?;
+		assert!(eof(runtime.block_on(archive.fetch("hello")).unwrap_err()));
+	}
+
+	Ok(())
+}
+
 #[test]
 #[cfg(all(feature = "compression", feature = "builder"))]
 fn builder_no_signature() -> InternalResult {
@@ -181,7 +243,7 @@ fn decryptor_test() -> InternalResult {
 
 	let vk = gen_keypair().verifying_key();
 
-	let crypt = Encryptor::new(&vk, crate::DEFAULT_MAGIC.clone());
+	let crypt = Encryptor::new(&vk, *crate::DEFAULT_MAGIC);
 	let data = vec![12, 12, 12, 12];
 
 	let ciphertext = crypt.encrypt(&data)?;
@@ -205,7 +267,7 @@ fn builder_with_encryption() -> InternalResult {
 	builder.add_leaf(
 		Leaf::new(b"Snitches get stitches, iOS sucks" as &[u8])
 			.sign(false)
-			.compression_algo(CompressionAlgorithm::Brotli(11))
+			.compression_algo(CompressionAlgorithm::Brotli(BrotliParams::new(11)))
 			.compress(CompressMode::Always)
 			.id("stitches.snitches"),
 	)?;
@@ -320,7 +382,7 @@ fn test_compressors() -> InternalResult {
 	builder.add_leaf(
 		Leaf::new(input.as_slice())
 			.id("BROTLI")
-			.compression_algo(CompressionAlgorithm::Brotli(9))
+			.compression_algo(CompressionAlgorithm::Brotli(BrotliParams::new(9)))
 			.compress(CompressMode::Always),
 	)?;
 	builder.add_leaf(
@@ -343,9 +405,9 @@ fn test_compressors() -> InternalResult {
 	assert_eq!(d2.data.len(), INPUT_LEN);
 	assert_eq!(d3.data.len(), INPUT_LEN);
 
-	assert!(&d1.data[..] == &input);
-	assert!(&d2.data[..] == &input);
-	assert!(&d3.data[..] == &input);
+	assert!(d1.data[..] == input);
+	assert!(d2.data[..] == input);
+	assert!(d3.data[..] == input);
 
 	// Compression tests
 	assert!(archive.fetch_entry("LZ4").unwrap().offset < INPUT_LEN as u64);
@@ -392,12 +454,12 @@ fn test_batch_fetching() -> InternalResult {
 	let mut resources = ids
 		.as_slice()
 		.par_iter()
-		.map(|id| (id.as_str(), archive.fetch(&id)))
+		.map(|id| (id.as_str(), archive.fetch(id)))
 		.collect::<HashMap<_, _>>();
 
 	// Tests and checks
-	assert!(resources.get("NON_EXISTENT").is_none());
-	assert!(resources.get("ERRORS").is_some());
+	assert!(!resources.contains_key("NON_EXISTENT"));
+	assert!(resources.contains_key("ERRORS"));
 
 	match resources.remove("ERRORS").unwrap() {
 		Ok(_) => return Err(InternalError::OtherError("This should be an error".into())),
@@ -416,3 +478,3745 @@ fn test_batch_fetching() -> InternalResult {
 
 	Ok(())
 }
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn tampered_data_is_not_authenticated() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut target = Cursor::new(Vec::<u8>::new());
+	let mut config = BuilderConfig::default();
+	config.load_keypair(KEYPAIR.as_slice())?;
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Sign me, then tamper with me" as &[u8]).id("data").sign(true))?;
+	builder.dump(&mut target, &config)?;
+
+	let mut config = ArchiveConfig::default();
+	config.load_public_key(&KEYPAIR[crate::SECRET_KEY_LENGTH..])?;
+
+	// Untouched data authenticates
+	let location = {
+		let mut archive = Archive::with_config(&mut target, &config)?;
+		assert!(archive.fetch_mut("data")?.authenticated);
+		archive.fetch_entry_ref("data").unwrap().location as usize
+	};
+
+	// Flip a single bit within the leaf
+	target.get_mut()[location] ^= 0b0000_0001;
+
+	let mut archive = Archive::with_config(&mut target, &config)?;
+	assert!(!archive.fetch_mut("data")?.authenticated);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn fetch_batch_keeps_order() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut target = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new();
+
+	for i in 0..32u8 {
+		builder.add(Cursor::new(vec![i; i as usize * 16]), format!("leaf-{i}"))?;
+	}
+
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	let ids = (0..32u8).rev().map(|i| format!("leaf-{i}")).chain(["missing".to_string()]);
+	let results = archive.fetch_batch(ids);
+
+	assert_eq!(results.len(), 33);
+	assert!(matches!(results[32], Err(InternalError::MissingResourceError(_))));
+
+	for (res, i) in results.into_iter().zip((0..32u8).rev()) {
+		assert_eq!(res?.data.as_ref(), vec![i; i as usize * 16].as_slice());
+	}
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "uring", target_os = "linux", feature = "builder", feature = "archive"))]
+fn fetch_batch_uring() -> InternalResult {
+	let path = std::env::temp_dir().join("vach-fetch-batch-uring.vach");
+
+	let mut builder = Builder::new();
+	builder.add(File::open("test_data/quicksort.wasm")?, "wasm")?;
+	builder.add(File::open("test_data/poem.txt")?, "poem")?;
+	builder.dump(File::create(&path)?, &BuilderConfig::default())?;
+
+	let archive = Archive::new(File::open(&path)?)?;
+	archive.prefetch_uring(["poem", "wasm"])?;
+	assert!(archive.prefetch_uring(["poem", "missing"]).is_err());
+
+	let results = archive.fetch_batch_uring(["poem", "wasm", "missing"]);
+
+	assert_eq!(results[0].as_ref().unwrap().data.as_ref(), std::fs::read("test_data/poem.txt")?);
+	assert_eq!(results[1].as_ref().unwrap().data.len(), 106537);
+	assert!(results[2].is_err());
+
+	std::fs::remove_file(path)?;
+	Ok(())
+}
+
+#[test]
+#[cfg(all(any(unix, windows), feature = "builder", feature = "archive"))]
+fn fetch_positional_concurrently() -> InternalResult {
+	use rayon::prelude::*;
+
+	let path = std::env::temp_dir().join("vach-fetch-positional.vach");
+
+	let mut builder = Builder::new();
+	for i in 0..64 {
+		builder.add(std::io::Cursor::new(format!("Resource number {i}")), format!("{i}"))?;
+	}
+	builder.dump(File::create(&path)?, &BuilderConfig::default())?;
+
+	// Positional reads mix with regular ones, which seek through the same handle
+	let archive = Archive::new(File::open(&path)?)?;
+	(0..64).into_par_iter().try_for_each(|i| {
+		let resource = match i % 2 {
+			0 => archive.fetch_positional(format!("{i}"))?,
+			_ => archive.fetch(format!("{i}"))?,
+		};
+		assert_eq!(resource.data.as_ref(), format!("Resource number {i}").as_bytes());
+		InternalResult::Ok(())
+	})?;
+
+	std::fs::remove_file(path)?;
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn entry_iterators() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut target = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new();
+
+	for id in ["c", "a", "b"] {
+		builder.add(Cursor::new(id.as_bytes()), id)?;
+	}
+
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	assert_eq!(archive.iter().len(), 3);
+	assert_eq!((&archive).into_iter().count(), 3);
+
+	let sorted = archive.entries_vec_sorted();
+	let ids = sorted.iter().map(|(id, _)| id.as_ref()).collect::<Vec<_>>();
+	assert_eq!(ids, ["a", "b", "c"]);
+
+	let entries = archive.into_entries();
+	assert_eq!(entries.len(), 3);
+	assert!(entries.into_iter().all(|(id, entry)| id == entry.id));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn entry_id_normalization() -> InternalResult {
+	for (raw, normalized) in [
+		("maps/harbor.map", "maps/harbor.map"),
+		("maps\\harbor.map", "maps/harbor.map"),
+		("./maps//harbor.map", "maps/harbor.map"),
+		(".\\.\\maps\\\\harbor.map", "maps/harbor.map"),
+		("dlc1::maps//harbor.map", "dlc1::maps/harbor.map"),
+	] {
+		assert_eq!(EntryId::new(raw)?, normalized);
+	}
+
+	let invalid = |result: InternalResult<EntryId>| matches!(result, Err(InternalError::InvalidIdError(..)));
+	assert!(invalid(EntryId::new("")));
+	assert!(invalid(EntryId::new("./")));
+	assert!(invalid(EntryId::new("maps/\0harbor.map")));
+
+	// Leaf IDs and dependencies are normalized and validated the same way
+	let mut builder = Builder::new();
+	assert!(matches!(
+		builder.add(b"" as &[u8], "maps\0"),
+		Err(InternalError::InvalidIdError(..))
+	));
+	assert!(matches!(builder.add(b"" as &[u8], ""), Err(InternalError::InvalidIdError(..))));
+
+	builder.add_leaf(Leaf::new(b"Harbor" as &[u8]).id("maps\\harbor.map").depends_on(["./textures//water.png"]))?;
+	builder.add(b"Water" as &[u8], "textures/water.png")?;
+	assert!(matches!(
+		builder.add(b"" as &[u8], "./maps/harbor.map"),
+		Err(InternalError::LeafAppendError(..))
+	));
+
+	let mut target = std::io::Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	// Lookups are normalized too
+	let archive = Archive::new(target)?;
+	assert_eq!(archive.fetch_entry_ref("maps\\harbor.map").unwrap().id, "maps/harbor.map");
+	assert_eq!(archive.dependencies("./maps/harbor.map")?, ["textures/water.png"]);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "builder", feature = "archive"))]
+fn fetch_errors_carry_entry_context() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut config = BuilderConfig::default();
+	config.load_keypair(KEYPAIR.as_slice())?;
+
+	let mut target = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new().template(Leaf::default().encrypt(true));
+	builder.add(Cursor::new(b"Secret Stuff"), "secret")?;
+	builder.dump(&mut target, &config)?;
+
+	// No key is provided, so decryption must fail
+	let archive = Archive::new(target)?;
+	let entry = archive.fetch_entry("secret").unwrap();
+
+	match archive.fetch("secret") {
+		Err(InternalError::EntryError { id, offset, stage, source }) => {
+			assert_eq!(id, "secret");
+			assert_eq!(offset, Some(entry.location));
+			assert_eq!(stage, Stage::Decrypt);
+			assert!(matches!(*source, InternalError::NoKeypairError));
+		},
+		res => panic!("Expected an EntryError, found: {:?}", res.map(|_| ())),
+	};
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn fetch_stats_ring_buffer() -> InternalResult {
+	use std::{io::Cursor, time::Duration};
+
+	let mut target = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new();
+
+	for i in 0..4u8 {
+		builder.add(Cursor::new(vec![i; 64]), format!("leaf-{i}"))?;
+	}
+
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	fn slow(stats: &FetchStats) {
+		assert_eq!(stats.decoded_size, 64);
+	}
+
+	let config = ArchiveConfig::default()
+		.fetch_stats(2)
+		.slow_fetch(Duration::ZERO, slow);
+	let archive = Archive::with_config(target, &config)?;
+
+	for i in 0..4u8 {
+		archive.fetch(format!("leaf-{i}"))?;
+	}
+
+	let stats = archive.recent_fetch_stats();
+	assert_eq!(stats.len(), 2);
+	assert_eq!(stats[0].id, "leaf-2");
+	assert_eq!(stats[1].id, "leaf-3");
+	assert_eq!(stats[1].raw_size, 64);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "builder", feature = "archive"))]
+fn editor_renames_and_resigns() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let mut config = BuilderConfig::default();
+	config.load_keypair(KEYPAIR.as_slice())?;
+
+	let mut source = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new().template(Leaf::default().sign(true));
+	builder.add(Cursor::new(b"Drum roll"), "sfx/drums")?;
+	builder.add(Cursor::new(b"Hero walk cycle"), "characters/hero/walk")?;
+	builder.add(Cursor::new(b"Hero idle"), "characters/hero/idle")?;
+	builder.dump(&mut source, &config)?;
+
+	let archive_config = ArchiveConfig::default().key(keypair.verifying_key());
+
+	// Re-signing requires a key
+	let mut editor = ArchiveEditor::new(Archive::with_config(source.clone(), &archive_config)?);
+	editor.rename("sfx/drums", "sfx/percussion")?;
+	assert!(editor.commit(Cursor::new(Vec::new())).is_err());
+
+	let mut editor = ArchiveEditor::new(Archive::with_config(source, &archive_config)?);
+	editor.signing_key(keypair);
+
+	assert!(matches!(
+		editor.rename("missing", "something"),
+		Err(InternalError::MissingResourceError(_))
+	));
+	assert!(matches!(
+		editor.rename("sfx/drums", "characters/hero/idle"),
+		Err(InternalError::LeafAppendError(_))
+	));
+
+	editor.rename("sfx/drums", "sfx/percussion")?;
+	assert_eq!(editor.rename_prefix("characters/hero/", "characters/protagonist/")?, 2);
+
+	let mut target = Cursor::new(Vec::new());
+	editor.commit(&mut target)?;
+
+	let archive = Archive::with_config(target, &archive_config)?;
+	assert_eq!(archive.entries().len(), 3);
+
+	let resource = archive.fetch("characters/protagonist/walk")?;
+	assert!(resource.authenticated);
+	assert_eq!(resource.data.as_ref(), b"Hero walk cycle");
+
+	let resource = archive.fetch("sfx/percussion")?;
+	assert!(resource.authenticated);
+	assert!(archive.fetch("sfx/drums").is_err());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn editor_rename_prefix_is_atomic() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut source = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(b"One"), "a/1")?;
+	builder.add(Cursor::new(b"Two"), "a/2")?;
+	builder.add(Cursor::new(b"Other two"), "b/2")?;
+	builder.add(Cursor::new(b"Short"), "v")?;
+	builder.add(Cursor::new(b"Long"), "vv")?;
+	builder.dump(&mut source, &BuilderConfig::default())?;
+
+	// "a/1" could move, but "a/2" collides, so neither does
+	let mut editor = ArchiveEditor::new(Archive::new(source)?);
+	assert!(matches!(
+		editor.rename_prefix("a/", "b/"),
+		Err(InternalError::LeafAppendError(id)) if id == "b/2"
+	));
+
+	// "v" takes the place of "vv", which moves away in the same rename
+	assert_eq!(editor.rename_prefix("v", "vv")?, 2);
+
+	let mut target = Cursor::new(Vec::new());
+	editor.commit(&mut target)?;
+
+	let archive = Archive::new(target)?;
+	let mut ids = archive.entries().keys().map(|id| id.to_string()).collect::<Vec<_>>();
+	ids.sort();
+	assert_eq!(ids, ["a/1", "a/2", "b/2", "vv", "vvv"]);
+	assert_eq!(archive.fetch("vv")?.data.as_ref(), b"Short");
+	assert_eq!(archive.fetch("vvv")?.data.as_ref(), b"Long");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "builder", feature = "archive"))]
+fn editor_resign_rotates_key() -> InternalResult {
+	use std::io::Cursor;
+
+	let old_key = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let new_key = crate::crypto_utils::gen_keypair();
+
+	let mut config = BuilderConfig::default();
+	config.load_keypair(KEYPAIR.as_slice())?;
+
+	let mut source = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Signed" as &[u8]).id("signed").sign(true))?;
+	builder.add_leaf(Leaf::new(b"Unsigned" as &[u8]).id("unsigned"))?;
+	builder.dump(&mut source, &config)?;
+
+	let mut editor = ArchiveEditor::new(Archive::new(source)?);
+	editor.signing_key(new_key.clone());
+	assert_eq!(editor.resign(), 1);
+
+	let mut target = Cursor::new(Vec::new());
+	editor.commit(&mut target)?;
+
+	let archive = Archive::with_config(target.clone(), &ArchiveConfig::default().key(new_key.verifying_key()))?;
+	assert!(archive.fetch("signed")?.authenticated);
+	assert!(!archive.fetch("unsigned")?.authenticated);
+
+	let archive = Archive::with_config(target, &ArchiveConfig::default().key(old_key.verifying_key()))?;
+	assert!(!archive.fetch("signed")?.authenticated);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "builder", feature = "archive"))]
+fn external_leaf_signer() -> InternalResult {
+	use std::io::Cursor;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	// Stands in for a signer holding its key out of process
+	struct Remote(SigningKey, AtomicUsize);
+
+	impl LeafSigner for Remote {
+		fn verifying_key(&self) -> VerifyingKey {
+			self.0.verifying_key()
+		}
+
+		fn sign_leaf(&self, message: &[u8]) -> InternalResult<Signature> {
+			self.1.fetch_add(1, Ordering::SeqCst);
+			self.0.sign_leaf(message)
+		}
+	}
+
+	struct Locked;
+
+	impl LeafSigner for Locked {
+		fn verifying_key(&self) -> VerifyingKey {
+			crate::crypto_utils::read_keypair(KEYPAIR.as_slice()).unwrap().verifying_key()
+		}
+
+		fn sign_leaf(&self, _: &[u8]) -> InternalResult<Signature> {
+			Err(InternalError::NoKeypairError)
+		}
+	}
+
+	let remote = Remote(crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?, AtomicUsize::new(0));
+	let config = BuilderConfig::default().signer(&remote);
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new().template(Leaf::default().sign(true).encrypt(true));
+	builder.add(b"Signed remotely" as &[u8], "remote")?;
+	builder.add(b"Also signed remotely" as &[u8], "remote-2")?;
+	builder.dump(&mut target, &config)?;
+
+	assert_eq!(remote.1.load(Ordering::SeqCst), 2);
+
+	let archive = Archive::with_config(target, &ArchiveConfig::default().key(remote.verifying_key()))?;
+	let resource = archive.fetch("remote")?;
+	assert!(resource.authenticated);
+	assert_eq!(resource.data.as_ref(), b"Signed remotely");
+
+	// Failures surface with the entry and the stage
+	let mut builder = Builder::new().template(Leaf::default().sign(true));
+	builder.add(b"Never signed" as &[u8], "locked")?;
+
+	let config = BuilderConfig::default().signer(&Locked);
+	match builder.dump(Cursor::new(Vec::new()), &config) {
+		Err(InternalError::EntryError { id, stage, .. }) => {
+			assert_eq!(id, "locked");
+			assert_eq!(stage, Stage::Sign);
+		},
+		other => panic!("Expected a signing error, found {:?}", other),
+	};
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn vfs_over_archive_and_directory() -> InternalResult {
+	use std::io::{Cursor, Read};
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::default();
+	builder.add(b"Loose" as &[u8], "poem.txt")?;
+	builder.add(b"Nested" as &[u8], "sfx/ui/click.wav")?;
+	builder.add(b"Sibling" as &[u8], "sfx/boom.wav")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	let sources: [&dyn Vfs; 2] = [&archive, &DirectoryVfs::new("test_data")];
+
+	for vfs in sources {
+		assert!(!vfs.stat("poem.txt")?.is_dir);
+		assert!(vfs.list("")?.contains(&"poem.txt".to_string()));
+
+		let mut buffer = Vec::new();
+		vfs.open("poem.txt")?.read_to_end(&mut buffer)?;
+		assert_eq!(buffer, vfs.read("poem.txt")?);
+
+		assert!(!vfs.exists("missing.txt"));
+		assert!(matches!(vfs.read("missing.txt"), Err(InternalError::MissingResourceError(_))));
+	}
+
+	// Directories in archives are implied by their entries' IDs
+	assert!(archive.stat("sfx")?.is_dir);
+	assert_eq!(archive.list("sfx")?, ["sfx/boom.wav", "sfx/ui"]);
+	assert_eq!(archive.list("sfx/ui/")?, ["sfx/ui/click.wav"]);
+	assert!(archive.list("sf").is_err());
+
+	// Paths can't escape the directory
+	let dir = DirectoryVfs::new("test_data/simple");
+	assert!(dir.read("../poem.txt").is_err());
+	assert_eq!(dir.list("")?, ["target.vach"]);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn hybrid_source_priority() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::default();
+	builder.add(b"Packed poem" as &[u8], "poem.txt")?;
+	builder.add(b"Packed only" as &[u8], "packed.txt")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	// Loose files win during development, the rest falls through to the archive
+	let hybrid = HybridSource::new("test_data", Archive::new(target)?);
+	assert_eq!(hybrid.read("poem.txt")?, std::fs::read("test_data/poem.txt")?);
+	assert_eq!(hybrid.read("packed.txt")?, b"Packed only");
+	assert!(hybrid.read("missing.txt").is_err());
+
+	let listing = hybrid.list("")?;
+	assert!(listing.contains(&"packed.txt".to_string()) && listing.contains(&"lorem.txt".to_string()));
+	assert_eq!(listing.iter().filter(|p| *p == "poem.txt").count(), 1);
+
+	// Release builds read from the archive first
+	let hybrid = hybrid.prefer_archive(true);
+	assert_eq!(hybrid.read("poem.txt")?, b"Packed poem");
+	assert_eq!(hybrid.read("lorem.txt")?, std::fs::read("test_data/lorem.txt")?);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn lint_archives_and_builders() -> InternalResult {
+	use std::io::Cursor;
+
+	let text = std::fs::read("test_data/lorem.txt")?;
+	let noise = (0..64 * 1024u32)
+		.map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+		.collect::<Vec<u8>>();
+
+	let mut builder = Builder::default();
+	builder.add(text.as_slice(), "docs/lorem.txt")?;
+	builder.add(text.as_slice(), "docs\\copy.txt")?;
+	builder.add_leaf(Leaf::new(noise.as_slice()).id("noise.bin").compress(CompressMode::Always))?;
+	builder.add(b"Placeholder" as &[u8], "../tmp")?;
+
+	let mut warnings = builder.lint()?.iter().map(|w| (w.name(), w.id().to_string())).collect::<Vec<_>>();
+	warnings.sort();
+
+	// Separators are normalized when adding, parent segments are left as is
+	assert_eq!(
+		warnings,
+		[
+			("duplicate-content", "docs/copy.txt".to_string()),
+			("incompressible-compressed", "noise.bin".to_string()),
+			("non-canonical-id", "../tmp".to_string()),
+			("uncompressed-text", "docs/copy.txt".to_string()),
+			("uncompressed-text", "docs/lorem.txt".to_string()),
+		]
+	);
+
+	// Linting buffers the leaves, they can still be written
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	let warnings = archive.lint()?;
+	assert_eq!(warnings.len(), 5);
+	assert!(matches!(&warnings[0], LintWarning::NonCanonicalId { id } if id == "../tmp"));
+	assert_eq!(archive.fetch("noise.bin")?.data.as_ref(), noise.as_slice());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn detect_heuristics_skip_compressed_formats() -> InternalResult {
+	use std::io::Cursor;
+
+	let text = std::fs::read("test_data/lorem.txt")?;
+	let noise = (0..64 * 1024u32)
+		.map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+		.collect::<Vec<u8>>();
+
+	let heuristics = DetectHeuristics::default();
+	assert!(heuristics.skip("textures/grass.PNG", &text));
+	assert!(heuristics.skip("blob", &noise));
+	assert!(!heuristics.skip("docs/lorem.txt", &text));
+	assert!(!heuristics.skip("archive.png/readme", &text));
+
+	let build = |config: &BuilderConfig| -> InternalResult<Archive<Cursor<Vec<u8>>>> {
+		let mut target = Cursor::new(Vec::new());
+		let mut builder = Builder::new().template(Leaf::default().compress(CompressMode::Detect));
+		builder.add(text.as_slice(), "lorem.png")?;
+		builder.add(text.as_slice(), "lorem.txt")?;
+		builder.dump(&mut target, config)?;
+
+		Archive::new(target)
+	};
+
+	// Known extensions are stored as is, even if they'd compress
+	let archive = build(&BuilderConfig::default())?;
+	assert!(!archive.fetch_entry_ref("lorem.png").unwrap().flags.contains(Flags::COMPRESSED_FLAG));
+	assert!(archive.fetch_entry_ref("lorem.txt").unwrap().flags.contains(Flags::COMPRESSED_FLAG));
+
+	let archive = build(&BuilderConfig::default().detect_heuristics(DetectHeuristics::none().extension(".txt")))?;
+	assert!(archive.fetch_entry_ref("lorem.png").unwrap().flags.contains(Flags::COMPRESSED_FLAG));
+	assert!(!archive.fetch_entry_ref("lorem.txt").unwrap().flags.contains(Flags::COMPRESSED_FLAG));
+	assert_eq!(archive.fetch("lorem.png")?.data.as_ref(), text.as_slice());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn decoded_size_and_ratio() -> InternalResult {
+	use std::io::Cursor;
+
+	let text = std::fs::read("test_data/lorem.txt")?;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::default();
+	builder.add_leaf(Leaf::new(text.as_slice()).id("compressed").compress(CompressMode::Always))?;
+	builder.add_leaf(Leaf::new(text.as_slice()).id("detected").compress(CompressMode::Detect))?;
+	builder.add(text.as_slice(), "stored")?;
+	builder.add(b"" as &[u8], "empty")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+
+	for id in ["compressed", "detected", "stored"] {
+		let entry = archive.fetch_entry(id).unwrap();
+		assert_eq!(entry.decoded_size, text.len() as u64);
+		assert_eq!(archive.fetch(id)?.data.len() as u64, entry.decoded_size);
+	}
+
+	assert!(archive.fetch_entry("compressed").unwrap().ratio() < 0.5);
+	assert_eq!(archive.fetch_entry("stored").unwrap().ratio(), 1.0);
+	assert_eq!(archive.fetch_entry("empty").unwrap().ratio(), 1.0);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn deflate_leaves_are_gzip_streams() -> InternalResult {
+	use std::io::{Cursor, Read};
+
+	let text = std::fs::read("test_data/poem.txt")?;
+
+	let mut target = Cursor::new(Vec::new());
+	let template = Leaf::default().compress(CompressMode::Always);
+
+	let mut builder = Builder::new();
+	builder.add_leaf(
+		Leaf::new(text.as_slice())
+			.template(&template)
+			.id("poem")
+			.compression_algo(CompressionAlgorithm::Deflate(9)),
+	)?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	let entry = archive.fetch_entry("poem").unwrap();
+	assert!(entry.flags.contains(Flags::COMPRESSED_FLAG | Flags::DEFLATE_COMPRESSED));
+	assert_eq!(archive.fetch("poem")?.data.as_ref(), text.as_slice());
+
+	// The stored bytes are a plain gzip stream, readable without vach
+	let raw = &archive.into_inner().unwrap().into_inner()[entry.location as usize..][..entry.offset as usize];
+	let mut decoded = Vec::new();
+	flate2::read::GzDecoder::new(raw).read_to_end(&mut decoded)?;
+	assert_eq!(decoded, text);
+
+	// Levels are bounded
+	let mut builder = Builder::new();
+	builder.add_leaf(
+		Leaf::new(text.as_slice())
+			.template(&template)
+			.id("poem")
+			.compression_algo(CompressionAlgorithm::Deflate(10)),
+	)?;
+	assert!(builder.dump(Cursor::new(Vec::new()), &BuilderConfig::default()).is_err());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn brotli_params_are_recorded() -> InternalResult {
+	use std::io::Cursor;
+
+	let text = std::fs::read("test_data/lorem.txt")?;
+	let template = Leaf::default().compress(CompressMode::Always);
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(text.as_slice()).template(&template).id("wide").brotli(BrotliParams::new(5).window(24)))?;
+	builder.add_leaf(Leaf::new(text.as_slice()).template(&template).id("narrow").brotli(BrotliParams::new(5).window(10)))?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	for (id, window) in [("wide", 24), ("narrow", 10)] {
+		let flags = archive.fetch_entry(id).unwrap().flags;
+
+		match CompressionAlgorithm::from_flags(&flags) {
+			Some(CompressionAlgorithm::Brotli(params)) => assert_eq!(params.window, window),
+			other => panic!("Expected brotli, found {:?}", other),
+		}
+
+		assert_eq!(archive.fetch(id)?.data.as_ref(), text.as_slice());
+	}
+
+	// Entries without a recorded window fall back to the default
+	let legacy = Flags::from_bits(Flags::COMPRESSED_FLAG | Flags::BROTLI_COMPRESSED);
+	assert!(matches!(CompressionAlgorithm::from_flags(&legacy), Some(CompressionAlgorithm::Brotli(p)) if p == BrotliParams::default()));
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(text.as_slice()).template(&template).id("invalid").brotli(BrotliParams::new(5).window(30)))?;
+	assert!(builder.dump(Cursor::new(Vec::new()), &BuilderConfig::default()).is_err());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn dump_slice_of_leaves() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut leaves = vec![
+		Leaf::new(b"First" as &[u8]).id("first"),
+		Leaf::new(b"Second" as &[u8]).id("second").version(3),
+	];
+	dump(&mut target, &mut leaves, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	assert_eq!(archive.fetch("first")?.data.as_ref(), b"First");
+	assert_eq!(archive.fetch("second")?.content_version, 3);
+
+	// Slices skip the Builder's queue, but not it's checks
+	let mut leaves = [Leaf::default().id("twin"), Leaf::default().id("twin")];
+	match dump(Cursor::new(Vec::new()), &mut leaves, &BuilderConfig::default()) {
+		Err(InternalError::LeafAppendError(id)) => assert_eq!(id, "twin"),
+		other => panic!("Expected a duplicate ID error, found {:?}", other),
+	};
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "crypto"))]
+fn builder_config_validation() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut config = BuilderConfig::default().flags(Flags::from_bits(0b1111_0000_0000_0000));
+	config.validate()?;
+
+	// Contradictory settings are caught before anything is written
+	#[cfg(feature = "multithreaded")]
+	{
+		let config = BuilderConfig::default().compute_threads(0);
+		let mut target = Cursor::new(Vec::new());
+
+		match dump(&mut target, &mut [Leaf::new(b"data" as &[u8]).id("data")], &config) {
+			Err(InternalError::InvalidConfigError(_)) => assert!(target.get_ref().is_empty()),
+			other => panic!("Expected an invalid config error, found {:?}", other),
+		};
+	}
+
+	config.flags = Flags::from_bits(Flags::COMPRESSED_FLAG);
+	assert!(matches!(config.validate(), Err(InternalError::InvalidConfigError(_))));
+
+	let mut target = Cursor::new(Vec::new());
+	let mut leaves = [Leaf::new(b"secret" as &[u8]).id("secret").encrypt(true)];
+	match dump(&mut target, &mut leaves, &BuilderConfig::default()) {
+		Err(InternalError::NoKeypairError) => assert!(target.get_ref().is_empty()),
+		other => panic!("Expected a missing keypair error, found {:?}", other),
+	};
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn legacy_magic_compat() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::gen_keypair();
+	let mut builder = Builder::new().template(Leaf::default().encrypt(true));
+	builder.add(b"Branded" as &[u8], "asset")?;
+
+	let mut target = Cursor::new(Vec::new());
+	let config = BuilderConfig::default().magic(*b"GAME1").keypair(keypair.clone());
+	builder.dump(&mut target, &config)?;
+
+	// A rebrand breaks old archives, unless their magic is still accepted
+	let renamed = ArchiveConfig::new(*b"GAME2", Some(keypair.verifying_key()));
+	assert!(matches!(
+		Archive::with_config(&mut target, &renamed),
+		Err(InternalError::MalformedArchiveSource(magic)) if &magic == b"GAME1"
+	));
+
+	const LEGACY: &[[u8; crate::MAGIC_LENGTH]] = &[*b"GAME1"];
+	let compat = renamed.legacy_magic(LEGACY);
+	let archive = Archive::with_config(target, &compat)?;
+	assert_eq!(archive.magic(), b"GAME1");
+	assert_eq!(archive.fetch("asset")?.data.as_ref(), b"Branded");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "multithreaded"))]
+fn io_and_compute_threads() -> InternalResult {
+	use std::io::Cursor;
+
+	// An empty builder used to divide by zero when chunking leaves
+	let mut target = Cursor::new(Vec::new());
+	Builder::new().dump(&mut target, &BuilderConfig::default())?;
+	assert!(Archive::new(target)?.entries().is_empty());
+
+	for (compute, io) in [(1, 0), (3, 0), (1, 1), (4, 2)] {
+		let mut builder = Builder::new();
+		for i in 0..64 {
+			builder.add(Cursor::new(format!("Leaf number {}", i)), format!("leaf_{}", i))?;
+		}
+
+		let config = BuilderConfig::default().compute_threads(compute).io_threads(io);
+		let mut target = Cursor::new(Vec::new());
+		builder.dump(&mut target, &config)?;
+
+		let archive = Archive::new(target)?;
+		assert_eq!(archive.entries().len(), 64);
+		for i in 0..64 {
+			assert_eq!(archive.fetch(format!("leaf_{}", i))?.data.as_ref(), format!("Leaf number {}", i).as_bytes());
+		}
+	}
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn open_archive_from_path() -> InternalResult {
+	let path = std::env::temp_dir().join(format!("vach-open-{}.vach", std::process::id()));
+
+	let mut builder = Builder::new();
+	builder.add(b"Opened by path" as &[u8], "greeting")?;
+	builder.dump(File::create(&path)?, &BuilderConfig::default())?;
+
+	let archive = Archive::open(&path)?;
+	assert_eq!(archive.path(), Some(path.as_path()));
+	assert_eq!(archive.fetch("greeting")?.data.as_ref(), b"Opened by path");
+	drop(archive);
+
+	// Failures name the archive
+	std::fs::remove_file(&path)?;
+	match Archive::open(&path) {
+		Err(err @ InternalError::OpenError { .. }) => {
+			assert!(err.to_string().contains(&path.display().to_string()));
+			assert!(matches!(err.root(), InternalError::IOError(io) if io.kind() == std::io::ErrorKind::NotFound));
+		},
+		other => panic!("Expected an open error, found {:?}", other.map(|_| ())),
+	};
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto", feature = "compression"))]
+fn custom_pipelines() -> InternalResult {
+	use std::io::Cursor;
+
+	fn reverse(data: &[u8]) -> InternalResult<Vec<u8>> {
+		Ok(data.iter().rev().copied().collect())
+	}
+
+	const ENCRYPT_FIRST: &[PipelineStage] = &[PipelineStage::Encrypt, PipelineStage::Compress];
+	const REVERSED: &[PipelineStage] = &[
+		PipelineStage::Custom { flag: 0b1, encode: reverse },
+		PipelineStage::Compress,
+	];
+
+	let keypair = crate::crypto_utils::gen_keypair();
+	let text = "Stages run in the given order. ".repeat(64);
+
+	let mut builder = Builder::new();
+	let template = Leaf::default().compress(CompressMode::Always);
+	builder.add_leaf(Leaf::new(text.as_bytes()).id("default").template(&template).encrypt(true))?;
+	builder.add_leaf(
+		Leaf::new(text.as_bytes())
+			.id("encrypt_first")
+			.template(&template)
+			.encrypt(true)
+			.pipeline(ENCRYPT_FIRST),
+	)?;
+	builder.add_leaf(Leaf::new(text.as_bytes()).id("reversed").template(&template).pipeline(REVERSED))?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default().keypair(keypair.clone()))?;
+
+	let config = ArchiveConfig::default().key(keypair.verifying_key());
+	let archive = Archive::with_config(target, &config)?;
+
+	assert!(!archive.fetch_entry_ref("default").unwrap().flags.contains(Flags::PIPELINE_FLAG));
+	assert_eq!(archive.fetch("default")?.data.as_ref(), text.as_bytes());
+
+	let entry = archive.fetch_entry_ref("encrypt_first").unwrap();
+	assert!(entry.flags.contains(Flags::PIPELINE_FLAG | Flags::ENCRYPTED_FLAG | Flags::COMPRESSED_FLAG));
+	assert_eq!(archive.fetch("encrypt_first")?.data.as_ref(), text.as_bytes());
+
+	// Custom stages are flagged, and can't be undone without a decoder
+	assert!(archive.fetch_entry_ref("reversed").unwrap().flags.contains(0b1));
+	match archive.fetch("reversed") {
+		Err(err) => assert!(matches!(err.root(), InternalError::MissingStageError(0b1))),
+		Ok(_) => panic!("Custom stages can't be decoded without a decoder"),
+	};
+
+	// Enabled stages must be placed in the pipeline
+	const COMPRESS_ONLY: &[PipelineStage] = &[PipelineStage::Compress];
+	let mut leaves = [Leaf::new(b"secret" as &[u8]).id("secret").encrypt(true).pipeline(COMPRESS_ONLY)];
+	let result = dump(Cursor::new(Vec::new()), &mut leaves, &BuilderConfig::default().keypair(keypair));
+	assert!(matches!(result.map_err(|e| e.root().to_string()), Err(e) if e.contains("no Encrypt stage")));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn registered_stage_decoders() -> InternalResult {
+	use std::io::Cursor;
+
+	fn delta(data: &[u8]) -> InternalResult<Vec<u8>> {
+		Ok(data.iter().scan(0u8, |prev, b| Some(b.wrapping_sub(std::mem::replace(prev, *b)))).collect())
+	}
+
+	fn undelta(data: &[u8]) -> InternalResult<Vec<u8>> {
+		Ok(data
+			.iter()
+			.scan(0u8, |prev, d| {
+				*prev = prev.wrapping_add(*d);
+				Some(*prev)
+			})
+			.collect())
+	}
+
+	const PIPELINE: &[PipelineStage] = &[
+		PipelineStage::Custom { flag: 0b10, encode: delta },
+		PipelineStage::Compress,
+	];
+
+	let ramp = (0..4096u32).map(|i| (i / 3) as u8).collect::<Vec<_>>();
+	let mut builder = Builder::new();
+	builder.add_leaf(
+		Leaf::new(ramp.as_slice())
+			.id("ramp")
+			.compress(CompressMode::Always)
+			.pipeline(PIPELINE),
+	)?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let mut config = ArchiveConfig::default();
+	assert!(config.register_stage(Flags::COMPRESSED_FLAG, undelta).is_err());
+	assert!(config.register_stage(0b110, undelta).is_err());
+	config.register_stage(0b10, undelta)?;
+
+	let archive = Archive::with_config(target, &config)?;
+	assert_eq!(archive.fetch("ramp")?.data.as_ref(), ramp.as_slice());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn header_capabilities() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Squeeze me" as &[u8]).id("squeezed").compress(CompressMode::Always))?;
+	builder.add(b"Plain" as &[u8], "plain")?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(&mut target)?;
+	assert!(archive.capabilities().contains(Capabilities::COMPRESSION));
+	assert!(!archive.capabilities().contains(Capabilities::ENCRYPTION | Capabilities::PIPELINES));
+	drop(archive);
+
+	// Capabilities from a newer writer are refused upfront
+	let mut bytes = target.into_inner();
+	bytes[13..17].copy_from_slice(&(Capabilities::COMPRESSION | 0b1000_0000).to_le_bytes());
+	match Archive::new(Cursor::new(bytes.as_slice())) {
+		Err(InternalError::MissingCapabilityError(msg)) => assert!(msg.contains("0x80")),
+		other => panic!("Expected a missing capability error, found {:?}", other.map(|_| ())),
+	};
+
+	// As are archives needing a newer loader, even when the rest of the header is understood
+	bytes[13..17].copy_from_slice(&Capabilities::COMPRESSION.to_le_bytes());
+	bytes[9..11].copy_from_slice(&(crate::VERSION + 10).to_le_bytes());
+	assert!(Archive::new(Cursor::new(bytes.as_slice())).is_ok());
+
+	bytes[11..13].copy_from_slice(&(crate::VERSION + 10).to_le_bytes());
+	assert!(matches!(
+		Archive::new(Cursor::new(bytes.as_slice())),
+		Err(InternalError::IncompatibleArchiveVersionError(_))
+	));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn compare_archives() -> InternalResult {
+	use std::io::Cursor;
+	use crate::diff;
+
+	let mut builder = Builder::new();
+	builder.add(b"Stays the same" as &[u8], "same")?;
+	builder.add_leaf(Leaf::new(b"Compressed differently" as &[u8]).id("recompressed"))?;
+	builder.add(b"Old content" as &[u8], "edited")?;
+	builder.add(b"Going away" as &[u8], "removed")?;
+
+	let mut old = Cursor::new(Vec::new());
+	builder.dump(&mut old, &BuilderConfig::default())?;
+
+	let mut builder = Builder::new();
+	builder.add(b"Stays the same" as &[u8], "same")?;
+	builder.add_leaf(
+		Leaf::new(b"Compressed differently" as &[u8])
+			.id("recompressed")
+			.compress(CompressMode::Always),
+	)?;
+	builder.add(b"New content" as &[u8], "edited")?;
+	builder.add(b"Brand new" as &[u8], "added")?;
+
+	let mut new = Cursor::new(Vec::new());
+	builder.dump(&mut new, &BuilderConfig::default())?;
+
+	let (old, new) = (Archive::new(old)?, Archive::new(new)?);
+
+	let delta = diff::compare(&old, &new)?;
+	assert_eq!(delta.added, ["added"]);
+	assert_eq!(delta.removed, ["removed"]);
+	assert_eq!(delta.changed, ["edited"]);
+	assert_eq!(delta.identical, ["recompressed", "same"]);
+	assert!(delta.has_changes());
+
+	let delta = diff::compare(&new, &new)?;
+	assert!(!delta.has_changes());
+	assert_eq!(delta.identical.len(), 4);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn layout_report() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add(b"First leaf" as &[u8], "first")?;
+	builder.add(b"Second leaf" as &[u8], "second")?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target.clone())?;
+	let report = archive.layout_report()?;
+
+	// A fresh archive is tightly packed
+	assert_eq!(report.size, target.get_ref().len() as u64);
+	assert_eq!(report.unused(), 0);
+	assert_eq!(report.regions[0].kind, RegionKind::Header);
+	assert_eq!(report.regions[1].kind, RegionKind::Registry);
+	assert_eq!(report.regions.len(), 4);
+	assert!(report.regions.windows(2).all(|w| w[0].end == w[1].start));
+
+	// Trailing bytes nothing points to
+	let mut bytes = target.into_inner();
+	bytes.extend_from_slice(&[0; 100]);
+
+	let report = Archive::new(Cursor::new(bytes))?.layout_report()?;
+	let last = report.regions.last().unwrap();
+	assert_eq!((last.kind.clone(), last.len()), (RegionKind::Unused, 100));
+	assert_eq!(report.unused(), 100);
+	assert!(report.fragmentation() > 0.0);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "builder", feature = "archive"))]
+fn prefixed_ids() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let ids = [
+		"characters/hero/animations/walk",
+		"characters/hero/animations/idle",
+		"characters/hero/animations/idle_long",
+		"characters/héroïne/portrait",
+		"sfx/drums",
+		"characters/hero",
+	];
+
+	let dump = |prefix_ids: bool| -> InternalResult<Vec<u8>> {
+		let mut builder = Builder::new().template(Leaf::default().sign(true));
+		for id in ids {
+			builder.add(id.as_bytes(), id)?;
+		}
+
+		let mut target = Cursor::new(Vec::new());
+		let config = BuilderConfig::default().keypair(keypair.clone()).prefix_ids(prefix_ids);
+		builder.dump(&mut target, &config)?;
+		Ok(target.into_inner())
+	};
+
+	let (plain, prefixed) = (dump(false)?, dump(true)?);
+	assert!(prefixed.len() < plain.len());
+
+	let config = ArchiveConfig::default().key(keypair.verifying_key());
+	let archive = Archive::with_config(Cursor::new(prefixed.as_slice()), &config)?;
+	assert!(archive.capabilities().contains(Capabilities::PREFIXED_IDS));
+
+	for id in ids {
+		let resource = archive.fetch(id)?;
+		assert!(resource.authenticated);
+		assert_eq!(resource.data.as_ref(), id.as_bytes());
+	}
+
+	// Edits keep the registry prefixed
+	let mut editor = ArchiveEditor::new(archive);
+	editor.signing_key(keypair.clone());
+	editor.rename("sfx/drums", "characters/hero/sfx/drums")?;
+
+	let mut target = Cursor::new(Vec::new());
+	editor.commit(&mut target)?;
+
+	let archive = Archive::with_config(target, &config)?;
+	assert!(archive.capabilities().contains(Capabilities::PREFIXED_IDS));
+	assert_eq!(archive.fetch("characters/hero/sfx/drums")?.data.as_ref(), b"sfx/drums");
+	assert_eq!(archive.entries().len(), ids.len());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn sealed_archives() -> InternalResult {
+	use crate::seal;
+
+	let dir = std::env::temp_dir().join(format!("vach-seal-{}", std::process::id()));
+	let path = dir.with_extension("vach");
+
+	std::fs::create_dir_all(dir.join("scripts"))?;
+	std::fs::write(dir.join("scripts/intro.lua"), b"print('Hello')")?;
+	std::fs::write(dir.join("readme.txt"), b"Sealed")?;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	seal::seal_directory(&dir, &path, &keypair)?;
+	std::fs::remove_dir_all(&dir)?;
+
+	let sealed = seal::open_sealed(&path, keypair.verifying_key())?;
+	assert_eq!(sealed.archive().entries().len(), 2);
+	assert_eq!(sealed.fetch("scripts/intro.lua")?.data.as_ref(), b"print('Hello')");
+	let registry_start = sealed.archive().header.size();
+	drop(sealed);
+
+	// Tampering with the registry breaks the signature, the first entry's content version follows it's flags
+	let mut bytes = std::fs::read(&path)?;
+	bytes[registry_start + 4] ^= 1;
+	std::fs::write(&path, &bytes)?;
+
+	let sealed = seal::open_sealed(&path, keypair.verifying_key())?;
+	let failed = sealed
+		.archive()
+		.iter()
+		.filter(|(id, _)| matches!(sealed.fetch(id), Err(InternalError::UnauthenticatedError(_))))
+		.count();
+	assert_eq!(failed, 1);
+
+	// Archives that aren't sealed are refused
+	let mut builder = Builder::new();
+	builder.add(b"Plain" as &[u8], "plain")?;
+	builder.dump(File::create(&path)?, &BuilderConfig::default())?;
+
+	assert!(matches!(
+		seal::open_sealed(&path, keypair.verifying_key()),
+		Err(InternalError::UnauthenticatedError(_))
+	));
+
+	std::fs::remove_file(&path)?;
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn key_usage_policy() -> InternalResult {
+	use std::{
+		io::Cursor,
+		time::{Duration, SystemTime},
+	};
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let dump = |policy: KeyPolicy| -> InternalResult<Vec<u8>> {
+		let mut builder = Builder::new().template(Leaf::default().encrypt(true));
+		builder.add(b"Event content" as &[u8], "event")?;
+		builder.add(b"More event content" as &[u8], "event-extra")?;
+
+		let mut target = Cursor::new(Vec::new());
+		builder.dump(&mut target, &BuilderConfig::default().keypair(keypair.clone()).key_policy(policy))?;
+		Ok(target.into_inner())
+	};
+
+	// Too many leaves for the key, or an expired key
+	assert!(matches!(
+		dump(KeyPolicy::default().max_leaves(1)),
+		Err(InternalError::InvalidConfigError(_))
+	));
+
+	let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+	assert!(matches!(
+		dump(KeyPolicy::default().expires(yesterday)),
+		Err(InternalError::ExpiredKeyError(_))
+	));
+
+	// The expiry is recorded in the header, with second precision
+	let next_year = SystemTime::now() + Duration::from_secs(365 * 24 * 60 * 60);
+	let bytes = dump(KeyPolicy::default().max_leaves(2).expires(next_year))?;
+
+	let config = ArchiveConfig::default().key(keypair.verifying_key()).enforce_key_expiry(true);
+	let archive = Archive::with_config(Cursor::new(bytes.as_slice()), &config)?;
+
+	let expiry = archive.key_expiry().unwrap();
+	assert!(next_year.duration_since(expiry).unwrap() < Duration::from_secs(1));
+	assert!(!archive.key_expired());
+	assert!(archive.capabilities().contains(Capabilities::EXTENDED_HEADER));
+	assert_eq!(archive.fetch("event-extra")?.data.as_ref(), b"More event content");
+	assert_eq!(archive.layout_report()?.unused(), 0);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn tombstones() -> InternalResult {
+	use std::io::Cursor;
+
+	let dump = |leaves: Vec<Leaf<'static>>| -> InternalResult<Cursor<Vec<u8>>> {
+		let mut builder = Builder::new();
+		leaves.into_iter().try_for_each(|leaf| builder.add_leaf(leaf))?;
+
+		let mut target = Cursor::new(Vec::new());
+		builder.dump(&mut target, &BuilderConfig::default())?;
+		Ok(target)
+	};
+
+	let base = dump(vec![
+		Leaf::new(b"Base a" as &[u8]).id("a"),
+		Leaf::new(b"Base b" as &[u8]).id("dir/b"),
+		Leaf::new(b"Base c" as &[u8]).id("dir/c"),
+	])?;
+	let patch = dump(vec![Leaf::tombstone("a"), Leaf::new(b"Patched b" as &[u8]).id("dir/b"), Leaf::tombstone("dir/c")])?;
+
+	// Tombstones read as missing
+	let patch = Archive::new(patch)?;
+	assert!(patch.fetch_entry("a").unwrap().is_tombstone());
+	assert!(matches!(patch.fetch("a"), Err(InternalError::MissingResourceError(_))));
+	assert_eq!(patch.list("")?, vec!["dir"]);
+
+	let overlay = Overlay::new(Archive::new(base)?).layer(patch);
+	assert!(matches!(overlay.fetch("a"), Err(InternalError::MissingResourceError(_))));
+	assert_eq!(overlay.fetch("dir/b")?.data.as_ref(), b"Patched b");
+	assert_eq!(overlay.ids(), vec!["dir/b"]);
+	assert_eq!(overlay.list("dir")?, vec!["dir/b"]);
+	assert!(!overlay.exists("dir/c"));
+
+	// Compacting the patch leaves only live entries
+	let mut editor = ArchiveEditor::new(overlay.into_layers().pop().unwrap());
+	assert_eq!(editor.compact(), 2);
+
+	let mut target = Cursor::new(Vec::new());
+	editor.commit(&mut target)?;
+
+	let compacted = Archive::new(target)?;
+	assert_eq!(compacted.entries().len(), 1);
+	assert_eq!(compacted.fetch("dir/b")?.data.as_ref(), b"Patched b");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn residency_hints() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Boot splash" as &[u8]).id("splash").hint(ResidencyHint::Preload))?;
+	builder.add_leaf(Leaf::new(b"Soundtrack" as &[u8]).id("music").hint(ResidencyHint::Stream))?;
+	builder.add_leaf(Leaf::new(b"Level" as &[u8]).id("level").flags(Flags::from_bits(0b1)))?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	assert_eq!(archive.fetch_entry("splash").unwrap().hint(), ResidencyHint::Preload);
+	assert_eq!(archive.fetch_entry("music").unwrap().hint(), ResidencyHint::Stream);
+
+	// The hint doesn't disturb custom flags
+	let level = archive.fetch_entry("level").unwrap();
+	assert_eq!(level.hint(), ResidencyHint::OnDemand);
+	assert!(level.flags.contains(0b1));
+	assert_eq!(archive.fetch("music")?.data.as_ref(), b"Soundtrack");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn dependency_graph() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Rock mesh" as &[u8]).id("models/rock.obj").depends_on(["materials/rock.mat"]))?;
+	builder.add_leaf(Leaf::new(b"Rock material" as &[u8]).id("materials/rock.mat").depends_on([
+		"textures/rock.png",
+		"textures/rock-normal.png",
+		"models/rock.obj",
+	]))?;
+	builder.add(b"Rock texture" as &[u8], "textures/rock.png")?;
+	builder.add(b"Rock normals" as &[u8], "textures/rock-normal.png")?;
+	builder.add(b"Tree" as &[u8], "models/tree.obj")?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	assert!(archive.fetch_entry_ref(DEPENDENCIES_ID).is_some());
+	assert_eq!(archive.dependencies("models/rock.obj")?, vec!["materials/rock.mat"]);
+	assert!(archive.dependencies("models/tree.obj")?.is_empty());
+	assert!(archive.dependencies("models/missing.obj").is_err());
+
+	// Nearest first, the cycle back to the mesh is only followed once
+	let closure = archive.closure("models/rock.obj")?;
+	assert_eq!(
+		closure,
+		vec!["models/rock.obj", "materials/rock.mat", "textures/rock.png", "textures/rock-normal.png"]
+	);
+
+	let resources = archive.fetch_batch(&closure);
+	assert_eq!(resources[3].as_ref().unwrap().data.as_ref(), b"Rock normals");
+
+	// The reserved `ID` can't be used by other leaves
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::default().id("a").depends_on(["b"]))?;
+	assert!(matches!(
+		builder.add(b"" as &[u8], DEPENDENCIES_ID),
+		Err(InternalError::InvalidIdError(..))
+	));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn reserved_namespace() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Rock" as &[u8]).id("rock.png"))?;
+	builder.add_leaf(Leaf::new(b"Harbor" as &[u8]).id("harbor.map").depends_on(["rock.png"]))?;
+
+	// Leaves can't be added under the reserved namespace
+	let reserved = |result: InternalResult| matches!(result, Err(InternalError::InvalidIdError(..)));
+	assert!(reserved(builder.add(b"" as &[u8], "vach::x")));
+	assert!(reserved(dump(Cursor::new(Vec::new()), &mut [Leaf::default().id("vach::x")], &BuilderConfig::default()).map(|_| ())));
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	// Reserved entries are left out of listings, but can still be fetched
+	assert_eq!(archive.entries().len(), 2);
+	assert!(archive.iter().all(|(id, _)| !id.is_reserved()));
+
+	let reserved_ids = archive.reserved().map(|(id, _)| id.as_str()).collect::<Vec<_>>();
+	assert_eq!(reserved_ids, [DEPENDENCIES_ID]);
+	assert!(archive.fetch(DEPENDENCIES_ID).is_ok());
+
+	// The editor keeps them, without letting them be changed
+	let mut editor = ArchiveEditor::new(archive);
+	assert!(reserved(editor.rename("rock.png", "vach::rock.png")));
+	assert!(matches!(editor.rename(DEPENDENCIES_ID, "dependencies"), Err(InternalError::MissingResourceError(_))));
+
+	let mut edited = Cursor::new(Vec::new());
+	editor.commit(&mut edited)?;
+
+	let archive = Archive::new(edited)?;
+	assert_eq!(archive.entries().len(), 2);
+	assert_eq!(archive.dependencies("harbor.map")?, ["rock.png"]);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn query_selection() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(vec![7u8; 4096]), "textures/rock.png")?;
+	builder.add(Cursor::new(vec![7u8; 16]), "textures/ui/icon.png")?;
+	builder.add_leaf(Leaf::new(Cursor::new(vec![7u8; 2048])).id("scripts/main.lua").version(3))?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	let ids = |expr: &str| -> InternalResult<Vec<String>> {
+		Ok(archive.select(expr)?.into_iter().map(|e| e.id.to_string()).collect())
+	};
+
+	assert_eq!(ids(r#"id ~ "textures/*""#)?, vec!["textures/rock.png"]);
+	assert_eq!(ids(r#"id ~ "textures/**""#)?, vec!["textures/rock.png", "textures/ui/icon.png"]);
+	assert_eq!(ids(r#"id ~ "*/???k.*""#)?, vec!["textures/rock.png"]);
+	assert_eq!(ids("size >= 2KiB && version == 3")?, vec!["scripts/main.lua"]);
+	assert_eq!(ids(r#"!(size > 1KB || id == "scripts/main.lua")"#)?, vec!["textures/ui/icon.png"]);
+	assert_eq!(ids("decoded_size < 0x20 || flag(COMPRESSED)")?, vec!["textures/ui/icon.png"]);
+
+	for invalid in ["size >", "size ~ 1", r#"id > "a""#, "flag(NOPE)", "size > 1XB", "(size > 1", r#"id == "a"#] {
+		assert!(matches!(archive.select(invalid), Err(InternalError::ParseError(_))), "{}", invalid);
+	}
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn namespaces() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new().template(Leaf::default().namespace("dlc1"));
+	builder.add_leaf(Leaf::new(b"Core harbor" as &[u8]).id("maps/harbor.map").namespace("core"))?;
+	builder.add(b"DLC harbor" as &[u8], "maps/harbor.map")?;
+	builder.add(b"DLC boat" as &[u8], "models/boat.obj")?;
+	builder.add_leaf(Leaf::new(b"Shared" as &[u8]).id("readme.txt"))?;
+
+	// Collisions are checked on the qualified `ID`
+	assert!(builder.add(b"Again" as &[u8], "models/boat.obj").is_err());
+	assert!(builder.add_leaf(Leaf::default().id("a").namespace("bad::name")).is_err());
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	assert_eq!(archive.namespaces(), vec!["core", "dlc1"]);
+	assert!(archive.fetch_entry_ref("dlc1::models/boat.obj").is_some());
+
+	let dlc = archive.namespace("dlc1");
+	assert_eq!(dlc.name(), "dlc1");
+	assert_eq!(dlc.ids(), vec!["maps/harbor.map", "models/boat.obj"]);
+	assert_eq!(dlc.fetch("maps/harbor.map")?.data.as_ref(), b"DLC harbor");
+	assert!(dlc.fetch("readme.txt").is_err());
+
+	let core = archive.namespace("core");
+	assert_eq!(core.fetch_batch(["maps/harbor.map"])[0].as_ref().unwrap().data.as_ref(), b"Core harbor");
+	assert_eq!(core.fetch_entry_ref("maps/harbor.map").unwrap().id.namespace(), Some("core"));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn access_profile() -> InternalResult {
+	use std::io::Cursor;
+
+	let build = |config: &BuilderConfig| -> InternalResult<Cursor<Vec<u8>>> {
+		let mut builder = Builder::new();
+		for name in ["a", "b", "c", "d"] {
+			builder.add(Cursor::new(name.repeat(64)), name)?;
+		}
+
+		let mut target = Cursor::new(Vec::new());
+		builder.dump(&mut target, config)?;
+		Ok(target)
+	};
+
+	let archive = Archive::with_config(build(&BuilderConfig::default())?, &ArchiveConfig::default().record_access(true))?;
+	for id in ["c", "a", "c", "d"] {
+		archive.fetch(id)?;
+	}
+
+	// Only the first fetch of each entry is recorded
+	let profile = AccessProfile::from_bytes(&archive.take_access_profile().to_bytes())?;
+	let order = profile.accesses.iter().map(|a| a.id.as_ref()).collect::<Vec<_>>();
+	assert_eq!(order, vec!["c", "a", "d"]);
+	assert!(archive.access_profile().accesses.is_empty());
+	assert!(AccessProfile::from_bytes(b"VPRF\x01\x00").is_err());
+
+	// Profiled leaves come first, in the order they were fetched
+	let archive = Archive::new(build(&BuilderConfig::default().layout_from_profile(&profile))?)?;
+	let mut laid_out = archive.entries().values().collect::<Vec<_>>();
+	laid_out.sort_by_key(|e| e.location);
+
+	let laid_out = laid_out.iter().map(|e| e.id.as_ref()).collect::<Vec<_>>();
+	assert_eq!(laid_out, vec!["c", "a", "d", "b"]);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression"))]
+fn fetch_many_write() -> InternalResult {
+	use std::io::{Cursor, Write};
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(b"first"), "a")?;
+	builder.add_leaf(Leaf::new(Cursor::new(b"second".repeat(256))).id("b").compress(CompressMode::Always))?;
+	builder.add(Cursor::new(b"third"), "c")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	let (mut a, mut b, mut c) = (Vec::new(), Vec::new(), Vec::new());
+
+	// Out of order, with a missing entry in between
+	let results = archive.fetch_many_write(&mut [
+		("c", &mut c as &mut dyn Write),
+		("missing", &mut std::io::sink()),
+		("b", &mut b),
+		("a", &mut a),
+	]);
+
+	assert_eq!(results[0].as_ref().ok(), Some(&5));
+	assert!(matches!(results[1], Err(InternalError::MissingResourceError(_))));
+	assert_eq!(results[2].as_ref().ok(), Some(&(6 * 256)));
+	assert_eq!((a.as_slice(), c.as_slice()), (b"first".as_slice(), b"third".as_slice()));
+	assert_eq!(b, b"second".repeat(256));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression", feature = "crypto"))]
+fn empty_leaves() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut config = BuilderConfig::default();
+	config.load_keypair(KEYPAIR.as_slice())?;
+
+	let custom = Flags::from_bits(CUSTOM_FLAG_1);
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(Cursor::new([])).id("plain").flags(custom))?;
+	builder.add_leaf(Leaf::new(Cursor::new([])).id("compressed").compress(CompressMode::Always))?;
+	builder.add_leaf(Leaf::new(Cursor::new([])).id("detect").compress(CompressMode::Detect))?;
+	builder.add_leaf(Leaf::new(Cursor::new([])).id("encrypted").encrypt(true))?;
+	builder.add_leaf(Leaf::new(Cursor::new([])).id("signed").sign(true))?;
+	builder.add_leaf(Leaf::placeholder("reserved/patch"))?;
+	builder.dump(&mut target, &config)?;
+
+	let mut config = ArchiveConfig::default();
+	config.load_public_key(&KEYPAIR[crate::SECRET_KEY_LENGTH..])?;
+	let archive = Archive::with_config(target, &config)?;
+
+	for id in ["plain", "compressed", "detect", "encrypted", "signed", "reserved/patch"] {
+		let resource = archive.fetch(id)?;
+		assert!(resource.data.is_empty(), "{}", id);
+	}
+
+	assert!(archive.fetch("plain")?.flags.contains(CUSTOM_FLAG_1));
+	assert!(archive.fetch("signed")?.authenticated);
+
+	let placeholder = archive.fetch_entry_ref("reserved/patch").unwrap();
+	assert!(placeholder.is_placeholder());
+	assert!(!archive.fetch_entry_ref("compressed").unwrap().is_placeholder());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn validity_windows() -> InternalResult {
+	use std::{
+		io::Cursor,
+		time::{Duration, SystemTime, UNIX_EPOCH},
+	};
+
+	let at = |seconds: u64| UNIX_EPOCH + Duration::from_secs(seconds);
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(b"Always"), "always")?;
+	builder.add_leaf(Leaf::new(Cursor::new(b"Winter")).id("winter").valid_from(at(1_000)).valid_until(at(2_000)))?;
+	builder.add_leaf(Leaf::new(Cursor::new(b"Future")).id("future").valid_from(at(u32::MAX as u64 * 4)))?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	// Windows are readable, but not enforced, by default
+	let archive = Archive::new(target)?;
+	assert_eq!(archive.validity("always")?, Validity::default());
+	assert_eq!(
+		archive.validity("winter")?,
+		Validity {
+			valid_from: Some(at(1_000)),
+			valid_until: Some(at(2_000))
+		}
+	);
+	assert_eq!(archive.fetch("future")?.data.as_ref(), b"Future");
+
+	let target = archive.into_inner().unwrap();
+	let archive = Archive::with_config(target, &ArchiveConfig::default().enforce_validity(|| UNIX_EPOCH + Duration::from_secs(1_500)))?;
+	assert_eq!(archive.fetch("winter")?.data.as_ref(), b"Winter");
+	assert!(archive.fetch("always").is_ok());
+	assert!(matches!(archive.fetch("future"), Err(InternalError::OutsideValidityError(_))));
+
+	let target = archive.into_inner().unwrap();
+	let archive = Archive::with_config(target, &ArchiveConfig::default().enforce_validity(SystemTime::now))?;
+	assert!(matches!(archive.fetch("winter"), Err(InternalError::OutsideValidityError(_))));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn format_core_parses_archives() -> InternalResult {
+	use std::io::Cursor;
+	use crate::format::{header::RawHeader, registry::RawEntry};
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(b"Hello"), "greetings/hello")?;
+	builder.add(Cursor::new(b"World"), "greetings/world")?;
+	builder.dump(&mut target, &BuilderConfig::default().prefix_ids(true))?;
+
+	// Only slices, no IO
+	let bytes = target.into_inner();
+	let header = RawHeader::decode(bytes[..RawHeader::BASE_SIZE].try_into().unwrap());
+	assert_eq!(header.capabilities & Capabilities::PREFIXED_IDS, Capabilities::PREFIXED_IDS);
+
+	let mut rest = &bytes[header.size()..];
+	let mut previous = String::new();
+
+	for _ in 0..header.capacity {
+		let (entry, used) = RawEntry::decode(rest, Some(&previous))?;
+		let data = &bytes[entry.location as usize..(entry.location + entry.offset) as usize];
+
+		match entry.id.as_str() {
+			"greetings/hello" => assert_eq!(data, b"Hello"),
+			"greetings/world" => assert_eq!(data, b"World"),
+			id => panic!("Unexpected entry: {}", id),
+		}
+
+		// Re-encoding yields the same bytes
+		assert_eq!(entry.encode(Some(&previous), false)?, &rest[..used]);
+
+		previous = entry.id;
+		rest = &rest[used..];
+	}
+
+	assert!(matches!(RawEntry::decode(&[0; 8], None), Err(crate::format::Error::Truncated)));
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn parse_entry_points_reject_garbage() -> InternalResult {
+	use std::io::Cursor;
+	use crate::format::{parse_header, parse_registry, header::capabilities};
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(b"Some data"), "some/data")?;
+	builder.add(Cursor::new(b"More data"), "some/more")?;
+	builder.dump(&mut target, &BuilderConfig::default().prefix_ids(true))?;
+
+	let bytes = target.into_inner();
+	let parse = |bytes: &[u8]| -> Result<usize, crate::format::Error> {
+		let (header, size) = parse_header(bytes)?;
+		let prefixed = header.capabilities & capabilities::PREFIXED_IDS != 0;
+		Ok(parse_registry(bytes.get(size..).unwrap_or_default(), header.capacity, prefixed)?.len())
+	};
+
+	assert_eq!(parse(&bytes), Ok(2));
+
+	// Every truncation of the header and registry fails cleanly
+	let (header, size) = parse_header(&bytes).unwrap();
+	let registry_end = size + header.capacity as usize * 128;
+	for len in 0..registry_end.min(bytes.len()) {
+		let _ = parse(&bytes[..len]);
+	}
+
+	// As does garbage, and corrupted archives
+	let mut state = 0x2545_F491_4F6C_DD1Du64;
+	let mut next = || {
+		state ^= state << 13;
+		state ^= state >> 7;
+		state ^= state << 17;
+		state
+	};
+
+	for _ in 0..2048 {
+		let garbage = (0..next() % 256).map(|_| next() as u8).collect::<Vec<_>>();
+		let _ = parse(&garbage);
+
+		let mut corrupted = bytes.clone();
+		let at = next() as usize % corrupted.len();
+		corrupted[at] ^= next() as u8;
+		let _ = parse(&corrupted);
+	}
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "builder", feature = "archive"))]
+fn verify_stream() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let mut config = BuilderConfig::default();
+	config.load_keypair(KEYPAIR.as_slice())?;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(Cursor::new(b"Signed level data")).id("signed").sign(true))?;
+	builder.add_leaf(Leaf::new(Cursor::new(b"Unsigned readme")).id("unsigned"))?;
+	builder.add_leaf(Leaf::placeholder("placeholder"))?;
+	builder.dump(&mut target, &config)?;
+
+	let archive_config = ArchiveConfig::default().key(keypair.verifying_key());
+	let archive = Archive::with_config(target, &archive_config)?;
+
+	let mut calls = Vec::new();
+	let report = archive.verify_stream(|entry, progress| calls.push((entry.id.to_string(), *progress)));
+
+	assert!(report.is_ok());
+	assert_eq!((report.authenticated, report.unauthenticated), (1, 2));
+	assert_eq!(report.bytes, (b"Signed level data".len() + b"Unsigned readme".len()) as u64);
+
+	// Called once per entry, in order of location
+	assert_eq!(calls.len(), 3);
+	assert_eq!(calls.last().unwrap().1.entries_done, 3);
+	assert_eq!(calls.last().unwrap().1.bytes_done, calls.last().unwrap().1.bytes_total);
+
+	// Tamper with the signed leaf
+	let location = archive.fetch_entry("signed").unwrap().location as usize;
+	let mut bytes = archive.into_inner().unwrap().into_inner();
+	bytes[location] ^= 0xFF;
+
+	let archive = Archive::with_config(Cursor::new(bytes), &archive_config)?;
+	let report = archive.verify_stream(|_, _| {});
+
+	assert!(!report.is_ok());
+	assert_eq!(report.failures.len(), 1);
+	assert!(matches!(report.failures[0].root(), InternalError::UnauthenticatedError(id) if id.as_ref() == "signed"));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "builder", feature = "archive"))]
+fn editor_commit_in_place() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let mut config = BuilderConfig::default();
+	config.load_keypair(KEYPAIR.as_slice())?;
+
+	let mut source = Cursor::new(Vec::<u8>::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Signed" as &[u8]).id("signed").sign(true).version(1))?;
+	builder.add_leaf(Leaf::new(b"Unsigned" as &[u8]).id("unsigned"))?;
+	builder.dump(&mut source, &config)?;
+
+	let original = source.get_ref().clone();
+	let archive_config = ArchiveConfig::default().key(keypair.verifying_key());
+
+	let mut editor = ArchiveEditor::new(Archive::with_config(source, &archive_config)?);
+	editor.signing_key(keypair.clone());
+	editor.set_content_version("signed", 7)?;
+	editor.set_flags("unsigned", Flags::from_bits(0b1000_0000_0000_0000))?;
+	assert!(matches!(editor.set_flags("unsigned", Flags::from_bits(Flags::COMPRESSED_FLAG)), Err(InternalError::RestrictedFlagAccessError)));
+	assert!(editor.set_content_version("missing", 1).is_err());
+
+	let source = editor.commit_in_place()?;
+
+	// Leaves were left untouched, only the registry changed
+	let registry_end = Archive::new(Cursor::new(original.clone()))?.entries().values().map(|e| e.location).min().unwrap() as usize;
+	assert_eq!(source.get_ref().len(), original.len());
+	assert_eq!(&source.get_ref()[registry_end..], &original[registry_end..]);
+
+	let archive = Archive::with_config(source, &archive_config)?;
+	let signed = archive.fetch("signed")?;
+	assert_eq!(signed.content_version, 7);
+	assert!(signed.authenticated);
+
+	let unsigned = archive.fetch("unsigned")?;
+	assert!(unsigned.flags.contains(0b1000_0000_0000_0000));
+	assert_eq!(unsigned.data.as_ref(), b"Unsigned");
+
+	// Renames change the registry's size
+	let mut editor = ArchiveEditor::new(archive);
+	editor.rename("unsigned", "renamed")?;
+	assert!(matches!(editor.commit_in_place(), Err(InternalError::InvalidConfigError(_))));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn path_mapper_round_trip() -> InternalResult {
+	use std::{
+		fs,
+		io::Cursor,
+		path::{Path, PathBuf},
+	};
+
+	// Flat IDs, with directories spelled out as `.`
+	struct Dotted;
+
+	impl PathMapper for Dotted {
+		fn to_id(&self, path: &Path) -> InternalResult<String> {
+			Ok(SeparatorMapper.to_id(path)?.replace('/', "."))
+		}
+
+		fn to_path(&self, id: &str) -> InternalResult<PathBuf> {
+			SeparatorMapper.to_path(&id.replace('.', "/"))
+		}
+	}
+
+	let root = std::env::temp_dir().join(format!("vach-path-mapper-{}", std::process::id()));
+	let source = root.join("source");
+	fs::create_dir_all(source.join("textures/ui"))?;
+	fs::write(source.join("textures/ui/button"), b"Button")?;
+	fs::write(source.join("readme"), b"Readme")?;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	assert_eq!(builder.add_dir_recursive(&source, None, &Dotted)?, 2);
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	assert_eq!(archive.fetch("textures.ui.button")?.data.as_ref(), b"Button");
+
+	// The same mapper restores the original layout
+	assert_eq!(archive.unpack_to(root.join("dotted"), &Dotted)?, 2);
+	assert_eq!(fs::read(root.join("dotted/textures/ui/button"))?, b"Button");
+
+	assert_eq!(archive.unpack_to(root.join("flat"), &SeparatorMapper)?, 2);
+	assert_eq!(fs::read(root.join("flat/textures.ui.button"))?, b"Button");
+
+	// IDs can't escape the target directory
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(b"Escape"), "../escape")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	assert!(matches!(archive.unpack_to(root.join("escape"), &SeparatorMapper), Err(InternalError::ParseError(_))));
+	assert!(!root.join("escape").exists());
+
+	fs::remove_dir_all(root)?;
+	Ok(())
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn release_manifest() -> InternalResult {
+	use std::fs;
+	use crate::release::ReleaseManifest;
+
+	let dir = std::env::temp_dir().join(format!("vach-release-{}", std::process::id()));
+	fs::create_dir_all(dir.join("dlc"))?;
+	fs::write(dir.join("base.vach"), b"Base archive")?;
+	fs::write(dir.join("dlc/winter.vach"), b"Winter archive")?;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let mut manifest = ReleaseManifest::create("1.2.0", &dir, &["base.vach", "dlc/winter.vach"])?;
+	assert_eq!(manifest.archives[1].path, "dlc/winter.vach");
+	assert_eq!(manifest.archives[1].size, 14);
+
+	// Unsigned manifests never verify
+	assert!(matches!(manifest.verify(&dir, &keypair.verifying_key()), Err(InternalError::ReleaseMismatchError(_))));
+
+	manifest.sign(&keypair);
+	let parsed = ReleaseManifest::from_bytes(&manifest.to_bytes())?;
+	assert_eq!(parsed, manifest);
+	parsed.verify(&dir, &keypair.verifying_key())?;
+
+	// Another key, an edited manifest, and a swapped archive are all caught
+	let other = crate::crypto_utils::gen_keypair();
+	assert!(parsed.verify(&dir, &other.verifying_key()).is_err());
+
+	let mut renamed = parsed.clone();
+	renamed.name = "1.3.0".to_string();
+	assert!(renamed.verify(&dir, &keypair.verifying_key()).is_err());
+
+	fs::write(dir.join("dlc/winter.vach"), b"Summer archive")?;
+	assert!(matches!(parsed.verify(&dir, &keypair.verifying_key()), Err(InternalError::ReleaseMismatchError(_))));
+
+	// Paths can't leave the release's directory, and garbage doesn't parse
+	assert!(ReleaseManifest::create("1.2.0", &dir, &["../base.vach"]).is_err());
+	assert!(ReleaseManifest::from_bytes(&manifest.to_bytes()[..40]).is_err());
+
+	fs::remove_dir_all(dir)?;
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn throttled_archive() -> InternalResult {
+	use std::{io::Cursor, time::Instant};
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(vec![7u8; 1000]), "chunk")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = ThrottledArchive::new(Archive::new(target)?);
+	let limit = RateLimit {
+		bytes_per_second: 10_000,
+		burst: 1000,
+	};
+
+	archive.set_limit(Priority::Background, Some(limit));
+	assert_eq!(archive.limit(Priority::Background), Some(limit));
+	assert_eq!(archive.limit(Priority::Foreground), None);
+
+	// The first read fits the burst, the next three wait for about 100ms each
+	let start = Instant::now();
+	for _ in 0..4 {
+		assert_eq!(archive.fetch("chunk", Priority::Background)?.data.len(), 1000);
+	}
+	assert!(start.elapsed().as_millis() >= 250);
+
+	// Foreground reads draw from their own, unlimited, bucket
+	let start = Instant::now();
+	for _ in 0..4 {
+		archive.fetch("chunk", Priority::Foreground)?;
+	}
+	assert!(start.elapsed().as_millis() < 250);
+
+	assert!(matches!(archive.fetch("missing", Priority::Background), Err(InternalError::MissingResourceError(_))));
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn testing_harness() -> InternalResult {
+	use std::{
+		io::{Cursor, ErrorKind},
+		time::Duration,
+	};
+	use crate::testing::{CountingWriter, FaultyReader, SlowReader};
+
+	let mut target = CountingWriter::new(Cursor::new(Vec::new()));
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(b"Hello"), "hello")?;
+	builder.add(Cursor::new(b"World"), "world")?;
+	assert_eq!(builder.dump(&mut target, &BuilderConfig::default())?, 10);
+
+	assert!(target.writes() > 0);
+	let bytes = target.into_inner().into_inner();
+
+	// A fault within a leaf only fails fetches of that leaf
+	let archive = Archive::new(Cursor::new(bytes.clone()))?;
+	let location = archive.fetch_entry("world").unwrap().location;
+
+	let source = FaultyReader::new(Cursor::new(bytes.clone())).fail_at(location + 2, ErrorKind::ConnectionReset);
+	let archive = Archive::with_config(source, &ArchiveConfig::default().read_buffer(0))?;
+
+	assert_eq!(archive.fetch("hello")?.data.as_ref(), b"Hello");
+	assert!(matches!(archive.fetch("world").unwrap_err().root(), InternalError::IOError(err) if err.kind() == ErrorKind::ConnectionReset));
+
+	// Short reads are handled
+	let source = SlowReader::new(Cursor::new(bytes), Duration::ZERO).max_read(3);
+	let archive = Archive::new(source)?;
+	assert_eq!(archive.fetch("world")?.data.as_ref(), b"World");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression", feature = "crypto"))]
+fn fetch_write() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::gen_keypair();
+	let data = b"streamed".repeat(4096);
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(data.as_slice()).id("plain").version(7))?;
+	builder.add_leaf(Leaf::new(data.as_slice()).id("compressed").compress(CompressMode::Always))?;
+	builder.add_leaf(Leaf::new(data.as_slice()).id("sealed").encrypt(true).sign(true))?;
+	builder.dump(&mut target, &BuilderConfig::default().keypair(keypair.clone()))?;
+
+	let archive = Archive::with_config(target, &ArchiveConfig::default().key(keypair.verifying_key()))?;
+
+	for id in ["plain", "compressed", "sealed"] {
+		let mut output = Vec::new();
+		let (flags, content_version, verified) = archive.fetch_write(id, &mut output)?;
+		let resource = archive.fetch(id)?;
+
+		assert_eq!(output, data);
+		assert_eq!((flags, content_version, verified), (resource.flags, resource.content_version, resource.authenticated));
+	}
+
+	assert!(archive.fetch_write("sealed", std::io::sink())?.2);
+	assert!(matches!(archive.fetch_write("missing", std::io::sink()), Err(InternalError::MissingResourceError(_))));
+
+	// Write failures are reported as such
+	let mut full = [0u8; 16];
+	let err = archive.fetch_write("plain", full.as_mut_slice()).unwrap_err();
+	assert!(matches!(err, InternalError::EntryError { stage: Stage::Write, .. }));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn memory_gate() -> InternalResult {
+	use std::io::Cursor;
+
+	fn gate(size: usize) -> bool {
+		size <= 1024
+	}
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(vec![1u8; 512]), "small")?;
+	builder.add(Cursor::new(vec![2u8; 4096]), "large")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::with_config(target, &ArchiveConfig::default().memory_gate(gate))?;
+	assert_eq!(archive.fetch("small")?.data.len(), 512);
+
+	match archive.fetch("large") {
+		Err(InternalError::MemoryBudgetError(id, size)) => assert_eq!((id.as_ref(), size), ("large", 4096)),
+		result => panic!("The memory gate was not consulted: {:?}", result.map(|r| r.data.len())),
+	}
+
+	// Streaming needs no buffer for the whole entry, so it's not gated
+	assert_eq!(archive.fetch_write("large", std::io::sink())?.1, 0);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn tagged_groups() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"grass".as_slice()).id("grass.png").tag("level1").tag("level2").tag("level1"))?;
+	builder.add_leaf(Leaf::new(b"lava".as_slice()).id("lava.png").tag("level2"))?;
+	builder.add_leaf(Leaf::new(b"menu".as_slice()).id("menu.png"))?;
+	builder.add_leaf(Leaf::tombstone("old.png").tag("level1"))?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Archive::new(target)?;
+	assert_eq!(archive.groups()?, vec!["level1", "level2"]);
+	assert_eq!(archive.tags("grass.png")?, vec!["level1", "level2"]);
+	assert!(archive.tags("menu.png")?.is_empty());
+
+	// Tombstones are left out of groups
+	let level = archive.fetch_group("level1")?;
+	assert_eq!(level.iter().map(|(id, r)| (id.as_ref(), r.data.as_ref())).collect::<Vec<_>>(), vec![("grass.png", b"grass".as_slice())]);
+
+	assert_eq!(archive.group("level2")?.len(), 2);
+	assert!(archive.group("unknown")?.is_empty());
+
+	let directory = std::env::temp_dir().join("vach-unpack-group-test");
+	assert_eq!(archive.unpack_group("level2", &directory, &SeparatorMapper)?, 2);
+	assert!(!directory.join("menu.png").exists());
+	std::fs::remove_dir_all(directory)?;
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "compression"))]
+fn dump_report() -> InternalResult {
+	use std::io::Cursor;
+
+	let text = b"Lorem ipsum dolor sit amet ".repeat(512);
+
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(text.as_slice()).id("lz4").compress(CompressMode::Always))?;
+	builder.add_leaf(Leaf::new(text.as_slice()).id("snappy").compress(CompressMode::Always).compression_algo(CompressionAlgorithm::Snappy))?;
+	builder.add_leaf(Leaf::new(b"plain".as_slice()).id("plain"))?;
+
+	let mut target = Cursor::new(Vec::new());
+	let report = builder.dump_report(&mut target, &BuilderConfig::default())?;
+
+	assert_eq!(report.bytes_read, text.len() as u64 * 2 + 5);
+	// The capabilities are patched into the header once all leaves are written
+	assert!(report.bytes_written > target.get_ref().len() as u64);
+	assert!(report.leaf_bytes < report.bytes_read && report.write_amplification() < 1.0);
+	assert!(report.seeks >= 3);
+
+	assert_eq!(report.algorithms["LZ4"].decoded, text.len() as u64);
+	assert_eq!(report.algorithms["Snappy"].leaves, 1);
+	assert_eq!(report.algorithms["None"].stored, 5);
+	assert_eq!(report.algorithms.values().map(|t| t.stored).sum::<u64>(), report.leaf_bytes);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn data_offset() -> InternalResult {
+	use std::io::Cursor;
+
+	let build = |offset| -> InternalResult<Vec<u8>> {
+		let mut target = Cursor::new(Vec::new());
+		let mut builder = Builder::new();
+		builder.add(Cursor::new(b"Hello"), "hello")?;
+		builder.add(Cursor::new(b"World"), "world")?;
+		builder.dump(&mut target, &BuilderConfig::default().data_offset(offset))?;
+
+		Ok(target.into_inner())
+	};
+
+	let bytes = build(4096)?;
+	let archive = Archive::new(Cursor::new(bytes.as_slice()))?;
+
+	let first = archive.iter().map(|(_, entry)| entry.location).min().unwrap();
+	assert_eq!(first, 4096);
+	assert_eq!(bytes.len(), 4096 + 10);
+	assert_eq!(archive.fetch("world")?.data.as_ref(), b"World");
+
+	// The reserved gap is zeroed, and reported as unused
+	let layout = archive.layout_report()?;
+	let registry_end = layout.regions.iter().find(|r| matches!(r.kind, RegionKind::Registry)).unwrap().end as usize;
+	assert!(bytes[registry_end..4096].iter().all(|b| *b == 0));
+	assert_eq!(layout.unused(), 4096 - registry_end as u64);
+
+	// The header and registry must fit
+	assert!(matches!(build(8), Err(InternalError::InvalidConfigError(_))));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn extract_entry_to_file() -> InternalResult {
+	use std::io::Cursor;
+
+	let directory = std::env::temp_dir().join("vach-extract-test");
+	std::fs::create_dir_all(&directory)?;
+
+	let data = (0..256 * 1024).map(|i| (i % 253) as u8).collect::<Vec<_>>();
+	let path = directory.join("source.vach");
+
+	let mut builder = Builder::new();
+	builder.add(Cursor::new(data.clone()), "stored")?;
+	builder.add(Cursor::new(Vec::new()), "empty")?;
+	#[cfg(feature = "compression")]
+	builder.add_leaf(Leaf::new(Cursor::new(data.clone())).id("compressed").compress(CompressMode::Always))?;
+	builder.dump(File::create(&path)?, &BuilderConfig::default())?;
+
+	let archive = Archive::open(&path)?;
+	let output = directory.join("output");
+
+	assert_eq!(archive.extract_entry_to_file("stored", &output)?, data.len() as u64);
+	assert_eq!(std::fs::read(&output)?, data);
+
+	assert_eq!(archive.extract_entry_to_file("empty", &output)?, 0);
+	assert!(std::fs::read(&output)?.is_empty());
+
+	#[cfg(feature = "compression")]
+	{
+		assert_eq!(archive.extract_entry_to_file("compressed", &output)?, data.len() as u64);
+		assert_eq!(std::fs::read(&output)?, data);
+	}
+
+	// The archive's cursor is unaffected
+	assert_eq!(archive.fetch("stored")?.data.as_ref(), data.as_slice());
+	assert!(matches!(archive.extract_entry_to_file("missing", &output), Err(InternalError::MissingResourceError(_))));
+
+	std::fs::remove_dir_all(directory)?;
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "compression", feature = "crypto"))]
+fn store_mode() -> InternalResult {
+	use crate::crypto_utils::{gen_keypair, read_keypair};
+	use std::io::Cursor;
+
+	let keypair_bytes = gen_keypair().to_keypair_bytes();
+	let config = BuilderConfig::default().keypair(read_keypair(&keypair_bytes as &[u8])?);
+
+	// Store overrides the encryption and signing inherited from the template
+	let template = Leaf::default().encrypt(true).sign(true).compress(CompressMode::Always);
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(Cursor::new(b"Stored as is".to_vec())).template(&template).id("stored").compress(CompressMode::Store))?;
+	builder.add_leaf(Leaf::new(Cursor::new(b"Processed".to_vec())).template(&template).id("processed"))?;
+
+	let mut target = Cursor::new(Vec::new());
+	let report = builder.dump_report(&mut target, &config)?;
+	assert_eq!((report.stages.compress.is_zero(), report.algorithms["None"].leaves), (false, 1));
+
+	let mut archive_config = ArchiveConfig::default();
+	archive_config.load_public_key(&keypair_bytes[32..])?;
+	let archive = Archive::with_config(target, &archive_config)?;
+
+	let stored = archive.fetch_entry("stored").unwrap();
+	let processed = Flags::COMPRESSED_FLAG | Flags::ENCRYPTED_FLAG | Flags::SIGNED_FLAG;
+	assert_eq!(stored.flags.bits() & processed, 0);
+	assert_eq!(stored.offset, 12);
+	assert_eq!(archive.fetch_entry("processed").unwrap().flags.bits() & processed, processed);
+
+	assert_eq!(archive.fetch("stored")?.data.as_ref(), b"Stored as is");
+	assert_eq!(archive.fetch("processed")?.data.as_ref(), b"Processed");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn background_reads() -> InternalResult {
+	use std::{
+		future::Future,
+		io::Cursor,
+		sync::Arc,
+		task::{Context, Poll, Wake},
+		thread::{self, Thread},
+	};
+
+	// A minimal executor, parking the thread until the read wakes it
+	struct Unpark(Thread);
+
+	impl Wake for Unpark {
+		fn wake(self: Arc<Self>) {
+			self.0.unpark();
+		}
+	}
+
+	fn block_on<F: Future>(future: F) -> F::Output {
+		let waker = Arc::new(Unpark(thread::current())).into();
+		let mut cx = Context::from_waker(&waker);
+		let mut future = std::pin::pin!(future);
+
+		loop {
+			match future.as_mut().poll(&mut cx) {
+				Poll::Ready(output) => return output,
+				Poll::Pending => thread::park(),
+			}
+		}
+	}
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::default();
+	builder.add(b"Packed poem" as &[u8], "poem.txt")?;
+	builder.dump(&mut target, &BuilderConfig::default())?;
+
+	let archive = Arc::new(Archive::new(target)?);
+	assert_eq!(block_on(read_in_background(archive.clone(), "poem.txt"))?, b"Packed poem");
+	assert!(matches!(
+		block_on(read_in_background(archive, "missing.txt")),
+		Err(InternalError::MissingResourceError(_))
+	));
+
+	let loose = Arc::new(DirectoryVfs::new("test_data"));
+	assert_eq!(block_on(read_in_background(loose, "poem.txt"))?, std::fs::read("test_data/poem.txt")?);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn pack_set_shares_leaves() -> InternalResult {
+	let directory = std::env::temp_dir().join("vach-pack-set-test");
+	std::fs::create_dir_all(&directory)?;
+
+	let variant = |shaders: &'static [u8], extra: Option<&'static [u8]>| -> InternalResult<Builder<'static>> {
+		let mut builder = Builder::new();
+		builder.add(b"Shared music" as &[u8], "music")?;
+		builder.add(b"Shared level" as &[u8], "levels/1")?;
+		builder.add(shaders, "shaders")?;
+		if let Some(extra) = extra {
+			builder.add(extra, "extra")?;
+		}
+
+		Ok(builder)
+	};
+
+	let mut set = PackSet::new().common("common.vach");
+	set.add("windows.vach", variant(b"DirectX shaders", Some(b"Only on windows"))?)?;
+	set.add("linux.vach", variant(b"Vulkan shaders", None)?)?;
+	set.add("mac.vach", variant(b"Vulkan shaders", None)?)?;
+	assert!(set.add("mac.vach", Builder::new()).is_err());
+
+	let report = set.dump(|name| Ok(File::create(directory.join(name))?), &BuilderConfig::default())?;
+	assert_eq!(report.shared, ["levels/1", "music", "shaders"]);
+	assert_eq!(report.saved, (12 + 12) * 2 + 14);
+	assert_eq!(report.archives.len(), 4);
+
+	let open = |name: &str| Archive::open(directory.join(name));
+
+	let common = open("common.vach")?;
+	assert_eq!(common.shared_archive(), None);
+	assert_eq!(common.fetch("shaders")?.data.as_ref(), b"Vulkan shaders");
+
+	// Shared leaves are only referenced, the overlay resolves them
+	let windows = open("windows.vach")?;
+	assert_eq!(windows.shared_archive(), Some("common.vach"));
+	assert!(windows.fetch_entry("music").is_none());
+
+	let windows = Overlay::with_shared(windows, open)?;
+	assert_eq!(windows.fetch("music")?.data.as_ref(), b"Shared music");
+	assert_eq!(windows.fetch("shaders")?.data.as_ref(), b"DirectX shaders");
+	assert_eq!(windows.fetch("extra")?.data.as_ref(), b"Only on windows");
+
+	let linux = Overlay::with_shared(open("linux.vach")?, open)?;
+	assert_eq!(linux.fetch("shaders")?.data.as_ref(), b"Vulkan shaders");
+	assert_eq!(linux.ids().len(), 3);
+
+	std::fs::remove_dir_all(directory)?;
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn separate_key_roles() -> InternalResult {
+	use crate::crypto_utils::gen_keypair;
+	use std::io::Cursor;
+
+	let keypair = gen_keypair();
+	let encryption_key = EncryptionKey::generate();
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Secret and sealed" as &[u8]).id("both").encrypt(true).sign(true))?;
+	builder.add_leaf(Leaf::new(b"Only sealed" as &[u8]).id("signed").sign(true))?;
+	builder.dump(&mut target, &BuilderConfig::default().keypair(keypair.clone()).encryption_key(encryption_key))?;
+
+	let public = KeyId::of_public_key(&keypair.verifying_key());
+	let private = KeyId::of_encryption_key(&encryption_key);
+	assert_ne!(public, private);
+
+	// Decrypting doesn't imply authenticity
+	let archive = Archive::with_config(target.clone(), &ArchiveConfig::default().decryption_key(encryption_key))?;
+	let resource = archive.fetch("both")?;
+	assert_eq!(resource.data.as_ref(), b"Secret and sealed");
+	assert_eq!((resource.verified_with, resource.decrypted_with), (None, Some(private)));
+	assert!(!resource.authenticated);
+
+	// Verifying doesn't allow decrypting
+	let archive = Archive::with_config(target.clone(), &ArchiveConfig::default().key(keypair.verifying_key()))?;
+	assert!(archive.fetch("both").is_err());
+	let resource = archive.fetch("signed")?;
+	assert_eq!((resource.verified_with, resource.decrypted_with), (Some(public), None));
+
+	let config = ArchiveConfig::default().key(keypair.verifying_key()).decryption_key(encryption_key);
+	let resource = Archive::with_config(target, &config)?.fetch("both")?;
+	assert_eq!((resource.verified_with, resource.decrypted_with), (Some(public), Some(private)));
+
+	// Encryption works without a keypair too
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Secret" as &[u8]).id("secret").encrypt(true))?;
+	builder.dump(&mut target, &BuilderConfig::default().encryption_key(encryption_key))?;
+
+	let archive = Archive::with_config(target, &ArchiveConfig::default().decryption_key(encryption_key))?;
+	assert_eq!(archive.fetch("secret")?.data.as_ref(), b"Secret");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn timestamp_tokens() -> InternalResult {
+	use crate::{
+		crypto_utils::gen_keypair,
+		timestamp::{TimestampAuthority, TimestampVerifier, CONTENT_HASH_LENGTH},
+	};
+	use ed25519_dalek::{Signer, Verifier};
+	use std::{
+		io::Cursor,
+		time::{Duration, SystemTime, UNIX_EPOCH},
+	};
+
+	// Stands in for an RFC 3161 authority: the token is the attested time and a signature over it and the hash
+	struct Authority(SigningKey);
+
+	impl TimestampAuthority for Authority {
+		fn max_token_len(&self) -> usize {
+			128
+		}
+
+		fn timestamp(&self, hash: &[u8; CONTENT_HASH_LENGTH]) -> InternalResult<Vec<u8>> {
+			let time = 1_700_000_000u64.to_le_bytes();
+			let signature = self.0.sign(&[hash.as_slice(), &time].concat());
+			Ok([time.as_slice(), &signature.to_bytes()].concat())
+		}
+	}
+
+	impl TimestampVerifier for Authority {
+		fn verify(&self, hash: &[u8; CONTENT_HASH_LENGTH], token: &[u8]) -> InternalResult<SystemTime> {
+			let invalid = || InternalError::TimestampError("Invalid token".to_string());
+			let (time, signature) = token.split_first_chunk::<8>().ok_or_else(invalid)?;
+			let signature = Signature::from_slice(signature).map_err(|_| invalid())?;
+
+			self.0
+				.verifying_key()
+				.verify(&[hash.as_slice(), time].concat(), &signature)
+				.map_err(|_| invalid())?;
+
+			Ok(UNIX_EPOCH + Duration::from_secs(u64::from_le_bytes(*time)))
+		}
+	}
+
+	let authority = Authority(gen_keypair());
+	let build = |config: &BuilderConfig| -> InternalResult<Vec<u8>> {
+		let mut target = Cursor::new(Vec::new());
+		let mut builder = Builder::new();
+		builder.add(b"Released before the leak" as &[u8], "notes")?;
+		builder.add(Cursor::new(vec![7u8; 4096]), "blob")?;
+		builder.dump(&mut target, config)?;
+		Ok(target.into_inner())
+	};
+
+	let config = BuilderConfig::default().timestamp_authority(&authority).data_offset(1024);
+	let bytes = build(&config)?;
+
+	let archive = Archive::new(Cursor::new(bytes.clone()))?;
+	assert_eq!(archive.timestamp_token().map(|t| t.len()), Some(72));
+	assert_eq!(archive.verify_timestamp(&authority)?, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+	assert_eq!(archive.fetch("notes")?.data.as_ref(), b"Released before the leak");
+
+	// Compressed registries are hashed where they lie, after the leaves
+	#[cfg(feature = "compression")]
+	{
+		let config = BuilderConfig::default().timestamp_authority(&authority).compress_registry(CompressionAlgorithm::LZ4, 0);
+		let archive = Archive::new(Cursor::new(build(&config)?))?;
+		assert!(archive.verify_timestamp(&authority).is_ok());
+	}
+
+	// Any change to the content breaks the token
+	let mut tampered = bytes;
+	*tampered.last_mut().unwrap() ^= 1;
+	let archive = Archive::new(Cursor::new(tampered))?;
+	assert!(matches!(archive.verify_timestamp(&authority), Err(InternalError::TimestampError(_))));
+
+	let archive = Archive::new(Cursor::new(build(&BuilderConfig::default())?))?;
+	assert!(archive.timestamp_token().is_none());
+	assert!(matches!(archive.verify_timestamp(&authority), Err(InternalError::TimestampError(_))));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "multithreaded", feature = "compression", feature = "builder", feature = "archive"))]
+fn verify_parallel() -> InternalResult {
+	use std::{io::Cursor, sync::Mutex};
+
+	let mut target = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+
+	for i in 0..16 {
+		let data = format!("Level {} ", i).repeat(64);
+		let leaf = Leaf::new(Cursor::new(data)).id(format!("level-{}", i));
+		builder.add_leaf(leaf.compress(CompressMode::Always).compression_algo(CompressionAlgorithm::LZ4))?;
+	}
+
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	// Decodes the same entries as the sequential walk, calling progress once per entry
+	let calls = Mutex::new(0);
+	let report = archive.verify_parallel(4, |_, _| *calls.lock().unwrap() += 1);
+	let sequential = archive.verify_stream(|_, _| {});
+
+	assert!(report.is_ok());
+	assert_eq!((report.unauthenticated, report.bytes), (sequential.unauthenticated, sequential.bytes));
+	assert_eq!(*calls.lock().unwrap(), 16);
+
+	// Mangle a compressed leaf
+	let entry = archive.fetch_entry("level-7").unwrap();
+	let mut bytes = archive.into_inner().unwrap().into_inner();
+	let range = entry.location as usize..(entry.location + entry.offset) as usize;
+	bytes[range].iter_mut().for_each(|b| *b ^= 0xA5);
+
+	let report = Archive::new(Cursor::new(bytes))?.verify_parallel(3, |_, _| {});
+
+	assert_eq!((report.unauthenticated, report.failures.len(), report.undecodable()), (15, 1, 1));
+	assert!(report.failures[0].to_string().contains("level-7"));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "crypto", feature = "compression"))]
+fn guardrail() -> InternalResult {
+	use std::io::Cursor;
+
+	const ENCRYPT_FIRST: &[PipelineStage] = &[PipelineStage::Encrypt, PipelineStage::Compress];
+
+	let keypair = crate::crypto_utils::gen_keypair();
+	let text = "Encrypted data doesn't shrink. ".repeat(64);
+
+	let dump = |guardrail: Guardrail| {
+		let mut builder = Builder::new();
+		let leaf = Leaf::new(Cursor::new(text.clone()))
+			.id("encrypt_first")
+			.compress(CompressMode::Always)
+			.encrypt(true)
+			.pipeline(ENCRYPT_FIRST);
+
+		builder.add_leaf(leaf)?;
+		builder.add_leaf(Leaf::new(Cursor::new(text.clone())).id("fine").compress(CompressMode::Always))?;
+
+		let config = BuilderConfig::default().keypair(keypair.clone()).guardrail(guardrail);
+		builder.dump_report(Cursor::new(Vec::new()), &config)
+	};
+
+	// Ciphertext is caught before any work is done, then grows when compressed
+	let report = dump(Guardrail::Warn)?;
+	let names = report.warnings.iter().map(|w| (w.id().as_ref(), w.name())).collect::<Vec<_>>();
+	assert_eq!(
+		names,
+		[
+			("encrypt_first", "compressed-after-encryption"),
+			("encrypt_first", "compression-grew")
+		]
+	);
+
+	assert!(dump(Guardrail::Allow)?.warnings.is_empty());
+
+	match dump(Guardrail::Deny) {
+		Err(err) => assert!(matches!(err.root(), InternalError::InvalidConfigError(_))),
+		Ok(_) => panic!("Compressing after encrypting must be refused"),
+	}
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto", feature = "compression"))]
+fn recompress_streams() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::gen_keypair();
+	let text = "Recompressed without seeking. ".repeat(128);
+
+	let mut builder = Builder::new();
+	let template = Leaf::default().compress(CompressMode::Never);
+	builder.add_leaf(
+		Leaf::new(Cursor::new(text.clone()))
+			.template(&template)
+			.id("level")
+			.version(3)
+			.flags(Flags::from_bits(CUSTOM_FLAG_1))
+			.depends_on(["secret"])
+			.tag("level1"),
+	)?;
+	builder.add_leaf(Leaf::new(Cursor::new(text.clone())).template(&template).id("secret").encrypt(true).sign(true))?;
+	builder.add_leaf(Leaf::placeholder("later"))?;
+
+	let mut source = Cursor::new(Vec::new());
+	let config = BuilderConfig::default().keypair(keypair.clone());
+	builder.dump(&mut source, &config)?;
+
+	// Slices can't seek, same as a pipe
+	let archive_config = ArchiveConfig::default().key(keypair.verifying_key());
+	let mut target = Vec::new();
+	let report = recompress(
+		source.get_ref().as_slice(),
+		&mut target,
+		&archive_config,
+		CompressMode::Always,
+		CompressionAlgorithm::LZ4,
+		&config,
+	)?;
+
+	assert!(report.leaf_bytes < source.get_ref().len() as u64);
+
+	let archive = Archive::with_config(Cursor::new(target), &archive_config)?;
+	let level = archive.fetch_entry("level").unwrap();
+	assert!(level.flags.contains(Flags::LZ4_COMPRESSED) && level.flags.contains(CUSTOM_FLAG_1));
+	assert_eq!(level.content_version, 3);
+	assert_eq!(archive.dependencies("level")?, [EntryId::from_raw("secret")]);
+	assert_eq!(archive.tags("level")?, ["level1"]);
+
+	let secret = archive.fetch("secret")?;
+	assert!(secret.authenticated && secret.flags.contains(Flags::ENCRYPTED_FLAG) && secret.flags.contains(Flags::LZ4_COMPRESSED));
+	assert_eq!(secret.data.as_ref(), text.as_bytes());
+	assert!(archive.fetch_entry("later").unwrap().is_placeholder());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn poisoned_handle() -> InternalResult {
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "hello")?;
+
+	let mut target = std::io::Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	// A thread panicking while holding the handle doesn't take the archive down with it
+	let _ = std::thread::scope(|s| {
+		s.spawn(|| {
+			let _guard = archive.handle.lock();
+			panic!("Poisoning the handle");
+		})
+		.join()
+	});
+
+	assert!(archive.handle.is_poisoned());
+	assert_eq!(archive.fetch("hello")?.data.as_ref(), b"Hello, Cassandra!");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn id_index() -> InternalResult {
+	use std::io::{Cursor, Seek, SeekFrom};
+	use crate::global::{header::Header, reg_entry::RegistryEntry};
+
+	for prefix_ids in [false, true] {
+		let mut builder = Builder::new();
+		for idx in 0..500 {
+			builder.add_leaf(Leaf::new(Cursor::new(idx.to_string())).id(format!("levels/{}/map", idx)).tag("maps"))?;
+		}
+
+		let mut target = Cursor::new(Vec::new());
+		let config = BuilderConfig::default().id_index(true).prefix_ids(prefix_ids);
+		builder.dump(&mut target, &config)?;
+
+		// Read the registry in order, as a loader without a hash map would
+		let archive = Archive::new(target.clone())?;
+		target.seek(SeekFrom::Start(Header::BASE_SIZE as u64))?;
+
+		let mut registry: Vec<RegistryEntry> = Vec::new();
+		for _ in 0..archive.entries().len() {
+			let previous = registry.last().filter(|_| prefix_ids).map(|e| e.id.to_string());
+			let previous = previous.as_deref().or(prefix_ids.then_some(""));
+			registry.push(RegistryEntry::from_handle(&mut target, previous)?);
+		}
+
+		let index = archive.id_index()?.unwrap();
+		for (position, entry) in registry.iter().enumerate() {
+			assert_eq!(index.position(&entry.id), Some(position));
+		}
+
+		// Misses land on an empty slot or an entry with another ID
+		let miss = index.position("levels/500/map");
+		assert!(miss.is_none_or(|position| registry[position].id.as_ref() != "levels/500/map"));
+	}
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn footer() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "hello")?;
+	builder.add(b"Goodbye, Cassandra!" as &[u8], "goodbye")?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default().footer(true))?;
+	let bytes = target.into_inner();
+
+	assert_eq!(Archive::new(Cursor::new(bytes.clone()))?.fetch("hello")?.data.as_ref(), b"Hello, Cassandra!");
+
+	// Truncation is caught on open, before any entry is fetched
+	let truncated = Cursor::new(bytes[..bytes.len() - 3].to_vec());
+	assert!(matches!(Archive::new(truncated), Err(InternalError::FooterMismatchError(_))));
+
+	// Appended to other data, eg: an executable, the archive is found from the end
+	let mut executable = b"\x7fELF and then some".repeat(64);
+	executable.extend_from_slice(&bytes);
+
+	let archive = Archive::from_trailing(Cursor::new(executable), &ArchiveConfig::default())?;
+	assert_eq!(archive.fetch("goodbye")?.data.as_ref(), b"Goodbye, Cassandra!");
+
+	// Edits keep the footer in step with the header
+	let mut editor = ArchiveEditor::new(archive);
+	editor.rename("hello", "greetings/hello")?;
+
+	let mut edited = Cursor::new(Vec::new());
+	editor.commit(&mut edited)?;
+
+	let archive = Archive::new(edited)?;
+	assert_eq!(archive.fetch("greetings/hello")?.data.as_ref(), b"Hello, Cassandra!");
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn sink_writer() -> InternalResult {
+	use std::{
+		io::{self, Cursor},
+		sync::{Arc, Mutex},
+	};
+
+	// Records every range handed over, like an upload placing parts by offset
+	type Ranges = Vec<(u64, Vec<u8>)>;
+
+	#[derive(Default, Clone)]
+	struct Upload(Arc<Mutex<Ranges>>);
+
+	impl DumpSink for Upload {
+		fn write_at(&mut self, offset: u64, data: &[u8]) -> io::Result<()> {
+			self.0.lock().unwrap().push((offset, data.to_vec()));
+			Ok(())
+		}
+	}
+
+	struct Broken;
+
+	impl DumpSink for Broken {
+		fn write_at(&mut self, _: u64, _: &[u8]) -> io::Result<()> {
+			Err(io::Error::new(io::ErrorKind::ConnectionReset, "The CDN hung up"))
+		}
+	}
+
+	let leaves = || {
+		let mut builder = Builder::new();
+		for idx in 0..64 {
+			builder.add(Cursor::new(format!("Chunk {} ", idx).repeat(100)), format!("chunks/{}", idx)).unwrap();
+		}
+
+		builder
+	};
+
+	let upload = Upload::default();
+	let mut writer = SinkWriter::new(upload.clone(), 1024, 2);
+	leaves().dump(&mut writer, &BuilderConfig::default())?;
+	writer.finish()?;
+
+	// Leaf data streams out in order, only the header and registry are written again
+	let ranges = upload.0.lock().unwrap().clone();
+	let streamed = ranges.windows(2).take_while(|w| w[1].0 > w[0].0).count() + 1;
+	assert!(streamed > 1);
+	assert!(ranges[..streamed].iter().all(|(_, data)| data.len() <= 1024));
+
+	let mut bytes = Vec::new();
+	for (offset, data) in ranges {
+		let end = offset as usize + data.len();
+		bytes.resize(bytes.len().max(end), 0);
+		bytes[offset as usize..end].copy_from_slice(&data);
+	}
+
+	let archive = Archive::new(Cursor::new(bytes))?;
+	assert_eq!(archive.entries().len(), 64);
+	assert_eq!(archive.fetch("chunks/42")?.data.as_ref(), "Chunk 42 ".repeat(100).as_bytes());
+
+	// The sink's own error surfaces through the dump
+	let mut writer = SinkWriter::new(Broken, 1024, 2);
+	let err = leaves().dump(&mut writer, &BuilderConfig::default()).unwrap_err();
+	assert!(matches!(err.root(), InternalError::IOError(err) if err.kind() == io::ErrorKind::ConnectionReset));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn signature_domain() -> InternalResult {
+	use std::io::Cursor;
+	use crate::global::header::Header;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let strict = ArchiveConfig::default().key(keypair.verifying_key()).legacy_signatures(false);
+
+	let pack = |build_id: [u8; 16]| -> InternalResult<Vec<u8>> {
+		let mut builder = Builder::new();
+		builder.add_leaf(Leaf::new(b"Signed, sealed, delivered" as &[u8]).id("x").sign(true))?;
+
+		let mut target = Cursor::new(Vec::new());
+		builder.dump(&mut target, &BuilderConfig::default().keypair(keypair.clone()).build_id(build_id))?;
+		Ok(target.into_inner())
+	};
+
+	let (first, mut second) = (pack([1; 16])?, pack([2; 16])?);
+	let archive = Archive::with_config(Cursor::new(first.clone()), &strict)?;
+	assert_eq!(archive.build_id(), Some([1; 16]));
+	assert!(archive.fetch("x")?.authenticated);
+
+	// Both archives share a layout, so the signature of "x" can be moved from one to the other. It doesn't carry over
+	let signature = archive.fetch_entry("x").unwrap().signature.unwrap().to_bytes();
+	let position = first.windows(signature.len()).position(|w| w == signature).unwrap();
+	second[position..position + signature.len()].copy_from_slice(&signature);
+
+	let archive = Archive::with_config(Cursor::new(second), &strict)?;
+	assert!(!archive.fetch("x")?.authenticated);
+
+	// Drop the build ID and re-sign the entry, as archives were signed before
+	let mut archive = Archive::new(Cursor::new(first))?;
+	archive.header.extensions.retain(|(tag, _)| *tag != Header::BUILD_ID);
+
+	let mut editor = ArchiveEditor::new(archive);
+	editor.signing_key(keypair.clone()).rename("x", "y")?;
+
+	let mut legacy = Cursor::new(Vec::new());
+	editor.commit(&mut legacy)?;
+
+	let lenient = ArchiveConfig::default().key(keypair.verifying_key());
+	let archive = Archive::with_config(Cursor::new(legacy.get_ref().clone()), &lenient)?;
+	assert_eq!(archive.build_id(), None);
+	assert!(archive.fetch("y")?.authenticated);
+
+	let archive = Archive::with_config(legacy, &strict)?;
+	assert!(!archive.fetch("y")?.authenticated);
+
+	// Re-signing migrates it
+	let mut editor = ArchiveEditor::new(archive);
+	assert_eq!(editor.signing_key(keypair).resign(), 1);
+
+	let mut migrated = Cursor::new(Vec::new());
+	editor.commit(&mut migrated)?;
+
+	let archive = Archive::with_config(migrated, &strict)?;
+	assert!(archive.build_id().is_some());
+	assert!(archive.fetch("y")?.authenticated);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn legacy_archive() -> InternalResult {
+	use std::io::Cursor;
+	use ed25519_dalek::Signer;
+	use crate::format::{header::RawHeader, registry::RawEntry};
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let leaves: [(&str, &[u8], u32); 2] = [
+		("hello", b"Hello, Cassandra!", Flags::SIGNED_FLAG),
+		("stray", b"Not a pipeline", Flags::PIPELINE_FLAG | CUSTOM_FLAG_1),
+	];
+
+	// Written the way archives were before the current spec version
+	let mut entries = leaves
+		.iter()
+		.map(|(id, data, flags)| RawEntry {
+			id: id.to_string(),
+			flags: *flags,
+			offset: data.len() as u64,
+			signature: (flags & Flags::SIGNED_FLAG != 0).then_some([0; crate::SIGNATURE_LENGTH]),
+			..RawEntry::default()
+		})
+		.collect::<Vec<_>>();
+
+	let registry_size = entries.iter().map(|e| e.encode_legacy(false).unwrap().len()).sum::<usize>();
+	let mut location = (RawHeader::LEGACY_SIZE + registry_size) as u64;
+
+	for (entry, (_, data, _)) in entries.iter_mut().zip(leaves) {
+		entry.location = location;
+		location += data.len() as u64;
+
+		if entry.signature.is_some() {
+			let message = [data, entry.encode_legacy(true)?.as_slice()].concat();
+			entry.signature = Some(keypair.sign(&message).to_bytes());
+		}
+	}
+
+	let header = RawHeader {
+		magic: *crate::DEFAULT_MAGIC,
+		flags: Flags::SIGNED_FLAG,
+		arch_version: crate::LEGACY_VERSION,
+		min_loader_version: crate::LEGACY_VERSION,
+		capabilities: 0,
+		capacity: entries.len() as u16,
+		extensions: Vec::new(),
+	};
+
+	let mut bytes = header.encode();
+	for entry in &entries {
+		bytes.extend_from_slice(&entry.encode_legacy(false)?);
+	}
+
+	for (_, data, _) in leaves {
+		bytes.extend_from_slice(data);
+	}
+
+	let lenient = ArchiveConfig::default().key(keypair.verifying_key());
+	let archive = Archive::with_config(Cursor::new(bytes.clone()), &lenient)?;
+	assert_eq!(archive.fetch_entry("hello").unwrap().decoded_size, 17);
+
+	let resource = archive.fetch("hello")?;
+	assert_eq!(resource.data.as_ref(), b"Hello, Cassandra!");
+	assert!(resource.authenticated);
+
+	// The pipeline bit meant nothing back then
+	assert_eq!(archive.fetch_entry("stray").unwrap().flags.bits(), CUSTOM_FLAG_1);
+	assert_eq!(archive.fetch("stray")?.data.as_ref(), b"Not a pipeline");
+
+	let strict = ArchiveConfig::default().key(keypair.verifying_key()).legacy_signatures(false);
+	assert!(!Archive::with_config(Cursor::new(bytes.clone()), &strict)?.fetch("hello")?.authenticated);
+
+	#[cfg(feature = "async")]
+	{
+		let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+		let archive = runtime.block_on(crate::archive::AsyncArchive::with_config(Cursor::new(bytes.clone()), &lenient))?;
+		assert!(runtime.block_on(archive.fetch("hello"))?.authenticated);
+	}
+
+	// The registry can't be rewritten in place, committing upgrades it
+	let err = ArchiveEditor::new(Archive::new(Cursor::new(bytes.clone()))?).commit_in_place().unwrap_err();
+	assert!(matches!(err, InternalError::InvalidConfigError(_)));
+
+	let mut editor = ArchiveEditor::new(archive);
+	editor.signing_key(keypair.clone());
+
+	let mut upgraded = Cursor::new(Vec::new());
+	editor.commit(&mut upgraded)?;
+
+	let archive = Archive::with_config(upgraded, &lenient)?;
+	assert_eq!(archive.header.arch_version, crate::VERSION);
+	assert!(archive.fetch("hello")?.authenticated);
+	assert_eq!(archive.fetch("stray")?.data.as_ref(), b"Not a pipeline");
+
+	Ok(())
+}
+
+#[test]
+fn symbolic_flags() -> InternalResult {
+	let flags = "COMPRESSED|lz4 | 0b1000_0000_0000".parse::<Flags>()?;
+	assert_eq!(flags.bits(), Flags::COMPRESSED_FLAG | Flags::LZ4_COMPRESSED | CUSTOM_FLAG_1);
+	assert_eq!(format!("{:#}", flags), "COMPRESSED|LZ4|0x800");
+	assert_eq!(format!("{}", flags), "Flags[C--]");
+
+	// Every bit round trips, named or not
+	for bits in [0, u32::MAX, Flags::RESIDENCY_HINT_MASK | CUSTOM_FLAG_4, 35984] {
+		let flags = Flags::from_bits(bits);
+		assert_eq!(format!("{:#}", flags).parse::<Flags>()?, flags);
+	}
+
+	assert_eq!("".parse::<Flags>()?, Flags::empty());
+	assert!(matches!("SIGNED|SEALED".parse::<Flags>(), Err(InternalError::ParseError(_))));
+	assert!("0x1_0000_0000".parse::<Flags>().is_err());
+
+	#[cfg(feature = "serde")]
+	{
+		let json = serde_json::to_string(&Flags::from_bits(Flags::SIGNED_FLAG | CUSTOM_FLAG_2)).unwrap();
+		assert_eq!(json, "\"SIGNED|0x400\"");
+
+		let flags = serde_json::from_str::<Vec<Flags>>("[\"signed|0x400\", 1024]").unwrap();
+		assert_eq!(flags, [Flags::from_bits(Flags::SIGNED_FLAG | CUSTOM_FLAG_2), Flags::from_bits(CUSTOM_FLAG_2)]);
+	}
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn background_verification() -> InternalResult {
+	use std::{io::Cursor, sync::Arc, time::Duration};
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+
+	let mut builder = Builder::new();
+	for idx in 0..16 {
+		let leaf = Leaf::new(Cursor::new(format!("Level {} ", idx).repeat(512))).id(format!("levels/{}", idx));
+		builder.add_leaf(leaf.sign(true))?;
+	}
+	builder.add(b"Not signed" as &[u8], "unsigned")?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default().keypair(keypair.clone()))?;
+	let mut bytes = target.into_inner();
+
+	// Tamper with one leaf
+	let config = ArchiveConfig::default().key(keypair.verifying_key());
+	let location = Archive::with_config(Cursor::new(bytes.clone()), &config)?.fetch_entry("levels/7").unwrap().location;
+	bytes[location as usize] ^= 1;
+
+	let archive = Arc::new(Archive::with_config(Cursor::new(bytes.clone()), &config)?);
+	assert_eq!(archive.is_verified("levels/3"), None);
+
+	let verifier = archive.clone().verify_in_background(Duration::from_micros(200), Duration::from_micros(50));
+	assert_eq!(verifier.total(), 16);
+	verifier.wait();
+
+	assert_eq!(archive.is_verified("levels/3"), Some(true));
+	assert_eq!(archive.is_verified("levels/7"), Some(false));
+	assert_eq!(archive.is_verified("unsigned"), None);
+
+	// Fetches go by the recorded result
+	assert!(archive.fetch("levels/3")?.authenticated);
+	assert!(!archive.fetch("levels/7")?.authenticated);
+
+	// Results don't carry over to an entry that moved or was re-signed
+	let verified = archive.verified().unwrap();
+	let mut moved = archive.fetch_entry("levels/3").unwrap();
+	assert_eq!(verified.get(&moved), Some(true));
+	moved.location += 1;
+	assert_eq!(verified.get(&moved), None);
+
+	// Without a key there is nothing to check against
+	let unkeyed = Arc::new(Archive::new(Cursor::new(bytes))?);
+	assert!(unkeyed.clone().verify_in_background(Duration::ZERO, Duration::ZERO).is_finished());
+	assert_eq!(unkeyed.is_verified("levels/3"), None);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(feature = "builder")]
+fn dump_metrics() -> InternalResult {
+	use std::io::Cursor;
+	use crate::metrics::{JsonLines, Metrics, Reporter};
+
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "hello")?;
+	builder.add(b"Goodbye" as &[u8], "goodbye")?;
+
+	let report = builder.dump_report(Cursor::new(Vec::new()), &BuilderConfig::default())?;
+	let mut metrics = report.metrics("pack \"release\"");
+
+	assert_eq!((metrics.bytes_in, metrics.entries), (24, 2));
+	assert_eq!(metrics.ratio(), report.write_amplification());
+	assert_eq!(metrics.extra["leaf_bytes"], 24.0);
+	assert!(metrics.extra.contains_key("stage.compress"));
+
+	// Each run is a line of JSON, measurements that aren't numbers are written as null
+	metrics.extra.insert("undefined".to_string(), f64::NAN);
+
+	let mut reporter = JsonLines::new(Vec::new());
+	reporter.report(&metrics)?;
+	reporter.report(&Metrics::new("unpack"))?;
+
+	let output = String::from_utf8(reporter.into_inner()).unwrap();
+	let runs = output
+		.lines()
+		.map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+		.collect::<Vec<_>>();
+
+	assert_eq!(runs.len(), 2);
+	assert_eq!(runs[0]["operation"], "pack \"release\"");
+	assert_eq!(runs[0]["bytes_out"], report.bytes_written);
+	assert!(runs[0]["extra"]["undefined"].is_null());
+	assert_eq!(runs[1]["ratio"], 0.0);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn archive_locks() -> InternalResult {
+	use std::time::{Duration, UNIX_EPOCH};
+	use crate::lock::{ArchiveLock, LockMode, LockedFile};
+
+	let path = std::env::temp_dir().join(format!("vach-archive-locks-{}.vach", std::process::id()));
+	let lock_path = ArchiveLock::lock_path(&path);
+
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "hello")?;
+	builder.dump(File::create(&path)?, &BuilderConfig::default())?;
+
+	// Readers share, writers exclude everyone
+	{
+		let mut archive = Archive::new(LockedFile::open(&path, LockMode::Shared)?)?;
+		let _reader = ArchiveLock::try_shared(&path)?;
+
+		assert_eq!(archive.fetch_mut("hello")?.data.as_ref(), b"Hello, Cassandra!");
+		assert!(matches!(ArchiveLock::try_exclusive(&path), Err(InternalError::LockedError(p)) if p == lock_path));
+		assert!(ArchiveLock::acquire(&path, LockMode::Exclusive, Some(Duration::from_millis(30))).is_err());
+	}
+
+	let writer = ArchiveLock::try_exclusive(&path)?;
+	assert!(ArchiveLock::try_shared(&path).is_err());
+	assert_eq!(ArchiveLock::owner(&path)?.map(|o| o.pid), Some(std::process::id()));
+	assert_eq!(writer.recovered(), None);
+
+	// Released writers clear their record
+	drop(writer);
+	assert_eq!(ArchiveLock::owner(&path)?, None);
+
+	// A writer that crashed leaves it's record behind, which the next writer reclaims
+	std::fs::write(&lock_path, "4242 1700000000")?;
+	let writer = ArchiveLock::exclusive(&path)?;
+	let recovered = writer.recovered().unwrap();
+
+	assert_eq!(recovered.pid, 4242);
+	assert_eq!(recovered.acquired, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+	drop(writer);
+
+	std::fs::remove_file(&path)?;
+	std::fs::remove_file(&lock_path)?;
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn format_descriptor() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "hello")?;
+
+	let mut described = Cursor::new(Vec::new());
+	builder.dump(&mut described, &BuilderConfig::default().format_descriptor(true))?;
+
+	let archive = Archive::new(described)?;
+	let descriptor = archive.format_descriptor()?;
+
+	assert_eq!(descriptor, FormatDescriptor::current());
+	assert!(descriptor.writer.as_ref().unwrap().starts_with("vach "));
+	assert_eq!(descriptor.codec("brotli"), Some(Flags::BROTLI_COMPRESSED));
+	assert_eq!(descriptor.extension("build_id"), Some(crate::format::header::extensions::BUILD_ID));
+
+	// Archives without one are described from their header
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "hello")?;
+
+	let mut plain = Cursor::new(Vec::new());
+	builder.dump(&mut plain, &BuilderConfig::default())?;
+
+	let descriptor = Archive::new(plain)?.format_descriptor()?;
+	assert_eq!(descriptor.writer, None);
+	assert_eq!(descriptor.spec_version, crate::VERSION);
+	assert_eq!(descriptor.flag("TOMBSTONE"), Some(Flags::TOMBSTONE_FLAG));
+
+	// Lines from newer writers are skipped, newer encodings are refused
+	let text = format!("{}future-key something\nflag NEW 0x8000\n", FormatDescriptor::current());
+	let parsed = text.parse::<FormatDescriptor>()?;
+	assert_eq!(parsed.flag("new"), Some(0x8000));
+
+	assert!("vach-format 2\n".parse::<FormatDescriptor>().is_err());
+	assert!("flag SIGNED 0x8000000\n".parse::<FormatDescriptor>().is_err());
+
+	Ok(())
+}
+
+#[test]
+#[cfg(feature = "bench")]
+fn bench_workloads() -> InternalResult {
+	use crate::bench::{run, run_all, BenchConfig, Workload};
+
+	let config = BenchConfig::default().scale(0.01).iterations(2);
+	let results = run_all(&config)?;
+
+	let expected = Workload::ALL
+		.iter()
+		.filter(|w| cfg!(feature = "crypto") || **w != Workload::Encrypted)
+		.count();
+	assert_eq!(results.len(), expected);
+
+	for result in &results {
+		assert!(result.bytes > 0 && result.archive_size > 0);
+		assert!(result.pack > std::time::Duration::ZERO && result.fetch > std::time::Duration::ZERO);
+		assert!(!result.regressed(result, 0.0));
+
+		let metrics = result.metrics();
+		assert_eq!(metrics.operation, format!("bench:{}", result.workload.name()));
+		assert_eq!(metrics.entries, result.leaves);
+	}
+
+	// Data is the same on every run
+	let small = run(Workload::FewHugeLeaves, &config)?;
+	assert_eq!(small.bytes, results[1].bytes);
+	assert_eq!(small.leaves, 4);
+
+	// Throughput dropping past the tolerance is a regression
+	let mut slower = small.clone();
+	slower.pack = small.pack * 2;
+	assert!(slower.regressed(&small, 0.25));
+	assert!(!small.regressed(&slower, 0.25));
+
+	#[cfg(not(feature = "crypto"))]
+	assert!(matches!(run(Workload::Encrypted, &config), Err(InternalError::MissingFeatureError("crypto"))));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn fetch_reader() -> InternalResult {
+	use std::io::{Cursor, Read};
+
+	let text = b"Streaming keeps large leaves out of memory. ".repeat(4096);
+
+	let mut builder = Builder::new();
+	builder.add(text.as_slice(), "plain")?;
+	#[cfg(feature = "compression")]
+	builder.add_leaf(
+		Leaf::new(text.as_slice())
+			.id("compressed")
+			.compress(CompressMode::Always)
+			.compression_algo(CompressionAlgorithm::LZ4),
+	)?;
+	#[cfg(feature = "crypto")]
+	builder.add_leaf(Leaf::new(text.as_slice()).id("encrypted").encrypt(true))?;
+
+	let config = BuilderConfig::default();
+	#[cfg(feature = "crypto")]
+	let config = config.keypair(crate::crypto_utils::gen_keypair());
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &config)?;
+	let source = target.into_inner();
+
+	let archive = Archive::new(Cursor::new(source.as_slice()))?;
+	let mut ids = vec!["plain"];
+	if cfg!(feature = "compression") {
+		ids.push("compressed");
+	}
+
+	for id in ids {
+		let mut reader = archive.fetch_reader(id)?;
+		assert!(!reader.authenticated());
+
+		// Read in small chunks, interleaved with other fetches
+		let mut data = Vec::new();
+		let mut chunk = [0; 1000];
+		loop {
+			let read = reader.read(&mut chunk)?;
+			if read == 0 {
+				break;
+			}
+
+			data.extend_from_slice(&chunk[..read]);
+			archive.fetch("plain")?;
+		}
+
+		assert_eq!(data, text);
+		assert_eq!(data.as_slice(), &*archive.fetch(id)?.data);
+	}
+
+	// Encrypted leaves are decoded in memory first
+	#[cfg(feature = "crypto")]
+	{
+		let archive = Archive::with_config(
+			Cursor::new(source.as_slice()),
+			&ArchiveConfig::default().key(config.keypair.as_ref().unwrap().verifying_key()),
+		)?;
+		let mut data = Vec::new();
+		archive.fetch_reader("encrypted")?.read_to_end(&mut data)?;
+		assert_eq!(data, text);
+	}
+
+	assert!(matches!(
+		archive.fetch_reader("missing"),
+		Err(InternalError::MissingResourceError(_))
+	));
+
+	// Truncated sources fail while reading, not with a short leaf
+	let plain = archive.fetch_entry("plain").unwrap();
+	let truncated = &source[..(plain.location + plain.offset / 2) as usize];
+	let archive = Archive::new(Cursor::new(truncated))?;
+
+	let mut data = Vec::new();
+	let err = archive.fetch_reader("plain")?.read_to_end(&mut data).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "crypto", feature = "compression", feature = "builder", feature = "archive"))]
+fn editor_append_replace() -> InternalResult {
+	use std::io::Cursor;
+
+	let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+	let archive_config = ArchiveConfig::default().key(keypair.verifying_key());
+
+	let config = BuilderConfig::default().keypair(keypair.clone()).footer(true);
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"First, signed" as &[u8]).id("first").sign(true))?;
+	builder.add_leaf(Leaf::new(b"Second, to be patched" as &[u8]).id("second"))?;
+	builder.add_leaf(Leaf::placeholder("third"))?;
+
+	let mut source = Cursor::new(Vec::new());
+	builder.dump(&mut source, &config)?;
+	let original = source.get_ref().clone();
+
+	// Rejected edits
+	let mut editor = ArchiveEditor::new(Archive::with_config(Cursor::new(original.clone()), &archive_config)?);
+	assert!(matches!(editor.append(Leaf::new(b"" as &[u8]).id("first")), Err(InternalError::LeafAppendError(_))));
+	assert!(matches!(editor.replace("missing", Leaf::default()), Err(InternalError::MissingResourceError(_))));
+	assert!(matches!(editor.append(Leaf::new(b"" as &[u8]).id("tagged").tag("dlc")), Err(InternalError::InvalidConfigError(_))));
+
+	// Replacements fitting where the old leaf was are written over it, the rest of the archive is left as is
+	let before = Archive::new(Cursor::new(original.clone()))?;
+	let (first, second) = (before.fetch_entry("first").unwrap(), before.fetch_entry("second").unwrap());
+	let mut editor = ArchiveEditor::new(Archive::with_config(source, &archive_config)?);
+	editor.replace("second", Leaf::new(b"Second, patched" as &[u8]))?;
+
+	let source = editor.commit_in_place()?;
+	assert_eq!(source.get_ref().len(), original.len());
+	let untouched = first.location as usize..second.location as usize;
+	assert_eq!(&source.get_ref()[untouched.clone()], &original[untouched]);
+
+	// Appending grows the registry, moving the first leaves after the last one. Signed leaves are re-signed where they land
+	let mut editor = ArchiveEditor::new(Archive::with_config(source, &archive_config)?);
+	editor.signing_key(keypair.clone());
+	editor.replace("third", Leaf::new(b"Third, filled in later and much longer" as &[u8]))?;
+	editor.append(Leaf::new(b"Fourth, encrypted and signed" as &[u8]).id("fourth").encrypt(true).sign(true))?;
+	editor.append(
+		Leaf::new(b"Fifth ".repeat(64).as_slice())
+			.id("fifth")
+			.compress(CompressMode::Always),
+	)?;
+
+	let archive = Archive::with_config(editor.commit_in_place()?, &archive_config)?;
+	assert_eq!(archive.entries().len(), 5);
+
+	let first = archive.fetch("first")?;
+	assert_eq!(first.data.as_ref(), b"First, signed");
+	assert!(first.authenticated);
+
+	let fourth = archive.fetch("fourth")?;
+	assert_eq!(fourth.data.as_ref(), b"Fourth, encrypted and signed");
+	assert!(fourth.authenticated);
+
+	assert_eq!(archive.fetch("second")?.data.as_ref(), b"Second, patched");
+	assert_eq!(archive.fetch("third")?.data.as_ref(), b"Third, filled in later and much longer");
+	assert_eq!(archive.fetch("fifth")?.data.as_ref(), b"Fifth ".repeat(64).as_slice());
+	assert!(archive.fetch_entry("fifth").unwrap().flags.contains(Flags::COMPRESSED_FLAG));
+
+	// Copying into a new target gives the same archive
+	let mut editor = ArchiveEditor::new(Archive::with_config(Cursor::new(original), &archive_config)?);
+	editor.signing_key(keypair);
+	editor.replace("second", Leaf::new(b"Second, patched" as &[u8]))?;
+	editor.append(Leaf::new(b"Sixth" as &[u8]).id("sixth").sign(true))?;
+
+	let mut target = Cursor::new(Vec::new());
+	editor.commit(&mut target)?;
+
+	let copied = Archive::with_config(target, &archive_config)?;
+	assert_eq!(copied.fetch("second")?.data.as_ref(), b"Second, patched");
+	assert!(copied.fetch("sixth")?.authenticated);
+	assert!(copied.fetch("first")?.authenticated);
+
+	// New `ID`s can't be added to an `ID` index in place
+	let mut indexed = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add(b"Indexed" as &[u8], "indexed")?;
+	builder.dump(&mut indexed, &BuilderConfig::default().id_index(true))?;
+
+	let mut editor = ArchiveEditor::new(Archive::new(indexed)?);
+	editor.append(Leaf::new(b"Unindexed" as &[u8]).id("unindexed"))?;
+	assert!(matches!(editor.commit_in_place(), Err(InternalError::InvalidConfigError(_))));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn dump_incremental() -> InternalResult {
+	use std::io::Cursor;
+
+	#[allow(unused_mut)]
+	let mut config = BuilderConfig::default();
+	#[allow(unused_mut)]
+	let mut archive_config = ArchiveConfig::default();
+
+	#[cfg(feature = "crypto")]
+	{
+		let keypair = crate::crypto_utils::read_keypair(KEYPAIR.as_slice())?;
+		archive_config = archive_config.key(keypair.verifying_key());
+		config = config.keypair(keypair);
+	}
+
+	let leaf = |data: &'static [u8], id: &str| {
+		let leaf = Leaf::new(data).id(id);
+		#[cfg(feature = "compression")]
+		let leaf = leaf.compress(CompressMode::Always);
+		#[cfg(feature = "crypto")]
+		let leaf = leaf.sign(true);
+		leaf
+	};
+
+	let mut builder = Builder::new();
+	builder.add_leaf(leaf(b"Models, unchanged", "models"))?;
+	builder.add_leaf(leaf(b"Textures, first pass", "textures"))?;
+	builder.add_leaf(leaf(b"Sounds, to be removed", "sounds"))?;
+	assert!(builder.is_dirty("models"));
+
+	let mut first = Cursor::new(Vec::new());
+	let report = builder.dump_incremental(None::<Archive<Cursor<Vec<u8>>>>, &mut first, &config)?;
+	assert_eq!(report.reused, 0);
+	assert!(!builder.is_dirty("models"));
+
+	// Clean leaves need an archive to be copied from
+	let mut unused = Cursor::new(Vec::new());
+	assert!(matches!(
+		builder.dump_incremental(None::<Archive<Cursor<Vec<u8>>>>, &mut unused, &config),
+		Err(InternalError::MissingResourceError(_))
+	));
+
+	// Only staged leaves are processed again, the rest are copied as they were stored
+	builder.stage(leaf(b"Textures, second pass", "textures"))?;
+	builder.stage(leaf(b"Levels, new", "levels"))?;
+	assert!(builder.remove("sounds").is_some());
+	assert!(builder.is_dirty("textures") && !builder.is_dirty("models"));
+
+	let source = Archive::with_config(Cursor::new(first.into_inner()), &archive_config)?;
+	let stored = source.fetch_entry("models").unwrap();
+
+	let mut second = Cursor::new(Vec::new());
+	let report = builder.dump_incremental(Some(source), &mut second, &config)?;
+	assert_eq!(report.reused, 1);
+
+	let archive = Archive::with_config(second, &archive_config)?;
+	assert_eq!(archive.entries().len(), 3);
+	assert!(archive.fetch_entry("sounds").is_none());
+
+	let models = archive.fetch("models")?;
+	assert_eq!(models.data.as_ref(), b"Models, unchanged");
+	assert_eq!(models.flags, stored.flags);
+	#[cfg(feature = "crypto")]
+	assert!(models.authenticated);
+
+	assert_eq!(archive.fetch("textures")?.data.as_ref(), b"Textures, second pass");
+	assert_eq!(archive.fetch("levels")?.data.as_ref(), b"Levels, new");
+
+	// Leaves queued again in a later session can be marked clean
+	let mut builder = Builder::new();
+	builder.add_leaf(leaf(b"", "models"))?;
+	builder.mark_clean();
+
+	let mut third = Cursor::new(Vec::new());
+	let report = builder.dump_incremental(Some(archive), &mut third, &config)?;
+	assert_eq!(report.reused, 1);
+	assert_eq!(Archive::with_config(third, &archive_config)?.fetch("models")?.data.as_ref(), b"Models, unchanged");
+
+	// Data spent by a failed dump isn't mistaken for an empty leaf
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Spent" as &[u8]).id("spent"))?;
+
+	let cramped = BuilderConfig::default().data_offset(1);
+	assert!(builder.dump_incremental(None::<Archive<Cursor<Vec<u8>>>>, Cursor::new(Vec::new()), &cramped).is_err());
+	assert!(builder.is_dirty("spent"));
+	assert!(builder.dump_incremental(None::<Archive<Cursor<Vec<u8>>>>, Cursor::new(Vec::new()), &config).is_err());
+
+	Ok(())
+}
+
+#[cfg(all(feature = "builder", feature = "archive"))]
+#[test]
+fn normalize_newlines() -> InternalResult {
+	use std::io::Cursor;
+
+	let pack = |leaves: Vec<Leaf<'static>>| -> InternalResult<Vec<u8>> {
+		let mut builder = Builder::new();
+		for leaf in leaves {
+			builder.add_leaf(leaf)?;
+		}
+
+		let mut target = Cursor::new(Vec::new());
+		builder.dump(&mut target, &BuilderConfig::default())?;
+		Ok(target.into_inner())
+	};
+
+	// Sources checked out on Windows and Unix pack into identical archives
+	let windows = pack(vec![Leaf::new(b"line one\r\nline two\r\n" as &[u8]).id("text").normalize_newlines(true)])?;
+	let unix = pack(vec![Leaf::new(b"line one\nline two\n" as &[u8]).id("text").normalize_newlines(true)])?;
+	assert_eq!(windows, unix);
+
+	let archive = Archive::new(Cursor::new(windows))?;
+	assert_eq!(archive.fetch("text")?.data.as_ref(), b"line one\nline two\n");
+	assert_eq!(archive.fetch_entry("text").unwrap().decoded_size, 18);
+
+	// Line endings are kept by default, and binary data is left alone by detection
+	let binary: &[u8] = b"\0\r\n\xFF\r\n";
+	let source = pack(vec![
+		Leaf::new(b"kept\r\n" as &[u8]).id("kept"),
+		Leaf::new(binary).id("binary").newlines(NewlineMode::Detect),
+		Leaf::new(b"detected\r\n" as &[u8]).id("detected").newlines(NewlineMode::Detect),
+	])?;
+
+	let archive = Archive::new(Cursor::new(source))?;
+	assert_eq!(archive.fetch("kept")?.data.as_ref(), b"kept\r\n");
+	assert_eq!(archive.fetch("binary")?.data.as_ref(), binary);
+	assert_eq!(archive.fetch("detected")?.data.as_ref(), b"detected\n");
+
+	Ok(())
+}
+
+#[cfg(all(feature = "mmap", unix, feature = "builder", feature = "archive"))]
+#[test]
+fn fetch_slice() -> InternalResult {
+	use std::borrow::Cow;
+
+	let mut builder = Builder::new();
+	builder.add(b"Stored as is, straight from the map" as &[u8], "raw")?;
+	#[cfg(feature = "compression")]
+	builder.add_leaf(Leaf::new(b"Compressed, decoded on fetch" as &[u8]).id("packed").compress(CompressMode::Always))?;
+
+	let config = BuilderConfig::default();
+	#[cfg(feature = "crypto")]
+	let config = config.keypair(crate::crypto_utils::read_keypair(&KEYPAIR[..])?);
+	#[cfg(feature = "crypto")]
+	builder.add_leaf(Leaf::new(b"Signed" as &[u8]).id("signed").sign(true))?;
+
+	let path = std::env::temp_dir().join(format!("vach-fetch-slice-{}.vach", std::process::id()));
+	builder.dump(File::create(&path)?, &config)?;
+
+	let archive_config = ArchiveConfig::default();
+	#[cfg(feature = "crypto")]
+	let archive_config = archive_config.key(crate::crypto_utils::read_keypair(&KEYPAIR[..])?.verifying_key());
+
+	// SAFETY: The file is private to this test
+	let archive = unsafe { Archive::from_mmap_with_config(&path, &archive_config)? };
+
+	let raw = archive.fetch_slice("raw")?;
+	assert!(matches!(raw.data, Cow::Borrowed(b"Stored as is, straight from the map")));
+	assert_eq!(archive.fetch("raw")?.data.as_ref(), raw.data.as_ref());
+
+	#[cfg(feature = "compression")]
+	assert!(matches!(archive.fetch_slice("packed")?.data, Cow::Owned(data) if data == b"Compressed, decoded on fetch"));
+
+	#[cfg(feature = "crypto")]
+	{
+		let signed = archive.fetch_slice("signed")?;
+		assert!(matches!(signed.data, Cow::Borrowed(b"Signed")) && signed.authenticated);
+	}
+
+	assert!(archive.fetch_slice("missing").is_err());
+
+	drop(archive);
+	std::fs::remove_file(&path)?;
+
+	Ok(())
+}
+
+#[cfg(all(feature = "async", feature = "builder"))]
+#[test]
+fn async_archive() -> InternalResult {
+	use std::io::Cursor;
+	use crate::archive::AsyncArchive;
+
+	let mut builder = Builder::new();
+	builder.add(b"Hello, Cassandra!" as &[u8], "greetings/hello")?;
+	builder.add(b"Goodbye, Cassandra!" as &[u8], "greetings/goodbye")?;
+	builder.add(b"" as &[u8], "empty")?;
+
+	// Footers and prefixed IDs make the header and registry variable in size
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default().footer(true).prefix_ids(true))?;
+	let source = target.into_inner();
+
+	let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+	runtime.block_on(async {
+		let archive = AsyncArchive::new(Cursor::new(source.clone())).await?;
+		assert_eq!(archive.entries().len(), 3);
+
+		// Fetches match those of the blocking loader
+		let blocking = Archive::new(Cursor::new(source.clone()))?;
+		for id in ["greetings/hello", "greetings/goodbye", "empty"] {
+			assert_eq!(archive.fetch(id).await?.data, blocking.fetch(id)?.data);
+		}
+
+		assert!(matches!(
+			archive.fetch("missing").await,
+			Err(InternalError::MissingResourceError(_))
+		));
+
+		// Truncated sources are caught by the footer
+		let truncated = Cursor::new(source[..source.len() - 1].to_vec());
+		assert!(matches!(
+			AsyncArchive::new(truncated).await,
+			Err(InternalError::FooterMismatchError(_))
+		));
+
+		Ok(())
+	})
+}
+
+#[test]
+#[cfg(all(feature = "compression", feature = "builder", feature = "archive"))]
+fn registry_compression() -> InternalResult {
+	use std::io::Cursor;
+
+	let build = |config: &BuilderConfig| -> InternalResult<Vec<u8>> {
+		let mut builder = Builder::new();
+		for idx in 0..500 {
+			builder.add(Cursor::new(format!("Leaf number {}", idx)), format!("assets/textures/tile-{}.png", idx))?;
+		}
+
+		let mut target = Cursor::new(Vec::new());
+		builder.dump(&mut target, config)?;
+		Ok(target.into_inner())
+	};
+
+	let plain = build(&BuilderConfig::default())?;
+	let compressed = build(&BuilderConfig::default().footer(true).compress_registry(CompressionAlgorithm::LZ4, 1024))?;
+	assert!(compressed.len() < plain.len());
+
+	// Decompressed transparently at load
+	let archive = Archive::new(Cursor::new(compressed))?;
+	assert!(archive.capabilities().contains(Capabilities::COMPRESSED_REGISTRY));
+	assert_eq!(archive.entries().len(), 500);
+	assert_eq!(archive.fetch("assets/textures/tile-321.png")?.data.as_ref(), b"Leaf number 321");
+
+	// The block follows the leaves
+	let layout = archive.layout_report()?;
+	let registry = layout.regions.iter().filter(|r| r.kind == RegionKind::Registry).collect::<Vec<_>>();
+	assert_eq!(registry.len(), 1);
+	assert!(archive.entries().values().all(|entry| entry.location < registry[0].start));
+
+	// Registries under the threshold are stored as is
+	let small = build(&BuilderConfig::default().compress_registry(CompressionAlgorithm::LZ4, 1 << 20))?;
+	assert_eq!(small, plain);
+
+	// Editing writes the registry back uncompressed, it can't be edited in place
+	let mut edited = Cursor::new(Vec::new());
+	ArchiveEditor::new(archive).commit(&mut edited)?;
+
+	let archive = Archive::new(edited)?;
+	assert!(!archive.capabilities().contains(Capabilities::COMPRESSED_REGISTRY));
+	assert_eq!(archive.fetch("assets/textures/tile-7.png")?.data.as_ref(), b"Leaf number 7");
+
+	let source = Cursor::new(build(&BuilderConfig::default().compress_registry(CompressionAlgorithm::LZ4, 0))?);
+	let result = ArchiveEditor::new(Archive::new(source)?).commit_in_place();
+	assert!(matches!(result, Err(InternalError::InvalidConfigError(_))));
+
+	// A block recording less than the registry inflates to is refused once decompression passes the recorded size
+	let mut forged = build(&BuilderConfig::default().compress_registry(CompressionAlgorithm::LZ4, 0))?;
+	let block = Archive::new(Cursor::new(forged.clone()))?.layout_report()?;
+	let block = block.regions.iter().find(|r| r.kind == RegionKind::Registry).unwrap();
+
+	let mut described = Flags::LZ4_COMPRESSED.to_le_bytes().to_vec();
+	described.extend_from_slice(&block.start.to_le_bytes());
+	described.extend_from_slice(&block.len().to_le_bytes());
+
+	let at = forged.windows(described.len()).position(|w| w == described).unwrap() + described.len();
+	forged[at..at + 8].copy_from_slice(&16u64.to_le_bytes());
+
+	let result = Archive::new(Cursor::new(forged));
+	assert!(matches!(result, Err(InternalError::OtherError(err)) if err.to_string().contains("more than the 16 bytes")));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive"))]
+fn directory_queries() -> InternalResult {
+	use std::io::Cursor;
+
+	let mut builder = Builder::new();
+	for id in [
+		"textures/rock.png",
+		"textures/rock/normal.png",
+		"textures/terrain/grass.png",
+		"textures/terrain/sand.png",
+		"sounds/sfx/boom.ogg",
+		"sounds/music/ambient/wind.ogg",
+		"sounds/music/theme.mp3",
+		"readme.txt",
+	] {
+		builder.add(id.as_bytes(), id)?;
+	}
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	// Subdirectories show up once, files and directories sharing a name both show up
+	assert_eq!(
+		archive.list_dir("textures"),
+		vec![
+			DirEntry::Dir("textures/rock"),
+			DirEntry::File("textures/rock.png"),
+			DirEntry::Dir("textures/terrain"),
+		]
+	);
+	assert_eq!(archive.list_dir("textures/"), archive.list_dir("textures"));
+	assert_eq!(archive.children("textures").count(), 3);
+
+	let root = archive.list_dir("");
+	assert_eq!(root.iter().map(|child| child.name()).collect::<Vec<_>>(), ["readme.txt", "sounds", "textures"]);
+	assert!(archive.list_dir("missing").is_empty());
+	assert!(archive.list_dir("textures/rock.png").is_empty());
+
+	// Globs only visit their literal prefix
+	let ids = |entries: Vec<&RegistryEntry>| entries.iter().map(|entry| entry.id.to_string()).collect::<Vec<_>>();
+	assert_eq!(ids(archive.glob("sounds/**/*.ogg")), ["sounds/music/ambient/wind.ogg", "sounds/sfx/boom.ogg"]);
+	assert_eq!(ids(archive.glob("textures/*.png")), ["textures/rock.png"]);
+	assert_eq!(ids(archive.glob("textures/terrain/?and.png")), ["textures/terrain/sand.png"]);
+	assert_eq!(ids(archive.glob("readme.txt")), ["readme.txt"]);
+	assert_eq!(archive.glob("**").len(), 8);
+
+	// Tombstones are left out
+	let mut builder = Builder::new();
+	builder.add(b"Rock" as &[u8], "textures/rock.png")?;
+	builder.add_leaf(Leaf::tombstone("readme.txt"))?;
+
+	let mut target = Cursor::new(Vec::new());
+	builder.dump(&mut target, &BuilderConfig::default())?;
+	let archive = Archive::new(target)?;
+
+	assert!(archive.glob("readme.*").is_empty());
+	assert!(!archive.list_dir("").contains(&DirEntry::File("readme.txt")));
+
+	Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "builder", feature = "archive", feature = "crypto"))]
+fn key_epochs() -> InternalResult {
+	use std::io::Cursor;
+
+	let (first, second) = (EncryptionKey::generate(), EncryptionKey::generate());
+
+	let mut source = Cursor::new(Vec::new());
+	let mut builder = Builder::new();
+	builder.add_leaf(Leaf::new(b"Written in spring" as &[u8]).id("spring").encrypt(true))?;
+	builder.dump(&mut source, &BuilderConfig::default().encryption_key(first))?;
+
+	// The key was rotated since, appended leaves record the new epoch
+	let mut editor = ArchiveEditor::new(Archive::with_config(source, &ArchiveConfig::default().decryption_key(first))?);
+	editor.encryption_key(second).key_epoch(1);
+	editor.append(Leaf::new(b"Written in autumn" as &[u8]).id("autumn").encrypt(true))?;
+
+	let mut target = Cursor::new(Vec::new());
+	editor.commit(&mut target)?;
+
+	let config = ArchiveConfig::default().decryption_key(first).add_decryption_key(1, second);
+
+	let archive = Archive::with_config(target.clone(), &config)?;
+	assert!(archive.capabilities().contains(Capabilities::KEY_EPOCHS));
+	assert!(!archive.fetch_entry("spring").unwrap().flags.contains(Flags::KEY_EPOCH_FLAG));
+	assert!(archive.fetch_entry("autumn").unwrap().flags.contains(Flags::KEY_EPOCH_FLAG));
+
+	let spring = archive.fetch("spring")?;
+	let autumn = archive.fetch("autumn")?;
+	assert_eq!(spring.data.as_ref(), b"Written in spring");
+	assert_eq!(autumn.data.as_ref(), b"Written in autumn");
+	assert_eq!(spring.decrypted_with, Some(KeyId::of_encryption_key(&first)));
+	assert_eq!(autumn.decrypted_with, Some(KeyId::of_encryption_key(&second)));
+
+	// Without the later key only the earlier leaves are readable
+	let archive = Archive::with_config(target, &ArchiveConfig::default().decryption_key(first))?;
+	assert_eq!(archive.fetch("spring")?.data.as_ref(), b"Written in spring");
+	assert!(matches!(archive.fetch("autumn").unwrap_err().root(), InternalError::NoKeypairError));
+
+	// Epochs are replaced in place, however many times the key was rotated
+	let mut config = ArchiveConfig::default().add_decryption_key(0, first);
+	assert_eq!(config.decryption_key, Some(first));
+
+	for epoch in 1..=64 {
+		config = config.add_decryption_key(epoch, second);
+	}
+
+	config = config.add_decryption_key(16, first);
+	assert_eq!(config.epoch_keys.len(), 64);
+	assert_eq!(config.epoch_keys[&16], first);
+
+	Ok(())
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "crypto")]
+#![cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+
+use std::time::SystemTime;
+
+use ed25519_dalek::{Digest, Sha512};
+
+use crate::global::error::*;
+
+/// The size of an archive's content hash, `SHA-512`
+pub const CONTENT_HASH_LENGTH: usize = 64;
+
+/// Requests timestamp tokens while writing archives, eg: an RFC 3161 client asking a timestamping authority to countersign the content hash.
+/// The token is stored in the archive's header and proves the archive existed at the attested time, even if the signing keys later leak.
+/// See `BuilderConfig::timestamp_authority`
+pub trait TimestampAuthority: Send + Sync {
+	/// The largest token `timestamp` returns, space for it is reserved in the header before the content hash is known. At most `65533` bytes
+	fn max_token_len(&self) -> usize;
+	/// Requests a token over the archive's content hash, eg: an RFC 3161 `TimeStampToken` with a `SHA-512` message imprint
+	fn timestamp(&self, hash: &[u8; CONTENT_HASH_LENGTH]) -> InternalResult<Vec<u8>>;
+}
+
+/// Checks the timestamp tokens of loaded archives, see `Archive::verify_timestamp`
+pub trait TimestampVerifier {
+	/// Checks that `token` is valid and covers `hash`, returning the time it attests to
+	fn verify(&self, hash: &[u8; CONTENT_HASH_LENGTH], token: &[u8]) -> InternalResult<SystemTime>;
+}
+
+/// The content hash covers everything after the header: the registry, any reserved gap and all leaf data.
+/// The registry is hashed apart from the data, as writers only know it once all leaves are written
+#[derive(Default)]
+pub(crate) struct ContentHasher {
+	registry: Sha512,
+	data: Sha512,
+}
+
+impl ContentHasher {
+	pub(crate) fn registry(&mut self, bytes: &[u8]) {
+		self.registry.update(bytes);
+	}
+
+	pub(crate) fn data(&mut self, bytes: &[u8]) {
+		self.data.update(bytes);
+	}
+
+	pub(crate) fn finish(self) -> [u8; CONTENT_HASH_LENGTH] {
+		Sha512::new()
+			.chain_update(b"vach::content:")
+			.chain_update(self.registry.finalize())
+			.chain_update(self.data.finalize())
+			.finalize()
+			.into()
+	}
+}
+
+/// The header extension holding a token, padded to the reserved size: a `u16` length, the token, then zeroes
+pub(crate) fn encode_token(token: &[u8], reserved: usize) -> Vec<u8> {
+	let mut data = Vec::with_capacity(2 + reserved);
+	data.extend_from_slice(&(token.len() as u16).to_le_bytes());
+	data.extend_from_slice(token);
+	data.resize(2 + reserved, 0);
+	data
+}
+
+pub(crate) fn decode_token(data: &[u8]) -> Option<&[u8]> {
+	let (length, rest) = data.split_first_chunk::<2>()?;
+	rest.get(..u16::from_le_bytes(*length) as usize)
+}
+
+#[cfg(feature = "archive")]
+mod archive {
+	use std::{
+		io::{Read, Seek, SeekFrom},
+		ops::DerefMut,
+		time::SystemTime,
+	};
+
+	use super::*;
+	use crate::{
+		global::{header::Header, sync},
+		loader::archive::Archive,
+	};
+
+	impl<T: Read + Seek> Archive<T> {
+		/// The timestamp token stored in the header, see `BuilderConfig::timestamp_authority`. Nothing is verified, see `Archive::verify_timestamp`
+		pub fn timestamp_token(&self) -> Option<&[u8]> {
+			decode_token(self.header.extension(Header::TIMESTAMP)?)
+		}
+
+		/// Hashes everything after the header, the hash timestamp tokens are issued over. Reads the whole archive
+		pub fn content_hash(&self) -> InternalResult<[u8; CONTENT_HASH_LENGTH]> {
+			let mut hasher = ContentHasher::default();
+			let mut guard = sync::lock(&self.handle);
+			let handle = guard.deref_mut();
+
+			let mut registry = vec![0; (self.registry_end - self.header.size() as u64) as usize];
+			handle.seek(SeekFrom::Start(self.header.size() as u64))?;
+			handle.read_exact(&mut registry)?;
+			hasher.registry(&registry);
+
+			let mut buffer = vec![0; 64 * 1024];
+			loop {
+				match handle.read(&mut buffer)? {
+					0 => break,
+					read => hasher.data(&buffer[..read]),
+				}
+			}
+
+			Ok(hasher.finish())
+		}
+
+		/// Checks the archive's timestamp token against it's content using `verifier`, returning the attested time.
+		/// Fails with [`InternalError::TimestampError`] if the archive has no token
+		pub fn verify_timestamp(&self, verifier: &dyn TimestampVerifier) -> InternalResult<SystemTime> {
+			let token = self
+				.timestamp_token()
+				.ok_or_else(|| InternalError::TimestampError("The archive has no timestamp token".to_string()))?;
+
+			verifier.verify(&self.content_hash()?, token)
+		}
+	}
+}
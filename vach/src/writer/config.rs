@@ -1,14 +1,26 @@
-use crate::global::{flags::Flags, reg_entry::RegistryEntry};
+use crate::global::{error::*, flags::Flags, profile::AccessProfile, reg_entry::RegistryEntry};
 
 #[cfg(feature = "crypto")]
-use crate::crypto;
+use {crate::crypto, std::time::SystemTime};
+
+#[cfg(feature = "compression")]
+use crate::global::{
+	compressor::{CompressionAlgorithm, DetectHeuristics},
+	lint::LintWarning,
+};
 
 /// Allows for the customization of valid `vach` archives during their construction.
 /// Such as custom `MAGIC`, custom `Header` flags and signing by providing a keypair.
 pub struct BuilderConfig<'a> {
-	/// Number of threads to spawn during `Builder::dump`, defaults to 4
+	/// Number of threads compressing, encrypting and hashing leaves during `Builder::dump`, defaults to the available parallelism
+	#[cfg(feature = "multithreaded")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "multithreaded")))]
+	pub compute_threads: usize,
+	/// Number of threads reading leaves into memory ahead of the compute threads, useful when leaves are slow to read, eg: on network drives.
+	/// With `0` compute threads read their own leaves, streaming them where possible. Defaults to a quarter of the available parallelism, at least 1
 	#[cfg(feature = "multithreaded")]
-	pub num_threads: usize,
+	#[cfg_attr(docsrs, doc(cfg(feature = "multithreaded")))]
+	pub io_threads: usize,
 	/// Used to write a unique magic sequence into the write target.
 	pub magic: [u8; crate::MAGIC_LENGTH],
 	/// Flags to be written into the `Header` section of the write target.
@@ -17,9 +29,45 @@ pub struct BuilderConfig<'a> {
 	#[cfg(feature = "crypto")]
 	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
 	pub keypair: Option<crypto::SigningKey>,
+	/// An optional external signer, used instead of `keypair` when set. Lets the secret key live outside the process, see [`LeafSigner`](crate::crypto::LeafSigner)
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub signer: Option<&'a dyn crypto::LeafSigner>,
+	/// An optional dedicated key to encrypt leaves with, loaders then need it as `ArchiveConfig::decryption_key`.
+	/// Separates reading encrypted leaves from verifying them, and allows encryption without a keypair. When `None`, the key is derived from the keypair's public key
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub encryption_key: Option<crypto::EncryptionKey>,
+	/// The epoch of the key leaves are encrypted with, bumped each time the key is rotated. Leaves of any epoch but `0` record it,
+	/// so loaders holding the keys of every epoch, see `ArchiveConfig::add_decryption_key`, can read archives appended to across rotations. Defaults to `0`
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub key_epoch: u32,
+	/// An optional timestamping authority, countersigning the archive's content hash once everything is written.
+	/// The token is stored in the header, see [`TimestampAuthority`](crate::timestamp::TimestampAuthority) and `Archive::verify_timestamp`
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub timestamp_authority: Option<&'a dyn crate::timestamp::TimestampAuthority>,
+	/// Decides which leaves using `CompressMode::Detect` skip the trial compression, see [`DetectHeuristics`]
+	#[cfg(feature = "compression")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+	pub detect_heuristics: DetectHeuristics,
+	/// What to do about leaves compressed after being encrypted, or that grow when compressed, see [`Guardrail`]
+	#[cfg(feature = "compression")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+	pub guardrail: Guardrail,
+	/// The algorithm the registry is compressed with once it takes at least `registry_threshold` bytes, see `BuilderConfig::compress_registry`. Defaults to `None`
+	#[cfg(feature = "compression")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+	pub registry_compression: Option<CompressionAlgorithm>,
+	/// The size in bytes from which the registry is compressed, defaults to [`BuilderConfig::REGISTRY_THRESHOLD`]
+	#[cfg(feature = "compression")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+	pub registry_threshold: usize,
 	/// An optional callback that is called every time a [Leaf](crate::builder::Leaf) finishes processing.
 	/// The callback get passed to it: a reference to the leaf and the generated registry entry. Use the RegEntry to get info on how the data was integrated for the given [`Leaf`].
 	/// > **To avoid** the `implementation of "FnOnce" is not general enough` error consider adding types to the closure's parameters, as this is a type inference error. Rust somehow cannot infer enough information, [link](https://www.reddit.com/r/rust/comments/ntqu68/implementation_of_fnonce_is_not_general_enough/).
+	///
 	/// Usage:
 	/// ```
 	/// use vach::prelude::{RegistryEntry, BuilderConfig, Leaf};
@@ -32,6 +80,114 @@ pub struct BuilderConfig<'a> {
 	/// builder_config.callback(&callback);
 	/// ```
 	pub progress_callback: Option<&'a dyn Fn(&RegistryEntry)>,
+	/// Sort the registry by `ID` and store each `ID` relative to the previous one, shrinking the registry when many `ID`s share long prefixes,
+	/// eg: `characters/hero/animations/...`. Archives written this way need a loader supporting `Capabilities::PREFIXED_IDS`, defaults to `false`
+	pub prefix_ids: bool,
+	/// Store a perfect hash over every `ID` in the reserved leaf `vach::index`, mapping each to it's position in the registry.
+	/// Loaders in other languages, eg: over FFI, can then look entries up without building a hash map at load, see [`IdIndex`](crate::builder::IdIndex).
+	/// Keeps the registry in the order leaves were added, defaults to `false`
+	pub id_index: bool,
+	/// Store the writer's [`FormatDescriptor`](crate::builder::FormatDescriptor) in the reserved leaf `vach::format`, describing the flags,
+	/// header extensions and codecs of the format as written, for third-party readers to adapt to. Defaults to `false`
+	pub format_descriptor: bool,
+	/// End the archive with a footer mirroring the header under a CRC, so loaders catch truncation right away and archives appended to other files,
+	/// eg: self-extracting executables, can be found from the end, see `Archive::from_trailing`. Defaults to `false`
+	pub footer: bool,
+	/// Lay leaves out in the order they were first fetched in a profiling run, so loads read the archive front to back.
+	/// Leaves missing from the profile are written last, in the order they were added. See [`AccessProfile`]
+	pub layout_profile: Option<&'a AccessProfile>,
+	/// Where the first leaf's data starts, as an absolute offset into the target. The gap between the end of the registry and this offset is zeroed and left alone,
+	/// reserving room for a platform specific header, a DRM block or future registry growth. `Archive::layout_report` lists the gap as unused.
+	/// `None`, the default, starts leaf data right after the registry
+	pub data_offset: Option<u64>,
+	/// Limits on how the keypair or signer may be used, checked by `Builder::dump` before anything is written, see [`KeyPolicy`]
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub key_policy: KeyPolicy,
+	/// The build ID of signed archives, bound into every signature so they can't be replayed in another archive.
+	/// `None`, the default, picks one at random. Set it for reproducible builds, never reuse one across archives signed with the same key
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub build_id: Option<[u8; 16]>,
+}
+
+/// Usage limits for the key an archive is encrypted and|or signed with, for compliance requirements around key hygiene.
+/// The expiry is recorded in the archive's header, loaders can then warn about or refuse expired archives, see `Archive::key_expired`
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use vach::prelude::{BuilderConfig, KeyPolicy};
+///
+/// let policy = KeyPolicy::default()
+///   .max_leaves(10_000)
+///   .expires(SystemTime::now() + Duration::from_secs(90 * 24 * 60 * 60));
+///
+/// let config = BuilderConfig::default().key_policy(policy);
+/// ```
+#[cfg(feature = "crypto")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyPolicy {
+	/// The most leaves one archive may encrypt or sign with the key, unlimited if `None`
+	pub max_leaves: Option<usize>,
+	/// When the key expires. Writing with an expired key fails, unlimited if `None`
+	pub expires: Option<SystemTime>,
+}
+
+#[cfg(feature = "crypto")]
+impl KeyPolicy {
+	/// Setter for the `max_leaves` field
+	pub fn max_leaves(mut self, max_leaves: usize) -> Self {
+		self.max_leaves = Some(max_leaves);
+		self
+	}
+
+	/// Setter for the `expires` field
+	pub fn expires(mut self, expires: SystemTime) -> Self {
+		self.expires = Some(expires);
+		self
+	}
+}
+
+/// Catches leaves whose compression bloats the archive instead of shrinking it: custom pipelines compressing after encrypting,
+/// and leaves that grow when compressed using `CompressMode::Always`, for example data that was compressed or encrypted before being added
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// // Noise doesn't compress, LZ4 frames add a few bytes on top
+/// let mut state = 0x2545F491u32;
+/// let noise = (0..4096).map(|_| { state ^= state << 13; state ^= state >> 17; state ^= state << 5; state as u8 }).collect::<Vec<_>>();
+///
+/// let mut builder = Builder::new();
+/// builder.add_leaf(Leaf::new(Cursor::new(noise)).id("noise").compress(CompressMode::Always)).unwrap();
+///
+/// // Warns by default, listing offending leaves in the report
+/// let report = builder.dump_report(Cursor::new(Vec::new()), &BuilderConfig::default()).unwrap();
+/// assert_eq!(report.warnings[0].name(), "compression-grew");
+/// ```
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Guardrail {
+	/// Write leaves as configured
+	Allow,
+	/// Write leaves as configured, listing each offending leaf in `DumpReport::warnings`
+	#[default]
+	Warn,
+	/// Refuse to write offending leaves, failing with [`InternalError::InvalidConfigError`]
+	Deny,
+}
+
+#[cfg(feature = "compression")]
+impl Guardrail {
+	/// Applies the guardrail to an offending leaf, returning the warning to record if any
+	pub(crate) fn check(self, warning: LintWarning) -> InternalResult<Option<LintWarning>> {
+		match self {
+			Guardrail::Allow => Ok(None),
+			Guardrail::Warn => Ok(Some(warning)),
+			Guardrail::Deny => Err(InternalError::InvalidConfigError(warning.to_string())),
+		}
+	}
 }
 
 impl<'a> std::fmt::Debug for BuilderConfig<'a> {
@@ -39,7 +195,19 @@ impl<'a> std::fmt::Debug for BuilderConfig<'a> {
 		let mut f = f.debug_struct("BuilderConfig");
 
 		f.field("magic", &self.magic);
+
+		#[cfg(feature = "multithreaded")]
+		f.field("compute_threads", &self.compute_threads);
+		#[cfg(feature = "multithreaded")]
+		f.field("io_threads", &self.io_threads);
+
 		f.field("flags", &self.flags);
+		f.field("prefix_ids", &self.prefix_ids);
+		f.field("id_index", &self.id_index);
+		f.field("format_descriptor", &self.format_descriptor);
+		f.field("footer", &self.footer);
+		f.field("layout_profile", &self.layout_profile.map(|p| p.accesses.len()));
+		f.field("data_offset", &self.data_offset);
 		f.field(
 			"progress_callback",
 			if self.progress_callback.is_some() {
@@ -49,14 +217,48 @@ impl<'a> std::fmt::Debug for BuilderConfig<'a> {
 			},
 		);
 
+		#[cfg(feature = "compression")]
+		f.field("detect_heuristics", &self.detect_heuristics);
+
+		#[cfg(feature = "compression")]
+		f.field("guardrail", &self.guardrail);
+
+		#[cfg(feature = "compression")]
+		f.field("registry_compression", &self.registry_compression);
+
+		#[cfg(feature = "compression")]
+		f.field("registry_threshold", &self.registry_threshold);
+
 		#[cfg(feature = "crypto")]
 		f.field("keypair", &self.keypair);
 
+		#[cfg(feature = "crypto")]
+		f.field("signer", &self.signer.map(|s| s.verifying_key()));
+
+		#[cfg(feature = "crypto")]
+		f.field("encryption_key", &self.encryption_key);
+
+		#[cfg(feature = "crypto")]
+		f.field("key_epoch", &self.key_epoch);
+
+		#[cfg(feature = "crypto")]
+		f.field("timestamp_authority", &self.timestamp_authority.map(|ta| ta.max_token_len()));
+
+		#[cfg(feature = "crypto")]
+		f.field("key_policy", &self.key_policy);
+
+		#[cfg(feature = "crypto")]
+		f.field("build_id", &self.build_id);
+
 		f.finish()
 	}
 }
 
 impl<'a> BuilderConfig<'a> {
+	/// The default size in bytes from which the registry is compressed, see `BuilderConfig::compress_registry`
+	#[cfg(feature = "compression")]
+	pub const REGISTRY_THRESHOLD: usize = 64 * 1024;
+
 	// Helper functions
 	/// Setter for the `keypair` field
 	#[cfg(feature = "crypto")]
@@ -65,6 +267,65 @@ impl<'a> BuilderConfig<'a> {
 		self
 	}
 
+	/// Setter for the `signer` field
+	#[cfg(feature = "crypto")]
+	pub fn signer(mut self, signer: &'a dyn crypto::LeafSigner) -> Self {
+		self.signer = Some(signer);
+		self
+	}
+
+	/// Setter for the `encryption_key` field
+	#[cfg(feature = "crypto")]
+	pub fn encryption_key(mut self, key: crypto::EncryptionKey) -> Self {
+		self.encryption_key = Some(key);
+		self
+	}
+
+	/// Setter for the `key_epoch` field
+	#[cfg(feature = "crypto")]
+	pub fn key_epoch(mut self, epoch: u32) -> Self {
+		self.key_epoch = epoch;
+		self
+	}
+
+	/// Setter for the `timestamp_authority` field
+	#[cfg(feature = "crypto")]
+	pub fn timestamp_authority(mut self, authority: &'a dyn crate::timestamp::TimestampAuthority) -> Self {
+		self.timestamp_authority = Some(authority);
+		self
+	}
+
+	/// The signer used during `Builder::dump`, `signer` takes precedence over `keypair`
+	#[cfg(feature = "crypto")]
+	pub(crate) fn active_signer(&self) -> Option<&dyn crypto::LeafSigner> {
+		match self.signer {
+			Some(signer) => Some(signer),
+			None => self.keypair.as_ref().map(|kp| kp as &dyn crypto::LeafSigner),
+		}
+	}
+
+	/// Setter for the `compute_threads` field
+	///```
+	/// use vach::prelude::BuilderConfig;
+	/// let config = BuilderConfig::default().compute_threads(8);
+	///```
+	#[cfg(feature = "multithreaded")]
+	pub fn compute_threads(mut self, compute_threads: usize) -> Self {
+		self.compute_threads = compute_threads;
+		self
+	}
+
+	/// Setter for the `io_threads` field
+	///```
+	/// use vach::prelude::BuilderConfig;
+	/// let config = BuilderConfig::default().io_threads(0);
+	///```
+	#[cfg(feature = "multithreaded")]
+	pub fn io_threads(mut self, io_threads: usize) -> Self {
+		self.io_threads = io_threads;
+		self
+	}
+
 	/// Setter for the `flags` field
 	///```
 	/// use vach::prelude::{Flags, BuilderConfig};
@@ -86,6 +347,122 @@ impl<'a> BuilderConfig<'a> {
 		self
 	}
 
+	/// Setter for the `detect_heuristics` field
+	///```
+	/// use vach::prelude::{BuilderConfig, DetectHeuristics};
+	///
+	/// // Also skip trial compression for DDS textures
+	/// let config = BuilderConfig::default().detect_heuristics(DetectHeuristics::default().extension("dds"));
+	///```
+	#[cfg(feature = "compression")]
+	pub fn detect_heuristics(mut self, heuristics: DetectHeuristics) -> BuilderConfig<'a> {
+		self.detect_heuristics = heuristics;
+		self
+	}
+
+	/// Setter for the `guardrail` field
+	///```
+	/// use vach::prelude::{BuilderConfig, Guardrail};
+	///
+	/// // Fail the dump instead of writing bloated leaves
+	/// let config = BuilderConfig::default().guardrail(Guardrail::Deny);
+	///```
+	#[cfg(feature = "compression")]
+	pub fn guardrail(mut self, guardrail: Guardrail) -> BuilderConfig<'a> {
+		self.guardrail = guardrail;
+		self
+	}
+
+	/// Compress the registry using `algo` once it takes at least `threshold` bytes, shrinking archives of very many leaves whose registry dwarfs their data.
+	/// The compressed registry is stored after the leaves and decompressed when the archive is opened, by loaders supporting `Capabilities::COMPRESSED_REGISTRY`.
+	/// Such archives can't be edited in place, `ArchiveEditor::commit` writes the registry back uncompressed
+	///```
+	/// use vach::prelude::{BuilderConfig, CompressionAlgorithm};
+	/// let config = BuilderConfig::default().compress_registry(CompressionAlgorithm::LZ4, BuilderConfig::REGISTRY_THRESHOLD);
+	///```
+	#[cfg(feature = "compression")]
+	pub fn compress_registry(mut self, algo: CompressionAlgorithm, threshold: usize) -> BuilderConfig<'a> {
+		self.registry_compression = Some(algo);
+		self.registry_threshold = threshold;
+		self
+	}
+
+	/// Setter for the `key_policy` field
+	#[cfg(feature = "crypto")]
+	pub fn key_policy(mut self, policy: KeyPolicy) -> BuilderConfig<'a> {
+		self.key_policy = policy;
+		self
+	}
+
+	/// Setter for the `build_id` field
+	#[cfg(feature = "crypto")]
+	pub fn build_id(mut self, build_id: [u8; 16]) -> BuilderConfig<'a> {
+		self.build_id = Some(build_id);
+		self
+	}
+
+	/// Setter for the `prefix_ids` field
+	///```
+	/// use vach::prelude::BuilderConfig;
+	/// let config = BuilderConfig::default().prefix_ids(true);
+	///```
+	pub fn prefix_ids(mut self, prefix_ids: bool) -> BuilderConfig<'a> {
+		self.prefix_ids = prefix_ids;
+		self
+	}
+
+	/// Setter for the `id_index` field
+	///```
+	/// use vach::prelude::BuilderConfig;
+	/// let config = BuilderConfig::default().id_index(true);
+	///```
+	pub fn id_index(mut self, id_index: bool) -> BuilderConfig<'a> {
+		self.id_index = id_index;
+		self
+	}
+
+	/// Setter for the `format_descriptor` field
+	///```
+	/// use vach::prelude::BuilderConfig;
+	/// let config = BuilderConfig::default().format_descriptor(true);
+	///```
+	pub fn format_descriptor(mut self, format_descriptor: bool) -> BuilderConfig<'a> {
+		self.format_descriptor = format_descriptor;
+		self
+	}
+
+	/// Setter for the `footer` field
+	///```
+	/// use vach::prelude::BuilderConfig;
+	/// let config = BuilderConfig::default().footer(true);
+	///```
+	pub fn footer(mut self, footer: bool) -> BuilderConfig<'a> {
+		self.footer = footer;
+		self
+	}
+
+	/// Setter for the `layout_profile` field
+	///```
+	/// use vach::prelude::{AccessProfile, BuilderConfig};
+	///
+	/// let profile = AccessProfile::default();
+	/// let config = BuilderConfig::default().layout_from_profile(&profile);
+	///```
+	pub fn layout_from_profile(mut self, profile: &'a AccessProfile) -> BuilderConfig<'a> {
+		self.layout_profile = Some(profile);
+		self
+	}
+
+	/// Setter for the `data_offset` field, `Builder::dump` fails if the header and registry don't fit before it
+	///```
+	/// use vach::prelude::BuilderConfig;
+	/// let config = BuilderConfig::default().data_offset(64 * 1024);
+	///```
+	pub fn data_offset(mut self, offset: u64) -> BuilderConfig<'a> {
+		self.data_offset = Some(offset);
+		self
+	}
+
 	/// Setter for the `progress_callback` field
 	///```
 	/// use vach::prelude::{BuilderConfig, RegistryEntry, Leaf};
@@ -98,24 +475,92 @@ impl<'a> BuilderConfig<'a> {
 		self
 	}
 
+	/// Checks for settings that can't produce a valid archive, this is also done by `Builder::dump` before writing anything.
+	/// Settings that depend on the leaves, like encryption without a key, are checked by `Builder::dump` too.
+	///```
+	/// use vach::prelude::BuilderConfig;
+	///
+	/// assert!(BuilderConfig::default().validate().is_ok());
+	/// # #[cfg(feature = "multithreaded")]
+	/// assert!(BuilderConfig::default().compute_threads(0).validate().is_err());
+	///```
+	pub fn validate(&self) -> InternalResult {
+		#[cfg(feature = "multithreaded")]
+		if self.compute_threads == 0 {
+			return Err(InternalError::InvalidConfigError(
+				"compute_threads must be at least 1".to_string(),
+			));
+		}
+
+		if self.flags.bits() & Flags::RESERVED_MASK & !Flags::SIGNED_FLAG != 0 {
+			return Err(InternalError::InvalidConfigError(format!(
+				"Header flags {:#034b} set reserved bits, use Flags::set to set custom bits",
+				self.flags.bits()
+			)));
+		}
+
+		// The token is stored in a header extension, after it's `u16` length
+		#[cfg(feature = "crypto")]
+		if let Some(max) = self.timestamp_authority.map(|ta| ta.max_token_len()).filter(|max| *max > u16::MAX as usize - 2) {
+			return Err(InternalError::InvalidConfigError(format!(
+				"Timestamp tokens can take at most {} bytes, the authority reserves {}",
+				u16::MAX - 2,
+				max
+			)));
+		}
+
+		Ok(())
+	}
+
 	// Keypair helpers
 	/// Parses and stores a keypair from a source.
 	#[cfg(feature = "crypto")]
-	pub fn load_keypair<T: std::io::Read>(&mut self, handle: T) -> crate::global::error::InternalResult {
+	pub fn load_keypair<T: std::io::Read>(&mut self, handle: T) -> InternalResult {
 		crate::crypto_utils::read_keypair(handle).map(|kp| self.keypair = Some(kp))
 	}
 }
 
 impl<'a> Default for BuilderConfig<'a> {
 	fn default() -> BuilderConfig<'a> {
+		#[cfg(feature = "multithreaded")]
+		let parallelism = std::thread::available_parallelism().map_or(4, |n| n.get());
+
 		BuilderConfig {
 			#[cfg(feature = "multithreaded")]
-			num_threads: 4,
+			compute_threads: parallelism,
+			#[cfg(feature = "multithreaded")]
+			io_threads: (parallelism / 4).max(1),
 			flags: Flags::default(),
 			magic: *crate::DEFAULT_MAGIC,
 			progress_callback: None,
+			prefix_ids: false,
+			id_index: false,
+			format_descriptor: false,
+			footer: false,
+			layout_profile: None,
+			data_offset: None,
+			#[cfg(feature = "compression")]
+			detect_heuristics: DetectHeuristics::default(),
+			#[cfg(feature = "compression")]
+			guardrail: Guardrail::default(),
+			#[cfg(feature = "compression")]
+			registry_compression: None,
+			#[cfg(feature = "compression")]
+			registry_threshold: BuilderConfig::REGISTRY_THRESHOLD,
 			#[cfg(feature = "crypto")]
 			keypair: None,
+			#[cfg(feature = "crypto")]
+			signer: None,
+			#[cfg(feature = "crypto")]
+			encryption_key: None,
+			#[cfg(feature = "crypto")]
+			key_epoch: 0,
+			#[cfg(feature = "crypto")]
+			timestamp_authority: None,
+			#[cfg(feature = "crypto")]
+			key_policy: KeyPolicy::default(),
+			#[cfg(feature = "crypto")]
+			build_id: None,
 		}
 	}
 }
@@ -0,0 +1,778 @@
+use std::{
+	collections::{HashMap, HashSet},
+	io::{Read, Seek, SeekFrom, Write},
+};
+
+use crate::{
+	format::footer::RawFooter,
+	global::{
+		entry_id::{normalize, EntryId},
+		error::*,
+		flags::Flags,
+		header::{Capabilities, Header},
+		index::INDEX_ID,
+		reg_entry::RegistryEntry,
+		sync,
+	},
+	loader::archive::Archive,
+};
+
+use super::{Builder, Leaf};
+
+#[cfg(feature = "crypto")]
+use {
+	crate::crypto::{self, Encryptor},
+	ed25519_dalek::Signer,
+};
+
+#[cfg(feature = "compression")]
+use super::{CompressMode, DetectHeuristics, Guardrail};
+
+/// Modifies the registry of an existing [`Archive`], then writes the result out into a new target.
+/// Leaves are copied over as is, without being decompressed or decrypted. New leaves can be appended, or replace existing ones, see [`ArchiveEditor::append`].
+/// Signatures cover an entry's `ID` and location, so signed entries that are renamed or moved are re-signed, requiring a signing key.
+/// [`ArchiveEditor::resign`] re-signs all signed entries, to rotate keys.
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// let mut source = Cursor::new(Vec::new());
+/// let mut builder = Builder::new();
+/// builder.add(Cursor::new(b"Hello, Cassandra!"), "old/greeting").unwrap();
+/// builder.dump(&mut source, &BuilderConfig::default()).unwrap();
+///
+/// let mut editor = ArchiveEditor::new(Archive::new(source).unwrap());
+/// editor.rename("old/greeting", "new/greeting").unwrap();
+///
+/// let mut target = Cursor::new(Vec::new());
+/// editor.commit(&mut target).unwrap();
+///
+/// let archive = Archive::new(target).unwrap();
+/// assert_eq!(archive.fetch("new/greeting").unwrap().data.as_ref(), b"Hello, Cassandra!");
+/// ```
+#[derive(Debug)]
+pub struct ArchiveEditor<T> {
+	archive: Archive<T>,
+	// Entries and whether they must be re-signed
+	entries: HashMap<EntryId, (RegistryEntry, bool)>,
+	// Processed data of appended and replacing leaves, the rest is read from the source
+	added: HashMap<EntryId, Vec<u8>>,
+	#[cfg(feature = "crypto")]
+	signing_key: Option<crypto::SigningKey>,
+	#[cfg(feature = "crypto")]
+	encryption_key: Option<crypto::EncryptionKey>,
+	#[cfg(feature = "crypto")]
+	key_epoch: u32,
+	// Give the archive a build ID on commit, see `ArchiveEditor::resign`
+	#[cfg(feature = "crypto")]
+	migrate: bool,
+}
+
+impl<T: Read + Seek> ArchiveEditor<T> {
+	/// Start editing the given [`Archive`]
+	pub fn new(archive: Archive<T>) -> ArchiveEditor<T> {
+		let entries = archive
+			.registry()
+			.map(|(id, entry)| (id.clone(), (entry.clone(), false)))
+			.collect();
+
+		ArchiveEditor {
+			archive,
+			entries,
+			added: HashMap::new(),
+			#[cfg(feature = "crypto")]
+			signing_key: None,
+			#[cfg(feature = "crypto")]
+			encryption_key: None,
+			#[cfg(feature = "crypto")]
+			key_epoch: 0,
+			#[cfg(feature = "crypto")]
+			migrate: false,
+		}
+	}
+
+	/// Provide the key used to re-sign signed entries whose `ID` or location changed, or all signed entries after [`ArchiveEditor::resign`]
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn signing_key(&mut self, key: crypto::SigningKey) -> &mut Self {
+		self.signing_key = Some(key);
+		self
+	}
+
+	/// Provide the key new leaves are encrypted with, when the archive was written using `BuilderConfig::encryption_key`.
+	/// Otherwise the key is derived from the signing key's public key, like `Builder::dump` does
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn encryption_key(&mut self, key: crypto::EncryptionKey) -> &mut Self {
+		self.encryption_key = Some(key);
+		self
+	}
+
+	/// The epoch of the encryption key, when it was rotated since the archive was written, see `BuilderConfig::key_epoch`.
+	/// Entries already in the archive keep their epoch, so loaders need the keys of both, see `ArchiveConfig::add_decryption_key`
+	#[cfg(feature = "crypto")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+	pub fn key_epoch(&mut self, epoch: u32) -> &mut Self {
+		self.key_epoch = epoch;
+		self
+	}
+
+	/// Marks every signed entry to be re-signed on commit, eg to rotate the key an archive is signed with.
+	/// Archives signed without a build ID are given one, binding their signatures to the archive, see `ArchiveConfig::legacy_signatures`.
+	/// Signatures are not checked beforehand, verify the source first to avoid vouching for tampered data. Returns the number of signed entries
+	pub fn resign(&mut self) -> usize {
+		let signed = self
+			.entries
+			.values_mut()
+			.filter(|(entry, _)| entry.flags.contains(Flags::SIGNED_FLAG))
+			.map(|(_, resign)| *resign = true)
+			.count();
+
+		#[cfg(feature = "crypto")]
+		{
+			self.migrate = signed > 0 && self.archive.header.build_id().is_none();
+		}
+
+		signed
+	}
+
+	/// Renames an entry, fails if no entry has the `ID` `from` or an entry with the `ID` `to` already exists
+	pub fn rename(&mut self, from: impl AsRef<str>, to: impl AsRef<str>) -> InternalResult {
+		let to = EntryId::new(to)?;
+		to.unreserved()?;
+
+		if self.entries.contains_key(&to) {
+			return Err(InternalError::LeafAppendError(to));
+		};
+
+		// Reserved entries can't be moved, like they can't be listed
+		let from = EntryId::normalized(from);
+		let (mut entry, _) = match (!from.is_reserved()).then(|| self.entries.remove(&from)).flatten() {
+			Some(entry) => entry,
+			None => return Err(InternalError::MissingResourceError(from)),
+		};
+
+		if let Some(data) = self.added.remove(&entry.id) {
+			self.added.insert(to.clone(), data);
+		}
+
+		entry.id = to.clone();
+		self.entries.insert(to, (entry, true));
+
+		Ok(())
+	}
+
+	/// Replaces the prefix `from` with `to` in every `ID` starting with `from`, returns the number of renamed entries.
+	/// Fails with [`InternalError::LeafAppendError`] if a new `ID` is taken, renaming nothing
+	pub fn rename_prefix(&mut self, from: impl AsRef<str>, to: impl AsRef<str>) -> InternalResult<usize> {
+		let (from, to) = (from.as_ref(), to.as_ref());
+
+		let mut matching = self
+			.entries
+			.keys()
+			.filter(|id| id.starts_with(from) && !id.is_reserved())
+			.cloned()
+			.collect::<Vec<_>>();
+
+		// Deterministic order, so collisions are reported consistently
+		matching.sort();
+
+		// Every new `ID` is built and checked before any entry is touched, so a failed rename leaves the editor as it was
+		let renames = matching
+			.into_iter()
+			.map(|id| {
+				let renamed = EntryId::new(format!("{}{}", to, &id[from.len()..]))?;
+				renamed.unreserved()?;
+				Ok((id, renamed))
+			})
+			.collect::<InternalResult<Vec<_>>>()?;
+
+		// Matches move out of the way, so renames within the prefix can't collide with each other
+		let moved = renames.iter().map(|(id, _)| id).collect::<HashSet<_>>();
+		let mut taken = HashSet::with_capacity(renames.len());
+
+		for (_, renamed) in &renames {
+			if !taken.insert(renamed) || (self.entries.contains_key(renamed) && !moved.contains(renamed)) {
+				return Err(InternalError::LeafAppendError(renamed.clone()));
+			}
+		}
+
+		let entries = renames
+			.iter()
+			.filter_map(|(id, renamed)| self.entries.remove(id).map(|(entry, _)| (entry, renamed)))
+			.collect::<Vec<_>>();
+
+		for (mut entry, renamed) in entries {
+			if let Some(data) = self.added.remove(&entry.id) {
+				self.added.insert(renamed.clone(), data);
+			}
+
+			entry.id = renamed.clone();
+			self.entries.insert(renamed.clone(), (entry, true));
+		}
+
+		Ok(renames.len())
+	}
+
+	/// Appends a new leaf, failing with [`InternalError::LeafAppendError`] if an entry with it's `ID` exists, or [`InternalError::InvalidIdError`] if it's `ID` is reserved. Only the new leaf's data is written on commit,
+	/// after the existing leaves; with [`ArchiveEditor::commit_in_place`] the rest of the archive stays where it is.
+	///
+	/// The leaf is processed right away, like `Builder::dump` would: compressed, encrypted and run through it's pipeline.
+	/// Encrypted leaves need [`ArchiveEditor::signing_key`] or [`ArchiveEditor::encryption_key`] set beforehand, signed ones the signing key on commit.
+	/// Dependencies, tags and validity windows are stored in reserved leaves the editor doesn't rewrite, so leaves with any fail with [`InternalError::InvalidConfigError`]
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut builder = Builder::new();
+	/// builder.add(b"Hello, Cassandra!" as &[u8], "greeting").unwrap();
+	///
+	/// let mut source = Cursor::new(Vec::new());
+	/// builder.dump(&mut source, &BuilderConfig::default()).unwrap();
+	///
+	/// let mut editor = ArchiveEditor::new(Archive::new(source).unwrap());
+	/// editor.append(Leaf::new(b"Goodbye, Cassandra!" as &[u8]).id("farewell")).unwrap();
+	/// editor.replace("greeting", Leaf::new(b"Hi, Cassandra!" as &[u8])).unwrap();
+	///
+	/// let archive = Archive::new(editor.commit_in_place().unwrap()).unwrap();
+	/// assert_eq!(archive.fetch("farewell").unwrap().data.as_ref(), b"Goodbye, Cassandra!");
+	/// assert_eq!(archive.fetch("greeting").unwrap().data.as_ref(), b"Hi, Cassandra!");
+	/// ```
+	pub fn append(&mut self, mut leaf: Leaf<'_>) -> InternalResult {
+		leaf.qualify()?;
+
+		if self.entries.contains_key(&leaf.id) {
+			return Err(InternalError::LeafAppendError(leaf.id));
+		}
+
+		if self.entries.len() >= u16::MAX as usize {
+			return Err(InternalError::InvalidConfigError(format!(
+				"An archive holds at most {} leaves",
+				u16::MAX
+			)));
+		}
+
+		self.insert(leaf)
+	}
+
+	/// Replaces the data of an existing entry with a new leaf, processed like in [`ArchiveEditor::append`]. The leaf's own `ID` and namespace are ignored.
+	/// On commit the new data takes the old leaf's place if it fits there, otherwise it's written after the existing leaves and the old data is left unreferenced.
+	/// Signed replacements are re-signed, so a replaced signed leaf needs the signing key even if the new leaf isn't signed
+	pub fn replace(&mut self, id: impl AsRef<str>, mut leaf: Leaf<'_>) -> InternalResult {
+		let (entry, _) = self.entry_mut(id.as_ref())?;
+
+		leaf.id = entry.id.clone();
+		leaf.namespace = None;
+		self.insert(leaf)
+	}
+
+	fn insert(&mut self, mut leaf: Leaf<'_>) -> InternalResult {
+		leaf.id.validate()?;
+		leaf.id.unreserved()?;
+
+		if !leaf.dependencies.is_empty() || !leaf.tags.is_empty() || !leaf.validity.is_unbounded() {
+			return Err(InternalError::InvalidConfigError(format!(
+				"{} has dependencies, tags or a validity window, which the editor can't record. Rebuild the archive instead",
+				leaf.id
+			)));
+		}
+
+		#[cfg(feature = "compression")]
+		if !matches!(leaf.compress, CompressMode::Never | CompressMode::Store) {
+			leaf.compression_algo
+				.validate()
+				.map_err(|err| err.context(&leaf.id, None, Stage::Compress))?;
+		}
+
+		if !leaf.pipeline.is_empty() {
+			leaf.validate_pipeline()
+				.map_err(|err| err.context(&leaf.id, None, Stage::Custom))?;
+		}
+
+		// Mirrors how `Builder::dump` picks the key
+		#[cfg(feature = "crypto")]
+		let encryptor = match (leaf.encrypt, &self.encryption_key, &self.signing_key) {
+			(false, _, _) => None,
+			(true, Some(key), _) => Some(Encryptor::from_key(&key.to_bytes(), self.archive.header.magic)),
+			(true, None, Some(key)) => Some(Encryptor::new(&key.verifying_key(), self.archive.header.magic)),
+			(true, None, None) => return Err(InternalError::NoKeypairError.context(&leaf.id, None, Stage::Encrypt)),
+		};
+
+		let prepared = Builder::process_leaf(
+			&mut leaf,
+			#[cfg(feature = "crypto")]
+			encryptor.as_ref(),
+			#[cfg(feature = "crypto")]
+			self.key_epoch,
+			#[cfg(feature = "compression")]
+			&DetectHeuristics::default(),
+			#[cfg(feature = "compression")]
+			Guardrail::default(),
+		)?;
+
+		let mut entry = prepared.entry;
+		entry.offset = prepared.data.len() as u64;
+
+		#[cfg(feature = "crypto")]
+		entry.flags.force_set(Flags::SIGNED_FLAG, prepared.sign);
+
+		self.added.insert(entry.id.clone(), prepared.data);
+		self.entries.insert(entry.id.clone(), (entry, true));
+
+		Ok(())
+	}
+
+	/// Sets the content version of an entry, re-signing it on commit if it's signed
+	pub fn set_content_version(&mut self, id: impl AsRef<str>, version: u8) -> InternalResult {
+		let (entry, resign) = self.entry_mut(id.as_ref())?;
+		entry.content_version = version;
+		*resign = true;
+
+		Ok(())
+	}
+
+	/// Replaces the user defined bits of an entry's flags, reserved bits are kept as they are.
+	/// Fails with [`InternalError::RestrictedFlagAccessError`] if `flags` contains a reserved bit, see [`Flags::set`]
+	pub fn set_flags(&mut self, id: impl AsRef<str>, flags: Flags) -> InternalResult {
+		if flags.bits() & Flags::RESERVED_MASK != 0 {
+			return Err(InternalError::RestrictedFlagAccessError);
+		}
+
+		let (entry, resign) = self.entry_mut(id.as_ref())?;
+		entry.flags = Flags::from_bits((entry.flags.bits() & Flags::RESERVED_MASK) | flags.bits());
+		*resign = true;
+
+		Ok(())
+	}
+
+	fn entry_mut(&mut self, id: &str) -> InternalResult<&mut (RegistryEntry, bool)> {
+		match self.entries.get_mut(normalize(id).as_ref()) {
+			Some((entry, _)) if entry.id.is_reserved() => Err(InternalError::MissingResourceError(EntryId::from_raw(id))),
+			Some(entry) => Ok(entry),
+			None => Err(InternalError::MissingResourceError(EntryId::from_raw(id))),
+		}
+	}
+
+	/// Drops every tombstone, eg: once a patch archive has been merged into it's base. Returns the number of tombstones dropped
+	pub fn compact(&mut self) -> usize {
+		let before = self.entries.len();
+		self.entries.retain(|_, (entry, _)| !entry.is_tombstone());
+		before - self.entries.len()
+	}
+
+	/// Writes the edited archive into `target`, copying leaves from the source, followed by appended and replacing leaves. Returns the number of leaf bytes written.
+	/// Entries may move within the registry, so an `ID` index written using `BuilderConfig::id_index` is dropped and a compressed registry is written back uncompressed.
+	/// Archives of the [`LEGACY_VERSION`](crate::LEGACY_VERSION) are written in the current one, re-signing their signed entries
+	pub fn commit<W: Write + Seek>(self, mut target: W) -> InternalResult<u64> {
+		let ArchiveEditor {
+			mut archive,
+			mut entries,
+			mut added,
+			#[cfg(feature = "crypto")]
+			signing_key,
+			#[cfg(feature = "crypto")]
+			migrate,
+			..
+		} = self;
+
+		entries.remove(INDEX_ID);
+
+		// Keep leaves in the order they appear in the source, new leaves follow in `ID` order
+		let mut entries = entries.into_values().collect::<Vec<_>>();
+		entries.sort_by(|(a, _), (b, _)| {
+			let key = |e: &RegistryEntry| (added.contains_key(&e.id), e.location);
+			key(a).cmp(&key(b)).then_with(|| a.id.cmp(&b.id))
+		});
+
+		// Without the crypto feature, signatures are discarded when parsing and can't be written back
+		#[cfg(not(feature = "crypto"))]
+		if entries.iter().any(|(e, _)| e.flags.contains(Flags::SIGNED_FLAG)) {
+			return Err(InternalError::MissingFeatureError("crypto"));
+		};
+
+		// Prefixed registries stay prefixed, and in `ID` order
+		let prefixed = archive.header.capabilities.contains(Capabilities::PREFIXED_IDS);
+
+		let mut ids = entries
+			.iter()
+			.map(|(e, _)| (e.id.as_ref(), e.flags.contains(Flags::SIGNED_FLAG)))
+			.collect::<Vec<_>>();
+
+		if prefixed {
+			ids.sort_unstable();
+		}
+
+		let mut previous = prefixed.then_some("");
+		let registry_size = ids
+			.into_iter()
+			.map(|(id, signed)| {
+				let size = RegistryEntry::encoded_size(id, previous, signed);
+				previous = previous.map(|_| id);
+				size as u64
+			})
+			.sum::<u64>();
+
+		let mut header = Header {
+			capacity: entries.len() as u16,
+			..archive.header
+		};
+
+		// Legacy archives are upgraded to the current spec, declaring the capabilities of every entry
+		let legacy = header.is_legacy();
+		if legacy {
+			header.arch_version = crate::VERSION;
+			header.min_loader_version = crate::VERSION;
+		}
+
+		// Compressed registries are written back as is, right after the header
+		header.remove_extension(Header::COMPRESSED_REGISTRY);
+		header.capabilities.remove(Capabilities::from_bits(Capabilities::COMPRESSED_REGISTRY));
+
+		for (entry, _) in entries.iter().filter(|(e, _)| legacy || added.contains_key(&e.id)) {
+			header.capabilities.insert(Capabilities::of(&entry.flags));
+
+			if entry.flags.contains(Flags::SIGNED_FLAG) {
+				header.flags.force_set(Flags::SIGNED_FLAG, true);
+			}
+		}
+
+		#[cfg(feature = "crypto")]
+		if migrate {
+			header.set_extension(Header::BUILD_ID, rand::random::<[u8; 16]>().to_vec());
+		}
+
+		#[cfg(feature = "crypto")]
+		let domain = header.signature_domain().unwrap_or_default();
+
+		// Leaves are shifted by the change in the size of the header and registry
+		let old_start = entries
+			.iter()
+			.filter(|(e, _)| !added.contains_key(&e.id))
+			.map(|(e, _)| e.location)
+			.min()
+			.unwrap_or(0);
+		let new_start = header.size() as u64 + registry_size;
+
+		// Write Header
+		target.seek(SeekFrom::Start(0))?;
+		target.write_all(&header.to_bytes())?;
+
+		let handle = sync::get_mut(&mut archive.handle);
+		let mut written = Vec::with_capacity(entries.len());
+		let mut bytes_written = 0;
+		let mut end = new_start;
+
+		for (mut entry, resign) in entries {
+			let (raw, moved) = match added.remove(&entry.id) {
+				Some(raw) => {
+					entry.location = end;
+					(raw, true)
+				},
+				None => {
+					let raw = Archive::read_raw(handle, &entry)?;
+					entry.location = entry.location - old_start + new_start;
+					(raw, old_start != new_start)
+				},
+			};
+
+			end = end.max(entry.location + entry.offset);
+
+			// Legacy signatures covered the legacy entry layout
+			#[cfg(feature = "crypto")]
+			if entry.flags.contains(Flags::SIGNED_FLAG) && (resign || moved || legacy) {
+				sign(signing_key.as_ref(), &domain, &mut entry, &raw)?;
+			};
+
+			#[cfg(not(feature = "crypto"))]
+			let _ = (resign, moved);
+
+			target.seek(SeekFrom::Start(entry.location))?;
+			target.write_all(&raw)?;
+			bytes_written += raw.len() as u64;
+
+			written.push(entry);
+		}
+
+		if prefixed {
+			written.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+		}
+
+		let registry_start = header.size() as u64;
+		let mut registry = Vec::with_capacity(registry_size as usize);
+		let mut previous = "";
+
+		for entry in &written {
+			match prefixed {
+				true => registry.extend_from_slice(&entry.to_bytes_prefixed(previous)?),
+				false => registry.extend_from_slice(&entry.to_bytes(false)?),
+			}
+
+			previous = &entry.id;
+		}
+
+		target.seek(SeekFrom::Start(registry_start))?;
+		target.write_all(&registry)?;
+
+		// The footer follows the last leaf, mirroring the rewritten header
+		if header.extension(Header::FOOTER).is_some() {
+			let mut mirror = [0; Header::BASE_SIZE];
+			mirror.copy_from_slice(&header.to_bytes()[..Header::BASE_SIZE]);
+
+			let footer = RawFooter {
+				header: mirror,
+				archive_size: end + RawFooter::SIZE as u64,
+			};
+
+			target.seek(SeekFrom::Start(end))?;
+			target.write_all(&footer.encode())?;
+		}
+
+		Ok(bytes_written)
+	}
+}
+
+impl<T: Read + Write + Seek> ArchiveEditor<T> {
+	/// Rewrites only the registry of the source archive and the data of appended and replacing leaves, in place, leaving other leaves untouched. Returns the source.
+	/// Suits metadata changes on large archives, like [`ArchiveEditor::set_flags`] and [`ArchiveEditor::set_content_version`], or patching a few leaves, where `commit` would copy every leaf.
+	/// Fails with [`InternalError::InvalidConfigError`] if an entry was renamed or dropped, the registry is compressed or the archive is legacy, use `commit` instead.
+	///
+	/// New data is written after the last leaf, or over the leaf it replaces if it fits. When appended entries grow the registry past the first leaves,
+	/// those leaves are moved after the last one, and re-signed if signed. Archives with an `ID` index can't have entries appended in place.
+	/// The registry is encoded, and edited signed entries re-signed, before anything is written, so errors leave the source untouched.
+	/// Unlike `commit` into a temporary file that then replaces the original, an interrupted write can leave the archive corrupt.
+	///
+	/// Signatures are made afresh from the data on disk, never from results recorded by `Archive::verify_in_background`. Those assume an unchanged source,
+	/// so other `Archive`s open on the same file must be reopened once it's rewritten
+	pub fn commit_in_place(self) -> InternalResult<T> {
+		let ArchiveEditor {
+			mut archive,
+			mut entries,
+			mut added,
+			#[cfg(feature = "crypto")]
+			signing_key,
+			#[cfg(feature = "crypto")]
+			migrate,
+			..
+		} = self;
+
+		// A build ID grows the header
+		#[cfg(feature = "crypto")]
+		if migrate {
+			return Err(InternalError::InvalidConfigError(
+				"Giving the archive a build ID changes the header's size, it can't be rewritten in place".to_string(),
+			));
+		}
+
+		// Legacy entries are smaller, the registry can't be rewritten in the current layout without moving leaves
+		if archive.header.is_legacy() {
+			return Err(InternalError::InvalidConfigError(
+				"The archive was written in the legacy spec version, it can't be rewritten in place".to_string(),
+			));
+		}
+
+		// The leaves start right after the header, the registry can't grow there
+		if archive.header.capabilities.contains(Capabilities::COMPRESSED_REGISTRY) {
+			return Err(InternalError::InvalidConfigError(
+				"The archive's registry is compressed, it can't be rewritten in place".to_string(),
+			));
+		}
+
+		// Entries are only added or changed in place, every entry must keep it's `ID`
+		if !archive.registry().all(|(id, _)| entries.contains_key(id)) {
+			return Err(InternalError::InvalidConfigError(
+				"Entries were renamed or removed, the registry can't be rewritten in place".to_string(),
+			));
+		}
+
+		// New `ID`s would be missing from the index, which can't be rebuilt in place
+		let mut appended = entries
+			.keys()
+			.filter(|id| archive.fetch_entry_ref(id).is_none())
+			.cloned()
+			.collect::<Vec<_>>();
+
+		if !appended.is_empty() && archive.fetch_entry_ref(INDEX_ID).is_some() {
+			return Err(InternalError::InvalidConfigError(
+				"The archive has an `ID` index, entries can't be appended in place".to_string(),
+			));
+		}
+
+		// Without the crypto feature, signatures are discarded when parsing and can't be written back
+		#[cfg(not(feature = "crypto"))]
+		if entries.values().any(|(e, _)| e.flags.contains(Flags::SIGNED_FLAG)) {
+			return Err(InternalError::MissingFeatureError("crypto"));
+		};
+
+		// Where replaced leaves were, and where the data of the source ends
+		let slots = added
+			.keys()
+			.filter_map(|id| archive.fetch_entry_ref(id).map(|e| (id.clone(), (e.location, e.offset))))
+			.collect::<HashMap<_, _>>();
+
+		let data_end = archive.registry().map(|(_, e)| e.location + e.offset).max();
+
+		let prefixed = archive.header.capabilities.contains(Capabilities::PREFIXED_IDS);
+		let registry_start = archive.header.size() as u64;
+		let handle = sync::get_mut(&mut archive.handle);
+
+		// Entries are written back in their original order, read from the source
+		handle.seek(SeekFrom::Start(registry_start))?;
+		let mut previous = String::new();
+		let mut order = Vec::with_capacity(entries.len());
+
+		for _ in 0..archive.header.capacity {
+			let entry = RegistryEntry::from_handle(&mut *handle, prefixed.then_some(previous.as_str()))?;
+			previous.clear();
+			previous.push_str(&entry.id);
+			order.push(entry.id);
+		}
+
+		let registry_end = handle.stream_position()?;
+
+		// Appended entries follow in `ID` order, prefixed registries stay in `ID` order
+		appended.sort_unstable();
+		order.extend(appended);
+
+		if prefixed {
+			order.sort_unstable();
+		}
+
+		let mut previous = prefixed.then_some("");
+		let registry_size = order
+			.iter()
+			.map(|id| {
+				let signed = entries.get(id).is_some_and(|(e, _)| e.flags.contains(Flags::SIGNED_FLAG));
+				let size = RegistryEntry::encoded_size(id, previous, signed);
+				previous = previous.map(|_| id.as_ref());
+				size as u64
+			})
+			.sum::<u64>();
+
+		// Leaves before this point are overwritten by the registry
+		let leaves_start = registry_start + registry_size;
+		let mut end = data_end.unwrap_or(registry_end).max(leaves_start);
+
+		let mut writes = Vec::new();
+		let mut registry = Vec::with_capacity(registry_size as usize);
+		let mut previous = "";
+
+		for id in &order {
+			#[allow(unused_mut)]
+			let (mut entry, resign) = entries.remove(id).ok_or_else(|| InternalError::MissingResourceError(id.clone()))?;
+
+			let (raw, moved) = match added.remove(id) {
+				Some(raw) => {
+					entry.location = match slots.get(id) {
+						Some(&(location, offset)) if location >= leaves_start && raw.len() as u64 <= offset => location,
+						_ => end,
+					};
+
+					(Some(raw), true)
+				},
+				None if entry.offset > 0 && entry.location < leaves_start => {
+					let raw = Archive::read_raw(handle, &entry)?;
+					entry.location = end;
+
+					(Some(raw), true)
+				},
+				None => (None, false),
+			};
+
+			end = end.max(entry.location + entry.offset);
+
+			#[cfg(feature = "crypto")]
+			if entry.flags.contains(Flags::SIGNED_FLAG) && (resign || moved) {
+				let raw = match &raw {
+					Some(raw) => raw,
+					None => &Archive::read_raw(handle, &entry)?,
+				};
+
+				let domain = archive.header.signature_domain().unwrap_or_default();
+				sign(signing_key.as_ref(), &domain, &mut entry, raw)?;
+			};
+
+			#[cfg(not(feature = "crypto"))]
+			let _ = (resign, moved);
+
+			if raw.is_some() {
+				archive.header.capabilities.insert(Capabilities::of(&entry.flags));
+
+				if entry.flags.contains(Flags::SIGNED_FLAG) {
+					archive.header.flags.force_set(Flags::SIGNED_FLAG, true);
+				}
+			}
+
+			match prefixed {
+				true => registry.extend_from_slice(&entry.to_bytes_prefixed(previous)?),
+				false => registry.extend_from_slice(&entry.to_bytes(false)?),
+			}
+
+			writes.extend(raw.map(|raw| (entry.location, raw)));
+			previous = id;
+		}
+
+		// Guards against writing past the registry, into the first leaf
+		if registry.len() as u64 != registry_size {
+			return Err(InternalError::OtherError("The registry changed size, it can't be rewritten in place".into()));
+		}
+
+		let inner = handle.get_mut();
+
+		// Leaf data goes first, so the old registry still points at intact leaves if the write is interrupted early
+		for (location, raw) in &writes {
+			inner.seek(SeekFrom::Start(*location))?;
+			inner.write_all(raw)?;
+		}
+
+		archive.header.capacity = order.len() as u16;
+
+		// The footer follows the last leaf, mirroring the rewritten header
+		if archive.header.extension(Header::FOOTER).is_some() {
+			let mut mirror = [0; Header::BASE_SIZE];
+			mirror.copy_from_slice(&archive.header.to_bytes()[..Header::BASE_SIZE]);
+
+			let footer = RawFooter {
+				header: mirror,
+				archive_size: end + RawFooter::SIZE as u64,
+			};
+
+			inner.seek(SeekFrom::Start(end))?;
+			inner.write_all(&footer.encode())?;
+		}
+
+		inner.seek(SeekFrom::Start(registry_start))?;
+		inner.write_all(&registry)?;
+
+		inner.seek(SeekFrom::Start(0))?;
+		inner.write_all(&archive.header.to_bytes())?;
+		inner.flush()?;
+
+		archive
+			.into_inner()
+			.map_err(|_| InternalError::OtherError("The archive's handle is poisoned".into()))
+	}
+}
+
+/// Signs an entry and the given raw data of it's leaf under the archive's signature domain
+#[cfg(feature = "crypto")]
+fn sign(key: Option<&crypto::SigningKey>, domain: &[u8], entry: &mut RegistryEntry, raw: &[u8]) -> InternalResult {
+	let context = Some(entry.location);
+
+	match key {
+		Some(key) => {
+			let entry_bytes = entry
+				.to_bytes(true)
+				.map_err(|err| err.context(&entry.id, context, Stage::Sign))?;
+
+			let mut message = Vec::with_capacity(domain.len() + raw.len() + entry_bytes.len());
+			message.extend_from_slice(domain);
+			message.extend_from_slice(raw);
+			message.extend_from_slice(&entry_bytes);
+
+			entry.signature = Some(key.sign(&message));
+
+			Ok(())
+		},
+		None => Err(InternalError::NoKeypairError.context(&entry.id, context, Stage::Sign)),
+	}
+}
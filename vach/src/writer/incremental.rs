@@ -0,0 +1,126 @@
+use std::{
+	io::{self, Cursor, Read, Seek, Write},
+	ops::DerefMut,
+	sync::Arc,
+};
+
+use super::{dump_report, Builder, BuilderConfig, DumpReport};
+use crate::{
+	global::{error::*, reg_entry::RegistryEntry, sync},
+	loader::archive::Archive,
+};
+
+impl<'a> Builder<'a> {
+	/// Dumps every queued [`Leaf`](super::Leaf) into `target`, like `Builder::dump_report`, but only processes dirty leaves: those added or staged since the last call.
+	/// Clean leaves are copied as is from `existing`, the archive written by the last call, without reading their sources or compressing and encrypting them again;
+	/// they're only re-signed, since their location changes. Reserved leaves are always written anew. The [`Builder`] is kept, for the next call.
+	///
+	/// Clean leaves are assumed to be configured as they were when `existing` was written, and `config` to use the same magic and keys.
+	/// Fails with [`InternalError::MissingResourceError`] if a clean leaf is missing from `existing`, pass `None` when every leaf is dirty, eg: on the first call.
+	/// Every leaf's handle is spent by the dump, so on failure leaves must be staged again before the next call, which fails otherwise
+	/// ```
+	/// use std::io::Cursor;
+	/// use vach::prelude::*;
+	///
+	/// let mut builder = Builder::new();
+	/// builder.add(b"Hello, Cassandra!" as &[u8], "hello").unwrap();
+	/// builder.add(b"Goodbye, Cassandra!" as &[u8], "goodbye").unwrap();
+	///
+	/// // The first dump processes every leaf
+	/// let mut first = Cursor::new(Vec::new());
+	/// let report = builder.dump_incremental(None::<Archive<Cursor<Vec<u8>>>>, &mut first, &BuilderConfig::default()).unwrap();
+	/// assert_eq!(report.reused, 0);
+	///
+	/// // Later dumps only process what changed
+	/// builder.stage(Leaf::new(b"Hi, Cassandra!" as &[u8]).id("hello")).unwrap();
+	///
+	/// let mut second = Cursor::new(Vec::new());
+	/// let report = builder.dump_incremental(Some(Archive::new(first).unwrap()), &mut second, &BuilderConfig::default()).unwrap();
+	/// assert_eq!(report.reused, 1);
+	///
+	/// let archive = Archive::new(second).unwrap();
+	/// assert_eq!(archive.fetch("hello").unwrap().data.as_ref(), b"Hi, Cassandra!");
+	/// ```
+	pub fn dump_incremental<R, W>(
+		&mut self, existing: Option<Archive<R>>, target: W, config: &BuilderConfig,
+	) -> InternalResult<DumpReport>
+	where
+		R: Read + Seek + Send + 'a,
+		W: Write + Seek + Send,
+	{
+		let existing = existing.map(Arc::new);
+		let mut reused = 0;
+
+		for leaf in self.leafs.iter_mut().filter(|leaf| !self.dirty.contains(&leaf.id)) {
+			let entry = existing
+				.as_ref()
+				.and_then(|archive| archive.fetch_entry_ref(&leaf.id).map(|entry| (archive, entry)));
+
+			let Some((archive, entry)) = entry else {
+				return Err(InternalError::MissingResourceError(leaf.id.clone()));
+			};
+
+			// Tombstones are written anew, there's nothing to copy
+			if entry.is_tombstone() {
+				continue;
+			}
+
+			leaf.verbatim = Some(entry.clone());
+			leaf.handle = Box::new(Verbatim {
+				archive: Arc::clone(archive),
+				entry: entry.clone(),
+				data: None,
+			});
+
+			reused += 1;
+		}
+
+		let result = dump_report(target, &mut self.leafs, config);
+
+		// Handles are spent either way, and those of clean leaves hold on to `existing`
+		for leaf in self.leafs.iter_mut() {
+			leaf.verbatim = None;
+			leaf.handle = Box::new(Spent);
+		}
+
+		let mut report = result?;
+		report.reused = reused;
+
+		self.dirty.clear();
+		Ok(report)
+	}
+}
+
+/// Reads the raw data of a leaf out of an earlier dump, only once the leaf is processed, see `Builder::dump_incremental`
+struct Verbatim<R> {
+	archive: Arc<Archive<R>>,
+	entry: RegistryEntry,
+	data: Option<Cursor<Vec<u8>>>,
+}
+
+impl<R: Read + Seek> Read for Verbatim<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let data = match &mut self.data {
+			Some(data) => data,
+			None => {
+				let raw = {
+					let mut guard = sync::lock(&self.archive.handle);
+					Archive::read_raw(guard.deref_mut(), &self.entry).map_err(io::Error::other)?
+				};
+
+				self.data.insert(Cursor::new(raw))
+			},
+		};
+
+		data.read(buf)
+	}
+}
+
+/// The handle of a leaf whose data was consumed by an earlier dump
+struct Spent;
+
+impl Read for Spent {
+	fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+		Err(io::Error::other("The leaf's data was consumed by an earlier dump, stage it again"))
+	}
+}
@@ -1,8 +1,15 @@
 #[cfg(feature = "compression")]
-use crate::global::compressor::CompressionAlgorithm;
-use crate::global::{reg_entry::RegistryEntry, flags::Flags};
+use crate::global::compressor::{BrotliParams, CompressionAlgorithm};
+use crate::global::{
+	entry_id::{normalize, EntryId, NAMESPACE_SEPARATOR},
+	error::*,
+	flags::Flags,
+	pipeline::{self, PipelineStage},
+	reg_entry::{RegistryEntry, ResidencyHint},
+	validity::Validity,
+};
 
-use std::{fmt, io::Read, sync::Arc};
+use std::{borrow::Cow, fmt, io::Read, time::SystemTime};
 
 /// Configures how `Leaf`s should be compressed.
 /// Default is `CompressMode::Never`.
@@ -17,6 +24,46 @@ pub enum CompressMode {
 	Always,
 	/// The compressed data is used, only if it is smaller than the original data.
 	Detect,
+	/// The data is copied in as is, skipping all processing: it's never compressed, encrypted, run through a custom pipeline or signed,
+	/// overriding `Leaf::encrypt`, `Leaf::sign` and `Leaf::pipeline`. Loaders can then hand out the stored bytes directly, eg: `Archive::extract_entry_to_file`
+	Store,
+}
+
+/// How line endings in a [`Leaf`]'s data are stored, see `Leaf::newlines`.
+/// Default is `NewlineMode::Keep`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NewlineMode {
+	/// The data is stored with it's line endings as they are.
+	#[default]
+	Keep,
+	/// Every `\r\n` is stored as `\n`, so text checked out with Windows line endings packs the same as on Unix.
+	Normalize,
+	/// Like `Normalize`, but only for data that looks like text: valid UTF-8 without any `NUL` bytes. Anything else is stored as is.
+	Detect,
+}
+
+impl NewlineMode {
+	/// Normalizes the line endings of `data` if the mode calls for it
+	pub(crate) fn apply(&self, data: Vec<u8>) -> Vec<u8> {
+		let normalize = match self {
+			NewlineMode::Keep => false,
+			NewlineMode::Normalize => true,
+			NewlineMode::Detect => !data.contains(&0) && std::str::from_utf8(&data).is_ok(),
+		};
+
+		if !normalize || !data.windows(2).any(|pair| pair == b"\r\n") {
+			return data;
+		}
+
+		let mut normalized = Vec::with_capacity(data.len());
+		for (idx, byte) in data.iter().enumerate() {
+			if !(*byte == b'\r' && data.get(idx + 1) == Some(&b'\n')) {
+				normalized.push(*byte);
+			}
+		}
+
+		normalized
+	}
 }
 
 /// A wrapper around an [`io::Read`](std::io::Read) handle.
@@ -25,9 +72,11 @@ pub enum CompressMode {
 pub struct Leaf<'a> {
 	/// The lifetime simply reflects to the [`Builder`](crate::builder::Builder)'s lifetime, meaning the handle must live longer than or the same as the Builder
 	pub(crate) handle: Box<dyn Read + Send + Sync + 'a>,
+	/// The entry of an earlier dump whose raw data the handle reads, copied in as is, see `Builder::dump_incremental`
+	pub(crate) verbatim: Option<RegistryEntry>,
 
 	/// The `ID` under which the embedded data will be referenced
-	pub id: Arc<str>,
+	pub id: EntryId,
 	/// The version of the content, allowing you to track obsolete data.
 	pub content_version: u8,
 	/// The flags that will go into the archive write target.
@@ -52,6 +101,20 @@ pub struct Leaf<'a> {
 	#[cfg(feature = "crypto")]
 	#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
 	pub sign: bool,
+	/// When the data is meant to be loaded, recorded in the entry's flags. See `Leaf::hint`
+	pub hint: ResidencyHint,
+	/// How line endings in the data are stored, see [`NewlineMode`]
+	pub newlines: NewlineMode,
+	/// The `ID`s of the leaves this one needs once loaded, see `Leaf::depends_on`
+	pub dependencies: Vec<EntryId>,
+	/// The namespace the leaf is stored in, see `Leaf::namespace`
+	pub namespace: Option<String>,
+	/// When the leaf may be served, see `Leaf::valid_from` and `Leaf::valid_until`
+	pub validity: Validity,
+	/// The groups the leaf belongs to, see `Leaf::tag`
+	pub tags: Vec<String>,
+	/// The order of processing stages, empty for the default of compression then encryption. See `Leaf::pipeline`
+	pub pipeline: &'a [PipelineStage],
 }
 
 impl<'a> Leaf<'a> {
@@ -71,6 +134,32 @@ impl<'a> Leaf<'a> {
 		}
 	}
 
+	/// A tombstone, holding no data. Fetching it fails as if it were missing, and in an [`Overlay`](crate::archive::Overlay) it hides
+	/// the entry with the same `ID` in earlier layers, so patch archives can remove files. `ArchiveEditor::compact` drops tombstones
+	///```
+	/// use vach::prelude::Leaf;
+	///
+	/// let leaf = Leaf::tombstone("levels/cut-content.map");
+	///```
+	pub fn tombstone(id: impl AsRef<str>) -> Leaf<'a> {
+		Leaf {
+			flags: Flags::from_bits(Flags::TOMBSTONE_FLAG),
+			..Leaf::default()
+		}
+		.id(id)
+	}
+
+	/// An empty leaf reserving an `ID`, to be filled in later by rewriting the archive. Placeholders are never compressed,
+	/// encrypted or signed, so their data can be replaced without a key. Fetching one returns an empty [`Resource`](crate::archive::Resource)
+	///```
+	/// use vach::prelude::Leaf;
+	///
+	/// let leaf = Leaf::placeholder("patches/day-one.bin");
+	///```
+	pub fn placeholder(id: impl AsRef<str>) -> Leaf<'a> {
+		Leaf::default().id(id)
+	}
+
 	/// Consume the [Leaf] and return the underlying Boxed handle
 	pub fn into_inner(self) -> Box<dyn Read + Send + 'a> {
 		self.handle
@@ -89,7 +178,11 @@ impl<'a> Leaf<'a> {
 	pub fn template(self, other: &Leaf<'a>) -> Self {
 		Leaf {
 			handle: self.handle,
+			verbatim: self.verbatim,
 			id: self.id,
+			dependencies: other.dependencies.clone(),
+			namespace: other.namespace.clone(),
+			tags: other.tags.clone(),
 			..*other
 		}
 	}
@@ -119,14 +212,14 @@ impl<'a> Leaf<'a> {
 		self
 	}
 
-	/// Setter used to set the `id` field of a [`Leaf`]
+	/// Setter used to set the `id` field of a [`Leaf`], the `ID` is validated once the [`Leaf`] is added to a [`Builder`](crate::builder::Builder)
 	/// ```rust
 	/// use vach::prelude::{Leaf};
 	///
 	/// let leaf = Leaf::default().id("whatzitouya");
 	/// ```
 	pub fn id<S: AsRef<str>>(mut self, id: S) -> Self {
-		self.id = Arc::from(id.as_ref());
+		self.id = EntryId::normalized(id);
 		self
 	}
 
@@ -169,6 +262,213 @@ impl<'a> Leaf<'a> {
 		self.compression_algo = compression_algo;
 		self
 	}
+
+	/// Use [brotli](https://crates.io/crates/brotli) compression with the given parameters, same as `compression_algo(CompressionAlgorithm::Brotli(params))`
+	/// ```rust
+	/// use vach::prelude::{Leaf, BrotliParams, CompressMode};
+	///
+	/// let leaf = Leaf::default().compress(CompressMode::Always).brotli(BrotliParams::new(11).window(24));
+	/// ```
+	#[cfg(feature = "compression")]
+	pub fn brotli(self, params: BrotliParams) -> Self {
+		self.compression_algo(CompressionAlgorithm::Brotli(params))
+	}
+
+	/// Setter for the `newlines` field, see [`NewlineMode`]. Line endings are normalized before any other processing
+	/// ```rust
+	/// use vach::prelude::{Leaf, NewlineMode};
+	///
+	/// let leaf = Leaf::default().newlines(NewlineMode::Detect);
+	/// ```
+	pub fn newlines(mut self, newlines: NewlineMode) -> Self {
+		self.newlines = newlines;
+		self
+	}
+
+	/// Stores every `\r\n` in the data as `\n` when `true`, so archives built on Windows and Unix from the same text sources are byte-identical.
+	/// Shorthand for `Leaf::newlines` with `NewlineMode::Normalize`, or `NewlineMode::Keep` when `false`. Use `NewlineMode::Detect` for a mix of text and binary data
+	/// ```rust
+	/// use vach::prelude::Leaf;
+	///
+	/// let leaf = Leaf::default().normalize_newlines(true);
+	/// ```
+	pub fn normalize_newlines(self, normalize: bool) -> Self {
+		self.newlines(match normalize {
+			true => NewlineMode::Normalize,
+			false => NewlineMode::Keep,
+		})
+	}
+
+	/// Setter for the `hint` field, readable from the registry through `RegistryEntry::hint` without fetching the data
+	/// ```rust
+	/// use vach::prelude::{Leaf, ResidencyHint};
+	///
+	/// let leaf = Leaf::default().hint(ResidencyHint::Preload);
+	/// ```
+	pub fn hint(mut self, hint: ResidencyHint) -> Self {
+		self.hint = hint;
+		self
+	}
+
+	/// Records that this [`Leaf`] needs the given leaves once loaded, eg: a model needing it's materials and textures.
+	/// The graph is stored in a reserved leaf, see `Archive::dependencies` and `Archive::closure`
+	/// ```rust
+	/// use vach::prelude::Leaf;
+	///
+	/// let leaf = Leaf::default().id("models/rock.obj").depends_on(["materials/rock.mat", "textures/rock.png"]);
+	/// ```
+	pub fn depends_on<S: AsRef<str>>(mut self, ids: impl IntoIterator<Item = S>) -> Self {
+		self.dependencies.extend(ids.into_iter().map(EntryId::normalized));
+		self
+	}
+
+	/// Adds the [`Leaf`] to a group, eg: everything a level needs. A leaf can carry several tags, adding a tag twice has no effect.
+	/// Tags are stored in a reserved leaf, see `Archive::fetch_group` and `Archive::unpack_group`
+	/// ```rust
+	/// use vach::prelude::Leaf;
+	///
+	/// let leaf = Leaf::default().id("textures/grass.png").tag("level1").tag("level2");
+	/// ```
+	pub fn tag(mut self, tag: impl Into<String>) -> Self {
+		let tag = tag.into();
+		if !self.tags.contains(&tag) {
+			self.tags.push(tag);
+		}
+
+		self
+	}
+
+	/// The [`Leaf`] is not served before the given time, by loaders using `ArchiveConfig::enforce_validity`. Combine with encryption for content
+	/// shipped ahead of it's unlock date, as the window is only enforced by cooperating loaders. Windows are stored in a reserved leaf, see `Archive::validity`
+	/// ```rust
+	/// use std::time::{Duration, SystemTime};
+	/// use vach::prelude::Leaf;
+	///
+	/// let unlock = SystemTime::now() + Duration::from_secs(7 * 24 * 60 * 60);
+	/// let leaf = Leaf::default().id("events/winter.map").valid_from(unlock);
+	/// ```
+	pub fn valid_from(mut self, time: SystemTime) -> Self {
+		self.validity.valid_from = Some(time);
+		self
+	}
+
+	/// The [`Leaf`] is no longer served from the given time onwards, by loaders using `ArchiveConfig::enforce_validity`. See `Leaf::valid_from`
+	pub fn valid_until(mut self, time: SystemTime) -> Self {
+		self.validity.valid_until = Some(time);
+		self
+	}
+
+	/// Stores the [`Leaf`] within a namespace, eg: `core` or `dlc1`, so separately packaged content can share one archive without `ID` collisions.
+	/// The leaf is stored as `namespace::id`, read it back through `Archive::namespace`
+	/// ```rust
+	/// use vach::prelude::Leaf;
+	///
+	/// let leaf = Leaf::default().id("maps/harbor.map").namespace("dlc1");
+	/// ```
+	pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+		self.namespace = Some(namespace.into());
+		self
+	}
+
+	/// Run the [`Leaf`]'s data through the given stages, in order. The stages that ran are recorded in the archive so the loader can undo them in reverse.
+	/// `Compress` and `Encrypt` still need to be enabled using `Leaf::compress` and `Leaf::encrypt`, and must be in the pipeline when enabled
+	/// ```rust
+	/// use vach::prelude::{Leaf, PipelineStage, InternalResult};
+	///
+	/// // Stores each byte as the difference from the previous one
+	/// fn delta(data: &[u8]) -> InternalResult<Vec<u8>> {
+	///   Ok(data.iter().scan(0u8, |prev, b| Some(b.wrapping_sub(std::mem::replace(prev, *b)))).collect())
+	/// }
+	///
+	/// const PIPELINE: &[PipelineStage] = &[PipelineStage::Custom { flag: 0b1, encode: delta }, PipelineStage::Compress];
+	/// let leaf = Leaf::default().pipeline(PIPELINE);
+	/// ```
+	pub fn pipeline(mut self, stages: &'a [PipelineStage]) -> Self {
+		self.pipeline = stages;
+		self
+	}
+}
+
+impl<'a> Leaf<'a> {
+	/// Moves the namespace into the `ID` and normalizes it, so the rest of the writer only deals with qualified, canonical `ID`s
+	pub(crate) fn qualify(&mut self) -> InternalResult {
+		if let Some(namespace) = self.namespace.take() {
+			if namespace.is_empty() || namespace.contains(NAMESPACE_SEPARATOR) {
+				return Err(InternalError::InvalidConfigError(format!(
+					"Invalid namespace {:?} for {}, namespaces can't be empty or contain `{}`",
+					namespace, self.id, NAMESPACE_SEPARATOR
+				)));
+			}
+
+			self.id = EntryId::from_raw(format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, self.id));
+		}
+
+		// The fields may have been set directly, bypassing `Leaf::id` and `Leaf::depends_on`
+		for id in std::iter::once(&mut self.id).chain(self.dependencies.iter_mut()) {
+			if let Cow::Owned(normalized) = normalize(id) {
+				*id = EntryId::from_raw(normalized);
+			}
+		}
+
+		// Stored leaves skip every stage, including any inherited from a template
+		if self.stored() {
+			self.pipeline = &[];
+			self.newlines = NewlineMode::Keep;
+
+			#[cfg(feature = "crypto")]
+			{
+				self.encrypt = false;
+				self.sign = false;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Whether the leaf uses `CompressMode::Store`
+	pub(crate) fn stored(&self) -> bool {
+		#[cfg(feature = "compression")]
+		return matches!(self.compress, CompressMode::Store);
+		#[cfg(not(feature = "compression"))]
+		return false;
+	}
+
+	/// Whether the custom pipeline compresses after encrypting, with both enabled
+	#[cfg(all(feature = "compression", feature = "crypto"))]
+	pub(crate) fn compresses_ciphertext(&self) -> bool {
+		let position = |stage: fn(&PipelineStage) -> bool| self.pipeline.iter().position(stage);
+		let compress = position(|s| matches!(s, PipelineStage::Compress));
+		let encrypt = position(|s| matches!(s, PipelineStage::Encrypt));
+
+		let compresses = !matches!(self.compress, CompressMode::Never | CompressMode::Store);
+		matches!((compress, encrypt), (Some(c), Some(e)) if compresses && self.encrypt && e < c)
+	}
+
+	/// Checks the custom pipeline can be recorded, and that it runs every stage enabled on this [`Leaf`]
+	pub(crate) fn validate_pipeline(&self) -> InternalResult {
+		pipeline::validate(self.pipeline)?;
+
+		#[cfg(feature = "compression")]
+		if !matches!(self.compress, CompressMode::Never | CompressMode::Store) && !self.pipeline.iter().any(|s| matches!(s, PipelineStage::Compress)) {
+			return Err(missing_stage("Compress"));
+		}
+
+		#[cfg(feature = "crypto")]
+		if self.encrypt && !self.pipeline.iter().any(|s| matches!(s, PipelineStage::Encrypt)) {
+			return Err(missing_stage("Encrypt"));
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(any(feature = "compression", feature = "crypto"))]
+fn missing_stage(stage: &str) -> InternalError {
+	InternalError::InvalidConfigError(format!(
+		"{} is enabled, but the pipeline has no {} stage",
+		stage.to_lowercase(),
+		stage
+	))
 }
 
 impl<'a> Default for Leaf<'a> {
@@ -177,8 +477,9 @@ impl<'a> Default for Leaf<'a> {
 	fn default() -> Leaf<'a> {
 		Leaf {
 			handle: Box::<&[u8]>::new(&[]),
+			verbatim: None,
 
-			id: Arc::from(""),
+			id: EntryId::from_raw(""),
 			flags: Default::default(),
 			content_version: Default::default(),
 
@@ -191,6 +492,14 @@ impl<'a> Default for Leaf<'a> {
 			compress: Default::default(),
 			#[cfg(feature = "compression")]
 			compression_algo: Default::default(),
+
+			hint: Default::default(),
+			newlines: Default::default(),
+			dependencies: Vec::new(),
+			namespace: None,
+			validity: Default::default(),
+			tags: Vec::new(),
+			pipeline: &[],
 		}
 	}
 }
@@ -200,7 +509,9 @@ impl<'a> fmt::Debug for Leaf<'a> {
 		let mut d = f.debug_struct("Leaf");
 		d.field("id", &self.id)
 			.field("content_version", &self.content_version)
-			.field("flags", &self.flags);
+			.field("flags", &self.flags)
+			.field("hint", &self.hint)
+			.field("newlines", &self.newlines);
 
 		#[cfg(feature = "crypto")]
 		{
@@ -214,15 +525,39 @@ impl<'a> fmt::Debug for Leaf<'a> {
 			d.field("compression_algo", &self.compression_algo);
 		}
 
+		if !self.dependencies.is_empty() {
+			d.field("dependencies", &self.dependencies);
+		}
+
+		if let Some(namespace) = &self.namespace {
+			d.field("namespace", namespace);
+		}
+
+		if !self.validity.is_unbounded() {
+			d.field("validity", &self.validity);
+		}
+
+		if !self.tags.is_empty() {
+			d.field("tags", &self.tags);
+		}
+
+		if !self.pipeline.is_empty() {
+			d.field("pipeline", &self.pipeline);
+		}
+
 		d.finish()
 	}
 }
 
 impl From<&mut Leaf<'_>> for RegistryEntry {
 	fn from(leaf: &mut Leaf<'_>) -> Self {
+		let mut flags = leaf.flags;
+		flags.force_set(Flags::RESIDENCY_HINT_MASK, false);
+		flags.force_set(leaf.hint.bits(), true);
+
 		RegistryEntry {
 			id: leaf.id.clone(),
-			flags: leaf.flags,
+			flags,
 			content_version: leaf.content_version,
 			..RegistryEntry::empty()
 		}
@@ -2,39 +2,100 @@ use std::{
 	collections::HashSet,
 	io::{Read, Seek, SeekFrom, Write},
 	path::Path,
-	sync::Arc,
+	time::Instant,
+};
+
+#[cfg(feature = "crypto")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "multithreaded")]
+use std::{
+	collections::BTreeMap,
+	thread,
+	sync::{mpsc, Mutex},
 };
 
 #[cfg(feature = "multithreaded")]
-use std::{thread, sync::mpsc};
+use crate::global::sync;
 
 mod config;
 mod leaf;
+mod pack_set;
 mod prepared;
+mod report;
+mod sink;
+
+#[cfg(feature = "archive")]
+mod editor;
+
+#[cfg(feature = "archive")]
+mod incremental;
+
+#[cfg(all(feature = "archive", feature = "compression"))]
+mod recompress;
 
 pub use config::BuilderConfig;
-pub use leaf::Leaf;
+
+#[cfg(feature = "crypto")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+pub use config::KeyPolicy;
+pub use leaf::{Leaf, NewlineMode};
+pub use pack_set::{PackSet, PackSetReport};
+pub use report::{AlgorithmTotals, DumpReport, StageTimes};
+pub use sink::{DumpSink, SinkWriter};
+
+#[cfg(feature = "archive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
+pub use editor::ArchiveEditor;
+
+#[cfg(all(feature = "archive", feature = "compression"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "archive", feature = "compression"))))]
+pub use recompress::recompress;
 
 #[cfg(feature = "compression")]
 pub use leaf::CompressMode;
 
 #[cfg(feature = "compression")]
-use crate::global::compressor::Compressor;
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub use config::Guardrail;
+
+#[cfg(feature = "compression")]
+use crate::{
+	format::registry::RegistryBlock,
+	global::compressor::{Compressor, DetectHeuristics},
+};
 
 use crate::global::error::*;
-use crate::global::{header::Header, reg_entry::RegistryEntry, flags::Flags};
+use crate::format::footer::RawFooter;
+use crate::global::{
+	dependencies::{self, DEPENDENCIES_ID},
+	descriptor::{FormatDescriptor, DESCRIPTOR_ID},
+	tags::{self, TAGS_ID},
+	validity::{self, VALIDITY_ID},
+	entry_id::EntryId,
+	header::{Capabilities, Header},
+	index::{IdIndex, INDEX_ID},
+	reg_entry::RegistryEntry,
+	flags::Flags,
+	lint::{Linter, LintWarning},
+	path_mapper::PathMapper,
+	pipeline::{self, PipelineStage},
+};
+use crate::testing::CountingWriter;
 
 #[cfg(feature = "crypto")]
-use {crate::crypto::Encryptor, ed25519_dalek::Signer};
-
-#[cfg(not(feature = "crypto"))]
-type Encryptor = ();
+use crate::{
+	crypto::Encryptor,
+	timestamp::{self, ContentHasher},
+};
 
 /// The archive builder. Provides an interface with which one can configure and build valid `vach` archives.
 #[derive(Default)]
 pub struct Builder<'a> {
 	pub(crate) leafs: Vec<Leaf<'a>>,
-	pub(crate) id_set: HashSet<Arc<str>>,
+	pub(crate) id_set: HashSet<EntryId>,
+	// Leaves added or staged since the last `Builder::dump_incremental`
+	dirty: HashSet<EntryId>,
 	leaf_template: Leaf<'a>,
 }
 
@@ -50,7 +111,7 @@ impl<'a> Builder<'a> {
 	/// The second argument is the `ID` with which the embedded data will be tagged
 	pub fn add<D: Read + Send + Sync + 'a>(&mut self, data: D, id: impl AsRef<str>) -> InternalResult {
 		let leaf = Leaf::new(data)
-			.id(id.as_ref().to_string())
+			.id(id.as_ref())
 			.template(&self.leaf_template);
 
 		self.add_leaf(leaf)
@@ -59,6 +120,7 @@ impl<'a> Builder<'a> {
 	/// Removes all the [`Leaf`]s from the [`Builder`]. Leaves the `template` intact. Use this to re-use [`Builder`]s instead of instantiating new ones
 	pub fn clear(&mut self) {
 		self.id_set.clear();
+		self.dirty.clear();
 		self.leafs.clear();
 	}
 
@@ -72,17 +134,18 @@ impl<'a> Builder<'a> {
 		for file in directory {
 			let uri = file?.path();
 
-			let v = uri
-				.iter()
-				.map(|u| String::from(u.to_str().unwrap()))
-				.collect::<Vec<String>>();
-
 			if !uri.is_dir() {
-				// Therefore a file
-				let file = fs::File::open(uri)?;
-				let leaf = Leaf::new(file)
+				// Therefore a file, named after it's parent directory and itself
+				let unmappable = || InternalError::ParseError(format!("{} can't be mapped to an ID", uri.display()));
+				let mut names = uri.iter().rev().map(|name| name.to_str().ok_or_else(unmappable));
+				let (file_name, directory) = match (names.next(), names.next()) {
+					(Some(file_name), Some(directory)) => (file_name?, directory?),
+					_ => return Err(unmappable()),
+				};
+
+				let leaf = Leaf::new(fs::File::open(&uri)?)
 					.template(template.unwrap_or(&self.leaf_template))
-					.id(&format!("{}/{}", v.get(v.len() - 2).unwrap(), v.last().unwrap()));
+					.id(format!("{}/{}", directory, file_name));
 
 				self.add_leaf(leaf)?;
 			}
@@ -91,18 +154,111 @@ impl<'a> Builder<'a> {
 		Ok(())
 	}
 
+	/// Loads all files within a directory and it's subdirectories, `ID`s are assigned by `mapper` from each file's path relative to `path`.
+	/// Files are added in path order, each inheriting from `template` or the [`Builder`]'s template when `None`. Symbolic links are not followed.
+	/// Returns the number of files added
+	/// ```
+	/// use vach::prelude::*;
+	///
+	/// let mut builder = Builder::new();
+	/// builder.add_dir_recursive("test_data", None, &SeparatorMapper).unwrap();
+	/// ```
+	pub fn add_dir_recursive(
+		&mut self, path: impl AsRef<Path>, template: Option<&Leaf<'a>>, mapper: &dyn PathMapper,
+	) -> InternalResult<usize> {
+		use std::fs;
+
+		let root = path.as_ref();
+		let mut pending = vec![root.to_path_buf()];
+		let mut files = Vec::new();
+
+		while let Some(directory) = pending.pop() {
+			for entry in fs::read_dir(directory)? {
+				let entry = entry?;
+				let file_type = entry.file_type()?;
+
+				if file_type.is_dir() {
+					pending.push(entry.path());
+				} else if file_type.is_file() {
+					files.push(entry.path());
+				}
+			}
+		}
+
+		files.sort();
+		for file in &files {
+			let relative = file
+				.strip_prefix(root)
+				.map_err(|_| InternalError::ParseError(format!("{} can't be mapped to an ID", file.display())))?;
+			let id = mapper.to_id(relative)?;
+			let leaf = Leaf::new(fs::File::open(file)?)
+				.template(template.unwrap_or(&self.leaf_template))
+				.id(id);
+
+			self.add_leaf(leaf)?;
+		}
+
+		Ok(files.len())
+	}
+
 	/// Directly add a [`Leaf`] to the [`Builder`]
 	/// [`Leaf`]s added directly do not inherit  data from the [`Builder`]s template.
-	pub fn add_leaf(&mut self, leaf: Leaf<'a>) -> InternalResult {
+	/// Fails with [`InternalError::InvalidIdError`] if the leaf's `ID` is in the [`RESERVED_NAMESPACE`](crate::builder::RESERVED_NAMESPACE)
+	pub fn add_leaf(&mut self, mut leaf: Leaf<'a>) -> InternalResult {
+		leaf.qualify()?;
+		leaf.id.validate()?;
+		leaf.id.unreserved()?;
+
 		// Make sure no two leaves are written with the same ID
 		if !self.id_set.insert(leaf.id.clone()) {
 			Err(InternalError::LeafAppendError(leaf.id))
 		} else {
+			self.dirty.insert(leaf.id.clone());
 			self.leafs.push(leaf);
 			Ok(())
 		}
 	}
 
+	/// Adds a [`Leaf`], replacing the queued [`Leaf`] with the same `ID` if there is one, and marks it dirty so [`Builder::dump_incremental`] processes it again.
+	/// Use it to update the data or settings of a leaf in a long lived [`Builder`]. Like `Builder::add_leaf`, the leaf doesn't inherit the [`Builder`]'s template
+	pub fn stage(&mut self, mut leaf: Leaf<'a>) -> InternalResult {
+		leaf.qualify()?;
+		leaf.id.validate()?;
+		leaf.id.unreserved()?;
+
+		self.dirty.insert(leaf.id.clone());
+		match self.leafs.iter_mut().find(|queued| queued.id == leaf.id) {
+			Some(queued) => *queued = leaf,
+			None => {
+				self.id_set.insert(leaf.id.clone());
+				self.leafs.push(leaf);
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Removes the [`Leaf`] with the given `ID` from the processing queue, returning it if there was one
+	pub fn remove(&mut self, id: impl AsRef<str>) -> Option<Leaf<'a>> {
+		let id = EntryId::normalized(id);
+		let position = self.leafs.iter().position(|leaf| leaf.id == id)?;
+
+		self.id_set.remove(&id);
+		self.dirty.remove(&id);
+		Some(self.leafs.remove(position))
+	}
+
+	/// Marks every queued [`Leaf`] clean, so [`Builder::dump_incremental`] copies them from the existing archive instead of processing them.
+	/// Use it when leaves are queued again for an archive written earlier, eg: in a previous session of an editor, and only some have changed since
+	pub fn mark_clean(&mut self) {
+		self.dirty.clear();
+	}
+
+	/// Whether the [`Leaf`] with the given `ID` was added or staged since the last [`Builder::dump_incremental`]. Leaves never dumped are dirty
+	pub fn is_dirty(&self, id: impl AsRef<str>) -> bool {
+		self.dirty.contains(id.as_ref())
+	}
+
 	/// Avoid unnecessary boilerplate by auto-templating all [`Leaf`]s added with `Builder::add(--)` with the given template
 	/// ```
 	/// use vach::builder::{Builder, Leaf};
@@ -113,235 +269,919 @@ impl<'a> Builder<'a> {
 	/// builder.add(b"JEB" as &[u8], "JEB_NAME").unwrap();
 	/// // `JEB` is compressed and has a version of 12
 	/// ```
-	pub fn template(mut self, template: Leaf<'a>) -> Builder {
+	pub fn template(mut self, template: Leaf<'a>) -> Builder<'a> {
 		self.leaf_template = template;
 		self
 	}
 
-	fn process_leaf(leaf: &mut Leaf<'a>, encryptor: Option<&Encryptor>) -> InternalResult<prepared::Prepared> {
+	/// Checks every queued [`Leaf`] for likely mistakes before writing, see [`LintWarning`].
+	/// > *NOTE:* Each [`Leaf`]'s handle is read to the end and replaced by an in-memory buffer of its contents, so the [`Builder`] can still be dumped afterwards
+	pub fn lint(&mut self) -> InternalResult<Vec<LintWarning>> {
+		let mut linter = Linter::default();
+
+		for leaf in self.leafs.iter_mut() {
+			linter.id(&leaf.id);
+
+			let mut data = Vec::new();
+			leaf.handle.read_to_end(&mut data)?;
+
+			#[cfg(feature = "compression")]
+			let compressed = !matches!(leaf.compress, CompressMode::Never | CompressMode::Store);
+			#[cfg(not(feature = "compression"))]
+			let compressed = false;
+
+			linter.content(&leaf.id, &data, compressed);
+			leaf.handle = Box::new(std::io::Cursor::new(data));
+		}
+
+		Ok(linter.finish())
+	}
+
+	fn process_leaf(
+		leaf: &mut Leaf<'a>,
+		#[cfg(feature = "crypto")] encryptor: Option<&Encryptor>,
+		#[cfg(feature = "crypto")] key_epoch: u32,
+		#[cfg(feature = "compression")] heuristics: &DetectHeuristics,
+		#[cfg(feature = "compression")] guardrail: Guardrail,
+	) -> InternalResult<prepared::Prepared> {
+		// Leaves copied from an earlier dump keep their processed data and entry, only their location and signature change
+		if let Some(mut entry) = leaf.verbatim.take() {
+			let start = Instant::now();
+			let mut data = Vec::with_capacity(entry.offset as usize);
+			leaf.handle
+				.read_to_end(&mut data)
+				.map_err(|err| InternalError::from(err).context(&entry.id, None, Stage::Read))?;
+
+			let times = StageTimes {
+				read: start.elapsed(),
+				..StageTimes::default()
+			};
+
+			#[cfg(feature = "crypto")]
+			let sign = entry.flags.contains(Flags::SIGNED_FLAG);
+
+			entry.flags.force_set(Flags::SIGNED_FLAG, false);
+			#[cfg(feature = "crypto")]
+			{
+				entry.signature = None;
+			}
+
+			return Ok(prepared::Prepared {
+				data,
+				entry,
+				times,
+				warning: None,
+				#[cfg(feature = "crypto")]
+				sign,
+			});
+		}
+
 		let mut entry: RegistryEntry = leaf.into();
-		let mut raw = Vec::new(); // 10MB
 
-		// Compression comes first
+		// Tombstones only carry their `ID`
+		if entry.is_tombstone() {
+			return Ok(prepared::Prepared {
+				data: Vec::new(),
+				entry,
+				times: StageTimes::default(),
+				warning: None,
+				#[cfg(feature = "crypto")]
+				sign: leaf.sign,
+			});
+		}
+
+		let id = &leaf.id.clone();
+		let context = |stage| move |err: InternalError| err.context(id, None, stage);
+		let read = |err: std::io::Error| context(Stage::Read)(err.into());
+
+		// Stored leaves are copied in as is, `qualify` already turned off encryption and signing
+		if leaf.stored() {
+			let mut times = StageTimes::default();
+			let data = take_data(leaf, &mut None, &mut entry, &mut times).map_err(read)?;
+
+			return Ok(prepared::Prepared {
+				data,
+				entry,
+				times,
+				warning: None,
+				#[cfg(feature = "crypto")]
+				sign: false,
+			});
+		}
+
+		// If the compression feature is turned off, compressed leaves can't be processed
+		#[cfg(not(feature = "compression"))]
+		if entry.flags.contains(Flags::COMPRESSED_FLAG) {
+			return Err(context(Stage::Compress)(InternalError::MissingFeatureError("compression")));
+		};
+
+		// `None` while the data is still in the leaf's handle, so compression can stream it
+		let mut data: Option<Vec<u8>> = None;
+		// Codes of the stages that ran, recorded for custom pipelines
+		let mut codes = Vec::new();
+		let mut times = StageTimes::default();
+		// Raised by the guardrail, which only watches compression
 		#[cfg(feature = "compression")]
-		match leaf.compress {
-			CompressMode::Never => {
-				leaf.handle.read_to_end(&mut raw)?;
-			},
-			CompressMode::Always => {
-				Compressor::new(&mut leaf.handle).compress(leaf.compression_algo, &mut raw)?;
+		let mut warning = None;
+		#[cfg(not(feature = "compression"))]
+		let warning = None;
 
-				entry.flags.force_set(Flags::COMPRESSED_FLAG, true);
-				entry.flags.force_set(leaf.compression_algo.into(), true);
-			},
-			CompressMode::Detect => {
-				let mut buffer = Vec::new();
-				leaf.handle.read_to_end(&mut buffer)?;
+		// Line endings are normalized first, so every stage sees the same data on every platform
+		if leaf.newlines != NewlineMode::Keep {
+			let buffer = take_data(leaf, &mut data, &mut entry, &mut times).map_err(read)?;
+			let normalized = leaf.newlines.apply(buffer);
 
-				let mut compressed_data = Vec::new();
-				Compressor::new(buffer.as_slice()).compress(leaf.compression_algo, &mut compressed_data)?;
+			entry.decoded_size = normalized.len() as u64;
+			data = Some(normalized);
+		}
 
-				if compressed_data.len() <= buffer.len() {
-					entry.flags.force_set(Flags::COMPRESSED_FLAG, true);
-					entry.flags.force_set(leaf.compression_algo.into(), true);
+		let stages = match leaf.pipeline {
+			[] => pipeline::DEFAULT_PIPELINE,
+			stages => stages,
+		};
 
-					raw = compressed_data;
-				} else {
-					buffer.as_slice().read_to_end(&mut raw)?;
-				};
-			},
+		for stage in stages {
+			match stage {
+				#[cfg(feature = "compression")]
+				PipelineStage::Compress => match leaf.compress {
+					CompressMode::Never | CompressMode::Store => (),
+					CompressMode::Always => {
+						let start = Instant::now();
+						let mut compressed = Vec::new();
+						let before = match data.take() {
+							Some(buffer) => Compressor::new(buffer.as_slice())
+								.compress(leaf.compression_algo, &mut compressed)
+								.map(|_| buffer.len() as u64),
+							None => {
+								let mut source = CountingReader::new(&mut leaf.handle);
+								let result = Compressor::new(&mut source).compress(leaf.compression_algo, &mut compressed);
+
+								entry.decoded_size = source.count;
+								result.map(|_| source.count)
+							},
+						}
+						.map_err(context(Stage::Compress))?;
+
+						// Unlike `Detect`, `Always` keeps the compressed data even if it grew
+						let after = compressed.len() as u64;
+						if after > before {
+							let grew = LintWarning::CompressionGrew { id: id.clone(), before, after };
+							warning = guardrail.check(grew).map_err(context(Stage::Compress))?;
+						}
+
+						entry.flags.force_set(Flags::COMPRESSED_FLAG, true);
+						entry.flags.force_set(leaf.compression_algo.into(), true);
+
+						data = Some(compressed);
+						codes.push(pipeline::COMPRESS);
+						times.compress += start.elapsed();
+					},
+					CompressMode::Detect => {
+						let buffer = take_data(leaf, &mut data, &mut entry, &mut times).map_err(read)?;
+
+						// Already compressed formats are stored as is, skipping the costly trial compression
+						if heuristics.skip(&leaf.id, &buffer) {
+							data = Some(buffer);
+							continue;
+						}
+
+						let start = Instant::now();
+						let mut compressed = Vec::new();
+						Compressor::new(buffer.as_slice())
+							.compress(leaf.compression_algo, &mut compressed)
+							.map_err(context(Stage::Compress))?;
+
+						times.compress += start.elapsed();
+
+						if compressed.len() <= buffer.len() {
+							entry.flags.force_set(Flags::COMPRESSED_FLAG, true);
+							entry.flags.force_set(leaf.compression_algo.into(), true);
+
+							data = Some(compressed);
+							codes.push(pipeline::COMPRESS);
+						} else {
+							data = Some(buffer);
+						};
+					},
+				},
+				#[cfg(feature = "crypto")]
+				PipelineStage::Encrypt => {
+					if let (true, Some(ex)) = (leaf.encrypt, encryptor) {
+						let buffer = take_data(leaf, &mut data, &mut entry, &mut times).map_err(read)?;
+
+						let start = Instant::now();
+						data = Some(ex.encrypt(&buffer).map_err(context(Stage::Encrypt))?);
+						times.encrypt += start.elapsed();
+
+						entry.flags.force_set(Flags::ENCRYPTED_FLAG, true);
+						codes.push(pipeline::ENCRYPT);
+					}
+				},
+				PipelineStage::Custom { flag, encode } => {
+					let buffer = take_data(leaf, &mut data, &mut entry, &mut times).map_err(read)?;
+
+					let start = Instant::now();
+					data = Some(encode(&buffer).map_err(context(Stage::Custom))?);
+					times.custom += start.elapsed();
+
+					entry.flags.force_set(*flag, true);
+					codes.push(pipeline::custom_code(*flag));
+				},
+				#[allow(unreachable_patterns)]
+				_ => (),
+			}
 		}
 
-		// If the compression feature is turned off, simply reads into buffer
-		#[cfg(not(feature = "compression"))]
-		{
-			if entry.flags.contains(Flags::COMPRESSED_FLAG) {
-				return Err(InternalError::MissingFeatureError("compression"));
-			};
+		let mut raw = take_data(leaf, &mut data, &mut entry, &mut times).map_err(read)?;
 
-			leaf.handle.read_to_end(&mut raw)?;
+		// Custom pipelines are recorded, so the loader can undo them in reverse
+		if !leaf.pipeline.is_empty() {
+			raw = pipeline::with_descriptor(&codes, raw);
+			entry.flags.force_set(Flags::PIPELINE_FLAG, true);
 		}
 
-		// Encryption comes second
+		// The key's epoch leads the data, so loaders pick the right key before undoing anything
 		#[cfg(feature = "crypto")]
-		if leaf.encrypt {
-			if let Some(ex) = encryptor {
-				raw = ex.encrypt(&raw)?;
-				entry.flags.force_set(Flags::ENCRYPTED_FLAG, true);
-			}
+		if key_epoch != 0 && entry.flags.contains(Flags::ENCRYPTED_FLAG) {
+			raw.splice(0..0, key_epoch.to_le_bytes());
+			entry.flags.force_set(Flags::KEY_EPOCH_FLAG, true);
 		}
 
 		Ok(prepared::Prepared {
 			data: raw,
 			entry,
+			times,
+			warning,
 			#[cfg(feature = "crypto")]
 			sign: leaf.sign,
 		})
 	}
 
 	/// This iterates over all [`Leaf`]s in the processing queue, parses them and writes the bytes out into a the target.
-	/// Configure the custom *`MAGIC`*, `Header` flags and a [`Keypair`](crate::crypto::Keypair) using the [`BuilderConfig`] struct.
-	pub fn dump<W: Write + Seek + Send>(self, mut target: W, config: &BuilderConfig) -> InternalResult<u64> {
+	/// Configure the custom *`MAGIC`*, `Header` flags and a [`Keypair`](crate::crypto::Keypair) or [`LeafSigner`](crate::crypto::LeafSigner) using the [`BuilderConfig`] struct.
+	/// Same as the free function [`dump`], over the [`Builder`]'s queue.
+	pub fn dump<W: Write + Seek + Send>(self, target: W, config: &BuilderConfig) -> InternalResult<u64> {
 		let Builder { mut leafs, .. } = self;
+		dump(target, &mut leafs, config)
+	}
 
-		// Calculate the size of the registry and check for [`Leaf`]s that request for encryption
-		let mut bytes_written = 0;
-		let mut leaf_offset = {
-			leafs
-				.iter()
-				.map(|leaf| {
-					// The size of it's ID, the minimum size of an entry without a signature, and the size of a signature only if a signature is incorporated into the entry
-					leaf.id.len() + RegistryEntry::MIN_SIZE + {
-						#[cfg(feature = "crypto")]
-						if config.keypair.is_some() && leaf.sign {
-							crate::SIGNATURE_LENGTH
-						} else {
-							0
-						}
-						#[cfg(not(feature = "crypto"))]
-						{
-							0
-						}
-					}
-				})
-				.reduce(|l1, l2| l1 + l2)
-				.unwrap_or(0) + Header::BASE_SIZE
-		} as u64;
+	/// Same as `Builder::dump`, but returns a [`DumpReport`] of the IO performed and the time spent in each stage, instead of the bytes of leaf data written
+	pub fn dump_report<W: Write + Seek + Send>(self, target: W, config: &BuilderConfig) -> InternalResult<DumpReport> {
+		let Builder { mut leafs, .. } = self;
+		dump_report(target, &mut leafs, config)
+	}
+}
 
-		// Start at the very start of the file
-		target.seek(SeekFrom::Start(0))?;
-		target.write_all(&config.magic)?;
+/// Parses the given [`Leaf`]s and writes them out into the target as an archive, in order. For when the leaves are already held elsewhere, without queueing them in a [`Builder`].
+/// `ID`s are validated and must be unique, same as `Builder::add_leaf`. Returns the number of bytes of leaf data written.
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::{dump, BuilderConfig, Leaf};
+///
+/// let mut leaves = [Leaf::new(b"Hello" as &[u8]).id("hello"), Leaf::new(b"World" as &[u8]).id("world")];
+/// dump(Cursor::new(Vec::new()), &mut leaves, &BuilderConfig::default()).unwrap();
+/// ```
+pub fn dump<'a, W: Write + Seek + Send>(target: W, leaves: &mut [Leaf<'a>], config: &BuilderConfig) -> InternalResult<u64> {
+	dump_report(target, leaves, config).map(|report| report.leaf_bytes)
+}
 
-		// INSERT flags
-		let mut temp = config.flags;
+/// Same as the free function [`dump`], but returns a [`DumpReport`] of the IO performed and the time spent in each stage
+pub fn dump_report<'a, W: Write + Seek + Send>(
+	target: W, leaves: &mut [Leaf<'a>], config: &BuilderConfig,
+) -> InternalResult<DumpReport> {
+	dump_extended(target, leaves, config, &[])
+}
 
-		#[cfg(feature = "crypto")]
-		if config.keypair.is_some() {
-			temp.force_set(Flags::SIGNED_FLAG, true);
-		};
+/// Same as [`dump_report`], additionally writing the given header extension records
+pub(crate) fn dump_extended<'a, W: Write + Seek + Send>(
+	target: W, leaves: &mut [Leaf<'a>], config: &BuilderConfig, extensions: &[(u8, Vec<u8>)],
+) -> InternalResult<DumpReport> {
+	let started = Instant::now();
+	let mut target = CountingWriter::new(target);
+	let mut report = DumpReport::default();
 
-		// Write remaining Header
-		target.write_all(&temp.bits().to_le_bytes())?;
-		target.write_all(&crate::VERSION.to_le_bytes())?;
-		target.write_all(&(leafs.len() as u16).to_le_bytes())?;
+	// Catch bad settings before any IO happens
+	config.validate()?;
 
-		// Build encryptor
-		#[cfg(feature = "crypto")]
-		let encryptor = {
-			let use_encryption = leafs.iter().any(|leaf| leaf.encrypt);
-			if use_encryption {
-				if let Some(keypair) = config.keypair.as_ref() {
-					Some(Encryptor::new(&keypair.verifying_key(), config.magic))
-				} else {
-					return Err(InternalError::NoKeypairError);
-				}
-			} else {
-				None
-			}
-		};
+	leaves.iter_mut().try_for_each(|leaf| leaf.qualify())?;
 
-		#[cfg(not(feature = "crypto"))]
-		let encryptor = None;
+	// Lay leaves out in the order they were fetched, a stable sort keeps unprofiled leaves in the order they were added
+	if let Some(profile) = config.layout_profile {
+		let ranks = profile.ranks();
+		leaves.sort_by_key(|leaf| ranks.get(leaf.id.as_ref()).copied().unwrap_or(usize::MAX));
+	}
 
-		// Callback for processing IO
-		let mut registry = Vec::with_capacity(leaf_offset as usize - Header::BASE_SIZE);
+	// The dependency graph, validity windows and tags are written as reserved leaves
+	let mut reserved = dependency_leaf(leaves)?
+		.into_iter()
+		.chain(validity_leaf(leaves))
+		.chain(tags_leaf(leaves)?)
+		.collect::<Vec<_>>();
 
-		#[allow(unused_mut)]
-		let mut write = |result: InternalResult<prepared::Prepared>| -> InternalResult<()> {
-			let mut result = result?;
-			let bytes = result.data.len() as u64;
+	if config.format_descriptor {
+		let descriptor = FormatDescriptor::current().to_string();
+		reserved.push(Leaf::new(std::io::Cursor::new(descriptor.into_bytes())).id(DESCRIPTOR_ID));
+	}
 
-			// write
-			target.seek(SeekFrom::Start(leaf_offset))?;
-			target.write_all(&result.data)?;
+	// The index covers every leaf, itself included, so it's built last
+	if config.id_index {
+		reserved.push(index_leaf(leaves, &reserved, config.prefix_ids)?);
+	}
 
-			// update entry
-			result.entry.location = leaf_offset;
-			result.entry.offset = bytes;
+	let count = leaves.len() + reserved.len();
 
-			// update state
-			leaf_offset += result.data.len() as u64;
-			bytes_written += bytes;
+	if count > u16::MAX as usize {
+		return Err(InternalError::InvalidConfigError(format!(
+			"An archive holds at most {} leaves, found {}",
+			u16::MAX,
+			count
+		)));
+	}
 
-			// write out registry entry
-			#[cfg(feature = "crypto")]
-			if result.sign {
-				if let Some(keypair) = &config.keypair {
-					result.entry.flags.force_set(Flags::SIGNED_FLAG, true);
+	// Only `vach` writes leaves in it's own namespace
+	leaves.iter().try_for_each(|leaf| leaf.id.unreserved())?;
 
-					let entry_bytes = result.entry.to_bytes(true)?;
-					result.data.extend_from_slice(&entry_bytes);
+	let mut ids = HashSet::with_capacity(count);
+	for leaf in leaves.iter().chain(reserved.iter()) {
+		leaf.id.validate()?;
+		leaf.dependencies.iter().try_for_each(|id| id.validate())?;
 
-					// Include registry data in the signature
-					result.entry.signature = Some(keypair.sign(&result.data));
-				};
+		if !ids.insert(leaf.id.as_ref()) {
+			return Err(InternalError::LeafAppendError(leaf.id.clone()));
+		}
+
+		#[cfg(feature = "compression")]
+		if !matches!(leaf.compress, CompressMode::Never | CompressMode::Store) {
+			leaf.compression_algo
+				.validate()
+				.map_err(|err| err.context(&leaf.id, None, Stage::Compress))?;
+		}
+
+		if !leaf.pipeline.is_empty() {
+			leaf.validate_pipeline()
+				.map_err(|err| err.context(&leaf.id, None, Stage::Custom))?;
+		}
+
+		#[cfg(all(feature = "compression", feature = "crypto"))]
+		if leaf.compresses_ciphertext() {
+			let warning = LintWarning::CompressedAfterEncryption { id: leaf.id.clone() };
+			let warning = config.guardrail.check(warning).map_err(|err| err.context(&leaf.id, None, Stage::Compress))?;
+			report.warnings.extend(warning);
+		}
+	}
+
+	#[cfg(feature = "crypto")]
+	let signer = config.active_signer();
+
+	// INSERT flags
+	#[cfg(feature = "crypto")]
+	let mut temp = config.flags;
+	#[cfg(not(feature = "crypto"))]
+	let temp = config.flags;
+
+	#[cfg(feature = "crypto")]
+	if signer.is_some() {
+		temp.force_set(Flags::SIGNED_FLAG, true);
+	};
+
+	let mut header = Header {
+		magic: config.magic,
+		flags: temp,
+		capacity: count as u16,
+		..Header::default()
+	};
+
+	for (tag, data) in extensions {
+		header.set_extension(*tag, data.clone());
+	}
+
+	if config.footer {
+		header.set_extension(Header::FOOTER, Vec::new());
+	}
+
+	// Room for the timestamp token is reserved up front, it's only requested once the content hash is known
+	#[cfg(feature = "crypto")]
+	let mut timestamp = config.timestamp_authority.map(|authority| {
+		let reserved = authority.max_token_len();
+		header.set_extension(Header::TIMESTAMP, timestamp::encode_token(&[], reserved));
+
+		(authority, reserved, ContentHasher::default())
+	});
+
+	// Enforce the key policy, recording the key's expiry for loaders
+	#[cfg(feature = "crypto")]
+	if signer.is_some() {
+		let policy = &config.key_policy;
+
+		if let Some(expires) = policy.expires {
+			if expires <= SystemTime::now() {
+				return Err(InternalError::ExpiredKeyError(expires));
 			}
 
-			// write to registry buffer, this one might include the Signature
-			let entry_bytes = result.entry.to_bytes(false)?;
-			registry.write_all(&entry_bytes)?;
+			let seconds = expires.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+			header.set_extension(Header::KEY_EXPIRY, seconds.to_le_bytes().to_vec());
+		}
 
-			// Call the progress callback bound within the [`BuilderConfig`]
-			config.progress_callback.inspect(|c| c(&result.entry));
+		let build_id = config.build_id.unwrap_or_else(rand::random);
+		header.set_extension(Header::BUILD_ID, build_id.to_vec());
 
-			Ok(())
-		};
+		// A dedicated encryption key takes encryption off the keypair
+		let encrypts = config.encryption_key.is_none();
+		let uses = leaves.iter().filter(|leaf| (leaf.encrypt && encrypts) || leaf.sign).count();
+		if let Some(max) = policy.max_leaves.filter(|max| uses > *max) {
+			return Err(InternalError::InvalidConfigError(format!(
+				"The key policy allows at most {} leaves per key, found {} encrypted or signed leaves",
+				max, uses
+			)));
+		}
+	}
 
-		#[cfg(feature = "multithreaded")]
-		let (tx, rx) = mpsc::sync_channel(leafs.len());
+	// Calculate the size of the registry, prefixed IDs are stored in `ID` order
+	let mut order = leaves.iter().chain(reserved.iter()).collect::<Vec<_>>();
+	if config.prefix_ids {
+		order.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+	}
 
-		#[cfg(feature = "multithreaded")]
-		{
-			thread::scope(|s| -> InternalResult<()> {
-				let count = leafs.len();
-				let chunk_size = leafs.len() / config.num_threads.min(1);
+	let registry_size = {
+		let mut previous = config.prefix_ids.then_some("");
 
-				let chunks = leafs.chunks_mut(chunk_size);
-				let encryptor = encryptor.as_ref();
+		order
+			.iter()
+			.map(|leaf| {
+				// A signature is only incorporated into the entry if the leaf is signed
+				#[cfg(feature = "crypto")]
+				let signed = signer.is_some() && leaf.sign;
+				#[cfg(not(feature = "crypto"))]
+				let signed = false;
 
-				// Spawn CPU threads
-				for chunk in chunks {
-					let queue = tx.clone();
+				let size = RegistryEntry::encoded_size(&leaf.id, previous, signed);
+				previous = previous.map(|_| leaf.id.as_ref());
+				size
+			})
+			.sum::<usize>()
+	};
 
-					s.spawn(move || {
-						for leaf in chunk {
-							let res = Builder::process_leaf(leaf, encryptor);
-							queue.send(res).unwrap();
-						}
-					});
-				}
+	// Large registries are compressed once every entry is known, then stored after the leaves, which start right after the header
+	#[cfg(feature = "compression")]
+	let registry_codec = config.registry_compression.filter(|_| registry_size >= config.registry_threshold);
+
+	#[cfg(feature = "compression")]
+	if registry_codec.is_some() {
+		header.set_extension(Header::COMPRESSED_REGISTRY, vec![0; RegistryBlock::SIZE]);
+		header.capabilities.insert(Capabilities::from_bits(Capabilities::COMPRESSED_REGISTRY));
+	}
+
+	#[cfg(feature = "compression")]
+	let inline_registry = registry_codec.is_none();
+	#[cfg(not(feature = "compression"))]
+	let inline_registry = true;
+
+	let mut leaf_offset = (header.size() + if inline_registry { registry_size } else { 0 }) as u64;
+
+	// Leaf data may start further in, leaving a reserved gap after the registry
+	let registry_end = leaf_offset;
+	if let Some(offset) = config.data_offset {
+		if offset < registry_end {
+			return Err(InternalError::InvalidConfigError(format!(
+				"The header and registry take {} bytes, they don't fit before the data offset of {}",
+				registry_end, offset
+			)));
+		}
+
+		leaf_offset = offset;
+	}
+
+	// Build encryptor
+	#[cfg(feature = "crypto")]
+	let encryptor = {
+		let use_encryption = leaves.iter().any(|leaf| leaf.encrypt);
+		match (use_encryption, config.encryption_key, signer) {
+			(false, _, _) => None,
+			(true, Some(key), _) => Some(Encryptor::from_key(&key.to_bytes(), config.magic)),
+			(true, None, Some(signer)) => Some(Encryptor::new(&signer.verifying_key(), config.magic)),
+			(true, None, None) => return Err(InternalError::NoKeypairError),
+		}
+	};
+
+	target.seek(SeekFrom::Start(0))?;
+	target.write_all(&header.to_bytes())?;
+
+	if leaf_offset > registry_end {
+		target.seek(SeekFrom::Start(registry_end))?;
+		std::io::copy(&mut std::io::repeat(0).take(leaf_offset - registry_end), &mut target)?;
+
+		#[cfg(feature = "crypto")]
+		if let Some((_, _, hasher)) = timestamp.as_mut() {
+			std::io::copy(&mut std::io::repeat(0).take(leaf_offset - registry_end), &mut HashWriter(hasher))?;
+		}
+	}
+
+	#[cfg(feature = "compression")]
+	let heuristics = &config.detect_heuristics;
+
+	#[cfg(feature = "compression")]
+	let guardrail = config.guardrail;
+
+	#[cfg(feature = "crypto")]
+	let key_epoch = config.key_epoch;
+
+	if config.prefix_ids {
+		header.capabilities.insert(Capabilities::from_bits(Capabilities::PREFIXED_IDS));
+	}
+
+	#[cfg(feature = "crypto")]
+	let domain = header.signature_domain().unwrap_or_default();
+
+	// Callback for processing IO
+	let mut registry = Vec::with_capacity(registry_size);
+	let mut prefixed = Vec::new();
+
+	#[allow(unused_mut)]
+	let mut write = |result: InternalResult<prepared::Prepared>| -> InternalResult<()> {
+		let mut result = result?;
+		let bytes = result.data.len() as u64;
+		let mut times = result.times;
+
+		let id = &result.entry.id.clone();
+		let location = Some(leaf_offset);
+		let context = |stage| move |err: InternalError| err.context(id, location, stage);
+
+		// write
+		let start = Instant::now();
+		target
+			.seek(SeekFrom::Start(leaf_offset))
+			.and_then(|_| target.write_all(&result.data))
+			.map_err(|err| context(Stage::Write)(err.into()))?;
 
-				// Process IO, read results from
-				let mut results = 0;
-				loop {
-					match rx.try_recv() {
-						Ok(r) => {
-							results += 1;
-							write(r)?
+		#[cfg(feature = "crypto")]
+		if let Some((_, _, hasher)) = timestamp.as_mut() {
+			hasher.data(&result.data);
+		}
+
+		times.write += start.elapsed();
+
+		// update entry
+		result.entry.location = leaf_offset;
+		result.entry.offset = bytes;
+		header.capabilities.insert(Capabilities::of(&result.entry.flags));
+
+		// update state
+		leaf_offset += result.data.len() as u64;
+		report.leaf_bytes += bytes;
+		report.bytes_read += result.entry.decoded_size;
+		report.record(&result.entry.flags, result.entry.decoded_size, bytes);
+		report.warnings.extend(result.warning.take());
+
+		// write out registry entry
+		#[cfg(feature = "crypto")]
+		if result.sign {
+			if let Some(signer) = signer {
+				let start = Instant::now();
+				result.entry.flags.force_set(Flags::SIGNED_FLAG, true);
+
+				let entry_bytes = result.entry.to_bytes(true).map_err(context(Stage::Sign))?;
+				result.data.extend_from_slice(&entry_bytes);
+
+				// Include registry data in the signature, bound to this archive by the signature domain
+				result.data.splice(0..0, domain.iter().copied());
+				result.entry.signature = Some(signer.sign_leaf(&result.data).map_err(context(Stage::Sign))?);
+				times.sign += start.elapsed();
+			};
+		}
+
+		report.stages.add(&times);
+
+		// Call the progress callback bound within the [`BuilderConfig`]
+		config.progress_callback.inspect(|c| c(&result.entry));
+
+		// write to registry buffer, this one might include the Signature. Prefixed entries are written once all are known
+		if config.prefix_ids {
+			prefixed.push(result.entry);
+		} else {
+			let entry_bytes = result.entry.to_bytes(false).map_err(context(Stage::Write))?;
+			registry.extend_from_slice(&entry_bytes);
+		}
+
+		Ok(())
+	};
+
+	#[cfg(feature = "multithreaded")]
+	{
+		// Bounded, so a writer stalled on it's target stalls the compute threads too, see `SinkWriter`
+		let (tx, rx) = mpsc::sync_channel(config.compute_threads * 2);
+		let (read_tx, read_rx) = mpsc::sync_channel::<(usize, &mut Leaf<'a>)>(config.compute_threads * 2);
+
+		// Threads pull leaves off a shared queue, so a few large leaves can't stall a whole chunk
+		let queue = Mutex::new(leaves.iter_mut().chain(reserved.iter_mut()).enumerate());
+		let read_rx = Mutex::new(read_rx);
+		#[cfg(feature = "crypto")]
+		let encryptor = encryptor.as_ref();
+		let prefetch = config.io_threads > 0;
+
+		thread::scope(|s| -> InternalResult<()> {
+			// IO threads read leaves into memory ahead of the compute threads
+			for _ in 0..config.io_threads {
+				let (queue, results, reads) = (&queue, tx.clone(), read_tx.clone());
+
+				s.spawn(move || loop {
+					let Some((idx, leaf)) = sync::lock(queue).next() else { break };
+
+					let mut data = Vec::new();
+					match leaf.handle.read_to_end(&mut data) {
+						Ok(_) => {
+							leaf.handle = Box::new(std::io::Cursor::new(data));
+							let _ = reads.send((idx, leaf));
 						},
-						Err(e) => match e {
-							mpsc::TryRecvError::Empty => {
-								if results >= count {
-									break Ok(());
-								}
-							},
-							mpsc::TryRecvError::Disconnected => break Ok(()),
+						Err(err) => {
+							let error = InternalError::from(err).context(&leaf.id, None, Stage::Read);
+							let _ = results.send((idx, Err(error)));
 						},
 					}
+				});
+			}
+
+			drop(read_tx);
+
+			// Compute threads compress and encrypt, taking leaves from the IO threads or straight from the queue
+			for _ in 0..config.compute_threads {
+				let (queue, read_rx, results) = (&queue, &read_rx, tx.clone());
+
+				s.spawn(move || loop {
+					let next = match prefetch {
+						true => sync::lock(read_rx).recv().ok(),
+						false => sync::lock(queue).next(),
+					};
+
+					let Some((idx, leaf)) = next else { break };
+					let _ = results.send((idx, Builder::process_leaf(
+						leaf,
+						#[cfg(feature = "crypto")]
+						encryptor,
+						#[cfg(feature = "crypto")]
+						key_epoch,
+						#[cfg(feature = "compression")]
+						heuristics,
+						#[cfg(feature = "compression")]
+						guardrail,
+					)));
+				});
+			}
+
+			drop(tx);
+
+			// The calling thread does all the writing, in completion order unless a layout or an `ID` index was requested
+			let ordered = config.layout_profile.is_some() || config.id_index;
+			let mut pending = BTreeMap::new();
+			let mut next = 0;
+
+			rx.into_iter().try_for_each(|(idx, result)| {
+				if !ordered {
+					return write(result);
+				}
+
+				pending.insert(idx, result);
+				while let Some(result) = pending.remove(&next) {
+					next += 1;
+					write(result)?;
 				}
-			})?;
+
+				Ok(())
+			})
+		})?;
+	};
+
+	#[cfg(not(feature = "multithreaded"))]
+	leaves
+		.iter_mut()
+		.chain(reserved.iter_mut())
+		.map(|l| {
+			Builder::process_leaf(
+				l,
+				#[cfg(feature = "crypto")]
+				encryptor.as_ref(),
+				#[cfg(feature = "crypto")]
+				key_epoch,
+				#[cfg(feature = "compression")]
+				heuristics,
+				#[cfg(feature = "compression")]
+				guardrail,
+			)
+		})
+		.try_for_each(write)?;
+
+	prefixed.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+	let mut previous = "";
+
+	for entry in &prefixed {
+		let entry_bytes = entry
+			.to_bytes_prefixed(previous)
+			.map_err(|err| err.context(&entry.id, Some(entry.location), Stage::Write))?;
+
+		registry.extend_from_slice(&entry_bytes);
+		previous = &entry.id;
+	}
+
+	// The compressed registry follows the leaves, only registries stored as is are hashed as such
+	#[cfg(feature = "compression")]
+	if let Some(algo) = registry_codec {
+		let mut compressed = Vec::new();
+		Compressor::new(registry.as_slice()).compress(algo, &mut compressed)?;
+
+		let block = RegistryBlock {
+			codec: algo.into(),
+			location: leaf_offset,
+			size: compressed.len() as u64,
+			decoded_size: registry.len() as u64,
 		};
 
-		#[cfg(not(feature = "multithreaded"))]
-		leafs
-			.iter_mut()
-			.map(|l| Builder::process_leaf(l, encryptor.as_ref()))
-			.try_for_each(write)?;
+		target.seek(SeekFrom::Start(leaf_offset))?;
+		target.write_all(&compressed)?;
+
+		#[cfg(feature = "crypto")]
+		if let Some((_, _, hasher)) = timestamp.as_mut() {
+			hasher.data(&compressed);
+		}
+
+		leaf_offset += block.size;
+		registry.clear();
+
+		// The extension's room was reserved up front, so the header keeps it's size
+		header.set_extension(Header::COMPRESSED_REGISTRY, block.encode());
+		target.seek(SeekFrom::Start(0))?;
+		target.write_all(&header.to_bytes())?;
+	}
+
+	// write out Registry
+	target.seek(SeekFrom::Start(header.size() as _))?;
+	target.write_all(&registry)?;
+
+	// Capabilities are only known once every leaf is processed
+	target.seek(SeekFrom::Start(Header::CAPABILITIES_OFFSET as _))?;
+	target.write_all(&header.capabilities.bits().to_le_bytes())?;
 
-		// write out Registry
-		target.seek(SeekFrom::Start(Header::BASE_SIZE as _))?;
-		target.write_all(&registry)?;
+	// The footer mirrors the final header, timestamp tokens live in an extension so they never change it
+	if config.footer {
+		let mut mirror = [0; Header::BASE_SIZE];
+		mirror.copy_from_slice(&header.to_bytes()[..Header::BASE_SIZE]);
+
+		let footer = RawFooter {
+			header: mirror,
+			archive_size: leaf_offset + RawFooter::SIZE as u64,
+		}
+		.encode();
+
+		target.seek(SeekFrom::Start(leaf_offset))?;
+		target.write_all(&footer)?;
+
+		#[cfg(feature = "crypto")]
+		if let Some((_, _, hasher)) = timestamp.as_mut() {
+			hasher.data(&footer);
+		}
+	}
+
+	// The token fills the reserved room, so the header is rewritten without moving anything
+	#[cfg(feature = "crypto")]
+	if let Some((authority, reserved, mut hasher)) = timestamp {
+		hasher.registry(&registry);
+
+		let token = authority.timestamp(&hasher.finish())?;
+		if token.len() > reserved {
+			return Err(InternalError::TimestampError(format!(
+				"The timestamp token takes {} bytes, only {} were reserved",
+				token.len(),
+				reserved
+			)));
+		}
+
+		header.set_extension(Header::TIMESTAMP, timestamp::encode_token(&token, reserved));
+		target.seek(SeekFrom::Start(0))?;
+		target.write_all(&header.to_bytes())?;
+	}
+
+	report.bytes_written = target.bytes_written();
+	report.seeks = target.seeks() as u64;
+	report.elapsed = started.elapsed();
+
+	Ok(report)
+}
+
+/// The reserved leaf holding the dependencies recorded using `Leaf::depends_on`, `None` if no leaf has any
+fn dependency_leaf<'a>(leaves: &[Leaf]) -> InternalResult<Option<Leaf<'a>>> {
+	let mut graph = leaves
+		.iter()
+		.filter(|leaf| !leaf.dependencies.is_empty())
+		.map(|leaf| (&leaf.id, leaf.dependencies.as_slice()))
+		.peekable();
+
+	if graph.peek().is_none() {
+		return Ok(None);
+	}
 
-		Ok(bytes_written)
+	let data = dependencies::encode(graph)?;
+	Ok(Some(Leaf::new(std::io::Cursor::new(data)).id(DEPENDENCIES_ID)))
+}
+
+/// The reserved leaf holding the windows set using `Leaf::valid_from` and `Leaf::valid_until`, `None` if no leaf has one
+fn validity_leaf<'a>(leaves: &[Leaf]) -> Option<Leaf<'a>> {
+	let mut windows = leaves
+		.iter()
+		.filter(|leaf| !leaf.validity.is_unbounded())
+		.map(|leaf| (&leaf.id, leaf.validity))
+		.peekable();
+
+	windows.peek()?;
+
+	let data = validity::encode(windows);
+	Some(Leaf::new(std::io::Cursor::new(data)).id(VALIDITY_ID))
+}
+
+/// The reserved leaf holding the tags set using `Leaf::tag`, `None` if no leaf has any
+fn tags_leaf<'a>(leaves: &[Leaf]) -> InternalResult<Option<Leaf<'a>>> {
+	let tagged = leaves
+		.iter()
+		.filter(|leaf| !leaf.tags.is_empty())
+		.map(|leaf| (&leaf.id, leaf.tags.as_slice()))
+		.collect::<Vec<_>>();
+
+	if tagged.is_empty() {
+		return Ok(None);
+	}
+
+	let data = tags::encode(&tagged)?;
+	Ok(Some(Leaf::new(std::io::Cursor::new(data)).id(TAGS_ID)))
+}
+
+/// The reserved leaf holding the perfect hash over all `ID`s, each mapped to it's position in the registry. See `BuilderConfig::id_index`
+fn index_leaf<'a>(leaves: &[Leaf], reserved: &[Leaf], sorted: bool) -> InternalResult<Leaf<'a>> {
+	let mut ids = leaves
+		.iter()
+		.chain(reserved)
+		.map(|leaf| leaf.id.as_ref())
+		.chain([INDEX_ID])
+		.collect::<Vec<_>>();
+
+	// Prefixed registries are stored in `ID` order, others in the order leaves are written
+	if sorted {
+		ids.sort_unstable();
+	}
+
+	let data = IdIndex::build(&ids)?.encode();
+	Ok(Leaf::new(std::io::Cursor::new(data)).id(INDEX_ID))
+}
+
+/// Takes the data processed by earlier stages, or reads the leaf's data if no stage has yet
+fn take_data(
+	leaf: &mut Leaf, data: &mut Option<Vec<u8>>, entry: &mut RegistryEntry, times: &mut StageTimes,
+) -> std::io::Result<Vec<u8>> {
+	match data.take() {
+		Some(data) => Ok(data),
+		None => {
+			let start = Instant::now();
+			let mut buffer = Vec::new();
+			leaf.handle.read_to_end(&mut buffer)?;
+
+			entry.decoded_size = buffer.len() as u64;
+			times.read += start.elapsed();
+			Ok(buffer)
+		},
+	}
+}
+
+/// Feeds bytes written into it into the data part of a content hash
+#[cfg(feature = "crypto")]
+struct HashWriter<'a>(&'a mut ContentHasher);
+
+#[cfg(feature = "crypto")]
+impl Write for HashWriter<'_> {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.data(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Counts the bytes read through it, to record the decoded size of leaves compressed while streaming
+#[cfg(feature = "compression")]
+struct CountingReader<R> {
+	inner: R,
+	count: u64,
+}
+
+#[cfg(feature = "compression")]
+impl<R: Read> CountingReader<R> {
+	fn new(inner: R) -> CountingReader<R> {
+		CountingReader { inner, count: 0 }
+	}
+}
+
+#[cfg(feature = "compression")]
+impl<R: Read> Read for CountingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let read = self.inner.read(buf)?;
+		self.count += read as u64;
+		Ok(read)
 	}
 }
@@ -0,0 +1,187 @@
+use std::{
+	collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+	hash::{Hash, Hasher},
+	io::{Cursor, Read, Seek, Write},
+};
+
+use super::{dump_extended, report::DumpReport, Builder, BuilderConfig, Leaf};
+use crate::global::{entry_id::EntryId, error::*, flags::Flags, header::Header};
+
+/// Builds several archives in one pass, eg: per-platform variants of the same content, detecting leaves they have in common.
+/// A leaf is shared if two or more archives hold it under the same `ID`, with identical data, flags and version.
+/// With [`PackSet::common`] set, shared leaves are written once into a separate archive, which the others reference by name.
+/// Loaders resolve them by layering an archive over the shared one, see `Overlay::with_shared`
+/// > *NOTE:* Every leaf is read into memory to compare it's data. Shared leaves keep the settings of the first archive they were found in
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// let mut windows = Builder::new();
+/// windows.add(b"Shared music" as &[u8], "music").unwrap();
+/// windows.add(b"DirectX shaders" as &[u8], "shaders").unwrap();
+///
+/// let mut linux = Builder::new();
+/// linux.add(b"Shared music" as &[u8], "music").unwrap();
+/// linux.add(b"Vulkan shaders" as &[u8], "shaders").unwrap();
+///
+/// let mut set = PackSet::new().common("common");
+/// set.add("windows", windows).unwrap();
+/// set.add("linux", linux).unwrap();
+///
+/// let report = set.dump(|_| Ok(Cursor::new(Vec::new())), &BuilderConfig::default()).unwrap();
+/// assert_eq!(report.shared, ["music"]);
+/// assert_eq!(report.archives.len(), 3);
+/// ```
+#[derive(Default)]
+pub struct PackSet<'a> {
+	archives: Vec<(String, Builder<'a>)>,
+	common: Option<String>,
+}
+
+/// What a `PackSet::dump` wrote
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackSetReport {
+	/// The `ID`s of leaves found in more than one archive, sorted
+	pub shared: Vec<EntryId>,
+	/// Bytes of leaf data, as read from their sources, that were written once into the shared archive instead of into every archive holding them
+	pub saved: u64,
+	/// The report of every archive written, by name, including the shared archive
+	pub archives: BTreeMap<String, DumpReport>,
+}
+
+impl<'a> PackSet<'a> {
+	/// An empty [`PackSet`], shared leaves are only detected until [`PackSet::common`] is set
+	pub fn new() -> PackSet<'a> {
+		PackSet::default()
+	}
+
+	/// Write shared leaves into a separate archive with the given name, instead of into every archive holding them
+	pub fn common(mut self, name: impl Into<String>) -> Self {
+		self.common = Some(name.into());
+		self
+	}
+
+	/// Adds an archive to the set, names must be unique
+	pub fn add(&mut self, name: impl Into<String>, builder: Builder<'a>) -> InternalResult {
+		let name = name.into();
+
+		if self.archives.iter().any(|(n, _)| *n == name) {
+			return Err(InternalError::InvalidConfigError(format!("The pack set already has an archive named {:?}", name)));
+		}
+
+		self.archives.push((name, builder));
+		Ok(())
+	}
+
+	/// Writes every archive, and the shared archive if any leaves are shared and [`PackSet::common`] is set.
+	/// `open` is called with the name of each archive, returning the target to write it into
+	pub fn dump<W: Write + Seek + Send>(
+		self, mut open: impl FnMut(&str) -> InternalResult<W>, config: &BuilderConfig,
+	) -> InternalResult<PackSetReport> {
+		let PackSet { mut archives, common } = self;
+
+		if let Some(common) = common.as_ref().filter(|c| archives.iter().any(|(name, _)| name == *c)) {
+			return Err(InternalError::InvalidConfigError(format!("The shared archive {:?} clashes with an archive of the pack set", common)));
+		}
+
+		// Group leaves by `ID` and a hash of their data, confirming matches byte for byte
+		let mut groups = HashMap::<(EntryId, u64), Vec<(usize, usize)>>::new();
+		let mut contents = Vec::with_capacity(archives.len());
+
+		for (archive, (_, builder)) in archives.iter_mut().enumerate() {
+			let mut buffers = Vec::with_capacity(builder.leafs.len());
+
+			for (idx, leaf) in builder.leafs.iter_mut().enumerate() {
+				let mut data = Vec::new();
+				leaf.handle.read_to_end(&mut data)?;
+
+				if !leaf.flags.contains(Flags::TOMBSTONE_FLAG) {
+					let mut hasher = DefaultHasher::new();
+					data.hash(&mut hasher);
+					groups.entry((leaf.id.clone(), hasher.finish())).or_default().push((archive, idx));
+				}
+
+				buffers.push(data);
+			}
+
+			contents.push(buffers);
+		}
+
+		let mut shared = groups
+			.into_values()
+			.filter_map(|group| {
+				let (first, rest) = group.split_first()?;
+				let leaf = |(a, i): (usize, usize)| (&archives[a].1.leafs[i], &contents[a][i]);
+
+				let (template, data) = leaf(*first);
+				let matching = rest
+					.iter()
+					.copied()
+					.filter(|other| {
+						let (other, other_data) = leaf(*other);
+						other_data == data && other.flags == template.flags && other.content_version == template.content_version
+					})
+					.collect::<Vec<_>>();
+
+				(!matching.is_empty()).then_some((*first, matching))
+			})
+			.collect::<Vec<_>>();
+
+		// An `ID` shared with different data by separate groups of archives is only shared by the largest group
+		let id = |(a, i): (usize, usize)| &archives[a].1.leafs[i].id;
+		shared.sort_unstable_by(|(a, a_rest), (b, b_rest)| id(*a).cmp(id(*b)).then(b_rest.len().cmp(&a_rest.len())));
+		shared.dedup_by(|(a, _), (b, _)| id(*a) == id(*b));
+
+		let mut report = PackSetReport {
+			shared: shared.iter().map(|((a, i), _)| archives[*a].1.leafs[*i].id.clone()).collect(),
+			..PackSetReport::default()
+		};
+
+		// Leaves are written from memory, shared ones are moved into the shared archive
+		let mut removed = vec![Vec::new(); archives.len()];
+		if common.is_some() {
+			for ((archive, idx), others) in &shared {
+				removed[*archive].push(*idx);
+				for (other, other_idx) in others {
+					removed[*other].push(*other_idx);
+					report.saved += contents[*other][*other_idx].len() as u64;
+				}
+			}
+		}
+
+		let mut common_leaves = BTreeMap::new();
+		let first_shared = shared.iter().map(|(first, _)| *first).collect::<Vec<_>>();
+
+		for (archive, ((_, builder), buffers)) in archives.iter_mut().zip(contents).enumerate() {
+			let leaves = std::mem::take(&mut builder.leafs);
+
+			for (idx, (mut leaf, data)) in leaves.into_iter().zip(buffers).enumerate() {
+				leaf.handle = Box::new(Cursor::new(data));
+
+				if !removed[archive].contains(&idx) {
+					builder.leafs.push(leaf);
+				} else if first_shared.contains(&(archive, idx)) {
+					common_leaves.insert(leaf.id.clone(), leaf);
+				}
+			}
+		}
+
+		let mut extensions = Vec::new();
+		if let (Some(common), false) = (&common, common_leaves.is_empty()) {
+			let mut leaves = common_leaves.into_values().collect::<Vec<Leaf>>();
+			let written = dump_extended(open(common)?, &mut leaves, config, &[])?;
+
+			report.archives.insert(common.clone(), written);
+			extensions.push((Header::SHARED_ARCHIVE, common.as_bytes().to_vec()));
+		}
+
+		for (name, builder) in archives {
+			let Builder { mut leafs, .. } = builder;
+			let written = dump_extended(open(&name)?, &mut leafs, config, &extensions)?;
+
+			report.archives.insert(name, written);
+		}
+
+		Ok(report)
+	}
+}
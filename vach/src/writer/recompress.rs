@@ -0,0 +1,117 @@
+use std::{
+	collections::HashMap,
+	io::{Cursor, Read, Write},
+};
+
+use super::{dump_report, BuilderConfig, CompressMode, DumpReport, Leaf};
+use crate::{
+	global::{
+		compressor::CompressionAlgorithm,
+		entry_id::EntryId,
+		error::*,
+		flags::Flags,
+		header::ArchiveConfig,
+	},
+	loader::archive::Archive,
+};
+
+/// Reads an archive from `source`, then writes it into `target` with every leaf compressed anew using `compress` and `algorithm`.
+/// Neither side is ever seeked, so both may be pipes, eg: stdin and stdout within a CI step on a read-only filesystem.
+/// Since the registry precedes the leaf data it describes, the source is read whole and the new archive is assembled in memory before being written out.
+///
+/// Everything else carries over: `ID`s, content versions, custom flags, residency hints, dependencies, tags and validity windows.
+/// Encrypted leaves are encrypted again and signed leaves signed again, so the [`BuilderConfig`] needs a key whenever the source has any.
+/// Leaves that went through custom pipeline stages are refused, as their stages can't be run again. An `ID` index and format descriptor are only written if `BuilderConfig::id_index` and `BuilderConfig::format_descriptor` are set
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// let mut source = Cursor::new(Vec::new());
+/// let mut builder = Builder::new();
+/// builder.add_leaf(Leaf::new(Cursor::new("Lorem ipsum ".repeat(256))).id("lorem").compress(CompressMode::Never)).unwrap();
+/// builder.dump(&mut source, &BuilderConfig::default()).unwrap();
+///
+/// let mut target = Vec::new();
+/// let (config, builder_config) = (ArchiveConfig::default(), BuilderConfig::default());
+/// let source = source.get_ref().as_slice();
+///
+/// recompress(source, &mut target, &config, CompressMode::Always, CompressionAlgorithm::LZ4, &builder_config).unwrap();
+///
+/// let archive = Archive::new(Cursor::new(target)).unwrap();
+/// assert!(archive.fetch_entry("lorem").unwrap().flags.contains(Flags::LZ4_COMPRESSED));
+/// ```
+pub fn recompress<R: Read, W: Write>(
+	mut source: R, mut target: W, config: &ArchiveConfig, compress: CompressMode, algorithm: CompressionAlgorithm,
+	builder_config: &BuilderConfig,
+) -> InternalResult<DumpReport> {
+	let mut data = Vec::new();
+	source.read_to_end(&mut data)?;
+
+	let archive = Archive::with_config(Cursor::new(data), config)?;
+
+	// Reserved leaves are written anew from the metadata of the leaves they describe
+	let mut dependencies = archive.dependency_graph()?;
+	let mut validity = archive.validity_windows()?;
+	let mut tags = HashMap::<EntryId, Vec<String>>::new();
+
+	for (tag, ids) in archive.tag_table()? {
+		for id in ids {
+			tags.entry(id).or_default().push(tag.clone());
+		}
+	}
+
+	// Reserved leaves aren't listed, they're written anew from the leaves' own dependencies, tags and validity windows
+	let mut entries = archive.entries().values().collect::<Vec<_>>();
+
+	// Keep the source's layout, so access patterns it was laid out for still hold
+	entries.sort_by_key(|e| e.location);
+
+	let mut leaves = Vec::with_capacity(entries.len());
+	for entry in entries {
+		if entry.is_tombstone() {
+			leaves.push(Leaf::tombstone(&entry.id));
+			continue;
+		}
+
+		if entry.is_placeholder() {
+			leaves.push(Leaf::placeholder(&entry.id));
+			continue;
+		}
+
+		if entry.flags.contains(Flags::PIPELINE_FLAG) {
+			let err = InternalError::InvalidConfigError("Leaves with custom pipelines can't be recompressed".into());
+			return Err(err.context(&entry.id, Some(entry.location), Stage::Compress));
+		}
+
+		let resource = archive.fetch(&entry.id)?;
+		let mut leaf = Leaf::new(Cursor::new(resource.data.into_vec()))
+			.id(&entry.id)
+			.version(entry.content_version)
+			.flags(Flags::from_bits(entry.flags.bits() & !Flags::RESERVED_MASK))
+			.hint(entry.hint())
+			.compress(compress)
+			.compression_algo(algorithm)
+			.depends_on(dependencies.remove(&entry.id).unwrap_or_default());
+
+		#[cfg(feature = "crypto")]
+		{
+			leaf = leaf
+				.encrypt(entry.flags.contains(Flags::ENCRYPTED_FLAG))
+				.sign(entry.signature.is_some());
+		}
+
+		leaf.validity = validity.remove(&entry.id).unwrap_or_default();
+		leaf.tags = tags.remove(&entry.id).unwrap_or_default();
+		leaf.tags.sort_unstable();
+
+		leaves.push(leaf);
+	}
+
+	let mut output = Cursor::new(Vec::new());
+	let report = dump_report(&mut output, &mut leaves, builder_config)?;
+
+	target.write_all(output.get_ref())?;
+	target.flush()?;
+
+	Ok(report)
+}
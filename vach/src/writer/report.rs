@@ -0,0 +1,156 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::global::{flags::Flags, lint::LintWarning, metrics::Metrics};
+
+/// Time spent in each stage of processing leaves, summed over all leaves. With the `multithreaded` feature stages run in parallel,
+/// so the sum may exceed the wall time of the dump. Leaves compressed while streaming from their source count their reading towards `compress`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageTimes {
+	/// Reading leaf data from it's source
+	pub read: Duration,
+	/// Compressing leaf data
+	pub compress: Duration,
+	/// Encrypting leaf data
+	pub encrypt: Duration,
+	/// Running custom pipeline stages
+	pub custom: Duration,
+	/// Signing leaves and their registry entries
+	pub sign: Duration,
+	/// Writing leaf data into the target
+	pub write: Duration,
+}
+
+impl StageTimes {
+	pub(crate) fn add(&mut self, other: &StageTimes) {
+		self.read += other.read;
+		self.compress += other.compress;
+		self.encrypt += other.encrypt;
+		self.custom += other.custom;
+		self.sign += other.sign;
+		self.write += other.write;
+	}
+}
+
+/// Totals over every leaf stored with one compression algorithm, see [`DumpReport::algorithms`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AlgorithmTotals {
+	/// How many leaves were stored with the algorithm
+	pub leaves: usize,
+	/// The size of those leaves as read from their sources
+	pub decoded: u64,
+	/// The size of those leaves as stored in the archive
+	pub stored: u64,
+}
+
+/// IO statistics of a single `Builder::dump_report`, to track pack performance across releases.
+/// Compare `bytes_written` against `bytes_read` to catch write amplification, see [`DumpReport::write_amplification`]
+/// ```
+/// use std::io::Cursor;
+/// use vach::prelude::*;
+///
+/// let mut builder = Builder::new();
+/// builder.add(Cursor::new(b"Hello"), "hello").unwrap();
+///
+/// let report = builder.dump_report(Cursor::new(Vec::new()), &BuilderConfig::default()).unwrap();
+/// assert_eq!((report.bytes_read, report.leaf_bytes), (5, 5));
+/// assert_eq!(report.algorithms["None"].leaves, 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DumpReport {
+	/// Bytes read from the sources of all leaves, including reserved leaves
+	pub bytes_read: u64,
+	/// Bytes written into the target: the header, the registry and leaf data. Bytes rewritten after seeking back are counted again
+	pub bytes_written: u64,
+	/// Bytes of leaf data written, as returned by `Builder::dump`
+	pub leaf_bytes: u64,
+	/// Seeks performed on the target
+	pub seeks: u64,
+	/// Time spent in each stage, see [`StageTimes`]
+	pub stages: StageTimes,
+	/// Wall time of the whole dump
+	pub elapsed: Duration,
+	/// Totals per compression algorithm, keyed by it's name. Uncompressed leaves are listed as `None`
+	pub algorithms: BTreeMap<&'static str, AlgorithmTotals>,
+	/// Leaves let through by `BuilderConfig::guardrail`, in the order they were caught
+	pub warnings: Vec<LintWarning>,
+	/// Leaves copied as is from an earlier dump instead of being processed again, see `Builder::dump_incremental`
+	pub reused: usize,
+}
+
+impl DumpReport {
+	/// Bytes written into the target per byte read from the sources. `0.0` if nothing was read
+	pub fn write_amplification(&self) -> f64 {
+		match self.bytes_read {
+			0 => 0.0,
+			read => self.bytes_written as f64 / read as f64,
+		}
+	}
+
+	/// The report as [`Metrics`] of a run of `operation`, for a [`Reporter`](crate::metrics::Reporter).
+	/// The total leaf count, seeks, write amplification and the time spent in each stage, in seconds, are kept in `Metrics::extra`
+	pub fn metrics(&self, operation: impl Into<String>) -> Metrics {
+		let mut metrics = Metrics::new(operation);
+		metrics.started -= self.elapsed;
+		metrics.elapsed = self.elapsed;
+		metrics.bytes_in = self.bytes_read;
+		metrics.bytes_out = self.bytes_written;
+		metrics.entries = self.algorithms.values().map(|totals| totals.leaves).sum();
+
+		let stages = [
+			("read", self.stages.read),
+			("compress", self.stages.compress),
+			("encrypt", self.stages.encrypt),
+			("custom", self.stages.custom),
+			("sign", self.stages.sign),
+			("write", self.stages.write),
+		];
+
+		metrics.extra.insert("leaf_bytes".to_string(), self.leaf_bytes as f64);
+		metrics.extra.insert("seeks".to_string(), self.seeks as f64);
+		metrics.extra.insert("reused".to_string(), self.reused as f64);
+		metrics.extra.insert("write_amplification".to_string(), self.write_amplification());
+		for (stage, time) in stages {
+			metrics.extra.insert(format!("stage.{}", stage), time.as_secs_f64());
+		}
+
+		metrics
+	}
+
+	pub(crate) fn record(&mut self, flags: &Flags, decoded: u64, stored: u64) {
+		let totals = self.algorithms.entry(algorithm(flags)).or_default();
+		totals.leaves += 1;
+		totals.decoded += decoded;
+		totals.stored += stored;
+	}
+}
+
+impl fmt::Display for DumpReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"[DumpReport] read: {}B, written: {}B, leaf data: {}B, seeks: {}, amplification: {:.2}, elapsed: {:?}",
+			self.bytes_read,
+			self.bytes_written,
+			self.leaf_bytes,
+			self.seeks,
+			self.write_amplification(),
+			self.elapsed
+		)
+	}
+}
+
+fn algorithm(flags: &Flags) -> &'static str {
+	if !flags.contains(Flags::COMPRESSED_FLAG) {
+		"None"
+	} else if flags.contains(Flags::LZ4_COMPRESSED) {
+		"LZ4"
+	} else if flags.contains(Flags::SNAPPY_COMPRESSED) {
+		"Snappy"
+	} else if flags.contains(Flags::BROTLI_COMPRESSED) {
+		"Brotli"
+	} else if flags.contains(Flags::DEFLATE_COMPRESSED) {
+		"Deflate"
+	} else {
+		"Unknown"
+	}
+}
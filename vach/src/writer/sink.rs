@@ -0,0 +1,213 @@
+use std::{
+	io::{self, Seek, SeekFrom, Write},
+	mem,
+	sync::mpsc::{self, SyncSender},
+	thread::{self, JoinHandle},
+};
+
+/// A destination that can't be seeked, but can place data at an offset: eg: a multipart upload to a CDN or object store.
+/// Fed by a [`SinkWriter`], on a thread of it's own
+pub trait DumpSink: Send + 'static {
+	/// Stores `data` at `offset` within the archive. Leaf data arrives first, in order and in chunks.
+	/// Once every leaf is written the header and registry are written again, as calls with an `offset` before earlier ones,
+	/// so a sink must either accept ranges out of order or hold on to the start of the archive until [`DumpSink::finish`]
+	fn write_at(&mut self, offset: u64, data: &[u8]) -> io::Result<()>;
+
+	/// Called once after the last write, eg: to complete an upload
+	fn finish(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Lets `dump()` write into a [`DumpSink`] while packing, instead of buffering the whole archive first.
+/// Data is gathered into chunks handed to the sink on a background thread. At most `in_flight` chunks wait on the sink,
+/// past that writes block, which in turn stalls the compute threads, so memory stays bounded however slow the sink is.
+///
+/// Bytes not written yet by the time a later offset is written are sent as zeroes, eg: the room reserved for the registry,
+/// then overwritten once known. Call [`SinkWriter::finish`] after the dump to flush the last chunk and wait on the sink
+/// ```
+/// use std::{io, sync::{Arc, Mutex}};
+/// use vach::prelude::*;
+///
+/// #[derive(Default, Clone)]
+/// struct Upload(Arc<Mutex<Vec<u8>>>);
+///
+/// impl DumpSink for Upload {
+///     fn write_at(&mut self, offset: u64, data: &[u8]) -> io::Result<()> {
+///         let mut bytes = self.0.lock().unwrap();
+///         let end = offset as usize + data.len();
+///         let length = bytes.len().max(end);
+///
+///         bytes.resize(length, 0);
+///         bytes[offset as usize..end].copy_from_slice(data);
+///         Ok(())
+///     }
+/// }
+///
+/// let mut builder = Builder::new();
+/// builder.add(b"Hello, Cassandra!" as &[u8], "hello").unwrap();
+///
+/// let upload = Upload::default();
+/// let mut writer = SinkWriter::new(upload.clone(), 64 * 1024, 4);
+/// builder.dump(&mut writer, &BuilderConfig::default()).unwrap();
+/// writer.finish().unwrap();
+///
+/// let bytes = upload.0.lock().unwrap().clone();
+/// let mut archive = Archive::new(std::io::Cursor::new(bytes)).unwrap();
+/// assert_eq!(archive.fetch_mut("hello").unwrap().data.as_ref(), b"Hello, Cassandra!");
+/// ```
+#[derive(Debug)]
+pub struct SinkWriter<S: DumpSink> {
+	sender: Option<SyncSender<(u64, Vec<u8>)>>,
+	worker: Option<JoinHandle<io::Result<S>>>,
+	chunk: Vec<u8>,
+	chunk_size: usize,
+	// Offset of the chunk's first byte, everything before it has been handed over
+	chunk_start: u64,
+	position: u64,
+}
+
+impl<S: DumpSink> SinkWriter<S> {
+	/// Spawns a thread feeding `sink` chunks of `chunk_size` bytes, with at most `in_flight` chunks queued for it
+	pub fn new(sink: S, chunk_size: usize, in_flight: usize) -> SinkWriter<S> {
+		let (sender, receiver) = mpsc::sync_channel::<(u64, Vec<u8>)>(in_flight);
+
+		let worker = thread::spawn(move || {
+			let mut sink = sink;
+			for (offset, data) in receiver {
+				sink.write_at(offset, &data)?;
+			}
+
+			sink.finish()?;
+			Ok(sink)
+		});
+
+		SinkWriter {
+			sender: Some(sender),
+			worker: Some(worker),
+			chunk: Vec::with_capacity(chunk_size),
+			chunk_size: chunk_size.max(1),
+			chunk_start: 0,
+			position: 0,
+		}
+	}
+
+	/// Hands over the last chunk, then waits for the sink to take everything and finish. Returns the sink, or the first error it ran into
+	pub fn finish(mut self) -> io::Result<S> {
+		self.send_chunk()?;
+		self.sender = None;
+		self.join()
+	}
+
+	/// The end of everything written so far
+	fn end(&self) -> u64 {
+		self.chunk_start + self.chunk.len() as u64
+	}
+
+	fn send(&mut self, offset: u64, data: Vec<u8>) -> io::Result<()> {
+		let sent = match self.sender.as_ref() {
+			Some(sender) => sender.send((offset, data)).is_ok(),
+			None => false,
+		};
+
+		match sent {
+			true => Ok(()),
+			// The worker only hangs up after the sink fails
+			false => self.join().map(|_| ()),
+		}
+	}
+
+	fn send_chunk(&mut self) -> io::Result<()> {
+		if self.chunk.is_empty() {
+			return Ok(());
+		}
+
+		let chunk = mem::replace(&mut self.chunk, Vec::with_capacity(self.chunk_size));
+		let offset = self.chunk_start;
+
+		self.chunk_start += chunk.len() as u64;
+		self.send(offset, chunk)
+	}
+
+	fn join(&mut self) -> io::Result<S> {
+		self.sender = None;
+
+		match self.worker.take().map(JoinHandle::join) {
+			Some(Ok(result)) => result,
+			Some(Err(_)) => Err(io::Error::other("The sink's thread panicked")),
+			None => Err(io::Error::new(io::ErrorKind::BrokenPipe, "The sink already finished")),
+		}
+	}
+}
+
+impl<S: DumpSink> Write for SinkWriter<S> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let (written, mut buf) = (buf.len(), buf);
+
+		// Rewrites of data already handed over go straight to the sink, in order with the chunks before them
+		if self.position < self.chunk_start {
+			let length = buf.len().min((self.chunk_start - self.position) as usize);
+			let (head, rest) = buf.split_at(length);
+
+			self.send(self.position, head.to_vec())?;
+			self.position += length as u64;
+			buf = rest;
+		}
+
+		if buf.is_empty() {
+			return Ok(written);
+		}
+
+		// Fill any gap with zeroes, it is overwritten later
+		let end = self.end();
+		if self.position > end {
+			self.chunk.resize(self.chunk.len() + (self.position - end) as usize, 0);
+		}
+
+		let start = (self.position - self.chunk_start) as usize;
+		let overlap = buf.len().min(self.chunk.len() - start);
+
+		self.chunk[start..start + overlap].copy_from_slice(&buf[..overlap]);
+		self.chunk.extend_from_slice(&buf[overlap..]);
+		self.position += buf.len() as u64;
+
+		while self.chunk.len() >= self.chunk_size {
+			let rest = self.chunk.split_off(self.chunk_size);
+			let chunk = mem::replace(&mut self.chunk, rest);
+			let offset = self.chunk_start;
+
+			self.chunk_start += chunk.len() as u64;
+			self.send(offset, chunk)?;
+		}
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+impl<S: DumpSink> Seek for SinkWriter<S> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let position = match pos {
+			SeekFrom::Start(offset) => Some(offset),
+			SeekFrom::End(offset) => self.end().checked_add_signed(offset),
+			SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+		};
+
+		self.position = position
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Seeked before the start of the archive"))?;
+		Ok(self.position)
+	}
+}
+
+impl<S: DumpSink> Drop for SinkWriter<S> {
+	fn drop(&mut self) {
+		// Unfinished writers still hand over what they hold, errors are only reported by `SinkWriter::finish`
+		if self.worker.is_some() {
+			let _ = self.send_chunk();
+			let _ = self.join();
+		}
+	}
+}